@@ -0,0 +1,231 @@
+use crate::config::default_cache_dir;
+use crate::error::KibelClientError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const APQ_CACHE_FILE: &str = "apq.json";
+
+/// Persisted-query registration state shared across processes.
+///
+/// Trusted queries whose hash is confirmed for an endpoint go straight to a
+/// hash-only GET; unknown hashes are registered with a single POST instead of
+/// a GET that is expected to miss; endpoints that rejected APQ skip it
+/// entirely. Writes are best-effort: a cache that cannot be saved never fails
+/// a request.
+#[derive(Debug)]
+pub struct ApqCache {
+    path: PathBuf,
+    state: Mutex<ApqCacheState>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApqCacheState {
+    #[serde(default)]
+    pub endpoints: BTreeMap<String, ApqEndpointState>,
+    #[serde(default)]
+    pub stats: ApqCacheStats,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApqEndpointState {
+    /// The endpoint answered a persisted-query request with "not supported"
+    /// or an HTTP status that rules out GET.
+    #[serde(default)]
+    pub unsupported: bool,
+    /// Confirmed query hashes and when they were last confirmed (unix seconds).
+    #[serde(default)]
+    pub confirmed: BTreeMap<String, u64>,
+}
+
+/// Cumulative counters across every process that used the cache file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApqCacheStats {
+    /// Hash-only GETs served for a confirmed hash.
+    #[serde(default)]
+    pub hits: u64,
+    /// Hashes registered through a POST carrying the query text.
+    #[serde(default)]
+    pub registrations: u64,
+    /// Confirmed hashes the server no longer recognized.
+    #[serde(default)]
+    pub evictions: u64,
+    /// Requests sent as plain POST because the endpoint does not support APQ.
+    #[serde(default)]
+    pub fallbacks: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApqLookup {
+    Confirmed,
+    Unknown,
+    Unsupported,
+}
+
+impl ApqCache {
+    /// Opens the cache at `path`. A missing or unreadable file starts empty.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let state = load_state(&path).unwrap_or_default();
+        Self {
+            path,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Returns the default cache file path, `apq.json` in the OS cache directory.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::ConfigDirectoryUnavailable`] when the OS
+    /// cache directory cannot be resolved.
+    pub fn default_path() -> Result<PathBuf, KibelClientError> {
+        Ok(default_cache_dir()?.join(APQ_CACHE_FILE))
+    }
+
+    /// Returns the cache file path under `dir`.
+    #[must_use]
+    pub fn path_in(dir: &Path) -> PathBuf {
+        dir.join(APQ_CACHE_FILE)
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> ApqCacheState {
+        self.state
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn lookup(&self, endpoint: &str, hash: &str) -> ApqLookup {
+        let Ok(state) = self.state.lock() else {
+            return ApqLookup::Unknown;
+        };
+        match state.endpoints.get(endpoint) {
+            Some(entry) if entry.unsupported => ApqLookup::Unsupported,
+            Some(entry) if entry.confirmed.contains_key(hash) => ApqLookup::Confirmed,
+            _ => ApqLookup::Unknown,
+        }
+    }
+
+    /// Records that the server served `hash`, either from a hash-only GET
+    /// (`via_get`) or after registering it through POST.
+    pub fn confirm(&self, endpoint: &str, hash: &str, via_get: bool) {
+        self.update(|state| {
+            let entry = state.endpoints.entry(endpoint.to_string()).or_default();
+            entry.confirmed.insert(hash.to_string(), unix_now());
+            if via_get {
+                state.stats.hits += 1;
+            } else {
+                state.stats.registrations += 1;
+            }
+        });
+    }
+
+    pub fn evict(&self, endpoint: &str, hash: &str) {
+        self.update(|state| {
+            let removed = state
+                .endpoints
+                .get_mut(endpoint)
+                .and_then(|entry| entry.confirmed.remove(hash));
+            if removed.is_some() {
+                state.stats.evictions += 1;
+            }
+        });
+    }
+
+    pub fn record_fallback(&self, endpoint: &str) {
+        self.update(|state| {
+            let entry = state.endpoints.entry(endpoint.to_string()).or_default();
+            entry.unsupported = true;
+            entry.confirmed.clear();
+            state.stats.fallbacks += 1;
+        });
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut ApqCacheState)) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        // Re-read first so concurrent invocations do not drop each other's entries.
+        if let Some(latest) = load_state(&self.path) {
+            *state = latest;
+        }
+        apply(&mut state);
+        let _ = save_state(&self.path, &state);
+    }
+}
+
+fn load_state(path: &Path) -> Option<ApqCacheState> {
+    let raw = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+fn save_state(path: &Path, state: &ApqCacheState) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let serialized = serde_json::to_string_pretty(state).map_err(std::io::Error::other)?;
+    let staging = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    fs::write(&staging, serialized)?;
+    fs::rename(&staging, path)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApqCache, ApqLookup};
+
+    #[test]
+    fn state_round_trips_through_the_cache_file() {
+        let dir = std::env::temp_dir().join(format!("kibel-apq-cache-{}", std::process::id()));
+        let path = ApqCache::path_in(&dir);
+        let _ = std::fs::remove_file(&path);
+
+        let cache = ApqCache::open(&path);
+        assert_eq!(cache.lookup("https://a/api/v1", "h1"), ApqLookup::Unknown);
+        cache.confirm("https://a/api/v1", "h1", false);
+        cache.confirm("https://a/api/v1", "h1", true);
+        cache.record_fallback("https://b/api/v1");
+
+        let reopened = ApqCache::open(&path);
+        assert_eq!(
+            reopened.lookup("https://a/api/v1", "h1"),
+            ApqLookup::Confirmed
+        );
+        assert_eq!(
+            reopened.lookup("https://b/api/v1", "h1"),
+            ApqLookup::Unsupported
+        );
+        reopened.evict("https://a/api/v1", "h1");
+        let stats = reopened.snapshot().stats;
+        assert_eq!(
+            (
+                stats.hits,
+                stats.registrations,
+                stats.evictions,
+                stats.fallbacks
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(
+            reopened.lookup("https://a/api/v1", "h1"),
+            ApqLookup::Unknown
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,4 +1,13 @@
+use crate::apq_cache::{ApqCache, ApqLookup};
 use crate::error::KibelClientError;
+use crate::headers::{ExtraHeaders, HeaderRequest};
+use crate::http::{HttpAgent, HttpRequest, HttpResponse};
+use crate::metrics::Metrics;
+use crate::path::{FolderPath, NotePath};
+use crate::policy::Policy;
+use crate::redact::{register_secret, Redactor};
+use crate::transport::{generate_request_id, GraphqlRequest, GraphqlTransport, Timeouts};
+use crate::usage::Usage;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
@@ -7,7 +16,7 @@ use std::collections::BTreeSet;
 use std::fs;
 use std::io::Read;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[path = "generated_create_note_contract.rs"]
 mod generated_create_note_contract;
@@ -19,10 +28,14 @@ use self::generated_create_note_contract::{
 };
 pub use self::generated_resource_contracts::{ResourceContract, TrustedOperation};
 
-const DEFAULT_TIMEOUT_MS: u64 = 5000;
 const DEFAULT_FIRST: u32 = 16;
+/// Groups `ensure_folder_path` reads when matching a group by name.
+const GROUP_LOOKUP_FIRST: u32 = 100;
+/// Replies `get_comment_thread` reads under the comment.
+const COMMENT_THREAD_REPLIES_FIRST: u32 = 100;
 const GRAPHQL_ACCEPT_HEADER: &str = "application/graphql-response+json, application/json;q=0.9";
 const APQ_VERSION: u64 = 1;
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
 const APQ_GET_VARIABLES_LIMIT_BYTES: usize = 1024;
 const SEARCH_NOTE_RESOURCE_KINDS: [&str; 3] = ["NOTE", "COMMENT", "ATTACHMENT"];
 
@@ -79,11 +92,37 @@ query GetCurrentUserId {
 }
 ";
 
+const QUERY_CURRENT_USER_ACCOUNT: &str = r"
+query GetCurrentUserAccount {
+  currentUser {
+    account
+  }
+}
+";
+
 #[must_use]
 pub fn resource_contracts() -> &'static [ResourceContract] {
     generated_resource_contracts::RESOURCE_CONTRACTS
 }
 
+/// Field names of the generated createNote contract, as pinned by
+/// `schema/contracts/create_note_contract.snapshot.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreateNoteContract {
+    pub input_fields: &'static [&'static str],
+    pub payload_fields: &'static [&'static str],
+    pub note_projection_fields: &'static [&'static str],
+}
+
+#[must_use]
+pub fn create_note_contract() -> CreateNoteContract {
+    CreateNoteContract {
+        input_fields: CREATE_NOTE_INPUT_FIELDS,
+        payload_fields: CREATE_NOTE_PAYLOAD_FIELDS,
+        note_projection_fields: CREATE_NOTE_NOTE_PROJECTION_FIELDS,
+    }
+}
+
 #[must_use]
 pub fn resource_contract_version() -> u32 {
     generated_resource_contracts::RESOURCE_CONTRACT_VERSION
@@ -148,6 +187,33 @@ pub struct UpdateNoteInput {
     pub new_content: String,
 }
 
+/// Note metadata to change; fields left as `None` are not sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateNoteMetadataInput {
+    pub id: String,
+    pub title: Option<String>,
+    pub coediting: Option<bool>,
+    pub group_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishNoteInput {
+    pub id: String,
+    pub published_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DraftNotesInput {
+    pub first: Option<u32>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteCommentsInput {
+    pub note_id: String,
+    pub last: Option<u32>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SearchNoteInput {
     pub query: String,
@@ -184,6 +250,16 @@ impl SearchNoteInput {
     }
 }
 
+/// One page of `search_note` results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchNotePage {
+    pub results: Vec<Value>,
+    /// The connection `pageInfo` (`{hasNextPage, endCursor, ...}`).
+    pub page_info: Value,
+    /// Total hits for the query across all pages, when the API reports it.
+    pub total_count: Option<u64>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SearchFolderInput {
     pub query: String,
@@ -219,6 +295,7 @@ pub struct FeedSectionsInput {
     pub kind: String,
     pub group_id: String,
     pub first: Option<u32>,
+    pub after: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -233,12 +310,53 @@ pub struct CreateCommentReplyInput {
     pub comment_id: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreateInlineCommentInput {
+    pub content: String,
+    pub note_id: String,
+    /// Passage of the note content the comment is attached to.
+    pub anchor: String,
+    /// Character offset of `anchor` in the note content, for passages that
+    /// occur more than once.
+    pub anchor_position: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateCommentInput {
+    pub id: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeleteCommentInput {
+    pub id: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CreateFolderInput {
     pub group_id: String,
     pub full_name: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnsureFolderPathInput {
+    /// `/<group>/<folder>[/<subfolder>...]`; the leading `/` is optional.
+    pub path: String,
+    /// Create the folders of the chain that do not exist yet.
+    pub create_missing: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnsuredFolder {
+    pub id: String,
+    pub group_id: String,
+    /// Full folder name below the group, e.g. `engineering/api`.
+    pub folder_name: String,
+    pub path: String,
+    /// Paths of the folders created on the way, outermost first.
+    pub created: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MoveNoteToAnotherFolderInput {
     pub id: String,
@@ -252,6 +370,41 @@ pub struct AttachNoteToFolderInput {
     pub folder: CreateNoteFolderInput,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentDigestInput {
+    pub url: String,
+    pub max_bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentDigest {
+    pub url: String,
+    pub sha256: String,
+    pub bytes_hashed: u64,
+    pub content_length: Option<u64>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadAttachmentInput {
+    pub url: String,
+    pub max_bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadAttachmentInput {
+    /// File name, e.g. `diagram.png`; also decides `IMAGE` or `FILE`.
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadedAttachment {
+    pub id: String,
+    /// Root-relative path of the uploaded file on this client's origin.
+    pub path: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IdOnlyResult {
     pub id: String,
@@ -260,16 +413,33 @@ pub struct IdOnlyResult {
 #[derive(Debug, Clone)]
 pub struct KibelClient {
     origin: String,
+    timeouts: Timeouts,
+    create_note_schema: Arc<Mutex<Option<CreateNoteSchema>>>,
+    http: HttpTransport,
+    transport: Option<Arc<dyn GraphqlTransport>>,
+    read_only: bool,
+    policy: Option<Policy>,
+    metrics: Option<Arc<Metrics>>,
+    usage: Option<Arc<Usage>>,
+}
+
+/// The default [`GraphqlTransport`]: GraphQL over HTTP against
+/// `<origin>/api/v1`, sending trusted queries as persisted-query GETs where
+/// the endpoint supports them.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
     endpoint: String,
     token: String,
-    timeout_ms: u64,
-    create_note_schema: Arc<Mutex<Option<CreateNoteSchema>>>,
+    agent: HttpAgent,
+    apq_cache: Option<Arc<ApqCache>>,
+    headers: ExtraHeaders,
+    request_id: String,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum QueryTransportMode {
     PostOnly,
-    TrustedQueryApqGet,
+    PersistedQueryGet,
 }
 
 #[derive(Debug, Clone)]
@@ -284,6 +454,9 @@ impl KibelClient {
     /// # Errors
     /// Returns [`KibelClientError::InputInvalid`] when `origin` or `token` is
     /// empty after trimming.
+    ///
+    /// The token is registered with [`crate::redact::register_secret`], and
+    /// errors the client returns never contain it.
     pub fn new(
         origin: impl Into<String>,
         token: impl Into<String>,
@@ -302,22 +475,156 @@ impl KibelClient {
             ));
         }
 
-        let endpoint = endpoint_from_origin(&origin);
-
+        register_secret(token.as_str());
         Ok(Self {
+            http: HttpTransport::new(&origin, &token),
             origin,
-            endpoint,
-            token,
-            timeout_ms: DEFAULT_TIMEOUT_MS,
+            timeouts: Timeouts::default(),
             create_note_schema: Arc::new(Mutex::new(None)),
+            transport: env_fixture_transport(),
+            read_only: env_flag_is_true("KIBEL_READ_ONLY"),
+            policy: None,
+            metrics: None,
+            usage: None,
         })
     }
 
+    /// Rejects every mutation before it reaches the network with
+    /// [`KibelClientError::ReadOnlyViolation`].
+    ///
+    /// `KIBEL_READ_ONLY=1` (or `true`/`yes`) turns this on for every client; passing `false`
+    /// here does not turn it off again.
+    #[must_use]
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only |= read_only;
+        self
+    }
+
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Checks every request against `policy` before it is sent, failing with
+    /// [`KibelClientError::PolicyViolation`].
+    #[must_use]
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    #[must_use]
+    pub fn policy(&self) -> Option<&Policy> {
+        self.policy.as_ref()
+    }
+
+    /// Sends `id` as the `X-Request-Id` header of every request and tags
+    /// errors from the server with it (see [`KibelClientError::request_id`]).
+    /// Without it, each client sends an id of its own.
+    #[must_use]
+    pub fn with_request_id(mut self, id: impl Into<String>) -> Self {
+        self.http = self.http.with_request_id(id);
+        self
+    }
+
+    /// The `X-Request-Id` this client sends.
+    #[must_use]
+    pub fn request_id(&self) -> &str {
+        self.http.request_id()
+    }
+
+    /// Records every trusted and ad-hoc GraphQL request (count, errors,
+    /// latency) in `metrics`, labelled with the operation name or `adhoc`.
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Counts every trusted and ad-hoc GraphQL request and its response size
+    /// in `usage`, per day and operation name (or `adhoc`).
+    #[must_use]
+    pub fn with_usage(mut self, usage: Arc<Usage>) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Shares persisted-query registration state with other processes
+    /// through `cache`, so trusted queries skip lookups that are known to miss.
+    #[must_use]
+    pub fn with_apq_cache(mut self, cache: Arc<ApqCache>) -> Self {
+        self.http = self.http.with_apq_cache(cache);
+        self
+    }
+
+    /// Adds `headers` to every HTTP request the client sends (see
+    /// [`HttpTransport::with_extra_headers`]).
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] for an invalid header.
+    pub fn with_extra_headers<K, V>(
+        mut self,
+        headers: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, KibelClientError>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.http = self.http.with_extra_headers(headers)?;
+        Ok(self)
+    }
+
+    /// Computes headers per HTTP request (see
+    /// [`HttpTransport::with_header_hook`]).
+    #[must_use]
+    pub fn with_header_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&HeaderRequest<'_>) -> Result<Vec<(String, String)>, KibelClientError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.http = self.http.with_header_hook(hook);
+        self
+    }
+
+    /// Replaces the connect, read, and total deadlines of every request
+    /// (default [`Timeouts::INTERACTIVE`]).
+    #[must_use]
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self.http = self.http.with_timeouts(timeouts);
+        self
+    }
+
+    #[must_use]
+    pub fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    fn total_timeout_ms(&self) -> u64 {
+        u64::try_from(self.timeouts.total.as_millis()).unwrap_or(u64::MAX)
+    }
+
+    /// Sends GraphQL requests through `transport` instead of HTTP.
+    #[must_use]
+    pub fn with_transport(mut self, transport: Arc<dyn GraphqlTransport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
     #[must_use]
     pub fn origin(&self) -> &str {
         &self.origin
     }
 
+    /// The HTTP transport this client uses unless another one is injected,
+    /// e.g. to wrap it in a [`VcrTransport`](crate::VcrTransport).
+    #[must_use]
+    pub fn http_transport(&self) -> &HttpTransport {
+        &self.http
+    }
+
     /// Executes an ad-hoc GraphQL request outside the trusted operation registry.
     ///
     /// # Errors
@@ -329,6 +636,73 @@ impl KibelClient {
         variables: Value,
         timeout_ms: u64,
         max_response_bytes: usize,
+    ) -> Result<Value, KibelClientError> {
+        self.run_untrusted_graphql_operation(query, None, variables, timeout_ms, max_response_bytes)
+    }
+
+    /// Executes an ad-hoc GraphQL request, selecting `operation_name` when the
+    /// document defines more than one operation.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when query, operation name, or
+    /// limits are invalid, or transport/API errors from GraphQL.
+    pub fn run_untrusted_graphql_operation(
+        &self,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: Value,
+        timeout_ms: u64,
+        max_response_bytes: usize,
+    ) -> Result<Value, KibelClientError> {
+        self.run_untrusted(
+            query,
+            operation_name,
+            variables,
+            timeout_ms,
+            max_response_bytes,
+            QueryTransportMode::PostOnly,
+        )
+    }
+
+    /// Executes an ad-hoc GraphQL query through automatic persisted queries:
+    /// the query is registered by its hash once, then sent as a hash-only GET,
+    /// sharing the registration cache with trusted queries.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when the selected operation
+    /// is a mutation, or the errors of
+    /// [`KibelClient::run_untrusted_graphql_operation`].
+    pub fn run_untrusted_graphql_persisted(
+        &self,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: Value,
+        timeout_ms: u64,
+        max_response_bytes: usize,
+    ) -> Result<Value, KibelClientError> {
+        if document_selects_mutation(query, operation_name.map(str::trim)) {
+            return Err(KibelClientError::InputInvalid(
+                "only queries can be persisted; mutations are always sent as POST".to_string(),
+            ));
+        }
+        self.run_untrusted(
+            query,
+            operation_name,
+            variables,
+            timeout_ms,
+            max_response_bytes,
+            QueryTransportMode::PersistedQueryGet,
+        )
+    }
+
+    fn run_untrusted(
+        &self,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: Value,
+        timeout_ms: u64,
+        max_response_bytes: usize,
+        mode: QueryTransportMode,
     ) -> Result<Value, KibelClientError> {
         let query = query.trim();
         if query.is_empty() {
@@ -341,13 +715,30 @@ impl KibelClient {
                 "max response bytes must be greater than 0".to_string(),
             ));
         }
-        self.request_graphql_raw_with_limits(
-            query,
-            variables,
-            timeout_ms.max(100),
-            Some(max_response_bytes),
-            QueryTransportMode::PostOnly,
-        )
+        let operation_name = operation_name.map(str::trim);
+        if operation_name.is_some_and(str::is_empty) {
+            return Err(KibelClientError::InputInvalid(
+                "operation name must not be empty".to_string(),
+            ));
+        }
+        if self.read_only && document_selects_mutation(query, operation_name) {
+            return Err(KibelClientError::ReadOnlyViolation(
+                "read-only mode blocks mutations in ad-hoc GraphQL".to_string(),
+            ));
+        }
+        if let Some(policy) = &self.policy {
+            policy.check_untrusted(&variables)?;
+        }
+        self.observe("adhoc", || {
+            self.request_graphql_raw_with_limits(
+                query,
+                operation_name,
+                variables,
+                timeout_ms.max(100),
+                Some(max_response_bytes),
+                mode,
+            )
+        })
     }
 
     /// Fetches a note by id.
@@ -518,77 +909,278 @@ impl KibelClient {
         parse_note_at(&payload, "/data/updateNoteContent/note")
     }
 
-    /// Searches notes.
-    ///
-    /// # Errors
-    /// Returns [`KibelClientError::InputInvalid`] when paging is invalid,
-    /// or transport/API errors from GraphQL.
-    pub fn search_note(&self, input: &SearchNoteInput) -> Result<Value, KibelClientError> {
-        let payload = self.search_note_with_page_info(input)?;
-        Ok(payload
-            .get("results")
-            .cloned()
-            .unwrap_or_else(|| Value::Array(Vec::new())))
-    }
-
-    /// Searches notes and returns both result items and connection page info.
+    /// Updates note title, coediting, and sharing groups through `updateNote`.
     ///
     /// # Errors
-    /// Returns [`KibelClientError::InputInvalid`] when paging is invalid,
+    /// Returns [`KibelClientError::InputInvalid`] when the note id is empty,
+    /// nothing would change, or the title/group ids are empty,
     /// or transport/API errors from GraphQL.
-    pub fn search_note_with_page_info(
+    pub fn update_note_metadata(
         &self,
-        input: &SearchNoteInput,
+        input: &UpdateNoteMetadataInput,
     ) -> Result<Value, KibelClientError> {
-        let first = normalize_first(input.first)?;
-        let variables = build_search_note_variables(input, first)?;
+        let id = input.id.trim();
+        if id.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "note id is required".to_string(),
+            ));
+        }
+        let mut mutation_input = serde_json::Map::new();
+        mutation_input.insert("id".to_string(), Value::String(id.to_string()));
+        if let Some(title) = &input.title {
+            let title = title.trim();
+            if title.is_empty() {
+                return Err(KibelClientError::InputInvalid(
+                    "title must not be empty".to_string(),
+                ));
+            }
+            mutation_input.insert("title".to_string(), Value::String(title.to_string()));
+        }
+        if let Some(coediting) = input.coediting {
+            mutation_input.insert("coediting".to_string(), Value::Bool(coediting));
+        }
+        if let Some(group_ids) = &input.group_ids {
+            let group_ids = normalize_vec(group_ids);
+            if group_ids.is_empty() {
+                return Err(KibelClientError::InputInvalid(
+                    "at least one group id is required".to_string(),
+                ));
+            }
+            mutation_input.insert("groupIds".to_string(), json!(group_ids));
+        }
+        if mutation_input.len() == 1 {
+            return Err(KibelClientError::InputInvalid(
+                "nothing to update: set title, coediting, or group ids".to_string(),
+            ));
+        }
 
         let payload = self.request_trusted_graphql(
-            TrustedOperation::SearchNote,
-            trusted_operation_document(TrustedOperation::SearchNote),
-            Value::Object(variables),
+            TrustedOperation::UpdateNote,
+            trusted_operation_document(TrustedOperation::UpdateNote),
+            json!({ "input": Value::Object(mutation_input) }),
         )?;
-        let edges = require_array_at(&payload, "/data/search/edges", "search result not found")?;
-        let mut items = Vec::with_capacity(edges.len());
-        for edge in edges {
-            let node = edge.get("node").unwrap_or(&Value::Null);
-            items.push(json!({
-                "id": node.pointer("/document/id").cloned().unwrap_or(Value::Null),
-                "title": node.get("title").cloned().unwrap_or(Value::Null),
-                "url": node.get("url").cloned().unwrap_or(Value::Null),
-                "contentSummaryHtml": node.get("contentSummaryHtml").cloned().unwrap_or(Value::Null),
-                "path": node.get("path").cloned().unwrap_or(Value::Null),
-                "author": {
-                    "id": node.pointer("/author/id").cloned().unwrap_or(Value::Null),
-                    "account": node.pointer("/author/account").cloned().unwrap_or(Value::Null),
-                    "realName": node.pointer("/author/realName").cloned().unwrap_or(Value::Null),
-                }
-            }));
-        }
-        let page_info = payload
-            .pointer("/data/search/pageInfo")
-            .cloned()
-            .unwrap_or(Value::Null);
-        Ok(json!({
-            "results": items,
-            "pageInfo": page_info,
-        }))
+        require_value_at(&payload, "/data/updateNote/note", "note not found")
     }
 
-    /// Returns latest notes for the current authenticated user.
+    /// Publishes a draft note by clearing its draft state.
+    ///
+    /// `published_at` (RFC 3339) is sent only when given; otherwise Kibela
+    /// stamps the publish time.
     ///
     /// # Errors
-    /// Returns [`KibelClientError::InputInvalid`] when paging is invalid,
+    /// Returns [`KibelClientError::InputInvalid`] when the note id is empty,
     /// or transport/API errors from GraphQL.
-    pub fn get_current_user_latest_notes(
-        &self,
-        input: PageInput,
-    ) -> Result<Value, KibelClientError> {
+    pub fn publish_note(&self, input: &PublishNoteInput) -> Result<Value, KibelClientError> {
+        let id = input.id.trim();
+        if id.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "note id is required".to_string(),
+            ));
+        }
+        let mut mutation_input = serde_json::Map::new();
+        mutation_input.insert("id".to_string(), Value::String(id.to_string()));
+        mutation_input.insert("draft".to_string(), Value::Bool(false));
+        if let Some(published_at) = input
+            .published_at
+            .as_deref()
+            .map(str::trim)
+            .filter(|published_at| !published_at.is_empty())
+        {
+            mutation_input.insert(
+                "publishedAt".to_string(),
+                Value::String(published_at.to_string()),
+            );
+        }
+
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::UpdateNote,
+            trusted_operation_document(TrustedOperation::UpdateNote),
+            json!({ "input": Value::Object(mutation_input) }),
+        )?;
+        require_value_at(&payload, "/data/updateNote/note", "note not found")
+    }
+
+    /// Lists the current user's draft notes with connection page info.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when paging is invalid,
+    /// or transport/API errors from GraphQL.
+    pub fn get_draft_notes(&self, input: &DraftNotesInput) -> Result<Value, KibelClientError> {
+        let first = normalize_first(input.first)?;
+        let mut variables = serde_json::Map::new();
+        variables.insert("first".to_string(), json!(first));
+        if let Some(after) = input
+            .after
+            .as_deref()
+            .map(str::trim)
+            .filter(|after| !after.is_empty())
+        {
+            variables.insert("after".to_string(), Value::String(after.to_string()));
+        }
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::GetDraftNotes,
+            trusted_operation_document(TrustedOperation::GetDraftNotes),
+            Value::Object(variables),
+        )?;
+        let edges = require_array_at(
+            &payload,
+            "/data/currentUser/draftNotes/edges",
+            "draft notes not found",
+        )?;
+        let drafts = edges
+            .iter()
+            .filter_map(|edge| edge.get("node").cloned())
+            .collect::<Vec<_>>();
+        let page_info = payload
+            .pointer("/data/currentUser/draftNotes/pageInfo")
+            .cloned()
+            .unwrap_or(Value::Null);
+        Ok(json!({
+            "drafts": drafts,
+            "pageInfo": page_info,
+        }))
+    }
+
+    /// Lists the most recent comments on a note, oldest first.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when `last` is zero,
+    /// or transport/API errors from GraphQL.
+    pub fn get_note_comments(&self, input: &NoteCommentsInput) -> Result<Value, KibelClientError> {
+        let last = normalize_first(input.last)?;
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::GetNoteComments,
+            trusted_operation_document(TrustedOperation::GetNoteComments),
+            json!({
+                "id": input.note_id,
+                "last": last,
+            }),
+        )?;
+        let comments = require_array_at(
+            &payload,
+            "/data/note/comments/nodes",
+            "note comments not found",
+        )?;
+        Ok(json!({ "comments": comments }))
+    }
+
+    /// Gets one comment with its replies, oldest first. At most 100 replies
+    /// are returned; `replies.pageInfo.hasNextPage` says whether there are
+    /// more.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when `id` is empty,
+    /// `NOT_FOUND` when the API returns no comment, or transport/API errors
+    /// from GraphQL.
+    pub fn get_comment_thread(&self, id: &str) -> Result<Value, KibelClientError> {
+        let id = id.trim();
+        if id.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "comment id is required".to_string(),
+            ));
+        }
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::GetCommentThread,
+            trusted_operation_document(TrustedOperation::GetCommentThread),
+            json!({
+                "id": id,
+                "first": COMMENT_THREAD_REPLIES_FIRST,
+            }),
+        )?;
+        require_value_at(&payload, "/data/comment", "comment not found")
+    }
+
+    /// Searches notes.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when paging is invalid,
+    /// or transport/API errors from GraphQL.
+    pub fn search_note(&self, input: &SearchNoteInput) -> Result<Value, KibelClientError> {
+        let payload = self.search_note_with_page_info(input)?;
+        Ok(payload
+            .get("results")
+            .cloned()
+            .unwrap_or_else(|| Value::Array(Vec::new())))
+    }
+
+    /// Searches notes and returns both result items and connection page info.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when paging is invalid,
+    /// or transport/API errors from GraphQL.
+    pub fn search_note_with_page_info(
+        &self,
+        input: &SearchNoteInput,
+    ) -> Result<Value, KibelClientError> {
+        let page = self.search_note_page(input)?;
+        Ok(json!({
+            "results": page.results,
+            "pageInfo": page.page_info,
+            "totalCount": page.total_count,
+        }))
+    }
+
+    /// Searches notes and returns one typed page of results.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when paging is invalid,
+    /// or transport/API errors from GraphQL.
+    pub fn search_note_page(
+        &self,
+        input: &SearchNoteInput,
+    ) -> Result<SearchNotePage, KibelClientError> {
+        let first = normalize_first(input.first)?;
+        let variables = build_search_note_variables(input, first)?;
+
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::SearchNote,
+            trusted_operation_document(TrustedOperation::SearchNote),
+            Value::Object(variables),
+        )?;
+        let edges = require_array_at(&payload, "/data/search/edges", "search result not found")?;
+        let mut items = Vec::with_capacity(edges.len());
+        for edge in edges {
+            let node = edge.get("node").unwrap_or(&Value::Null);
+            items.push(json!({
+                "id": node.pointer("/document/id").cloned().unwrap_or(Value::Null),
+                "title": node.get("title").cloned().unwrap_or(Value::Null),
+                "url": node.get("url").cloned().unwrap_or(Value::Null),
+                "contentSummaryHtml": node.get("contentSummaryHtml").cloned().unwrap_or(Value::Null),
+                "path": node.get("path").cloned().unwrap_or(Value::Null),
+                "author": {
+                    "id": node.pointer("/author/id").cloned().unwrap_or(Value::Null),
+                    "account": node.pointer("/author/account").cloned().unwrap_or(Value::Null),
+                    "realName": node.pointer("/author/realName").cloned().unwrap_or(Value::Null),
+                }
+            }));
+        }
+        let page_info = payload
+            .pointer("/data/search/pageInfo")
+            .cloned()
+            .unwrap_or(Value::Null);
+        Ok(SearchNotePage {
+            results: items,
+            page_info,
+            total_count: payload
+                .pointer("/data/search/totalCount")
+                .and_then(Value::as_u64),
+        })
+    }
+
+    /// Returns latest notes for the current authenticated user.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when paging is invalid,
+    /// or transport/API errors from GraphQL.
+    pub fn get_current_user_latest_notes(
+        &self,
+        input: PageInput,
+    ) -> Result<Value, KibelClientError> {
         let first = normalize_first(input.first)?;
         let payload = self.run_internal_bootstrap_query(
             QUERY_CURRENT_USER_LATEST_NOTES,
             json!({ "first": first }),
-            self.timeout_ms,
+            self.total_timeout_ms(),
             2 * 1024 * 1024,
             INTERNAL_BOOTSTRAP_ROOT_CURRENT_USER,
         )?;
@@ -624,7 +1216,7 @@ impl KibelClient {
         let payload = self.run_internal_bootstrap_query(
             QUERY_CURRENT_USER_ID,
             json!({}),
-            self.timeout_ms,
+            self.total_timeout_ms(),
             512 * 1024,
             INTERNAL_BOOTSTRAP_ROOT_CURRENT_USER,
         )?;
@@ -632,9 +1224,35 @@ impl KibelClient {
             .pointer("/data/currentUser/id")
             .and_then(Value::as_str)
             .map(str::to_string)
-            .ok_or_else(|| KibelClientError::Api {
-                code: "NOT_FOUND".to_string(),
-                message: "current user id not found".to_string(),
+            .ok_or_else(|| {
+                KibelClientError::api(
+                    "NOT_FOUND".to_string(),
+                    "current user id not found".to_string(),
+                )
+            })
+    }
+
+    /// Returns current authenticated user account name.
+    ///
+    /// # Errors
+    /// Returns transport/API errors from GraphQL.
+    pub fn get_current_user_account(&self) -> Result<String, KibelClientError> {
+        let payload = self.run_internal_bootstrap_query(
+            QUERY_CURRENT_USER_ACCOUNT,
+            json!({}),
+            self.total_timeout_ms(),
+            512 * 1024,
+            INTERNAL_BOOTSTRAP_ROOT_CURRENT_USER,
+        )?;
+        payload
+            .pointer("/data/currentUser/account")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                KibelClientError::api(
+                    "NOT_FOUND".to_string(),
+                    "current user account not found".to_string(),
+                )
             })
     }
 
@@ -731,8 +1349,10 @@ impl KibelClient {
             items.push(json!({
                 "id": node.get("id").cloned().unwrap_or(Value::Null),
                 "name": node.get("name").cloned().unwrap_or(Value::Null),
+                "description": node.get("description").cloned().unwrap_or(Value::Null),
                 "isDefault": node.get("isDefault").cloned().unwrap_or(Value::Null),
                 "isArchived": node.get("isArchived").cloned().unwrap_or(Value::Null),
+                "isPrivate": node.get("isPrivate").cloned().unwrap_or(Value::Null),
             }));
         }
         Ok(Value::Array(items))
@@ -740,6 +1360,9 @@ impl KibelClient {
 
     /// Lists folders.
     ///
+    /// Folders have no archived or private flag of their own; `isArchived`
+    /// and `isPrivate` are the owning group's.
+    ///
     /// # Errors
     /// Returns [`KibelClientError::InputInvalid`] when paging is invalid, or
     /// transport/API errors from GraphQL.
@@ -754,9 +1377,16 @@ impl KibelClient {
         let mut items = Vec::with_capacity(edges.len());
         for edge in edges {
             let node = edge.get("node").unwrap_or(&Value::Null);
+            let group = node.get("group").unwrap_or(&Value::Null);
             items.push(json!({
                 "id": node.get("id").cloned().unwrap_or(Value::Null),
                 "name": node.get("name").cloned().unwrap_or(Value::Null),
+                "group": group.is_object().then(|| json!({
+                    "id": group.get("id").cloned().unwrap_or(Value::Null),
+                    "name": group.get("name").cloned().unwrap_or(Value::Null),
+                })),
+                "isArchived": group.get("isArchived").cloned().unwrap_or(Value::Null),
+                "isPrivate": group.get("isPrivate").cloned().unwrap_or(Value::Null),
             }));
         }
         Ok(Value::Array(items))
@@ -803,12 +1433,7 @@ impl KibelClient {
     /// Returns [`KibelClientError::InputInvalid`] when path/paging is invalid,
     /// or transport/API errors from GraphQL.
     pub fn get_note_from_path(&self, input: &PathLookupInput) -> Result<Value, KibelClientError> {
-        let path = input.path.trim();
-        if path.is_empty() {
-            return Err(KibelClientError::InputInvalid(
-                "path is required".to_string(),
-            ));
-        }
+        let path = NotePath::parse(&input.path)?.to_string();
         let first = normalize_first(input.first)?;
         let payload = self.request_trusted_graphql(
             TrustedOperation::GetNoteFromPath,
@@ -821,6 +1446,30 @@ impl KibelClient {
         require_value_at(&payload, "/data/noteFromPath", "note not found")
     }
 
+    /// Gets a note with the metadata an exported Markdown file carries: url,
+    /// `updatedAt`, groups, and folders, plus its author.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when `id` is empty, or
+    /// transport/API errors from GraphQL.
+    pub fn get_note_metadata(&self, id: &str) -> Result<Value, KibelClientError> {
+        let id = id.trim();
+        if id.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "note id is required".to_string(),
+            ));
+        }
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::GetNoteMetadata,
+            trusted_operation_document(TrustedOperation::GetNoteMetadata),
+            json!({
+                "id": id,
+                "first": DEFAULT_FIRST,
+            }),
+        )?;
+        require_value_at(&payload, "/data/note", "note not found")
+    }
+
     /// Gets folder details by id.
     ///
     /// # Errors
@@ -851,12 +1500,7 @@ impl KibelClient {
     /// Returns [`KibelClientError::InputInvalid`] when path/paging is invalid,
     /// or transport/API errors from GraphQL.
     pub fn get_folder_from_path(&self, input: &PathLookupInput) -> Result<Value, KibelClientError> {
-        let path = input.path.trim();
-        if path.is_empty() {
-            return Err(KibelClientError::InputInvalid(
-                "path is required".to_string(),
-            ));
-        }
+        let path = FolderPath::parse(&input.path)?.to_string();
         let first = normalize_first(input.first)?;
         let payload = self.request_trusted_graphql(
             TrustedOperation::GetFolderFromPath,
@@ -875,6 +1519,22 @@ impl KibelClient {
     /// Returns [`KibelClientError::InputInvalid`] when kind/group/paging is
     /// invalid, or transport/API errors from GraphQL.
     pub fn get_feed_sections(&self, input: &FeedSectionsInput) -> Result<Value, KibelClientError> {
+        let payload = self.get_feed_sections_with_page_info(input)?;
+        Ok(payload
+            .get("sections")
+            .cloned()
+            .unwrap_or_else(|| Value::Array(Vec::new())))
+    }
+
+    /// Lists feed section entries and returns connection page info.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when kind/group/paging is
+    /// invalid, or transport/API errors from GraphQL.
+    pub fn get_feed_sections_with_page_info(
+        &self,
+        input: &FeedSectionsInput,
+    ) -> Result<Value, KibelClientError> {
         let kind = input.kind.trim();
         let group_id = input.group_id.trim();
         if kind.is_empty() {
@@ -888,20 +1548,36 @@ impl KibelClient {
             ));
         }
         let first = normalize_first(input.first)?;
+        let mut variables = serde_json::Map::new();
+        variables.insert("kind".to_string(), Value::String(kind.to_string()));
+        variables.insert("groupId".to_string(), Value::String(group_id.to_string()));
+        variables.insert("first".to_string(), json!(first));
+        if let Some(after) = input
+            .after
+            .as_deref()
+            .map(str::trim)
+            .filter(|after| !after.is_empty())
+        {
+            variables.insert("after".to_string(), Value::String(after.to_string()));
+        }
         let payload = self.request_trusted_graphql(
             TrustedOperation::GetFeedSections,
             trusted_operation_document(TrustedOperation::GetFeedSections),
-            json!({
-                "kind": kind,
-                "groupId": group_id,
-                "first": first,
-            }),
+            Value::Object(variables),
         )?;
-        require_value_at(
+        let sections = require_value_at(
             &payload,
             "/data/feedSections/edges",
             "feed sections not found",
-        )
+        )?;
+        let page_info = payload
+            .pointer("/data/feedSections/pageInfo")
+            .cloned()
+            .unwrap_or(Value::Null);
+        Ok(json!({
+            "sections": sections,
+            "pageInfo": page_info,
+        }))
     }
 
     /// Creates a top-level comment on a note.
@@ -980,6 +1656,121 @@ impl KibelClient {
         )
     }
 
+    /// Creates an inline comment attached to a passage of a note.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when required fields are empty,
+    /// or transport/API errors from GraphQL.
+    pub fn create_inline_comment(
+        &self,
+        input: &CreateInlineCommentInput,
+    ) -> Result<IdOnlyResult, KibelClientError> {
+        let content = input.content.trim();
+        let note_id = input.note_id.trim();
+        if content.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "content is required".to_string(),
+            ));
+        }
+        if note_id.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "note id is required".to_string(),
+            ));
+        }
+        if input.anchor.trim().is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "anchor is required".to_string(),
+            ));
+        }
+        let mut variables = json!({
+            "input": {
+                "content": content,
+                "commentableId": note_id,
+                "anchor": input.anchor,
+            }
+        });
+        if let Some(position) = input.anchor_position {
+            variables["input"]["anchorPosition"] = json!(position);
+        }
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::CreateInlineComment,
+            trusted_operation_document(TrustedOperation::CreateInlineComment),
+            variables,
+        )?;
+        parse_id_only_at(
+            &payload,
+            "/data/createInlineComment/inlineComment/id",
+            "createInlineComment response",
+        )
+    }
+
+    /// Replaces the content of an existing comment or reply.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when required fields are empty,
+    /// or transport/API errors from GraphQL.
+    pub fn update_comment(
+        &self,
+        input: &UpdateCommentInput,
+    ) -> Result<IdOnlyResult, KibelClientError> {
+        let id = input.id.trim();
+        let content = input.content.trim();
+        if id.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "comment id is required".to_string(),
+            ));
+        }
+        if content.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "content is required".to_string(),
+            ));
+        }
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::UpdateComment,
+            trusted_operation_document(TrustedOperation::UpdateComment),
+            json!({
+                "input": {
+                    "id": id,
+                    "content": content,
+                }
+            }),
+        )?;
+        parse_id_only_at(
+            &payload,
+            "/data/updateComment/comment/id",
+            "updateComment response",
+        )
+    }
+
+    /// Deletes a comment or reply.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when the id is empty,
+    /// `NOT_FOUND` when the API returns no payload, or transport/API errors
+    /// from GraphQL.
+    pub fn delete_comment(
+        &self,
+        input: &DeleteCommentInput,
+    ) -> Result<IdOnlyResult, KibelClientError> {
+        let id = input.id.trim();
+        if id.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "comment id is required".to_string(),
+            ));
+        }
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::DeleteComment,
+            trusted_operation_document(TrustedOperation::DeleteComment),
+            json!({
+                "input": {
+                    "id": id,
+                }
+            }),
+        )?;
+        require_value_at(&payload, "/data/deleteComment", "comment not found")?;
+        Ok(IdOnlyResult { id: id.to_string() })
+    }
+
     /// Creates a folder in a group.
     ///
     /// # Errors
@@ -1020,6 +1811,93 @@ impl KibelClient {
         )
     }
 
+    /// Resolves a folder path to its id, creating the missing folders of the
+    /// chain when `create_missing` is set. The group is taken from the
+    /// deepest existing folder, or matched by name (case-insensitively) when
+    /// none of the chain exists.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] for a malformed path,
+    /// `NOT_FOUND` when the folder (or, with `create_missing`, the group)
+    /// does not exist, or transport/API errors from GraphQL.
+    pub fn ensure_folder_path(
+        &self,
+        input: &EnsureFolderPathInput,
+    ) -> Result<EnsuredFolder, KibelClientError> {
+        let path = FolderPath::parse(&input.path)?;
+        let mut missing = Vec::new();
+        let mut cursor = Some(path.clone());
+        let mut existing = None;
+        while let Some(current) = cursor {
+            if let Some(folder) = self.lookup_folder_path(&current)? {
+                existing = Some(folder);
+                break;
+            }
+            if !input.create_missing {
+                return Err(KibelClientError::api(
+                    "NOT_FOUND".to_string(),
+                    format!("folder not found: {path}"),
+                ));
+            }
+            cursor = current.parent();
+            missing.push(current);
+        }
+
+        let (mut id, group_id) = match &existing {
+            Some(folder) => (string_at(folder, "/id"), string_at(folder, "/group/id")),
+            None => (String::new(), self.group_id_by_name(path.group())?),
+        };
+        let mut created = Vec::with_capacity(missing.len());
+        for folder in missing.iter().rev() {
+            id = self
+                .create_folder(&CreateFolderInput {
+                    group_id: group_id.clone(),
+                    full_name: folder.name(),
+                })?
+                .id;
+            created.push(folder.to_string());
+        }
+        Ok(EnsuredFolder {
+            id,
+            group_id,
+            folder_name: path.name(),
+            path: path.to_string(),
+            created,
+        })
+    }
+
+    /// `folderFromPath`, with `NOT_FOUND` mapped to `None`.
+    fn lookup_folder_path(&self, path: &FolderPath) -> Result<Option<Value>, KibelClientError> {
+        match self.get_folder_from_path(&PathLookupInput {
+            path: path.to_string(),
+            first: Some(1),
+        }) {
+            Ok(folder) => Ok(Some(folder)),
+            Err(KibelClientError::Api { code, .. }) if code == "NOT_FOUND" => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn group_id_by_name(&self, name: &str) -> Result<String, KibelClientError> {
+        let groups = self.get_groups(PageInput {
+            first: Some(GROUP_LOOKUP_FIRST),
+        })?;
+        groups
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|group| {
+                group
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .is_some_and(|candidate| candidate.eq_ignore_ascii_case(name))
+            })
+            .map(|group| string_at(group, "/id"))
+            .ok_or_else(|| {
+                KibelClientError::api("NOT_FOUND".to_string(), format!("group not found: {name}"))
+            })
+    }
+
     /// Moves a note from one folder to another.
     ///
     /// # Errors
@@ -1088,6 +1966,153 @@ impl KibelClient {
         )
     }
 
+    /// Downloads an attachment from this client's origin and returns its
+    /// SHA-256 digest, hashing at most `max_bytes` of the body.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when the URL is empty or not
+    /// on this client's origin (the access token is never sent elsewhere), or
+    /// transport errors from the download.
+    pub fn digest_attachment(
+        &self,
+        input: &AttachmentDigestInput,
+    ) -> Result<AttachmentDigest, KibelClientError> {
+        let url = input.url.trim();
+        if url.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "attachment url is required".to_string(),
+            ));
+        }
+        if input.max_bytes == 0 {
+            return Err(KibelClientError::InputInvalid(
+                "max bytes must be greater than 0".to_string(),
+            ));
+        }
+        let absolute = self.attachment_url(url)?;
+
+        if let Some(body) = load_attachment_fixture() {
+            return digest_reader(&absolute, None, body.as_bytes(), input.max_bytes);
+        }
+
+        let response = self.fetch_attachment(&absolute)?;
+        digest_reader(
+            &absolute,
+            response.content_length,
+            response.body,
+            input.max_bytes,
+        )
+    }
+
+    /// Downloads an attachment from this client's origin.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when the URL is empty or not
+    /// on this client's origin, or the body is larger than `max_bytes`, or
+    /// transport errors from the download.
+    pub fn download_attachment(
+        &self,
+        input: &DownloadAttachmentInput,
+    ) -> Result<Vec<u8>, KibelClientError> {
+        let url = input.url.trim();
+        if url.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "attachment url is required".to_string(),
+            ));
+        }
+        let absolute = self.attachment_url(url)?;
+        let body = match load_attachment_fixture() {
+            Some(body) => read_limited(body.as_bytes(), input.max_bytes)?,
+            None => read_limited(self.fetch_attachment(&absolute)?.body, input.max_bytes)?,
+        };
+        body.ok_or_else(|| {
+            KibelClientError::InputInvalid(format!(
+                "attachment {absolute} is larger than {} bytes",
+                input.max_bytes
+            ))
+        })
+    }
+
+    /// Uploads a file as an attachment and returns where it was stored.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when the name or data is
+    /// empty, or transport/API errors from GraphQL.
+    pub fn upload_attachment(
+        &self,
+        input: &UploadAttachmentInput,
+    ) -> Result<UploadedAttachment, KibelClientError> {
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "attachment name is required".to_string(),
+            ));
+        }
+        if input.data.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "attachment data is empty".to_string(),
+            ));
+        }
+        let payload = self.request_trusted_graphql(
+            TrustedOperation::UploadAttachment,
+            trusted_operation_document(TrustedOperation::UploadAttachment),
+            json!({
+                "input": {
+                    "name": name,
+                    "kind": attachment_kind(name),
+                    "data": encode_base64(&input.data),
+                }
+            }),
+        )?;
+        let attachment = require_value_at(
+            &payload,
+            "/data/uploadAttachment/attachment",
+            "uploadAttachment response",
+        )?;
+        Ok(UploadedAttachment {
+            id: string_at(&attachment, "/id"),
+            path: string_at(&attachment, "/path"),
+        })
+    }
+
+    /// Makes `url` absolute and checks it is on this client's origin, so the
+    /// access token is never sent elsewhere.
+    fn attachment_url(&self, url: &str) -> Result<String, KibelClientError> {
+        let absolute = if url.starts_with('/') {
+            format!("{}{url}", self.origin)
+        } else {
+            url.to_string()
+        };
+        let origin_prefix = format!("{}/", self.origin.to_ascii_lowercase());
+        if !absolute.to_ascii_lowercase().starts_with(&origin_prefix) {
+            return Err(KibelClientError::InputInvalid(format!(
+                "attachment url must be on {}",
+                self.origin
+            )));
+        }
+        Ok(absolute)
+    }
+
+    fn fetch_attachment(&self, absolute: &str) -> Result<HttpResponse, KibelClientError> {
+        let response = self
+            .http
+            .send(HttpRequest {
+                url: absolute,
+                query: &[],
+                headers: &[("Authorization", &format!("Bearer {}", self.http.token))],
+                body: None,
+                timeout: self.timeouts.total,
+            })
+            .map_err(|error| error.with_request_id(self.http.request_id()))?;
+        if response.status >= 400 {
+            return Err(KibelClientError::Transport {
+                message: format!("{absolute}: status code {}", response.status),
+                status_code: Some(response.status),
+                request_id: Some(self.http.request_id().to_string()),
+            });
+        }
+        Ok(response)
+    }
+
     fn request_trusted_graphql(
         &self,
         operation: TrustedOperation,
@@ -1095,11 +2120,51 @@ impl KibelClient {
         variables: Value,
     ) -> Result<Value, KibelClientError> {
         validate_trusted_operation_request(operation, query, &variables)?;
-        let mode = match trusted_operation_contract(operation).kind {
-            "query" => QueryTransportMode::TrustedQueryApqGet,
+        let contract = trusted_operation_contract(operation);
+        if self.read_only && contract.kind == "mutation" {
+            return Err(KibelClientError::ReadOnlyViolation(format!(
+                "read-only mode blocks the `{}` mutation",
+                contract.name
+            )));
+        }
+        if let Some(policy) = &self.policy {
+            policy.check_trusted(contract.name, &variables)?;
+        }
+        let mode = match contract.kind {
+            "query" => QueryTransportMode::PersistedQueryGet,
             _ => QueryTransportMode::PostOnly,
         };
-        self.request_graphql_raw_with_limits(query, variables, self.timeout_ms, None, mode)
+        self.observe(contract.name, || {
+            self.request_graphql_raw_with_limits(
+                query,
+                None,
+                variables,
+                self.total_timeout_ms(),
+                None,
+                mode,
+            )
+        })
+    }
+
+    /// Runs `send` and records it under `operation` when metrics or usage
+    /// are on.
+    fn observe(
+        &self,
+        operation: &str,
+        send: impl FnOnce() -> Result<Value, KibelClientError>,
+    ) -> Result<Value, KibelClientError> {
+        if self.metrics.is_none() && self.usage.is_none() {
+            return send();
+        }
+        let started = Instant::now();
+        let result = send();
+        if let Some(metrics) = &self.metrics {
+            metrics.record(operation, started.elapsed(), result.as_ref().err());
+        }
+        if let Some(usage) = &self.usage {
+            usage.record_operation(operation, result.as_ref().ok().map(json_size));
+        }
+        result
     }
 
     fn request_graphql_raw(
@@ -1109,8 +2174,9 @@ impl KibelClient {
     ) -> Result<Value, KibelClientError> {
         self.request_graphql_raw_with_limits(
             query,
+            None,
             variables,
-            self.timeout_ms,
+            self.total_timeout_ms(),
             None,
             QueryTransportMode::PostOnly,
         )
@@ -1119,204 +2185,450 @@ impl KibelClient {
     fn request_graphql_raw_with_limits(
         &self,
         query: &str,
+        operation_name: Option<&str>,
         variables: Value,
         timeout_ms: u64,
         max_response_bytes: Option<usize>,
         mode: QueryTransportMode,
     ) -> Result<Value, KibelClientError> {
-        let timeout = Duration::from_millis(timeout_ms.max(100));
-        let payload = json!({
-            "query": query,
-            "variables": variables.clone(),
-        });
-        let payload_raw = payload.to_string();
-
-        test_capture_request_payload(&payload_raw)?;
-
-        if let Some(message) = test_transport_error_message() {
-            return Err(KibelClientError::Transport(message));
+        let request = GraphqlRequest {
+            query: query.to_string(),
+            operation_name: operation_name.map(ToOwned::to_owned),
+            variables,
+            timeout: Duration::from_millis(timeout_ms.max(100)),
+            max_response_bytes,
+            persisted_query: mode == QueryTransportMode::PersistedQueryGet,
+        };
+        let redactor = Redactor::new().with_secret(self.http.token.as_str());
+        let request_id = self.http.request_id();
+        let payload = match &self.transport {
+            Some(transport) => transport.execute(&request),
+            None => self.http.execute(&request),
         }
+        .map_err(|error| redactor.error(error).with_request_id(request_id))?;
+        if let Some(error) = graphql_error(&payload, None) {
+            return Err(redactor.error(error).with_request_id(request_id));
+        }
+        Ok(payload)
+    }
 
-        if let Some(parsed) = load_graphql_response_fixture()? {
-            if let Some((code, message)) = extract_graphql_error(&parsed) {
-                return Err(KibelClientError::Api { code, message });
+    fn resolve_create_note_schema(&self) -> CreateNoteSchema {
+        if let Some(schema) = load_schema_fixture_from_env() {
+            return schema;
+        }
+        if should_skip_runtime_introspection() {
+            return CreateNoteSchema::default();
+        }
+        if let Ok(guard) = self.create_note_schema.lock() {
+            if let Some(schema) = guard.as_ref() {
+                return schema.clone();
             }
-            return Ok(parsed);
         }
 
-        let parsed = match mode {
-            QueryTransportMode::PostOnly => {
-                self.request_graphql_post(timeout, max_response_bytes, query, &variables, None)?
-            }
-            QueryTransportMode::TrustedQueryApqGet => {
-                self.request_trusted_query_with_apq(timeout, max_response_bytes, query, &variables)?
+        if let Ok(payload) = self.request_graphql_raw(QUERY_CREATE_NOTE_SCHEMA, json!({})) {
+            if let Some(schema) = CreateNoteSchema::from_introspection(&payload) {
+                if let Ok(mut guard) = self.create_note_schema.lock() {
+                    *guard = Some(schema.clone());
+                }
+                return schema;
             }
-        };
+        }
+        CreateNoteSchema::default()
+    }
+}
 
-        finalize_graphql_response(parsed)
+impl HttpTransport {
+    /// Builds a transport for `<origin>/api/v1` authenticated with `token`.
+    #[must_use]
+    pub fn new(origin: &str, token: &str) -> Self {
+        Self::for_endpoint(&endpoint_from_origin(origin), token)
+    }
+
+    /// Builds a transport that posts to `endpoint` as given, for GraphQL
+    /// endpoints that are not `<origin>/api/v1` (a proxy, a stub server).
+    #[must_use]
+    pub fn for_endpoint(endpoint: &str, token: &str) -> Self {
+        Self {
+            endpoint: endpoint.trim().to_string(),
+            token: token.trim().to_string(),
+            agent: HttpAgent::new(Timeouts::default()),
+            apq_cache: None,
+            headers: ExtraHeaders::default(),
+            request_id: generate_request_id(),
+        }
+    }
+
+    /// Sends `id` as the `X-Request-Id` header of every request instead of
+    /// the id generated for this transport.
+    #[must_use]
+    pub fn with_request_id(mut self, id: impl Into<String>) -> Self {
+        self.request_id = id.into();
+        self
+    }
+
+    /// The `X-Request-Id` sent with every request.
+    #[must_use]
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// Shares persisted-query registration state through `cache`.
+    #[must_use]
+    pub fn with_apq_cache(mut self, cache: Arc<ApqCache>) -> Self {
+        self.apq_cache = Some(cache);
+        self
+    }
+
+    /// Rebuilds the connection pool with `timeouts.connect` and
+    /// `timeouts.read`. The total deadline comes from each request.
+    #[must_use]
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.agent = HttpAgent::new(timeouts);
+        self
+    }
+
+    /// Adds `headers` to every request, e.g. the `Cf-Access-*` pair an access
+    /// proxy in front of Kibela requires. The request's own headers
+    /// (`Authorization`, `Content-Type`, `Accept`) take precedence.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] for an invalid header name
+    /// or a value with control characters.
+    pub fn with_extra_headers<K, V>(
+        mut self,
+        headers: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, KibelClientError>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.headers.extend(
+            headers
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into())),
+        )?;
+        Ok(self)
+    }
+
+    /// Calls `hook` before every request and adds the headers it returns,
+    /// after the fixed ones from [`HttpTransport::with_extra_headers`].
+    #[must_use]
+    pub fn with_header_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&HeaderRequest<'_>) -> Result<Vec<(String, String)>, KibelClientError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.headers.set_hook(Arc::new(hook));
+        self
+    }
+
+    /// Sends `request` with the extra headers merged in.
+    fn send(&self, request: HttpRequest<'_>) -> Result<HttpResponse, KibelClientError> {
+        let extra = self.headers.resolve(&HeaderRequest {
+            method: if request.body.is_some() {
+                "POST"
+            } else {
+                "GET"
+            },
+            url: request.url,
+            query: request.query,
+            body: request.body,
+        })?;
+        let headers = extra
+            .iter()
+            .filter(|(name, _)| {
+                !request
+                    .headers
+                    .iter()
+                    .any(|(own, _)| own.eq_ignore_ascii_case(name))
+            })
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .chain(request.headers.iter().copied())
+            .chain(
+                (!extra
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case(REQUEST_ID_HEADER)))
+                .then_some((REQUEST_ID_HEADER, self.request_id.as_str())),
+            )
+            .collect::<Vec<_>>();
+        self.agent.send(HttpRequest {
+            headers: &headers,
+            ..request
+        })
     }
 
-    fn request_trusted_query_with_apq(
+    fn request_persisted_query(
         &self,
         timeout: Duration,
         max_response_bytes: Option<usize>,
         query: &str,
+        operation_name: Option<&str>,
         variables: &Value,
     ) -> Result<ParsedGraphqlResponse, KibelClientError> {
         let persisted_hash = sha256_hex(query);
-        let extensions = json!({
-            "persistedQuery": {
-                "version": APQ_VERSION,
-                "sha256Hash": persisted_hash,
+        let extensions = persisted_query_extensions(&persisted_hash);
+        let cache = self.apq_cache.as_deref();
+
+        match cache.map(|cache| cache.lookup(&self.endpoint, &persisted_hash)) {
+            Some(ApqLookup::Unsupported) => {
+                return self.request_graphql_post(
+                    timeout,
+                    max_response_bytes,
+                    query,
+                    operation_name,
+                    variables,
+                    None,
+                );
             }
-        });
+            Some(ApqLookup::Unknown) => {
+                return self.register_persisted_query(
+                    timeout,
+                    max_response_bytes,
+                    query,
+                    operation_name,
+                    variables,
+                    &persisted_hash,
+                );
+            }
+            Some(ApqLookup::Confirmed) | None => {}
+        }
 
         let variables_raw = serde_json::to_string(variables)
-            .map_err(|error| KibelClientError::Transport(format!("json render failed: {error}")))?;
+            .map_err(|error| KibelClientError::transport(format!("json render failed: {error}")))?;
 
         if variables_raw.len() > APQ_GET_VARIABLES_LIMIT_BYTES {
-            return self.request_graphql_post(
+            return self.register_persisted_query(
                 timeout,
                 max_response_bytes,
                 query,
+                operation_name,
                 variables,
-                Some(&extensions),
+                &persisted_hash,
             );
         }
 
         let get_response = self.request_graphql_get_hash_only(
             timeout,
             max_response_bytes,
+            operation_name,
             variables,
             &extensions,
         )?;
         if should_fallback_apq_status(get_response.status_code) {
-            return self.request_graphql_post(timeout, max_response_bytes, query, variables, None);
+            if let Some(cache) = cache {
+                cache.record_fallback(&self.endpoint);
+            }
+            return self.request_graphql_post(
+                timeout,
+                max_response_bytes,
+                query,
+                operation_name,
+                variables,
+                None,
+            );
         }
 
         let Some((error_code, message)) = extract_graphql_error(&get_response.payload) else {
+            if let Some(cache) = cache {
+                cache.confirm(&self.endpoint, &persisted_hash, true);
+            }
             return Ok(get_response);
         };
 
         if is_persisted_query_not_found(&error_code, &message) {
-            return self.request_graphql_post(
+            if let Some(cache) = cache {
+                cache.evict(&self.endpoint, &persisted_hash);
+            }
+            return self.register_persisted_query(
                 timeout,
                 max_response_bytes,
                 query,
+                operation_name,
                 variables,
-                Some(&extensions),
+                &persisted_hash,
             );
         }
         if is_persisted_query_not_supported(&error_code, &message) {
-            return self.request_graphql_post(timeout, max_response_bytes, query, variables, None);
+            if let Some(cache) = cache {
+                cache.record_fallback(&self.endpoint);
+            }
+            return self.request_graphql_post(
+                timeout,
+                max_response_bytes,
+                query,
+                operation_name,
+                variables,
+                None,
+            );
         }
 
         Ok(get_response)
     }
 
+    /// Sends the query text with its persisted hash so the server stores it,
+    /// recording the hash as confirmed when the server accepts it.
+    fn register_persisted_query(
+        &self,
+        timeout: Duration,
+        max_response_bytes: Option<usize>,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: &Value,
+        persisted_hash: &str,
+    ) -> Result<ParsedGraphqlResponse, KibelClientError> {
+        let response = self.request_graphql_post(
+            timeout,
+            max_response_bytes,
+            query,
+            operation_name,
+            variables,
+            Some(&persisted_query_extensions(persisted_hash)),
+        )?;
+        let cache = self.apq_cache.as_deref();
+        match extract_graphql_error(&response.payload) {
+            Some((code, message)) if is_persisted_query_not_supported(&code, &message) => {
+                if let Some(cache) = cache {
+                    cache.record_fallback(&self.endpoint);
+                }
+                self.request_graphql_post(
+                    timeout,
+                    max_response_bytes,
+                    query,
+                    operation_name,
+                    variables,
+                    None,
+                )
+            }
+            None if response.status_code.is_none() => {
+                if let Some(cache) = cache {
+                    cache.confirm(&self.endpoint, persisted_hash, false);
+                }
+                Ok(response)
+            }
+            _ => Ok(response),
+        }
+    }
+
     fn request_graphql_post(
         &self,
         timeout: Duration,
         max_response_bytes: Option<usize>,
         query: &str,
+        operation_name: Option<&str>,
         variables: &Value,
         extensions: Option<&Value>,
     ) -> Result<ParsedGraphqlResponse, KibelClientError> {
         let mut payload_object = serde_json::Map::new();
         payload_object.insert("query".to_string(), Value::String(query.to_string()));
+        if let Some(operation_name) = operation_name {
+            payload_object.insert(
+                "operationName".to_string(),
+                Value::String(operation_name.to_string()),
+            );
+        }
         payload_object.insert("variables".to_string(), variables.clone());
         if let Some(extensions) = extensions {
             payload_object.insert("extensions".to_string(), extensions.clone());
         }
         let payload_raw = Value::Object(payload_object).to_string();
 
-        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
-        let request = agent
-            .post(&self.endpoint)
-            .set("Content-Type", "application/json")
-            .set("Accept", GRAPHQL_ACCEPT_HEADER)
-            .set("Authorization", &format!("Bearer {}", self.token));
-
-        let (raw, status_code) = match request.send_string(&payload_raw) {
-            Ok(response) => {
-                let body = read_response_body(response, max_response_bytes)?;
-                (body, None)
-            }
-            Err(ureq::Error::Status(code, response)) => {
-                let body = read_response_body(response, max_response_bytes)?;
-                (body, Some(code))
-            }
-            Err(err) => {
-                return Err(KibelClientError::Transport(err.to_string()));
-            }
-        };
-
-        parse_http_response(raw, status_code)
+        let authorization = format!("Bearer {}", self.token);
+        let response = self.send(HttpRequest {
+            url: &self.endpoint,
+            query: &[],
+            headers: &[
+                ("Content-Type", "application/json"),
+                ("Accept", GRAPHQL_ACCEPT_HEADER),
+                ("Authorization", &authorization),
+            ],
+            body: Some(&payload_raw),
+            timeout,
+        })?;
+        parse_http_response(response, max_response_bytes)
     }
 
     fn request_graphql_get_hash_only(
         &self,
         timeout: Duration,
         max_response_bytes: Option<usize>,
+        operation_name: Option<&str>,
         variables: &Value,
         extensions: &Value,
     ) -> Result<ParsedGraphqlResponse, KibelClientError> {
         let variables_raw = serde_json::to_string(variables)
-            .map_err(|error| KibelClientError::Transport(format!("json render failed: {error}")))?;
+            .map_err(|error| KibelClientError::transport(format!("json render failed: {error}")))?;
         let extensions_raw = serde_json::to_string(extensions)
-            .map_err(|error| KibelClientError::Transport(format!("json render failed: {error}")))?;
-
-        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
-        let request = agent
-            .get(&self.endpoint)
-            .query("variables", &variables_raw)
-            .query("extensions", &extensions_raw)
-            .set("Accept", GRAPHQL_ACCEPT_HEADER)
-            .set("Authorization", &format!("Bearer {}", self.token));
-
-        let (raw, status_code) = match request.call() {
-            Ok(response) => {
-                let body = read_response_body(response, max_response_bytes)?;
-                (body, None)
-            }
-            Err(ureq::Error::Status(code, response)) => {
-                let body = read_response_body(response, max_response_bytes)?;
-                (body, Some(code))
-            }
-            Err(err) => {
-                return Err(KibelClientError::Transport(err.to_string()));
-            }
-        };
+            .map_err(|error| KibelClientError::transport(format!("json render failed: {error}")))?;
+
+        let mut params = vec![
+            ("variables", variables_raw.as_str()),
+            ("extensions", extensions_raw.as_str()),
+        ];
+        if let Some(operation_name) = operation_name {
+            params.push(("operationName", operation_name));
+        }
+        let authorization = format!("Bearer {}", self.token);
+        let response = self.send(HttpRequest {
+            url: &self.endpoint,
+            query: &params,
+            headers: &[
+                ("Accept", GRAPHQL_ACCEPT_HEADER),
+                ("Authorization", &authorization),
+            ],
+            body: None,
+            timeout,
+        })?;
+        parse_http_response(response, max_response_bytes)
+    }
+}
 
-        parse_http_response(raw, status_code)
+impl GraphqlTransport for HttpTransport {
+    fn execute(&self, request: &GraphqlRequest) -> Result<Value, KibelClientError> {
+        let parsed = if request.persisted_query {
+            self.request_persisted_query(
+                request.timeout,
+                request.max_response_bytes,
+                &request.query,
+                request.operation_name.as_deref(),
+                &request.variables,
+            )?
+        } else {
+            self.request_graphql_post(
+                request.timeout,
+                request.max_response_bytes,
+                &request.query,
+                request.operation_name.as_deref(),
+                &request.variables,
+                None,
+            )?
+        };
+        finalize_graphql_response(parsed)
     }
+}
 
-    fn resolve_create_note_schema(&self) -> CreateNoteSchema {
-        if let Some(schema) = load_schema_fixture_from_env() {
-            return schema;
-        }
-        if should_skip_runtime_introspection() {
-            return CreateNoteSchema::default();
+/// Length of `value` serialized as compact JSON, without building the string.
+fn json_size(value: &Value) -> u64 {
+    struct Counter(u64);
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
         }
-        if let Ok(guard) = self.create_note_schema.lock() {
-            if let Some(schema) = guard.as_ref() {
-                return schema.clone();
-            }
-        }
-
-        if let Ok(payload) = self.request_graphql_raw(QUERY_CREATE_NOTE_SCHEMA, json!({})) {
-            if let Some(schema) = CreateNoteSchema::from_introspection(&payload) {
-                if let Ok(mut guard) = self.create_note_schema.lock() {
-                    *guard = Some(schema.clone());
-                }
-                return schema;
-            }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
         }
-        CreateNoteSchema::default()
     }
+    let mut counter = Counter(0);
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
 }
 
-fn endpoint_from_origin(origin: &str) -> String {
+/// The GraphQL endpoint of a Kibela origin, `<origin>/api/v1`. An origin
+/// that already ends with `/api/v1` is kept.
+#[must_use]
+pub fn endpoint_from_origin(origin: &str) -> String {
     let normalized = origin.trim().trim_end_matches('/');
     if normalized.ends_with("/api/v1") {
         normalized.to_string()
@@ -1325,38 +2637,72 @@ fn endpoint_from_origin(origin: &str) -> String {
     }
 }
 
+/// Bytes read from a response body per `read` call while a size limit is
+/// enforced.
+const RESPONSE_READ_CHUNK_BYTES: usize = 16 * 1024;
+
+/// Reads a response body, enforcing `max_response_bytes` as the bytes
+/// arrive.
+///
+/// A declared `Content-Length` over the limit fails before anything is read,
+/// and a body that grows past it fails as soon as the excess chunk arrives.
+/// The reader is dropped unfinished either way, which closes the connection
+/// instead of draining the rest of the response.
 fn read_response_body(
-    response: ureq::Response,
+    mut body: impl Read,
+    content_length: Option<u64>,
     max_response_bytes: Option<usize>,
 ) -> Result<String, KibelClientError> {
-    match max_response_bytes {
-        Some(limit) => {
-            let mut buffer = Vec::new();
-            response
-                .into_reader()
-                .take((limit.saturating_add(1)) as u64)
-                .read_to_end(&mut buffer)
-                .map_err(|error| KibelClientError::Transport(error.to_string()))?;
-            if buffer.len() > limit {
-                return Err(KibelClientError::Transport(format!(
-                    "response body exceeds limit: {limit} bytes"
-                )));
-            }
-            String::from_utf8(buffer)
-                .map_err(|error| KibelClientError::Transport(error.to_string()))
+    let Some(limit) = max_response_bytes else {
+        let mut raw = String::new();
+        body.read_to_string(&mut raw)
+            .map_err(|error| KibelClientError::transport(error.to_string()))?;
+        return Ok(raw);
+    };
+    let too_large = |received: usize| KibelClientError::ResponseTooLarge {
+        limit,
+        received,
+        content_length,
+        request_id: None,
+    };
+    if content_length.is_some_and(|length| length > limit as u64) {
+        return Err(too_large(0));
+    }
+
+    let mut buffer = Vec::with_capacity(
+        content_length.map_or(RESPONSE_READ_CHUNK_BYTES, |length| length as usize),
+    );
+    let mut chunk = vec![0_u8; RESPONSE_READ_CHUNK_BYTES];
+    loop {
+        let read = match body.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(KibelClientError::transport(error.to_string())),
+        };
+        if buffer.len() + read > limit {
+            return Err(too_large(buffer.len() + read));
         }
-        None => response
-            .into_string()
-            .map_err(|error| KibelClientError::Transport(error.to_string())),
+        buffer.extend_from_slice(&chunk[..read]);
     }
+    String::from_utf8(buffer).map_err(|error| KibelClientError::transport(error.to_string()))
 }
 
 fn parse_http_response(
-    raw: String,
-    status_code: Option<u16>,
+    response: HttpResponse,
+    max_response_bytes: Option<usize>,
 ) -> Result<ParsedGraphqlResponse, KibelClientError> {
-    let payload = serde_json::from_str::<Value>(&raw)
-        .map_err(|error| KibelClientError::Transport(format!("invalid JSON response: {error}")))?;
+    let status_code = (response.status >= 400).then_some(response.status);
+    let raw = read_response_body(response.body, response.content_length, max_response_bytes)?;
+    let payload =
+        serde_json::from_str::<Value>(&raw).map_err(|error| KibelClientError::Transport {
+            message: match status_code {
+                Some(status) => format!("http status {status} with a non-JSON body"),
+                None => format!("invalid JSON response: {error}"),
+            },
+            status_code,
+            request_id: None,
+        })?;
     Ok(ParsedGraphqlResponse {
         payload,
         status_code,
@@ -1364,17 +2710,45 @@ fn parse_http_response(
 }
 
 fn finalize_graphql_response(response: ParsedGraphqlResponse) -> Result<Value, KibelClientError> {
-    if let Some((code, message)) = extract_graphql_error(&response.payload) {
-        return Err(KibelClientError::Api { code, message });
+    if let Some(error) = graphql_error(&response.payload, response.status_code) {
+        return Err(error);
     }
     if let Some(code) = response.status_code {
-        return Err(KibelClientError::Transport(format!(
-            "http status {code} without graphql errors"
-        )));
+        return Err(KibelClientError::Transport {
+            message: format!("http status {code} without graphql errors"),
+            status_code: Some(code),
+            request_id: None,
+        });
     }
     Ok(response.payload)
 }
 
+/// The first GraphQL error of `payload` as [`KibelClientError::Api`], with
+/// its `path` and the HTTP status it came with.
+fn graphql_error(payload: &Value, status_code: Option<u16>) -> Option<KibelClientError> {
+    let (code, message) = extract_graphql_error(payload)?;
+    let path = payload
+        .pointer("/errors/0/path")
+        .and_then(Value::as_array)
+        .map(|segments| {
+            segments
+                .iter()
+                .map(|segment| match segment {
+                    Value::String(name) => name.clone(),
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(KibelClientError::Api {
+        code,
+        message,
+        status_code,
+        path,
+        request_id: None,
+    })
+}
+
 fn should_fallback_apq_status(status_code: Option<u16>) -> bool {
     matches!(
         status_code,
@@ -1396,6 +2770,15 @@ fn is_persisted_query_not_supported(code: &str, message: &str) -> bool {
             .contains("PERSISTED_QUERY_NOT_SUPPORTED")
 }
 
+fn persisted_query_extensions(persisted_hash: &str) -> Value {
+    json!({
+        "persistedQuery": {
+            "version": APQ_VERSION,
+            "sha256Hash": persisted_hash,
+        }
+    })
+}
+
 fn sha256_hex(raw: &str) -> String {
     let digest = Sha256::digest(raw.as_bytes());
     let mut output = String::with_capacity(digest.len() * 2);
@@ -1405,6 +2788,94 @@ fn sha256_hex(raw: &str) -> String {
     output
 }
 
+/// Reads the whole body, or `None` when it is longer than `max_bytes`.
+fn read_limited(reader: impl Read, max_bytes: usize) -> Result<Option<Vec<u8>>, KibelClientError> {
+    let mut body = Vec::new();
+    reader
+        .take(max_bytes.saturating_add(1) as u64)
+        .read_to_end(&mut body)
+        .map_err(|error| KibelClientError::transport(error.to_string()))?;
+    Ok((body.len() <= max_bytes).then_some(body))
+}
+
+/// `IMAGE` for common image extensions, `FILE` otherwise.
+fn attachment_kind(name: &str) -> &'static str {
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp" => "IMAGE",
+        _ => "FILE",
+    }
+}
+
+/// Standard base64 with padding, as the `Blob` scalar expects.
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let group = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for (index, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if index <= chunk.len() {
+                output.push(char::from(ALPHABET[(group >> shift & 0x3f) as usize]));
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+fn digest_reader(
+    url: &str,
+    content_length: Option<u64>,
+    reader: impl Read,
+    max_bytes: usize,
+) -> Result<AttachmentDigest, KibelClientError> {
+    let mut hasher = Sha256::new();
+    let mut limited = reader.take(max_bytes.saturating_add(1) as u64);
+    let mut buffer = [0u8; 8192];
+    let mut bytes_hashed = 0u64;
+    let mut truncated = false;
+    loop {
+        let read = limited
+            .read(&mut buffer)
+            .map_err(|error| KibelClientError::transport(error.to_string()))?;
+        if read == 0 {
+            break;
+        }
+        let remaining = (max_bytes as u64).saturating_sub(bytes_hashed);
+        let take = (read as u64).min(remaining);
+        if take < read as u64 {
+            truncated = true;
+        }
+        let take = usize::try_from(take).unwrap_or(read);
+        hasher.update(&buffer[..take]);
+        bytes_hashed += take as u64;
+        if truncated {
+            break;
+        }
+    }
+    let digest = hasher.finalize();
+    let mut sha256 = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        sha256.push_str(&format!("{byte:02x}"));
+    }
+    Ok(AttachmentDigest {
+        url: url.to_string(),
+        sha256,
+        bytes_hashed,
+        content_length: content_length.or(if truncated { None } else { Some(bytes_hashed) }),
+        truncated,
+    })
+}
+
 fn validate_trusted_operation_request(
     operation: TrustedOperation,
     query: &str,
@@ -1416,26 +2887,26 @@ fn validate_trusted_operation_request(
         .rsplit('.')
         .next()
         .ok_or_else(|| {
-            KibelClientError::Transport(format!(
+            KibelClientError::transport(format!(
                 "invalid graphql_file format in contract: {}",
                 contract.graphql_file
             ))
         })?
         .trim();
     if expected_root.is_empty() {
-        return Err(KibelClientError::Transport(
+        return Err(KibelClientError::transport(
             "empty root field in trusted contract".to_string(),
         ));
     }
 
     let actual_root = extract_root_field(query).ok_or_else(|| {
-        KibelClientError::Transport(format!(
+        KibelClientError::transport(format!(
             "failed to extract root field for trusted operation `{}`",
             contract.name
         ))
     })?;
     if actual_root != expected_root {
-        return Err(KibelClientError::Transport(format!(
+        return Err(KibelClientError::transport(format!(
             "trusted operation `{}` root field mismatch: expected `{}`, got `{}`",
             contract.name, expected_root, actual_root
         )));
@@ -1449,7 +2920,7 @@ fn validate_trusted_operation_request(
         .copied()
         .collect::<Vec<_>>();
     if !missing_declarations.is_empty() {
-        return Err(KibelClientError::Transport(format!(
+        return Err(KibelClientError::transport(format!(
             "trusted operation `{}` required variable(s) are not declared in query: {}",
             contract.name,
             missing_declarations.join(", ")
@@ -1457,7 +2928,7 @@ fn validate_trusted_operation_request(
     }
 
     let object = variables.as_object().ok_or_else(|| {
-        KibelClientError::Transport(format!(
+        KibelClientError::transport(format!(
             "trusted operation `{}` requires JSON object variables",
             contract.name
         ))
@@ -1470,7 +2941,7 @@ fn validate_trusted_operation_request(
         .copied()
         .collect::<Vec<_>>();
     if !missing_required.is_empty() {
-        return Err(KibelClientError::Transport(format!(
+        return Err(KibelClientError::transport(format!(
             "trusted operation `{}` missing required variable(s): {}",
             contract.name,
             missing_required.join(", ")
@@ -1483,7 +2954,7 @@ fn validate_trusted_operation_request(
         .cloned()
         .collect::<Vec<_>>();
     if !unsupported.is_empty() {
-        return Err(KibelClientError::Transport(format!(
+        return Err(KibelClientError::transport(format!(
             "trusted operation `{}` has undeclared variable(s): {}",
             contract.name,
             unsupported.join(", ")
@@ -1569,22 +3040,22 @@ fn read_identifier(bytes: &[u8], index: &mut usize) -> Option<String> {
 
 fn parse_note_at(payload: &Value, pointer: &str) -> Result<Note, KibelClientError> {
     let value = payload.pointer(pointer).ok_or_else(|| {
-        KibelClientError::Transport(format!("missing `{pointer}` field in GraphQL response"))
+        KibelClientError::transport(format!("missing `{pointer}` field in GraphQL response"))
     })?;
 
     serde_json::from_value::<Note>(value.clone())
-        .map_err(|err| KibelClientError::Transport(format!("invalid note payload: {err}")))
+        .map_err(|err| KibelClientError::transport(format!("invalid note payload: {err}")))
 }
 
 fn parse_create_note_at(payload: &Value, pointer: &str) -> Result<Note, KibelClientError> {
     let value = payload.pointer(pointer).ok_or_else(|| {
-        KibelClientError::Transport(format!("missing `{pointer}` field in GraphQL response"))
+        KibelClientError::transport(format!("missing `{pointer}` field in GraphQL response"))
     })?;
     let id = value
         .get("id")
         .and_then(Value::as_str)
         .ok_or_else(|| {
-            KibelClientError::Transport("missing `id` in createNote response".to_string())
+            KibelClientError::transport("missing `id` in createNote response".to_string())
         })?
         .to_string();
     let title = value
@@ -1609,7 +3080,7 @@ fn parse_id_only_at(
     let id = payload
         .pointer(pointer)
         .and_then(Value::as_str)
-        .ok_or_else(|| KibelClientError::Transport(format!("missing `id` in {context}")))?
+        .ok_or_else(|| KibelClientError::transport(format!("missing `id` in {context}")))?
         .to_string();
     Ok(IdOnlyResult { id })
 }
@@ -1622,9 +3093,8 @@ fn require_array_at<'a>(
     payload
         .pointer(pointer)
         .and_then(Value::as_array)
-        .ok_or_else(|| KibelClientError::Api {
-            code: "NOT_FOUND".to_string(),
-            message: not_found_message.to_string(),
+        .ok_or_else(|| {
+            KibelClientError::api("NOT_FOUND".to_string(), not_found_message.to_string())
         })
 }
 
@@ -1633,19 +3103,15 @@ fn require_value_at(
     pointer: &str,
     not_found_message: &str,
 ) -> Result<Value, KibelClientError> {
-    let value = payload
-        .pointer(pointer)
-        .cloned()
-        .ok_or_else(|| KibelClientError::Api {
-            code: "NOT_FOUND".to_string(),
-            message: not_found_message.to_string(),
-        })?;
+    let value = payload.pointer(pointer).cloned().ok_or_else(|| {
+        KibelClientError::api("NOT_FOUND".to_string(), not_found_message.to_string())
+    })?;
 
     if value.is_null() {
-        return Err(KibelClientError::Api {
-            code: "NOT_FOUND".to_string(),
-            message: not_found_message.to_string(),
-        });
+        return Err(KibelClientError::api(
+            "NOT_FOUND".to_string(),
+            not_found_message.to_string(),
+        ));
     }
     Ok(value)
 }
@@ -1754,6 +3220,14 @@ fn normalize_vec(values: &[String]) -> Vec<String> {
         .collect()
 }
 
+fn string_at(value: &Value, pointer: &str) -> String {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
 fn normalize_first(first: Option<u32>) -> Result<u32, KibelClientError> {
     let value = first.unwrap_or(DEFAULT_FIRST);
     if value == 0 {
@@ -1778,49 +3252,179 @@ fn normalize_folder(folder: &CreateNoteFolderInput) -> Result<Value, KibelClient
     }))
 }
 
+/// Replays `KIBEL_TEST_GRAPHQL_RESPONSE` (or fails with
+/// `KIBEL_TEST_TRANSPORT_ERROR`) for every request, writing the request
+/// payload to `KIBEL_TEST_CAPTURE_REQUEST_PATH` first. Installed by
+/// [`KibelClient::new`] when either variable is set, so CLI end-to-end tests
+/// run without a server.
 #[cfg(any(test, feature = "test-hooks"))]
-fn test_capture_request_payload(payload_raw: &str) -> Result<(), KibelClientError> {
-    if let Ok(path) = std::env::var("KIBEL_TEST_CAPTURE_REQUEST_PATH") {
-        let path = path.trim();
-        if !path.is_empty() {
-            fs::write(path, payload_raw)
-                .map_err(|err| KibelClientError::Transport(err.to_string()))?;
-        }
+#[derive(Debug, Clone, Default)]
+struct EnvFixtureTransport {
+    response: Option<String>,
+    error: Option<String>,
+    capture_path: Option<String>,
+}
+
+#[cfg(any(test, feature = "test-hooks"))]
+impl EnvFixtureTransport {
+    fn from_env() -> Option<Self> {
+        let fixture = Self {
+            response: std::env::var("KIBEL_TEST_GRAPHQL_RESPONSE").ok(),
+            error: std::env::var("KIBEL_TEST_TRANSPORT_ERROR")
+                .ok()
+                .map(|message| message.trim().to_string())
+                .filter(|message| !message.is_empty()),
+            capture_path: std::env::var("KIBEL_TEST_CAPTURE_REQUEST_PATH")
+                .ok()
+                .map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty()),
+        };
+        (fixture.response.is_some() || fixture.error.is_some()).then_some(fixture)
     }
-    Ok(())
 }
 
-#[cfg(not(any(test, feature = "test-hooks")))]
-fn test_capture_request_payload(_payload_raw: &str) -> Result<(), KibelClientError> {
-    Ok(())
+#[cfg(any(test, feature = "test-hooks"))]
+impl GraphqlTransport for EnvFixtureTransport {
+    fn execute(&self, request: &GraphqlRequest) -> Result<Value, KibelClientError> {
+        if let Some(path) = &self.capture_path {
+            fs::write(path, request.to_payload().to_string())
+                .map_err(|err| KibelClientError::transport(err.to_string()))?;
+        }
+        if let Some(message) = &self.error {
+            return Err(KibelClientError::transport(message.clone()));
+        }
+        let fixture = self.response.as_deref().unwrap_or("{}");
+        serde_json::from_str::<Value>(fixture)
+            .map_err(|err| KibelClientError::transport(format!("invalid test fixture JSON: {err}")))
+    }
 }
 
 #[cfg(any(test, feature = "test-hooks"))]
-fn test_transport_error_message() -> Option<String> {
-    std::env::var("KIBEL_TEST_TRANSPORT_ERROR")
-        .ok()
-        .map(|message| message.trim().to_string())
-        .filter(|message| !message.is_empty())
+fn env_fixture_transport() -> Option<Arc<dyn GraphqlTransport>> {
+    EnvFixtureTransport::from_env().map(|fixture| Arc::new(fixture) as Arc<dyn GraphqlTransport>)
 }
 
 #[cfg(not(any(test, feature = "test-hooks")))]
-fn test_transport_error_message() -> Option<String> {
+fn env_fixture_transport() -> Option<Arc<dyn GraphqlTransport>> {
     None
 }
 
-#[cfg(any(test, feature = "test-hooks"))]
-fn load_graphql_response_fixture() -> Result<Option<Value>, KibelClientError> {
-    let Some(fixture) = std::env::var("KIBEL_TEST_GRAPHQL_RESPONSE").ok() else {
-        return Ok(None);
+/// Whether the operation that would run is a mutation: the one named
+/// `operation_name`, or the document's only operation.
+///
+/// This is a lexical scan of top-level definitions (strings and comments are
+/// skipped). When the choice is ambiguous, any mutation in the document
+/// counts, so read-only mode errs on the side of blocking.
+fn document_selects_mutation(query: &str, operation_name: Option<&str>) -> bool {
+    let chars = query.chars().collect::<Vec<_>>();
+    // (is_mutation, name) per operation definition.
+    let mut operations: Vec<(bool, Option<String>)> = Vec::new();
+    let (mut depth, mut parens, mut index) = (0_usize, 0_usize, 0);
+    let mut in_definition = false;
+    let mut expect_name = false;
+    let mut skip_words = 0;
+    while index < chars.len() {
+        let ch = chars[index];
+        match ch {
+            '#' => {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                }
+                continue;
+            }
+            '"' => {
+                let block = chars[index..].starts_with(&['"', '"', '"']);
+                index += if block { 3 } else { 1 };
+                while index < chars.len() {
+                    if chars[index] == '\\' {
+                        index += 2;
+                        continue;
+                    }
+                    if block && chars[index..].starts_with(&['"', '"', '"']) {
+                        index += 3;
+                        break;
+                    }
+                    if !block && (chars[index] == '"' || chars[index] == '\n') {
+                        index += 1;
+                        break;
+                    }
+                    index += 1;
+                }
+                continue;
+            }
+            '(' => parens += 1,
+            ')' => parens = parens.saturating_sub(1),
+            '{' => {
+                if depth == 0 && !in_definition {
+                    operations.push((false, None));
+                }
+                depth += 1;
+                expect_name = false;
+            }
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    in_definition = false;
+                }
+            }
+            ch if ch.is_alphabetic() || ch == '_' => {
+                let start = index;
+                while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_')
+                {
+                    index += 1;
+                }
+                if depth == 0 && parens == 0 {
+                    let word = chars[start..index].iter().collect::<String>();
+                    if skip_words > 0 {
+                        skip_words -= 1;
+                    } else if expect_name {
+                        if let Some(operation) = operations.last_mut() {
+                            operation.1 = Some(word);
+                        }
+                        expect_name = false;
+                    } else if !in_definition {
+                        match word.as_str() {
+                            "query" | "mutation" | "subscription" => {
+                                operations.push((word == "mutation", None));
+                                in_definition = true;
+                                expect_name = true;
+                            }
+                            "fragment" => {
+                                in_definition = true;
+                                skip_words = 3;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+
+    let selected = match operation_name {
+        Some(name) => operations
+            .iter()
+            .find(|(_, found)| found.as_deref() == Some(name)),
+        None if operations.len() == 1 => operations.first(),
+        None => None,
     };
-    let parsed = serde_json::from_str::<Value>(&fixture)
-        .map_err(|err| KibelClientError::Transport(format!("invalid test fixture JSON: {err}")))?;
-    Ok(Some(parsed))
+    match selected {
+        Some((is_mutation, _)) => *is_mutation,
+        None => operations.iter().any(|(is_mutation, _)| *is_mutation),
+    }
+}
+
+#[cfg(any(test, feature = "test-hooks"))]
+fn load_attachment_fixture() -> Option<String> {
+    std::env::var("KIBEL_TEST_ATTACHMENT_BODY").ok()
 }
 
 #[cfg(not(any(test, feature = "test-hooks")))]
-fn load_graphql_response_fixture() -> Result<Option<Value>, KibelClientError> {
-    Ok(None)
+fn load_attachment_fixture() -> Option<String> {
+    None
 }
 
 #[cfg(any(test, feature = "test-hooks"))]
@@ -1983,16 +3587,97 @@ fn collect_name_set(value: &Value) -> BTreeSet<String> {
 #[cfg(test)]
 mod tests {
     use super::{
-        build_search_note_variables, collect_name_set, endpoint_from_origin, extract_graphql_error,
-        extract_root_field, is_persisted_query_not_found, is_persisted_query_not_supported,
-        load_schema_fixture_from_env, parse_create_note_at, resource_contract_upstream_commit,
-        resource_contract_version, resource_contracts, should_fallback_apq_status,
-        should_skip_runtime_introspection, trusted_operation_contract, trusted_operation_document,
-        trusted_operations, validate_trusted_operation_request, CreateNoteInput, CreateNoteSchema,
-        KibelClient, SearchNoteInput, TrustedOperation,
+        build_search_note_variables, collect_name_set, digest_reader, endpoint_from_origin,
+        extract_graphql_error, extract_root_field, finalize_graphql_response,
+        is_persisted_query_not_found, is_persisted_query_not_supported,
+        load_schema_fixture_from_env, parse_create_note_at, read_response_body,
+        resource_contract_upstream_commit, resource_contract_version, resource_contracts,
+        should_fallback_apq_status, should_skip_runtime_introspection, trusted_operation_contract,
+        trusted_operation_document, trusted_operations, validate_trusted_operation_request,
+        CreateNoteInput, CreateNoteSchema, GraphqlRequest, KibelClient, KibelClientError,
+        ParsedGraphqlResponse, SearchNoteInput, TrustedOperation,
     };
     use serde_json::json;
-    use tempfile::NamedTempFile;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn read_response_body_aborts_as_soon_as_the_limit_is_passed() {
+        assert_eq!(
+            read_response_body(&b"{}"[..], Some(2), Some(2)).expect("fits"),
+            "{}"
+        );
+
+        // An endless body without Content-Length stops one chunk past the limit.
+        let error = read_response_body(std::io::repeat(b'x'), None, Some(40_000))
+            .expect_err("endless body must abort");
+        let KibelClientError::ResponseTooLarge {
+            limit,
+            received,
+            content_length,
+            ..
+        } = error
+        else {
+            panic!("unexpected error: {error}");
+        };
+        assert_eq!((limit, content_length), (40_000, None));
+        assert!(received > limit && received <= limit + super::RESPONSE_READ_CHUNK_BYTES);
+
+        let error = read_response_body(std::io::repeat(b'x'), Some(1 << 40), Some(1024))
+            .expect_err("declared length over the limit");
+        assert!(matches!(
+            error,
+            KibelClientError::ResponseTooLarge {
+                received: 0,
+                content_length: Some(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn digest_reader_hashes_full_body_and_marks_truncation() {
+        let full = digest_reader("https://example.kibe.la/a.png", None, &b"hello"[..], 16)
+            .expect("digest should succeed");
+        assert_eq!(
+            full.sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(full.bytes_hashed, 5);
+        assert_eq!(full.content_length, Some(5));
+        assert!(!full.truncated);
+
+        let truncated = digest_reader("https://example.kibe.la/a.png", None, &b"hello"[..], 2)
+            .expect("digest should succeed");
+        assert_eq!(truncated.bytes_hashed, 2);
+        assert_eq!(truncated.content_length, None);
+        assert!(truncated.truncated);
+    }
+
+    #[test]
+    fn upload_encoding_pads_base64_and_picks_attachment_kind() {
+        assert_eq!(super::encode_base64(b""), "");
+        assert_eq!(super::encode_base64(b"f"), "Zg==");
+        assert_eq!(super::encode_base64(b"fo"), "Zm8=");
+        assert_eq!(super::encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(super::attachment_kind("Diagram.PNG"), "IMAGE");
+        assert_eq!(super::attachment_kind("notes.pdf"), "FILE");
+        assert_eq!(super::attachment_kind("README"), "FILE");
+        assert_eq!(super::read_limited(&b"hello"[..], 4).expect("read"), None);
+    }
+
+    #[test]
+    fn digest_attachment_rejects_foreign_origin() {
+        let client = KibelClient::new("https://example.kibe.la", "token").expect("client");
+        let error = client
+            .digest_attachment(&super::AttachmentDigestInput {
+                url: "https://attacker.example/a.png".to_string(),
+                max_bytes: 16,
+            })
+            .expect_err("foreign origin should be rejected");
+        assert!(error
+            .to_string()
+            .contains("must be on https://example.kibe.la"));
+    }
 
     #[test]
     fn endpoint_keeps_api_path_when_present() {
@@ -2026,6 +3711,41 @@ mod tests {
         assert_eq!(message, "no note");
     }
 
+    #[test]
+    fn failed_responses_keep_status_path_and_retryability() {
+        let error = finalize_graphql_response(ParsedGraphqlResponse {
+            payload: json!({
+                "errors": [{
+                    "message": "folder lookup failed",
+                    "path": ["note", "folders", 0],
+                    "extensions": {"code": "INTERNAL"}
+                }]
+            }),
+            status_code: Some(503),
+        })
+        .expect_err("errors must fail")
+        .with_request_id("req-1");
+        assert_eq!(error.graphql_code(), Some("INTERNAL"));
+        assert_eq!(error.status_code(), Some(503));
+        assert_eq!(error.path(), ["note", "folders", "0"]);
+        assert!(error.retryable());
+        assert_eq!(
+            error.to_string(),
+            "api error [INTERNAL]: folder lookup failed (request id req-1)"
+        );
+
+        let error = finalize_graphql_response(ParsedGraphqlResponse {
+            payload: json!({"message": "bad request"}),
+            status_code: Some(400),
+        })
+        .expect_err("4xx must fail");
+        assert_eq!(error.status_code(), Some(400));
+        assert!(!error.retryable());
+        assert!(KibelClientError::transport("connection reset").retryable());
+        assert!(!KibelClientError::api("NOT_FOUND", "gone").retryable());
+        assert!(KibelClientError::api("TOKEN_BUDGET_EXHAUSTED", "wait").retryable());
+    }
+
     #[test]
     fn create_note_schema_parses_introspection_payload() {
         let payload = json!({
@@ -2182,11 +3902,13 @@ mod tests {
     #[test]
     fn generated_resource_contracts_cover_all_resources() {
         let contracts = resource_contracts();
-        assert!(contracts.len() >= 17);
+        assert!(contracts.len() >= 20);
         assert!(contracts.iter().any(|item| item.name == "createNote"));
         assert!(contracts
             .iter()
             .any(|item| item.name == "updateNoteContent"));
+        assert!(contracts.iter().any(|item| item.name == "getDraftNotes"));
+        assert!(contracts.iter().any(|item| item.name == "updateNote"));
     }
 
     #[test]
@@ -2235,7 +3957,7 @@ query AliasQuery($id: ID!) {
         )
         .expect_err("validation should fail");
         match error {
-            super::KibelClientError::Transport(message) => {
+            super::KibelClientError::Transport { message, .. } => {
                 assert!(message.contains("missing required variable(s): id"));
             }
             other => panic!("unexpected error: {other:?}"),
@@ -2254,7 +3976,7 @@ query AliasQuery($id: ID!) {
         )
         .expect_err("validation should fail");
         match error {
-            super::KibelClientError::Transport(message) => {
+            super::KibelClientError::Transport { message, .. } => {
                 assert!(message.contains("undeclared variable(s): first"));
             }
             other => panic!("unexpected error: {other:?}"),
@@ -2286,7 +4008,7 @@ query AliasQuery($id: ID!) {
         )
         .expect_err("validation should fail");
         match error {
-            super::KibelClientError::Transport(message) => {
+            super::KibelClientError::Transport { message, .. } => {
                 assert!(message.contains("root field mismatch"));
             }
             other => panic!("unexpected error: {other:?}"),
@@ -2320,12 +4042,14 @@ query AliasQuery($id: ID!) {
 
     #[test]
     fn create_note_normalizes_group_ids_before_request() {
-        let capture = NamedTempFile::new().expect("capture temp file should be created");
-        let capture_path = capture.path().to_string_lossy().to_string();
-        std::env::set_var("KIBEL_TEST_CAPTURE_REQUEST_PATH", &capture_path);
-
-        let client =
-            KibelClient::new("http://127.0.0.1:9", "test-token").expect("client should be created");
+        let captured = Arc::new(Mutex::new(Vec::<GraphqlRequest>::new()));
+        let sink = Arc::clone(&captured);
+        let client = KibelClient::new("http://127.0.0.1:9", "test-token")
+            .expect("client should be created")
+            .with_transport(Arc::new(move |request: &GraphqlRequest| {
+                sink.lock().expect("capture lock").push(request.clone());
+                Err(KibelClientError::transport("captured".to_string()))
+            }));
         let _ = client.create_note(&CreateNoteInput {
             title: "Title".to_string(),
             content: "Content".to_string(),
@@ -2338,15 +4062,145 @@ query AliasQuery($id: ID!) {
             client_mutation_id: None,
         });
 
-        let payload =
-            std::fs::read_to_string(&capture_path).expect("captured payload should exist");
-        let parsed: serde_json::Value =
-            serde_json::from_str(&payload).expect("captured payload should be valid json");
+        let captured = captured.lock().expect("capture lock");
+        let request = captured.last().expect("request should be captured");
+        assert!(!request.persisted_query);
         assert_eq!(
-            parsed["variables"]["input"]["groupIds"],
+            request.to_payload()["variables"]["input"]["groupIds"],
             json!(["G1", "G2"])
         );
-        std::env::remove_var("KIBEL_TEST_CAPTURE_REQUEST_PATH");
+    }
+
+    #[test]
+    fn ensure_folder_path_creates_missing_levels_below_existing_folder() {
+        let captured = Arc::new(Mutex::new(Vec::<GraphqlRequest>::new()));
+        let sink = Arc::clone(&captured);
+        let client = KibelClient::new("http://127.0.0.1:9", "test-token")
+            .expect("client should be created")
+            .with_transport(Arc::new(move |request: &GraphqlRequest| {
+                sink.lock().expect("capture lock").push(request.clone());
+                let variables = &request.variables;
+                let operation = super::extract_root_field(&request.query);
+                Ok(match operation.as_deref() {
+                    Some("folderFromPath") if variables["path"] == "/acme/eng" => json!({
+                        "data": { "folderFromPath": { "id": "F-eng", "group": { "id": "G1" } } }
+                    }),
+                    Some("folderFromPath") => json!({ "data": { "folderFromPath": null } }),
+                    Some("createFolder") => json!({
+                        "data": { "createFolder": { "folder": {
+                            "id": format!("F:{}", variables["input"]["folder"]["folderName"].as_str().unwrap_or_default())
+                        } } }
+                    }),
+                    other => panic!("unexpected operation: {other:?}"),
+                })
+            }));
+
+        let input = |create_missing| super::EnsureFolderPathInput {
+            path: "acme/eng/api/v2".to_string(),
+            create_missing,
+        };
+        match client.ensure_folder_path(&input(false)) {
+            Err(KibelClientError::Api { code, .. }) => assert_eq!(code, "NOT_FOUND"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        let ensured = client
+            .ensure_folder_path(&input(true))
+            .expect("folder chain should be ensured");
+        assert_eq!(ensured.id, "F:eng/api/v2");
+        assert_eq!(ensured.group_id, "G1");
+        assert_eq!(ensured.folder_name, "eng/api/v2");
+        assert_eq!(ensured.created, vec!["/acme/eng/api", "/acme/eng/api/v2"]);
+        let created = captured
+            .lock()
+            .expect("capture lock")
+            .iter()
+            .filter(|request| request.query.contains("createFolder("))
+            .map(|request| request.variables["input"]["folder"]["groupId"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(created, vec![json!("G1"), json!("G1")]);
+    }
+
+    #[test]
+    fn read_only_client_blocks_mutations_before_the_transport() {
+        let captured = Arc::new(Mutex::new(Vec::<String>::new()));
+        let sink = Arc::clone(&captured);
+        let client = KibelClient::new("http://127.0.0.1:9", "test-token")
+            .expect("client should be created")
+            .with_read_only(true)
+            .with_transport(Arc::new(move |request: &GraphqlRequest| {
+                sink.lock()
+                    .expect("capture lock")
+                    .push(request.query.clone());
+                Ok(json!({ "data": { "currentUser": { "id": "U1" } } }))
+            }));
+        assert!(client.is_read_only());
+        assert!(client.clone().with_read_only(false).is_read_only());
+
+        let created = client.create_note(&CreateNoteInput {
+            title: "Title".to_string(),
+            content: "Content".to_string(),
+            group_ids: vec!["G1".to_string()],
+            draft: None,
+            coediting: false,
+            folders: vec![],
+            author_id: None,
+            published_at: None,
+            client_mutation_id: None,
+        });
+        assert!(matches!(
+            created,
+            Err(KibelClientError::ReadOnlyViolation(_))
+        ));
+        let adhoc = client.run_untrusted_graphql(
+            "mutation M { deleteNote(input: {id: \"N1\"}) { clientMutationId } }",
+            json!({}),
+            1000,
+            1024,
+        );
+        assert!(matches!(adhoc, Err(KibelClientError::ReadOnlyViolation(_))));
+        assert!(captured.lock().expect("capture lock").is_empty());
+
+        client
+            .run_untrusted_graphql("query { currentUser { id } }", json!({}), 1000, 1024)
+            .expect("queries still run");
+        assert_eq!(captured.lock().expect("capture lock").len(), 1);
+    }
+
+    #[test]
+    fn document_selects_mutation_follows_the_selected_operation() {
+        use super::document_selects_mutation;
+
+        assert!(!document_selects_mutation("{ currentUser { id } }", None));
+        assert!(!document_selects_mutation(
+            "# mutation\nquery Q($s: String = \"mutation\") { note(id: \"1\") { id } }",
+            None
+        ));
+        assert!(document_selects_mutation("mutation { a }", None));
+        let both = "query Read { a } mutation Write { b }";
+        assert!(!document_selects_mutation(both, Some("Read")));
+        assert!(document_selects_mutation(both, Some("Write")));
+        assert!(document_selects_mutation(both, None));
+        assert!(!document_selects_mutation(
+            "query Q { ...F } fragment F on Query { mutation }",
+            None
+        ));
+    }
+
+    #[test]
+    fn injected_transport_results_map_graphql_errors() {
+        let client = KibelClient::new("http://127.0.0.1:9", "test-token")
+            .expect("client should be created")
+            .with_transport(Arc::new(|request: &GraphqlRequest| {
+                assert!(request.persisted_query, "getNote is a trusted query");
+                Ok(json!({
+                    "errors": [{"message": "missing", "extensions": {"code": "NOT_FOUND"}}]
+                }))
+            }));
+        match client.get_note("N1") {
+            Err(KibelClientError::Api { code, .. }) => assert_eq!(code, "NOT_FOUND"),
+            other => panic!("unexpected result: {other:?}"),
+        }
     }
 
     #[test]
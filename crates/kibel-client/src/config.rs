@@ -1,4 +1,6 @@
 use crate::error::KibelClientError;
+use crate::secret_scan::SecretScanConfig;
+use crate::store::TokenStoreBackend;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -14,9 +16,64 @@ pub struct Config {
     #[serde(default)]
     pub default_team: Option<String>,
     #[serde(default)]
+    pub templates_dir: Option<String>,
+    #[serde(default)]
+    pub on_fetch: Option<Vec<String>>,
+    /// Command `open` launches URLs with; `BROWSER` overrides it.
+    #[serde(default)]
+    pub browser: Option<String>,
+    /// Default for `--copy-url` on `note create` and `note get`.
+    #[serde(default)]
+    pub copy_url: Option<bool>,
+    /// `false` stops recording the local stats `kibel usage` reports.
+    #[serde(default)]
+    pub usage_stats: Option<bool>,
+    /// Where `auth login` keeps tokens; `KIBEL_TOKEN_STORE` overrides it.
+    #[serde(default)]
+    pub token_store: Option<TokenStoreBackend>,
+    #[serde(default)]
     pub profiles: BTreeMap<String, Profile>,
     #[serde(default)]
     pub search_note_presets: BTreeMap<String, SearchNotePreset>,
+    /// Rules `note create --check` validates content against.
+    #[serde(default)]
+    pub pre_publish: Option<PrePublishChecks>,
+    /// Credential detection for outgoing note and comment content.
+    #[serde(default)]
+    pub secret_scan: Option<SecretScanConfig>,
+}
+
+/// `[pre_publish]`: validations run on note content before it is published.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrePublishChecks {
+    /// Largest content accepted, in bytes.
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+    /// Headings (any level, case-insensitive) the content must have.
+    #[serde(default)]
+    pub required_sections: Vec<String>,
+    /// Strings that must not appear, e.g. secret prefixes like `AKIA`.
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+    /// Flags relative links that break once published and note links to
+    /// notes that do not exist.
+    #[serde(default = "default_true")]
+    pub check_links: bool,
+}
+
+impl Default for PrePublishChecks {
+    fn default() -> Self {
+        Self {
+            max_bytes: None,
+            required_sections: Vec::new(),
+            forbidden: Vec::new(),
+            check_links: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -25,6 +82,22 @@ pub struct Profile {
     pub token: Option<String>,
     #[serde(default)]
     pub origin: Option<String>,
+    #[serde(default)]
+    pub on_fetch: Option<Vec<String>>,
+    /// Headers sent with every request to this team, e.g. for an access
+    /// proxy in front of Kibela.
+    #[serde(default)]
+    pub extra_headers: Option<BTreeMap<String, String>>,
+    /// Group id `note create` shares with when no group or folder is given.
+    #[serde(default)]
+    pub default_group: Option<String>,
+    /// Folder path (`GROUP/FOLDER/...`) `note create` files into when no
+    /// group or folder is given.
+    #[serde(default)]
+    pub default_folder_path: Option<String>,
+    /// Whether `note create` turns coediting on unless told otherwise.
+    #[serde(default)]
+    pub default_coediting: Option<bool>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -60,10 +133,69 @@ pub struct SearchNotePreset {
 /// # Errors
 /// Returns [`KibelClientError::ConfigDirectoryUnavailable`] when the OS config
 /// directory cannot be resolved.
+///
+/// This is `config.toml`, or `config.json` when only that one exists.
 pub fn default_config_path() -> Result<PathBuf, KibelClientError> {
     let dirs = ProjectDirs::from(PROJECT_QUALIFIER, PROJECT_ORGANIZATION, PROJECT_APPLICATION)
         .ok_or(KibelClientError::ConfigDirectoryUnavailable)?;
-    Ok(dirs.config_dir().join("config.toml"))
+    let toml = dirs.config_dir().join("config.toml");
+    let json = toml.with_extension(ConfigFormat::Json.extension());
+    Ok(if !toml.exists() && json.exists() {
+        json
+    } else {
+        toml
+    })
+}
+
+/// Syntax of a config file, picked by its extension: `.json` is JSON and
+/// anything else TOML. Both hold the same [`Config`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// File name of the per-repository config found by [`find_project_config`].
+pub const PROJECT_CONFIG_FILE: &str = ".kibel.toml";
+
+/// Finds the nearest [`PROJECT_CONFIG_FILE`] in `start` or one of its
+/// ancestors.
+#[must_use]
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Returns the OS cache directory for kibel.
+///
+/// # Errors
+/// Returns [`KibelClientError::ConfigDirectoryUnavailable`] when the OS cache
+/// directory cannot be resolved.
+pub fn default_cache_dir() -> Result<PathBuf, KibelClientError> {
+    let dirs = ProjectDirs::from(PROJECT_QUALIFIER, PROJECT_ORGANIZATION, PROJECT_APPLICATION)
+        .ok_or(KibelClientError::ConfigDirectoryUnavailable)?;
+    Ok(dirs.cache_dir().to_path_buf())
 }
 
 impl Config {
@@ -72,8 +204,8 @@ impl Config {
     /// If the file does not exist, this returns `Config::default()`.
     ///
     /// # Errors
-    /// Returns [`KibelClientError::ConfigRead`] on I/O errors and
-    /// [`KibelClientError::ConfigParse`] when TOML parsing fails.
+    /// Returns [`KibelClientError::ConfigRead`] on I/O errors and a parse
+    /// error when the file is not valid in the format its extension names.
     pub fn load(path: impl AsRef<Path>) -> Result<Self, KibelClientError> {
         let path = path.as_ref();
         if !path.exists() {
@@ -81,22 +213,73 @@ impl Config {
         }
 
         let raw = fs::read_to_string(path).map_err(KibelClientError::ConfigRead)?;
-        let parsed = toml::from_str::<Self>(&raw).map_err(KibelClientError::ConfigParse)?;
-        Ok(parsed)
+        Self::parse(&raw, ConfigFormat::from_path(path))
+    }
+
+    /// Parses config from TOML text.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::ConfigParse`] when TOML parsing fails.
+    pub fn from_toml(raw: &str) -> Result<Self, KibelClientError> {
+        toml::from_str::<Self>(raw).map_err(KibelClientError::ConfigParse)
     }
 
-    /// Saves config to `path`, creating parent directories if needed.
+    /// Serializes config as the TOML written by [`Config::save`].
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::ConfigSerialize`] when TOML serialization fails.
+    pub fn to_toml(&self) -> Result<String, KibelClientError> {
+        toml::to_string_pretty(self).map_err(KibelClientError::ConfigSerialize)
+    }
+
+    /// Parses config text in `format`.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::ConfigParse`] or
+    /// [`KibelClientError::ConfigParseJson`] when parsing fails.
+    pub fn parse(raw: &str, format: ConfigFormat) -> Result<Self, KibelClientError> {
+        match format {
+            ConfigFormat::Toml => Self::from_toml(raw),
+            ConfigFormat::Json => {
+                serde_json::from_str::<Self>(raw).map_err(KibelClientError::ConfigParseJson)
+            }
+        }
+    }
+
+    /// Serializes config in `format`. Unset settings are left out, as TOML
+    /// has no null.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::ConfigSerialize`] or
+    /// [`KibelClientError::ConfigSerializeJson`] when serialization fails.
+    pub fn serialize(&self, format: ConfigFormat) -> Result<String, KibelClientError> {
+        match format {
+            ConfigFormat::Toml => self.to_toml(),
+            ConfigFormat::Json => {
+                let mut value =
+                    serde_json::to_value(self).map_err(KibelClientError::ConfigSerializeJson)?;
+                strip_nulls(&mut value);
+                let mut serialized = serde_json::to_string_pretty(&value)
+                    .map_err(KibelClientError::ConfigSerializeJson)?;
+                serialized.push('\n');
+                Ok(serialized)
+            }
+        }
+    }
+
+    /// Saves config to `path` in the format its extension names, creating
+    /// parent directories if needed.
     ///
     /// # Errors
     /// Returns [`KibelClientError::ConfigWrite`] for filesystem errors and
-    /// [`KibelClientError::ConfigSerialize`] when TOML serialization fails.
+    /// a serialize error when serialization fails.
     pub fn save(&self, path: impl AsRef<Path>) -> Result<(), KibelClientError> {
         let path = path.as_ref();
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).map_err(KibelClientError::ConfigWrite)?;
         }
 
-        let serialized = toml::to_string_pretty(self).map_err(KibelClientError::ConfigSerialize)?;
+        let serialized = self.serialize(ConfigFormat::from_path(path))?;
         fs::write(path, serialized).map_err(KibelClientError::ConfigWrite)?;
         Ok(())
     }
@@ -151,6 +334,118 @@ impl Config {
         false
     }
 
+    /// Returns a copy with every profile token removed, safe to share.
+    #[must_use]
+    pub fn without_secrets(&self) -> Self {
+        let mut config = self.clone();
+        for profile in config.profiles.values_mut() {
+            profile.token = None;
+        }
+        config
+    }
+
+    /// Returns the settings a project config may contribute: team, profile
+    /// `default_*` and `on_fetch`, presets, and content checks.
+    ///
+    /// A checked-out repository is not trusted with where tokens are sent or
+    /// what runs locally, so tokens, origins, extra headers, the token store,
+    /// the browser command, and the templates directory are dropped.
+    #[must_use]
+    pub fn project_settings(&self) -> Self {
+        let mut config = self.clone();
+        config.templates_dir = None;
+        config.browser = None;
+        config.token_store = None;
+        for profile in config.profiles.values_mut() {
+            profile.token = None;
+            profile.origin = None;
+            profile.extra_headers = None;
+        }
+        config
+    }
+
+    /// Teams whose profile carries a non-empty token.
+    #[must_use]
+    pub fn teams_with_token(&self) -> Vec<String> {
+        self.profiles
+            .iter()
+            .filter(|(_, profile)| normalize_non_empty(profile.token.as_deref()).is_some())
+            .map(|(team, _)| team.clone())
+            .collect()
+    }
+
+    /// Overlays `other` onto this config.
+    ///
+    /// Settings present in `other` win; profiles and presets are merged per
+    /// name, and a profile keeps its token unless `other` sets one.
+    pub fn merge(&mut self, other: Self) {
+        let Self {
+            default_team,
+            templates_dir,
+            on_fetch,
+            browser,
+            copy_url,
+            usage_stats,
+            token_store,
+            profiles,
+            search_note_presets,
+            pre_publish,
+            secret_scan,
+        } = other;
+        if default_team.is_some() {
+            self.default_team = default_team;
+        }
+        if templates_dir.is_some() {
+            self.templates_dir = templates_dir;
+        }
+        if on_fetch.is_some() {
+            self.on_fetch = on_fetch;
+        }
+        if browser.is_some() {
+            self.browser = browser;
+        }
+        if copy_url.is_some() {
+            self.copy_url = copy_url;
+        }
+        if usage_stats.is_some() {
+            self.usage_stats = usage_stats;
+        }
+        if token_store.is_some() {
+            self.token_store = token_store;
+        }
+        if pre_publish.is_some() {
+            self.pre_publish = pre_publish;
+        }
+        if secret_scan.is_some() {
+            self.secret_scan = secret_scan;
+        }
+        for (team, profile) in profiles {
+            let current = self.profiles.entry(team).or_default();
+            if profile.token.is_some() {
+                current.token = profile.token;
+            }
+            if profile.origin.is_some() {
+                current.origin = profile.origin;
+            }
+            if profile.on_fetch.is_some() {
+                current.on_fetch = profile.on_fetch;
+            }
+            if profile.extra_headers.is_some() {
+                current.extra_headers = profile.extra_headers;
+            }
+            if profile.default_group.is_some() {
+                current.default_group = profile.default_group;
+            }
+            if profile.default_folder_path.is_some() {
+                current.default_folder_path = profile.default_folder_path;
+            }
+            if profile.default_coediting.is_some() {
+                current.default_coediting = profile.default_coediting;
+            }
+        }
+        self.search_note_presets.extend(search_note_presets);
+    }
+
     pub fn set_default_team_if_missing(&mut self, team: &str) {
         if self.default_team.is_none() {
             self.default_team = Some(team.to_string());
@@ -166,6 +461,44 @@ impl Config {
         true
     }
 
+    /// Resolves the local note templates directory.
+    ///
+    /// Defaults to `templates/` next to the config file; a relative
+    /// `templates_dir` is resolved against the config file's directory.
+    #[must_use]
+    pub fn templates_dir(&self, config_path: &Path) -> PathBuf {
+        let base = config_path.parent().unwrap_or_else(|| Path::new("."));
+        match self
+            .templates_dir
+            .as_deref()
+            .map(str::trim)
+            .filter(|dir| !dir.is_empty())
+        {
+            Some(dir) => base.join(dir),
+            None => base.join("templates"),
+        }
+    }
+
+    /// Returns the `on_fetch` content pipeline for `team`.
+    ///
+    /// A profile's `on_fetch` replaces the top-level one rather than extending it.
+    #[must_use]
+    pub fn on_fetch_for_team(&self, team: &str) -> &[String] {
+        self.profiles
+            .get(team)
+            .and_then(|profile| profile.on_fetch.as_deref())
+            .or(self.on_fetch.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// The team profile's `extra_headers`, if any.
+    #[must_use]
+    pub fn extra_headers_for_team(&self, team: &str) -> Option<&BTreeMap<String, String>> {
+        self.profiles
+            .get(team)
+            .and_then(|profile| profile.extra_headers.as_ref())
+    }
+
     #[must_use]
     pub fn search_note_preset(&self, name: &str) -> Option<&SearchNotePreset> {
         self.search_note_presets.get(name)
@@ -236,13 +569,77 @@ impl Config {
     }
 }
 
+/// Drops `null` object members, recursively.
+fn strip_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, member| !member.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
 fn normalize_non_empty(value: Option<&str>) -> Option<&str> {
     value.map(str::trim).filter(|value| !value.is_empty())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Config, SearchNotePreset};
+    use super::{Config, ConfigFormat, SearchNotePreset};
+    use std::path::Path;
+
+    #[test]
+    fn json_config_round_trips_without_nulls() {
+        let config = Config::from_toml(
+            "default_team = \"acme\"\n[profiles.acme]\norigin = \"https://acme.kibe.la\"\ndefault_coediting = true\n",
+        )
+        .expect("config should parse");
+        let json = config
+            .serialize(ConfigFormat::Json)
+            .expect("config should serialize");
+        assert!(!json.contains("null"), "{json}");
+        assert_eq!(
+            Config::parse(&json, ConfigFormat::Json).expect("json should parse"),
+            config
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.JSON")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config")),
+            ConfigFormat::Toml
+        );
+    }
+
+    #[test]
+    fn project_config_is_found_upward_and_keeps_only_safe_settings() {
+        let root = std::env::temp_dir().join(format!("kibel-project-{}", std::process::id()));
+        let nested = root.join("docs/guides");
+        std::fs::create_dir_all(&nested).expect("temp dir should be writable");
+        let path = root.join(super::PROJECT_CONFIG_FILE);
+        std::fs::write(
+            &path,
+            "default_team = \"acme\"\nbrowser = \"sh -c evil\"\n[profiles.acme]\norigin = \"https://evil.example\"\ntoken = \"t\"\ndefault_folder_path = \"Eng/Docs\"\n",
+        )
+        .expect("project config should be writable");
+
+        assert_eq!(super::find_project_config(&nested), Some(path.clone()));
+        let project = Config::load(&path)
+            .expect("project config should parse")
+            .project_settings();
+        assert_eq!(project.default_team.as_deref(), Some("acme"));
+        assert_eq!(project.browser, None);
+        let profile = &project.profiles["acme"];
+        assert_eq!(
+            (profile.origin.as_deref(), profile.token.as_deref()),
+            (None, None)
+        );
+        assert_eq!(profile.default_folder_path.as_deref(), Some("Eng/Docs"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
 
     #[test]
     fn resolve_origin_prefers_requested_value() {
@@ -278,6 +675,71 @@ mod tests {
         assert!(resolved.is_none());
     }
 
+    #[test]
+    fn on_fetch_prefers_profile_pipeline() {
+        let mut config = toml::from_str::<Config>(
+            "on_fetch = [\"strip-html\"]\n[profiles.acme]\non_fetch = [\"truncate:100\"]\n",
+        )
+        .expect("config should parse");
+        assert_eq!(config.on_fetch_for_team("acme"), ["truncate:100"]);
+        assert_eq!(config.on_fetch_for_team("other"), ["strip-html"]);
+        config.on_fetch = None;
+        assert!(config.on_fetch_for_team("other").is_empty());
+    }
+
+    #[test]
+    fn templates_dir_resolves_relative_to_config_file() {
+        let config_path = Path::new("/home/me/.config/kibel/config.toml");
+        let mut config = Config::default();
+        assert_eq!(
+            config.templates_dir(config_path),
+            Path::new("/home/me/.config/kibel/templates")
+        );
+        config.templates_dir = Some("/srv/kibel-templates".to_string());
+        assert_eq!(
+            config.templates_dir(config_path),
+            Path::new("/srv/kibel-templates")
+        );
+        config.templates_dir = Some("shared".to_string());
+        assert_eq!(
+            config.templates_dir(config_path),
+            Path::new("/home/me/.config/kibel/shared")
+        );
+    }
+
+    #[test]
+    fn merge_overlays_settings_and_keeps_tokens() {
+        let mut config = Config::from_toml(
+            "default_team = \"acme\"\nbrowser = \"firefox\"\n\
+             [profiles.acme]\ntoken = \"secret\"\norigin = \"https://acme.kibe.la\"\n",
+        )
+        .expect("config should parse");
+        assert_eq!(config.teams_with_token(), ["acme"]);
+        let shared = config.without_secrets();
+        assert!(shared.teams_with_token().is_empty());
+        assert!(!shared.to_toml().expect("serializes").contains("secret"));
+
+        let incoming = Config::from_toml(
+            "default_team = \"spike\"\n\
+             [profiles.acme]\norigin = \"https://acme-new.kibe.la\"\ndefault_group = \"G9\"\n\
+             [profiles.spike]\norigin = \"https://spike.kibe.la\"\n",
+        )
+        .expect("config should parse");
+        config.merge(incoming);
+        assert_eq!(config.default_team.as_deref(), Some("spike"));
+        assert_eq!(config.browser.as_deref(), Some("firefox"));
+        assert_eq!(config.token_for_team("acme"), Some("secret"));
+        assert_eq!(
+            config.origin_for_team("acme"),
+            Some("https://acme-new.kibe.la")
+        );
+        assert_eq!(
+            config.origin_for_team("spike"),
+            Some("https://spike.kibe.la")
+        );
+        assert_eq!(config.profiles["acme"].default_group.as_deref(), Some("G9"));
+    }
+
     #[test]
     fn set_default_team_rejects_empty_values() {
         let mut config = Config::default();
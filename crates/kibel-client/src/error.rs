@@ -14,12 +14,153 @@ pub enum KibelClientError {
     ConfigParse(#[source] toml::de::Error),
     #[error("failed to serialize config file: {0}")]
     ConfigSerialize(#[source] toml::ser::Error),
+    #[error("failed to parse config file: {0}")]
+    ConfigParseJson(#[source] serde_json::Error),
+    #[error("failed to serialize config file: {0}")]
+    ConfigSerializeJson(#[source] serde_json::Error),
     #[error("keychain error: {0}")]
     Keychain(String),
-    #[error("api error [{code}]: {message}")]
-    Api { code: String, message: String },
-    #[error("transport error: {0}")]
-    Transport(String),
+    #[error("token store error: {0}")]
+    TokenStore(String),
+    #[error("local store error: {0}")]
+    LocalStore(String),
+    #[error("read-only violation: {0}")]
+    ReadOnlyViolation(String),
+    #[error("policy violation: {0}")]
+    PolicyViolation(String),
+    /// A GraphQL error (the first entry of `errors`). `status_code` is the
+    /// HTTP status when it was not 2xx, `path` the error's `path` (field
+    /// names and list indexes), and `request_id` the `X-Request-Id` the
+    /// failed request was sent with.
+    #[error("api error [{code}]: {message}{}", request_id_suffix(request_id.as_deref()))]
+    Api {
+        code: String,
+        message: String,
+        status_code: Option<u16>,
+        path: Vec<String>,
+        request_id: Option<String>,
+    },
+    /// The request did not produce a GraphQL response: connection failure,
+    /// timeout, an HTTP error status without GraphQL errors, or a malformed
+    /// body.
+    #[error("transport error: {message}{}", request_id_suffix(request_id.as_deref()))]
+    Transport {
+        message: String,
+        status_code: Option<u16>,
+        request_id: Option<String>,
+    },
+    /// The response body passed the caller's size limit; `received` bytes
+    /// were read before the connection was dropped.
+    #[error(
+        "transport error: response body exceeds limit: {limit} bytes (aborted after {received} bytes){}",
+        request_id_suffix(request_id.as_deref())
+    )]
+    ResponseTooLarge {
+        limit: usize,
+        received: usize,
+        content_length: Option<u64>,
+        request_id: Option<String>,
+    },
+}
+
+/// GraphQL error codes that clear up on their own; waiting and retrying the
+/// same request succeeds.
+const RETRYABLE_API_CODES: [&str; 2] = ["TOKEN_BUDGET_EXHAUSTED", "TEAM_BUDGET_EXHAUSTED"];
+
+impl KibelClientError {
+    /// An [`KibelClientError::Api`] error with only a code and message.
+    #[must_use]
+    pub fn api(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Api {
+            code: code.into(),
+            message: message.into(),
+            status_code: None,
+            path: Vec::new(),
+            request_id: None,
+        }
+    }
+
+    /// A [`KibelClientError::Transport`] error with only a message.
+    #[must_use]
+    pub fn transport(message: impl Into<String>) -> Self {
+        Self::Transport {
+            message: message.into(),
+            status_code: None,
+            request_id: None,
+        }
+    }
+
+    /// The HTTP status of the failed response, when it was not 2xx.
+    #[must_use]
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::Api { status_code, .. } | Self::Transport { status_code, .. } => *status_code,
+            _ => None,
+        }
+    }
+
+    /// The GraphQL `extensions.code` of an API error.
+    #[must_use]
+    pub fn graphql_code(&self) -> Option<&str> {
+        match self {
+            Self::Api { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    /// The GraphQL `path` of an API error; empty for other errors.
+    #[must_use]
+    pub fn path(&self) -> &[String] {
+        match self {
+            Self::Api { path, .. } => path,
+            _ => &[],
+        }
+    }
+
+    /// The `X-Request-Id` of the request that failed, when it was sent.
+    #[must_use]
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Self::Api { request_id, .. }
+            | Self::Transport { request_id, .. }
+            | Self::ResponseTooLarge { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether sending the same request again may succeed: connection
+    /// failures, timeouts, 408/429/5xx responses, and exhausted API budgets.
+    #[must_use]
+    pub fn retryable(&self) -> bool {
+        let retryable_status =
+            |status: Option<u16>| status.is_some_and(|status| matches!(status, 408 | 429 | 500..));
+        match self {
+            Self::Api {
+                code, status_code, ..
+            } => RETRYABLE_API_CODES.contains(&code.as_str()) || retryable_status(*status_code),
+            Self::Transport { status_code, .. } => {
+                status_code.is_none() || retryable_status(*status_code)
+            }
+            _ => false,
+        }
+    }
+
+    /// Tags an error from a sent request with its `X-Request-Id`, keeping an
+    /// id it already has.
+    #[must_use]
+    pub fn with_request_id(mut self, id: &str) -> Self {
+        if let Self::Api { request_id, .. }
+        | Self::Transport { request_id, .. }
+        | Self::ResponseTooLarge { request_id, .. } = &mut self
+        {
+            request_id.get_or_insert_with(|| id.to_string());
+        }
+        self
+    }
+}
+
+fn request_id_suffix(request_id: Option<&str>) -> String {
+    request_id.map_or_else(String::new, |id| format!(" (request id {id})"))
 }
 
 impl From<keyring::Error> for KibelClientError {
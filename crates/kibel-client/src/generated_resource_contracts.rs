@@ -68,6 +68,7 @@ pub const RESOURCE_CONTRACTS: &[ResourceContract] = &[
     first: $first
     after: $after
   ) {
+    totalCount
     pageInfo {
       hasNextPage
       hasPreviousPage
@@ -132,8 +133,10 @@ pub const RESOURCE_CONTRACTS: &[ResourceContract] = &[
       node {
         id
         name
+        description
         isDefault
         isArchived
+        isPrivate
       }
     }
   }
@@ -161,6 +164,12 @@ pub const RESOURCE_CONTRACTS: &[ResourceContract] = &[
       node {
         id
         name
+        group {
+          id
+          name
+          isArchived
+          isPrivate
+        }
       }
     }
   }
@@ -339,6 +348,7 @@ pub const RESOURCE_CONTRACTS: &[ResourceContract] = &[
         client_method: "get_folder_from_path",
         document: "query GetFolderFromPath($path: String!, $first: Int!) {
   folderFromPath(path: $path) {
+    id
     name
     fullName
     fixedPath
@@ -375,8 +385,13 @@ pub const RESOURCE_CONTRACTS: &[ResourceContract] = &[
         required_variables: &["first"],
         graphql_file: "endpoint:query.feedSections",
         client_method: "get_feed_sections",
-        document: "query GetFeedSections($kind: FeedKind!, $groupId: ID!, $first: Int!) {
-  feedSections(kind: $kind, groupId: $groupId, first: $first) {
+        document:
+            "query GetFeedSections($kind: FeedKind!, $groupId: ID!, $first: Int!, $after: String) {
+  feedSections(kind: $kind, groupId: $groupId, first: $first, after: $after) {
+    pageInfo {
+      hasNextPage
+      endCursor
+    }
     edges {
       node {
         ... on FeedNote {
@@ -422,6 +437,57 @@ pub const RESOURCE_CONTRACTS: &[ResourceContract] = &[
       }
     }
   }
+}",
+    },
+    ResourceContract {
+        name: "getDraftNotes",
+        kind: "query",
+        operation: "GetDraftNotes",
+        all_variables: &[],
+        required_variables: &[],
+        graphql_file: "endpoint:query.currentUser",
+        client_method: "get_draft_notes",
+        document: "query GetDraftNotes($first: Int!, $after: String) {
+  currentUser {
+    draftNotes(first: $first, after: $after) {
+      pageInfo {
+        hasNextPage
+        endCursor
+      }
+      edges {
+        node {
+          id
+          title
+          url
+          updatedAt
+        }
+      }
+    }
+  }
+}",
+    },
+    ResourceContract {
+        name: "getNoteComments",
+        kind: "query",
+        operation: "GetNoteComments",
+        all_variables: &["id"],
+        required_variables: &["id"],
+        graphql_file: "endpoint:query.note",
+        client_method: "get_note_comments",
+        document: "query GetNoteComments($id: ID!, $last: Int!) {
+  note(id: $id) {
+    id
+    comments(last: $last) {
+      nodes {
+        id
+        content
+        publishedAt
+        author {
+          account
+        }
+      }
+    }
+  }
 }",
     },
     ResourceContract {
@@ -539,6 +605,160 @@ pub const RESOURCE_CONTRACTS: &[ResourceContract] = &[
       content
     }
   }
+}",
+    },
+    ResourceContract {
+        name: "updateNote",
+        kind: "mutation",
+        operation: "UpdateNote",
+        all_variables: &["input"],
+        required_variables: &["input"],
+        graphql_file: "endpoint:mutation.updateNote",
+        client_method: "publish_note",
+        document: "mutation UpdateNote($input: UpdateNoteInput!) {
+  updateNote(input: $input) {
+    note {
+      id
+      title
+      url
+      publishedAt
+    }
+  }
+}",
+    },
+    ResourceContract {
+        name: "updateComment",
+        kind: "mutation",
+        operation: "UpdateComment",
+        all_variables: &["input"],
+        required_variables: &["input"],
+        graphql_file: "endpoint:mutation.updateComment",
+        client_method: "update_comment",
+        document: "mutation UpdateComment($input: UpdateCommentInput!) {
+  updateComment(input: $input) {
+    comment {
+      id
+    }
+  }
+}",
+    },
+    ResourceContract {
+        name: "deleteComment",
+        kind: "mutation",
+        operation: "DeleteComment",
+        all_variables: &["input"],
+        required_variables: &["input"],
+        graphql_file: "endpoint:mutation.deleteComment",
+        client_method: "delete_comment",
+        document: "mutation DeleteComment($input: DeleteCommentInput!) {
+  deleteComment(input: $input) {
+    clientMutationId
+  }
+}",
+    },
+    ResourceContract {
+        name: "createInlineComment",
+        kind: "mutation",
+        operation: "CreateInlineComment",
+        all_variables: &["input"],
+        required_variables: &["input"],
+        graphql_file: "endpoint:mutation.createInlineComment",
+        client_method: "create_inline_comment",
+        document: "mutation CreateInlineComment($input: CreateInlineCommentInput!) {
+  createInlineComment(input: $input) {
+    inlineComment {
+      id
+    }
+  }
+}",
+    },
+    ResourceContract {
+        name: "uploadAttachment",
+        kind: "mutation",
+        operation: "UploadAttachment",
+        all_variables: &["input"],
+        required_variables: &["input"],
+        graphql_file: "endpoint:mutation.uploadAttachment",
+        client_method: "upload_attachment",
+        document: "mutation UploadAttachment($input: UploadAttachmentInput!) {
+  uploadAttachment(input: $input) {
+    attachment {
+      id
+      path
+    }
+  }
+}",
+    },
+    ResourceContract {
+        name: "getNoteMetadata",
+        kind: "query",
+        operation: "GetNoteMetadata",
+        all_variables: &["id"],
+        required_variables: &["id"],
+        graphql_file: "endpoint:query.note",
+        client_method: "get_note_metadata",
+        document: "query GetNoteMetadata($id: ID!, $first: Int!) {
+  note(id: $id) {
+    id
+    title
+    content
+    url
+    updatedAt
+    author {
+      account
+      realName
+    }
+    groups {
+      id
+      name
+    }
+    folders(first: $first) {
+      edges {
+        node {
+          id
+          name
+          fullName
+          fixedPath
+          group {
+            id
+            name
+          }
+        }
+      }
+    }
+  }
+}",
+    },
+    ResourceContract {
+        name: "getCommentThread",
+        kind: "query",
+        operation: "GetCommentThread",
+        all_variables: &["id"],
+        required_variables: &["id"],
+        graphql_file: "endpoint:query.comment",
+        client_method: "get_comment_thread",
+        document: "query GetCommentThread($id: ID!, $first: Int!) {
+  comment(id: $id) {
+    id
+    content
+    publishedAt
+    author {
+      account
+    }
+    replies(first: $first) {
+      pageInfo {
+        hasNextPage
+      }
+      nodes {
+        id
+        content
+        publishedAt
+        author {
+          account
+        }
+      }
+    }
+  }
 }",
     },
 ];
@@ -555,6 +775,8 @@ pub enum TrustedOperation {
     GetFolder,
     GetFolderFromPath,
     GetFeedSections,
+    GetDraftNotes,
+    GetNoteComments,
     CreateNote,
     CreateComment,
     CreateCommentReply,
@@ -562,6 +784,13 @@ pub enum TrustedOperation {
     MoveNoteToAnotherFolder,
     AttachNoteToFolder,
     UpdateNoteContent,
+    UpdateNote,
+    UpdateComment,
+    DeleteComment,
+    CreateInlineComment,
+    UploadAttachment,
+    GetNoteMetadata,
+    GetCommentThread,
 }
 
 pub const TRUSTED_OPERATIONS: &[TrustedOperation] = &[
@@ -575,6 +804,8 @@ pub const TRUSTED_OPERATIONS: &[TrustedOperation] = &[
     TrustedOperation::GetFolder,
     TrustedOperation::GetFolderFromPath,
     TrustedOperation::GetFeedSections,
+    TrustedOperation::GetDraftNotes,
+    TrustedOperation::GetNoteComments,
     TrustedOperation::CreateNote,
     TrustedOperation::CreateComment,
     TrustedOperation::CreateCommentReply,
@@ -582,6 +813,13 @@ pub const TRUSTED_OPERATIONS: &[TrustedOperation] = &[
     TrustedOperation::MoveNoteToAnotherFolder,
     TrustedOperation::AttachNoteToFolder,
     TrustedOperation::UpdateNoteContent,
+    TrustedOperation::UpdateNote,
+    TrustedOperation::UpdateComment,
+    TrustedOperation::DeleteComment,
+    TrustedOperation::CreateInlineComment,
+    TrustedOperation::UploadAttachment,
+    TrustedOperation::GetNoteMetadata,
+    TrustedOperation::GetCommentThread,
 ];
 
 pub const fn trusted_operation_contract_index(operation: TrustedOperation) -> usize {
@@ -596,13 +834,22 @@ pub const fn trusted_operation_contract_index(operation: TrustedOperation) -> us
         TrustedOperation::GetFolder => 7,
         TrustedOperation::GetFolderFromPath => 8,
         TrustedOperation::GetFeedSections => 9,
-        TrustedOperation::CreateNote => 10,
-        TrustedOperation::CreateComment => 11,
-        TrustedOperation::CreateCommentReply => 12,
-        TrustedOperation::CreateFolder => 13,
-        TrustedOperation::MoveNoteToAnotherFolder => 14,
-        TrustedOperation::AttachNoteToFolder => 15,
-        TrustedOperation::UpdateNoteContent => 16,
+        TrustedOperation::GetDraftNotes => 10,
+        TrustedOperation::GetNoteComments => 11,
+        TrustedOperation::CreateNote => 12,
+        TrustedOperation::CreateComment => 13,
+        TrustedOperation::CreateCommentReply => 14,
+        TrustedOperation::CreateFolder => 15,
+        TrustedOperation::MoveNoteToAnotherFolder => 16,
+        TrustedOperation::AttachNoteToFolder => 17,
+        TrustedOperation::UpdateNoteContent => 18,
+        TrustedOperation::UpdateNote => 19,
+        TrustedOperation::UpdateComment => 20,
+        TrustedOperation::DeleteComment => 21,
+        TrustedOperation::CreateInlineComment => 22,
+        TrustedOperation::UploadAttachment => 23,
+        TrustedOperation::GetNoteMetadata => 24,
+        TrustedOperation::GetCommentThread => 25,
     }
 }
 
@@ -0,0 +1,4 @@
+// This file is generated by crates/kibel-tools.
+// Do not edit by hand.
+
+// No endpoint schema snapshot was available; run `kibel-tools resource-contract refresh-endpoint` and `resource-contract write` to generate response types.
@@ -0,0 +1,150 @@
+use crate::error::KibelClientError;
+use crate::redact::{is_secret_key, register_secret};
+use std::fmt;
+use std::sync::Arc;
+
+/// The request a [`HeaderHook`] computes headers for.
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderRequest<'a> {
+    /// `POST` or `GET`.
+    pub method: &'a str,
+    pub url: &'a str,
+    /// Query parameters appended to `url` (persisted-query GETs).
+    pub query: &'a [(&'a str, &'a str)],
+    pub body: Option<&'a str>,
+}
+
+/// Computes extra headers for one request, e.g. a signature over its body.
+///
+/// Runs right before every HTTP request the client sends; an error aborts
+/// the request.
+pub type HeaderHook =
+    dyn Fn(&HeaderRequest<'_>) -> Result<Vec<(String, String)>, KibelClientError> + Send + Sync;
+
+/// Headers added to every request: fixed pairs, then the hook's output.
+#[derive(Clone, Default)]
+pub(crate) struct ExtraHeaders {
+    fixed: Vec<(String, String)>,
+    hook: Option<Arc<HeaderHook>>,
+}
+
+impl fmt::Debug for ExtraHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtraHeaders")
+            .field(
+                "fixed",
+                &self.fixed.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+impl ExtraHeaders {
+    /// Adds fixed headers after validating them. Values of headers whose name
+    /// looks secret (`Cf-Access-Client-Secret`, `X-Api-Token`, ...) are
+    /// registered for redaction.
+    pub(crate) fn extend(
+        &mut self,
+        headers: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<(), KibelClientError> {
+        for (name, value) in headers {
+            validate_header(&name, &value)?;
+            if is_secret_key(&name) {
+                register_secret(value.as_str());
+            }
+            self.fixed
+                .retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+            self.fixed.push((name, value));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_hook(&mut self, hook: Arc<HeaderHook>) {
+        self.hook = Some(hook);
+    }
+
+    /// The headers for `request`.
+    pub(crate) fn resolve(
+        &self,
+        request: &HeaderRequest<'_>,
+    ) -> Result<Vec<(String, String)>, KibelClientError> {
+        let mut headers = self.fixed.clone();
+        if let Some(hook) = &self.hook {
+            for (name, value) in hook(request)? {
+                validate_header(&name, &value)?;
+                headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+                headers.push((name, value));
+            }
+        }
+        Ok(headers)
+    }
+}
+
+/// Checks that `name` is an HTTP header token and `value` has no line
+/// breaks or other control characters.
+///
+/// # Errors
+/// Returns [`KibelClientError::InputInvalid`] naming the bad header.
+pub fn validate_header(name: &str, value: &str) -> Result<(), KibelClientError> {
+    let valid_name = !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte));
+    if !valid_name {
+        return Err(KibelClientError::InputInvalid(format!(
+            "invalid header name `{name}`"
+        )));
+    }
+    if value.chars().any(|ch| ch.is_control() && ch != '\t') {
+        return Err(KibelClientError::InputInvalid(format!(
+            "header `{name}` value contains control characters"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_header, ExtraHeaders, HeaderRequest};
+    use std::sync::Arc;
+
+    #[test]
+    fn fixed_headers_are_validated_and_the_hook_sees_each_request() {
+        let mut headers = ExtraHeaders::default();
+        headers
+            .extend([
+                ("X-Proxy".to_string(), "one".to_string()),
+                ("x-proxy".to_string(), "two".to_string()),
+            ])
+            .expect("valid headers");
+        assert!(headers
+            .extend([("Bad Header".to_string(), "x".to_string())])
+            .is_err());
+        headers.set_hook(Arc::new(|request: &HeaderRequest<'_>| {
+            Ok(vec![(
+                "X-Signature".to_string(),
+                format!(
+                    "{}:{}",
+                    request.method,
+                    request.body.unwrap_or_default().len()
+                ),
+            )])
+        }));
+
+        let request = HeaderRequest {
+            method: "POST",
+            url: "https://acme.kibe.la/api/v1",
+            query: &[],
+            body: Some("{}"),
+        };
+        assert_eq!(
+            headers.resolve(&request).expect("resolves"),
+            vec![
+                ("x-proxy".to_string(), "two".to_string()),
+                ("X-Signature".to_string(), "POST:2".to_string()),
+            ]
+        );
+        assert!(validate_header("Cf-Access-Client-Id", "abc\r\nX-Evil: 1").is_err());
+    }
+}
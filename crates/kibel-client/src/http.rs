@@ -0,0 +1,312 @@
+use crate::error::KibelClientError;
+use crate::transport::Timeouts;
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+/// Idle keep-alive connections kept per host. Batch commands issue requests
+/// from a few worker threads, so one idle connection per host is not enough.
+const MAX_IDLE_CONNECTIONS_PER_HOST: usize = 8;
+
+/// Blocking HTTP agent shared by every request a client instance makes, so
+/// keep-alive connections and their TLS sessions are reused instead of
+/// re-handshaking per call.
+///
+/// The default backend is `ureq` (HTTP/1.1). With the `http2` feature the
+/// agent uses a `reqwest` client, which negotiates HTTP/2 through ALPN and
+/// multiplexes requests over one connection.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpAgent {
+    backend: Backend,
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    /// The agent and its per-read deadline.
+    Ureq(ureq::Agent, Duration),
+    #[cfg(feature = "http2")]
+    Reqwest(reqwest::blocking::Client),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HttpRequest<'a> {
+    pub url: &'a str,
+    pub query: &'a [(&'a str, &'a str)],
+    pub headers: &'a [(&'a str, &'a str)],
+    /// `Some` sends a POST with this body; `None` sends a GET.
+    pub body: Option<&'a str>,
+    pub timeout: Duration,
+}
+
+/// A response of any status; non-2xx bodies are kept because GraphQL errors
+/// ride on them.
+pub(crate) struct HttpResponse {
+    pub status: u16,
+    pub content_length: Option<u64>,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl HttpAgent {
+    /// An agent whose connections honor `timeouts.connect` and
+    /// `timeouts.read`; the total deadline is set per request.
+    pub(crate) fn new(timeouts: Timeouts) -> Self {
+        #[cfg(feature = "http2")]
+        if let Ok(client) = reqwest::blocking::Client::builder()
+            .pool_max_idle_per_host(MAX_IDLE_CONNECTIONS_PER_HOST)
+            .connect_timeout(timeouts.connect)
+            .build()
+        {
+            return Self {
+                backend: Backend::Reqwest(client),
+            };
+        }
+        Self::http1(timeouts)
+    }
+
+    /// An HTTP/1.1 keep-alive agent regardless of enabled features.
+    pub(crate) fn http1(timeouts: Timeouts) -> Self {
+        Self {
+            backend: Backend::Ureq(
+                ureq::AgentBuilder::new()
+                    .max_idle_connections_per_host(MAX_IDLE_CONNECTIONS_PER_HOST)
+                    .timeout_connect(timeouts.connect)
+                    .timeout_read(timeouts.read)
+                    .build(),
+                timeouts.read,
+            ),
+        }
+    }
+
+    pub(crate) fn send(&self, request: HttpRequest<'_>) -> Result<HttpResponse, KibelClientError> {
+        match &self.backend {
+            Backend::Ureq(agent, read) => send_ureq(agent, *read, request),
+            #[cfg(feature = "http2")]
+            Backend::Reqwest(client) => send_reqwest(client, request),
+        }
+    }
+}
+
+/// ureq replaces the per-read deadline with the request deadline when both
+/// are set, so a read deadline shorter than the total one is kept on the
+/// agent and the total is enforced here instead.
+fn send_ureq(
+    agent: &ureq::Agent,
+    read_timeout: Duration,
+    request: HttpRequest<'_>,
+) -> Result<HttpResponse, KibelClientError> {
+    let method = if request.body.is_some() {
+        "POST"
+    } else {
+        "GET"
+    };
+    let deadline = Instant::now() + request.timeout;
+    let mut builder = agent.request(method, request.url);
+    if read_timeout >= request.timeout {
+        builder = builder.timeout(request.timeout);
+    }
+    for (name, value) in request.query {
+        builder = builder.query(name, value);
+    }
+    for (name, value) in request.headers {
+        builder = builder.set(name, value);
+    }
+    let result = match request.body {
+        Some(body) => builder.send_string(body),
+        None => builder.call(),
+    };
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(error) => return Err(KibelClientError::transport(error.to_string())),
+    };
+    if Instant::now() > deadline {
+        return Err(KibelClientError::transport(format!(
+            "{}: timed out after {}ms",
+            request.url,
+            request.timeout.as_millis()
+        )));
+    }
+    Ok(HttpResponse {
+        status: response.status(),
+        content_length: response
+            .header("Content-Length")
+            .and_then(|value| value.trim().parse::<u64>().ok()),
+        body: Box::new(DeadlineReader {
+            inner: response.into_reader(),
+            deadline,
+        }),
+    })
+}
+
+/// Fails reads once `deadline` has passed.
+struct DeadlineReader<R> {
+    inner: R,
+    deadline: Instant,
+}
+
+impl<R: Read> Read for DeadlineReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if Instant::now() > self.deadline {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "response exceeded the total timeout",
+            ));
+        }
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(feature = "http2")]
+fn send_reqwest(
+    client: &reqwest::blocking::Client,
+    request: HttpRequest<'_>,
+) -> Result<HttpResponse, KibelClientError> {
+    let mut builder = match request.body {
+        Some(body) => client.post(request.url).body(body.to_string()),
+        None => client.get(request.url),
+    }
+    .timeout(request.timeout);
+    if !request.query.is_empty() {
+        builder = builder.query(request.query);
+    }
+    for (name, value) in request.headers {
+        builder = builder.header(*name, *value);
+    }
+    let response = builder
+        .send()
+        .map_err(|error| KibelClientError::transport(error.to_string()))?;
+    Ok(HttpResponse {
+        status: response.status().as_u16(),
+        content_length: response.content_length(),
+        body: Box::new(response),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HttpAgent, HttpRequest};
+    use crate::transport::Timeouts;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const REQUESTS: usize = 20;
+
+    /// Serves `{"data":{}}` over keep-alive HTTP/1.1 and counts accepted
+    /// connections.
+    fn spawn_keep_alive_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind loopback");
+        let url = format!("http://{}/api/v1", listener.local_addr().expect("addr"));
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || serve_connection(stream));
+            }
+        });
+        (url, accepted)
+    }
+
+    fn serve_connection(stream: TcpStream) {
+        let mut writer = stream.try_clone().expect("clone stream");
+        let mut reader = BufReader::new(stream);
+        loop {
+            let mut content_length = 0usize;
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+            }
+            let mut body = vec![0u8; content_length];
+            if reader.read_exact(&mut body).is_err() {
+                return;
+            }
+            let payload = "{\"data\":{}}";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{payload}",
+                payload.len()
+            );
+            if writer.write_all(response.as_bytes()).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn post(agent: &HttpAgent, url: &str) {
+        let mut response = agent
+            .send(HttpRequest {
+                url,
+                query: &[],
+                headers: &[("Content-Type", "application/json")],
+                body: Some("{\"query\":\"{ __typename }\"}"),
+                timeout: Duration::from_secs(5),
+            })
+            .expect("request should succeed");
+        assert_eq!(response.status, 200);
+        let mut body = String::new();
+        response.body.read_to_string(&mut body).expect("read body");
+        assert_eq!(body, "{\"data\":{}}");
+    }
+
+    #[test]
+    fn shared_agent_reuses_connections_across_requests() {
+        let (url, accepted) = spawn_keep_alive_server();
+
+        let started = Instant::now();
+        for _ in 0..REQUESTS {
+            post(&HttpAgent::http1(Timeouts::INTERACTIVE), &url);
+        }
+        let per_request_agents = started.elapsed();
+        let fresh_connections = accepted.swap(0, Ordering::SeqCst);
+
+        let agent = HttpAgent::http1(Timeouts::INTERACTIVE);
+        let started = Instant::now();
+        for _ in 0..REQUESTS {
+            post(&agent, &url);
+        }
+        let shared_agent = started.elapsed();
+
+        eprintln!(
+            "{REQUESTS} requests: per-request agents {per_request_agents:?}, shared agent {shared_agent:?}"
+        );
+        assert_eq!(fresh_connections, REQUESTS);
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn read_deadline_fires_before_the_total_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind loopback");
+        let url = format!("http://{}/api/v1", listener.local_addr().expect("addr"));
+        // Accepts the connection and never answers.
+        thread::spawn(move || {
+            let _held: Vec<TcpStream> = listener.incoming().flatten().collect();
+        });
+
+        let agent = HttpAgent::http1(Timeouts {
+            read: Duration::from_millis(200),
+            ..Timeouts::BATCH
+        });
+        let started = Instant::now();
+        let result = agent.send(HttpRequest {
+            url: &url,
+            query: &[],
+            headers: &[],
+            body: None,
+            timeout: Duration::from_secs(30),
+        });
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
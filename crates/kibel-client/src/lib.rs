@@ -1,21 +1,64 @@
+pub mod apq_cache;
 pub mod auth;
 pub mod client;
 pub mod config;
 pub mod error;
+pub mod headers;
+mod http;
+#[cfg(feature = "local-store")]
+pub mod local_store;
+pub mod manifest;
+pub mod metrics;
+pub mod path;
+pub mod policy;
+pub mod redact;
+/// Typed `data` of each trusted operation's response, generated by
+/// `kibel-tools resource-contract write` from the operation documents and the
+/// endpoint schema snapshot.
+#[path = "generated_response_types.rs"]
+pub mod response_types;
+pub mod secret_scan;
 pub mod store;
+pub mod transport;
+pub mod usage;
+pub mod vcr;
 
+pub use apq_cache::{ApqCache, ApqCacheState, ApqCacheStats, ApqEndpointState, ApqLookup};
 pub use auth::{
     require_team, resolve_access_token, token_source_label, token_store_subject, ResolveTokenInput,
     TokenResolution, TokenSource,
 };
 pub use client::{
-    resource_contract_upstream_commit, resource_contract_version, resource_contracts,
-    trusted_operation_contract, trusted_operations, AttachNoteToFolderInput, CreateCommentInput,
-    CreateCommentReplyInput, CreateFolderInput, CreateNoteFolderInput, CreateNoteInput,
-    CreateNoteResult, FeedSectionsInput, FolderLookupInput, GetNotesInput, IdOnlyResult,
-    KibelClient, MoveNoteToAnotherFolderInput, Note, PageInput, PathLookupInput, ResourceContract,
-    SearchFolderInput, SearchNoteInput, TrustedOperation, UpdateNoteInput,
+    create_note_contract, endpoint_from_origin, resource_contract_upstream_commit,
+    resource_contract_version, resource_contracts, trusted_operation_contract, trusted_operations,
+    AttachNoteToFolderInput, AttachmentDigest, AttachmentDigestInput, CreateCommentInput,
+    CreateCommentReplyInput, CreateFolderInput, CreateInlineCommentInput, CreateNoteContract,
+    CreateNoteFolderInput, CreateNoteInput, CreateNoteResult, DeleteCommentInput,
+    DownloadAttachmentInput, DraftNotesInput, EnsureFolderPathInput, EnsuredFolder,
+    FeedSectionsInput, FolderLookupInput, GetNotesInput, HttpTransport, IdOnlyResult, KibelClient,
+    MoveNoteToAnotherFolderInput, Note, NoteCommentsInput, PageInput, PathLookupInput,
+    PublishNoteInput, ResourceContract, SearchFolderInput, SearchNoteInput, SearchNotePage,
+    TrustedOperation, UpdateCommentInput, UpdateNoteInput, UpdateNoteMetadataInput,
+    UploadAttachmentInput, UploadedAttachment,
+};
+pub use config::{
+    default_cache_dir, default_config_path, find_project_config, Config, ConfigFormat,
+    PrePublishChecks, Profile, SearchNotePreset, PROJECT_CONFIG_FILE,
 };
-pub use config::{default_config_path, Config, Profile, SearchNotePreset};
 pub use error::KibelClientError;
-pub use store::{InMemoryTokenStore, KeychainTokenStore, TokenStore};
+pub use headers::{HeaderHook, HeaderRequest};
+#[cfg(feature = "local-store")]
+pub use local_store::{LocalStore, StoreStats};
+pub use manifest::{ContractManifest, ManifestOperation};
+pub use metrics::{Metrics, MetricsState, OperationMetrics};
+pub use path::{folder_web_path, FolderPath, NotePath};
+pub use policy::Policy;
+pub use redact::{register_secret, Redactor};
+pub use secret_scan::{SecretFinding, SecretScanConfig, SecretScanMode, SecretScanner};
+pub use store::{
+    EncryptedFileTokenStore, InMemoryTokenStore, KeychainTokenStore, NullTokenStore, TokenStore,
+    TokenStoreBackend,
+};
+pub use transport::{generate_request_id, GraphqlRequest, GraphqlTransport, Timeouts};
+pub use usage::{CommandUsage, DayUsage, OperationUsage, Usage, UsageState};
+pub use vcr::VcrTransport;
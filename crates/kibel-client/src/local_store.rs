@@ -0,0 +1,356 @@
+use crate::config::default_cache_dir;
+use crate::error::KibelClientError;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const STORE_FILE: &str = "kibel.db";
+
+/// How long a write waits for another process holding the database lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema changes in order. `PRAGMA user_version` records how many have run,
+/// so a new entry is applied once to every existing store; never edit one
+/// that has shipped.
+const MIGRATIONS: &[&str] = &[
+    // 1: user ids resolved by account, and group/folder names for completion.
+    "CREATE TABLE user_ids (
+        team TEXT NOT NULL,
+        account TEXT NOT NULL,
+        id TEXT NOT NULL,
+        updated_at INTEGER NOT NULL DEFAULT (unixepoch()),
+        PRIMARY KEY (team, account)
+    );
+    CREATE TABLE completion_entries (
+        team TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        PRIMARY KEY (team, kind, id)
+    );",
+];
+
+/// Tables reported by [`LocalStore::stats`].
+const TABLES: [&str; 2] = ["user_ids", "completion_entries"];
+
+/// Local state kept between invocations in one SQLite database, so updates
+/// are transactional and safe across concurrent processes.
+///
+/// Opening runs any pending schema migrations. Callers treat the store as a
+/// cache: a store that cannot be opened only costs extra requests.
+#[derive(Debug)]
+pub struct LocalStore {
+    path: PathBuf,
+    connection: Connection,
+}
+
+/// Size and row counts of a store, for `store stats`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoreStats {
+    pub path: PathBuf,
+    pub schema_version: usize,
+    /// Bytes the database file takes, including free pages.
+    pub size_bytes: u64,
+    /// Bytes in free pages that [`LocalStore::vacuum`] would give back.
+    pub free_bytes: u64,
+    /// Rows per table.
+    pub tables: BTreeMap<&'static str, u64>,
+}
+
+impl LocalStore {
+    /// Opens the store at `path`, creating it and its directory when missing,
+    /// and brings its schema up to date.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::LocalStore`] when the database cannot be
+    /// opened or migrated, or was written by a newer schema.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, KibelClientError> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(store_error)?;
+        }
+        let connection = Connection::open(&path).map_err(store_error)?;
+        connection.busy_timeout(BUSY_TIMEOUT).map_err(store_error)?;
+        let mut store = Self { path, connection };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    /// Returns the default store path, `kibel.db` in the OS cache directory.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::ConfigDirectoryUnavailable`] when the OS
+    /// cache directory cannot be resolved.
+    pub fn default_path() -> Result<PathBuf, KibelClientError> {
+        Ok(default_cache_dir()?.join(STORE_FILE))
+    }
+
+    /// Returns the store path under `dir`.
+    #[must_use]
+    pub fn path_in(dir: &Path) -> PathBuf {
+        dir.join(STORE_FILE)
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn migrate(&mut self) -> Result<(), KibelClientError> {
+        let version = self.schema_version()?;
+        if version > MIGRATIONS.len() {
+            return Err(KibelClientError::LocalStore(format!(
+                "{} has schema version {version}; this binary supports up to {}",
+                self.path.display(),
+                MIGRATIONS.len()
+            )));
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = self.connection.transaction().map_err(store_error)?;
+            transaction
+                .execute_batch(migration)
+                .and_then(|()| transaction.pragma_update(None, "user_version", index + 1))
+                .and_then(|()| transaction.commit())
+                .map_err(store_error)?;
+        }
+        Ok(())
+    }
+
+    fn schema_version(&self) -> Result<usize, KibelClientError> {
+        self.connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(store_error)
+    }
+
+    /// The cached user id of `account` in `team`.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::LocalStore`] when the query fails.
+    pub fn user_id(&self, team: &str, account: &str) -> Result<Option<String>, KibelClientError> {
+        self.connection
+            .query_row(
+                "SELECT id FROM user_ids WHERE team = ?1 AND account = ?2",
+                params![team, account],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(store_error)
+    }
+
+    /// Records `(account, id)` pairs for `team` in one transaction.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::LocalStore`] when the write fails; no pair
+    /// is recorded then.
+    pub fn record_user_ids<'a>(
+        &mut self,
+        team: &str,
+        pairs: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<(), KibelClientError> {
+        let transaction = self.connection.transaction().map_err(store_error)?;
+        {
+            let mut insert = transaction
+                .prepare(
+                    "INSERT INTO user_ids (team, account, id) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (team, account)
+                     DO UPDATE SET id = excluded.id, updated_at = unixepoch()",
+                )
+                .map_err(store_error)?;
+            for (account, id) in pairs {
+                insert
+                    .execute(params![team, account, id])
+                    .map_err(store_error)?;
+            }
+        }
+        transaction.commit().map_err(store_error)
+    }
+
+    /// `(id, name)` entries of `kind` for `team`, or for every team when
+    /// `team` is `None`, ordered by id.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::LocalStore`] when the query fails.
+    pub fn completion_entries(
+        &self,
+        team: Option<&str>,
+        kind: &str,
+    ) -> Result<Vec<(String, String)>, KibelClientError> {
+        let mut select = self
+            .connection
+            .prepare(
+                "SELECT id, name FROM completion_entries
+                 WHERE kind = ?1 AND (?2 IS NULL OR team = ?2)
+                 ORDER BY id, team",
+            )
+            .map_err(store_error)?;
+        let rows = select
+            .query_map(params![kind, team], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(store_error)?;
+        rows.collect::<Result<_, _>>().map_err(store_error)
+    }
+
+    /// Whether any completion entry is stored for `team`.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::LocalStore`] when the query fails.
+    pub fn has_completion_team(&self, team: &str) -> Result<bool, KibelClientError> {
+        self.connection
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM completion_entries WHERE team = ?1)",
+                params![team],
+                |row| row.get(0),
+            )
+            .map_err(store_error)
+    }
+
+    /// Replaces the `kind` entries of `team` in one transaction, so renamed
+    /// or deleted entries do not linger.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::LocalStore`] when the write fails; the
+    /// previous entries are kept then.
+    pub fn replace_completion_entries<'a>(
+        &mut self,
+        team: &str,
+        kind: &str,
+        entries: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<(), KibelClientError> {
+        let transaction = self.connection.transaction().map_err(store_error)?;
+        transaction
+            .execute(
+                "DELETE FROM completion_entries WHERE team = ?1 AND kind = ?2",
+                params![team, kind],
+            )
+            .map_err(store_error)?;
+        {
+            let mut insert = transaction
+                .prepare(
+                    "INSERT OR REPLACE INTO completion_entries (team, kind, id, name)
+                     VALUES (?1, ?2, ?3, ?4)",
+                )
+                .map_err(store_error)?;
+            for (id, name) in entries {
+                insert
+                    .execute(params![team, kind, id, name])
+                    .map_err(store_error)?;
+            }
+        }
+        transaction.commit().map_err(store_error)
+    }
+
+    /// Reports the file size, reclaimable bytes, and rows per table.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::LocalStore`] when a query fails.
+    pub fn stats(&self) -> Result<StoreStats, KibelClientError> {
+        let pragma = |name: &str| -> Result<u64, KibelClientError> {
+            self.connection
+                .pragma_query_value(None, name, |row| row.get(0))
+                .map_err(store_error)
+        };
+        let page_size = pragma("page_size")?;
+        let mut tables = BTreeMap::new();
+        for table in TABLES {
+            let rows = self
+                .connection
+                .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                    row.get(0)
+                })
+                .map_err(store_error)?;
+            tables.insert(table, rows);
+        }
+        Ok(StoreStats {
+            path: self.path.clone(),
+            schema_version: self.schema_version()?,
+            size_bytes: pragma("page_count")? * page_size,
+            free_bytes: pragma("freelist_count")? * page_size,
+            tables,
+        })
+    }
+
+    /// Rebuilds the database file without free pages. Returns the size in
+    /// bytes before and after.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::LocalStore`] when the rebuild fails, e.g.
+    /// while another process holds the database.
+    pub fn vacuum(&self) -> Result<(u64, u64), KibelClientError> {
+        let before = self.stats()?.size_bytes;
+        self.connection
+            .execute_batch("VACUUM")
+            .map_err(store_error)?;
+        Ok((before, self.stats()?.size_bytes))
+    }
+}
+
+fn store_error(error: impl std::fmt::Display) -> KibelClientError {
+    KibelClientError::LocalStore(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LocalStore, MIGRATIONS};
+
+    #[test]
+    fn store_migrates_once_and_keeps_rows_across_opens() {
+        let dir = std::env::temp_dir().join(format!("kibel-store-{}", std::process::id()));
+        let path = LocalStore::path_in(&dir);
+        let mut store = LocalStore::open(&path).expect("store should open");
+        store
+            .record_user_ids("acme", [("alice", "U1"), ("bob", "U2")])
+            .expect("record user ids");
+        store
+            .record_user_ids("acme", [("alice", "U3")])
+            .expect("re-record user id");
+        store
+            .replace_completion_entries("acme", "group", [("G1", "Eng"), ("G2", "Ops")])
+            .expect("record groups");
+        store
+            .replace_completion_entries("acme", "group", [("G2", "Ops")])
+            .expect("replace groups");
+        store
+            .replace_completion_entries("other", "group", [("G9", "Sales")])
+            .expect("record other team");
+        drop(store);
+
+        let store = LocalStore::open(&path).expect("store should reopen");
+        assert_eq!(
+            store.user_id("acme", "alice").expect("query"),
+            Some("U3".to_string())
+        );
+        assert_eq!(store.user_id("other", "alice").expect("query"), None);
+        assert_eq!(
+            store
+                .completion_entries(Some("acme"), "group")
+                .expect("query"),
+            [("G2".to_string(), "Ops".to_string())]
+        );
+        assert_eq!(
+            store
+                .completion_entries(None, "group")
+                .expect("query")
+                .len(),
+            2
+        );
+        assert!(store.has_completion_team("other").expect("query"));
+        assert!(!store.has_completion_team("missing").expect("query"));
+
+        let stats = store.stats().expect("stats");
+        assert_eq!(stats.schema_version, MIGRATIONS.len());
+        assert_eq!(stats.tables["user_ids"], 2);
+        assert_eq!(stats.tables["completion_entries"], 2);
+        let (_, after) = store.vacuum().expect("vacuum");
+        assert_eq!(store.stats().expect("stats").size_bytes, after);
+
+        store
+            .connection
+            .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .expect("bump version");
+        drop(store);
+        assert!(LocalStore::open(&path).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
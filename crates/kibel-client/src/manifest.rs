@@ -0,0 +1,154 @@
+use crate::client::{
+    resource_contract_upstream_commit, resource_contract_version, resource_contracts,
+    ResourceContract,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The resource contracts as a machine-readable manifest for tooling outside
+/// the workspace: MCP tool descriptions, docs generators, stub servers.
+///
+/// `kibel-tools resource-contract write` writes it to
+/// `schema/contracts/resource_contracts.manifest.json`, and
+/// `kibel meta contracts` prints the one compiled into the binary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractManifest {
+    pub contract_version: u32,
+    pub upstream_commit: String,
+    pub operations: Vec<ManifestOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestOperation {
+    /// Resource name, e.g. `searchNote`.
+    pub name: String,
+    /// `query` or `mutation`.
+    pub kind: String,
+    /// Operation name in `document`.
+    pub operation: String,
+    /// `KibelClient` method that sends the operation.
+    pub client_method: String,
+    pub required_variables: Vec<String>,
+    pub optional_variables: Vec<String>,
+    /// GraphQL type of each variable `document` declares (`String!`, `[ID!]`).
+    pub variable_types: BTreeMap<String, String>,
+    pub document: String,
+}
+
+impl ContractManifest {
+    /// The manifest of the contracts compiled into this crate.
+    #[must_use]
+    pub fn embedded() -> Self {
+        Self {
+            contract_version: resource_contract_version(),
+            upstream_commit: resource_contract_upstream_commit().to_string(),
+            operations: resource_contracts()
+                .iter()
+                .map(ManifestOperation::from_contract)
+                .collect(),
+        }
+    }
+}
+
+impl ManifestOperation {
+    #[must_use]
+    pub fn from_contract(contract: &ResourceContract) -> Self {
+        Self::from_parts(
+            contract.name,
+            contract.kind,
+            contract.operation,
+            contract.client_method,
+            contract.all_variables,
+            contract.required_variables,
+            contract.document,
+        )
+    }
+
+    /// Builds an entry from contract fields that are not compiled in yet,
+    /// e.g. a freshly written snapshot.
+    #[must_use]
+    pub fn from_parts(
+        name: &str,
+        kind: &str,
+        operation: &str,
+        client_method: &str,
+        all_variables: &[impl AsRef<str>],
+        required_variables: &[impl AsRef<str>],
+        document: &str,
+    ) -> Self {
+        let required_variables = required_variables
+            .iter()
+            .map(|name| name.as_ref().to_string())
+            .collect::<Vec<_>>();
+        let optional_variables = all_variables
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|name| !required_variables.iter().any(|required| required == name))
+            .map(str::to_string)
+            .collect();
+        Self {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            operation: operation.to_string(),
+            client_method: client_method.to_string(),
+            required_variables,
+            optional_variables,
+            variable_types: declared_variable_types(document),
+            document: document.to_string(),
+        }
+    }
+}
+
+/// Reads `$name: Type` pairs from the operation's variable definitions.
+/// Default values are dropped.
+fn declared_variable_types(document: &str) -> BTreeMap<String, String> {
+    let header = document.split('{').next().unwrap_or_default();
+    let (Some(start), Some(end)) = (header.find('('), header.rfind(')')) else {
+        return BTreeMap::new();
+    };
+    header[start + 1..end]
+        .split('$')
+        .skip(1)
+        .filter_map(|definition| {
+            let (name, rest) = definition.split_once(':')?;
+            let graphql_type = rest.split('=').next()?.trim().trim_end_matches(',').trim();
+            Some((name.trim().to_string(), graphql_type.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContractManifest, ManifestOperation};
+
+    #[test]
+    fn entries_split_variables_and_read_declared_types() {
+        let operation = ManifestOperation::from_parts(
+            "searchNote",
+            "query",
+            "SearchNote",
+            "search_note",
+            &["query", "first", "after", "before"],
+            &["query"],
+            "query SearchNote(\n  $query: String!\n  $first: Int! = 16\n  $after: String\n) {\n  search(query: $query) { totalCount }\n}",
+        );
+        assert_eq!(operation.required_variables, ["query"]);
+        assert_eq!(operation.optional_variables, ["first", "after", "before"]);
+        assert_eq!(
+            operation
+                .variable_types
+                .iter()
+                .map(|(name, graphql_type)| format!("{name}: {graphql_type}"))
+                .collect::<Vec<_>>(),
+            ["after: String", "first: Int!", "query: String!"]
+        );
+
+        let embedded = ContractManifest::embedded();
+        let search = embedded
+            .operations
+            .iter()
+            .find(|operation| operation.name == "searchNote")
+            .expect("searchNote should be embedded");
+        assert_eq!(search.variable_types["query"], "String!");
+    }
+}
@@ -0,0 +1,291 @@
+use crate::config::default_cache_dir;
+use crate::error::KibelClientError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const METRICS_FILE: &str = "metrics.json";
+
+/// Upper bounds of the latency histogram buckets, in milliseconds. Requests
+/// slower than the last bound count only toward `+Inf`.
+pub const LATENCY_BUCKETS_MS: [u64; 9] = [25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Per-operation request counters and latency histograms.
+///
+/// In memory by default; [`Metrics::open`] also accumulates into a file so
+/// short-lived invocations and long-running ones (`feed watch`) add up to one
+/// set of counters. Like [`crate::ApqCache`], writes are best-effort and never
+/// fail a request.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    path: Option<PathBuf>,
+    state: Mutex<MetricsState>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricsState {
+    /// Keyed by trusted operation name, or `adhoc` for `graphql run`.
+    #[serde(default)]
+    pub operations: BTreeMap<String, OperationMetrics>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationMetrics {
+    #[serde(default)]
+    pub requests: u64,
+    /// Failed requests by error code (`TRANSPORT`, an API error code, ...).
+    #[serde(default)]
+    pub errors: BTreeMap<String, u64>,
+    /// Requests per [`LATENCY_BUCKETS_MS`] bucket (not cumulative); the extra
+    /// last entry counts requests slower than every bound.
+    #[serde(default)]
+    pub latency_buckets: Vec<u64>,
+    #[serde(default)]
+    pub latency_sum_ms: u64,
+}
+
+impl Metrics {
+    /// In-memory metrics for this process only.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Metrics accumulated in the file at `path`. A missing or unreadable
+    /// file starts empty.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let state = load_state(&path).unwrap_or_default();
+        Self {
+            path: Some(path),
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Returns the default accumulator path, `metrics.json` in the OS cache
+    /// directory.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::ConfigDirectoryUnavailable`] when the OS
+    /// cache directory cannot be resolved.
+    pub fn default_path() -> Result<PathBuf, KibelClientError> {
+        Ok(default_cache_dir()?.join(METRICS_FILE))
+    }
+
+    /// Returns the accumulator path under `dir`.
+    #[must_use]
+    pub fn path_in(dir: &Path) -> PathBuf {
+        dir.join(METRICS_FILE)
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> MetricsState {
+        self.state
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or_default()
+    }
+
+    /// Records one request of `operation` that took `elapsed`, failed with
+    /// `error` when given.
+    pub fn record(&self, operation: &str, elapsed: Duration, error: Option<&KibelClientError>) {
+        self.update(|state| {
+            let entry = state.operations.entry(operation.to_string()).or_default();
+            entry.requests += 1;
+            if let Some(error) = error {
+                *entry.errors.entry(error_code(error)).or_default() += 1;
+            }
+            let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+            entry
+                .latency_buckets
+                .resize(LATENCY_BUCKETS_MS.len() + 1, 0);
+            let bucket = LATENCY_BUCKETS_MS
+                .iter()
+                .position(|bound| elapsed_ms <= *bound)
+                .unwrap_or(LATENCY_BUCKETS_MS.len());
+            entry.latency_buckets[bucket] += 1;
+            entry.latency_sum_ms = entry.latency_sum_ms.saturating_add(elapsed_ms);
+        });
+    }
+
+    /// Clears every counter, including the accumulator file.
+    pub fn reset(&self) {
+        self.update(|state| *state = MetricsState::default());
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut MetricsState)) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(path) = &self.path {
+            // Re-read first so concurrent invocations do not drop each other's counts.
+            if let Some(latest) = load_state(path) {
+                *state = latest;
+            }
+        }
+        apply(&mut state);
+        if let Some(path) = &self.path {
+            let _ = save_state(path, &state);
+        }
+    }
+}
+
+impl MetricsState {
+    /// Renders the counters in the Prometheus text exposition format:
+    /// `kibel_requests_total`, `kibel_request_errors_total`, and the
+    /// `kibel_request_duration_seconds` histogram, labelled by `operation`.
+    #[must_use]
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP kibel_requests_total Kibela GraphQL requests sent.\n");
+        out.push_str("# TYPE kibel_requests_total counter\n");
+        for (operation, metrics) in &self.operations {
+            let _ = writeln!(
+                out,
+                "kibel_requests_total{{operation=\"{}\"}} {}",
+                escape_label(operation),
+                metrics.requests
+            );
+        }
+        out.push_str("# HELP kibel_request_errors_total Kibela GraphQL requests that failed.\n");
+        out.push_str("# TYPE kibel_request_errors_total counter\n");
+        for (operation, metrics) in &self.operations {
+            for (code, count) in &metrics.errors {
+                let _ = writeln!(
+                    out,
+                    "kibel_request_errors_total{{operation=\"{}\",code=\"{}\"}} {count}",
+                    escape_label(operation),
+                    escape_label(code)
+                );
+            }
+        }
+        out.push_str("# HELP kibel_request_duration_seconds Kibela GraphQL request latency.\n");
+        out.push_str("# TYPE kibel_request_duration_seconds histogram\n");
+        for (operation, metrics) in &self.operations {
+            let operation = escape_label(operation);
+            let mut cumulative = 0;
+            for (index, bound) in LATENCY_BUCKETS_MS.iter().enumerate() {
+                cumulative += metrics.latency_buckets.get(index).copied().unwrap_or(0);
+                let _ = writeln!(
+                    out,
+                    "kibel_request_duration_seconds_bucket{{operation=\"{operation}\",le=\"{}\"}} {cumulative}",
+                    seconds(*bound)
+                );
+            }
+            let _ = writeln!(
+                out,
+                "kibel_request_duration_seconds_bucket{{operation=\"{operation}\",le=\"+Inf\"}} {}",
+                metrics.requests
+            );
+            let _ = writeln!(
+                out,
+                "kibel_request_duration_seconds_sum{{operation=\"{operation}\"}} {}",
+                seconds(metrics.latency_sum_ms)
+            );
+            let _ = writeln!(
+                out,
+                "kibel_request_duration_seconds_count{{operation=\"{operation}\"}} {}",
+                metrics.requests
+            );
+        }
+        out
+    }
+}
+
+fn error_code(error: &KibelClientError) -> String {
+    match error {
+        KibelClientError::Api { code, .. } => code.clone(),
+        KibelClientError::Transport { .. } => "TRANSPORT".to_string(),
+        KibelClientError::ResponseTooLarge { .. } => "RESPONSE_TOO_LARGE".to_string(),
+        KibelClientError::InputInvalid(_) => "INPUT_INVALID".to_string(),
+        KibelClientError::ReadOnlyViolation(_) => "READ_ONLY_VIOLATION".to_string(),
+        KibelClientError::PolicyViolation(_) => "POLICY_VIOLATION".to_string(),
+        _ => "CLIENT".to_string(),
+    }
+}
+
+/// Milliseconds as seconds, without trailing zeros (`250` -> `0.25`).
+fn seconds(ms: u64) -> String {
+    let whole = ms / 1000;
+    let fraction = ms % 1000;
+    if fraction == 0 {
+        whole.to_string()
+    } else {
+        format!("{whole}.{fraction:03}")
+            .trim_end_matches('0')
+            .to_string()
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn load_state(path: &Path) -> Option<MetricsState> {
+    let raw = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+fn save_state(path: &Path, state: &MetricsState) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let serialized = serde_json::to_string_pretty(state).map_err(std::io::Error::other)?;
+    let staging = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    fs::write(&staging, serialized)?;
+    fs::rename(&staging, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+    use crate::error::KibelClientError;
+    use std::time::Duration;
+
+    #[test]
+    fn counters_accumulate_in_the_file_and_render_as_prometheus_text() {
+        let dir = std::env::temp_dir().join(format!("kibel-metrics-{}", std::process::id()));
+        let path = Metrics::path_in(&dir);
+        let _ = std::fs::remove_file(&path);
+
+        let metrics = Metrics::open(&path);
+        metrics.record("getNote", Duration::from_millis(40), None);
+        Metrics::open(&path).record(
+            "getNote",
+            Duration::from_millis(3000),
+            Some(&KibelClientError::transport("reset".to_string())),
+        );
+        metrics.record("adhoc", Duration::from_millis(250), None);
+
+        let text = Metrics::open(&path).snapshot().to_prometheus();
+        assert!(text.contains("kibel_requests_total{operation=\"getNote\"} 2\n"));
+        assert!(text
+            .contains("kibel_request_errors_total{operation=\"getNote\",code=\"TRANSPORT\"} 1\n"));
+        assert!(text.contains(
+            "kibel_request_duration_seconds_bucket{operation=\"getNote\",le=\"0.05\"} 1\n"
+        ));
+        assert!(text
+            .contains("kibel_request_duration_seconds_bucket{operation=\"getNote\",le=\"5\"} 2\n"));
+        assert!(text.contains(
+            "kibel_request_duration_seconds_bucket{operation=\"adhoc\",le=\"0.25\"} 1\n"
+        ));
+        assert!(text.contains("kibel_request_duration_seconds_sum{operation=\"getNote\"} 3.04\n"));
+        assert!(text.contains("kibel_request_duration_seconds_count{operation=\"adhoc\"} 1\n"));
+
+        metrics.reset();
+        assert!(Metrics::open(&path).snapshot().operations.is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
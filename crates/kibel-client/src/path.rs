@@ -0,0 +1,414 @@
+use crate::error::KibelClientError;
+use std::fmt;
+use std::str::FromStr;
+
+/// A Kibela note path.
+///
+/// Parsing strips query strings and fragments, percent-decodes segments, and
+/// collapses folder-relative paths (`/groups/1/folders/2/notes/3`) to the
+/// canonical `/notes/3`. [`Display`](fmt::Display) renders the canonical,
+/// percent-encoded form.
+///
+/// # Examples
+/// ```
+/// use kibel_client::NotePath;
+///
+/// let path: NotePath = "/groups/1/folders/2/notes/366?tab=comments".parse().unwrap();
+/// assert_eq!(path.to_string(), "/notes/366");
+/// assert_eq!(NotePath::blog("alice", "12").unwrap().to_string(), "/@alice/12");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NotePath {
+    /// `/notes/<slug>`
+    Note { slug: String },
+    /// `/@<account>/<slug>`, a note on a user's blog.
+    Blog { account: String, slug: String },
+}
+
+/// A Kibela folder path: the group, then the folder hierarchy from the top,
+/// e.g. `/engineering/design/api`.
+///
+/// # Examples
+/// ```
+/// use kibel_client::FolderPath;
+///
+/// let path = FolderPath::new("acme", ["Design Docs", "2026/Q1"]).unwrap();
+/// assert_eq!(path.to_string(), "/acme/Design%20Docs/2026%2FQ1");
+/// assert_eq!(path.to_string().parse::<FolderPath>().unwrap(), path);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FolderPath {
+    group: String,
+    folders: Vec<String>,
+}
+
+impl NotePath {
+    /// Builds `/notes/<slug>`.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when `slug` is not a valid
+    /// path segment.
+    pub fn note(slug: impl Into<String>) -> Result<Self, KibelClientError> {
+        Ok(Self::Note {
+            slug: segment(slug.into(), "note slug")?,
+        })
+    }
+
+    /// Builds `/@<account>/<slug>`.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when `account` or `slug` is
+    /// not a valid path segment.
+    pub fn blog(
+        account: impl Into<String>,
+        slug: impl Into<String>,
+    ) -> Result<Self, KibelClientError> {
+        let account = account.into();
+        Ok(Self::Blog {
+            account: segment(account.trim_start_matches('@').to_string(), "account")?,
+            slug: segment(slug.into(), "note slug")?,
+        })
+    }
+
+    /// Parses a note path; a leading `/` is optional for `@account/...`.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when the path is empty or
+    /// is neither `/notes/<slug>`, `/@<account>/<slug>`, nor a folder-relative
+    /// `.../notes/<slug>` path.
+    pub fn parse(raw: &str) -> Result<Self, KibelClientError> {
+        let segments = split_segments(raw)?;
+        match segments.as_slice() {
+            [account, slug] if account.starts_with('@') => {
+                Self::blog(account.as_str(), slug.as_str())
+            }
+            [.., notes, slug] if notes == "notes" => Self::note(slug.as_str()),
+            _ => Err(KibelClientError::InputInvalid(format!(
+                "note path must be `/notes/<slug>` or `/@<account>/<slug>`: {}",
+                raw.trim()
+            ))),
+        }
+    }
+
+    /// Derives `/notes/<number>` from a Kibela GraphQL id, which is a
+    /// base64-encoded `Blog/<number>` (or `Note/<number>`) string.
+    #[must_use]
+    pub fn from_relay_id(id: &str) -> Option<Self> {
+        let decoded = String::from_utf8(decode_base64(id.trim())?).ok()?;
+        let (kind, number) = decoded.split_once('/')?;
+        if !matches!(kind, "Blog" | "Note")
+            || number.is_empty()
+            || !number.bytes().all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+        Some(Self::Note {
+            slug: number.to_string(),
+        })
+    }
+
+    #[must_use]
+    pub fn slug(&self) -> &str {
+        match self {
+            Self::Note { slug } | Self::Blog { slug, .. } => slug,
+        }
+    }
+
+    /// Joins the path onto `origin`.
+    #[must_use]
+    pub fn url(&self, origin: &str) -> String {
+        format!("{}{self}", origin.trim_end_matches('/'))
+    }
+}
+
+/// Derives a folder's web path, `/folders/<number>`, from its GraphQL id,
+/// which is a base64-encoded `Folder/<number>` string.
+///
+/// # Examples
+/// ```
+/// assert_eq!(
+///     kibel_client::folder_web_path("Rm9sZGVyLzEy").as_deref(),
+///     Some("/folders/12")
+/// );
+/// ```
+#[must_use]
+pub fn folder_web_path(id: &str) -> Option<String> {
+    let decoded = String::from_utf8(decode_base64(id.trim())?).ok()?;
+    let number = decoded.strip_prefix("Folder/")?;
+    if number.is_empty() || !number.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("/folders/{number}"))
+}
+
+impl FolderPath {
+    /// Builds a folder path from a group and folder names, outermost first.
+    /// Names may contain `/`; it is percent-encoded when rendered.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when a name is empty or
+    /// `.`/`..`, or when no folder is given.
+    pub fn new<I, S>(group: impl Into<String>, folders: I) -> Result<Self, KibelClientError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let folders = folders
+            .into_iter()
+            .map(|name| segment(name.into(), "folder name"))
+            .collect::<Result<Vec<_>, _>>()?;
+        if folders.is_empty() {
+            return Err(KibelClientError::InputInvalid(
+                "folder path needs at least one folder below the group".to_string(),
+            ));
+        }
+        Ok(Self {
+            group: segment(group.into(), "group")?,
+            folders,
+        })
+    }
+
+    /// Parses `/<group>/<folder>[/<subfolder>...]`.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when the path is empty or
+    /// names only a group.
+    pub fn parse(raw: &str) -> Result<Self, KibelClientError> {
+        let mut segments = split_segments(raw)?.into_iter();
+        let group = segments.next().unwrap_or_default();
+        Self::new(group, segments)
+    }
+
+    #[must_use]
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Folder names from the top-level folder down.
+    #[must_use]
+    pub fn folders(&self) -> &[String] {
+        &self.folders
+    }
+
+    /// The folder name as Kibela displays it, e.g. `design/api`.
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.folders.join("/")
+    }
+
+    /// Returns the path of a direct subfolder.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when `name` is not a valid
+    /// folder name.
+    pub fn child(&self, name: impl Into<String>) -> Result<Self, KibelClientError> {
+        let mut child = self.clone();
+        child.folders.push(segment(name.into(), "folder name")?);
+        Ok(child)
+    }
+
+    /// Returns the enclosing folder, or `None` for a top-level folder.
+    #[must_use]
+    pub fn parent(&self) -> Option<Self> {
+        let (_, parent) = self.folders.split_last()?;
+        (!parent.is_empty()).then(|| Self {
+            group: self.group.clone(),
+            folders: parent.to_vec(),
+        })
+    }
+
+    /// Joins the path onto `origin`.
+    #[must_use]
+    pub fn url(&self, origin: &str) -> String {
+        format!("{}{self}", origin.trim_end_matches('/'))
+    }
+}
+
+impl fmt::Display for NotePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Note { slug } => write!(f, "/notes/{}", encode_segment(slug)),
+            Self::Blog { account, slug } => {
+                write!(f, "/@{}/{}", encode_segment(account), encode_segment(slug))
+            }
+        }
+    }
+}
+
+impl fmt::Display for FolderPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/{}", encode_segment(&self.group))?;
+        for folder in &self.folders {
+            write!(f, "/{}", encode_segment(folder))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for NotePath {
+    type Err = KibelClientError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        Self::parse(raw)
+    }
+}
+
+impl FromStr for FolderPath {
+    type Err = KibelClientError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        Self::parse(raw)
+    }
+}
+
+/// Splits a path into percent-decoded segments, dropping the query string,
+/// fragment, and empty segments.
+fn split_segments(raw: &str) -> Result<Vec<String>, KibelClientError> {
+    let path = raw.split(['?', '#']).next().unwrap_or_default();
+    let segments = path
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(decode_segment)
+        .collect::<Vec<_>>();
+    if segments.is_empty() {
+        return Err(KibelClientError::InputInvalid(
+            "path is required".to_string(),
+        ));
+    }
+    Ok(segments)
+}
+
+fn segment(value: String, label: &str) -> Result<String, KibelClientError> {
+    let value = value.trim();
+    if value.is_empty() || value == "." || value == ".." || value.chars().any(char::is_control) {
+        return Err(KibelClientError::InputInvalid(format!(
+            "invalid {label}: `{value}`"
+        )));
+    }
+    Ok(value.to_string())
+}
+
+fn encode_segment(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            output.push(char::from(byte));
+        } else {
+            output.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    output
+}
+
+/// Decodes `%XX` escapes; malformed escapes are kept as written.
+fn decode_segment(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                output.push(byte);
+                index += 3;
+            }
+            None => {
+                output.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+fn decode_base64(raw: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(raw.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for c in raw.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        buffer = ((buffer << 6) | u32::from(value)) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push(u8::try_from((buffer >> bits) & 0xff).ok()?);
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FolderPath, NotePath};
+
+    #[test]
+    fn note_path_parses_canonical_blog_and_folder_relative_forms() {
+        assert_eq!(
+            NotePath::parse("/notes/366/").expect("note path"),
+            NotePath::Note {
+                slug: "366".to_string()
+            }
+        );
+        assert_eq!(
+            NotePath::parse("@alice/12").expect("blog path").to_string(),
+            "/@alice/12"
+        );
+        assert_eq!(
+            NotePath::parse("/groups/1/folders/2/notes/3#comment_1")
+                .expect("folder-relative path")
+                .to_string(),
+            "/notes/3"
+        );
+        assert!(NotePath::parse("  ").is_err());
+        assert!(NotePath::parse("/acme/engineering").is_err());
+        assert!(NotePath::note("..").is_err());
+    }
+
+    #[test]
+    fn note_path_round_trips_percent_encoding() {
+        let path = NotePath::note("設計 メモ").expect("slug");
+        let rendered = path.to_string();
+        assert_eq!(rendered, "/notes/%E8%A8%AD%E8%A8%88%20%E3%83%A1%E3%83%A2");
+        assert_eq!(NotePath::parse(&rendered).expect("encoded path"), path);
+        assert_eq!(
+            path.url("https://acme.kibe.la/"),
+            format!("https://acme.kibe.la{rendered}")
+        );
+    }
+
+    #[test]
+    fn note_path_from_relay_id_decodes_note_numbers() {
+        assert_eq!(
+            NotePath::from_relay_id("QmxvZy8zNjY=").map(|path| path.to_string()),
+            Some("/notes/366".to_string())
+        );
+        assert_eq!(NotePath::from_relay_id("VXNlci8x"), None);
+        assert_eq!(NotePath::from_relay_id("N1"), None);
+    }
+
+    #[test]
+    fn folder_path_builds_hierarchy_and_rejects_bare_groups() {
+        let path = FolderPath::parse("/acme/engineering/").expect("folder path");
+        assert_eq!(path.group(), "acme");
+        assert_eq!(path.folders(), ["engineering"]);
+        let child = path.child("API v2").expect("child folder");
+        assert_eq!(child.to_string(), "/acme/engineering/API%20v2");
+        assert_eq!(child.name(), "engineering/API v2");
+        assert_eq!(child.parent(), Some(path.clone()));
+        assert_eq!(path.parent(), None);
+        assert!(FolderPath::parse("/acme").is_err());
+        assert!(FolderPath::new("acme", [""]).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use crate::client::resource_contracts;
+use crate::error::KibelClientError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Operator limits for agent deployments, enforced by the client before a
+/// request is sent.
+///
+/// Loaded from TOML, or JSON when the file ends in `.json`:
+///
+/// ```toml
+/// allow_operations = ["searchNote", "getNote"]
+/// max_first = 50
+/// forbidden_group_ids = ["R3JvdXAvMQ"]
+/// allow_untrusted_graphql = false
+/// ```
+///
+/// # Examples
+/// ```
+/// use kibel_client::Policy;
+/// use serde_json::json;
+///
+/// let policy = Policy::parse("max_first = 20\nforbidden_group_ids = [\"G9\"]\n", false)
+///     .expect("policy parses");
+/// assert!(policy.check_trusted("searchNote", &json!({ "first": 16 })).is_ok());
+/// assert!(policy.check_trusted("searchNote", &json!({ "first": 100 })).is_err());
+/// assert!(policy.check_trusted("searchNote", &json!({ "groupIds": ["G1", "G9"] })).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Trusted operations (resource contract names) that may run; `None`
+    /// allows every operation.
+    #[serde(default)]
+    pub allow_operations: Option<Vec<String>>,
+    /// Largest `first` any request may ask for.
+    #[serde(default)]
+    pub max_first: Option<u64>,
+    /// Group ids no request may reference through `groupId` or `groupIds`.
+    #[serde(default)]
+    pub forbidden_group_ids: Vec<String>,
+    /// Whether ad-hoc documents (`graphql run`) may be sent at all.
+    #[serde(default = "default_true")]
+    pub allow_untrusted_graphql: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            allow_operations: None,
+            max_first: None,
+            forbidden_group_ids: Vec::new(),
+            allow_untrusted_graphql: true,
+        }
+    }
+}
+
+impl Policy {
+    /// Loads a policy file; `.json` files are read as JSON, anything else as
+    /// TOML.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] when the file cannot be read
+    /// or parsed, or names an unknown operation.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KibelClientError> {
+        let path = path.as_ref();
+        let raw = fs::read_to_string(path).map_err(|error| {
+            KibelClientError::InputInvalid(format!(
+                "failed to read policy {}: {error}",
+                path.display()
+            ))
+        })?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        Self::parse(&raw, is_json).map_err(|error| match error {
+            KibelClientError::InputInvalid(message) => {
+                KibelClientError::InputInvalid(format!("policy {}: {message}", path.display()))
+            }
+            other => other,
+        })
+    }
+
+    /// Parses policy text as JSON or TOML.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::InputInvalid`] for malformed text or an
+    /// `allow_operations` entry that is not a resource contract name.
+    pub fn parse(raw: &str, is_json: bool) -> Result<Self, KibelClientError> {
+        let policy = if is_json {
+            serde_json::from_str::<Self>(raw).map_err(|error| error.to_string())
+        } else {
+            toml::from_str::<Self>(raw).map_err(|error| error.to_string())
+        }
+        .map_err(KibelClientError::InputInvalid)?;
+
+        if let Some(allowed) = &policy.allow_operations {
+            let unknown = allowed
+                .iter()
+                .filter(|name| {
+                    !resource_contracts()
+                        .iter()
+                        .any(|contract| contract.name == name.as_str())
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !unknown.is_empty() {
+                return Err(KibelClientError::InputInvalid(format!(
+                    "unknown operation(s) in allow_operations: {}",
+                    unknown.join(", ")
+                )));
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Checks a trusted operation and its variables.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::PolicyViolation`] when the operation is not
+    /// allowlisted or a variable breaks a cap.
+    pub fn check_trusted(
+        &self,
+        operation: &str,
+        variables: &Value,
+    ) -> Result<(), KibelClientError> {
+        if let Some(allowed) = &self.allow_operations {
+            if !allowed.iter().any(|name| name == operation) {
+                return Err(KibelClientError::PolicyViolation(format!(
+                    "operation `{operation}` is not in the policy's allow_operations"
+                )));
+            }
+        }
+        self.check_values(variables)
+    }
+
+    /// Checks an ad-hoc request: whether it may be sent at all, and its
+    /// variables.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::PolicyViolation`] when ad-hoc GraphQL is
+    /// disabled or a variable breaks a cap.
+    pub fn check_untrusted(&self, variables: &Value) -> Result<(), KibelClientError> {
+        if !self.allow_untrusted_graphql {
+            return Err(KibelClientError::PolicyViolation(
+                "the policy does not allow ad-hoc GraphQL".to_string(),
+            ));
+        }
+        self.check_values(variables)
+    }
+
+    /// Checks the root fields of an ad-hoc document against the root fields
+    /// of the allowlisted operations.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::PolicyViolation`] naming the fields outside
+    /// the allowlist.
+    pub fn check_root_fields(&self, fields: &[String]) -> Result<(), KibelClientError> {
+        let Some(allowed) = &self.allow_operations else {
+            return Ok(());
+        };
+        let allowed_fields = resource_contracts()
+            .iter()
+            .filter(|contract| allowed.iter().any(|name| name == contract.name))
+            .filter_map(|contract| contract.graphql_file.rsplit_once('.'))
+            .map(|(_, field)| field)
+            .collect::<Vec<_>>();
+        let blocked = fields
+            .iter()
+            .filter(|field| !allowed_fields.contains(&field.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if blocked.is_empty() {
+            return Ok(());
+        }
+        Err(KibelClientError::PolicyViolation(format!(
+            "root field(s) outside the policy's allow_operations: {}",
+            blocked.join(", ")
+        )))
+    }
+
+    /// Walks `value` for `first`, `groupId`, and `groupIds` at any depth.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::PolicyViolation`] for the first value that
+    /// breaks a cap.
+    pub fn check_values(&self, value: &Value) -> Result<(), KibelClientError> {
+        match value {
+            Value::Object(fields) => {
+                for (key, field) in fields {
+                    self.check_field(key, field)?;
+                    self.check_values(field)?;
+                }
+                Ok(())
+            }
+            Value::Array(items) => items.iter().try_for_each(|item| self.check_values(item)),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_field(&self, key: &str, value: &Value) -> Result<(), KibelClientError> {
+        match key {
+            "first" => {
+                if let (Some(max), Some(first)) = (self.max_first, value.as_u64()) {
+                    if first > max {
+                        return Err(KibelClientError::PolicyViolation(format!(
+                            "`first` {first} exceeds the policy's max_first {max}"
+                        )));
+                    }
+                }
+            }
+            "groupId" | "groupIds" => {
+                let ids = match value {
+                    Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+                    other => other.as_str().into_iter().collect::<Vec<_>>(),
+                };
+                if let Some(id) = ids.into_iter().find(|id| {
+                    self.forbidden_group_ids
+                        .iter()
+                        .any(|forbidden| forbidden == id.trim())
+                }) {
+                    return Err(KibelClientError::PolicyViolation(format!(
+                        "group `{id}` is forbidden by the policy"
+                    )));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Policy;
+    use serde_json::json;
+
+    #[test]
+    fn policy_allowlists_operations_and_root_fields() {
+        let policy = Policy::parse(
+            r#"{"allow_operations": ["getNote"], "allow_untrusted_graphql": false}"#,
+            true,
+        )
+        .expect("policy parses");
+        assert!(policy
+            .check_trusted("getNote", &json!({ "id": "N1" }))
+            .is_ok());
+        assert!(policy.check_trusted("createNote", &json!({})).is_err());
+        assert!(policy.check_untrusted(&json!({})).is_err());
+        assert!(policy.check_root_fields(&["note".to_string()]).is_ok());
+        assert!(policy
+            .check_root_fields(&["note".to_string(), "createNote".to_string()])
+            .is_err());
+
+        assert!(Policy::parse("allow_operations = [\"dropTables\"]\n", false).is_err());
+        assert!(Policy::parse("max_frist = 3\n", false).is_err());
+        assert!(Policy::default().check_untrusted(&json!({})).is_ok());
+    }
+
+    #[test]
+    fn policy_caps_nested_values() {
+        let policy = Policy {
+            max_first: Some(10),
+            forbidden_group_ids: vec!["G9".to_string()],
+            ..Policy::default()
+        };
+        assert!(policy
+            .check_values(&json!({ "input": { "groupId": "G9" } }))
+            .is_err());
+        assert!(policy
+            .check_values(&json!([{ "folders": { "first": 11 } }]))
+            .is_err());
+        assert!(policy
+            .check_values(&json!({ "first": 10, "groupIds": ["G1"] }))
+            .is_ok());
+    }
+}
@@ -1,7 +1,74 @@
 use crate::error::KibelClientError;
-use std::collections::HashMap;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, MutexGuard};
 
+/// Where `auth login` keeps access tokens, chosen by the config `token_store`
+/// key or `KIBEL_TOKEN_STORE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenStoreBackend {
+    /// The OS credential store (see [`KeychainTokenStore::platform_service`]).
+    #[default]
+    Keychain,
+    /// [`EncryptedFileTokenStore`] next to the config file.
+    EncryptedFile,
+    /// Only the config profile `token`, in plain text.
+    Plaintext,
+    /// Nothing is persisted; tokens come from stdin or env on every run.
+    None,
+}
+
+impl TokenStoreBackend {
+    pub const ALL: [Self; 4] = [
+        Self::Keychain,
+        Self::EncryptedFile,
+        Self::Plaintext,
+        Self::None,
+    ];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Keychain => "keychain",
+            Self::EncryptedFile => "encrypted-file",
+            Self::Plaintext => "plaintext",
+            Self::None => "none",
+        }
+    }
+
+    /// Parses a backend name as written in config.
+    ///
+    /// # Examples
+    /// ```
+    /// use kibel_client::TokenStoreBackend;
+    ///
+    /// assert_eq!(
+    ///     TokenStoreBackend::parse(" encrypted-file "),
+    ///     Some(TokenStoreBackend::EncryptedFile)
+    /// );
+    /// assert_eq!(TokenStoreBackend::parse("vault"), None);
+    /// ```
+    #[must_use]
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.as_str() == raw)
+    }
+
+    /// Whether `auth login` also writes the token to the config profile.
+    #[must_use]
+    pub fn persists_to_config(self) -> bool {
+        matches!(self, Self::Keychain | Self::Plaintext)
+    }
+}
+
 pub trait TokenStore {
     /// Returns token for `team`.
     ///
@@ -40,11 +107,74 @@ impl KeychainTokenStore {
         }
     }
 
+    /// The OS credential service used on this platform, or `None` where
+    /// there is none (the keyring crate would otherwise fall back to an
+    /// in-process mock that forgets tokens on exit).
+    #[must_use]
+    pub fn platform_service() -> Option<&'static str> {
+        if cfg!(target_os = "macos") {
+            Some("macos-keychain")
+        } else if cfg!(target_os = "windows") {
+            Some("windows-credential-manager")
+        } else if cfg!(any(
+            target_os = "linux",
+            target_os = "freebsd",
+            target_os = "openbsd"
+        )) {
+            Some("secret-service")
+        } else {
+            None
+        }
+    }
+
     fn entry_for_team(&self, team: &str) -> Result<keyring::Entry, KibelClientError> {
-        keyring::Entry::new(&self.service, team).map_err(KibelClientError::from)
+        platform_credential(&self.service, team).map(keyring::Entry::new_with_credential)
     }
 }
 
+#[cfg(target_os = "macos")]
+fn platform_credential(
+    service: &str,
+    user: &str,
+) -> Result<Box<keyring::credential::Credential>, KibelClientError> {
+    let credential = keyring::macos::MacCredential::new_with_target(None, service, user)?;
+    Ok(Box::new(credential))
+}
+
+#[cfg(target_os = "windows")]
+fn platform_credential(
+    service: &str,
+    user: &str,
+) -> Result<Box<keyring::credential::Credential>, KibelClientError> {
+    let credential = keyring::windows::WinCredential::new_with_target(None, service, user)?;
+    Ok(Box::new(credential))
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+fn platform_credential(
+    service: &str,
+    user: &str,
+) -> Result<Box<keyring::credential::Credential>, KibelClientError> {
+    let credential = keyring::secret_service::SsCredential::new_with_target(None, service, user)?;
+    Ok(Box::new(credential))
+}
+
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd"
+)))]
+fn platform_credential(
+    _service: &str,
+    _user: &str,
+) -> Result<Box<keyring::credential::Credential>, KibelClientError> {
+    Err(KibelClientError::Keychain(
+        "no OS credential store on this platform".to_string(),
+    ))
+}
+
 impl TokenStore for KeychainTokenStore {
     fn get_token(&self, team: &str) -> Result<Option<String>, KibelClientError> {
         let entry = self.entry_for_team(team)?;
@@ -87,7 +217,7 @@ impl InMemoryTokenStore {
 
     fn lock_tokens(&self) -> Result<MutexGuard<'_, HashMap<String, String>>, KibelClientError> {
         self.tokens.lock().map_err(|_| {
-            KibelClientError::Transport("in-memory token store lock poisoned".to_string())
+            KibelClientError::transport("in-memory token store lock poisoned".to_string())
         })
     }
 }
@@ -110,3 +240,281 @@ impl TokenStore for InMemoryTokenStore {
         Ok(())
     }
 }
+
+/// A store that keeps nothing, for the `plaintext` and `none` backends.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullTokenStore;
+
+impl TokenStore for NullTokenStore {
+    fn get_token(&self, _team: &str) -> Result<Option<String>, KibelClientError> {
+        Ok(None)
+    }
+
+    fn set_token(&self, _team: &str, _token: &str) -> Result<(), KibelClientError> {
+        Ok(())
+    }
+
+    fn delete_token(&self, _team: &str) -> Result<(), KibelClientError> {
+        Ok(())
+    }
+}
+
+const ENCRYPTED_FILE_VERSION: u32 = 1;
+const DEFAULT_KDF_ITERATIONS: u32 = 210_000;
+
+/// Tokens kept in one JSON file, each sealed with ChaCha20-Poly1305 under a
+/// key derived from a passphrase with PBKDF2-HMAC-SHA256.
+///
+/// The subject is bound as associated data, so a sealed token cannot be
+/// moved to another subject.
+#[derive(Debug, Clone)]
+pub struct EncryptedFileTokenStore {
+    path: PathBuf,
+    passphrase: Option<String>,
+    iterations: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EncryptedTokenFile {
+    version: u32,
+    iterations: u32,
+    salt: String,
+    #[serde(default)]
+    tokens: BTreeMap<String, SealedToken>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SealedToken {
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedFileTokenStore {
+    /// A store at `path`; `passphrase` is only needed once a token is read or
+    /// written.
+    pub fn new(path: impl Into<PathBuf>, passphrase: Option<String>) -> Self {
+        Self {
+            path: path.into(),
+            passphrase: passphrase.filter(|passphrase| !passphrase.is_empty()),
+            iterations: DEFAULT_KDF_ITERATIONS,
+        }
+    }
+
+    /// `tokens.enc` next to the config file at `config_path`.
+    #[must_use]
+    pub fn default_path(config_path: &Path) -> PathBuf {
+        config_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("tokens.enc")
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// PBKDF2 rounds used when a new file is created.
+    #[must_use]
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations.max(1);
+        self
+    }
+
+    fn load(&self) -> Result<Option<EncryptedTokenFile>, KibelClientError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let raw = fs::read_to_string(&self.path).map_err(|error| {
+            KibelClientError::TokenStore(format!("failed to read {}: {error}", self.path.display()))
+        })?;
+        let file = serde_json::from_str::<EncryptedTokenFile>(&raw).map_err(|error| {
+            KibelClientError::TokenStore(format!("{}: {error}", self.path.display()))
+        })?;
+        if file.version != ENCRYPTED_FILE_VERSION {
+            return Err(KibelClientError::TokenStore(format!(
+                "{}: unsupported version {}",
+                self.path.display(),
+                file.version
+            )));
+        }
+        Ok(Some(file))
+    }
+
+    fn save(&self, file: &EncryptedTokenFile) -> Result<(), KibelClientError> {
+        let write_error = |error: std::io::Error| {
+            KibelClientError::TokenStore(format!(
+                "failed to write {}: {error}",
+                self.path.display()
+            ))
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        let serialized = serde_json::to_string_pretty(file)
+            .map_err(|error| KibelClientError::TokenStore(error.to_string()))?;
+        fs::write(&self.path, serialized).map_err(write_error)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))
+                .map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    fn cipher(&self, file: &EncryptedTokenFile) -> Result<ChaCha20Poly1305, KibelClientError> {
+        let passphrase = self.passphrase.as_deref().ok_or_else(|| {
+            KibelClientError::TokenStore(
+                "the encrypted-file token store needs KIBEL_TOKEN_STORE_PASSPHRASE".to_string(),
+            )
+        })?;
+        let salt = decode_hex(&file.salt)?;
+        let mut key = [0_u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, file.iterations, &mut key);
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+}
+
+impl TokenStore for EncryptedFileTokenStore {
+    fn get_token(&self, team: &str) -> Result<Option<String>, KibelClientError> {
+        let Some(file) = self.load()? else {
+            return Ok(None);
+        };
+        let Some(sealed) = file.tokens.get(team) else {
+            return Ok(None);
+        };
+        let cipher = self.cipher(&file)?;
+        let nonce = decode_hex(&sealed.nonce)?;
+        if nonce.len() != 12 {
+            return Err(KibelClientError::TokenStore(format!(
+                "{}: malformed nonce",
+                self.path.display()
+            )));
+        }
+        let plain = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &decode_hex(&sealed.ciphertext)?,
+                    aad: team.as_bytes(),
+                },
+            )
+            .map_err(|_| {
+                KibelClientError::TokenStore(format!(
+                    "failed to decrypt {}: wrong passphrase or tampered file",
+                    self.path.display()
+                ))
+            })?;
+        String::from_utf8(plain).map(Some).map_err(|_| {
+            KibelClientError::TokenStore(format!("{}: token is not UTF-8", self.path.display()))
+        })
+    }
+
+    fn set_token(&self, team: &str, token: &str) -> Result<(), KibelClientError> {
+        let mut file = match self.load()? {
+            Some(file) => file,
+            None => EncryptedTokenFile {
+                version: ENCRYPTED_FILE_VERSION,
+                iterations: self.iterations,
+                salt: encode_hex(&ChaCha20Poly1305::generate_nonce(&mut OsRng)),
+                tokens: BTreeMap::new(),
+            },
+        };
+        let cipher = self.cipher(&file)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: token.as_bytes(),
+                    aad: team.as_bytes(),
+                },
+            )
+            .map_err(|_| KibelClientError::TokenStore("failed to encrypt token".to_string()))?;
+        file.tokens.insert(
+            team.to_string(),
+            SealedToken {
+                nonce: encode_hex(&nonce),
+                ciphertext: encode_hex(&ciphertext),
+            },
+        );
+        self.save(&file)
+    }
+
+    fn delete_token(&self, team: &str) -> Result<(), KibelClientError> {
+        let Some(mut file) = self.load()? else {
+            return Ok(());
+        };
+        if file.tokens.remove(team).is_some() {
+            self.save(&file)?;
+        }
+        Ok(())
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(raw: &str) -> Result<Vec<u8>, KibelClientError> {
+    let invalid = || KibelClientError::TokenStore(format!("invalid hex `{raw}`"));
+    if raw.len() % 2 != 0 {
+        return Err(invalid());
+    }
+    (0..raw.len())
+        .step_by(2)
+        .map(|index| {
+            raw.get(index..index + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(invalid)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EncryptedFileTokenStore, TokenStore, TokenStoreBackend};
+
+    #[test]
+    fn backend_names_round_trip() {
+        for backend in TokenStoreBackend::ALL {
+            assert_eq!(TokenStoreBackend::parse(backend.as_str()), Some(backend));
+        }
+        assert!(TokenStoreBackend::Plaintext.persists_to_config());
+        assert!(!TokenStoreBackend::EncryptedFile.persists_to_config());
+    }
+
+    #[test]
+    fn encrypted_file_store_seals_tokens_per_subject() {
+        let path = std::env::temp_dir().join(format!(
+            "kibel-token-store-{}/tokens.enc",
+            std::process::id()
+        ));
+        let store =
+            EncryptedFileTokenStore::new(&path, Some("hunter2".to_string())).with_iterations(10);
+        assert_eq!(store.get_token("acme").expect("reads"), None);
+        store.set_token("acme", "secret-token").expect("writes");
+        store.set_token("spike", "other-token").expect("writes");
+
+        let raw = std::fs::read_to_string(&path).expect("file exists");
+        assert!(!raw.contains("secret-token"));
+        assert_eq!(
+            store.get_token("acme").expect("reads").as_deref(),
+            Some("secret-token")
+        );
+
+        let wrong = EncryptedFileTokenStore::new(&path, Some("nope".to_string()));
+        assert!(wrong.get_token("acme").is_err());
+        let locked = EncryptedFileTokenStore::new(&path, None);
+        assert!(locked.get_token("acme").is_err());
+
+        store.delete_token("acme").expect("deletes");
+        assert_eq!(store.get_token("acme").expect("reads"), None);
+        assert_eq!(
+            store.get_token("spike").expect("reads").as_deref(),
+            Some("other-token")
+        );
+        let _ = std::fs::remove_dir_all(path.parent().expect("parent"));
+    }
+}
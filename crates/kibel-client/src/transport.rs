@@ -0,0 +1,139 @@
+use crate::error::KibelClientError;
+use serde_json::Value;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Deadlines for the HTTP requests a client sends.
+///
+/// `connect` bounds the TCP and TLS handshake, `read` bounds the wait for
+/// each chunk of the response, and `total` bounds the whole request. The
+/// `http2` backend has no per-read deadline and applies `connect` and
+/// `total` only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub connect: Duration,
+    pub read: Duration,
+    pub total: Duration,
+}
+
+impl Timeouts {
+    /// Defaults for one-off commands: fail fast when the network is down.
+    pub const INTERACTIVE: Self = Self {
+        connect: Duration::from_secs(3),
+        read: Duration::from_secs(5),
+        total: Duration::from_secs(5),
+    };
+
+    /// Defaults for long unattended runs, which prefer waiting out a slow
+    /// response to failing an item.
+    pub const BATCH: Self = Self {
+        connect: Duration::from_secs(10),
+        read: Duration::from_secs(30),
+        total: Duration::from_secs(60),
+    };
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self::INTERACTIVE
+    }
+}
+
+/// A fresh `req-xxxxxxxx` id for the `X-Request-Id` header. Mixes the
+/// clock, the process id, and a per-process counter so concurrent
+/// invocations do not collide.
+#[must_use]
+pub fn generate_request_id() -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let mixed = nanos
+        ^ std::process::id().rotate_left(16)
+        ^ COUNTER
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_mul(0x9E37_79B9);
+    format!("req-{mixed:08x}")
+}
+
+/// One GraphQL request as handed to a [`GraphqlTransport`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphqlRequest {
+    pub query: String,
+    pub operation_name: Option<String>,
+    pub variables: Value,
+    pub timeout: Duration,
+    /// Response body limit; `None` reads the whole body.
+    pub max_response_bytes: Option<usize>,
+    /// The request is a trusted query, or an ad-hoc query run with
+    /// `--persist`, so the HTTP transport may send it as a persisted-query
+    /// GET. Mutations are always POST.
+    pub persisted_query: bool,
+}
+
+impl GraphqlRequest {
+    /// Renders the request as the JSON body of a GraphQL-over-HTTP POST.
+    #[must_use]
+    pub fn to_payload(&self) -> Value {
+        let mut payload = serde_json::json!({
+            "query": self.query,
+            "variables": self.variables,
+        });
+        if let Some(operation_name) = &self.operation_name {
+            payload["operationName"] = Value::String(operation_name.clone());
+        }
+        payload
+    }
+}
+
+/// Sends GraphQL requests on behalf of a [`KibelClient`](crate::KibelClient).
+///
+/// The default is [`HttpTransport`](crate::HttpTransport). Inject another
+/// implementation with
+/// [`KibelClient::with_transport`](crate::KibelClient::with_transport) to
+/// unit-test code built on the client without a server. Closures taking a
+/// `&GraphqlRequest` implement the trait.
+///
+/// Implementations return the decoded response body; the client maps a
+/// GraphQL `errors` entry to [`KibelClientError::Api`].
+///
+/// # Examples
+/// ```
+/// use kibel_client::{GraphqlRequest, KibelClient, KibelClientError};
+/// use serde_json::{json, Value};
+/// use std::sync::Arc;
+///
+/// let mock = |request: &GraphqlRequest| -> Result<Value, KibelClientError> {
+///     assert!(request.query.contains("note("));
+///     Ok(json!({"data": {"note": {"id": "N1", "title": "t", "content": "c"}}}))
+/// };
+/// let client = KibelClient::new("https://example.kibe.la", "token")
+///     .unwrap()
+///     .with_transport(Arc::new(mock));
+/// assert_eq!(client.get_note("N1").unwrap().title, "t");
+/// ```
+pub trait GraphqlTransport: Send + Sync {
+    /// Sends `request` and returns the response body.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::Transport`] when the request cannot be
+    /// delivered or the response cannot be decoded.
+    fn execute(&self, request: &GraphqlRequest) -> Result<Value, KibelClientError>;
+}
+
+impl<F> GraphqlTransport for F
+where
+    F: Fn(&GraphqlRequest) -> Result<Value, KibelClientError> + Send + Sync,
+{
+    fn execute(&self, request: &GraphqlRequest) -> Result<Value, KibelClientError> {
+        self(request)
+    }
+}
+
+impl fmt::Debug for dyn GraphqlTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("GraphqlTransport")
+    }
+}
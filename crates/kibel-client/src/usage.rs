@@ -0,0 +1,252 @@
+use crate::config::default_cache_dir;
+use crate::error::KibelClientError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USAGE_FILE: &str = "usage.json";
+
+/// Days of history kept; older days are dropped on the next write.
+pub const USAGE_RETENTION_DAYS: u64 = 400;
+
+/// Per-day command invocations and API calls, kept on this machine only.
+///
+/// Unlike [`crate::Metrics`], which is opt-in and keeps latency, usage is on
+/// by default and answers "which workflows call Kibela the most" over weeks.
+/// Writes are best-effort and never fail a command or request.
+#[derive(Debug, Default)]
+pub struct Usage {
+    path: Option<PathBuf>,
+    state: Mutex<UsageState>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageState {
+    /// Keyed by UTC day, counted in days since 1970-01-01.
+    #[serde(default)]
+    pub days: BTreeMap<u64, DayUsage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayUsage {
+    /// Keyed by command path, e.g. `search note`.
+    #[serde(default)]
+    pub commands: BTreeMap<String, CommandUsage>,
+    /// Keyed by trusted operation name, or `adhoc` for `graphql run`.
+    #[serde(default)]
+    pub operations: BTreeMap<String, OperationUsage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandUsage {
+    #[serde(default)]
+    pub invocations: u64,
+    #[serde(default)]
+    pub failures: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationUsage {
+    #[serde(default)]
+    pub calls: u64,
+    #[serde(default)]
+    pub errors: u64,
+    /// Size of the successful responses as JSON.
+    #[serde(default)]
+    pub response_bytes: u64,
+}
+
+impl Usage {
+    /// In-memory usage for this process only.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Usage accumulated in the file at `path`. A missing or unreadable file
+    /// starts empty.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let state = load_state(&path).unwrap_or_default();
+        Self {
+            path: Some(path),
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Returns the default stats path, `usage.json` in the OS cache directory.
+    ///
+    /// # Errors
+    /// Returns [`KibelClientError::ConfigDirectoryUnavailable`] when the OS
+    /// cache directory cannot be resolved.
+    pub fn default_path() -> Result<PathBuf, KibelClientError> {
+        Ok(default_cache_dir()?.join(USAGE_FILE))
+    }
+
+    /// Returns the stats path under `dir`.
+    #[must_use]
+    pub fn path_in(dir: &Path) -> PathBuf {
+        dir.join(USAGE_FILE)
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> UsageState {
+        self.state
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or_default()
+    }
+
+    /// Counts one invocation of `command` today.
+    pub fn record_command(&self, command: &str, failed: bool) {
+        self.update(|day| {
+            let entry = day.commands.entry(command.to_string()).or_default();
+            entry.invocations += 1;
+            entry.failures += u64::from(failed);
+        });
+    }
+
+    /// Counts one call of `operation` today that returned `response_bytes`,
+    /// or failed.
+    pub fn record_operation(&self, operation: &str, response_bytes: Option<u64>) {
+        self.update(|day| {
+            let entry = day.operations.entry(operation.to_string()).or_default();
+            entry.calls += 1;
+            match response_bytes {
+                Some(bytes) => entry.response_bytes = entry.response_bytes.saturating_add(bytes),
+                None => entry.errors += 1,
+            }
+        });
+    }
+
+    /// Clears every day, including the stats file.
+    pub fn reset(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        *state = UsageState::default();
+        if let Some(path) = &self.path {
+            let _ = save_state(path, &state);
+        }
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut DayUsage)) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(path) = &self.path {
+            // Re-read first so concurrent invocations do not drop each other's counts.
+            if let Some(latest) = load_state(path) {
+                *state = latest;
+            }
+        }
+        let today = today();
+        state
+            .days
+            .retain(|day, _| *day + USAGE_RETENTION_DAYS > today);
+        apply(state.days.entry(today).or_default());
+        if let Some(path) = &self.path {
+            let _ = save_state(path, &state);
+        }
+    }
+}
+
+impl UsageState {
+    /// Commands and operations summed over the days from `first_day` on.
+    #[must_use]
+    pub fn totals_since(&self, first_day: u64) -> DayUsage {
+        let mut totals = DayUsage::default();
+        for (_, day) in self.days.range(first_day..) {
+            for (command, usage) in &day.commands {
+                let entry = totals.commands.entry(command.clone()).or_default();
+                entry.invocations += usage.invocations;
+                entry.failures += usage.failures;
+            }
+            for (operation, usage) in &day.operations {
+                let entry = totals.operations.entry(operation.clone()).or_default();
+                entry.calls += usage.calls;
+                entry.errors += usage.errors;
+                entry.response_bytes = entry.response_bytes.saturating_add(usage.response_bytes);
+            }
+        }
+        totals
+    }
+}
+
+/// Today in UTC, as days since 1970-01-01.
+#[must_use]
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400)
+}
+
+fn load_state(path: &Path) -> Option<UsageState> {
+    let raw = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+fn save_state(path: &Path, state: &UsageState) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let serialized = serde_json::to_string_pretty(state).map_err(std::io::Error::other)?;
+    let staging = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    fs::write(&staging, serialized)?;
+    fs::rename(&staging, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{today, DayUsage, Usage, UsageState, USAGE_RETENTION_DAYS};
+
+    #[test]
+    fn counts_accumulate_per_day_and_old_days_are_dropped() {
+        let dir = std::env::temp_dir().join(format!("kibel-usage-{}", std::process::id()));
+        let path = Usage::path_in(&dir);
+        let today = today();
+        let stale = UsageState {
+            days: [today - USAGE_RETENTION_DAYS, today - 3]
+                .into_iter()
+                .map(|day| (day, DayUsage::default()))
+                .collect(),
+        };
+        std::fs::create_dir_all(&dir).expect("temp dir should be writable");
+        std::fs::write(
+            &path,
+            serde_json::to_string(&stale).expect("state serializes"),
+        )
+        .expect("usage file should be writable");
+
+        let usage = Usage::open(&path);
+        usage.record_command("search note", false);
+        Usage::open(&path).record_command("search note", true);
+        usage.record_operation("searchNote", Some(120));
+        usage.record_operation("searchNote", None);
+
+        let state = Usage::open(&path).snapshot();
+        assert_eq!(
+            state.days.keys().copied().collect::<Vec<_>>(),
+            [today - 3, today]
+        );
+        let totals = state.totals_since(today - 7);
+        assert_eq!(totals.commands["search note"].invocations, 2);
+        assert_eq!(totals.commands["search note"].failures, 1);
+        assert_eq!(totals.operations["searchNote"].calls, 2);
+        assert_eq!(totals.operations["searchNote"].errors, 1);
+        assert_eq!(totals.operations["searchNote"].response_bytes, 120);
+        assert!(state.totals_since(today + 1).commands.is_empty());
+
+        usage.reset();
+        assert!(Usage::open(&path).snapshot().days.is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::error::KibelClientError;
+use crate::redact::Redactor;
+use crate::transport::{GraphqlRequest, GraphqlTransport};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Records GraphQL exchanges to a cassette directory, or replays them from it.
+///
+/// Each exchange is stored as `<dir>/<operation>/<key>.json`, where `key`
+/// hashes the query and its variables. Before anything is written or
+/// matched, everything a [`Redactor`] removes (variables whose name looks
+/// secret, registered secret strings, `Bearer` credentials, e-mail
+/// addresses) is replaced with `[REDACTED]`, so cassettes are safe to
+/// commit. Replaying a request that was never recorded
+/// fails instead of reaching the network.
+///
+/// # Examples
+/// ```
+/// use kibel_client::{GraphqlRequest, GraphqlTransport, VcrTransport};
+/// use serde_json::{json, Value};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let dir = std::env::temp_dir().join(format!("kibel-vcr-doc-{}", std::process::id()));
+/// let live = |_: &GraphqlRequest| Ok::<Value, _>(json!({"data": {"ok": true}}));
+/// let request = GraphqlRequest {
+///     query: "query Ping { ok }".to_string(),
+///     operation_name: None,
+///     variables: json!({}),
+///     timeout: Duration::from_secs(1),
+///     max_response_bytes: None,
+///     persisted_query: false,
+/// };
+///
+/// VcrTransport::record(&dir, Arc::new(live)).execute(&request).unwrap();
+/// let replayed = VcrTransport::replay(&dir).execute(&request).unwrap();
+/// assert_eq!(replayed, json!({"data": {"ok": true}}));
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct VcrTransport {
+    dir: PathBuf,
+    inner: Option<Arc<dyn GraphqlTransport>>,
+    redactor: Redactor,
+}
+
+impl VcrTransport {
+    /// Forwards requests to `inner` and stores each exchange under `dir`.
+    pub fn record(dir: impl Into<PathBuf>, inner: Arc<dyn GraphqlTransport>) -> Self {
+        Self {
+            dir: dir.into(),
+            inner: Some(inner),
+            redactor: Redactor::new(),
+        }
+    }
+
+    /// Answers requests from exchanges stored under `dir`.
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            inner: None,
+            redactor: Redactor::new(),
+        }
+    }
+
+    /// Redacts `secret` (for example the access token) wherever it appears in
+    /// recorded requests and responses.
+    #[must_use]
+    pub fn redacting(mut self, secret: impl Into<String>) -> Self {
+        self.redactor = self.redactor.with_secret(secret);
+        self
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    #[must_use]
+    pub fn is_recording(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Returns the cassette file `request` is recorded in or replayed from.
+    #[must_use]
+    pub fn cassette_path(&self, request: &GraphqlRequest) -> PathBuf {
+        let variables = self.redactor.variables(&request.variables);
+        let mut hasher = Sha256::new();
+        hasher.update(request.query.trim().as_bytes());
+        hasher.update(b"\n");
+        hasher.update(variables.to_string().as_bytes());
+        let key = hasher
+            .finalize()
+            .iter()
+            .take(8)
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        self.dir
+            .join(operation_label(request))
+            .join(format!("{key}.json"))
+    }
+
+    fn store(
+        &self,
+        path: &Path,
+        request: &GraphqlRequest,
+        response: &Value,
+    ) -> Result<(), KibelClientError> {
+        let cassette = self.redactor.json(&json!({
+            "operation": operation_label(request),
+            "query": request.query,
+            "variables": self.redactor.variables(&request.variables),
+            "response": response,
+        }));
+        let rendered = serde_json::to_string_pretty(&cassette)
+            .map_err(|error| KibelClientError::transport(format!("json render failed: {error}")))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|error| KibelClientError::transport(error.to_string()))?;
+        }
+        fs::write(path, format!("{rendered}\n"))
+            .map_err(|error| KibelClientError::transport(error.to_string()))
+    }
+
+    fn load(path: &Path) -> Result<Value, KibelClientError> {
+        let raw = fs::read_to_string(path).map_err(|_| {
+            KibelClientError::transport(format!(
+                "no recorded response for this request: {}",
+                path.display()
+            ))
+        })?;
+        let mut cassette = serde_json::from_str::<Value>(&raw).map_err(|error| {
+            KibelClientError::transport(format!("invalid cassette {}: {error}", path.display()))
+        })?;
+        Ok(cassette
+            .get_mut("response")
+            .map(Value::take)
+            .unwrap_or(Value::Null))
+    }
+}
+
+impl GraphqlTransport for VcrTransport {
+    fn execute(&self, request: &GraphqlRequest) -> Result<Value, KibelClientError> {
+        let path = self.cassette_path(request);
+        let Some(inner) = &self.inner else {
+            return Self::load(&path);
+        };
+        let response = inner.execute(request)?;
+        self.store(&path, request, &response)?;
+        Ok(response)
+    }
+}
+
+/// The operation name, or the first operation declared in the query, used as
+/// the cassette subdirectory.
+fn operation_label(request: &GraphqlRequest) -> String {
+    let source = match &request.operation_name {
+        Some(name) => name.as_str(),
+        None => {
+            let query = request.query.trim_start();
+            ["query", "mutation", "subscription"]
+                .iter()
+                .find_map(|keyword| query.strip_prefix(keyword))
+                .unwrap_or_default()
+                .trim_start()
+        }
+    };
+    let name = source
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect::<String>();
+    if name.is_empty() {
+        "anonymous".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VcrTransport;
+    use crate::{GraphqlRequest, GraphqlTransport, KibelClientError};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn request(variables: Value) -> GraphqlRequest {
+        GraphqlRequest {
+            query: "query GetNote($id: ID!) { note(id: $id) { id } }".to_string(),
+            operation_name: None,
+            variables,
+            timeout: Duration::from_secs(1),
+            max_response_bytes: None,
+            persisted_query: true,
+        }
+    }
+
+    #[test]
+    fn records_redacted_cassettes_and_replays_them() {
+        let dir = std::env::temp_dir().join(format!("kibel-vcr-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let live = |request: &GraphqlRequest| -> Result<Value, KibelClientError> {
+            Ok(json!({"data": {"note": {"id": request.variables["id"], "echo": "secret-token"}}}))
+        };
+        let recorder =
+            VcrTransport::record(&dir, Arc::new(live)).redacting("secret-token".to_string());
+        let with_token = request(json!({"id": "N1", "apiToken": "abc"}));
+        recorder.execute(&with_token).expect("record");
+
+        let path = recorder.cassette_path(&with_token);
+        assert!(path.starts_with(dir.join("GetNote")));
+        let stored = std::fs::read_to_string(&path).expect("cassette");
+        assert!(!stored.contains("secret-token"));
+        assert!(!stored.contains("abc"));
+
+        let replayer = VcrTransport::replay(&dir);
+        let replayed = replayer
+            .execute(&request(json!({"id": "N1", "apiToken": "other"})))
+            .expect("replay matches on redacted variables");
+        assert_eq!(replayed["data"]["note"]["id"], "N1");
+        assert_eq!(replayed["data"]["note"]["echo"], "[REDACTED]");
+        assert!(replayer.execute(&request(json!({"id": "N2"}))).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
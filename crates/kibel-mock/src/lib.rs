@@ -0,0 +1,1504 @@
+//! A contract-driven fake Kibela GraphQL endpoint.
+//!
+//! [`MockServer`] answers every root field in kibel's resource contracts with
+//! canned data, validates required variables, supports persisted-query GETs,
+//! and records what it received. Fixtures, latency, GraphQL errors, and
+//! transport [`Fault`]s (429s, 5xx, truncated bodies, slow responses,
+//! persisted queries being unsupported) can be configured per root field, so
+//! integrations built on `kibel` or `kibel-client` can be tested without a
+//! Kibela team.
+//!
+//! ```
+//! use kibel_mock::MockServer;
+//! use serde_json::json;
+//!
+//! let server = MockServer::builder()
+//!     .fixture("note", json!({"data": {"note": {"id": "N9", "title": "t", "content": "c"}}}))
+//!     .start()
+//!     .unwrap();
+//! let client = kibel_client::KibelClient::new(server.origin(), "token").unwrap();
+//! assert_eq!(client.get_note("N9").unwrap().title, "t");
+//! let last = server.captured_requests().pop().unwrap();
+//! assert_eq!(last.root_field.as_deref(), Some("note"));
+//! ```
+
+use kibel_client::{create_note_contract, resource_contracts};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A request the server received, after resolving persisted-query hashes.
+#[derive(Debug, Clone)]
+pub struct CapturedRequest {
+    pub method: String,
+    pub path: String,
+    pub accept: Option<String>,
+    /// Every request header, keyed by lowercase name.
+    pub headers: HashMap<String, String>,
+    pub root_field: Option<String>,
+    pub query: String,
+    pub variables: Value,
+}
+
+/// A transport-level failure the server injects instead of a normal answer.
+///
+/// Parses from the `kibel-mock --fault` syntax: `rate-limit[:SECS]`,
+/// `server-error[:STATUS]`, `truncate`, `slow:MS`, `apq-unsupported`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// HTTP 429 with `Retry-After` and exhausted budget headers, and a
+    /// `TOKEN_BUDGET_EXHAUSTED` GraphQL error in the body.
+    RateLimited { retry_after: Duration },
+    /// The given 5xx status with a body that carries no GraphQL errors.
+    ServerError(u16),
+    /// Declares the full `Content-Length` but closes the connection halfway
+    /// through the body.
+    TruncatedBody,
+    /// Answers normally after the given delay.
+    Slow(Duration),
+    /// Rejects requests carrying a persisted-query hash with
+    /// `PERSISTED_QUERY_NOT_SUPPORTED`; plain POSTs are answered normally.
+    PersistedQueryNotSupported,
+}
+
+impl Fault {
+    fn applies_to(self, persisted: bool) -> bool {
+        persisted || self != Self::PersistedQueryNotSupported
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RateLimited { retry_after } => {
+                write!(f, "rate-limit:{}", retry_after.as_secs())
+            }
+            Self::ServerError(status) => write!(f, "server-error:{status}"),
+            Self::TruncatedBody => f.write_str("truncate"),
+            Self::Slow(delay) => write!(f, "slow:{}", delay.as_millis()),
+            Self::PersistedQueryNotSupported => f.write_str("apq-unsupported"),
+        }
+    }
+}
+
+impl FromStr for Fault {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match raw.trim().split_once(':') {
+            Some((kind, arg)) => (kind.trim(), Some(arg.trim())),
+            None => (raw.trim(), None),
+        };
+        let number = |default: Option<u64>| match arg {
+            Some(arg) => arg
+                .parse::<u64>()
+                .map_err(|_| format!("invalid number in fault `{raw}`")),
+            None => default.ok_or_else(|| format!("fault `{kind}` needs an argument")),
+        };
+        match kind {
+            "rate-limit" => Ok(Self::RateLimited {
+                retry_after: Duration::from_secs(number(Some(1))?),
+            }),
+            "server-error" => match number(Some(503))? {
+                status @ 500..=599 => Ok(Self::ServerError(status as u16)),
+                status => Err(format!("server-error status must be 5xx: {status}")),
+            },
+            "truncate" => Ok(Self::TruncatedBody),
+            "slow" => Ok(Self::Slow(Duration::from_millis(number(None)?))),
+            "apq-unsupported" => Ok(Self::PersistedQueryNotSupported),
+            _ => Err(format!(
+                "unknown fault `{kind}` (expected rate-limit, server-error, truncate, slow, apq-unsupported)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FaultRule {
+    fault: Fault,
+    /// Requests left to fail; `None` fails every matching request.
+    remaining: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct ResourceSpec {
+    required_variables: Vec<String>,
+}
+
+#[derive(Debug)]
+struct ServerState {
+    resource_specs_by_field: HashMap<String, ResourceSpec>,
+    fixtures: HashMap<String, Value>,
+    errors: HashMap<String, (String, String)>,
+    faults: Mutex<HashMap<String, FaultRule>>,
+    latency: Duration,
+    persisted_queries: Arc<Mutex<HashMap<String, String>>>,
+    captured_requests: Arc<Mutex<Vec<CapturedRequest>>>,
+}
+
+/// Configures a [`MockServer`] before it starts listening.
+#[derive(Debug, Clone)]
+pub struct MockServerBuilder {
+    addr: SocketAddr,
+    fixtures: HashMap<String, Value>,
+    errors: HashMap<String, (String, String)>,
+    faults: HashMap<String, FaultRule>,
+    latency: Duration,
+}
+
+impl Default for MockServerBuilder {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            fixtures: HashMap::new(),
+            errors: HashMap::new(),
+            faults: HashMap::new(),
+            latency: Duration::ZERO,
+        }
+    }
+}
+
+impl MockServerBuilder {
+    /// Listens on `addr` instead of an ephemeral loopback port.
+    #[must_use]
+    pub fn bind(mut self, addr: SocketAddr) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// Answers every request for `root_field` with `response` (a full
+    /// GraphQL response body, `{"data": ...}` or `{"errors": [...]}`).
+    #[must_use]
+    pub fn fixture(mut self, root_field: impl Into<String>, response: Value) -> Self {
+        self.fixtures.insert(root_field.into(), response);
+        self
+    }
+
+    /// Fails every request for `root_field` with a GraphQL error carrying
+    /// `extensions.code = code`.
+    #[must_use]
+    pub fn graphql_error(
+        mut self,
+        root_field: impl Into<String>,
+        code: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        self.errors
+            .insert(root_field.into(), (code.into(), message.into()));
+        self
+    }
+
+    /// Injects `fault` into every request for `root_field`. The field `*`
+    /// matches any request, including persisted-query GETs whose hash the
+    /// server has not seen.
+    #[must_use]
+    pub fn fault(mut self, root_field: impl Into<String>, fault: Fault) -> Self {
+        self.faults.insert(
+            root_field.into(),
+            FaultRule {
+                fault,
+                remaining: None,
+            },
+        );
+        self
+    }
+
+    /// Injects `fault` into the first `times` requests for `root_field` only,
+    /// so a client retry can succeed.
+    #[must_use]
+    pub fn fault_times(
+        mut self,
+        root_field: impl Into<String>,
+        fault: Fault,
+        times: usize,
+    ) -> Self {
+        self.faults.insert(
+            root_field.into(),
+            FaultRule {
+                fault,
+                remaining: Some(times),
+            },
+        );
+        self
+    }
+
+    /// Delays every response by `latency`.
+    #[must_use]
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Binds the listener and starts serving on a background thread.
+    ///
+    /// # Errors
+    /// Returns the I/O error when the address cannot be bound.
+    pub fn start(self) -> std::io::Result<MockServer> {
+        let state = ServerState {
+            resource_specs_by_field: resource_specs(),
+            fixtures: self.fixtures,
+            errors: self.errors,
+            faults: Mutex::new(self.faults),
+            latency: self.latency,
+            persisted_queries: Arc::new(Mutex::new(HashMap::new())),
+            captured_requests: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let listener = TcpListener::bind(self.addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let captured_requests = Arc::clone(&state.captured_requests);
+        let root_fields = state.resource_specs_by_field.keys().cloned().collect();
+
+        let thread = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(error) = handle_connection(stream, &state) {
+                            eprintln!("kibel-mock connection error: {error}");
+                        }
+                    }
+                    Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(5));
+                    }
+                    Err(error) => {
+                        eprintln!("kibel-mock accept error: {error}");
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(MockServer {
+            origin: format!("http://{local_addr}"),
+            root_fields,
+            captured_requests,
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// A running fake Kibela endpoint; it stops when dropped.
+pub struct MockServer {
+    origin: String,
+    root_fields: Vec<String>,
+    captured_requests: Arc<Mutex<Vec<CapturedRequest>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    #[must_use]
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    /// Starts a server with the built-in canned responses.
+    ///
+    /// # Errors
+    /// Returns the I/O error when no loopback port can be bound.
+    pub fn start() -> std::io::Result<Self> {
+        Self::builder().start()
+    }
+
+    /// The origin to hand to `KibelClient::new` or `kibel --origin`.
+    #[must_use]
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Root fields the server answers, one per distinct resource contract field.
+    #[must_use]
+    pub fn root_fields(&self) -> &[String] {
+        &self.root_fields
+    }
+
+    #[must_use]
+    pub fn captured_requests(&self) -> Vec<CapturedRequest> {
+        self.captured_requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default()
+    }
+
+    /// Blocks the calling thread until the server thread exits.
+    pub fn wait(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    query_params: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Required variables per root field, from the generated resource contracts
+/// (`endpoint:query.search` answers the `search` field).
+fn resource_specs() -> HashMap<String, ResourceSpec> {
+    resource_contracts()
+        .iter()
+        .filter_map(|contract| {
+            let field = contract.graphql_file.split('.').next_back()?.trim();
+            Some((
+                field.to_string(),
+                ResourceSpec {
+                    required_variables: contract
+                        .required_variables
+                        .iter()
+                        .map(|name| (*name).to_string())
+                        .collect(),
+                },
+            ))
+        })
+        .collect()
+}
+
+fn handle_connection(mut stream: TcpStream, state: &ServerState) -> Result<(), String> {
+    stream
+        .set_nonblocking(false)
+        .map_err(|error| format!("failed to set blocking stream: {error}"))?;
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .map_err(|error| format!("failed to set read timeout: {error}"))?;
+
+    let request = read_http_request(&mut stream)?;
+    let persisted = carries_persisted_hash(&request);
+    let (query, variables) = match parse_graphql_request(&request, state) {
+        Ok(parsed) => parsed,
+        Err(error) if error.contains("PERSISTED_QUERY_NOT_FOUND") => {
+            let variables = request
+                .query_params
+                .get("variables")
+                .and_then(|raw| serde_json::from_str::<Value>(raw).ok())
+                .unwrap_or_else(|| json!({}));
+            state
+                .captured_requests
+                .lock()
+                .map_err(|_| "captured requests mutex poisoned".to_string())?
+                .push(CapturedRequest {
+                    method: request.method.clone(),
+                    path: request.path.clone(),
+                    accept: request.headers.get("accept").cloned(),
+                    headers: request.headers.clone(),
+                    root_field: None,
+                    query: String::new(),
+                    variables,
+                });
+            let payload = graphql_error("persisted query not found", "PERSISTED_QUERY_NOT_FOUND");
+            let fault = take_fault(state, None, persisted);
+            return respond(&mut stream, state, fault, &payload);
+        }
+        Err(error) => return Err(error),
+    };
+
+    let root_field = extract_root_field(&query);
+    state
+        .captured_requests
+        .lock()
+        .map_err(|_| "captured requests mutex poisoned".to_string())?
+        .push(CapturedRequest {
+            method: request.method.clone(),
+            path: request.path,
+            accept: request.headers.get("accept").cloned(),
+            headers: request.headers.clone(),
+            root_field: root_field.clone(),
+            query: query.clone(),
+            variables: variables.clone(),
+        });
+
+    let fault = take_fault(state, root_field.as_deref(), persisted);
+    let response_payload = route_graphql_request(&query, &variables, root_field, state);
+    respond(&mut stream, state, fault, &response_payload)
+}
+
+/// Writes `payload` after the configured latency, or the injected `fault`
+/// in its place.
+fn respond(
+    stream: &mut TcpStream,
+    state: &ServerState,
+    fault: Option<Fault>,
+    payload: &Value,
+) -> Result<(), String> {
+    if !state.latency.is_zero() {
+        thread::sleep(state.latency);
+    }
+    let Some(fault) = fault else {
+        return write_json_response(stream, payload);
+    };
+    match fault {
+        Fault::RateLimited { retry_after } => {
+            let seconds = retry_after.as_secs().to_string();
+            let body =
+                graphql_error("token budget exhausted", "TOKEN_BUDGET_EXHAUSTED").to_string();
+            write_response(
+                stream,
+                429,
+                &[
+                    ("Retry-After", seconds.as_str()),
+                    ("X-RateLimit-Remaining", "0"),
+                    ("X-RateLimit-Reset", seconds.as_str()),
+                ],
+                body.as_bytes(),
+                body.len(),
+            )
+        }
+        Fault::ServerError(status) => {
+            let body = json!({ "message": reason_phrase(status) }).to_string();
+            write_response(stream, status, &[], body.as_bytes(), body.len())
+        }
+        Fault::TruncatedBody => {
+            let body = payload.to_string();
+            write_response(
+                stream,
+                200,
+                &[],
+                &body.as_bytes()[..body.len() / 2],
+                body.len(),
+            )
+        }
+        Fault::Slow(delay) => {
+            thread::sleep(delay);
+            write_json_response(stream, payload)
+        }
+        Fault::PersistedQueryNotSupported => write_json_response(
+            stream,
+            &graphql_error(
+                "persisted queries are not supported",
+                "PERSISTED_QUERY_NOT_SUPPORTED",
+            ),
+        ),
+    }
+}
+
+/// Takes the fault configured for `root_field` (or `*`) that applies to this
+/// request, counting it against a `fault_times` budget.
+fn take_fault(state: &ServerState, root_field: Option<&str>, persisted: bool) -> Option<Fault> {
+    let mut faults = state.faults.lock().ok()?;
+    let key = root_field.into_iter().chain(["*"]).find(|key| {
+        faults
+            .get(*key)
+            .is_some_and(|rule| rule.fault.applies_to(persisted) && rule.remaining != Some(0))
+    })?;
+    let rule = faults.get_mut(key)?;
+    if let Some(remaining) = &mut rule.remaining {
+        *remaining -= 1;
+    }
+    Some(rule.fault)
+}
+
+fn carries_persisted_hash(request: &HttpRequest) -> bool {
+    let extensions = if request.method.eq_ignore_ascii_case("GET") {
+        request
+            .query_params
+            .get("extensions")
+            .and_then(|raw| serde_json::from_str::<Value>(raw).ok())
+    } else {
+        serde_json::from_slice::<Value>(&request.body)
+            .ok()
+            .and_then(|payload| payload.get("extensions").cloned())
+    };
+    extensions
+        .as_ref()
+        .and_then(extract_persisted_hash)
+        .is_some()
+}
+
+fn parse_graphql_request(
+    request: &HttpRequest,
+    state: &ServerState,
+) -> Result<(String, Value), String> {
+    let method = request.method.trim().to_ascii_uppercase();
+    if method == "POST" {
+        let payload = serde_json::from_slice::<Value>(&request.body)
+            .map_err(|error| format!("invalid JSON request body: {error}"))?;
+        let query = payload
+            .get("query")
+            .and_then(Value::as_str)
+            .ok_or_else(|| "request missing string query".to_string())?
+            .to_string();
+        let variables = payload
+            .get("variables")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        if let Some(hash) = payload.get("extensions").and_then(extract_persisted_hash) {
+            remember_persisted_query(state, &hash, &query)?;
+        }
+        return Ok((query, variables));
+    }
+
+    if method == "GET" {
+        let variables = request
+            .query_params
+            .get("variables")
+            .map(|raw| {
+                serde_json::from_str::<Value>(raw)
+                    .map_err(|error| format!("invalid GET variables JSON: {error}"))
+            })
+            .transpose()?
+            .unwrap_or_else(|| json!({}));
+
+        let query_from_param = request
+            .query_params
+            .get("query")
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        let extensions = request
+            .query_params
+            .get("extensions")
+            .map(|raw| {
+                serde_json::from_str::<Value>(raw)
+                    .map_err(|error| format!("invalid GET extensions JSON: {error}"))
+            })
+            .transpose()?;
+        if let (Some(query), Some(hash)) = (
+            query_from_param.as_deref(),
+            extensions.as_ref().and_then(extract_persisted_hash),
+        ) {
+            remember_persisted_query(state, &hash, query)?;
+        }
+
+        let query = if let Some(query) = query_from_param {
+            query
+        } else if let Some(hash) = extensions.as_ref().and_then(extract_persisted_hash) {
+            load_persisted_query(state, &hash).ok_or_else(|| {
+                "persisted query hash not found in stub cache: PERSISTED_QUERY_NOT_FOUND"
+                    .to_string()
+            })?
+        } else {
+            return Err("GET request missing `query` and persisted hash extensions".to_string());
+        };
+
+        return Ok((query, variables));
+    }
+
+    Err(format!("unsupported HTTP method in stub server: {method}"))
+}
+
+fn extract_persisted_hash(extensions: &Value) -> Option<String> {
+    extensions
+        .pointer("/persistedQuery/sha256Hash")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn remember_persisted_query(state: &ServerState, hash: &str, query: &str) -> Result<(), String> {
+    state
+        .persisted_queries
+        .lock()
+        .map_err(|_| "persisted query cache mutex poisoned".to_string())?
+        .insert(hash.to_string(), query.to_string());
+    Ok(())
+}
+
+fn load_persisted_query(state: &ServerState, hash: &str) -> Option<String> {
+    state
+        .persisted_queries
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(hash).cloned())
+}
+
+fn route_graphql_request(
+    query: &str,
+    variables: &Value,
+    root_field: Option<String>,
+    state: &ServerState,
+) -> Value {
+    if is_create_note_schema_query(query) {
+        return create_note_schema_response();
+    }
+
+    let Some(root_field) = root_field else {
+        return graphql_error(
+            "failed to extract root field from graphql query",
+            "INPUT_INVALID",
+        );
+    };
+
+    let Some(resource_spec) = state.resource_specs_by_field.get(&root_field) else {
+        return graphql_error(
+            &format!("unsupported root field: {root_field}"),
+            "INPUT_INVALID",
+        );
+    };
+
+    if let Some(error) = validate_required_variables(resource_spec, variables) {
+        return error;
+    }
+    if let Some((code, message)) = state.errors.get(&root_field) {
+        return graphql_error(message, code);
+    }
+    if let Some(fixture) = state.fixtures.get(&root_field) {
+        return fixture.clone();
+    }
+
+    response_for_root_field(&root_field, variables)
+}
+
+fn is_create_note_schema_query(query: &str) -> bool {
+    query.contains("CreateNoteSchema")
+        || (query.contains("CreateNoteInput")
+            && query.contains("CreateNotePayload")
+            && query.contains("noteType"))
+}
+
+fn validate_required_variables(spec: &ResourceSpec, variables: &Value) -> Option<Value> {
+    let Some(object) = variables.as_object() else {
+        return Some(graphql_error(
+            "variables must be a JSON object",
+            "INPUT_INVALID",
+        ));
+    };
+
+    let missing = spec
+        .required_variables
+        .iter()
+        .filter(|name| {
+            let value = object.get(*name);
+            value.is_none() || value.is_some_and(Value::is_null)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        None
+    } else {
+        Some(graphql_error(
+            &format!("missing required variable(s): {}", missing.join(", ")),
+            "INPUT_INVALID",
+        ))
+    }
+}
+
+fn create_note_schema_response() -> Value {
+    let contract = create_note_contract();
+    let names = |fields: &[&str]| {
+        fields
+            .iter()
+            .map(|name| json!({ "name": name }))
+            .collect::<Vec<_>>()
+    };
+    json!({
+        "data": {
+            "createNoteInput": { "inputFields": names(contract.input_fields) },
+            "createNotePayload": { "fields": names(contract.payload_fields) },
+            "noteType": { "fields": names(contract.note_projection_fields) }
+        }
+    })
+}
+
+#[allow(clippy::too_many_lines)]
+fn response_for_root_field(field: &str, variables: &Value) -> Value {
+    match field {
+        "search" => {
+            // Two pages, so `search note --all` has a cursor to follow.
+            let second_page =
+                variables.pointer("/after").and_then(Value::as_str) == Some("search-cursor-1");
+            let (id, page_info) = if second_page {
+                (
+                    "N-search-2",
+                    json!({ "hasNextPage": false, "endCursor": "search-cursor-2" }),
+                )
+            } else {
+                (
+                    "N-search",
+                    json!({ "hasNextPage": true, "endCursor": "search-cursor-1" }),
+                )
+            };
+            json!({
+                "data": {
+                    "search": {
+                        "totalCount": 2,
+                        "pageInfo": page_info,
+                        "edges": [{
+                            "node": {
+                                "document": { "id": id },
+                                "title": "search-title",
+                                "url": format!("https://example.kibe.la/notes/{id}"),
+                                "contentSummaryHtml": "summary",
+                                "path": format!("/notes/{id}"),
+                                "author": { "id": "U-stub", "account": "stub", "realName": "Stub User" }
+                            }
+                        }]
+                    }
+                }
+            })
+        }
+        "searchFolder" => json!({
+            "data": {
+                "searchFolder": {
+                    "edges": [{
+                        "node": {
+                            "name": "Engineering",
+                            "fixedPath": "/acme/engineering",
+                            "group": { "name": "Acme", "isPrivate": false }
+                        }
+                    }]
+                }
+            }
+        }),
+        "groups" => json!({
+            "data": {
+                "groups": {
+                    "edges": [{
+                        "node": {
+                            "id": "G1",
+                            "name": "Acme",
+                            "description": "Company-wide notes",
+                            "isDefault": true,
+                            "isArchived": false,
+                            "isPrivate": false
+                        }
+                    }]
+                }
+            }
+        }),
+        "folders" => json!({
+            "data": {
+                "folders": {
+                    "edges": [
+                        { "node": {
+                            "id": "F1",
+                            "name": "Engineering",
+                            "group": { "id": "G1", "name": "Acme", "isArchived": false, "isPrivate": false }
+                        } },
+                        { "node": {
+                            "id": "F2",
+                            "name": "Backend",
+                            "group": { "id": "G1", "name": "Acme", "isArchived": false, "isPrivate": false }
+                        } }
+                    ]
+                }
+            }
+        }),
+        "notes" => json!({
+            "data": {
+                "notes": {
+                    "edges": [{
+                        "node": {
+                            "id": "N-folder",
+                            "title": "folder-note",
+                            "url": "https://example.kibe.la/notes/N-folder"
+                        }
+                    }]
+                }
+            }
+        }),
+        "comment" => {
+            let id = variable_string(variables, "/id", "C1");
+            json!({
+                "data": {
+                    "comment": {
+                        "id": id,
+                        "content": "Should we roll back?",
+                        "publishedAt": "2026-02-23T00:00:00Z",
+                        "author": { "account": "stub" },
+                        "replies": {
+                            "pageInfo": { "hasNextPage": false },
+                            "nodes": [
+                                {
+                                    "id": "R1",
+                                    "content": "Not yet.\nWaiting on metrics.",
+                                    "publishedAt": "2026-02-23T01:00:00Z",
+                                    "author": { "account": "alice" }
+                                },
+                                {
+                                    "id": "R2",
+                                    "content": "Rolled back.",
+                                    "publishedAt": "2026-02-23T02:00:00Z",
+                                    "author": { "account": "stub" }
+                                }
+                            ]
+                        }
+                    }
+                }
+            })
+        }
+        "note" => {
+            let id = variable_string(variables, "/id", "N1");
+            let content = match id.as_str() {
+                "N-attachment" => "see ![diagram](/attachments/42/diagram.png)",
+                "N-folder" => "see /notes/1 and /notes/404.",
+                _ => "note-content",
+            };
+            json!({
+                "data": {
+                    "note": {
+                        "id": id,
+                        "title": "note-title",
+                        "content": content,
+                        "url": format!("https://example.kibe.la/notes/{id}"),
+                        "updatedAt": "2026-02-23T00:00:00Z",
+                        "author": { "account": "stub", "realName": "Stub User" },
+                        "groups": [{ "id": "G1", "name": "Acme" }],
+                        "folders": {
+                            "edges": [{
+                                "node": {
+                                    "id": "F1",
+                                    "name": "Runbooks",
+                                    "fullName": "Ops/Runbooks",
+                                    "fixedPath": "/groups/acme/folders/1",
+                                    "group": { "id": "G1", "name": "Acme" }
+                                }
+                            }]
+                        },
+                        "comments": {
+                            "nodes": [{
+                                "id": "C-existing",
+                                "content": "existing-comment",
+                                "publishedAt": "2026-02-23T00:00:00Z",
+                                "author": { "account": "stub-user" }
+                            }]
+                        }
+                    }
+                }
+            })
+        }
+        "noteFromPath" => {
+            let path = variable_string(variables, "/path", "/notes/N-path");
+            // `/notes/404` is the one path that no longer resolves.
+            if path == "/notes/404" {
+                return json!({ "data": { "noteFromPath": null } });
+            }
+            json!({
+                "data": {
+                    "noteFromPath": {
+                        "id": "N-path",
+                        "title": format!("note-from-{path}"),
+                        "content": "note-from-path-content",
+                        "url": "https://example.kibe.la/notes/N-path",
+                        "author": { "account": "stub", "realName": "Stub User" },
+                        "folders": { "edges": [] },
+                        "comments": { "edges": [] },
+                        "inlineComments": { "edges": [] }
+                    }
+                }
+            })
+        }
+        "folder" => {
+            let id = variable_string(variables, "/id", "F1");
+            // `F2` is the only sub-folder of the default `F1`.
+            let (name, path, folders, notes) = if id == "F2" {
+                (
+                    "Backend",
+                    "/acme/engineering/backend",
+                    json!([]),
+                    json!([{ "node": { "id": "N-backend", "title": "backend-note" } }]),
+                )
+            } else {
+                (
+                    "Engineering",
+                    "/acme/engineering",
+                    json!([{ "node": { "id": "F2", "name": "Backend" } }]),
+                    json!([]),
+                )
+            };
+            json!({
+                "data": {
+                    "folder": {
+                        "id": id,
+                        "name": name,
+                        "fullName": format!("Acme/{name}"),
+                        "fixedPath": path,
+                        "createdAt": "2026-02-23T00:00:00Z",
+                        "updatedAt": "2026-02-23T00:00:00Z",
+                        "group": { "id": "G1", "name": "Acme" },
+                        "folders": { "edges": folders },
+                        "notes": { "edges": notes }
+                    }
+                }
+            })
+        }
+        "folderFromPath" => {
+            let path = variable_string(variables, "/path", "/acme/engineering");
+            // Only `/acme/engineering` and folders below it exist.
+            if path != "/acme/engineering" && !path.starts_with("/acme/engineering/") {
+                return json!({ "data": { "folderFromPath": null } });
+            }
+            json!({
+                "data": {
+                    "folderFromPath": {
+                        "id": "F1",
+                        "name": "Engineering",
+                        "fullName": "Acme/Engineering",
+                        "fixedPath": path,
+                        "createdAt": "2026-02-23T00:00:00Z",
+                        "updatedAt": "2026-02-23T00:00:00Z",
+                        "group": { "id": "G1", "name": "Acme" },
+                        "folders": { "edges": [] },
+                        "notes": { "edges": [] }
+                    }
+                }
+            })
+        }
+        "feedSections" => json!({
+            "data": {
+                "feedSections": {
+                    "edges": [{
+                        "node": {
+                            "date": "2026-02-23",
+                            "note": {
+                                "id": "N-feed",
+                                "title": "feed-title",
+                                "contentSummaryHtml": "feed-summary"
+                            }
+                        }
+                    }]
+                }
+            }
+        }),
+        "createNote" => {
+            let title = variable_string(variables, "/input/title", "created-title");
+            let content = variable_string(variables, "/input/content", "created-content");
+            let client_mutation_id = variables
+                .pointer("/input/clientMutationId")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+
+            let mut payload = json!({
+                "data": {
+                    "createNote": {
+                        "note": {
+                            "id": "N-created",
+                            "title": title,
+                            "content": content,
+                            "url": "https://example.kibe.la/notes/N-created"
+                        }
+                    }
+                }
+            });
+
+            if let Some(client_mutation_id) = client_mutation_id {
+                payload["data"]["createNote"]["clientMutationId"] =
+                    Value::String(client_mutation_id);
+            }
+
+            payload
+        }
+        "createComment" => json!({
+            "data": {
+                "createComment": {
+                    "comment": { "id": "C-created" }
+                }
+            }
+        }),
+        "createCommentReply" => json!({
+            "data": {
+                "createCommentReply": {
+                    "reply": { "id": "R-created" }
+                }
+            }
+        }),
+        "createInlineComment" => json!({
+            "data": {
+                "createInlineComment": {
+                    "inlineComment": { "id": "IC-created" }
+                }
+            }
+        }),
+        "updateComment" => json!({
+            "data": {
+                "updateComment": {
+                    "comment": { "id": "C-updated" }
+                }
+            }
+        }),
+        "deleteComment" => json!({
+            "data": {
+                "deleteComment": { "clientMutationId": Value::Null }
+            }
+        }),
+        "createFolder" => json!({
+            "data": {
+                "createFolder": {
+                    "folder": { "id": "F-created" }
+                }
+            }
+        }),
+        "uploadAttachment" => {
+            let name = variable_string(variables, "/input/name", "attachment");
+            json!({
+                "data": {
+                    "uploadAttachment": {
+                        "attachment": {
+                            "id": "A-uploaded",
+                            "path": format!("/attachments/uploaded/{name}")
+                        }
+                    }
+                }
+            })
+        }
+        "moveNoteToAnotherFolder" => {
+            let id = variable_string(variables, "/input/noteId", "N1");
+            json!({
+                "data": {
+                    "moveNoteToAnotherFolder": {
+                        "note": { "id": id }
+                    }
+                }
+            })
+        }
+        "attachNoteToFolder" => {
+            let id = variable_string(variables, "/input/noteId", "N1");
+            json!({
+                "data": {
+                    "attachNoteToFolder": {
+                        "note": { "id": id }
+                    }
+                }
+            })
+        }
+        "updateNoteContent" => {
+            let id = variable_string(variables, "/input/id", "N1");
+            let content = variable_string(variables, "/input/newContent", "updated-content");
+            json!({
+                "data": {
+                    "updateNoteContent": {
+                        "note": {
+                            "id": id,
+                            "title": "updated-title",
+                            "content": content
+                        }
+                    }
+                }
+            })
+        }
+        "currentUser" => json!({
+            "data": {
+                "currentUser": {
+                    "account": "stub-user",
+                    "draftNotes": {
+                        "pageInfo": { "hasNextPage": false, "endCursor": null },
+                        "edges": [{
+                            "node": {
+                                "id": "N-draft",
+                                "title": "draft-title",
+                                "url": "https://example.kibe.la/notes/N-draft",
+                                "updatedAt": "2026-02-23T00:00:00Z"
+                            }
+                        }]
+                    }
+                }
+            }
+        }),
+        "updateNote" => {
+            let id = variable_string(variables, "/input/id", "N1");
+            let published_at =
+                variable_string(variables, "/input/publishedAt", "2026-02-23T00:00:00Z");
+            json!({
+                "data": {
+                    "updateNote": {
+                        "note": {
+                            "id": id,
+                            "title": variable_string(variables, "/input/title", "published-title"),
+                            "url": "https://example.kibe.la/notes/N1",
+                            "publishedAt": published_at
+                        }
+                    }
+                }
+            })
+        }
+        _ => graphql_error(&format!("unsupported root field: {field}"), "INPUT_INVALID"),
+    }
+}
+
+fn variable_string(variables: &Value, pointer: &str, fallback: &str) -> String {
+    variables
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .unwrap_or(fallback)
+        .to_string()
+}
+
+fn graphql_error(message: &str, code: &str) -> Value {
+    json!({
+        "errors": [{
+            "message": message,
+            "extensions": {
+                "code": code,
+            }
+        }]
+    })
+}
+
+fn read_http_request(stream: &mut TcpStream) -> Result<HttpRequest, String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0_u8; 4096];
+
+    let header_end = loop {
+        let read = stream
+            .read(&mut chunk)
+            .map_err(|error| format!("failed to read request: {error}"))?;
+        if read == 0 {
+            return Err("connection closed before request headers".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(index) = find_header_end(&buffer) {
+            break index;
+        }
+    };
+
+    let headers_raw = String::from_utf8(buffer[..header_end].to_vec())
+        .map_err(|error| format!("request headers are not utf-8: {error}"))?;
+    let request_line = headers_raw
+        .lines()
+        .next()
+        .ok_or_else(|| "request line missing".to_string())?;
+    let mut request_parts = request_line.split_whitespace();
+    let method = request_parts.next().unwrap_or("GET").to_string();
+    let raw_target = request_parts.next().unwrap_or("/");
+    let (path, query_params) = split_path_and_query(raw_target)?;
+
+    let mut content_length = 0_usize;
+    let mut headers = HashMap::new();
+    for line in headers_raw.lines().skip(1) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = name.trim().to_ascii_lowercase();
+        let normalized_value = value.trim().to_string();
+        if key == "content-length" {
+            content_length = normalized_value.parse::<usize>().unwrap_or(0);
+        }
+        headers.insert(key, normalized_value);
+    }
+
+    let body_start = header_end + 4;
+    let mut body = if buffer.len() > body_start {
+        buffer[body_start..].to_vec()
+    } else {
+        Vec::new()
+    };
+
+    while body.len() < content_length {
+        let read = stream
+            .read(&mut chunk)
+            .map_err(|error| format!("failed to read request body: {error}"))?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+
+    Ok(HttpRequest {
+        method,
+        path,
+        headers,
+        query_params,
+        body,
+    })
+}
+
+fn split_path_and_query(raw_target: &str) -> Result<(String, HashMap<String, String>), String> {
+    let (path, query_raw) = match raw_target.split_once('?') {
+        Some((path, query_raw)) => (path, Some(query_raw)),
+        None => (raw_target, None),
+    };
+    let query_params = parse_query_params(query_raw.unwrap_or(""))?;
+    Ok((path.to_string(), query_params))
+}
+
+fn parse_query_params(raw: &str) -> Result<HashMap<String, String>, String> {
+    let mut params = HashMap::new();
+    if raw.trim().is_empty() {
+        return Ok(params);
+    }
+    for pair in raw.split('&') {
+        if pair.trim().is_empty() {
+            continue;
+        }
+        let (key_raw, value_raw) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = percent_decode(key_raw)?;
+        let value = percent_decode(value_raw)?;
+        params.insert(key, value);
+    }
+    Ok(params)
+}
+
+fn percent_decode(raw: &str) -> Result<String, String> {
+    let bytes = raw.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut index = 0usize;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => {
+                output.push(b' ');
+                index += 1;
+            }
+            b'%' => {
+                if index + 2 >= bytes.len() {
+                    return Err("invalid percent-encoding in query parameter".to_string());
+                }
+                let high = decode_hex_nibble(bytes[index + 1])?;
+                let low = decode_hex_nibble(bytes[index + 2])?;
+                output.push((high << 4) | low);
+                index += 3;
+            }
+            value => {
+                output.push(value);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(output).map_err(|error| format!("query parameter is not utf-8: {error}"))
+}
+
+fn decode_hex_nibble(raw: u8) -> Result<u8, String> {
+    match raw {
+        b'0'..=b'9' => Ok(raw - b'0'),
+        b'a'..=b'f' => Ok(raw - b'a' + 10),
+        b'A'..=b'F' => Ok(raw - b'A' + 10),
+        _ => Err("invalid percent-encoding in query parameter".to_string()),
+    }
+}
+
+fn find_header_end(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+fn write_json_response(stream: &mut TcpStream, payload: &Value) -> Result<(), String> {
+    let body = payload.to_string();
+    write_response(stream, 200, &[], body.as_bytes(), body.len())
+}
+
+/// Writes a JSON response whose `Content-Length` is `declared_length`, which
+/// may exceed `body` to simulate a connection cut mid-body.
+fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    declared_length: usize,
+) -> Result<(), String> {
+    let mut head = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {declared_length}\r\nConnection: close\r\n",
+        reason_phrase(status)
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream
+        .write_all(head.as_bytes())
+        .and_then(|()| stream.write_all(body))
+        .map_err(|error| format!("failed to write response: {error}"))?;
+    stream
+        .flush()
+        .map_err(|error| format!("failed to flush response: {error}"))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Error",
+    }
+}
+
+fn extract_root_field(query: &str) -> Option<String> {
+    let start = query.find('{')? + 1;
+    let bytes = query.as_bytes();
+    let mut index = start;
+
+    skip_whitespace(bytes, &mut index);
+    let mut field = read_identifier(bytes, &mut index)?;
+    skip_whitespace(bytes, &mut index);
+
+    if bytes.get(index).copied() == Some(b':') {
+        index += 1;
+        skip_whitespace(bytes, &mut index);
+        field = read_identifier(bytes, &mut index)?;
+    }
+
+    Some(field)
+}
+
+fn skip_whitespace(bytes: &[u8], index: &mut usize) {
+    while *index < bytes.len() && bytes[*index].is_ascii_whitespace() {
+        *index += 1;
+    }
+}
+
+fn read_identifier(bytes: &[u8], index: &mut usize) -> Option<String> {
+    let start = *index;
+    while *index < bytes.len() {
+        let c = bytes[*index];
+        if c.is_ascii_alphanumeric() || c == b'_' {
+            *index += 1;
+        } else {
+            break;
+        }
+    }
+    if *index == start {
+        None
+    } else {
+        std::str::from_utf8(&bytes[start..*index])
+            .ok()
+            .map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fault, MockServer};
+    use kibel_client::{KibelClient, KibelClientError};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn serves_every_contract_root_field() {
+        let server = MockServer::start().expect("server should start");
+        for field in [
+            "search",
+            "note",
+            "noteFromPath",
+            "createNote",
+            "currentUser",
+        ] {
+            assert!(
+                server.root_fields().iter().any(|known| known == field),
+                "missing root field {field}"
+            );
+        }
+    }
+
+    #[test]
+    fn injects_errors_and_latency_per_root_field() {
+        let server = MockServer::builder()
+            .graphql_error("note", "NOT_FOUND", "gone")
+            .latency(Duration::from_millis(50))
+            .start()
+            .expect("server should start");
+        let client = KibelClient::new(server.origin(), "token").expect("client");
+
+        let started = Instant::now();
+        match client.get_note("N1") {
+            Err(KibelClientError::Api { code, message, .. }) => {
+                assert_eq!((code.as_str(), message.as_str()), ("NOT_FOUND", "gone"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(client.get_note("  ").is_err());
+    }
+
+    #[test]
+    fn injects_transport_faults_per_root_field() {
+        let cases = [
+            (
+                Fault::RateLimited {
+                    retry_after: Duration::from_secs(2),
+                },
+                "TOKEN_BUDGET_EXHAUSTED",
+            ),
+            (Fault::ServerError(503), "http status 503"),
+            (Fault::TruncatedBody, ""),
+        ];
+        for (fault, expected) in cases {
+            let server = MockServer::builder()
+                .fault_times("note", fault, 1)
+                .start()
+                .expect("server should start");
+            let client = KibelClient::new(server.origin(), "token").expect("client");
+
+            let message = match client.get_note("N1") {
+                Err(KibelClientError::Api { code, .. }) => code,
+                Err(KibelClientError::Transport { message, .. }) => message,
+                other => panic!("{fault}: unexpected result: {other:?}"),
+            };
+            assert!(message.contains(expected), "{fault}: {message}");
+            assert_eq!(client.get_note("N1").expect("fault is spent").id, "N1");
+        }
+    }
+
+    #[test]
+    fn slow_fault_only_delays_its_root_field() {
+        let server = MockServer::builder()
+            .fault("note", Fault::Slow(Duration::from_millis(50)))
+            .start()
+            .expect("server should start");
+        let client = KibelClient::new(server.origin(), "token").expect("client");
+
+        let started = Instant::now();
+        client.get_note("N1").expect("slow response still succeeds");
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn unsupported_persisted_queries_fall_back_to_plain_post() {
+        // `note` is only known once the hash is registered; `*` also rejects
+        // the hash-only GET.
+        for (field, expected) in [
+            ("note", ["GET", "POST", "POST"].as_slice()),
+            ("*", &["GET", "POST"]),
+        ] {
+            let server = MockServer::builder()
+                .fault(field, Fault::PersistedQueryNotSupported)
+                .start()
+                .expect("server should start");
+            let client = KibelClient::new(server.origin(), "token").expect("client");
+
+            assert_eq!(client.get_note("N1").expect("fallback").id, "N1");
+            let methods = server
+                .captured_requests()
+                .into_iter()
+                .map(|request| request.method)
+                .collect::<Vec<_>>();
+            assert_eq!(methods, expected, "fault on {field}");
+        }
+    }
+
+    #[test]
+    fn parses_fault_flags() {
+        assert_eq!(
+            "rate-limit:30".parse::<Fault>(),
+            Ok(Fault::RateLimited {
+                retry_after: Duration::from_secs(30)
+            })
+        );
+        assert_eq!("server-error".parse::<Fault>(), Ok(Fault::ServerError(503)));
+        assert_eq!(
+            "slow:250".parse::<Fault>(),
+            Ok(Fault::Slow(Duration::from_millis(250)))
+        );
+        assert_eq!(
+            "apq-unsupported".parse::<Fault>(),
+            Ok(Fault::PersistedQueryNotSupported)
+        );
+        assert!("server-error:404".parse::<Fault>().is_err());
+        assert!("slow".parse::<Fault>().is_err());
+        assert!("explode".parse::<Fault>().is_err());
+    }
+}
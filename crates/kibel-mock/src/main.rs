@@ -0,0 +1,96 @@
+use clap::Parser;
+use kibel_mock::{Fault, MockServer};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "kibel-mock",
+    version,
+    about = "Serve a contract-driven fake Kibela GraphQL endpoint"
+)]
+struct Args {
+    #[arg(long, default_value = "127.0.0.1:0", help = "Address to listen on")]
+    listen: SocketAddr,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "JSON object mapping root fields to full GraphQL responses"
+    )]
+    fixtures: Option<PathBuf>,
+    #[arg(
+        long = "error",
+        value_name = "FIELD=CODE[:MESSAGE]",
+        help = "Fail a root field with a GraphQL error (repeatable)"
+    )]
+    errors: Vec<String>,
+    #[arg(
+        long = "fault",
+        value_name = "FIELD=KIND[:ARG]",
+        help = "Inject a transport fault into a root field, or `*` for all (repeatable): \
+                rate-limit[:SECS], server-error[:STATUS], truncate, slow:MS, apq-unsupported"
+    )]
+    faults: Vec<String>,
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = 0,
+        help = "Delay every response"
+    )]
+    latency_ms: u64,
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("kibel-mock: {message}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let mut builder = MockServer::builder()
+        .bind(args.listen)
+        .latency(Duration::from_millis(args.latency_ms));
+
+    if let Some(path) = &args.fixtures {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        let fixtures = serde_json::from_str::<Value>(&raw)
+            .map_err(|error| format!("failed to parse {}: {error}", path.display()))?;
+        let Value::Object(fixtures) = fixtures else {
+            return Err(format!("{} must hold a JSON object", path.display()));
+        };
+        for (field, response) in fixtures {
+            builder = builder.fixture(field, response);
+        }
+    }
+
+    for raw in &args.errors {
+        let (field, rest) = raw
+            .split_once('=')
+            .ok_or_else(|| format!("--error expects FIELD=CODE[:MESSAGE]: {raw}"))?;
+        let (code, message) = rest.split_once(':').unwrap_or((rest, "injected error"));
+        builder = builder.graphql_error(field.trim(), code.trim(), message.trim());
+    }
+
+    for raw in &args.faults {
+        let (field, kind) = raw
+            .split_once('=')
+            .ok_or_else(|| format!("--fault expects FIELD=KIND[:ARG]: {raw}"))?;
+        let fault = kind.parse::<Fault>()?;
+        builder = builder.fault(field.trim(), fault);
+    }
+
+    let server = builder
+        .start()
+        .map_err(|error| format!("failed to listen on {}: {error}", args.listen))?;
+    println!("{}", server.origin());
+    server.wait();
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use super::{parse_graphql_fields, DiffOutputFormat, ResourceDefinition, ToolError, ToolResult};
+use serde_json::{json, Value};
+
+/// One query or mutation root field of the endpoint schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CoverageEntry {
+    pub(crate) kind: &'static str,
+    pub(crate) field: String,
+    pub(crate) args: usize,
+    pub(crate) required_args: usize,
+    /// Resources in `RESOURCE_DEFINITIONS` that call this field.
+    pub(crate) resources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CoverageReport {
+    pub(crate) covered: Vec<CoverageEntry>,
+    pub(crate) uncovered: Vec<CoverageEntry>,
+}
+
+impl CoverageReport {
+    fn total(&self) -> usize {
+        self.covered.len() + self.uncovered.len()
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn percent(&self) -> f64 {
+        if self.total() == 0 {
+            return 0.0;
+        }
+        self.covered.len() as f64 * 100.0 / self.total() as f64
+    }
+}
+
+/// Classifies every query/mutation root field of `schema` (a `__schema`
+/// value) by whether a resource definition calls it. Entries are sorted by
+/// kind, then field name.
+pub(crate) fn compute_coverage(
+    schema: &Value,
+    definitions: &[ResourceDefinition],
+) -> ToolResult<CoverageReport> {
+    let payload = json!({ "data": { "__schema": schema } });
+    let mut report = CoverageReport::default();
+    for kind in ["query", "mutation"] {
+        let fields = parse_graphql_fields(&payload, kind)?;
+        let mut names = fields.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let spec = &fields[name];
+            let entry = CoverageEntry {
+                kind,
+                field: name.clone(),
+                args: spec.args.len(),
+                required_args: spec.args.iter().filter(|arg| arg.required).count(),
+                resources: definitions
+                    .iter()
+                    .filter(|definition| definition.kind == kind && definition.field == name)
+                    .map(|definition| definition.name.to_string())
+                    .collect(),
+            };
+            if entry.resources.is_empty() {
+                report.uncovered.push(entry);
+            } else {
+                report.covered.push(entry);
+            }
+        }
+    }
+    Ok(report)
+}
+
+pub(crate) fn coverage_json(report: &CoverageReport) -> Value {
+    let entry = |entry: &CoverageEntry| {
+        json!({
+            "kind": entry.kind,
+            "field": entry.field,
+            "args": entry.args,
+            "required_args": entry.required_args,
+            "resources": entry.resources,
+        })
+    };
+    json!({
+        "covered_count": report.covered.len(),
+        "total_count": report.total(),
+        "covered": report.covered.iter().map(entry).collect::<Vec<_>>(),
+        "uncovered": report.uncovered.iter().map(entry).collect::<Vec<_>>(),
+    })
+}
+
+pub(crate) fn print_coverage(report: &CoverageReport, format: DiffOutputFormat) -> ToolResult<()> {
+    match format {
+        DiffOutputFormat::Text => {
+            println!(
+                "resource contract coverage: {}/{} root fields ({:.1}%)",
+                report.covered.len(),
+                report.total(),
+                report.percent()
+            );
+            if report.uncovered.is_empty() {
+                return Ok(());
+            }
+            let width = report
+                .uncovered
+                .iter()
+                .map(|entry| entry.field.len())
+                .max()
+                .unwrap_or_default()
+                .max("FIELD".len());
+            println!("uncovered operations:");
+            println!(
+                "  {:<8}  {:<width$}  {:>4}  {:>8}",
+                "KIND", "FIELD", "ARGS", "REQUIRED"
+            );
+            for entry in &report.uncovered {
+                println!(
+                    "  {:<8}  {:<width$}  {:>4}  {:>8}",
+                    entry.kind, entry.field, entry.args, entry.required_args
+                );
+            }
+        }
+        DiffOutputFormat::Json => {
+            let rendered =
+                serde_json::to_string_pretty(&coverage_json(report)).map_err(|error| {
+                    ToolError::message(format!("failed to render coverage json: {error}"))
+                })?;
+            println!("{rendered}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use super::schema_diff::{compute_schema_diff, load_schema_snapshot, SchemaDiffResult};
+use super::{
+    parse_graphql_fields, response_codegen, DiffOutputFormat, NormalizedResource,
+    ResourceDefinition, ToolError, ToolResult,
+};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TENANT_SNAPSHOT_SUFFIX: &str = ".schema.snapshot.json";
+
+/// Differences between one tenant's schema and what kibel relies on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct TenantDrift {
+    pub(crate) tenant: String,
+    /// Trusted operations the tenant's schema cannot serve as written.
+    pub(crate) contract_issues: Vec<String>,
+    /// Schema changes relative to the primary endpoint schema snapshot, when
+    /// one is available.
+    pub(crate) schema_diff: Option<SchemaDiffResult>,
+}
+
+impl TenantDrift {
+    pub(crate) fn is_drifting(&self) -> bool {
+        !self.contract_issues.is_empty()
+            || self
+                .schema_diff
+                .as_ref()
+                .is_some_and(|diff| !diff.breaking.is_empty())
+    }
+}
+
+/// `<tenants_dir>/<tenant>.schema.snapshot.json`.
+pub(crate) fn tenant_snapshot_path(tenants_dir: &Path, tenant: &str) -> ToolResult<PathBuf> {
+    let valid = !tenant.is_empty()
+        && tenant
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("tenant name must be [A-Za-z0-9_-]+: {tenant:?}").into());
+    }
+    Ok(tenants_dir.join(format!("{tenant}{TENANT_SNAPSHOT_SUFFIX}")))
+}
+
+/// Lists the tenant snapshots in `tenants_dir`, sorted by tenant name.
+pub(crate) fn list_tenant_snapshots(tenants_dir: &Path) -> ToolResult<Vec<(String, PathBuf)>> {
+    let entries = fs::read_dir(tenants_dir)
+        .map_err(|error| format!("failed to read {}: {error}", tenants_dir.display()))?;
+    let mut tenants = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|error| format!("failed to read {}: {error}", tenants_dir.display()))?
+            .path();
+        let tenant = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(TENANT_SNAPSHOT_SUFFIX))
+            .map(str::to_string);
+        if let Some(tenant) = tenant {
+            tenants.push((tenant, path));
+        }
+    }
+    tenants.sort();
+    Ok(tenants)
+}
+
+/// Checks every contract resource against `schema` (a tenant `__schema`):
+/// the root field must exist, accept every contract variable, require no
+/// argument the contract does not send, and resolve every selected field.
+pub(crate) fn contract_issues(
+    schema: &Value,
+    resources: &[NormalizedResource],
+    definitions: &[ResourceDefinition],
+) -> ToolResult<Vec<String>> {
+    let payload = json!({ "data": { "__schema": schema } });
+    let query_fields = parse_graphql_fields(&payload, "query")?;
+    let mutation_fields = parse_graphql_fields(&payload, "mutation")?;
+
+    let mut issues = Vec::new();
+    for resource in resources {
+        let Some(definition) = definitions
+            .iter()
+            .find(|definition| definition.name == resource.name)
+        else {
+            issues.push(format!(
+                "resource `{}` is not in RESOURCE_DEFINITIONS",
+                resource.name
+            ));
+            continue;
+        };
+        let fields = if resource.kind == "mutation" {
+            &mutation_fields
+        } else {
+            &query_fields
+        };
+        let Some(spec) = fields.get(definition.field) else {
+            issues.push(format!(
+                "resource `{}`: root field `{}.{}` is missing",
+                resource.name, resource.kind, definition.field
+            ));
+            continue;
+        };
+        for variable in &resource.all_variables {
+            if !spec.args.iter().any(|arg| &arg.name == variable) {
+                issues.push(format!(
+                    "resource `{}`: argument `{}({variable})` is not accepted",
+                    resource.name, definition.field
+                ));
+            }
+        }
+        for arg in spec.args.iter().filter(|arg| arg.required) {
+            if !resource.all_variables.contains(&arg.name) {
+                issues.push(format!(
+                    "resource `{}`: required argument `{}({})` is not sent",
+                    resource.name, definition.field, arg.name
+                ));
+            }
+        }
+        if let Err(error) = response_codegen::render_response_types_module(
+            std::slice::from_ref(resource),
+            Some(schema),
+        ) {
+            issues.push(format!("resource `{}`: {error}", resource.name));
+        }
+    }
+    Ok(issues)
+}
+
+pub(crate) fn compute_tenant_drift(
+    tenant: &str,
+    tenant_snapshot: &Path,
+    base_schema: Option<&Value>,
+    resources: &[NormalizedResource],
+    definitions: &[ResourceDefinition],
+) -> ToolResult<TenantDrift> {
+    let schema = load_schema_snapshot(tenant_snapshot)?;
+    Ok(TenantDrift {
+        tenant: tenant.to_string(),
+        contract_issues: contract_issues(&schema, resources, definitions)?,
+        schema_diff: base_schema
+            .map(|base| compute_schema_diff(base, &schema))
+            .transpose()?,
+    })
+}
+
+pub(crate) fn drift_json(report: &[TenantDrift]) -> Value {
+    let tenants = report
+        .iter()
+        .map(|drift| {
+            let mut item = json!({
+                "tenant": drift.tenant,
+                "drifting": drift.is_drifting(),
+                "contract_issues": drift.contract_issues,
+            });
+            if let Some(diff) = &drift.schema_diff {
+                item["schema_diff"] = super::schema_diff::schema_diff_json(diff);
+            }
+            item
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "tenant_count": report.len(),
+        "drifting_count": report.iter().filter(|drift| drift.is_drifting()).count(),
+        "tenants": tenants,
+    })
+}
+
+pub(crate) fn print_drift(report: &[TenantDrift], format: DiffOutputFormat) -> ToolResult<()> {
+    match format {
+        DiffOutputFormat::Text => {
+            for drift in report {
+                let diff_summary = drift.schema_diff.as_ref().map_or_else(
+                    || "no base schema".to_string(),
+                    |diff| {
+                        format!(
+                            "{} breaking / {} non-breaking schema change(s)",
+                            diff.breaking.len(),
+                            diff.non_breaking.len()
+                        )
+                    },
+                );
+                println!(
+                    "tenant `{}`: {} contract issue(s), {diff_summary}",
+                    drift.tenant,
+                    drift.contract_issues.len()
+                );
+                for issue in &drift.contract_issues {
+                    println!("  - contract: {issue}");
+                }
+                if let Some(diff) = &drift.schema_diff {
+                    for item in &diff.breaking {
+                        println!("  - breaking: {item}");
+                    }
+                    for item in &diff.non_breaking {
+                        println!("  - non-breaking: {item}");
+                    }
+                }
+            }
+        }
+        DiffOutputFormat::Json => {
+            let rendered = serde_json::to_string_pretty(&drift_json(report)).map_err(|error| {
+                ToolError::message(format!("failed to render drift json: {error}"))
+            })?;
+            println!("{rendered}");
+        }
+    }
+    Ok(())
+}
@@ -1,4 +1,9 @@
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use kibel_client::{
+    endpoint_from_origin, ContractManifest, GraphqlRequest, GraphqlTransport, HttpTransport,
+    ManifestOperation, Timeouts,
+};
+use selection::{SelectionOverride, SelectionOverrides};
 use serde_json::{json, Value};
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
@@ -7,6 +12,14 @@ use std::process::{Command, ExitCode};
 use std::time::Duration;
 use thiserror::Error;
 
+mod coverage;
+mod drift;
+mod response_codegen;
+mod scaffold;
+mod schema_diff;
+mod sdl;
+mod selection;
+
 const INTROSPECTION_QUERY: &str = r#"
 query EndpointIntrospection {
   __schema {
@@ -56,8 +69,15 @@ query EndpointIntrospection {
           ...TypeRef
         }
       }
+      interfaces {
+        name
+      }
       inputFields {
         name
+        defaultValue
+        type {
+          ...TypeRef
+        }
       }
       possibleTypes {
         name
@@ -102,7 +122,6 @@ fragment TypeRef on __Type {
   }
 }
 "#;
-const GRAPHQL_ACCEPT_HEADER: &str = "application/graphql-response+json, application/json;q=0.9";
 const REQUIRED_CREATE_NOTE_INPUT_FIELDS: &[&str] = &["title", "content", "groupIds", "coediting"];
 const REQUIRED_CREATE_NOTE_PAYLOAD_FIELDS: &[&str] = &["note"];
 
@@ -114,6 +133,28 @@ struct ResourceDefinition {
     client_method: &'static str,
 }
 
+/// A resource definition that may not be compiled into
+/// [`RESOURCE_DEFINITIONS`] yet, such as one being scaffolded by
+/// `resource-contract add`.
+#[derive(Debug, Clone, Copy)]
+struct ResourceSpec<'a> {
+    name: &'a str,
+    kind: &'a str,
+    field: &'a str,
+    client_method: &'a str,
+}
+
+impl From<&ResourceDefinition> for ResourceSpec<'static> {
+    fn from(definition: &ResourceDefinition) -> Self {
+        Self {
+            name: definition.name,
+            kind: definition.kind,
+            field: definition.field,
+            client_method: definition.client_method,
+        }
+    }
+}
+
 const RESOURCE_DEFINITIONS: &[ResourceDefinition] = &[
     ResourceDefinition {
         name: "searchNote",
@@ -175,6 +216,18 @@ const RESOURCE_DEFINITIONS: &[ResourceDefinition] = &[
         field: "feedSections",
         client_method: "get_feed_sections",
     },
+    ResourceDefinition {
+        name: "getDraftNotes",
+        kind: "query",
+        field: "currentUser",
+        client_method: "get_draft_notes",
+    },
+    ResourceDefinition {
+        name: "getNoteComments",
+        kind: "query",
+        field: "note",
+        client_method: "get_note_comments",
+    },
     ResourceDefinition {
         name: "createNote",
         kind: "mutation",
@@ -217,6 +270,48 @@ const RESOURCE_DEFINITIONS: &[ResourceDefinition] = &[
         field: "updateNoteContent",
         client_method: "update_note",
     },
+    ResourceDefinition {
+        name: "updateNote",
+        kind: "mutation",
+        field: "updateNote",
+        client_method: "publish_note",
+    },
+    ResourceDefinition {
+        name: "updateComment",
+        kind: "mutation",
+        field: "updateComment",
+        client_method: "update_comment",
+    },
+    ResourceDefinition {
+        name: "deleteComment",
+        kind: "mutation",
+        field: "deleteComment",
+        client_method: "delete_comment",
+    },
+    ResourceDefinition {
+        name: "createInlineComment",
+        kind: "mutation",
+        field: "createInlineComment",
+        client_method: "create_inline_comment",
+    },
+    ResourceDefinition {
+        name: "uploadAttachment",
+        kind: "mutation",
+        field: "uploadAttachment",
+        client_method: "upload_attachment",
+    },
+    ResourceDefinition {
+        name: "getNoteMetadata",
+        kind: "query",
+        field: "note",
+        client_method: "get_note_metadata",
+    },
+    ResourceDefinition {
+        name: "getCommentThread",
+        kind: "query",
+        field: "comment",
+        client_method: "get_comment_thread",
+    },
 ];
 
 #[derive(Parser)]
@@ -237,6 +332,10 @@ enum TopCommand {
         #[command(subcommand)]
         action: ResourceContractAction,
     },
+    Schema {
+        #[command(subcommand)]
+        action: SchemaAction,
+    },
 }
 
 #[derive(Subcommand)]
@@ -248,10 +347,24 @@ enum CreateNoteContractAction {
 
 #[derive(Subcommand)]
 enum ResourceContractAction {
-    Check(ResourceContractArgs),
+    Check(ResourceContractCheckArgs),
     Write(ResourceContractArgs),
     RefreshEndpoint(EndpointRefreshArgs),
     Diff(ResourceContractDiffArgs),
+    /// Register a new resource and emit client/CLI/mock stubs for it.
+    Add(Box<ResourceContractAddArgs>),
+    /// List schema root fields that no resource definition covers yet.
+    Coverage(ResourceContractCoverageArgs),
+    /// Check the contracts against every tenant schema snapshot.
+    Drift(ResourceContractDriftArgs),
+}
+
+#[derive(Subcommand)]
+enum SchemaAction {
+    /// Compare two endpoint schema snapshots and classify the changes.
+    Diff(SchemaDiffArgs),
+    /// Render the endpoint schema snapshot as GraphQL SDL next to it.
+    PrintSdl(SchemaPrintSdlArgs),
 }
 
 #[derive(Args, Clone)]
@@ -299,6 +412,92 @@ struct ResourceContractArgs {
         default_value = "crates/kibel-client/src/generated_resource_contracts.rs"
     )]
     generated: String,
+    #[arg(
+        long,
+        default_value = "schema/introspection/endpoint.schema.snapshot.json"
+    )]
+    schema_snapshot: String,
+    #[arg(
+        long,
+        default_value = "crates/kibel-client/src/generated_response_types.rs"
+    )]
+    response_types: String,
+    #[arg(long, default_value = "schema/introspection/selection_overrides.json")]
+    selection_overrides: String,
+    #[arg(
+        long,
+        default_value = "schema/contracts/resource_contracts.manifest.json"
+    )]
+    manifest: String,
+}
+
+#[derive(Args, Clone)]
+struct ResourceContractCheckArgs {
+    #[command(flatten)]
+    contract: ResourceContractArgs,
+    /// Compare the generated modules byte for byte after formatting with
+    /// `rustfmt` from PATH, instead of comparing their parsed syntax.
+    #[arg(long)]
+    use_rustfmt: bool,
+}
+
+#[derive(Args, Clone)]
+struct ResourceContractAddArgs {
+    #[arg(long, help = "Resource name, e.g. getNoteLikers")]
+    name: String,
+    #[arg(long, value_parser = ["query", "mutation"])]
+    kind: String,
+    #[arg(long, help = "Root field the operation selects")]
+    field: String,
+    #[arg(long, help = "KibelClient method name (default: snake_case of --name)")]
+    client_method: Option<String>,
+    #[arg(
+        long,
+        help = "Operation document to use instead of generating one from the schema snapshot"
+    )]
+    document: Option<String>,
+    #[arg(long, default_value = "crates/kibel-tools/src/main.rs")]
+    definitions_source: String,
+    #[arg(long, default_value = "target/kibel-tools/scaffold")]
+    scaffold_dir: String,
+    #[command(flatten)]
+    contract: ResourceContractArgs,
+}
+
+#[derive(Args, Clone)]
+struct ResourceContractCoverageArgs {
+    #[arg(
+        long,
+        default_value = "schema/introspection/endpoint.schema.snapshot.json"
+    )]
+    schema_snapshot: String,
+    #[arg(long, value_enum, default_value_t = DiffOutputFormat::Text)]
+    format: DiffOutputFormat,
+}
+
+#[derive(Args, Clone)]
+struct ResourceContractDriftArgs {
+    #[arg(long, default_value = "schema/introspection/tenants")]
+    tenants_dir: String,
+    #[arg(
+        long,
+        default_value = "schema/contracts/resource_contracts.snapshot.json"
+    )]
+    snapshot: String,
+    #[arg(
+        long,
+        default_value = "schema/introspection/endpoint.schema.snapshot.json",
+        help = "Primary schema snapshot to diff tenants against (skipped when absent)"
+    )]
+    base: String,
+    #[arg(long, value_enum, default_value_t = DiffOutputFormat::Text)]
+    format: DiffOutputFormat,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Exit non-zero on contract issues or breaking schema changes"
+    )]
+    fail_on_drift: bool,
 }
 
 #[derive(Args, Clone)]
@@ -313,6 +512,40 @@ struct ResourceContractDiffArgs {
     fail_on_breaking: bool,
 }
 
+#[derive(Args, Clone)]
+struct SchemaDiffArgs {
+    #[arg(
+        long,
+        help = "Schema snapshot or introspection response to compare from"
+    )]
+    base: String,
+    #[arg(long, help = "Schema snapshot or introspection response to compare to")]
+    target: String,
+    #[arg(long, value_enum, default_value_t = DiffOutputFormat::Text)]
+    format: DiffOutputFormat,
+    #[arg(long, value_enum, help = "Exit non-zero on breaking (or any) changes")]
+    fail_on: Option<schema_diff::FailOn>,
+}
+
+#[derive(Args, Clone)]
+struct SchemaPrintSdlArgs {
+    #[arg(
+        long,
+        default_value = "schema/introspection/endpoint.schema.snapshot.json"
+    )]
+    snapshot: String,
+    #[arg(
+        long,
+        help = "SDL file to write, or `-` for stdout (default: snapshot path with .graphql)"
+    )]
+    output: Option<String>,
+    #[arg(
+        long,
+        help = "Fail if the SDL file is out of sync instead of writing it"
+    )]
+    check: bool,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
 enum DiffOutputFormat {
     Text,
@@ -330,10 +563,31 @@ struct EndpointRefreshArgs {
         default_value = "schema/introspection/resource_contracts.endpoint.snapshot.json"
     )]
     endpoint_snapshot: String,
+    #[arg(
+        long,
+        default_value = "schema/introspection/endpoint.schema.snapshot.json"
+    )]
+    schema_snapshot: String,
     #[arg(long)]
     endpoint: Option<String>,
     #[arg(long, default_value_t = 30)]
     timeout_secs: u64,
+    #[arg(
+        long = "header",
+        value_name = "NAME: VALUE",
+        value_parser = parse_header,
+        help = "Extra header for the introspection request (repeatable), e.g. for an access proxy"
+    )]
+    headers: Vec<(String, String)>,
+    #[arg(
+        long,
+        help = "Capture only the schema snapshot as <tenants-dir>/<TENANT>.schema.snapshot.json"
+    )]
+    tenant: Option<String>,
+    #[arg(long, default_value = "schema/introspection/tenants")]
+    tenants_dir: String,
+    #[arg(long, default_value = "schema/introspection/selection_overrides.json")]
+    selection_overrides: String,
 }
 
 #[derive(Debug, Clone)]
@@ -397,6 +651,10 @@ struct EndpointResource {
     all_variables: Vec<String>,
     required_variables: Vec<String>,
     document: String,
+    /// `"source": "document"`: written from an operation document (e.g. by
+    /// `resource-contract add --document`) rather than captured by
+    /// `refresh-endpoint`, so the fields it selects are not verified yet.
+    from_document: bool,
 }
 
 type ToolResult<T> = Result<T, ToolError>;
@@ -453,6 +711,13 @@ fn run(cli: Cli) -> ToolResult<()> {
                 run_resource_contract_refresh_endpoint(&root, &args)
             }
             ResourceContractAction::Diff(args) => run_resource_contract_diff(&root, &args),
+            ResourceContractAction::Add(args) => run_resource_contract_add(&root, &args),
+            ResourceContractAction::Coverage(args) => run_resource_contract_coverage(&root, &args),
+            ResourceContractAction::Drift(args) => run_resource_contract_drift(&root, &args),
+        },
+        TopCommand::Schema { action } => match action {
+            SchemaAction::Diff(args) => run_schema_diff(&root, &args),
+            SchemaAction::PrintSdl(args) => run_schema_print_sdl(&root, &args),
         },
     }
 }
@@ -478,13 +743,11 @@ fn resource_definitions() -> &'static [ResourceDefinition] {
     RESOURCE_DEFINITIONS
 }
 
-fn endpoint_from_origin(origin: &str) -> String {
-    let normalized = origin.trim().trim_end_matches('/');
-    if normalized.ends_with("/api/v1") {
-        normalized.to_string()
-    } else {
-        format!("{normalized}/api/v1")
-    }
+fn resource_names() -> Vec<&'static str> {
+    resource_definitions()
+        .iter()
+        .map(|definition| definition.name)
+        .collect()
 }
 
 fn now_rfc3339() -> ToolResult<String> {
@@ -593,79 +856,49 @@ struct GraphqlTypeDefinition {
     enum_values: Vec<String>,
 }
 
-fn fetch_introspection_payload(
-    endpoint: &str,
-    token: &str,
-    timeout_secs: u64,
-) -> ToolResult<Value> {
-    fetch_graphql_payload(endpoint, token, INTROSPECTION_QUERY, timeout_secs)
+/// `NAME: VALUE` (or `NAME=VALUE`) from `--header`.
+fn parse_header(raw: &str) -> Result<(String, String), String> {
+    let (name, value) = raw
+        .split_once(':')
+        .or_else(|| raw.split_once('='))
+        .ok_or_else(|| format!("expected `NAME: VALUE`, got `{raw}`"))?;
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("invalid header name `{name}`"));
+    }
+    if value.contains(['\r', '\n']) {
+        return Err(format!("header `{name}` value contains a line break"));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
 }
 
-fn fetch_graphql_payload(
+/// Posts [`INTROSPECTION_QUERY`] through the client's HTTP transport, so the
+/// headers, request id, error mapping, and redaction match `kibel` itself.
+fn fetch_introspection_payload(
     endpoint: &str,
     token: &str,
-    query: &str,
+    headers: &[(String, String)],
     timeout_secs: u64,
 ) -> ToolResult<Value> {
-    let payload = json!({
-        "query": query,
-        "variables": {}
-    });
-    let payload_raw =
-        serde_json::to_string(&payload).map_err(|error| format!("json render failed: {error}"))?;
-
-    let agent = ureq::AgentBuilder::new()
-        .timeout(Duration::from_secs(timeout_secs.max(1)))
-        .build();
-    let request = agent
-        .post(endpoint)
-        .set("Content-Type", "application/json")
-        .set("Accept", GRAPHQL_ACCEPT_HEADER)
-        .set("Authorization", &format!("Bearer {token}"));
-
-    let (raw, status_code) = match request.send_string(&payload_raw) {
-        Ok(response) => {
-            let body = response
-                .into_string()
-                .map_err(|err| format!("failed to read response: {err}"))?;
-            (body, None)
-        }
-        Err(ureq::Error::Status(code, response)) => {
-            let body = response
-                .into_string()
-                .map_err(|err| format!("failed to read error response: {err}"))?;
-            (body, Some(code))
-        }
-        Err(err) => {
-            return Err((format!("request failed: {err}")).into());
-        }
-    };
-
-    let payload = serde_json::from_str::<Value>(&raw)
-        .map_err(|error| format!("failed to parse response: {error}"))?;
-    if let Some(message) = extract_graphql_error_message(&payload) {
-        if let Some(code) = status_code {
-            return Err((format!("graphql error (status {code}): {message}")).into());
-        }
-        return Err((format!("graphql error: {message}")).into());
-    }
-    Ok(payload)
-}
-
-fn extract_graphql_error_message(payload: &Value) -> Option<String> {
-    let errors = payload.get("errors")?.as_array()?;
-    if errors.is_empty() {
-        return None;
-    }
-    let mut messages = Vec::new();
-    for error in errors {
-        if let Some(message) = error.get("message").and_then(Value::as_str) {
-            messages.push(message.to_string());
-            continue;
-        }
-        messages.push(error.to_string());
-    }
-    Some(messages.join(" | "))
+    let timeout = Duration::from_secs(timeout_secs.max(1));
+    let transport = HttpTransport::for_endpoint(endpoint, token)
+        .with_timeouts(Timeouts {
+            connect: timeout,
+            read: timeout,
+            total: timeout,
+        })
+        .with_extra_headers(headers.iter().cloned())
+        .map_err(|error| format!("invalid --header: {error}"))?;
+    transport
+        .execute(&GraphqlRequest {
+            query: INTROSPECTION_QUERY.to_string(),
+            operation_name: None,
+            variables: json!({}),
+            timeout,
+            max_response_bytes: None,
+            persisted_query: false,
+        })
+        .map_err(|error| format!("introspection request failed: {error}").into())
 }
 
 fn parse_graphql_fields(
@@ -971,15 +1204,23 @@ fn terminal_field_candidates() -> &'static [&'static str] {
     ]
 }
 
-fn render_terminal_fields(type_def: &GraphqlTypeDefinition) -> String {
+fn render_terminal_fields(type_def: &GraphqlTypeDefinition, rules: &SelectionOverride) -> String {
     let mut fields = Vec::new();
     for candidate in terminal_field_candidates() {
-        if type_def.fields.iter().any(|field| field.name == *candidate) {
+        if !rules.excludes(candidate)
+            && type_def.fields.iter().any(|field| field.name == *candidate)
+        {
             fields.push((*candidate).to_string());
         }
     }
-    if fields.is_empty() && !type_def.fields.is_empty() {
-        fields.push(type_def.fields[0].name.clone());
+    if fields.is_empty() {
+        if let Some(field) = type_def
+            .fields
+            .iter()
+            .find(|field| !rules.excludes(&field.name))
+        {
+            fields.push(field.name.clone());
+        }
     }
     if fields.is_empty() {
         fields.push("__typename".to_string());
@@ -1031,8 +1272,9 @@ fn render_selection_set(
     type_map: &HashMap<String, GraphqlTypeDefinition>,
     stack: &mut Vec<String>,
     depth: usize,
-    max_depth: usize,
+    rules: &SelectionOverride,
 ) -> Option<String> {
+    let max_depth = rules.max_depth();
     let named = resolve_named_type(type_ref)?;
     let type_def = type_map.get(named)?;
     match type_def.kind.as_str() {
@@ -1044,7 +1286,7 @@ fn render_selection_set(
             }
             for possible_type in &type_def.possible_types {
                 if let Some(possible_def) = type_map.get(possible_type) {
-                    let inner = render_terminal_fields(possible_def);
+                    let inner = render_terminal_fields(possible_def, rules);
                     fragments.push(format!(
                         "... on {possible_type} {{\n{}\n}}",
                         indent_block(&inner, 2)
@@ -1055,12 +1297,12 @@ fn render_selection_set(
         }
         _ => {
             if depth >= max_depth || stack.iter().any(|entry| entry == named) {
-                return Some(render_terminal_fields(type_def));
+                return Some(render_terminal_fields(type_def, rules));
             }
             stack.push(named.to_string());
             let mut selected_fields = Vec::new();
             for field in &type_def.fields {
-                if field.name.starts_with("__") {
+                if field.name.starts_with("__") || rules.excludes(&field.name) {
                     continue;
                 }
                 let required_args = match render_required_args(&field.args, type_map) {
@@ -1073,7 +1315,7 @@ fn render_selection_set(
                     format!("{}({required_args})", field.name)
                 };
                 if let Some(child_selection) =
-                    render_selection_set(&field.type_ref, type_map, stack, depth + 1, max_depth)
+                    render_selection_set(&field.type_ref, type_map, stack, depth + 1, rules)
                 {
                     selected_fields.push(format!(
                         "{field_head} {{\n{}\n}}",
@@ -1085,7 +1327,7 @@ fn render_selection_set(
             }
             stack.pop();
             if selected_fields.is_empty() {
-                Some(render_terminal_fields(type_def))
+                Some(render_terminal_fields(type_def, rules))
             } else {
                 Some(selected_fields.join("\n"))
             }
@@ -1103,9 +1345,10 @@ fn indent_block(value: &str, spaces: usize) -> String {
 }
 
 fn build_operation_document(
-    definition: &ResourceDefinition,
+    definition: &ResourceSpec<'_>,
     field_spec: &GraphqlFieldSpec,
     type_map: &HashMap<String, GraphqlTypeDefinition>,
+    rules: &SelectionOverride,
 ) -> Option<String> {
     let mut variable_defs = Vec::new();
     let mut call_args = Vec::new();
@@ -1120,8 +1363,10 @@ fn build_operation_document(
     } else {
         format!("{}({call_args_raw})", definition.field)
     };
-    let mut stack = Vec::new();
-    let selection = render_selection_set(&field_spec.return_type, type_map, &mut stack, 0, 8);
+    let selection = match &rules.selection {
+        Some(pinned) => Some(selection::pinned_selection_block(pinned)),
+        None => render_selection_set(&field_spec.return_type, type_map, &mut Vec::new(), 0, rules),
+    };
     let root_block = if let Some(selection) = selection {
         format!("{field_head} {{\n{}\n  }}", indent_block(&selection, 4))
     } else {
@@ -1535,6 +1780,7 @@ fn build_endpoint_snapshot_from_introspection(
     origin: &str,
     endpoint: &str,
     captured_at: &str,
+    overrides: &SelectionOverrides,
 ) -> ToolResult<Value> {
     let query_fields = parse_graphql_fields(payload, "query")?;
     let mutation_fields = parse_graphql_fields(payload, "mutation")?;
@@ -1548,40 +1794,12 @@ fn build_endpoint_snapshot_from_introspection(
             "mutation" => &mutation_fields,
             other => return Err((format!("unsupported kind: {other}")).into()),
         };
-        let field_spec = fields
-            .get(definition.field)
-            .ok_or_else(|| format!("missing graphql field: {}", definition.field))?;
-
-        let mut all_variables = Vec::new();
-        let mut required_variables = Vec::new();
-        let mut seen = HashSet::new();
-        for arg in &field_spec.args {
-            if !seen.insert(arg.name.clone()) {
-                continue;
-            }
-            all_variables.push(arg.name.clone());
-            if arg.required {
-                required_variables.push(arg.name.clone());
-            }
-        }
-        let document =
-            build_operation_document(definition, field_spec, &type_map).ok_or_else(|| {
-                format!(
-                    "failed to build operation document for `{}` from endpoint introspection",
-                    definition.name
-                )
-            })?;
-
-        resources.push(json!({
-            "name": definition.name,
-            "kind": definition.kind,
-            "field": definition.field,
-            "operation": to_pascal_case(definition.name),
-            "client_method": definition.client_method,
-            "all_variables": all_variables,
-            "required_variables": required_variables,
-            "document": document,
-        }));
+        resources.push(build_endpoint_resource(
+            &ResourceSpec::from(definition),
+            fields,
+            &type_map,
+            &overrides.get(definition.name),
+        )?);
     }
 
     Ok(json!({
@@ -1601,19 +1819,64 @@ fn build_endpoint_snapshot_from_introspection(
     }))
 }
 
-#[allow(clippy::too_many_lines)]
-fn load_endpoint_snapshot(path: &Path) -> ToolResult<EndpointSnapshot> {
+/// Builds the endpoint snapshot entry of one resource from the introspected
+/// root fields of its kind.
+fn build_endpoint_resource(
+    definition: &ResourceSpec<'_>,
+    fields: &HashMap<String, GraphqlFieldSpec>,
+    type_map: &HashMap<String, GraphqlTypeDefinition>,
+    rules: &SelectionOverride,
+) -> ToolResult<Value> {
+    let field_spec = fields
+        .get(definition.field)
+        .ok_or_else(|| format!("missing graphql field: {}", definition.field))?;
+
+    let mut all_variables = Vec::new();
+    let mut required_variables = Vec::new();
+    let mut seen = HashSet::new();
+    for arg in &field_spec.args {
+        if !seen.insert(arg.name.clone()) {
+            continue;
+        }
+        all_variables.push(arg.name.clone());
+        if arg.required {
+            required_variables.push(arg.name.clone());
+        }
+    }
+    let document =
+        build_operation_document(definition, field_spec, type_map, rules).ok_or_else(|| {
+            format!(
+                "failed to build operation document for `{}` from endpoint introspection",
+                definition.name
+            )
+        })?;
+
+    Ok(json!({
+        "name": definition.name,
+        "kind": definition.kind,
+        "field": definition.field,
+        "operation": to_pascal_case(definition.name),
+        "client_method": definition.client_method,
+        "all_variables": all_variables,
+        "required_variables": required_variables,
+        "document": document,
+    }))
+}
+
+fn load_endpoint_snapshot(path: &Path, names: &[&str]) -> ToolResult<EndpointSnapshot> {
     let payload = read_json(path)?;
-    parse_endpoint_snapshot(&payload)
+    parse_endpoint_snapshot(&payload, names)
 }
 
-fn parse_endpoint_snapshot(payload: &Value) -> ToolResult<EndpointSnapshot> {
+/// Parses an endpoint snapshot that must cover exactly the resources in
+/// `names`, usually [`resource_names`].
+fn parse_endpoint_snapshot(payload: &Value, names: &[&str]) -> ToolResult<EndpointSnapshot> {
     let object = payload
         .as_object()
         .ok_or_else(|| "endpoint snapshot must be an object".to_string())?;
     let resources_array = endpoint_snapshot_resources_array(object)?;
     let resources = parse_endpoint_resources(resources_array)?;
-    validate_endpoint_resource_coverage(&resources)?;
+    validate_endpoint_resource_coverage(&resources, names)?;
     Ok(EndpointSnapshot {
         captured_at: endpoint_snapshot_meta_value(object, "captured_at"),
         origin: endpoint_snapshot_meta_value(object, "origin"),
@@ -1692,6 +1955,16 @@ fn parse_endpoint_resource(item: &Value, index: usize) -> ToolResult<EndpointRes
     validate_required_subset(&name, &all_variables, &required_variables)?;
 
     let document = parse_endpoint_resource_document(object, &name)?;
+    let from_document = match object.get("source").and_then(Value::as_str) {
+        None | Some("introspection") => false,
+        Some("document") => true,
+        Some(other) => {
+            return Err((format!(
+                "resource `{name}` has invalid source: {other} (expected introspection or document)"
+            ))
+            .into())
+        }
+    };
     Ok(EndpointResource {
         name,
         kind,
@@ -1701,6 +1974,7 @@ fn parse_endpoint_resource(item: &Value, index: usize) -> ToolResult<EndpointRes
         all_variables,
         required_variables,
         document,
+        from_document,
     })
 }
 
@@ -1743,20 +2017,18 @@ fn parse_endpoint_resource_document(
 
 fn validate_endpoint_resource_coverage(
     resources: &HashMap<String, EndpointResource>,
+    names: &[&str],
 ) -> ToolResult<()> {
-    let missing = resource_definitions()
+    let missing = names
         .iter()
-        .filter(|definition| !resources.contains_key(definition.name))
-        .map(|definition| definition.name.to_string())
+        .filter(|name| !resources.contains_key(**name))
+        .map(|name| (*name).to_string())
         .collect::<Vec<_>>();
     if !missing.is_empty() {
         return Err((format!("endpoint snapshot missing resources: {missing:?}")).into());
     }
 
-    let expected = resource_definitions()
-        .iter()
-        .map(|definition| definition.name)
-        .collect::<BTreeSet<_>>();
+    let expected = names.iter().copied().collect::<BTreeSet<_>>();
     let unexpected = resources
         .keys()
         .filter(|name| !expected.contains(name.as_str()))
@@ -1780,6 +2052,7 @@ fn build_resource_snapshot_value(
     root: &Path,
     endpoint_snapshot_path: &Path,
     endpoint_payload: &EndpointSnapshot,
+    names: &[&str],
 ) -> ToolResult<Value> {
     let endpoint_snapshot_rel = endpoint_snapshot_path
         .strip_prefix(root)
@@ -1794,11 +2067,11 @@ fn build_resource_snapshot_value(
         .to_string();
 
     let mut rendered_resources = Vec::new();
-    for definition in resource_definitions() {
+    for name in names {
         let item = endpoint_payload
             .resources
-            .get(definition.name)
-            .ok_or_else(|| format!("endpoint snapshot missing resource `{}`", definition.name))?;
+            .get(*name)
+            .ok_or_else(|| format!("endpoint snapshot missing resource `{name}`"))?;
         rendered_resources.push(json!({
             "name": item.name,
             "kind": item.kind,
@@ -2307,6 +2580,22 @@ fn render_resource_module(snapshot: &ResourceModuleSnapshot) -> String {
     rendered
 }
 
+/// Typed response structs need field types, so they are only generated once
+/// an endpoint schema snapshot has been captured. The source is unformatted.
+fn render_response_types(
+    root: &Path,
+    args: &ResourceContractArgs,
+    module_snapshot: &ResourceModuleSnapshot,
+) -> ToolResult<String> {
+    let schema_snapshot_path = resolve_path(root, &args.schema_snapshot);
+    let schema = if schema_snapshot_path.exists() {
+        Some(schema_diff::load_schema_snapshot(&schema_snapshot_path)?)
+    } else {
+        None
+    };
+    response_codegen::render_response_types_module(&module_snapshot.resources, schema.as_ref())
+}
+
 fn rustfmt_source(source: &str) -> ToolResult<String> {
     let temp_dir =
         tempfile::tempdir().map_err(|error| format!("failed to create temp dir: {error}"))?;
@@ -2323,14 +2612,59 @@ fn rustfmt_source(source: &str) -> ToolResult<String> {
         .map_err(|error| format!("failed to read rustfmt output: {error}"))?)
 }
 
-fn run_resource_contract_check(root: &Path, args: &ResourceContractArgs) -> ToolResult<()> {
+/// Whether the checked-in `actual` module is what `write` renders from
+/// `expected`, unformatted. Without `use_rustfmt` both sides are parsed and
+/// printed with prettyplease, so the check needs no toolchain component and
+/// ignores layout and plain `//` comments; doc comments still count.
+fn generated_source_matches(expected: &str, actual: &str, use_rustfmt: bool) -> ToolResult<bool> {
+    if use_rustfmt {
+        return Ok(rustfmt_source(expected)? == actual);
+    }
+    let expected = syn::parse_file(expected)
+        .map_err(|error| format!("generated module does not parse: {error}"))?;
+    let Ok(actual) = syn::parse_file(actual) else {
+        return Ok(false);
+    };
+    Ok(prettyplease::unparse(&expected) == prettyplease::unparse(&actual))
+}
+
+/// The manifest external tooling reads, built from the snapshot so it is
+/// current before the generated module is compiled in.
+fn render_contract_manifest(snapshot: &ResourceModuleSnapshot) -> ToolResult<Value> {
+    let manifest = ContractManifest {
+        contract_version: snapshot.schema_contract_version,
+        upstream_commit: snapshot.source_upstream_commit.clone(),
+        operations: snapshot
+            .resources
+            .iter()
+            .map(|resource| {
+                ManifestOperation::from_parts(
+                    &resource.name,
+                    &resource.kind,
+                    &resource.operation,
+                    &resource.client_method,
+                    &resource.all_variables,
+                    &resource.required_variables,
+                    &resource.document,
+                )
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_value(manifest)
+        .map_err(|error| format!("failed to render contract manifest: {error}"))?)
+}
+
+fn run_resource_contract_check(root: &Path, check: &ResourceContractCheckArgs) -> ToolResult<()> {
+    let args = &check.contract;
     let endpoint_snapshot_path = resolve_path(root, &args.endpoint_snapshot);
     let snapshot_path = resolve_path(root, &args.snapshot);
     let generated_path = resolve_path(root, &args.generated);
 
-    let endpoint_snapshot = load_endpoint_snapshot(&endpoint_snapshot_path)?;
+    let names = resource_names();
+    let endpoint_snapshot = load_endpoint_snapshot(&endpoint_snapshot_path, &names)?;
+    check_selection_overrides(root, args, &endpoint_snapshot, &names)?;
     let expected_snapshot_value =
-        build_resource_snapshot_value(root, &endpoint_snapshot_path, &endpoint_snapshot)?;
+        build_resource_snapshot_value(root, &endpoint_snapshot_path, &endpoint_snapshot, &names)?;
     let expected_snapshot = normalize_resource_snapshot(&expected_snapshot_value)?;
     let actual_snapshot_value = read_json(&snapshot_path)?;
     let actual_snapshot = normalize_resource_snapshot(&actual_snapshot_value)?;
@@ -2343,28 +2677,121 @@ fn run_resource_contract_check(root: &Path, args: &ResourceContractArgs) -> Tool
     }
 
     let module_snapshot = load_resource_module_snapshot(&snapshot_path)?;
-    let expected_generated = rustfmt_source(&render_resource_module(&module_snapshot))?;
+    let expected_generated = render_resource_module(&module_snapshot);
     let actual_generated = fs::read_to_string(&generated_path)
         .map_err(|error| format!("failed to read {}: {error}", generated_path.display()))?;
-    if actual_generated != expected_generated {
+    if !generated_source_matches(&expected_generated, &actual_generated, check.use_rustfmt)? {
         return Err(("generated resource contract module is stale. run:\n\
              cargo run -p kibel-tools -- resource-contract write"
             .to_string())
         .into());
     }
 
+    let response_types_path = resolve_path(root, &args.response_types);
+    let expected_response_types = render_response_types(root, args, &module_snapshot)?;
+    let actual_response_types = fs::read_to_string(&response_types_path)
+        .map_err(|error| format!("failed to read {}: {error}", response_types_path.display()))?;
+    if !generated_source_matches(
+        &expected_response_types,
+        &actual_response_types,
+        check.use_rustfmt,
+    )? {
+        return Err(("generated response types module is stale. run:\n\
+             cargo run -p kibel-tools -- resource-contract write"
+            .to_string())
+        .into());
+    }
+
+    let manifest_path = resolve_path(root, &args.manifest);
+    if read_json(&manifest_path)? != render_contract_manifest(&module_snapshot)? {
+        return Err(("resource contract manifest is stale. run:\n\
+             cargo run -p kibel-tools -- resource-contract write"
+            .to_string())
+        .into());
+    }
+
     println!("resource contract check: ok");
+    let mut unverified = endpoint_snapshot
+        .resources
+        .values()
+        .filter(|resource| resource.from_document)
+        .map(|resource| resource.name.as_str())
+        .collect::<Vec<_>>();
+    if !unverified.is_empty() {
+        unverified.sort_unstable();
+        println!(
+            "  not captured from introspection (run refresh-endpoint to verify): {}",
+            unverified.join(", ")
+        );
+    }
     Ok(())
 }
 
+/// Fails when a document in the endpoint snapshot no longer honors
+/// `selection_overrides.json`, e.g. after the file was edited without
+/// refreshing the snapshot.
+fn check_selection_overrides(
+    root: &Path,
+    args: &ResourceContractArgs,
+    endpoint_snapshot: &EndpointSnapshot,
+    names: &[&str],
+) -> ToolResult<()> {
+    let overrides =
+        selection::load_selection_overrides(&resolve_path(root, &args.selection_overrides))?;
+    let unknown = overrides
+        .resources
+        .keys()
+        .filter(|name| !names.contains(&name.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err((format!("selection overrides for unknown resources: {unknown:?}")).into());
+    }
+
+    let mut violations = Vec::new();
+    for (name, rules) in &overrides.resources {
+        let resource = endpoint_snapshot
+            .resources
+            .get(name)
+            .ok_or_else(|| format!("endpoint snapshot missing resource `{name}`"))?;
+        violations.extend(selection::selection_violations(
+            name,
+            &resource.document,
+            rules,
+        )?);
+    }
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err((format!(
+        "endpoint snapshot violates selection overrides:\n  - {}\nrun:\n \
+         cargo run -p kibel-tools -- resource-contract refresh-endpoint",
+        violations.join("\n  - ")
+    ))
+    .into())
+}
+
 fn run_resource_contract_write(root: &Path, args: &ResourceContractArgs) -> ToolResult<()> {
+    write_resource_contract_outputs(root, args, &resource_names())?;
+    println!("resource contract check: ok (written)");
+    Ok(())
+}
+
+/// Regenerates the contract snapshot, the contract module, the response
+/// types, and the contract manifest for the resources in `names`, in that
+/// order.
+fn write_resource_contract_outputs(
+    root: &Path,
+    args: &ResourceContractArgs,
+    names: &[&str],
+) -> ToolResult<()> {
     let endpoint_snapshot_path = resolve_path(root, &args.endpoint_snapshot);
     let snapshot_path = resolve_path(root, &args.snapshot);
     let generated_path = resolve_path(root, &args.generated);
 
-    let endpoint_snapshot = load_endpoint_snapshot(&endpoint_snapshot_path)?;
+    let endpoint_snapshot = load_endpoint_snapshot(&endpoint_snapshot_path, names)?;
     let snapshot_value =
-        build_resource_snapshot_value(root, &endpoint_snapshot_path, &endpoint_snapshot)?;
+        build_resource_snapshot_value(root, &endpoint_snapshot_path, &endpoint_snapshot, names)?;
     write_json_pretty(&snapshot_path, &snapshot_value)?;
 
     let module_snapshot = load_resource_module_snapshot(&snapshot_path)?;
@@ -2376,7 +2803,165 @@ fn run_resource_contract_write(root: &Path, args: &ResourceContractArgs) -> Tool
     fs::write(&generated_path, rendered)
         .map_err(|error| format!("failed to write {}: {error}", generated_path.display()))?;
 
-    println!("resource contract check: ok (written)");
+    let response_types_path = resolve_path(root, &args.response_types);
+    fs::write(
+        &response_types_path,
+        rustfmt_source(&render_response_types(root, args, &module_snapshot)?)?,
+    )
+    .map_err(|error| format!("failed to write {}: {error}", response_types_path.display()))?;
+
+    write_json_pretty(
+        &resolve_path(root, &args.manifest),
+        &render_contract_manifest(&module_snapshot)?,
+    )?;
+    Ok(())
+}
+
+fn run_resource_contract_add(root: &Path, args: &ResourceContractAddArgs) -> ToolResult<()> {
+    let name = args.name.trim();
+    let field = args.field.trim();
+    if name.is_empty() || field.is_empty() {
+        return Err(("--name and --field must not be empty".to_string()).into());
+    }
+    let client_method = args
+        .client_method
+        .clone()
+        .unwrap_or_else(|| scaffold::snake_case(name));
+    let spec = ResourceSpec {
+        name,
+        kind: &args.kind,
+        field,
+        client_method: &client_method,
+    };
+    let mut names = resource_names();
+    if names.contains(&name) {
+        return Err((format!("resource `{name}` is already defined")).into());
+    }
+    names.push(name);
+
+    let resource = if let Some(document) = &args.document {
+        let document_path = resolve_path(root, document);
+        let document = fs::read_to_string(&document_path)
+            .map_err(|error| format!("failed to read {}: {error}", document_path.display()))?;
+        scaffold::endpoint_resource_from_document(&spec, &document)?
+    } else {
+        let schema_snapshot_path = resolve_path(root, &args.contract.schema_snapshot);
+        if !schema_snapshot_path.exists() {
+            return Err((format!(
+                "{} not found. run:\n \
+                 cargo run -p kibel-tools -- resource-contract refresh-endpoint\n\
+                 or pass --document with the operation to register",
+                schema_snapshot_path.display()
+            ))
+            .into());
+        }
+        let payload = json!({ "data": { "__schema": schema_diff::load_schema_snapshot(&schema_snapshot_path)? } });
+        let overrides = selection::load_selection_overrides(&resolve_path(
+            root,
+            &args.contract.selection_overrides,
+        ))?;
+        build_endpoint_resource(
+            &spec,
+            &parse_graphql_fields(&payload, &args.kind)?,
+            &parse_schema_types(&payload)?,
+            &overrides.get(name),
+        )?
+    };
+    let document = resource["document"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    let endpoint_snapshot_path = resolve_path(root, &args.contract.endpoint_snapshot);
+    let mut endpoint_snapshot = read_json(&endpoint_snapshot_path)?;
+    let resources = endpoint_snapshot
+        .get_mut("resources")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| "endpoint snapshot must contain array `resources`".to_string())?;
+    resources.push(resource);
+    let resource_count = resources.len();
+    endpoint_snapshot["resource_count"] = json!(resource_count);
+    parse_endpoint_snapshot(&endpoint_snapshot, &names)?;
+
+    let definitions_path = resolve_path(root, &args.definitions_source);
+    let source = fs::read_to_string(&definitions_path)
+        .map_err(|error| format!("failed to read {}: {error}", definitions_path.display()))?;
+    let source = scaffold::insert_resource_definition(&source, &spec)?;
+
+    write_json_pretty(&endpoint_snapshot_path, &endpoint_snapshot)?;
+    write_resource_contract_outputs(root, &args.contract, &names)?;
+    fs::write(&definitions_path, source)
+        .map_err(|error| format!("failed to write {}: {error}", definitions_path.display()))?;
+    println!("resource contract add: `{name}` registered");
+
+    let scaffold_dir = resolve_path(root, &args.scaffold_dir).join(name);
+    fs::create_dir_all(&scaffold_dir)
+        .map_err(|error| format!("failed to create {}: {error}", scaffold_dir.display()))?;
+    for file in scaffold::render_scaffold_files(&spec, &document)? {
+        let path = scaffold_dir.join(file.file_name);
+        fs::write(&path, file.contents)
+            .map_err(|error| format!("failed to write {}: {error}", path.display()))?;
+        println!("  stub: {}", path.display());
+    }
+    println!(
+        "next: paste the stubs into kibel-client/kibel/kibel-mock, then run:\n \
+         cargo run -p kibel-tools -- resource-contract check"
+    );
+    Ok(())
+}
+
+fn run_resource_contract_coverage(
+    root: &Path,
+    args: &ResourceContractCoverageArgs,
+) -> ToolResult<()> {
+    let schema_snapshot_path = resolve_path(root, &args.schema_snapshot);
+    if !schema_snapshot_path.exists() {
+        return Err((format!(
+            "{} not found. run:\n \
+             cargo run -p kibel-tools -- resource-contract refresh-endpoint",
+            schema_snapshot_path.display()
+        ))
+        .into());
+    }
+    let schema = schema_diff::load_schema_snapshot(&schema_snapshot_path)?;
+    let report = coverage::compute_coverage(&schema, resource_definitions())?;
+    coverage::print_coverage(&report, args.format)
+}
+
+fn run_resource_contract_drift(root: &Path, args: &ResourceContractDriftArgs) -> ToolResult<()> {
+    let tenants = drift::list_tenant_snapshots(&resolve_path(root, &args.tenants_dir))?;
+    if tenants.is_empty() {
+        return Err((format!(
+            "no tenant snapshots in {}. run:\n \
+             cargo run -p kibel-tools -- resource-contract refresh-endpoint --tenant <NAME>",
+            args.tenants_dir
+        ))
+        .into());
+    }
+    let module_snapshot = load_resource_module_snapshot(&resolve_path(root, &args.snapshot))?;
+    let base_path = resolve_path(root, &args.base);
+    let base = if base_path.exists() {
+        Some(schema_diff::load_schema_snapshot(&base_path)?)
+    } else {
+        None
+    };
+
+    let mut report = Vec::new();
+    for (tenant, path) in &tenants {
+        report.push(drift::compute_tenant_drift(
+            tenant,
+            path,
+            base.as_ref(),
+            &module_snapshot.resources,
+            resource_definitions(),
+        )?);
+    }
+    drift::print_drift(&report, args.format)?;
+
+    let drifting = report.iter().filter(|item| item.is_drifting()).count();
+    if args.fail_on_drift && drifting > 0 {
+        return Err((format!("resource contract drift detected in {drifting} tenant(s)")).into());
+    }
     Ok(())
 }
 
@@ -2397,22 +2982,71 @@ fn run_resource_contract_refresh_endpoint(
         .endpoint
         .clone()
         .unwrap_or_else(|| endpoint_from_origin(origin));
-    let payload = fetch_introspection_payload(&endpoint, token, args.timeout_secs)?;
+    let payload = fetch_introspection_payload(&endpoint, token, &args.headers, args.timeout_secs)?;
     let captured_at = now_rfc3339()?;
+
+    if let Some(tenant) = &args.tenant {
+        let tenants_dir = resolve_path(root, &args.tenants_dir);
+        let schema_snapshot_path = drift::tenant_snapshot_path(&tenants_dir, tenant.trim())?;
+        fs::create_dir_all(&tenants_dir)
+            .map_err(|error| format!("failed to create {}: {error}", tenants_dir.display()))?;
+        let schema_snapshot =
+            build_schema_snapshot_from_introspection(&payload, origin, &endpoint, &captured_at)?;
+        write_json_pretty(&schema_snapshot_path, &schema_snapshot)?;
+        let sdl_path = sdl::sdl_path_for(&schema_snapshot_path);
+        fs::write(&sdl_path, sdl::render_sdl(&schema_snapshot["__schema"])?)
+            .map_err(|error| format!("failed to write {}: {error}", sdl_path.display()))?;
+        println!("tenant `{tenant}` schema snapshot refresh: ok (written)");
+        return Ok(());
+    }
+    let overrides =
+        selection::load_selection_overrides(&resolve_path(root, &args.selection_overrides))?;
     let snapshot_value = build_endpoint_snapshot_from_introspection(
         resource_definitions(),
         &payload,
         origin,
         &endpoint,
         &captured_at,
+        &overrides,
     )?;
 
     let endpoint_snapshot_path = resolve_path(root, &args.endpoint_snapshot);
     write_json_pretty(&endpoint_snapshot_path, &snapshot_value)?;
     println!("endpoint snapshot refresh: ok (written)");
+
+    let schema_snapshot_path = resolve_path(root, &args.schema_snapshot);
+    let schema_snapshot =
+        build_schema_snapshot_from_introspection(&payload, origin, &endpoint, &captured_at)?;
+    write_json_pretty(&schema_snapshot_path, &schema_snapshot)?;
+    println!("schema snapshot refresh: ok (written)");
+
+    let sdl_path = sdl::sdl_path_for(&schema_snapshot_path);
+    fs::write(&sdl_path, sdl::render_sdl(&schema_snapshot["__schema"])?)
+        .map_err(|error| format!("failed to write {}: {error}", sdl_path.display()))?;
+    println!("schema sdl refresh: ok (written)");
     Ok(())
 }
 
+/// Keeps the raw `__schema` introspection so `kibel graphql run
+/// --schema-snapshot` can validate ad-hoc documents offline.
+fn build_schema_snapshot_from_introspection(
+    payload: &Value,
+    origin: &str,
+    endpoint: &str,
+    captured_at: &str,
+) -> ToolResult<Value> {
+    let schema = payload
+        .pointer("/data/__schema")
+        .filter(|schema| schema.get("types").is_some_and(Value::is_array))
+        .ok_or_else(|| "introspection missing /data/__schema/types array".to_string())?;
+    Ok(json!({
+        "captured_at": captured_at,
+        "origin": origin,
+        "endpoint": endpoint,
+        "__schema": schema,
+    }))
+}
+
 fn load_normalized_snapshot_from_path(path: &Path) -> ToolResult<NormalizedSnapshot> {
     let payload = read_json(path)?;
     normalize_resource_snapshot(&payload)
@@ -2465,5 +3099,52 @@ fn run_resource_contract_diff(root: &Path, args: &ResourceContractDiffArgs) -> T
     Ok(())
 }
 
+fn run_schema_diff(root: &Path, args: &SchemaDiffArgs) -> ToolResult<()> {
+    let base = schema_diff::load_schema_snapshot(&resolve_path(root, &args.base))?;
+    let target = schema_diff::load_schema_snapshot(&resolve_path(root, &args.target))?;
+    let diff = schema_diff::compute_schema_diff(&base, &target)?;
+    schema_diff::print_schema_diff(&diff, args.format)?;
+
+    let failing = match args.fail_on {
+        Some(schema_diff::FailOn::Breaking) => diff.breaking.len(),
+        Some(schema_diff::FailOn::Any) => diff.breaking.len() + diff.non_breaking.len(),
+        None => 0,
+    };
+    if failing > 0 {
+        return Err((format!("schema diff detected {failing} failing change(s)")).into());
+    }
+    Ok(())
+}
+
+fn run_schema_print_sdl(root: &Path, args: &SchemaPrintSdlArgs) -> ToolResult<()> {
+    let snapshot_path = resolve_path(root, &args.snapshot);
+    let rendered = sdl::render_sdl(&schema_diff::load_schema_snapshot(&snapshot_path)?)?;
+    let output_path = match args.output.as_deref() {
+        Some("-") => {
+            print!("{rendered}");
+            return Ok(());
+        }
+        Some(path) => resolve_path(root, path),
+        None => sdl::sdl_path_for(&snapshot_path),
+    };
+
+    if args.check {
+        let actual = fs::read_to_string(&output_path).unwrap_or_default();
+        if actual != rendered {
+            return Err(("schema SDL is stale. run:\n\
+                 cargo run -p kibel-tools -- schema print-sdl"
+                .to_string())
+            .into());
+        }
+        println!("schema sdl check: ok");
+        return Ok(());
+    }
+
+    fs::write(&output_path, rendered)
+        .map_err(|error| format!("failed to write {}: {error}", output_path.display()))?;
+    println!("schema sdl: ok (written {})", output_path.display());
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests;
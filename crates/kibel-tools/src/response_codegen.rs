@@ -0,0 +1,321 @@
+use super::scaffold::snake_case;
+use super::{
+    parse_graphql_fields, parse_schema_types, resolve_named_type, to_pascal_case,
+    GraphqlTypeDefinition, GraphqlTypeRef, NormalizedResource, ToolResult,
+};
+use async_graphql_parser::types::{
+    DocumentOperations, FragmentDefinition, OperationType, Selection, SelectionSet,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// A field of a generated struct, merged across the inline fragments and
+/// fragment spreads that select it.
+struct CollectedField<'a> {
+    key: String,
+    type_ref: GraphqlTypeRef,
+    /// Only selected under a type condition, so absent for other types.
+    conditional: bool,
+    selections: Vec<(&'a SelectionSet, String)>,
+}
+
+struct Generator<'a> {
+    types: &'a HashMap<String, GraphqlTypeDefinition>,
+    fragments: HashMap<String, &'a FragmentDefinition>,
+    operation: &'a str,
+    structs: Vec<String>,
+}
+
+/// Renders `generated_response_types.rs`: one serde struct tree per trusted
+/// operation, rooted at `<Operation>Data`, derived from the operation
+/// document and the endpoint schema snapshot. Without a schema snapshot the
+/// module only carries the header, since field types cannot be known.
+pub(crate) fn render_response_types_module(
+    resources: &[NormalizedResource],
+    schema: Option<&Value>,
+) -> ToolResult<String> {
+    let mut lines = vec![
+        "// This file is generated by crates/kibel-tools.".to_string(),
+        "// Do not edit by hand.".to_string(),
+        String::new(),
+    ];
+    let Some(schema) = schema else {
+        lines.push(
+            "// No endpoint schema snapshot was available; run `kibel-tools resource-contract \
+             refresh-endpoint` and `resource-contract write` to generate response types."
+                .to_string(),
+        );
+        lines.push(String::new());
+        return Ok(lines.join("\n"));
+    };
+
+    let payload = json!({ "data": { "__schema": schema } });
+    let types = parse_schema_types(&payload)?;
+    let query_fields = parse_graphql_fields(&payload, "query")?;
+    let mutation_fields = parse_graphql_fields(&payload, "mutation")?;
+
+    lines.push("use serde::{Deserialize, Serialize};".to_string());
+    for resource in resources {
+        let document = async_graphql_parser::parse_query(&resource.document).map_err(|error| {
+            format!(
+                "resource `{}` document is not valid GraphQL: {error}",
+                resource.name
+            )
+        })?;
+        let operation = match &document.operations {
+            DocumentOperations::Single(operation) => &operation.node,
+            DocumentOperations::Multiple(operations) => {
+                &operations
+                    .values()
+                    .next()
+                    .ok_or_else(|| format!("resource `{}` has no operation", resource.name))?
+                    .node
+            }
+        };
+        let root_fields = match operation.ty {
+            OperationType::Mutation => &mutation_fields,
+            OperationType::Query | OperationType::Subscription => &query_fields,
+        };
+
+        let mut generator = Generator {
+            types: &types,
+            fragments: document
+                .fragments
+                .iter()
+                .map(|(name, fragment)| (name.to_string(), &fragment.node))
+                .collect(),
+            operation: &resource.operation,
+            structs: Vec::new(),
+        };
+        let mut fields = Vec::new();
+        for selection in &operation.selection_set.node.items {
+            let Selection::Field(field) = &selection.node else {
+                return Err(format!(
+                    "resource `{}` must select root fields directly",
+                    resource.name
+                )
+                .into());
+            };
+            let field = &field.node;
+            let name = field.name.node.as_str();
+            let spec = root_fields.get(name).ok_or_else(|| {
+                format!(
+                    "resource `{}` selects unknown root field `{name}`",
+                    resource.name
+                )
+            })?;
+            let mut selections = Vec::new();
+            if let Some(target) = resolve_named_type(&spec.return_type) {
+                if !field.selection_set.node.items.is_empty() {
+                    selections.push((&field.selection_set.node, target.to_string()));
+                }
+            }
+            fields.push(CollectedField {
+                key: field.response_key().node.to_string(),
+                type_ref: spec.return_type.clone(),
+                conditional: false,
+                selections,
+            });
+        }
+        let doc = format!(
+            "/// Response `data` of the `{}` trusted operation (`KibelClient::{}`).",
+            resource.operation, resource.client_method
+        );
+        let root = format!("{}Data", resource.operation);
+        generator.emit_struct(&root, &resource.operation, &doc, fields)?;
+        lines.extend(generator.structs);
+    }
+    lines.push(String::new());
+    Ok(lines.join("\n"))
+}
+
+impl<'a> Generator<'a> {
+    /// Emits struct `name` and, depth first, one struct per nested selection
+    /// named `<prefix><Key>`.
+    fn emit_struct(
+        &mut self,
+        name: &str,
+        prefix: &str,
+        doc: &str,
+        fields: Vec<CollectedField<'a>>,
+    ) -> ToolResult<()> {
+        let mut body = vec![
+            String::new(),
+            doc.to_string(),
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]".to_string(),
+            format!("pub struct {name} {{"),
+        ];
+        let mut children = Vec::new();
+        for field in fields {
+            let inner = if field.selections.is_empty() {
+                scalar_rust_type(resolve_named_type(&field.type_ref).unwrap_or_default())
+                    .to_string()
+            } else {
+                let child = format!("{prefix}{}", to_pascal_case(&field.key));
+                let child_fields = self.collect(&field.selections)?;
+                children.push((child.clone(), field.key.clone(), child_fields));
+                child
+            };
+            let mut rust_type = wrap_type(&field.type_ref, &inner);
+            if field.conditional && !rust_type.starts_with("Option<") {
+                rust_type = format!("Option<{rust_type}>");
+            }
+            let ident = snake_case(&field.key);
+            if ident != field.key {
+                body.push(format!("    #[serde(rename = \"{}\")]", field.key));
+            }
+            body.push(format!("    pub {}: {rust_type},", rust_ident(&ident)));
+        }
+        body.push("}".to_string());
+        self.structs.extend(body);
+
+        for (child, key, child_fields) in children {
+            let doc = format!("/// `{key}` in [`{name}`].");
+            self.emit_struct(&child, &child, &doc, child_fields)?;
+        }
+        Ok(())
+    }
+
+    /// Flattens fragments and merges same-keyed fields of `selections`, each
+    /// read against its parent type name.
+    fn collect(
+        &self,
+        selections: &[(&'a SelectionSet, String)],
+    ) -> ToolResult<Vec<CollectedField<'a>>> {
+        let mut fields: Vec<CollectedField<'a>> = Vec::new();
+        for (selection_set, parent) in selections {
+            self.collect_into(selection_set, parent, false, &mut fields)?;
+        }
+        Ok(fields)
+    }
+
+    fn collect_into(
+        &self,
+        selection_set: &'a SelectionSet,
+        parent: &str,
+        conditional: bool,
+        fields: &mut Vec<CollectedField<'a>>,
+    ) -> ToolResult<()> {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    let field = &field.node;
+                    let name = field.name.node.as_str();
+                    let type_ref = if name == "__typename" {
+                        non_null_named("String")
+                    } else {
+                        self.types
+                            .get(parent)
+                            .and_then(|definition| {
+                                definition.fields.iter().find(|item| item.name == name)
+                            })
+                            .map(|definition| definition.type_ref.clone())
+                            .ok_or_else(|| {
+                                format!(
+                                    "operation `{}` selects unknown field `{parent}.{name}`",
+                                    self.operation
+                                )
+                            })?
+                    };
+                    let key = field.response_key().node.to_string();
+                    let nested = (!field.selection_set.node.items.is_empty())
+                        .then(|| resolve_named_type(&type_ref).map(str::to_string))
+                        .flatten()
+                        .map(|target| (&field.selection_set.node, target));
+                    if let Some(existing) = fields.iter_mut().find(|item| item.key == key) {
+                        existing.conditional &= conditional;
+                        existing.selections.extend(nested);
+                    } else {
+                        fields.push(CollectedField {
+                            key,
+                            type_ref,
+                            conditional,
+                            selections: nested.into_iter().collect(),
+                        });
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    let fragment = &fragment.node;
+                    let target = fragment
+                        .type_condition
+                        .as_ref()
+                        .map_or(parent, |condition| condition.node.on.node.as_str());
+                    self.collect_into(
+                        &fragment.selection_set.node,
+                        target,
+                        conditional || target != parent,
+                        fields,
+                    )?;
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.node.fragment_name.node.as_str();
+                    let fragment = self.fragments.get(name).ok_or_else(|| {
+                        format!(
+                            "operation `{}` spreads unknown fragment `{name}`",
+                            self.operation
+                        )
+                    })?;
+                    let target = fragment.type_condition.node.on.node.as_str();
+                    self.collect_into(
+                        &fragment.selection_set.node,
+                        target,
+                        conditional || target != parent,
+                        fields,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn non_null_named(name: &str) -> GraphqlTypeRef {
+    GraphqlTypeRef {
+        kind: "NON_NULL".to_string(),
+        name: None,
+        of_type: Some(Box::new(GraphqlTypeRef {
+            kind: "SCALAR".to_string(),
+            name: Some(name.to_string()),
+            of_type: None,
+        })),
+    }
+}
+
+/// Built-in scalars map to Rust primitives and enums to their string names;
+/// custom scalars keep their raw JSON value.
+fn scalar_rust_type(name: &str) -> &'static str {
+    match name {
+        "ID" | "String" => "String",
+        "Int" => "i64",
+        "Float" => "f64",
+        "Boolean" => "bool",
+        _ => "serde_json::Value",
+    }
+}
+
+fn wrap_type(type_ref: &GraphqlTypeRef, inner: &str) -> String {
+    if type_ref.kind == "NON_NULL" {
+        return type_ref
+            .of_type
+            .as_deref()
+            .map_or_else(|| inner.to_string(), |of| wrap_non_null(of, inner));
+    }
+    format!("Option<{}>", wrap_non_null(type_ref, inner))
+}
+
+fn wrap_non_null(type_ref: &GraphqlTypeRef, inner: &str) -> String {
+    match (type_ref.kind.as_str(), type_ref.of_type.as_deref()) {
+        ("LIST", Some(of)) => format!("Vec<{}>", wrap_type(of, inner)),
+        ("NON_NULL", Some(of)) => wrap_non_null(of, inner),
+        _ => inner.to_string(),
+    }
+}
+
+fn rust_ident(value: &str) -> String {
+    match value {
+        "type" | "ref" | "self" | "match" | "move" | "use" | "where" | "impl" | "mod" | "fn" => {
+            format!("r#{value}")
+        }
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,326 @@
+use super::{to_pascal_case, ResourceSpec, ToolResult};
+use async_graphql_parser::types::{
+    BaseType, DocumentOperations, OperationType, Selection, Type, VariableDefinition,
+};
+use serde_json::{json, Value};
+
+const DEFINITIONS_HEAD: &str = "const RESOURCE_DEFINITIONS: &[ResourceDefinition] = &[";
+
+/// Appends `spec` to the `RESOURCE_DEFINITIONS` list in the kibel-tools
+/// source, keeping the existing entries untouched.
+pub(crate) fn insert_resource_definition(
+    source: &str,
+    spec: &ResourceSpec<'_>,
+) -> ToolResult<String> {
+    let start = source
+        .find(DEFINITIONS_HEAD)
+        .ok_or_else(|| format!("`{DEFINITIONS_HEAD}` not found in definitions source"))?;
+    let end = source[start..]
+        .find("\n];")
+        .map(|offset| start + offset)
+        .ok_or_else(|| "RESOURCE_DEFINITIONS is not terminated by `];`".to_string())?;
+    if source[start..end].contains(&format!("name: \"{}\",", spec.name)) {
+        return Err(format!("resource `{}` is already defined", spec.name).into());
+    }
+    let entry = format!(
+        "\n    ResourceDefinition {{\n        name: \"{}\",\n        kind: \"{}\",\n        field: \"{}\",\n        client_method: \"{}\",\n    }},",
+        spec.name, spec.kind, spec.field, spec.client_method
+    );
+    Ok(format!("{}{entry}{}", &source[..end], &source[end..]))
+}
+
+/// Builds the endpoint snapshot entry of `spec` from a hand-written
+/// operation document, for when no endpoint schema snapshot is available.
+/// Variables come from the operation's variable definitions; those that are
+/// non-null without a default are required.
+pub(crate) fn endpoint_resource_from_document(
+    spec: &ResourceSpec<'_>,
+    document: &str,
+) -> ToolResult<Value> {
+    let parsed = async_graphql_parser::parse_query(document).map_err(|error| {
+        format!(
+            "resource `{}` document is not valid GraphQL: {error}",
+            spec.name
+        )
+    })?;
+    let operation_name = to_pascal_case(spec.name);
+    let operation = match &parsed.operations {
+        DocumentOperations::Single(_) => None,
+        DocumentOperations::Multiple(operations) => operations.get(operation_name.as_str()),
+    }
+    .map(|operation| &operation.node)
+    .ok_or_else(|| format!("document must define operation `{operation_name}`"))?;
+
+    let kind = match operation.ty {
+        OperationType::Query => "query",
+        OperationType::Mutation => "mutation",
+        OperationType::Subscription => "subscription",
+    };
+    if kind != spec.kind {
+        return Err(format!(
+            "document operation is a {kind}, but resource `{}` is a {}",
+            spec.name, spec.kind
+        )
+        .into());
+    }
+    let selects_field = operation.selection_set.node.items.iter().any(|selection| {
+        matches!(&selection.node, Selection::Field(field) if field.node.name.node == spec.field)
+    });
+    if !selects_field {
+        return Err(format!("document must select root field `{}`", spec.field).into());
+    }
+
+    let variables = operation
+        .variable_definitions
+        .iter()
+        .map(|definition| &definition.node)
+        .collect::<Vec<_>>();
+    let all_variables = variables
+        .iter()
+        .map(|variable| variable.name.node.to_string())
+        .collect::<Vec<_>>();
+    let required_variables = variables
+        .iter()
+        .filter(|variable| is_required(variable))
+        .map(|variable| variable.name.node.to_string())
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "name": spec.name,
+        "kind": spec.kind,
+        "field": spec.field,
+        "operation": operation_name,
+        "client_method": spec.client_method,
+        "all_variables": all_variables,
+        "required_variables": required_variables,
+        "document": document.trim(),
+        "source": "document",
+    }))
+}
+
+fn is_required(variable: &VariableDefinition) -> bool {
+    !variable.var_type.node.nullable && variable.default_value.is_none()
+}
+
+/// A stub file emitted next to the contract changes, to be pasted into the
+/// crate named by its header.
+pub(crate) struct ScaffoldFile {
+    pub(crate) file_name: &'static str,
+    pub(crate) contents: String,
+}
+
+struct StubVariable {
+    name: String,
+    rust_type: String,
+}
+
+/// Renders the client method, CLI subcommand, and kibel-mock stubs of a new
+/// resource from its operation document.
+pub(crate) fn render_scaffold_files(
+    spec: &ResourceSpec<'_>,
+    document: &str,
+) -> ToolResult<Vec<ScaffoldFile>> {
+    let parsed = async_graphql_parser::parse_query(document).map_err(|error| {
+        format!(
+            "resource `{}` document is not valid GraphQL: {error}",
+            spec.name
+        )
+    })?;
+    let operation = match &parsed.operations {
+        DocumentOperations::Single(operation) => &operation.node,
+        DocumentOperations::Multiple(operations) => {
+            &operations
+                .values()
+                .next()
+                .ok_or_else(|| format!("resource `{}` has no operation", spec.name))?
+                .node
+        }
+    };
+    let variables = operation
+        .variable_definitions
+        .iter()
+        .map(|definition| StubVariable {
+            name: definition.node.name.node.to_string(),
+            rust_type: stub_rust_type(&definition.node.var_type.node),
+        })
+        .collect::<Vec<_>>();
+    let operation_name = to_pascal_case(spec.name);
+
+    Ok(vec![
+        ScaffoldFile {
+            file_name: "client.rs",
+            contents: render_client_stub(spec, &operation_name, &variables),
+        },
+        ScaffoldFile {
+            file_name: "cli.rs",
+            contents: render_cli_stub(spec, &operation_name, &variables),
+        },
+        ScaffoldFile {
+            file_name: "mock.rs",
+            contents: render_mock_stub(spec),
+        },
+    ])
+}
+
+fn render_client_stub(
+    spec: &ResourceSpec<'_>,
+    operation_name: &str,
+    variables: &[StubVariable],
+) -> String {
+    let mut lines = vec![
+        "// Stub for crates/kibel-client/src/client.rs.".to_string(),
+        String::new(),
+        "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]".to_string(),
+        format!("pub struct {operation_name}Input {{"),
+    ];
+    for variable in variables {
+        lines.push(format!(
+            "    pub {}: {},",
+            snake_case(&variable.name),
+            variable.rust_type
+        ));
+    }
+    lines.push("}".to_string());
+    lines.push(String::new());
+    lines.push("impl KibelClient {".to_string());
+    lines.push(format!("    /// Runs `{}.{}`.", spec.kind, spec.field));
+    lines.push("    ///".to_string());
+    lines.push("    /// # Errors".to_string());
+    lines.push("    /// Returns transport/API errors from GraphQL.".to_string());
+    lines.push(format!(
+        "    pub fn {}(&self, input: &{operation_name}Input) -> Result<Value, KibelClientError> {{",
+        spec.client_method
+    ));
+    lines.push("        let payload = self.request_trusted_graphql(".to_string());
+    lines.push(format!("            TrustedOperation::{operation_name},"));
+    lines.push(format!(
+        "            trusted_operation_document(TrustedOperation::{operation_name}),"
+    ));
+    lines.push("            json!({".to_string());
+    for variable in variables {
+        lines.push(format!(
+            "                \"{}\": input.{},",
+            variable.name,
+            snake_case(&variable.name)
+        ));
+    }
+    lines.push("            }),".to_string());
+    lines.push("        )?;".to_string());
+    lines.push(format!(
+        "        Ok(payload.pointer(\"/data/{}\").cloned().unwrap_or(Value::Null))",
+        spec.field
+    ));
+    lines.push("    }".to_string());
+    lines.push("}".to_string());
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn render_cli_stub(
+    spec: &ResourceSpec<'_>,
+    operation_name: &str,
+    variables: &[StubVariable],
+) -> String {
+    let mut lines = vec![
+        "// Stub for crates/kibel/src/cli.rs and crates/kibel/src/app.rs.".to_string(),
+        String::new(),
+        "#[derive(Debug, Clone, Args)]".to_string(),
+        format!("pub struct {operation_name}Args {{"),
+    ];
+    for variable in variables {
+        let field = snake_case(&variable.name);
+        let flag = field.replace('_', "-");
+        if flag == field {
+            lines.push("    #[arg(long)]".to_string());
+        } else {
+            lines.push(format!("    #[arg(long = \"{flag}\")]"));
+        }
+        lines.push(format!("    pub {field}: {},", variable.rust_type));
+    }
+    lines.push("}".to_string());
+    lines.push(String::new());
+    lines.push("// app.rs: match arm for the subcommand holding the args above.".to_string());
+    lines.push(format!(
+        "// let value = ctx.client.{}(&{operation_name}Input {{",
+        spec.client_method
+    ));
+    for variable in variables {
+        let field = snake_case(&variable.name);
+        let clone = if is_copy_type(&variable.rust_type) {
+            ""
+        } else {
+            ".clone()"
+        };
+        lines.push(format!("//     {field}: command.{field}{clone},"));
+    }
+    lines.push("// })?;".to_string());
+    lines.push("// Ok(CommandOutput {".to_string());
+    lines.push(format!(
+        "//     data: json!({{ \"{}\": value, \"meta\": context_meta(&ctx) }}),",
+        spec.field
+    ));
+    lines.push(format!(
+        "//     message: \"{} completed\".to_string(),",
+        spec.name
+    ));
+    lines.push("// })".to_string());
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn render_mock_stub(spec: &ResourceSpec<'_>) -> String {
+    [
+        "// Stub arm for `response_for_root_field` in crates/kibel-mock/src/lib.rs.".to_string(),
+        String::new(),
+        format!(
+            "\"{}\" => json!({{ \"data\": {{ \"{}\": null }} }}),",
+            spec.field, spec.field
+        ),
+        String::new(),
+    ]
+    .join("\n")
+}
+
+/// Maps a variable type the way the hand-written inputs do: `Int` paging
+/// arguments are `u32`, IDs and strings are `String`, and anything else is
+/// left as raw JSON.
+fn stub_rust_type(var_type: &Type) -> String {
+    let inner = match &var_type.base {
+        BaseType::Named(name) => match name.as_str() {
+            "ID" | "String" => "String".to_string(),
+            "Int" => "u32".to_string(),
+            "Float" => "f64".to_string(),
+            "Boolean" => "bool".to_string(),
+            _ => "serde_json::Value".to_string(),
+        },
+        BaseType::List(item) => format!("Vec<{}>", stub_rust_type(item)),
+    };
+    if var_type.nullable {
+        format!("Option<{inner}>")
+    } else {
+        inner
+    }
+}
+
+fn is_copy_type(rust_type: &str) -> bool {
+    let inner = rust_type
+        .strip_prefix("Option<")
+        .and_then(|rest| rest.strip_suffix('>'))
+        .unwrap_or(rust_type);
+    matches!(inner, "u32" | "f64" | "bool")
+}
+
+pub(crate) fn snake_case(value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 4);
+    for (index, c) in value.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if index > 0 {
+                output.push('_');
+            }
+            output.push(c.to_ascii_lowercase());
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
@@ -0,0 +1,375 @@
+use super::{
+    arg_is_required, get_trimmed_string, parse_graphql_type_ref, read_json,
+    render_graphql_type_ref, DiffOutputFormat, ToolError, ToolResult,
+};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub(crate) enum FailOn {
+    Breaking,
+    Any,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SchemaDiffResult {
+    pub(crate) breaking: Vec<String>,
+    pub(crate) non_breaking: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SchemaModel {
+    types: BTreeMap<String, TypeModel>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TypeModel {
+    kind: String,
+    fields: BTreeMap<String, FieldModel>,
+    input_fields: BTreeMap<String, InputValueModel>,
+    possible_types: BTreeSet<String>,
+    enum_values: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone)]
+struct FieldModel {
+    type_ref: String,
+    args: BTreeMap<String, InputValueModel>,
+}
+
+/// An argument or input object field. Snapshots taken before input field
+/// types were introspected only carry names, so `type_ref` is optional.
+#[derive(Debug, Clone)]
+struct InputValueModel {
+    type_ref: Option<String>,
+    required: bool,
+}
+
+/// Reads an endpoint schema snapshot (`{"__schema": ...}`) or a raw
+/// introspection response (`{"data": {"__schema": ...}}`).
+pub(crate) fn load_schema_snapshot(path: &Path) -> ToolResult<Value> {
+    let payload = read_json(path)?;
+    payload
+        .get("__schema")
+        .or_else(|| payload.pointer("/data/__schema"))
+        .filter(|schema| schema.get("types").is_some_and(Value::is_array))
+        .cloned()
+        .ok_or_else(|| {
+            ToolError::message(format!(
+                "{} must contain `__schema.types` (endpoint schema snapshot or introspection response)",
+                path.display()
+            ))
+        })
+}
+
+fn parse_schema_model(schema: &Value) -> ToolResult<SchemaModel> {
+    let items = schema
+        .get("types")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "__schema.types must be an array".to_string())?;
+    let mut types = BTreeMap::new();
+    for (index, item) in items.iter().enumerate() {
+        let context = format!("types[{index}]");
+        let object = item
+            .as_object()
+            .ok_or_else(|| format!("{context} must be object"))?;
+        let name = get_trimmed_string(object, "name", &context)?;
+        if name.is_empty() || name.starts_with("__") {
+            continue;
+        }
+        let mut fields = BTreeMap::new();
+        for (field_index, field) in array_items(object, "fields").iter().enumerate() {
+            let field_context = format!("{context}.fields[{field_index}]");
+            let field_object = field
+                .as_object()
+                .ok_or_else(|| format!("{field_context} must be object"))?;
+            let type_ref = parse_graphql_type_ref(
+                field_object
+                    .get("type")
+                    .ok_or_else(|| format!("{field_context} missing type"))?,
+                &format!("{field_context}.type"),
+            )?;
+            fields.insert(
+                get_trimmed_string(field_object, "name", &field_context)?,
+                FieldModel {
+                    type_ref: render_graphql_type_ref(&type_ref),
+                    args: parse_input_values(field_object, "args", &field_context)?,
+                },
+            );
+        }
+        types.insert(
+            name,
+            TypeModel {
+                kind: object
+                    .get("kind")
+                    .and_then(Value::as_str)
+                    .unwrap_or("UNKNOWN")
+                    .to_string(),
+                fields,
+                input_fields: parse_input_values(object, "inputFields", &context)?,
+                possible_types: member_names(object, "possibleTypes"),
+                enum_values: member_names(object, "enumValues"),
+            },
+        );
+    }
+    Ok(SchemaModel { types })
+}
+
+fn array_items<'a>(object: &'a serde_json::Map<String, Value>, key: &str) -> &'a [Value] {
+    object
+        .get(key)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn member_names(object: &serde_json::Map<String, Value>, key: &str) -> BTreeSet<String> {
+    array_items(object, key)
+        .iter()
+        .filter_map(|item| item.get("name").and_then(Value::as_str))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_input_values(
+    object: &serde_json::Map<String, Value>,
+    key: &str,
+    context: &str,
+) -> ToolResult<BTreeMap<String, InputValueModel>> {
+    let mut values = BTreeMap::new();
+    for (index, item) in array_items(object, key).iter().enumerate() {
+        let item_context = format!("{context}.{key}[{index}]");
+        let item_object = item
+            .as_object()
+            .ok_or_else(|| format!("{item_context} must be object"))?;
+        let name = get_trimmed_string(item_object, "name", &item_context)?;
+        let model = match item_object.get("type") {
+            Some(type_value) => InputValueModel {
+                type_ref: Some(render_graphql_type_ref(&parse_graphql_type_ref(
+                    type_value,
+                    &format!("{item_context}.type"),
+                )?)),
+                required: arg_is_required(item_object, &item_context)?,
+            },
+            None => InputValueModel {
+                type_ref: None,
+                required: false,
+            },
+        };
+        values.insert(name, model);
+    }
+    Ok(values)
+}
+
+/// Compares two schema snapshots. Removals, kind changes, and anything an
+/// existing document or variable set may stop satisfying are breaking;
+/// additions and loosened constraints are not.
+pub(crate) fn compute_schema_diff(base: &Value, target: &Value) -> ToolResult<SchemaDiffResult> {
+    let base = parse_schema_model(base)?;
+    let target = parse_schema_model(target)?;
+    let mut diff = SchemaDiffResult::default();
+
+    for (name, base_type) in &base.types {
+        let Some(target_type) = target.types.get(name) else {
+            diff.breaking
+                .push(format!("type removed: `{name}` ({})", base_type.kind));
+            continue;
+        };
+        if base_type.kind != target_type.kind {
+            diff.breaking.push(format!(
+                "type kind changed: `{name}` {} -> {}",
+                base_type.kind, target_type.kind
+            ));
+            continue;
+        }
+        diff_fields(name, base_type, target_type, &mut diff);
+        diff_input_values(
+            |field| format!("input field `{name}.{field}`"),
+            &base_type.input_fields,
+            &target_type.input_fields,
+            &mut diff,
+        );
+        diff_members(
+            name,
+            "possible type",
+            &base_type.possible_types,
+            &target_type.possible_types,
+            &mut diff,
+        );
+        diff_members(
+            name,
+            "enum value",
+            &base_type.enum_values,
+            &target_type.enum_values,
+            &mut diff,
+        );
+    }
+    for (name, target_type) in &target.types {
+        if !base.types.contains_key(name) {
+            diff.non_breaking
+                .push(format!("type added: `{name}` ({})", target_type.kind));
+        }
+    }
+
+    diff.breaking.sort();
+    diff.non_breaking.sort();
+    Ok(diff)
+}
+
+fn diff_fields(
+    type_name: &str,
+    base_type: &TypeModel,
+    target_type: &TypeModel,
+    diff: &mut SchemaDiffResult,
+) {
+    for (field_name, base_field) in &base_type.fields {
+        let path = format!("{type_name}.{field_name}");
+        let Some(target_field) = target_type.fields.get(field_name) else {
+            diff.breaking.push(format!("field removed: `{path}`"));
+            continue;
+        };
+        if base_field.type_ref != target_field.type_ref {
+            // Output positions may tighten `T` to `T!` without breaking readers.
+            let message = format!(
+                "field type changed: `{path}` {} -> {}",
+                base_field.type_ref, target_field.type_ref
+            );
+            if target_field.type_ref == format!("{}!", base_field.type_ref) {
+                diff.non_breaking.push(message);
+            } else {
+                diff.breaking.push(message);
+            }
+        }
+        diff_input_values(
+            |arg| format!("argument `{path}({arg})`"),
+            &base_field.args,
+            &target_field.args,
+            diff,
+        );
+    }
+    for field_name in target_type.fields.keys() {
+        if !base_type.fields.contains_key(field_name) {
+            diff.non_breaking
+                .push(format!("field added: `{type_name}.{field_name}`"));
+        }
+    }
+}
+
+/// Diffs the arguments of a field or the fields of an input object;
+/// `subject` renders how one of them is named in messages.
+fn diff_input_values(
+    subject: impl Fn(&str) -> String,
+    base: &BTreeMap<String, InputValueModel>,
+    target: &BTreeMap<String, InputValueModel>,
+    diff: &mut SchemaDiffResult,
+) {
+    for (name, base_value) in base {
+        let Some(target_value) = target.get(name) else {
+            diff.breaking.push(format!("{} removed", subject(name)));
+            continue;
+        };
+        let became_required = target_value.required && !base_value.required;
+        if became_required {
+            diff.breaking
+                .push(format!("{} became required", subject(name)));
+        }
+        match (&base_value.type_ref, &target_value.type_ref) {
+            (Some(base_type), Some(target_type)) if base_type != target_type => {
+                let message = format!(
+                    "{} type changed: {base_type} -> {target_type}",
+                    subject(name)
+                );
+                // Input positions may loosen `T!` to `T` without breaking
+                // callers; `T` to `T!` is already reported as required.
+                if *base_type == format!("{target_type}!") {
+                    diff.non_breaking.push(message);
+                } else if !(became_required && *target_type == format!("{base_type}!")) {
+                    diff.breaking.push(message);
+                }
+            }
+            _ if base_value.required && !target_value.required => {
+                diff.non_breaking
+                    .push(format!("{} no longer required", subject(name)));
+            }
+            _ => {}
+        }
+    }
+    for (name, target_value) in target {
+        if base.contains_key(name) {
+            continue;
+        }
+        if target_value.required {
+            diff.breaking.push(format!(
+                "required {} added: {}",
+                subject(name),
+                target_value.type_ref.as_deref().unwrap_or("?")
+            ));
+        } else {
+            diff.non_breaking.push(format!("{} added", subject(name)));
+        }
+    }
+}
+
+fn diff_members(
+    type_name: &str,
+    label: &str,
+    base: &BTreeSet<String>,
+    target: &BTreeSet<String>,
+    diff: &mut SchemaDiffResult,
+) {
+    for member in base.difference(target) {
+        diff.breaking
+            .push(format!("{label} removed: `{type_name}.{member}`"));
+    }
+    for member in target.difference(base) {
+        diff.non_breaking
+            .push(format!("{label} added: `{type_name}.{member}`"));
+    }
+}
+
+pub(crate) fn schema_diff_json(diff: &SchemaDiffResult) -> Value {
+    json!({
+        "breaking": diff.breaking,
+        "non_breaking": diff.non_breaking,
+        "breaking_count": diff.breaking.len(),
+        "non_breaking_count": diff.non_breaking.len(),
+    })
+}
+
+pub(crate) fn print_schema_diff(
+    diff: &SchemaDiffResult,
+    format: DiffOutputFormat,
+) -> ToolResult<()> {
+    match format {
+        DiffOutputFormat::Text => {
+            if diff.breaking.is_empty() {
+                println!("schema diff: no breaking changes");
+            } else {
+                println!("schema diff: {} breaking change(s)", diff.breaking.len());
+                for item in &diff.breaking {
+                    println!("  - {item}");
+                }
+            }
+            if !diff.non_breaking.is_empty() {
+                println!(
+                    "schema diff: {} non-breaking change(s)",
+                    diff.non_breaking.len()
+                );
+                for item in &diff.non_breaking {
+                    println!("  - {item}");
+                }
+            }
+        }
+        DiffOutputFormat::Json => {
+            let rendered =
+                serde_json::to_string_pretty(&schema_diff_json(diff)).map_err(|error| {
+                    ToolError::message(format!("failed to render diff json: {error}"))
+                })?;
+            println!("{rendered}");
+        }
+    }
+    Ok(())
+}
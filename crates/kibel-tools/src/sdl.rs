@@ -0,0 +1,156 @@
+use super::{parse_graphql_type_ref, render_graphql_type_ref, ToolResult};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+const BUILTIN_SCALARS: &[&str] = &["Boolean", "Float", "ID", "Int", "String"];
+
+/// The SDL rendering of a schema snapshot lives next to it, e.g.
+/// `endpoint.schema.snapshot.json` -> `endpoint.schema.snapshot.graphql`.
+pub(crate) fn sdl_path_for(snapshot_path: &Path) -> PathBuf {
+    snapshot_path.with_extension("graphql")
+}
+
+/// Renders the `__schema` of a snapshot as GraphQL SDL. Types are sorted by
+/// name and fields keep their introspection order, so the output is stable
+/// and diffs line by line in review.
+pub(crate) fn render_sdl(schema: &Value) -> ToolResult<String> {
+    let items = schema
+        .get("types")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "__schema.types must be an array".to_string())?;
+    let mut types = items
+        .iter()
+        .filter_map(|item| Some((item.get("name")?.as_str()?.trim(), item)))
+        .filter(|(name, _)| {
+            !name.is_empty() && !name.starts_with("__") && !BUILTIN_SCALARS.contains(name)
+        })
+        .collect::<Vec<_>>();
+    types.sort_by_key(|(name, _)| *name);
+
+    let mut blocks = Vec::new();
+    for (name, item) in types {
+        let kind = item.get("kind").and_then(Value::as_str).unwrap_or_default();
+        let block = match kind {
+            "SCALAR" => format!("scalar {name}"),
+            "UNION" => format!(
+                "union {name} = {}",
+                member_names(item, "possibleTypes").join(" | ")
+            ),
+            "ENUM" => braced(
+                format!("enum {name}"),
+                member_names(item, "enumValues")
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            "INPUT_OBJECT" => braced(
+                format!("input {name}"),
+                render_input_values(item, "inputFields", &format!("{name}.inputFields"))?,
+            ),
+            "OBJECT" | "INTERFACE" => {
+                let keyword = if kind == "OBJECT" {
+                    "type"
+                } else {
+                    "interface"
+                };
+                let interfaces = member_names(item, "interfaces");
+                let header = if interfaces.is_empty() {
+                    format!("{keyword} {name}")
+                } else {
+                    format!("{keyword} {name} implements {}", interfaces.join(" & "))
+                };
+                braced(header, render_fields(item, name)?)
+            }
+            other => format!("# unsupported type kind {other}: {name}"),
+        };
+        blocks.push(block);
+    }
+    Ok(format!("{}\n", blocks.join("\n\n")))
+}
+
+fn braced(header: String, lines: Vec<String>) -> String {
+    if lines.is_empty() {
+        return header;
+    }
+    let body = lines
+        .iter()
+        .map(|line| format!("  {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{header} {{\n{body}\n}}")
+}
+
+fn member_names<'a>(item: &'a Value, key: &str) -> Vec<&'a str> {
+    item.get(key)
+        .and_then(Value::as_array)
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(|member| member.get("name").and_then(Value::as_str))
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn render_type(item: &Value, context: &str) -> ToolResult<String> {
+    // Snapshots taken before input field types were introspected only carry
+    // names; render those like the codegen does for unknown types.
+    match item.get("type") {
+        Some(type_value) => Ok(render_graphql_type_ref(&parse_graphql_type_ref(
+            type_value,
+            &format!("{context}.type"),
+        )?)),
+        None => Ok("JSON".to_string()),
+    }
+}
+
+fn render_input_values(item: &Value, key: &str, context: &str) -> ToolResult<Vec<String>> {
+    let mut lines = Vec::new();
+    for (index, value) in item
+        .get(key)
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice)
+        .iter()
+        .enumerate()
+    {
+        let Some(name) = value.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let mut line = format!(
+            "{name}: {}",
+            render_type(value, &format!("{context}[{index}]"))?
+        );
+        if let Some(default) = value.get("defaultValue").and_then(Value::as_str) {
+            line.push_str(" = ");
+            line.push_str(default);
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn render_fields(item: &Value, type_name: &str) -> ToolResult<Vec<String>> {
+    let mut lines = Vec::new();
+    for (index, field) in item
+        .get("fields")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice)
+        .iter()
+        .enumerate()
+    {
+        let Some(name) = field.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let context = format!("{type_name}.fields[{index}]");
+        let args = render_input_values(field, "args", &format!("{context}.args"))?;
+        let args = if args.is_empty() {
+            String::new()
+        } else {
+            format!("({})", args.join(", "))
+        };
+        lines.push(format!("{name}{args}: {}", render_type(field, &context)?));
+    }
+    Ok(lines)
+}
@@ -0,0 +1,263 @@
+use super::{read_json, ToolResult};
+use async_graphql_parser::types::{DocumentOperations, Selection, SelectionSet};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Depth `build_operation_document` expands nested objects to when a
+/// resource has no `max_depth` override.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// Per-resource constraints on the generated operation document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SelectionOverride {
+    /// Deepest field level below the root field that may carry a selection.
+    pub(crate) max_depth: Option<usize>,
+    /// Field names never selected below the root field.
+    pub(crate) exclude_fields: Vec<String>,
+    /// Selection set of the root field, used verbatim instead of generating
+    /// one from the schema.
+    pub(crate) selection: Option<String>,
+}
+
+impl SelectionOverride {
+    pub(crate) fn max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)
+    }
+
+    pub(crate) fn excludes(&self, field: &str) -> bool {
+        self.exclude_fields.iter().any(|item| item == field)
+    }
+}
+
+/// `selection_overrides.json`, keyed by resource name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SelectionOverrides {
+    pub(crate) resources: BTreeMap<String, SelectionOverride>,
+}
+
+impl SelectionOverrides {
+    pub(crate) fn get(&self, resource: &str) -> SelectionOverride {
+        self.resources.get(resource).cloned().unwrap_or_default()
+    }
+}
+
+/// Reads the overrides file; a missing file means no overrides.
+pub(crate) fn load_selection_overrides(path: &Path) -> ToolResult<SelectionOverrides> {
+    if !path.exists() {
+        return Ok(SelectionOverrides::default());
+    }
+    parse_selection_overrides(&read_json(path)?)
+        .map_err(|error| format!("{}: {error}", path.display()).into())
+}
+
+pub(crate) fn parse_selection_overrides(payload: &Value) -> ToolResult<SelectionOverrides> {
+    let Some(resources) = payload.get("resources") else {
+        return Ok(SelectionOverrides::default());
+    };
+    let resources = resources
+        .as_object()
+        .ok_or_else(|| "`resources` must be an object".to_string())?;
+    let mut overrides = SelectionOverrides::default();
+    for (name, item) in resources {
+        let object = item
+            .as_object()
+            .ok_or_else(|| format!("resources.{name} must be an object"))?;
+        let mut entry = SelectionOverride::default();
+        for (key, value) in object {
+            let context = format!("resources.{name}.{key}");
+            match key.as_str() {
+                "max_depth" => {
+                    let depth = value
+                        .as_u64()
+                        .ok_or_else(|| format!("{context} must be a non-negative integer"))?;
+                    entry.max_depth = Some(
+                        usize::try_from(depth).map_err(|_| format!("{context} is too large"))?,
+                    );
+                }
+                "exclude_fields" => {
+                    entry.exclude_fields = value
+                        .as_array()
+                        .and_then(|items| {
+                            items
+                                .iter()
+                                .map(|item| item.as_str().map(|field| field.trim().to_string()))
+                                .collect::<Option<Vec<_>>>()
+                        })
+                        .ok_or_else(|| format!("{context} must be an array of field names"))?;
+                }
+                "selection" => {
+                    let selection = value
+                        .as_str()
+                        .filter(|selection| !selection.trim().is_empty())
+                        .ok_or_else(|| format!("{context} must be a non-empty string"))?;
+                    parse_selection(selection).map_err(|error| format!("{context}: {error}"))?;
+                    entry.selection = Some(selection.to_string());
+                }
+                other => {
+                    return Err(format!(
+                        "resources.{name} has unknown key `{other}` \
+                         (expected max_depth, exclude_fields, selection)"
+                    )
+                    .into())
+                }
+            }
+        }
+        overrides.resources.insert(name.clone(), entry);
+    }
+    Ok(overrides)
+}
+
+/// Parses a bare selection (`id title author { name }`) as the selection set
+/// of an anonymous query.
+fn parse_selection(selection: &str) -> ToolResult<String> {
+    let document = async_graphql_parser::parse_query(format!("{{ {selection} }}"))
+        .map_err(|error| format!("invalid selection: {error}"))?;
+    match &document.operations {
+        DocumentOperations::Single(operation) => {
+            Ok(canonical_selection(&operation.node.selection_set.node))
+        }
+        DocumentOperations::Multiple(_) => Err("invalid selection".to_string().into()),
+    }
+}
+
+/// Lists where `document` breaks `rules`: excluded fields it selects, levels
+/// deeper than `max_depth`, or a root selection different from the pinned one.
+pub(crate) fn selection_violations(
+    resource: &str,
+    document: &str,
+    rules: &SelectionOverride,
+) -> ToolResult<Vec<String>> {
+    let parsed = async_graphql_parser::parse_query(document)
+        .map_err(|error| format!("resource `{resource}` document is not valid GraphQL: {error}"))?;
+    let operations = match &parsed.operations {
+        DocumentOperations::Single(operation) => vec![&operation.node],
+        DocumentOperations::Multiple(operations) => operations
+            .values()
+            .map(|operation| &operation.node)
+            .collect(),
+    };
+
+    let mut violations = Vec::new();
+    for operation in operations {
+        for selection in &operation.selection_set.node.items {
+            let Selection::Field(root) = &selection.node else {
+                continue;
+            };
+            let root_selection = &root.node.selection_set.node;
+            let mut deepest = 0;
+            walk_fields(root_selection, 0, &mut |name, level| {
+                deepest = deepest.max(level);
+                if rules.excludes(name) {
+                    violations.push(format!(
+                        "resource `{resource}` selects excluded field `{name}`"
+                    ));
+                }
+            });
+            if deepest > rules.max_depth() {
+                violations.push(format!(
+                    "resource `{resource}` selection depth {deepest} exceeds max_depth {}",
+                    rules.max_depth()
+                ));
+            }
+            if let Some(pinned) = &rules.selection {
+                if parse_selection(pinned)? != canonical_selection(root_selection) {
+                    violations.push(format!(
+                        "resource `{resource}` root selection differs from the pinned selection"
+                    ));
+                }
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// Visits every field with its level, where the root field's own selection
+/// is level 0; fragments do not add a level.
+fn walk_fields(selection_set: &SelectionSet, level: usize, visit: &mut impl FnMut(&str, usize)) {
+    for selection in &selection_set.items {
+        match &selection.node {
+            Selection::Field(field) => {
+                visit(field.node.name.node.as_str(), level);
+                if !field.node.selection_set.node.items.is_empty() {
+                    walk_fields(&field.node.selection_set.node, level + 1, visit);
+                }
+            }
+            Selection::InlineFragment(fragment) => {
+                walk_fields(&fragment.node.selection_set.node, level, visit);
+            }
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+/// Renders a selection set on one line with normalized spacing, so
+/// differently formatted but equal selections compare equal.
+fn canonical_selection(selection_set: &SelectionSet) -> String {
+    let items = selection_set
+        .items
+        .iter()
+        .map(|selection| match &selection.node {
+            Selection::Field(field) => {
+                let field = &field.node;
+                let mut rendered = String::new();
+                if let Some(alias) = &field.alias {
+                    rendered.push_str(&format!("{}: ", alias.node));
+                }
+                rendered.push_str(field.name.node.as_str());
+                if !field.arguments.is_empty() {
+                    let args = field
+                        .arguments
+                        .iter()
+                        .map(|(name, value)| format!("{}: {}", name.node, value.node))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    rendered.push_str(&format!("({args})"));
+                }
+                if !field.selection_set.node.items.is_empty() {
+                    rendered.push_str(&format!(
+                        " {{ {} }}",
+                        canonical_selection(&field.selection_set.node)
+                    ));
+                }
+                rendered
+            }
+            Selection::InlineFragment(fragment) => {
+                let fragment = &fragment.node;
+                let condition = fragment
+                    .type_condition
+                    .as_ref()
+                    .map(|condition| format!(" on {}", condition.node.on.node))
+                    .unwrap_or_default();
+                format!(
+                    "...{condition} {{ {} }}",
+                    canonical_selection(&fragment.selection_set.node)
+                )
+            }
+            Selection::FragmentSpread(spread) => {
+                format!("...{}", spread.node.fragment_name.node)
+            }
+        })
+        .collect::<Vec<_>>();
+    items.join(" ")
+}
+
+/// Re-indents a pinned selection for embedding under the root field. Only
+/// ASCII spaces and tabs count as indent, so the cut stays on a char boundary.
+pub(crate) fn pinned_selection_block(selection: &str) -> String {
+    let lines = selection
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let indent = lines
+        .iter()
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or_default();
+    lines
+        .iter()
+        .map(|line| &line[indent..])
+        .collect::<Vec<_>>()
+        .join("\n")
+}
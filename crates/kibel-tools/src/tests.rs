@@ -210,6 +210,7 @@ fn build_endpoint_snapshot_from_introspection_extracts_required_args() {
         "https://example.kibe.la",
         "https://example.kibe.la/api/v1",
         "2026-02-24T00:00:00Z",
+        &SelectionOverrides::default(),
     )
     .expect("snapshot must build");
     let resources = snapshot
@@ -284,7 +285,8 @@ fn parse_endpoint_snapshot_strict_rejects_missing_document() {
         "resources": resources,
     });
 
-    let error = parse_endpoint_snapshot(&payload).expect_err("missing document should fail");
+    let error = parse_endpoint_snapshot(&payload, &resource_names())
+        .expect_err("missing document should fail");
     assert!(
         error.to_string().contains("missing `document`"),
         "unexpected error: {error}"
@@ -503,3 +505,620 @@ fn resource_contract_diff_json_contains_counts_and_items() {
         Some("breaking change")
     );
 }
+
+#[test]
+fn build_schema_snapshot_from_introspection_keeps_raw_schema() {
+    let payload = json!({
+        "data": {
+            "__schema": {
+                "queryType": { "fields": [] },
+                "types": [{ "kind": "SCALAR", "name": "ID" }]
+            }
+        }
+    });
+    let snapshot = build_schema_snapshot_from_introspection(
+        &payload,
+        "https://example.kibe.la",
+        "https://example.kibe.la/api/v1",
+        "2026-01-01T00:00:00Z",
+    )
+    .expect("schema snapshot should build");
+    assert_eq!(snapshot["__schema"]["types"][0]["name"], "ID");
+    assert_eq!(snapshot["endpoint"], "https://example.kibe.la/api/v1");
+
+    assert!(build_schema_snapshot_from_introspection(
+        &json!({ "data": {} }),
+        "https://example.kibe.la",
+        "https://example.kibe.la/api/v1",
+        "2026-01-01T00:00:00Z",
+    )
+    .is_err());
+}
+
+fn type_ref(rendered: &str) -> Value {
+    if let Some(inner) = rendered.strip_suffix('!') {
+        return json!({ "kind": "NON_NULL", "name": null, "ofType": type_ref(inner) });
+    }
+    if let Some(inner) = rendered
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        return json!({ "kind": "LIST", "name": null, "ofType": type_ref(inner) });
+    }
+    json!({ "kind": "SCALAR", "name": rendered, "ofType": null })
+}
+
+fn schema_with_note_query(note_args: &[(&str, &str)], title_type: &str, extra: &[Value]) -> Value {
+    let args = note_args
+        .iter()
+        .map(|(name, rendered)| json!({ "name": name, "defaultValue": null, "type": type_ref(rendered) }))
+        .collect::<Vec<_>>();
+    let mut types = vec![
+        json!({
+            "kind": "OBJECT",
+            "name": "Query",
+            "fields": [{ "name": "note", "args": args, "type": { "kind": "OBJECT", "name": "Note", "ofType": null } }]
+        }),
+        json!({
+            "kind": "OBJECT",
+            "name": "Note",
+            "fields": [
+                { "name": "id", "args": [], "type": type_ref("ID!") },
+                { "name": "title", "args": [], "type": type_ref(title_type) }
+            ]
+        }),
+        json!({ "kind": "OBJECT", "name": "__Schema", "fields": [] }),
+    ];
+    types.extend(extra.iter().cloned());
+    json!({ "types": types })
+}
+
+#[test]
+fn compute_schema_diff_classifies_breaking_and_non_breaking_changes() {
+    let base = schema_with_note_query(
+        &[("id", "ID!"), ("format", "String")],
+        "String",
+        &[
+            json!({ "kind": "ENUM", "name": "Visibility", "enumValues": [{ "name": "PUBLIC" }, { "name": "PRIVATE" }] }),
+            json!({ "kind": "OBJECT", "name": "Legacy", "fields": [] }),
+        ],
+    );
+    let target = schema_with_note_query(
+        &[("id", "ID"), ("format", "String!"), ("first", "Int!")],
+        "String!",
+        &[
+            json!({ "kind": "ENUM", "name": "Visibility", "enumValues": [{ "name": "PUBLIC" }, { "name": "LIMITED" }] }),
+            json!({ "kind": "OBJECT", "name": "Folder", "fields": [] }),
+        ],
+    );
+
+    let diff = schema_diff::compute_schema_diff(&base, &target).expect("schemas should diff");
+    assert_eq!(
+        diff.breaking,
+        vec![
+            "argument `Query.note(format)` became required",
+            "enum value removed: `Visibility.PRIVATE`",
+            "required argument `Query.note(first)` added: Int!",
+            "type removed: `Legacy` (OBJECT)",
+        ]
+    );
+    assert_eq!(
+        diff.non_breaking,
+        vec![
+            "argument `Query.note(id)` type changed: ID! -> ID",
+            "enum value added: `Visibility.LIMITED`",
+            "field type changed: `Note.title` String -> String!",
+            "type added: `Folder` (OBJECT)",
+        ]
+    );
+    let rendered = schema_diff::schema_diff_json(&diff);
+    assert_eq!(rendered["breaking_count"], 4);
+    assert_eq!(rendered["non_breaking_count"], 4);
+}
+
+#[test]
+fn compute_schema_diff_flags_removed_fields_and_input_field_changes() {
+    let input = |fields: Value| json!({ "kind": "INPUT_OBJECT", "name": "CreateNoteInput", "inputFields": fields });
+    let base = schema_with_note_query(
+        &[("id", "ID!")],
+        "String",
+        &[input(json!([
+            { "name": "title", "defaultValue": null, "type": type_ref("String!") },
+            { "name": "draft", "defaultValue": null, "type": type_ref("Boolean") }
+        ]))],
+    );
+    let mut target = schema_with_note_query(
+        &[("id", "ID!")],
+        "Int",
+        &[input(json!([
+            { "name": "title", "defaultValue": null, "type": type_ref("String!") },
+            { "name": "groupIds", "defaultValue": null, "type": type_ref("[ID!]!") }
+        ]))],
+    );
+    target["types"][1]["fields"]
+        .as_array_mut()
+        .expect("Note fields")
+        .remove(0);
+
+    let diff = schema_diff::compute_schema_diff(&base, &target).expect("schemas should diff");
+    assert_eq!(
+        diff.breaking,
+        vec![
+            "field removed: `Note.id`",
+            "field type changed: `Note.title` String -> Int",
+            "input field `CreateNoteInput.draft` removed",
+            "required input field `CreateNoteInput.groupIds` added: [ID!]!",
+        ]
+    );
+    assert!(diff.non_breaking.is_empty());
+}
+
+#[test]
+fn compute_schema_diff_tolerates_name_only_input_fields() {
+    let base = schema_with_note_query(
+        &[],
+        "String",
+        &[json!({ "kind": "INPUT_OBJECT", "name": "In", "inputFields": [{ "name": "a" }] })],
+    );
+    let target = schema_with_note_query(
+        &[],
+        "String",
+        &[
+            json!({ "kind": "INPUT_OBJECT", "name": "In", "inputFields": [{ "name": "a", "defaultValue": null, "type": type_ref("Int!") }] }),
+        ],
+    );
+    let diff = schema_diff::compute_schema_diff(&base, &target).expect("schemas should diff");
+    assert_eq!(diff.breaking, vec!["input field `In.a` became required"]);
+}
+
+#[test]
+fn render_sdl_prints_sorted_types_with_arguments_and_defaults() {
+    let schema = json!({
+        "types": [
+            {
+                "kind": "OBJECT",
+                "name": "Query",
+                "fields": [{
+                    "name": "notes",
+                    "args": [
+                        { "name": "first", "defaultValue": "16", "type": type_ref("Int") },
+                        { "name": "folderId", "defaultValue": null, "type": type_ref("ID!") }
+                    ],
+                    "type": type_ref("[Note!]!")
+                }]
+            },
+            {
+                "kind": "OBJECT",
+                "name": "Note",
+                "interfaces": [{ "name": "Node" }],
+                "fields": [{ "name": "id", "args": [], "type": type_ref("ID!") }]
+            },
+            { "kind": "INTERFACE", "name": "Node", "fields": [{ "name": "id", "args": [], "type": type_ref("ID!") }] },
+            { "kind": "UNION", "name": "Document", "possibleTypes": [{ "name": "Note" }, { "name": "Comment" }] },
+            { "kind": "ENUM", "name": "Role", "enumValues": [{ "name": "ADMIN" }, { "name": "MEMBER" }] },
+            { "kind": "INPUT_OBJECT", "name": "NoteInput", "inputFields": [{ "name": "title", "defaultValue": null, "type": type_ref("String!") }, { "name": "legacy" }] },
+            { "kind": "SCALAR", "name": "DateTime" },
+            { "kind": "SCALAR", "name": "String" },
+            { "kind": "OBJECT", "name": "__Type", "fields": [] }
+        ]
+    });
+
+    let rendered = sdl::render_sdl(&schema).expect("sdl should render");
+    assert_eq!(
+        rendered,
+        "scalar DateTime\n\n\
+         union Document = Note | Comment\n\n\
+         interface Node {\n  id: ID!\n}\n\n\
+         type Note implements Node {\n  id: ID!\n}\n\n\
+         input NoteInput {\n  title: String!\n  legacy: JSON\n}\n\n\
+         type Query {\n  notes(first: Int = 16, folderId: ID!): [Note!]!\n}\n\n\
+         enum Role {\n  ADMIN\n  MEMBER\n}\n"
+    );
+    assert_eq!(
+        sdl::sdl_path_for(Path::new(
+            "schema/introspection/endpoint.schema.snapshot.json"
+        )),
+        PathBuf::from("schema/introspection/endpoint.schema.snapshot.graphql")
+    );
+}
+
+fn object_type(name: &str, fields: &[(&str, &str)]) -> Value {
+    let fields = fields
+        .iter()
+        .map(|(field, rendered)| json!({ "name": field, "args": [], "type": type_ref(rendered) }))
+        .collect::<Vec<_>>();
+    json!({ "kind": "OBJECT", "name": name, "fields": fields })
+}
+
+#[test]
+fn render_response_types_module_emits_structs_per_selection() {
+    let schema = json!({
+        "queryType": { "fields": [{
+            "name": "search",
+            "args": [{ "name": "query", "defaultValue": null, "type": type_ref("String!") }],
+            "type": type_ref("SearchResultConnection!")
+        }] },
+        "mutationType": { "fields": [] },
+        "types": [
+            object_type("SearchResultConnection", &[("edges", "[SearchResultEdge!]"), ("totalCount", "Int!")]),
+            object_type("SearchResultEdge", &[("node", "SearchResult")]),
+            object_type("SearchResult", &[("title", "String!"), ("contentSummaryHtml", "String"), ("document", "Document!"), ("type", "String!")]),
+            object_type("Node", &[("id", "ID!")]),
+            { "kind": "UNION", "name": "Document", "possibleTypes": [{ "name": "Note" }] }
+        ]
+    });
+    let resource = NormalizedResource {
+        name: "searchNote".to_string(),
+        kind: "query".to_string(),
+        operation: "SearchNote".to_string(),
+        all_variables: vec!["query".to_string()],
+        required_variables: vec!["query".to_string()],
+        graphql_file: "endpoint:query.search".to_string(),
+        client_method: "search_note".to_string(),
+        document: "query SearchNote($query: String!) { search(query: $query) { totalCount edges { node { title contentSummaryHtml type document { ... on Node { id } } } } } }".to_string(),
+    };
+
+    let rendered = response_codegen::render_response_types_module(
+        std::slice::from_ref(&resource),
+        Some(&schema),
+    )
+    .expect("response types should render");
+    for expected in [
+        "/// Response `data` of the `SearchNote` trusted operation (`KibelClient::search_note`).",
+        "pub struct SearchNoteData {\n    pub search: SearchNoteSearch,\n}",
+        "pub struct SearchNoteSearch {\n    #[serde(rename = \"totalCount\")]\n    pub total_count: i64,\n    pub edges: Option<Vec<SearchNoteSearchEdges>>,\n}",
+        "pub struct SearchNoteSearchEdges {\n    pub node: Option<SearchNoteSearchEdgesNode>,\n}",
+        "    #[serde(rename = \"contentSummaryHtml\")]\n    pub content_summary_html: Option<String>,\n    pub r#type: String,\n    pub document: SearchNoteSearchEdgesNodeDocument,",
+        "pub struct SearchNoteSearchEdgesNodeDocument {\n    pub id: Option<String>,\n}",
+    ] {
+        assert!(rendered.contains(expected), "missing {expected:?} in:\n{rendered}");
+    }
+
+    let unknown = NormalizedResource {
+        document: "query SearchNote { search(query: \"x\") { missing } }".to_string(),
+        ..resource
+    };
+    assert!(response_codegen::render_response_types_module(&[unknown], Some(&schema)).is_err());
+    assert!(response_codegen::render_response_types_module(&[], None)
+        .expect("header only")
+        .contains("No endpoint schema snapshot"));
+}
+
+#[test]
+fn resource_contract_add_scaffolds_definition_document_and_stubs() {
+    let spec = ResourceSpec {
+        name: "getNoteLikers",
+        kind: "query",
+        field: "note",
+        client_method: "get_note_likers",
+    };
+    let source = "const RESOURCE_DEFINITIONS: &[ResourceDefinition] = &[\n    ResourceDefinition {\n        name: \"getNote\",\n        kind: \"query\",\n        field: \"note\",\n        client_method: \"get_note\",\n    },\n];\n";
+    let updated =
+        scaffold::insert_resource_definition(source, &spec).expect("definition should insert");
+    assert!(updated.ends_with(
+        "    ResourceDefinition {\n        name: \"getNoteLikers\",\n        kind: \"query\",\n        field: \"note\",\n        client_method: \"get_note_likers\",\n    },\n];\n"
+    ));
+    assert!(scaffold::insert_resource_definition(&updated, &spec).is_err());
+
+    let document = "query GetNoteLikers($id: ID!, $first: Int = 10, $after: String) {\n  note(id: $id) { likers(first: $first, after: $after) { nodes { account } } }\n}\n";
+    let resource = scaffold::endpoint_resource_from_document(&spec, document)
+        .expect("document should describe the resource");
+    assert_eq!(resource["operation"], "GetNoteLikers");
+    assert_eq!(resource["all_variables"], json!(["id", "first", "after"]));
+    assert_eq!(resource["required_variables"], json!(["id"]));
+    assert_eq!(resource["document"], document.trim());
+    let parsed = parse_endpoint_resource(&resource, 0).expect("entry should parse");
+    assert_eq!(parsed.client_method, "get_note_likers");
+    assert!(parsed.from_document);
+    let mut captured = resource.clone();
+    captured.as_object_mut().expect("object").remove("source");
+    assert!(
+        !parse_endpoint_resource(&captured, 0)
+            .expect("entry should parse")
+            .from_document
+    );
+    captured["source"] = json!("guess");
+    assert!(parse_endpoint_resource(&captured, 0).is_err());
+
+    let mutation = ResourceSpec {
+        kind: "mutation",
+        ..spec
+    };
+    assert!(scaffold::endpoint_resource_from_document(&mutation, document).is_err());
+    assert!(scaffold::endpoint_resource_from_document(
+        &spec,
+        "query Other($id: ID!) { note(id: $id) { id } }"
+    )
+    .is_err());
+
+    let files = scaffold::render_scaffold_files(&spec, document).expect("stubs should render");
+    let client = &files
+        .iter()
+        .find(|file| file.file_name == "client.rs")
+        .expect("client stub")
+        .contents;
+    assert!(client.contains("pub struct GetNoteLikersInput {\n    pub id: String,\n    pub first: Option<u32>,\n    pub after: Option<String>,\n}"));
+    assert!(client.contains("TrustedOperation::GetNoteLikers"));
+    let cli = &files
+        .iter()
+        .find(|file| file.file_name == "cli.rs")
+        .expect("cli stub")
+        .contents;
+    assert!(cli.contains("//     first: command.first,\n//     after: command.after.clone(),"));
+    assert!(files
+        .iter()
+        .any(|file| file.file_name == "mock.rs" && file.contents.contains("\"note\" => json!")));
+}
+
+#[test]
+fn coverage_reports_root_fields_without_resource_definitions() {
+    let field =
+        |name: &str, args: Value| json!({ "name": name, "args": args, "type": type_ref("String") });
+    let schema = json!({
+        "queryType": { "fields": [
+            field("note", json!([{ "name": "id", "defaultValue": null, "type": type_ref("ID!") }])),
+            field("budget", json!([])),
+            field("notes", json!([
+                { "name": "first", "defaultValue": null, "type": type_ref("Int") },
+                { "name": "folderId", "defaultValue": null, "type": type_ref("ID!") }
+            ]))
+        ] },
+        "mutationType": { "fields": [field("deleteNote", json!([{ "name": "input", "defaultValue": null, "type": type_ref("DeleteNoteInput!") }]))] },
+        "types": []
+    });
+    let definitions = [ResourceDefinition {
+        name: "getNote",
+        kind: "query",
+        field: "note",
+        client_method: "get_note",
+    }];
+
+    let report = coverage::compute_coverage(&schema, &definitions).expect("coverage");
+    assert_eq!(report.covered.len(), 1);
+    assert_eq!(report.covered[0].resources, vec!["getNote".to_string()]);
+    let uncovered = report
+        .uncovered
+        .iter()
+        .map(|entry| {
+            (
+                entry.kind,
+                entry.field.as_str(),
+                entry.args,
+                entry.required_args,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        uncovered,
+        vec![
+            ("query", "budget", 0, 0),
+            ("query", "notes", 2, 1),
+            ("mutation", "deleteNote", 1, 1),
+        ]
+    );
+    let rendered = coverage::coverage_json(&report);
+    assert_eq!(rendered["covered_count"], 1);
+    assert_eq!(rendered["total_count"], 4);
+}
+
+#[test]
+fn drift_reports_contract_issues_per_tenant_snapshot() {
+    let schema = |args: Value, note_fields: &[(&str, &str)]| {
+        json!({
+            "queryType": { "fields": [{ "name": "note", "args": args, "type": type_ref("Note") }] },
+            "mutationType": { "fields": [] },
+            "types": [object_type("Note", note_fields)]
+        })
+    };
+    let definitions = [ResourceDefinition {
+        name: "getNote",
+        kind: "query",
+        field: "note",
+        client_method: "get_note",
+    }];
+    let resource = NormalizedResource {
+        name: "getNote".to_string(),
+        kind: "query".to_string(),
+        operation: "GetNote".to_string(),
+        all_variables: vec!["id".to_string()],
+        required_variables: vec!["id".to_string()],
+        graphql_file: "endpoint:query.note".to_string(),
+        client_method: "get_note".to_string(),
+        document: "query GetNote($id: ID!) { note(id: $id) { id title } }".to_string(),
+    };
+    let id_arg = json!({ "name": "id", "defaultValue": null, "type": type_ref("ID!") });
+
+    let current = schema(json!([id_arg]), &[("id", "ID!"), ("title", "String!")]);
+    assert!(
+        drift::contract_issues(&current, std::slice::from_ref(&resource), &definitions)
+            .expect("issues")
+            .is_empty()
+    );
+
+    let older = schema(
+        json!([
+            { "name": "noteId", "defaultValue": null, "type": type_ref("ID!") }
+        ]),
+        &[("id", "ID!")],
+    );
+    let issues = drift::contract_issues(&older, std::slice::from_ref(&resource), &definitions)
+        .expect("issues");
+    assert_eq!(issues.len(), 3, "{issues:?}");
+    assert!(issues[0].contains("argument `note(id)` is not accepted"));
+    assert!(issues[1].contains("required argument `note(noteId)` is not sent"));
+    assert!(issues[2].contains("unknown field `Note.title`"));
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let acme = drift::tenant_snapshot_path(dir.path(), "acme").expect("valid tenant");
+    write_json_pretty(&acme, &json!({ "__schema": older })).expect("write tenant");
+    write_json_pretty(
+        &drift::tenant_snapshot_path(dir.path(), "beta").expect("valid tenant"),
+        &json!({ "__schema": current }),
+    )
+    .expect("write tenant");
+    assert!(drift::tenant_snapshot_path(dir.path(), "../acme").is_err());
+    let tenants = drift::list_tenant_snapshots(dir.path()).expect("list tenants");
+    assert_eq!(
+        tenants
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["acme", "beta"]
+    );
+
+    let drift =
+        drift::compute_tenant_drift("acme", &acme, Some(&current), &[resource], &definitions)
+            .expect("drift");
+    assert!(drift.is_drifting());
+    let diff = drift.schema_diff.expect("schema diff against base");
+    assert!(diff.breaking.iter().any(|item| item.contains("Note.title")));
+}
+
+#[test]
+fn selection_overrides_shape_generated_documents_and_are_enforced() {
+    let payload = json!({ "data": { "__schema": {
+        "queryType": { "fields": [{
+            "name": "note",
+            "args": [{ "name": "id", "defaultValue": null, "type": type_ref("ID!") }],
+            "type": type_ref("Note")
+        }] },
+        "mutationType": { "fields": [] },
+        "types": [
+            object_type("Note", &[("id", "ID!"), ("title", "String!"), ("contentHtml", "String!"), ("author", "User!")]),
+            object_type("User", &[("id", "ID!"), ("account", "String!"), ("latestNote", "Note")])
+        ]
+    } } });
+    let overrides = selection::parse_selection_overrides(&json!({
+        "resources": {
+            "getNote": { "max_depth": 1, "exclude_fields": ["contentHtml"] },
+            "getNoteTitle": { "selection": "\n    id\n    title\n  " }
+        }
+    }))
+    .expect("overrides should parse");
+    let fields = parse_graphql_fields(&payload, "query").expect("fields");
+    let types = parse_schema_types(&payload).expect("types");
+    let spec = |name| ResourceSpec {
+        name,
+        kind: "query",
+        field: "note",
+        client_method: "get_note",
+    };
+
+    let generated =
+        build_endpoint_resource(&spec("getNote"), &fields, &types, &overrides.get("getNote"))
+            .expect("document");
+    let document = generated["document"].as_str().expect("document string");
+    assert!(!document.contains("contentHtml"), "{document}");
+    assert!(document.contains("author {"), "{document}");
+    assert!(!document.contains("latestNote"), "{document}");
+    let rules = overrides.get("getNote");
+    assert!(selection::selection_violations("getNote", document, &rules)
+        .expect("violations")
+        .is_empty());
+    let unconstrained = build_endpoint_resource(
+        &spec("getNote"),
+        &fields,
+        &types,
+        &SelectionOverride::default(),
+    )
+    .expect("document");
+    let violations = selection::selection_violations(
+        "getNote",
+        unconstrained["document"].as_str().expect("document string"),
+        &rules,
+    )
+    .expect("violations");
+    assert!(violations
+        .iter()
+        .any(|item| item.contains("excluded field `contentHtml`")));
+    assert!(violations
+        .iter()
+        .any(|item| item.contains("exceeds max_depth 1")));
+
+    let pinned = overrides.get("getNoteTitle");
+    let generated =
+        build_endpoint_resource(&spec("getNoteTitle"), &fields, &types, &pinned).expect("document");
+    assert_eq!(
+        generated["document"],
+        "query GetNoteTitle($id: ID!) {\n  note(id: $id) {\n    id\n    title\n  }\n}"
+    );
+    assert!(selection::selection_violations(
+        "getNoteTitle",
+        "query GetNoteTitle($id: ID!) { note(id: $id) { id title } }",
+        &pinned
+    )
+    .expect("violations")
+    .is_empty());
+    assert_eq!(
+        selection::selection_violations(
+            "getNoteTitle",
+            "query GetNoteTitle($id: ID!) { note(id: $id) { id } }",
+            &pinned
+        )
+        .expect("violations")
+        .len(),
+        1
+    );
+
+    assert!(selection::parse_selection_overrides(
+        &json!({ "resources": { "getNote": { "depth": 2 } } })
+    )
+    .is_err());
+}
+
+#[test]
+fn pinned_selection_block_dedents_by_ascii_indent_only() {
+    assert_eq!(
+        selection::pinned_selection_block("\n    id\n      title\n  "),
+        "id\n  title"
+    );
+    assert_eq!(
+        selection::pinned_selection_block("  \u{3000}id\n   title\n  \u{a0}url"),
+        "\u{3000}id\n title\n\u{a0}url"
+    );
+}
+
+#[test]
+fn introspection_goes_through_the_client_transport() {
+    let server = kibel_mock::MockServer::start().expect("mock server should start");
+    let endpoint = endpoint_from_origin(server.origin());
+    let headers = [("Cf-Access-Client-Id".to_string(), "agent".to_string())];
+
+    // The mock serves kibel's contracts only, so `__schema` comes back as a
+    // GraphQL error mapped like any client error.
+    let error = fetch_introspection_payload(&endpoint, "test-token", &headers, 5)
+        .expect_err("the mock has no introspection");
+    assert_eq!(
+        error.to_string(),
+        "introspection request failed: api error [INPUT_INVALID]: unsupported root field: __schema"
+    );
+
+    let request = server
+        .captured_requests()
+        .pop()
+        .expect("request should be captured");
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.headers["authorization"], "Bearer test-token");
+    assert_eq!(request.headers["cf-access-client-id"], "agent");
+    assert!(request.headers["x-request-id"].starts_with("req-"));
+}
+
+#[test]
+fn generated_source_matches_ignores_layout_but_not_tokens() {
+    let expected = "/// Docs.\npub const A: &[&str] = &[\"x\", \"y\"];\n";
+    let reformatted = "/// Docs.\npub const A: &[&str] = &[\n    \"x\",\n    \"y\",\n];\n";
+    assert!(generated_source_matches(expected, reformatted, false).expect("should compare"));
+    assert!(!generated_source_matches(
+        expected,
+        "/// Docs.\npub const A: &[&str] = &[\"x\"];\n",
+        false
+    )
+    .expect("should compare"));
+    assert!(!generated_source_matches(
+        expected,
+        "/// Other docs.\npub const A: &[&str] = &[\"x\", \"y\"];\n",
+        false
+    )
+    .expect("should compare"));
+    assert!(!generated_source_matches(expected, "pub const A: &[", false).expect("should compare"));
+}
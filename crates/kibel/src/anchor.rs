@@ -0,0 +1,57 @@
+/// Where `anchor` occurs in a note's content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnchorMatch {
+    /// Character (not byte) offset of the selected occurrence.
+    pub position: usize,
+    /// How many times `anchor` occurs in the content.
+    pub occurrences: usize,
+}
+
+/// Finds the `occurrence`-th (1-based) match of `anchor` in `content`.
+///
+/// Returns `Err(occurrences)` when there are fewer matches than requested, so
+/// callers can report how many there were.
+pub fn find_anchor(content: &str, anchor: &str, occurrence: usize) -> Result<AnchorMatch, usize> {
+    if anchor.is_empty() {
+        return Err(0);
+    }
+    let starts = content
+        .match_indices(anchor)
+        .map(|(start, _)| start)
+        .collect::<Vec<_>>();
+    let byte_start = occurrence
+        .checked_sub(1)
+        .and_then(|index| starts.get(index))
+        .ok_or(starts.len())?;
+    Ok(AnchorMatch {
+        position: content[..*byte_start].chars().count(),
+        occurrences: starts.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_anchor, AnchorMatch};
+
+    #[test]
+    fn find_anchor_counts_characters_and_picks_occurrence() {
+        let content = "今日のリリース: ok\nリリース手順は別ノート";
+        assert_eq!(
+            find_anchor(content, "リリース", 1),
+            Ok(AnchorMatch {
+                position: 3,
+                occurrences: 2
+            })
+        );
+        assert_eq!(
+            find_anchor(content, "リリース", 2),
+            Ok(AnchorMatch {
+                position: 12,
+                occurrences: 2
+            })
+        );
+        assert_eq!(find_anchor(content, "リリース", 3), Err(2));
+        assert_eq!(find_anchor(content, "missing", 1), Err(0));
+        assert_eq!(find_anchor(content, "ok", 0), Err(1));
+    }
+}
@@ -0,0 +1,7429 @@
+use crate::error::{CliError, ErrorCode};
+use crate::listing::ListFilter;
+use crate::note_ref::{
+    note_path_from_id, parse_note_ref, resolve_note_id, resolve_note_ref, NoteRef,
+};
+use crate::output::{self, ColorChoice, CommandResult, OutputContext, OutputSink, StdioSink};
+use crate::progress::Progress;
+use crate::{
+    anchor, browser, cli, clipboard, completion, concurrency, config_keys, content, dedupe, diff,
+    digest, docs_mapping, export, folder_tree, frontmatter, graphql_doc, graphql_schema, help,
+    import, links, listing, manifest, paginate, picker, prepublish, publish, relay, render, report,
+    search_filter, self_update, snapshot, stats, store, template, tokens, transfer, transform, tui,
+    users, watch,
+};
+use clap::{CommandFactory, FromArgMatches};
+use clap_complete::{generate, CompleteEnv};
+use kibel_client::{
+    default_config_path, find_project_config, folder_web_path, register_secret, require_team,
+    resolve_access_token, resource_contract_upstream_commit, resource_contract_version,
+    resource_contracts, token_source_label, token_store_subject, trusted_operation_contract,
+    trusted_operations, ApqCache, AttachNoteToFolderInput, AttachmentDigestInput, Config,
+    ConfigFormat, ContractManifest, CreateCommentInput, CreateCommentReplyInput, CreateFolderInput,
+    CreateInlineCommentInput, CreateNoteFolderInput, CreateNoteInput, DeleteCommentInput,
+    DownloadAttachmentInput, DraftNotesInput, EncryptedFileTokenStore, EnsureFolderPathInput,
+    FeedSectionsInput, FolderLookupInput, GetNotesInput, KeychainTokenStore, KibelClient, Metrics,
+    MoveNoteToAnotherFolderInput, Note, NoteCommentsInput, NullTokenStore, PageInput,
+    PathLookupInput, Policy, PublishNoteInput, Redactor, ResolveTokenInput, SearchFolderInput,
+    SearchNoteInput, SearchNotePreset, SecretScanMode, SecretScanner, Timeouts, TokenSource,
+    TokenStore, TokenStoreBackend, UpdateCommentInput, UpdateNoteInput, UpdateNoteMetadataInput,
+    UploadAttachmentInput, Usage, VcrTransport,
+};
+use rpassword::prompt_password;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+struct CommandOutput {
+    data: Value,
+    message: String,
+}
+
+#[derive(Debug)]
+struct ClientContext {
+    team: Option<String>,
+    token_source: String,
+    client: KibelClient,
+    /// Config files applied, for `meta.config_layers`.
+    config_layers: Value,
+}
+
+/// Runs the CLI with the process arguments, writing to stdout/stderr.
+///
+/// Returns the process exit code.
+#[must_use]
+pub fn run_from_env() -> i32 {
+    // `COMPLETE=<shell> kibel ...` prints completions and exits here.
+    CompleteEnv::with_factory(completion::command).complete();
+    let (cli, command_path) = parse_cli(std::env::args_os()).unwrap_or_else(|error| error.exit());
+    run_parsed(&cli, &command_path, &mut StdioSink).exit_code
+}
+
+/// Runs a command in-process and captures its output.
+///
+/// `args` are the arguments after the program name, e.g.
+/// `["--json", "search", "note", "--query", "rust"]`. Environment variables
+/// (`KIBELA_*`) and the config file are resolved exactly as for the binary.
+pub fn run_command(args: &[&str], mut io: impl OutputSink) -> CommandResult {
+    let argv = std::iter::once("kibel").chain(args.iter().copied());
+    match parse_cli(argv) {
+        Ok((cli, command_path)) => run_parsed(&cli, &command_path, &mut io),
+        Err(error) => {
+            let rendered = error.render().to_string();
+            if error.use_stderr() {
+                io.stderr(&rendered);
+            } else {
+                io.stdout(&rendered);
+            }
+            CommandResult {
+                exit_code: error.exit_code(),
+                envelope: None,
+            }
+        }
+    }
+}
+
+/// Parses `argv` and also returns the subcommand path (`search note`) that
+/// usage stats are keyed by.
+fn parse_cli<I, T>(argv: I) -> Result<(cli::Cli, String), clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let matches = cli::Cli::command().try_get_matches_from(argv)?;
+    let cli = cli::Cli::from_arg_matches(&matches)?;
+    let mut path = Vec::new();
+    let mut current = &matches;
+    while let Some((name, sub)) = current.subcommand() {
+        path.push(name);
+        current = sub;
+    }
+    Ok((cli, path.join(" ")))
+}
+
+fn is_json_mode(cli: &cli::Cli) -> bool {
+    cli.json
+        || !cli.text
+        || matches!(
+            &cli.command,
+            cli::Command::Version(cli::VersionArgs { json: true, .. })
+        )
+}
+
+/// Resolves the global output flags once for the whole invocation.
+fn output_context(cli: &cli::Cli, io: &dyn OutputSink) -> OutputContext {
+    let choice = if cli.no_color {
+        ColorChoice::Never
+    } else {
+        cli.color
+    };
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    OutputContext::new(
+        is_json_mode(cli),
+        cli.quiet,
+        choice,
+        io.is_terminal(),
+        no_color_env,
+    )
+}
+
+fn run_parsed(cli: &cli::Cli, command_path: &str, io: &mut impl OutputSink) -> CommandResult {
+    let out = output_context(cli, io);
+
+    if let cli::Command::Completion(args) = &cli.command {
+        let mut command = cli::Cli::command();
+        let mut script = Vec::new();
+        generate(args.shell, &mut command, "kibel", &mut script);
+        io.stdout(&String::from_utf8_lossy(&script));
+        return CommandResult {
+            exit_code: 0,
+            envelope: None,
+        };
+    }
+
+    // Plain `help` prints clap's text help; `help --json` goes through the envelope.
+    if let cli::Command::Help(args) = &cli.command {
+        if !cli.json {
+            let mut root = cli::Cli::command();
+            root.build();
+            return match help::find_command(&root, &args.command) {
+                Ok(command) => {
+                    io.stdout(&format!("{}", command.clone().render_long_help()));
+                    CommandResult {
+                        exit_code: 0,
+                        envelope: None,
+                    }
+                }
+                Err(message) => {
+                    io.stderr(&format!(
+                        "[{}] {message}\n",
+                        ErrorCode::InputInvalid.as_str()
+                    ));
+                    CommandResult {
+                        exit_code: ErrorCode::InputInvalid.exit_code(),
+                        envelope: None,
+                    }
+                }
+            };
+        }
+    }
+
+    let request_id = &cli.request_id;
+    let started = Instant::now();
+
+    let result = execute(cli, out, io);
+    let elapsed_ms = started.elapsed().as_millis();
+    record_command_usage(cli, command_path, result.is_err());
+
+    match result {
+        Ok(output) => {
+            let mut envelope = json!({
+                "ok": true,
+                "data": output.data,
+                "error": Value::Null,
+                "meta": {
+                    "request_id": request_id,
+                    "elapsed_ms": elapsed_ms,
+                }
+            });
+            if cli.estimate_tokens {
+                envelope["meta"]["token_estimate"] = tokens::estimate_breakdown(&envelope["data"]);
+            }
+            if out.json {
+                out.stdout(io, &format!("{envelope}\n"));
+            } else {
+                out.stdout(io, &format!("{}\n", output.message));
+            }
+            CommandResult {
+                exit_code: 0,
+                envelope: Some(envelope),
+            }
+        }
+        Err(err) => {
+            // Errors can echo server messages and inputs; never let a token
+            // or e-mail address through.
+            let redactor = Redactor::process();
+            let message = redactor.text(&err.message);
+            let mut envelope = json!({
+                "ok": false,
+                "data": Value::Null,
+                "error": {
+                    "code": err.code.as_str(),
+                    "message": message,
+                    "retryable": err.code.retryable(),
+                    "details": redactor.json(&err.details),
+                },
+                "meta": {
+                    "request_id": request_id,
+                    "elapsed_ms": elapsed_ms,
+                }
+            });
+            if cli.estimate_tokens {
+                envelope["meta"]["token_estimate"] = tokens::estimate_breakdown(&envelope["error"]);
+            }
+            if out.json && !out.quiet {
+                io.stdout(&format!("{envelope}\n"));
+            } else {
+                let line = match err.request_id() {
+                    Some(id) => format!("{message} (request id {id})"),
+                    None => message,
+                };
+                io.stderr(&out.error_line(err.code.as_str(), &line));
+            }
+            CommandResult {
+                exit_code: err.code.exit_code(),
+                envelope: Some(envelope),
+            }
+        }
+    }
+}
+
+/// Counts the invocation in the usage stats. `usage` itself is left out so
+/// reading the stats does not change them.
+fn record_command_usage(cli: &cli::Cli, command_path: &str, failed: bool) {
+    if matches!(cli.command, cli::Command::Usage(_)) {
+        return;
+    }
+    let Ok((_, config)) = load_config(cli) else {
+        return;
+    };
+    if let Some(usage) = usage_stats(&config) {
+        usage.record_command(command_path, failed);
+    }
+}
+
+fn execute(
+    cli: &cli::Cli,
+    out: OutputContext,
+    io: &mut dyn OutputSink,
+) -> Result<CommandOutput, CliError> {
+    let token_inputs_required = command_uses_token_inputs(&cli.command);
+    let stdin_token = if token_inputs_required {
+        read_stdin_token(cli.with_token)?
+    } else {
+        None
+    };
+    let env_token = if token_inputs_required {
+        std::env::var(&cli.token_env).ok()
+    } else {
+        None
+    };
+    for token in stdin_token.iter().chain(env_token.iter()) {
+        register_secret(token.trim());
+    }
+
+    let env_token_set = env_token.is_some();
+    match dispatch(cli, stdin_token, env_token, out, io) {
+        Err(err)
+            if reauth_applies(&cli.command, err.code, cli.no_reauth, cli.with_token)
+                && is_interactive_terminal() =>
+        {
+            match prompt_reauth(cli, env_token_set, io)? {
+                // The entered token outranks every stored one, like a stdin token.
+                Some(token) => dispatch(cli, Some(token), None, out, io),
+                None => Err(err),
+            }
+        }
+        result => result,
+    }
+}
+
+fn dispatch(
+    cli: &cli::Cli,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+    io: &mut dyn OutputSink,
+) -> Result<CommandOutput, CliError> {
+    match &cli.command {
+        cli::Command::Auth(args) => execute_auth(cli, args, stdin_token, env_token),
+        cli::Command::Config(args) => execute_config(cli, args),
+        cli::Command::Search(args) => execute_search(cli, args, stdin_token, env_token, out),
+        cli::Command::Group(args) => execute_group(cli, args, stdin_token, env_token),
+        cli::Command::Folder(args) => execute_folder(cli, args, stdin_token, env_token),
+        cli::Command::Feed(args) => execute_feed(cli, args, stdin_token, env_token, out, io),
+        cli::Command::Comment(args) => execute_comment(cli, args, stdin_token, env_token),
+        cli::Command::Note(args) => execute_note(cli, args, stdin_token, env_token, out),
+        cli::Command::Link(args) => execute_link(cli, args, stdin_token, env_token),
+        cli::Command::Open(args) => execute_open(cli, args, stdin_token, env_token),
+        cli::Command::Tui(args) => execute_tui(cli, args, stdin_token, env_token),
+        cli::Command::Report(args) => execute_report(cli, args, stdin_token, env_token, out),
+        cli::Command::Stats(args) => execute_stats(cli, args, stdin_token, env_token),
+        cli::Command::Digest(args) => execute_digest(cli, args, stdin_token, env_token),
+        cli::Command::Publish(args) => execute_publish(cli, args, stdin_token, env_token),
+        cli::Command::Import(args) => execute_import(cli, args, stdin_token, env_token),
+        cli::Command::Ci(args) => execute_ci(cli, args, stdin_token, env_token, out),
+        cli::Command::Lint(args) => execute_lint(cli, args, stdin_token, env_token),
+        cli::Command::Template(args) => execute_template(cli, args),
+        cli::Command::Cache(args) => Ok(execute_cache(args)),
+        cli::Command::Store(args) => execute_store(args),
+        cli::Command::Metrics(args) => execute_metrics(args),
+        cli::Command::Usage(args) => execute_usage(args),
+        cli::Command::Meta(args) => Ok(execute_meta(args)),
+        cli::Command::Graphql(args) => execute_graphql(cli, args, stdin_token, env_token),
+        cli::Command::Version(args) => execute_version(args),
+        cli::Command::SelfManage(args) => execute_self(args),
+        cli::Command::Help(args) => execute_help(args),
+        cli::Command::Completion(_) => unreachable!("completion is handled before execute"),
+    }
+}
+
+fn execute_cache(args: &cli::CacheArgs) -> CommandOutput {
+    match &args.command {
+        cli::CacheCommand::Stats => {
+            let path = apq_cache_path();
+            let state = path
+                .as_ref()
+                .map(|path| ApqCache::open(path).snapshot())
+                .unwrap_or_default();
+            let endpoints = state
+                .endpoints
+                .iter()
+                .map(|(endpoint, entry)| {
+                    json!({
+                        "endpoint": endpoint,
+                        "apq_supported": !entry.unsupported,
+                        "confirmed_hashes": entry.confirmed.len(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            CommandOutput {
+                data: json!({
+                    "apq": {
+                        "path": path,
+                        "exists": path.as_ref().is_some_and(|path| path.exists()),
+                        "endpoints": endpoints,
+                        "confirmed_hashes": state
+                            .endpoints
+                            .values()
+                            .map(|entry| entry.confirmed.len())
+                            .sum::<usize>(),
+                        "stats": state.stats,
+                    },
+                }),
+                message: "cache stats completed".to_string(),
+            }
+        }
+    }
+}
+
+fn execute_store(args: &cli::StoreArgs) -> Result<CommandOutput, CliError> {
+    let local = store::open_required()?;
+    match &args.command {
+        cli::StoreCommand::Stats => Ok(CommandOutput {
+            data: json!(local.stats()?),
+            message: "store stats completed".to_string(),
+        }),
+        cli::StoreCommand::Vacuum => {
+            let (before, after) = local.vacuum()?;
+            Ok(CommandOutput {
+                data: json!({
+                    "path": local.path(),
+                    "size_bytes_before": before,
+                    "size_bytes_after": after,
+                    "reclaimed_bytes": before.saturating_sub(after),
+                }),
+                message: "store vacuum completed".to_string(),
+            })
+        }
+    }
+}
+
+fn execute_metrics(args: &cli::MetricsArgs) -> Result<CommandOutput, CliError> {
+    let path = metrics_path();
+    let metrics = path.as_ref().map(Metrics::open).unwrap_or_default();
+    let state = metrics.snapshot();
+    let text = state.to_prometheus();
+    if let Some(textfile) = &args.textfile {
+        // Written beside the target and renamed so a collector never reads a partial file.
+        let staging = textfile.with_extension(format!("prom.{}.tmp", std::process::id()));
+        write_output_file(&staging, text.as_bytes())?;
+        fs::rename(&staging, textfile).map_err(|error| {
+            CliError::new(
+                ErrorCode::TransportError,
+                format!("failed to write {}: {error}", textfile.display()),
+            )
+        })?;
+    }
+    if args.reset {
+        metrics.reset();
+    }
+    Ok(CommandOutput {
+        data: json!({
+            "path": path,
+            "exists": path.as_ref().is_some_and(|path| path.exists()),
+            "operations": state.operations,
+            "textfile": args.textfile,
+            "reset": args.reset,
+        }),
+        message: text,
+    })
+}
+
+fn execute_usage(args: &cli::UsageArgs) -> Result<CommandOutput, CliError> {
+    let path = usage_path();
+    let usage = path.as_ref().map(Usage::open).unwrap_or_default();
+    let state = usage.snapshot();
+    let today = kibel_client::usage::today();
+    let first_day = today.saturating_sub(args.days - 1);
+    let totals = state.totals_since(first_day);
+    let days = state
+        .days
+        .range(first_day..)
+        .map(|(day, usage)| {
+            json!({
+                "date": template::civil_date(i64::try_from(*day).unwrap_or_default()),
+                "invocations": usage.commands.values().map(|command| command.invocations).sum::<u64>(),
+                "calls": usage.operations.values().map(|operation| operation.calls).sum::<u64>(),
+                "response_bytes": usage
+                    .operations
+                    .values()
+                    .map(|operation| operation.response_bytes)
+                    .sum::<u64>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let since = template::utc_date_days_ago(args.days - 1);
+    let until = template::today_utc();
+
+    let mut operations = totals.operations.iter().collect::<Vec<_>>();
+    operations.sort_by(|left, right| right.1.calls.cmp(&left.1.calls).then(left.0.cmp(right.0)));
+    let mut commands = totals.commands.iter().collect::<Vec<_>>();
+    commands.sort_by(|left, right| {
+        right
+            .1
+            .invocations
+            .cmp(&left.1.invocations)
+            .then(left.0.cmp(right.0))
+    });
+    let mut message = format!("usage from {since} to {until}");
+    if operations.is_empty() && commands.is_empty() {
+        message.push_str(": nothing recorded");
+    }
+    for (operation, usage) in &operations {
+        message.push_str(&format!(
+            "\n  {operation}: {} call(s), {} error(s), {} byte(s)",
+            usage.calls, usage.errors, usage.response_bytes
+        ));
+    }
+    for (command, usage) in &commands {
+        message.push_str(&format!(
+            "\n  kibel {command}: {} run(s), {} failed",
+            usage.invocations, usage.failures
+        ));
+    }
+
+    if args.reset {
+        usage.reset();
+    }
+    Ok(CommandOutput {
+        data: json!({
+            "path": path,
+            "since": since,
+            "until": until,
+            "days": days,
+            "commands": totals.commands,
+            "operations": totals.operations,
+            "reset": args.reset,
+        }),
+        message,
+    })
+}
+
+fn execute_meta(args: &cli::MetaArgs) -> CommandOutput {
+    match &args.command {
+        cli::MetaCommand::Contracts => {
+            let manifest = ContractManifest::embedded();
+            let message = manifest
+                .operations
+                .iter()
+                .map(|operation| {
+                    format!(
+                        "{} ({}) required: {}",
+                        operation.name,
+                        operation.kind,
+                        if operation.required_variables.is_empty() {
+                            "-".to_string()
+                        } else {
+                            operation.required_variables.join(", ")
+                        }
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            CommandOutput {
+                data: json!(manifest),
+                message,
+            }
+        }
+        cli::MetaCommand::Operations => {
+            let operations = trusted_operations()
+                .iter()
+                .map(|operation| {
+                    let contract = trusted_operation_contract(*operation);
+                    json!({
+                        "name": contract.name,
+                        "kind": contract.kind,
+                        "operation": contract.operation,
+                        "client_method": contract.client_method,
+                        "transport": if contract.kind == "mutation" {
+                            "post"
+                        } else {
+                            "persisted-query-get"
+                        },
+                    })
+                })
+                .collect::<Vec<_>>();
+            let message = trusted_operations()
+                .iter()
+                .map(|operation| {
+                    let contract = trusted_operation_contract(*operation);
+                    format!("{} ({})", contract.operation, contract.kind)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            CommandOutput {
+                data: json!({
+                    "contract_version": resource_contract_version(),
+                    "upstream_commit": resource_contract_upstream_commit(),
+                    "operations": operations,
+                }),
+                message,
+            }
+        }
+        cli::MetaCommand::Limits => {
+            let timeouts = |timeouts: Timeouts| {
+                json!({
+                    "connect_secs": timeouts.connect.as_secs(),
+                    "read_secs": timeouts.read.as_secs(),
+                    "total_secs": timeouts.total.as_secs(),
+                })
+            };
+            let mut mutation_root_fields = trusted_mutation_root_fields()
+                .into_iter()
+                .collect::<Vec<_>>();
+            mutation_root_fields.sort_unstable();
+            let message = format!(
+                "graphql run: timeout {}s (max {}), response {} MiB (max {}), depth {}, cost {}\n\
+                 timeouts: interactive {}s/{}s/{}s, batch {}s/{}s/{}s (connect/read/total)",
+                cli::GRAPHQL_RUN_TIMEOUT_SECS,
+                cli::GRAPHQL_RUN_MAX_TIMEOUT_SECS,
+                cli::GRAPHQL_RUN_RESPONSE_LIMIT_MIB,
+                cli::GRAPHQL_RUN_MAX_RESPONSE_LIMIT_MIB,
+                cli::GRAPHQL_RUN_MAX_DEPTH,
+                cli::GRAPHQL_RUN_MAX_COMPLEXITY,
+                Timeouts::INTERACTIVE.connect.as_secs(),
+                Timeouts::INTERACTIVE.read.as_secs(),
+                Timeouts::INTERACTIVE.total.as_secs(),
+                Timeouts::BATCH.connect.as_secs(),
+                Timeouts::BATCH.read.as_secs(),
+                Timeouts::BATCH.total.as_secs(),
+            );
+            CommandOutput {
+                data: json!({
+                    "contract_version": resource_contract_version(),
+                    "upstream_commit": resource_contract_upstream_commit(),
+                    "graphql_run": {
+                        "timeout_secs": {
+                            "default": cli::GRAPHQL_RUN_TIMEOUT_SECS,
+                            "max": cli::GRAPHQL_RUN_MAX_TIMEOUT_SECS,
+                        },
+                        "response_limit_mib": {
+                            "default": cli::GRAPHQL_RUN_RESPONSE_LIMIT_MIB,
+                            "max": cli::GRAPHQL_RUN_MAX_RESPONSE_LIMIT_MIB,
+                        },
+                        "max_depth": cli::GRAPHQL_RUN_MAX_DEPTH,
+                        "max_complexity": cli::GRAPHQL_RUN_MAX_COMPLEXITY,
+                        "mutation_root_fields": mutation_root_fields,
+                    },
+                    "timeouts": {
+                        "interactive": timeouts(Timeouts::INTERACTIVE),
+                        "batch": timeouts(Timeouts::BATCH),
+                    },
+                }),
+                message,
+            }
+        }
+    }
+}
+
+enum VcrMode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+/// Request deadlines: the mode's defaults (`note move-to-folder --batch` runs
+/// in batch mode) with `--connect-timeout`/`--total-timeout` applied.
+fn client_timeouts(cli: &cli::Cli) -> Timeouts {
+    let batch = matches!(
+        &cli.command,
+        cli::Command::Note(cli::NoteArgs {
+            command: cli::NoteCommand::MoveToFolder(cli::NoteMoveToFolderArgs {
+                batch: Some(_),
+                ..
+            }),
+        })
+    );
+    let mut timeouts = if batch {
+        Timeouts::BATCH
+    } else {
+        Timeouts::INTERACTIVE
+    };
+    if let Some(secs) = cli.connect_timeout {
+        timeouts.connect = Duration::from_secs(secs);
+    }
+    if let Some(secs) = cli.total_timeout {
+        timeouts.total = Duration::from_secs(secs);
+    }
+    timeouts
+}
+
+/// `--record`/`--replay`, falling back to `KIBEL_VCR=record:<dir>|replay:<dir>`.
+fn vcr_mode(cli: &cli::Cli) -> Result<Option<VcrMode>, CliError> {
+    if let Some(dir) = &cli.record {
+        return Ok(Some(VcrMode::Record(dir.clone())));
+    }
+    if let Some(dir) = &cli.replay {
+        return Ok(Some(VcrMode::Replay(dir.clone())));
+    }
+    let Some(raw) = std::env::var("KIBEL_VCR")
+        .ok()
+        .filter(|raw| !raw.trim().is_empty())
+    else {
+        return Ok(None);
+    };
+    match raw.trim().split_once(':') {
+        Some(("record", dir)) if !dir.is_empty() => Ok(Some(VcrMode::Record(dir.into()))),
+        Some(("replay", dir)) if !dir.is_empty() => Ok(Some(VcrMode::Replay(dir.into()))),
+        _ => Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!("KIBEL_VCR must be `record:<dir>` or `replay:<dir>`: {raw}"),
+        )),
+    }
+}
+
+/// `KIBEL_CACHE_DIR` overrides the OS cache directory.
+fn apq_cache_path() -> Option<PathBuf> {
+    match std::env::var_os("KIBEL_CACHE_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(ApqCache::path_in(Path::new(&dir))),
+        None => ApqCache::default_path().ok(),
+    }
+}
+
+/// `metrics.json` next to the APQ cache, honoring `KIBEL_CACHE_DIR` the same way.
+fn metrics_path() -> Option<PathBuf> {
+    match std::env::var_os("KIBEL_CACHE_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(Metrics::path_in(Path::new(&dir))),
+        None => Metrics::default_path().ok(),
+    }
+}
+
+/// `usage.json` next to the APQ cache, honoring `KIBEL_CACHE_DIR` the same way.
+fn usage_path() -> Option<PathBuf> {
+    match std::env::var_os("KIBEL_CACHE_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(Usage::path_in(Path::new(&dir))),
+        None => Usage::default_path().ok(),
+    }
+}
+
+/// The usage stats to record into, unless the config sets `usage_stats = false`.
+fn usage_stats(config: &Config) -> Option<Usage> {
+    if config.usage_stats == Some(false) {
+        return None;
+    }
+    usage_path().map(Usage::open)
+}
+
+fn execute_template(cli: &cli::Cli, args: &cli::TemplateArgs) -> Result<CommandOutput, CliError> {
+    let (config_path, config) = load_config(cli)?;
+    let templates_dir = config.templates_dir(&config_path);
+    match &args.command {
+        cli::TemplateCommand::List => {
+            let templates = template::list_templates(&templates_dir)
+                .map_err(|message| CliError::new(ErrorCode::InputInvalid, message))?
+                .iter()
+                .map(|item| {
+                    json!({
+                        "name": item.name,
+                        "path": item.path,
+                        "title": item.title,
+                        "placeholders": item.placeholders(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            Ok(CommandOutput {
+                data: json!({
+                    "templates_dir": templates_dir,
+                    "templates": templates,
+                }),
+                message: "template list completed".to_string(),
+            })
+        }
+        cli::TemplateCommand::Show(command) => {
+            let item =
+                template::load_template(&templates_dir, &command.name).map_err(|message| {
+                    CliError::new(ErrorCode::NotFound, message)
+                        .with_details(json!({ "templates_dir": templates_dir }))
+                })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "templates_dir": templates_dir,
+                    "template": {
+                        "name": item.name,
+                        "path": item.path,
+                        "title": item.title,
+                        "body": item.body,
+                        "placeholders": item.placeholders(),
+                    },
+                }),
+                message: "template show completed".to_string(),
+            })
+        }
+    }
+}
+
+fn execute_help(args: &cli::HelpArgs) -> Result<CommandOutput, CliError> {
+    let mut root = cli::Cli::command();
+    root.build();
+    let command = help::find_command(&root, &args.command)
+        .map_err(|message| CliError::new(ErrorCode::InputInvalid, message))?;
+    Ok(CommandOutput {
+        data: json!({
+            "command": help::describe_command(command, &args.command),
+            "global_args": help::global_args(&root),
+        }),
+        message: "help completed".to_string(),
+    })
+}
+
+fn command_uses_token_inputs(command: &cli::Command) -> bool {
+    match command {
+        cli::Command::Auth(auth) => {
+            matches!(
+                &auth.command,
+                cli::AuthCommand::Login(_) | cli::AuthCommand::Status(_)
+            )
+        }
+        cli::Command::Link(link) => matches!(&link.command, cli::LinkCommand::Resolve(_)),
+        cli::Command::Search(_)
+        | cli::Command::Group(_)
+        | cli::Command::Folder(_)
+        | cli::Command::Feed(_)
+        | cli::Command::Comment(_)
+        | cli::Command::Note(_)
+        | cli::Command::Report(_)
+        | cli::Command::Open(_)
+        | cli::Command::Tui(_)
+        | cli::Command::Stats(_)
+        | cli::Command::Digest(_)
+        | cli::Command::Publish(_)
+        | cli::Command::Import(_)
+        | cli::Command::Ci(_)
+        | cli::Command::Lint(_) => true,
+        cli::Command::Graphql(graphql) => matches!(&graphql.command, cli::GraphqlCommand::Run(_)),
+        cli::Command::Config(_)
+        | cli::Command::Template(_)
+        | cli::Command::Cache(_)
+        | cli::Command::Store(_)
+        | cli::Command::Metrics(_)
+        | cli::Command::Usage(_)
+        | cli::Command::Meta(_)
+        | cli::Command::Completion(_)
+        | cli::Command::Version(_)
+        | cli::Command::SelfManage(_)
+        | cli::Command::Help(_) => false,
+    }
+}
+
+fn execute_auth(
+    cli: &cli::Cli,
+    args: &cli::AuthArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    match &args.command {
+        cli::AuthCommand::Login(command) => {
+            let (config_path, mut config) = load_user_config(cli.config_path.clone())?;
+            let interactive = !command.non_interactive && is_interactive_terminal();
+            let requested_team = command
+                .team
+                .clone()
+                .or_else(|| requested_team_from_cli(cli));
+            let requested_origin = requested_origin_from_cli(cli);
+            let origin = resolve_login_origin(
+                requested_origin.as_deref(),
+                requested_team.as_deref(),
+                &config,
+                interactive,
+            )?;
+            let team = resolve_login_team(
+                requested_team.as_deref(),
+                Some(&origin),
+                &config,
+                interactive,
+            )?;
+            let (token, token_source) = resolve_login_token(
+                cli.with_token,
+                stdin_token.as_deref(),
+                env_token.as_deref(),
+                interactive,
+            )?;
+
+            let backend = token_store_backend(&config)?;
+            let subject = token_store_subject(&team, Some(&origin));
+            let (stored_in, keychain_error) =
+                store_login_token(&config_path, &mut config, backend, &team, &origin, &token)?;
+            let access_token_settings_url = kibela_access_token_settings_url(&origin);
+
+            Ok(CommandOutput {
+                data: json!({
+                    "team": team,
+                    "origin": origin,
+                    "token_source": token_source,
+                    "stored_in": stored_in,
+                    "token_store_subject": subject,
+                    "keychain_available": keychain_error.is_none(),
+                    "keychain_error": keychain_error,
+                    "token_store": token_store_json(backend, &config_path),
+                    "access_token_settings_url": access_token_settings_url,
+                    "config_path": config_path,
+                }),
+                message: "auth login completed".to_string(),
+            })
+        }
+        cli::AuthCommand::Logout(command) => {
+            let (config_path, mut config) = load_user_config(cli.config_path.clone())?;
+            let requested_team = command
+                .team
+                .clone()
+                .or_else(|| requested_team_from_cli(cli));
+            let team = require_team(requested_team.as_deref(), &config)?;
+            let requested_origin = requested_origin_from_cli(cli);
+            let resolved_origin = config.resolve_origin(requested_origin.as_deref(), Some(&team));
+
+            let backend = token_store_backend(&config)?;
+            let store = open_token_store(backend, &config_path);
+            let mut keychain_deleted = false;
+            let mut keychain_error = None;
+            let mut subjects = token_store_lookup_subjects(&team, resolved_origin.as_deref());
+            if subjects.is_empty() {
+                subjects.push(team.clone());
+            }
+            if matches!(
+                backend,
+                TokenStoreBackend::Keychain | TokenStoreBackend::EncryptedFile
+            ) {
+                for subject in subjects {
+                    match store.delete_token(&subject) {
+                        Ok(()) => keychain_deleted = true,
+                        Err(err) => keychain_error = Some(err.to_string()),
+                    }
+                }
+            }
+            let config_token_removed = config.clear_profile_token(&team);
+            config.save(&config_path)?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "team": team,
+                    "origin": resolved_origin,
+                    "keychain_deleted": keychain_deleted,
+                    "keychain_error": keychain_error,
+                    "config_token_removed": config_token_removed,
+                    "token_store": token_store_json(backend, &config_path),
+                    "config_path": config_path,
+                }),
+                message: "auth logout completed".to_string(),
+            })
+        }
+        cli::AuthCommand::Status(command) => {
+            let (config_path, config) = load_user_config(cli.config_path.clone())?;
+            let backend = token_store_backend(&config)?;
+            let requested_team = command
+                .team
+                .clone()
+                .or_else(|| requested_team_from_cli(cli));
+            let requested_origin = requested_origin_from_cli(cli);
+            let resolved = resolve_access_token(
+                &ResolveTokenInput {
+                    requested_team,
+                    requested_origin,
+                    stdin_token,
+                    env_token,
+                },
+                &config,
+                open_token_store(backend, &config_path).as_ref(),
+            )?;
+
+            let token_store = token_store_json(backend, &config_path);
+            let data = if let Some(token) = resolved {
+                json!({
+                    "logged_in": true,
+                    "team": token.team,
+                    "token_source": token_source_name(token.source, backend),
+                    "token_store": token_store,
+                })
+            } else {
+                json!({
+                    "logged_in": false,
+                    "team": config.default_team,
+                    "token_source": Value::Null,
+                    "token_store": token_store,
+                })
+            };
+
+            let message = if data
+                .get("logged_in")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+            {
+                "auth status: logged in"
+            } else {
+                "auth status: not logged in"
+            }
+            .to_string();
+
+            Ok(CommandOutput { data, message })
+        }
+    }
+}
+
+fn execute_config(cli: &cli::Cli, args: &cli::ConfigArgs) -> Result<CommandOutput, CliError> {
+    let (config_path, mut config) = load_user_config(cli.config_path.clone())?;
+    match &args.command {
+        cli::ConfigCommand::Get(command) => {
+            let key = config_keys::ConfigKey::parse(&command.key)
+                .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+            let value = key.get(&config);
+            let message = match &value {
+                Value::Null => format!("{} is not set", command.key.trim()),
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+
+            Ok(CommandOutput {
+                data: json!({
+                    "key": command.key.trim(),
+                    "value": value,
+                    "config_path": config_path,
+                }),
+                message,
+            })
+        }
+        cli::ConfigCommand::Set(command) => match &command.command {
+            None => {
+                let raw_key = command.key.as_deref().unwrap_or_default().trim();
+                let key = config_keys::ConfigKey::parse(raw_key)
+                    .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+                let previous = key.get(&config);
+                let value = key
+                    .set(&mut config, command.value.as_deref())
+                    .map_err(|error| {
+                        CliError::new(ErrorCode::InputInvalid, format!("{raw_key}: {error}"))
+                    })?;
+                config.save(&config_path)?;
+
+                Ok(CommandOutput {
+                    data: json!({
+                        "key": raw_key,
+                        "value": value,
+                        "previous": previous,
+                        "config_path": config_path,
+                    }),
+                    message: if command.unset {
+                        format!("config unset {raw_key}")
+                    } else {
+                        format!("config set {raw_key} completed")
+                    },
+                })
+            }
+            Some(cli::ConfigSetCommand::Team(set_team)) => {
+                let team = normalize_owned(&set_team.team).ok_or_else(|| {
+                    CliError::new(
+                        ErrorCode::InputInvalid,
+                        "team is required for `config set team`",
+                    )
+                })?;
+                config.set_default_team(&team);
+                config.save(&config_path)?;
+
+                Ok(CommandOutput {
+                    data: json!({
+                        "default_team": team,
+                        "config_path": config_path,
+                    }),
+                    message: "config set team completed".to_string(),
+                })
+            }
+        },
+        cli::ConfigCommand::Profiles(_) => {
+            let mut profiles = Vec::new();
+            for (team, profile) in &config.profiles {
+                let has_token = profile
+                    .token
+                    .as_deref()
+                    .map(str::trim)
+                    .is_some_and(|token| !token.is_empty());
+                let origin = profile
+                    .origin
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|origin| !origin.is_empty())
+                    .map(ToOwned::to_owned);
+
+                profiles.push(json!({
+                    "team": team,
+                    "has_token": has_token,
+                    "has_origin": origin.is_some(),
+                    "origin": origin,
+                }));
+            }
+
+            Ok(CommandOutput {
+                data: json!({
+                    "default_team": config.default_team,
+                    "profiles": profiles,
+                    "config_path": config_path,
+                }),
+                message: "config profiles listed".to_string(),
+            })
+        }
+        cli::ConfigCommand::Export(command) => {
+            let shared = config.without_secrets();
+            let tokens_removed = config.teams_with_token().len();
+            let message = if let Some(output) = &command.output {
+                let serialized = shared.serialize(ConfigFormat::from_path(output))?;
+                fs::write(output, serialized).map_err(|error| {
+                    CliError::new(
+                        ErrorCode::TransportError,
+                        format!("failed to write {}: {error}", output.display()),
+                    )
+                })?;
+                format!("config exported to {}", output.display())
+            } else {
+                shared.to_toml()?.trim_end().to_string()
+            };
+
+            Ok(CommandOutput {
+                data: json!({
+                    "config": shared,
+                    "tokens_removed": tokens_removed,
+                    "output": command.output,
+                    "config_path": config_path,
+                }),
+                message,
+            })
+        }
+        cli::ConfigCommand::Import(command) => {
+            let label = command.file.display().to_string();
+            let raw = fs::read_to_string(&command.file).map_err(|error| {
+                CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("failed to read {label}: {error}"),
+                )
+            })?;
+            let imported =
+                Config::parse(&raw, ConfigFormat::from_path(&command.file)).map_err(|error| {
+                    CliError::new(ErrorCode::InputInvalid, format!("{label}: {error}"))
+                })?;
+            let teams_with_token = imported.teams_with_token();
+            if !teams_with_token.is_empty() {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!(
+                        "{label} contains access tokens; remove them and use `auth login --non-interactive`"
+                    ),
+                )
+                .with_details(json!({ "teams": teams_with_token })));
+            }
+
+            let teams = imported.profiles.keys().cloned().collect::<Vec<_>>();
+            if command.replace {
+                let previous = std::mem::replace(&mut config, imported);
+                for (team, profile) in &mut config.profiles {
+                    profile.token = previous.token_for_team(team).map(ToOwned::to_owned);
+                }
+            } else {
+                config.merge(imported);
+            }
+            config.save(&config_path)?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "file": label,
+                    "replaced": command.replace,
+                    "imported_profiles": teams,
+                    "default_team": config.default_team,
+                    "config_path": config_path,
+                }),
+                message: format!("config imported from {label}"),
+            })
+        }
+        cli::ConfigCommand::Convert(command) => {
+            if !config_path.exists() {
+                return Err(CliError::new(
+                    ErrorCode::NotFound,
+                    format!("no config file at {}", config_path.display()),
+                ));
+            }
+            let format = match command.to {
+                cli::ConfigFileFormat::Toml => ConfigFormat::Toml,
+                cli::ConfigFileFormat::Json => ConfigFormat::Json,
+            };
+            let output = command
+                .output
+                .clone()
+                .unwrap_or_else(|| config_path.with_extension(format.extension()));
+            if output == config_path {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!(
+                        "{} is already {}; pass --output to write elsewhere",
+                        config_path.display(),
+                        format.extension()
+                    ),
+                ));
+            }
+            if output.exists() && !command.force {
+                return Err(CliError::new(
+                    ErrorCode::PreconditionFailed,
+                    format!(
+                        "{} already exists; pass --force to overwrite it",
+                        output.display()
+                    ),
+                ));
+            }
+            if ConfigFormat::from_path(&output) != format {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!(
+                        "{} would be read back as the other format; use a .{} file",
+                        output.display(),
+                        format.extension()
+                    ),
+                ));
+            }
+            config.save(&output)?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "from": config_path,
+                    "to": output,
+                    "format": format.extension(),
+                }),
+                message: format!(
+                    "config converted to {}; {} is kept, pass --config-path {} to use the new file",
+                    output.display(),
+                    config_path.display(),
+                    output.display()
+                ),
+            })
+        }
+    }
+}
+
+fn execute_search(
+    cli: &cli::Cli,
+    args: &cli::SearchArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+) -> Result<CommandOutput, CliError> {
+    if let cli::SearchCommand::Note(command) = &args.command {
+        if command.all_teams {
+            return execute_search_note_all_teams(cli, command, out);
+        }
+    }
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+
+    match &args.command {
+        cli::SearchCommand::Note(command) => {
+            if command.mine {
+                if search_note_mine_has_unsupported_filters(command) {
+                    return Err(CliError::new(
+                        ErrorCode::InputInvalid,
+                        "--mine cannot be combined with other search filters",
+                    ));
+                }
+                let results = ctx.client.get_current_user_latest_notes(PageInput {
+                    first: command.first,
+                })?;
+                return finish_search_note(
+                    cli,
+                    command,
+                    CommandOutput {
+                        data: json!({
+                            "results": results,
+                            "page_info": Value::Null,
+                            "meta": context_meta(&ctx),
+                        }),
+                        message: "search note completed".to_string(),
+                    },
+                );
+            }
+
+            let mut search = resolve_search_note_request(cli, command)?;
+            apply_search_filters(&ctx, command, &mut search.input)?;
+            if !command.all {
+                let mut page = ctx.client.search_note_page(&search.input)?;
+                add_search_snippets(&mut page.results, command.content_stats);
+                let message = search_results_text(&page.results, out);
+                return finish_search_note(
+                    cli,
+                    command,
+                    CommandOutput {
+                        data: json!({
+                            "results": page.results,
+                            "page_info": page.page_info,
+                            "total_count": page.total_count,
+                            "preset": search.loaded_preset,
+                            "preset_saved": search.saved_preset,
+                            "meta": context_meta(&ctx),
+                        }),
+                        message,
+                    },
+                );
+            }
+
+            let limit = command.limit.unwrap_or(paginate::MAX_MERGED_ITEMS);
+            if limit == 0 || limit > paginate::MAX_MERGED_ITEMS {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("limit must be in range 1..={}", paginate::MAX_MERGED_ITEMS),
+                ));
+            }
+            let mut input = search.input.clone();
+            let mut total_count = None;
+            let progress = Progress::new(out, "searching", None);
+            let mut merged = paginate::collect_pages(
+                input.after.clone(),
+                Some(limit),
+                paginate::MAX_MERGED_PAGES,
+                |after| -> Result<paginate::Page<Value>, CliError> {
+                    input.after = after;
+                    let page = ctx.client.search_note_page(&input)?;
+                    if total_count.is_none() {
+                        if let Some(total) = page.total_count {
+                            progress.set_total(total.min(limit as u64));
+                        }
+                    }
+                    total_count = total_count.or(page.total_count);
+                    progress.advance(page.results.len() as u64);
+                    Ok(paginate::Page::new(page.results, page.page_info))
+                },
+            )?;
+            progress.finish();
+            add_search_snippets(&mut merged.items, command.content_stats);
+            let message = search_results_text(&merged.items, out);
+            finish_search_note(
+                cli,
+                command,
+                CommandOutput {
+                    data: json!({
+                        "results": merged.items,
+                        "page_info": merged.page_info,
+                        "total_count": total_count,
+                        "pages": merged.pages,
+                        "exhausted": merged.exhausted,
+                        "truncated": merged.truncated,
+                        "preset": search.loaded_preset,
+                        "preset_saved": search.saved_preset,
+                        "meta": context_meta(&ctx),
+                    }),
+                    message,
+                },
+            )
+        }
+        cli::SearchCommand::Folder(command) => {
+            let results = ctx.client.search_folder(&SearchFolderInput {
+                query: command.query.clone(),
+                first: command.first,
+            })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "results": results,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "search folder completed".to_string(),
+            })
+        }
+        cli::SearchCommand::User(command) => {
+            let search = ctx.client.search_note_with_page_info(&SearchNoteInput {
+                query: command.query.clone(),
+                resources: Vec::new(),
+                coediting: None,
+                updated: None,
+                group_ids: command.group_ids.clone(),
+                user_ids: Vec::new(),
+                folder_ids: command.folder_ids.clone(),
+                liker_ids: Vec::new(),
+                is_archived: None,
+                sort_by: None,
+                first: command.first,
+                after: None,
+            })?;
+            let users = collect_users_from_search_results(
+                search
+                    .get("results")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default(),
+            );
+            if let (Some(mut local), Some(found)) = (store::open(), users.as_array()) {
+                users::record(&mut local, ctx.team.as_deref().unwrap_or_default(), found);
+            }
+            Ok(CommandOutput {
+                data: json!({
+                    "users": users,
+                    "page_info": search.get("pageInfo").cloned().unwrap_or(Value::Null),
+                    "meta": context_meta(&ctx),
+                }),
+                message: "search user completed".to_string(),
+            })
+        }
+    }
+}
+
+fn search_note_mine_has_unsupported_filters(command: &cli::SearchNoteArgs) -> bool {
+    !command.query.trim().is_empty()
+        || command.all
+        || command
+            .after
+            .as_deref()
+            .is_some_and(|value| !value.trim().is_empty())
+        || !command.resources.is_empty()
+        || command.coediting.is_some()
+        || command.updated.is_some()
+        || command.updated_after.is_some()
+        || command.updated_before.is_some()
+        || !command.group_ids.is_empty()
+        || !command.user_ids.is_empty()
+        || !command.authors.is_empty()
+        || !command.folder_ids.is_empty()
+        || !command.in_folders.is_empty()
+        || !command.liker_ids.is_empty()
+        || command.is_archived.is_some()
+        || command.sort_by.is_some()
+        || command
+            .preset
+            .as_deref()
+            .is_some_and(|value| !value.trim().is_empty())
+        || command
+            .save_preset
+            .as_deref()
+            .is_some_and(|value| !value.trim().is_empty())
+}
+
+/// `search note` follow-ups on the collected `data.results`: `--open-first`
+/// sets `data.opened`, and `--pick` sets `data.picked` / `data.copied` and
+/// prints only the picked URL.
+fn finish_search_note(
+    cli: &cli::Cli,
+    command: &cli::SearchNoteArgs,
+    mut output: CommandOutput,
+) -> Result<CommandOutput, CliError> {
+    let results = output.data["results"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    output.data["opened"] = open_first_result(cli, command, &results)?;
+    if command.pick {
+        let picked = pick_item("search note", &results, |hit| {
+            let field = |key: &str| hit.get(key).and_then(Value::as_str).unwrap_or_default();
+            format!("{}  {}", field("title"), field("url"))
+        })?;
+        let field = |key: &str| {
+            picked
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let (id, url) = (field("id"), field("url"));
+        output.data["copied"] = if command.copy_url {
+            copy_to_clipboard(&url)
+        } else if command.copy_id {
+            copy_to_clipboard(&id)
+        } else {
+            Value::Null
+        };
+        output.message = if url.is_empty() { id } else { url };
+        output.data["picked"] = picked;
+    }
+    Ok(output)
+}
+
+/// `--pick`: the item of `items` the user chose, labelled by `label`.
+fn pick_item(
+    prompt: &str,
+    items: &[Value],
+    label: impl Fn(&Value) -> String,
+) -> Result<Value, CliError> {
+    if items.is_empty() {
+        return Err(CliError::new(
+            ErrorCode::NotFound,
+            "--pick: there is nothing to pick from",
+        ));
+    }
+    let labels = items.iter().map(label).collect::<Vec<_>>();
+    match picker::pick(prompt, &labels) {
+        Ok(Some(index)) => Ok(items[index].clone()),
+        Ok(None) => Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "--pick: nothing was picked",
+        )),
+        Err(error) => Err(CliError::new(ErrorCode::InputInvalid, error)),
+    }
+}
+
+/// `search note --open-first`: opens the first result's `url`, if any.
+fn open_first_result(
+    cli: &cli::Cli,
+    command: &cli::SearchNoteArgs,
+    results: &[Value],
+) -> Result<Value, CliError> {
+    if !command.open_first {
+        return Ok(Value::Null);
+    }
+    match results
+        .first()
+        .and_then(|item| item.get("url"))
+        .and_then(Value::as_str)
+    {
+        Some(url) => open_in_browser(cli, url),
+        None => Err(CliError::new(
+            ErrorCode::NotFound,
+            "--open-first: the search returned no results",
+        )),
+    }
+}
+
+/// Adds a plain-text `snippet` of `contentSummaryHtml` to each search result.
+/// `search note --all-teams`: one page of the same search per configured
+/// profile, run concurrently and concatenated in profile order with a `team`
+/// field on each row.
+///
+/// Each profile authenticates on its own (keychain or config token, never the
+/// shared `KIBELA_ACCESS_TOKEN`); a profile that fails becomes a warning, and
+/// only a search where every profile fails is an error.
+fn execute_search_note_all_teams(
+    cli: &cli::Cli,
+    command: &cli::SearchNoteArgs,
+    out: OutputContext,
+) -> Result<CommandOutput, CliError> {
+    let (_, config) = load_config(cli)?;
+    let teams = config.profiles.keys().cloned().collect::<Vec<_>>();
+    if teams.is_empty() {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "--all-teams needs at least one profile in config (see `config profiles`)",
+        ));
+    }
+    let search = resolve_search_note_request(cli, command)?;
+    let progress = Progress::new(out, "searching teams", None);
+    let outcomes = concurrency::run_with_progress(
+        &teams,
+        concurrency::DEFAULT_CONCURRENCY,
+        &progress,
+        |_, team| {
+            let ctx = resolve_client_context_for(cli, Some(team.clone()), None, None, None)?;
+            let mut input = search.input.clone();
+            apply_search_filters(&ctx, command, &mut input)?;
+            let page = ctx.client.search_note_page(&input)?;
+            Ok::<_, CliError>((ctx.client.origin().to_string(), page))
+        },
+    );
+
+    let mut results = Vec::new();
+    let mut searched = Vec::new();
+    let mut warnings = Vec::new();
+    let mut first_error = None;
+    for (team, outcome) in teams.iter().zip(outcomes) {
+        match outcome {
+            Ok((origin, mut page)) => {
+                add_search_snippets(&mut page.results, command.content_stats);
+                searched.push(json!({
+                    "team": team,
+                    "origin": origin,
+                    "count": page.results.len(),
+                    "total_count": page.total_count,
+                }));
+                for mut item in page.results {
+                    if let Some(object) = item.as_object_mut() {
+                        object.insert("team".to_string(), json!(team));
+                    }
+                    results.push(item);
+                }
+            }
+            Err(error) => {
+                warnings.push(json!({
+                    "team": team,
+                    "code": error.code.as_str(),
+                    "message": error.message,
+                }));
+                first_error.get_or_insert(error.code);
+            }
+        }
+    }
+    if searched.is_empty() {
+        return Err(CliError::new(
+            first_error.unwrap_or(ErrorCode::TransportError),
+            format!("search note failed for every team ({})", teams.len()),
+        )
+        .with_details(json!({ "warnings": warnings })));
+    }
+
+    let mut message = search_results_text(&results, out);
+    for warning in &warnings {
+        message.push_str(&format!(
+            "\nwarning: {}: {}",
+            warning["team"].as_str().unwrap_or_default(),
+            warning["message"].as_str().unwrap_or_default()
+        ));
+    }
+    Ok(CommandOutput {
+        data: json!({
+            "results": results,
+            "teams": searched,
+            "warnings": warnings,
+            "preset": search.loaded_preset,
+            "meta": { "teams": teams },
+        }),
+        message,
+    })
+}
+
+fn add_search_snippets(results: &mut [Value], content_stats: bool) {
+    for item in results {
+        let snippet = item
+            .get("contentSummaryHtml")
+            .and_then(Value::as_str)
+            .map(|html| output::html_to_text(html, output::Highlight::Plain));
+        if let (Some(snippet), Some(object)) = (snippet, item.as_object_mut()) {
+            if content_stats {
+                object.insert(
+                    "content_stats".to_string(),
+                    json!(content::analysis::analyze_text(&snippet)),
+                );
+            }
+            object.insert("snippet".to_string(), Value::String(snippet));
+        }
+    }
+}
+
+/// `note outline --text`: one indented line per heading with its anchor, or
+/// its deep link under `--links`.
+fn outline_text(nodes: &[content::analysis::OutlineNode], depth: usize, lines: &mut Vec<String>) {
+    for node in nodes {
+        let target = node
+            .url
+            .clone()
+            .unwrap_or_else(|| format!("#{}", node.heading.anchor));
+        lines.push(format!(
+            "{}- {}  {target}",
+            "  ".repeat(depth),
+            node.heading.text
+        ));
+        outline_text(&node.children, depth + 1, lines);
+    }
+}
+
+/// `--text` rendering of search results: title and URL, then the snippet with
+/// search highlights in bold (ANSI when output is colored, Markdown otherwise).
+fn search_results_text(results: &[Value], out: OutputContext) -> String {
+    let highlight = out.highlight();
+    let mut lines = vec![format!(
+        "search note completed ({} result(s))",
+        results.len()
+    )];
+    for item in results {
+        let field = |key: &str| item.get(key).and_then(Value::as_str).unwrap_or_default();
+        let team = match item.get("team").and_then(Value::as_str) {
+            Some(team) => format!("[{team}] "),
+            None => String::new(),
+        };
+        let stats = item
+            .get("content_stats")
+            .map(|stats| {
+                format!(
+                    "  ({} words, ~{} min)",
+                    stats["words"], stats["reading_minutes"]
+                )
+            })
+            .unwrap_or_default();
+        lines.push(format!(
+            "- {team}{}  {}{stats}",
+            field("title"),
+            field("url")
+        ));
+        let snippet = output::html_to_text(field("contentSummaryHtml"), highlight);
+        if !snippet.is_empty() {
+            lines.push(format!("  {snippet}"));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Expands `--updated-after/--updated-before`, `--in-folder`, and `--author`
+/// into the search input, looking folders up by path and accounts through
+/// their recent search hits.
+fn apply_search_filters(
+    ctx: &ClientContext,
+    command: &cli::SearchNoteArgs,
+    input: &mut SearchNoteInput,
+) -> Result<(), CliError> {
+    let filter = search_filter::SearchFilterBuilder::default()
+        .updated_after(command.updated_after.clone())
+        .updated_before(command.updated_before.clone())
+        .in_folders(&command.in_folders)
+        .authors(&command.authors);
+    if filter.is_empty() {
+        return Ok(());
+    }
+    filter.apply(
+        input,
+        |path| {
+            let folder = ctx.client.get_folder_from_path(&PathLookupInput {
+                path: path.to_string(),
+                first: Some(1),
+            })?;
+            folder
+                .get("id")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    CliError::new(ErrorCode::NotFound, format!("folder not found: {path}"))
+                })
+        },
+        |account| resolve_user_id(ctx, account, command.refresh_users),
+    )
+}
+
+/// Resolves `account` to a user id from the user cache, unless `refresh`,
+/// and otherwise through its recent search hits, caching what it finds.
+fn resolve_user_id(ctx: &ClientContext, account: &str, refresh: bool) -> Result<String, CliError> {
+    let team = ctx.team.as_deref().unwrap_or_default();
+    let mut local = store::open();
+    if !refresh {
+        if let Some(id) = local
+            .as_ref()
+            .and_then(|local| users::cached_id(local, team, account))
+        {
+            return Ok(id);
+        }
+    }
+    let mut lookup = SearchNoteInput::new(account);
+    lookup.first = Some(search_filter::AUTHOR_LOOKUP_FIRST);
+    let page = ctx.client.search_note_page(&lookup)?;
+    let id = page
+        .results
+        .iter()
+        .filter(|item| item.pointer("/author/account").and_then(Value::as_str) == Some(account))
+        .find_map(|item| item.pointer("/author/id").and_then(Value::as_str))
+        .map(str::to_string)
+        .ok_or_else(|| CliError::new(ErrorCode::NotFound, format!("user not found: {account}")))?;
+    if let Some(local) = &mut local {
+        let _ = local.record_user_ids(team, [(account, id.as_str())]);
+    }
+    Ok(id)
+}
+
+#[derive(Debug)]
+struct SearchNoteRequest {
+    input: SearchNoteInput,
+    loaded_preset: Option<String>,
+    saved_preset: Option<String>,
+}
+
+fn resolve_search_note_request(
+    cli: &cli::Cli,
+    command: &cli::SearchNoteArgs,
+) -> Result<SearchNoteRequest, CliError> {
+    let mut input = search_note_input_from_cli(command);
+    let mut loaded_preset = None;
+    let mut saved_preset = None;
+
+    let preset_name = command.preset.as_deref().and_then(normalize_owned);
+    let save_preset_name = command.save_preset.as_deref().and_then(normalize_owned);
+    if preset_name.is_none() && save_preset_name.is_none() {
+        return Ok(SearchNoteRequest {
+            input,
+            loaded_preset,
+            saved_preset,
+        });
+    }
+
+    let (_, config) = load_config(cli)?;
+
+    if let Some(preset_name) = preset_name {
+        let preset = config.search_note_preset(&preset_name).ok_or_else(|| {
+            CliError::new(
+                ErrorCode::NotFound,
+                format!("search note preset not found: {preset_name}"),
+            )
+        })?;
+        input = merge_search_note_preset(preset, input);
+        loaded_preset = Some(preset_name);
+    }
+
+    if let Some(save_preset_name) = save_preset_name {
+        let preset = search_note_preset_from_input(&input);
+        // Presets are saved to the user config, never to a project config.
+        let (config_path, mut config) = load_user_config(cli.config_path.clone())?;
+        if !config.set_search_note_preset(&save_preset_name, preset) {
+            return Err(CliError::new(
+                ErrorCode::InputInvalid,
+                "--save-preset requires non-empty preset name",
+            ));
+        }
+        config.save(&config_path)?;
+        saved_preset = Some(save_preset_name);
+    }
+
+    Ok(SearchNoteRequest {
+        input,
+        loaded_preset,
+        saved_preset,
+    })
+}
+
+fn search_note_input_from_cli(command: &cli::SearchNoteArgs) -> SearchNoteInput {
+    SearchNoteInput {
+        query: command.query.clone(),
+        resources: command.resources.clone(),
+        coediting: command.coediting,
+        updated: command.updated.clone(),
+        group_ids: command.group_ids.clone(),
+        user_ids: command.user_ids.clone(),
+        folder_ids: command.folder_ids.clone(),
+        liker_ids: command.liker_ids.clone(),
+        is_archived: command.is_archived,
+        sort_by: command.sort_by.clone(),
+        first: command.first,
+        after: command.after.clone(),
+    }
+}
+
+fn merge_search_note_preset(
+    preset: &SearchNotePreset,
+    cli_input: SearchNoteInput,
+) -> SearchNoteInput {
+    SearchNoteInput {
+        query: if cli_input.query.trim().is_empty() {
+            preset.query.clone()
+        } else {
+            cli_input.query
+        },
+        resources: if cli_input.resources.is_empty() {
+            preset.resources.clone()
+        } else {
+            cli_input.resources
+        },
+        coediting: cli_input.coediting.or(preset.coediting),
+        updated: cli_input.updated.or_else(|| preset.updated.clone()),
+        group_ids: if cli_input.group_ids.is_empty() {
+            preset.group_ids.clone()
+        } else {
+            cli_input.group_ids
+        },
+        user_ids: if cli_input.user_ids.is_empty() {
+            preset.user_ids.clone()
+        } else {
+            cli_input.user_ids
+        },
+        folder_ids: if cli_input.folder_ids.is_empty() {
+            preset.folder_ids.clone()
+        } else {
+            cli_input.folder_ids
+        },
+        liker_ids: if cli_input.liker_ids.is_empty() {
+            preset.liker_ids.clone()
+        } else {
+            cli_input.liker_ids
+        },
+        is_archived: cli_input.is_archived.or(preset.is_archived),
+        sort_by: cli_input.sort_by.or_else(|| preset.sort_by.clone()),
+        first: cli_input.first.or(preset.first),
+        after: cli_input.after.or_else(|| preset.after.clone()),
+    }
+}
+
+fn search_note_preset_from_input(input: &SearchNoteInput) -> SearchNotePreset {
+    SearchNotePreset {
+        query: input.query.clone(),
+        resources: input.resources.clone(),
+        coediting: input.coediting,
+        updated: input.updated.clone(),
+        group_ids: input.group_ids.clone(),
+        user_ids: input.user_ids.clone(),
+        folder_ids: input.folder_ids.clone(),
+        liker_ids: input.liker_ids.clone(),
+        is_archived: input.is_archived,
+        sort_by: input.sort_by.clone(),
+        first: input.first,
+        after: input.after.clone(),
+    }
+}
+
+fn collect_users_from_search_results(results: Vec<Value>) -> Value {
+    let mut users = HashMap::<String, (Value, u32)>::new();
+    for item in results {
+        let author = item.get("author").cloned().unwrap_or(Value::Null);
+        let id = author.get("id").cloned().unwrap_or(Value::Null);
+        let account = author.get("account").cloned().unwrap_or(Value::Null);
+        let real_name = author.get("realName").cloned().unwrap_or(Value::Null);
+        let key = if let Some(value) = id.as_str() {
+            format!("id:{value}")
+        } else if let Some(value) = account.as_str() {
+            format!("account:{value}")
+        } else if let Some(value) = real_name.as_str() {
+            format!("realName:{value}")
+        } else {
+            continue;
+        };
+
+        let entry = users.entry(key).or_insert_with(|| {
+            (
+                json!({
+                    "id": id,
+                    "account": account,
+                    "real_name": real_name,
+                    "match_count": 0u32,
+                }),
+                0u32,
+            )
+        });
+        entry.1 = entry.1.saturating_add(1);
+        if let Some(count) = entry.0.get_mut("match_count") {
+            *count = json!(entry.1);
+        }
+    }
+
+    let mut values = users
+        .into_values()
+        .map(|(value, _)| value)
+        .collect::<Vec<_>>();
+    values.sort_by(|left, right| {
+        let left_count = left.get("match_count").and_then(Value::as_u64).unwrap_or(0);
+        let right_count = right
+            .get("match_count")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        right_count.cmp(&left_count)
+    });
+    Value::Array(values)
+}
+
+fn execute_group(
+    cli: &cli::Cli,
+    args: &cli::GroupArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+
+    match &args.command {
+        cli::GroupCommand::List(command) => {
+            let groups = ctx.client.get_groups(PageInput {
+                first: command.first,
+            })?;
+            completion::remember(ctx.team.as_deref(), completion::EntryKind::Group, &groups);
+            let groups = list_filter(&command.filter).apply(&groups);
+            Ok(CommandOutput {
+                message: listing::render_table(
+                    &groups,
+                    &[
+                        ("NAME", "name"),
+                        ("ID", "id"),
+                        ("PRIVATE", "isPrivate"),
+                        ("ARCHIVED", "isArchived"),
+                        ("DESCRIPTION", "description"),
+                    ],
+                ),
+                data: json!({
+                    "groups": groups,
+                    "meta": context_meta(&ctx),
+                }),
+            })
+        }
+    }
+}
+
+fn list_filter(args: &cli::ListFilterArgs) -> ListFilter {
+    ListFilter {
+        include_archived: args.include_archived,
+        private_only: args.private_only,
+        name_contains: args.name_contains.clone(),
+    }
+}
+
+fn execute_folder(
+    cli: &cli::Cli,
+    args: &cli::FolderArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+
+    match &args.command {
+        cli::FolderCommand::List(command) => {
+            let folders = ctx.client.get_folders(PageInput {
+                first: command.first,
+            })?;
+            completion::remember(ctx.team.as_deref(), completion::EntryKind::Folder, &folders);
+            let folders = list_filter(&command.filter).apply(&folders);
+            if command.pick {
+                let picked = pick_item("folder list", &folders, |folder| {
+                    let field =
+                        |key: &str| folder.get(key).and_then(Value::as_str).unwrap_or_default();
+                    format!("{}  {}", field("name"), field("id"))
+                })?;
+                let id = picked
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let url = folder_web_path(&id)
+                    .map(|path| format!("{}{path}", ctx.client.origin().trim_end_matches('/')));
+                return Ok(CommandOutput {
+                    data: json!({
+                        "folders": folders,
+                        "picked": picked,
+                        "url": url,
+                        "meta": context_meta(&ctx),
+                    }),
+                    message: id,
+                });
+            }
+            Ok(CommandOutput {
+                message: listing::render_table(
+                    &folders,
+                    &[
+                        ("NAME", "name"),
+                        ("ID", "id"),
+                        ("GROUP", "group"),
+                        ("PRIVATE", "isPrivate"),
+                        ("ARCHIVED", "isArchived"),
+                    ],
+                ),
+                data: json!({
+                    "folders": folders,
+                    "meta": context_meta(&ctx),
+                }),
+            })
+        }
+        cli::FolderCommand::Get(command) => {
+            let folder = ctx.client.get_folder(&FolderLookupInput {
+                id: command.id.clone(),
+                first: command.first,
+            })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "folder": folder,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "folder get completed".to_string(),
+            })
+        }
+        cli::FolderCommand::GetFromPath(command) => {
+            let folder = ctx.client.get_folder_from_path(&PathLookupInput {
+                path: command.path.clone(),
+                first: command.first,
+            })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "folder": folder,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "folder get-from-path completed".to_string(),
+            })
+        }
+        cli::FolderCommand::Notes(command) => {
+            let notes = ctx.client.get_notes(&GetNotesInput {
+                folder_id: command.folder_id.clone(),
+                first: command.first,
+                last: command.last,
+            })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "notes": notes,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "folder notes completed".to_string(),
+            })
+        }
+        cli::FolderCommand::Tree(command) => {
+            if command.first == 0 {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "first must be greater than 0",
+                ));
+            }
+            let fetch = |id: &str| -> Result<Value, CliError> {
+                Ok(ctx.client.get_folder(&FolderLookupInput {
+                    id: id.to_string(),
+                    first: Some(command.first),
+                })?)
+            };
+            let mut prefetched = Vec::new();
+            let roots = if let Some(path) = command.path.as_deref() {
+                let folder = ctx.client.get_folder_from_path(&PathLookupInput {
+                    path: path.to_string(),
+                    first: Some(1),
+                })?;
+                let id = folder.get("id").and_then(Value::as_str).ok_or_else(|| {
+                    CliError::new(ErrorCode::NotFound, format!("folder not found: {path}"))
+                })?;
+                vec![id.to_string()]
+            } else {
+                let group_id = command.group_id.as_deref().unwrap_or_default();
+                let listed = ctx.client.get_folders(PageInput {
+                    first: Some(command.first),
+                })?;
+                for id in listed
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|folder| folder.get("id").and_then(Value::as_str))
+                {
+                    let folder = fetch(id)?;
+                    if folder.pointer("/group/id").and_then(Value::as_str) == Some(group_id) {
+                        prefetched.push((id.to_string(), folder));
+                    }
+                }
+                folder_tree::top_level_ids(&prefetched)
+            };
+            let page_size = usize::try_from(command.first).unwrap_or(usize::MAX);
+            let tree =
+                folder_tree::build_tree(&roots, prefetched, command.max_depth, page_size, fetch)?;
+            Ok(CommandOutput {
+                data: json!({
+                    "tree": tree.iter().map(folder_tree::FolderNode::to_json).collect::<Vec<_>>(),
+                    "folder_count": tree.iter().map(folder_tree::FolderNode::count).sum::<usize>(),
+                    "meta": context_meta(&ctx),
+                }),
+                message: folder_tree::render_text(&tree),
+            })
+        }
+        cli::FolderCommand::Create(command) => {
+            let folder = ctx.client.create_folder(&CreateFolderInput {
+                group_id: command.group_id.clone(),
+                full_name: command.full_name.clone(),
+            })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "folder": folder,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "folder create completed".to_string(),
+            })
+        }
+    }
+}
+
+fn execute_feed(
+    cli: &cli::Cli,
+    args: &cli::FeedArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+    io: &mut dyn OutputSink,
+) -> Result<CommandOutput, CliError> {
+    match &args.command {
+        cli::FeedCommand::Watch(command) => {
+            return execute_feed_watch(cli, command, stdin_token, env_token, out, io);
+        }
+        cli::FeedCommand::Relay(command) => {
+            return execute_feed_relay(cli, command, stdin_token, env_token, out, io);
+        }
+        cli::FeedCommand::Sections(_) => {}
+    }
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+
+    match &args.command {
+        cli::FeedCommand::Sections(command) => {
+            let mut input = FeedSectionsInput {
+                kind: command.kind.clone(),
+                group_id: command.group_id.clone(),
+                first: command.first,
+                after: command.after.clone(),
+            };
+            let Some(since) = command.since.as_deref() else {
+                let page = ctx.client.get_feed_sections_with_page_info(&input)?;
+                return Ok(CommandOutput {
+                    data: json!({
+                        "sections": page.get("sections").cloned().unwrap_or_else(|| json!([])),
+                        "page_info": page.get("pageInfo").cloned().unwrap_or(Value::Null),
+                        "meta": context_meta(&ctx),
+                    }),
+                    message: "feed sections completed".to_string(),
+                });
+            };
+            if command.max_pages == 0 {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "max-pages must be greater than 0",
+                ));
+            }
+
+            let mut reached_cutoff = false;
+            let merged = paginate::collect_pages(
+                command.after.clone(),
+                None,
+                command.max_pages,
+                |after| -> Result<paginate::Page<Value>, CliError> {
+                    input.after = after;
+                    let page = ctx.client.get_feed_sections_with_page_info(&input)?;
+                    let mut sections = Vec::new();
+                    for section in page
+                        .get("sections")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                    {
+                        if feed_section_date(section).is_some_and(|date| date < since) {
+                            reached_cutoff = true;
+                        } else {
+                            sections.push(section.clone());
+                        }
+                    }
+                    let mut page = paginate::Page::new(
+                        sections,
+                        page.get("pageInfo").cloned().unwrap_or(Value::Null),
+                    );
+                    page.stop = reached_cutoff;
+                    Ok(page)
+                },
+            )?;
+            Ok(CommandOutput {
+                data: json!({
+                    "sections": merged.items,
+                    "page_info": merged.page_info,
+                    "since": since,
+                    "pages": merged.pages,
+                    "reached_since": reached_cutoff,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "feed sections completed".to_string(),
+            })
+        }
+        cli::FeedCommand::Watch(_) | cli::FeedCommand::Relay(_) => {
+            unreachable!("feed watch/relay are handled above")
+        }
+    }
+}
+
+const MIN_WATCH_INTERVAL_SECS: u64 = 5;
+
+fn execute_feed_watch(
+    cli: &cli::Cli,
+    command: &cli::FeedWatchArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+    io: &mut dyn OutputSink,
+) -> Result<CommandOutput, CliError> {
+    let summary = run_feed_watch(
+        cli,
+        command,
+        stdin_token,
+        env_token,
+        out,
+        io,
+        &mut |event| (event.clone(), true),
+    )?;
+    Ok(CommandOutput {
+        message: format!(
+            "feed watch stopped after {} poll(s), {} event(s)",
+            summary.polls, summary.events
+        ),
+        data: json!({
+            "polls": summary.polls,
+            "events": summary.events,
+            "state_file": command.state_file.as_ref().map(|path| path.display().to_string()),
+            "meta": summary.meta,
+        }),
+    })
+}
+
+fn execute_feed_relay(
+    cli: &cli::Cli,
+    command: &cli::FeedRelayArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+    io: &mut dyn OutputSink,
+) -> Result<CommandOutput, CliError> {
+    let url = relay::validate_relay_url(&command.url)
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+    let secret = std::env::var(&command.secret_env)
+        .ok()
+        .and_then(|secret| normalize_owned(&secret))
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorCode::InputInvalid,
+                format!("signing secret is required (set {})", command.secret_env),
+            )
+        })?;
+    if command.max_retries > 10 {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "max-retries must be in range 0..=10",
+        ));
+    }
+    let target = relay::RelayTarget {
+        url,
+        secret,
+        max_retries: command.max_retries,
+        retry_base: std::time::Duration::from_millis(command.retry_base_ms),
+    };
+
+    let mut delivered = 0usize;
+    let mut failed = 0usize;
+    let summary = run_feed_watch(
+        cli,
+        &command.watch,
+        stdin_token,
+        env_token,
+        out,
+        io,
+        &mut |event| {
+            let delivery = target.deliver(event, unix_timestamp_secs());
+            if delivery.ok() {
+                delivered += 1;
+            } else {
+                failed += 1;
+            }
+            let mut line = event.clone();
+            line["delivery"] = delivery.to_json();
+            (line, delivery.ok())
+        },
+    )?;
+    Ok(CommandOutput {
+        message: format!(
+            "feed relay stopped after {} poll(s), {delivered} delivered, {failed} failed",
+            summary.polls
+        ),
+        data: json!({
+            "polls": summary.polls,
+            "events": summary.events,
+            "delivered": delivered,
+            "failed": failed,
+            "url": target.url,
+            "meta": summary.meta,
+        }),
+    })
+}
+
+struct WatchSummary {
+    polls: u32,
+    events: usize,
+    meta: Value,
+}
+
+/// Polls until `--max-polls` (or forever), passing each new event to
+/// `on_event` and printing the line it returns. Events `on_event` reports as
+/// not handled stay unseen, so the next poll passes them again.
+fn run_feed_watch(
+    cli: &cli::Cli,
+    command: &cli::FeedWatchArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+    io: &mut dyn OutputSink,
+    on_event: &mut dyn FnMut(&Value) -> (Value, bool),
+) -> Result<WatchSummary, CliError> {
+    if command.interval < MIN_WATCH_INTERVAL_SECS {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!("interval must be at least {MIN_WATCH_INTERVAL_SECS} seconds"),
+        ));
+    }
+    if command.max_polls == Some(0) {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "max-polls must be greater than 0",
+        ));
+    }
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+    let mut state = match &command.state_file {
+        Some(path) => watch::WatchState::load(path)
+            .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?,
+        None => watch::WatchState::default(),
+    };
+    let mut polls = 0u32;
+    let mut emitted = 0usize;
+    loop {
+        polls += 1;
+        let before = state.clone();
+        match poll_feed_once(&ctx, command, &mut state) {
+            Ok(events) => {
+                let announce = state.initialized || command.emit_existing;
+                state.initialized = true;
+                if announce {
+                    let observed_at = unix_timestamp_secs();
+                    for mut event in events {
+                        event["observed_at"] = json!(observed_at);
+                        let (line, handled) = on_event(&event);
+                        write_watch_event(out, io, &line);
+                        emitted += 1;
+                        if !handled {
+                            state.forget(&event, &before);
+                        }
+                    }
+                }
+                if let Some(path) = &command.state_file {
+                    state
+                        .save(path)
+                        .map_err(|error| CliError::new(ErrorCode::TransportError, error))?;
+                }
+            }
+            Err(error) if error.code.retryable() => {
+                write_watch_event(
+                    out,
+                    io,
+                    &json!({
+                        "type": "error",
+                        "code": error.code.as_str(),
+                        "message": error.message,
+                        "observed_at": unix_timestamp_secs(),
+                    }),
+                );
+            }
+            Err(error) => return Err(error),
+        }
+        if command.max_polls.is_some_and(|max| polls >= max) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_secs(command.interval));
+    }
+
+    Ok(WatchSummary {
+        polls,
+        events: emitted,
+        meta: context_meta(&ctx),
+    })
+}
+
+fn poll_feed_once(
+    ctx: &ClientContext,
+    command: &cli::FeedWatchArgs,
+    state: &mut watch::WatchState,
+) -> Result<Vec<Value>, CliError> {
+    let sections = ctx.client.get_feed_sections(&FeedSectionsInput {
+        kind: command.kind.clone(),
+        group_id: command.group_id.clone(),
+        first: command.first,
+        after: None,
+    })?;
+    let mut events = state.observe_notes(sections.as_array().map_or(&[], Vec::as_slice));
+    if command.comments {
+        let comments = ctx.client.search_note(&SearchNoteInput {
+            resources: vec!["COMMENT".to_string()],
+            group_ids: vec![command.group_id.clone()],
+            first: command.first,
+            ..SearchNoteInput::new("")
+        })?;
+        events.extend(state.observe_comments(comments.as_array().map_or(&[], Vec::as_slice)));
+    }
+    Ok(events)
+}
+
+fn write_watch_event(out: OutputContext, io: &mut dyn OutputSink, event: &Value) {
+    if out.json {
+        out.stdout(io, &format!("{event}\n"));
+    } else {
+        let field = |key: &str| event.get(key).and_then(Value::as_str).unwrap_or_default();
+        let detail = if field("type") == "error" {
+            field("message")
+        } else {
+            field("title")
+        };
+        out.stdout(
+            io,
+            &format!("{}\t{}\t{detail}\n", field("type"), field("id")),
+        );
+    }
+}
+
+/// Returns the `YYYY-MM-DD` part of a feed section's date.
+fn feed_section_date(section: &Value) -> Option<&str> {
+    section
+        .pointer("/node/date")
+        .and_then(Value::as_str)
+        .and_then(|date| date.get(..10))
+}
+
+fn execute_comment(
+    cli: &cli::Cli,
+    args: &cli::CommentArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+
+    match &args.command {
+        cli::CommentCommand::Create(command) => {
+            let note_id = resolve_note_id(&ctx.client, &command.note_id)?;
+            let secret_scan = scan_outgoing(cli, &[("content", &command.content)])?;
+            if command.inline {
+                let mut output = create_inline_comment(&ctx, command, note_id)?;
+                output.data["secret_scan"] = secret_scan.clone();
+                output.message = with_secret_warning(&output.message, &secret_scan);
+                return Ok(output);
+            }
+            if command.suppress_duplicate {
+                if let Some(existing) =
+                    find_duplicate_comment(&ctx, &note_id, &command.content, command.window)?
+                {
+                    return Ok(CommandOutput {
+                        data: json!({
+                            "comment": Value::Null,
+                            "skipped": true,
+                            "duplicate_of": {
+                                "id": existing.get("id").cloned().unwrap_or(Value::Null),
+                                "publishedAt": existing
+                                    .get("publishedAt")
+                                    .cloned()
+                                    .unwrap_or(Value::Null),
+                            },
+                            "meta": context_meta(&ctx),
+                        }),
+                        message: "comment create skipped (duplicate)".to_string(),
+                    });
+                }
+            }
+            let comment = ctx.client.create_comment(&CreateCommentInput {
+                content: command.content.clone(),
+                note_id,
+            })?;
+            let mut data = json!({
+                "comment": comment,
+                "secret_scan": secret_scan,
+                "meta": context_meta(&ctx),
+            });
+            if command.suppress_duplicate {
+                data["skipped"] = Value::Bool(false);
+            }
+            Ok(CommandOutput {
+                data,
+                message: with_secret_warning("comment create completed", &secret_scan),
+            })
+        }
+        cli::CommentCommand::Reply(command) => {
+            let secret_scan = scan_outgoing(cli, &[("content", &command.content)])?;
+            let reply = ctx.client.create_comment_reply(&CreateCommentReplyInput {
+                content: command.content.clone(),
+                comment_id: command.comment_id.clone(),
+            })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "reply": reply,
+                    "secret_scan": secret_scan,
+                    "meta": context_meta(&ctx),
+                }),
+                message: with_secret_warning("comment reply completed", &secret_scan),
+            })
+        }
+        cli::CommentCommand::Edit(command) => {
+            let secret_scan = scan_outgoing(cli, &[("content", &command.content)])?;
+            let comment = ctx.client.update_comment(&UpdateCommentInput {
+                id: command.id.clone(),
+                content: command.content.clone(),
+            })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "comment": comment,
+                    "secret_scan": secret_scan,
+                    "meta": context_meta(&ctx),
+                }),
+                message: with_secret_warning("comment edit completed", &secret_scan),
+            })
+        }
+        cli::CommentCommand::Delete(command) => {
+            if !command.yes && !confirm_comment_delete(&command.id)? {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "comment delete was not confirmed; pass --yes to delete without a prompt",
+                ));
+            }
+            let deleted = ctx.client.delete_comment(&DeleteCommentInput {
+                id: command.id.clone(),
+            })?;
+            Ok(CommandOutput {
+                data: json!({
+                    "deleted": deleted,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "comment delete completed".to_string(),
+            })
+        }
+        cli::CommentCommand::Thread(command) => {
+            let comment = ctx.client.get_comment_thread(&command.comment_id)?;
+            let thread = comment_thread_node(&comment);
+            let mut message = String::new();
+            comment_thread_text(&thread, 0, &mut message);
+            let truncated = comment
+                .pointer("/replies/pageInfo/hasNextPage")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            if truncated {
+                message.push_str("(more replies not shown)\n");
+            }
+            Ok(CommandOutput {
+                data: json!({
+                    "thread": thread,
+                    "truncated": truncated,
+                    "meta": context_meta(&ctx),
+                }),
+                message: message.trim_end().to_string(),
+            })
+        }
+    }
+}
+
+/// A comment or reply with the replies under it, oldest first.
+fn comment_thread_node(comment: &Value) -> Value {
+    let replies = comment
+        .pointer("/replies/nodes")
+        .and_then(Value::as_array)
+        .map(|replies| replies.iter().map(comment_thread_node).collect::<Vec<_>>())
+        .unwrap_or_default();
+    json!({
+        "id": comment["id"],
+        "author": comment["author"],
+        "publishedAt": comment["publishedAt"],
+        "content": comment["content"],
+        "replies": replies,
+    })
+}
+
+/// Renders `node` as a header line and its content, with replies indented
+/// four spaces per level below it.
+fn comment_thread_text(node: &Value, depth: usize, out: &mut String) {
+    let indent = "    ".repeat(depth);
+    out.push_str(&format!(
+        "{indent}@{} {} [{}]\n",
+        node.pointer("/author/account")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown"),
+        node["publishedAt"].as_str().unwrap_or_default(),
+        node["id"].as_str().unwrap_or_default(),
+    ));
+    for line in node["content"].as_str().unwrap_or_default().lines() {
+        out.push_str(&format!("{indent}  {line}\n"));
+    }
+    for reply in node["replies"].as_array().into_iter().flatten() {
+        comment_thread_text(reply, depth + 1, out);
+    }
+}
+
+/// Posts `comment create --inline`, resolving `--anchor` against the note
+/// content first so a passage that is not in the note fails before posting.
+fn create_inline_comment(
+    ctx: &ClientContext,
+    command: &cli::CommentCreateArgs,
+    note_id: String,
+) -> Result<CommandOutput, CliError> {
+    let anchor = command.anchor.clone().unwrap_or_default();
+    let note = ctx.client.get_note(&note_id)?;
+    let occurrence = usize::try_from(command.anchor_occurrence).unwrap_or(usize::MAX);
+    let found = anchor::find_anchor(&note.content, &anchor, occurrence).map_err(|occurrences| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "--anchor matches the note content {occurrences} time(s); \
+                 occurrence {occurrence} was requested"
+            ),
+        )
+        .with_details(json!({
+            "anchor": anchor,
+            "occurrences": occurrences,
+        }))
+    })?;
+    let comment = ctx
+        .client
+        .create_inline_comment(&CreateInlineCommentInput {
+            content: command.content.clone(),
+            note_id,
+            anchor: anchor.clone(),
+            anchor_position: Some(u32::try_from(found.position).unwrap_or(u32::MAX)),
+        })?;
+    Ok(CommandOutput {
+        data: json!({
+            "comment": comment,
+            "inline": {
+                "anchor": anchor,
+                "position": found.position,
+                "occurrences": found.occurrences,
+            },
+            "meta": context_meta(ctx),
+        }),
+        message: "comment create completed".to_string(),
+    })
+}
+
+/// Asks for confirmation on a TTY; without one, deletion requires `--yes`.
+fn confirm_comment_delete(id: &str) -> Result<bool, CliError> {
+    if !is_interactive_terminal() {
+        return Ok(false);
+    }
+    let answer = read_prompt_line(&format!("Delete comment {id}? [y/N]"))?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Looks for a comment by the current user on `note_id` within the last
+/// `window_secs` whose content matches `content` after normalization.
+fn find_duplicate_comment(
+    ctx: &ClientContext,
+    note_id: &str,
+    content: &str,
+    window_secs: u64,
+) -> Result<Option<Value>, CliError> {
+    let account = ctx.client.get_current_user_account()?;
+    let recent = ctx.client.get_note_comments(&NoteCommentsInput {
+        note_id: note_id.to_string(),
+        last: Some(dedupe::RECENT_COMMENT_LIMIT),
+    })?;
+    let comments = recent
+        .get("comments")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let now = i64::try_from(unix_timestamp_secs()).unwrap_or(i64::MAX);
+    let cutoff = now.saturating_sub(i64::try_from(window_secs).unwrap_or(i64::MAX));
+    Ok(dedupe::find_duplicate(comments, &account, content, cutoff).cloned())
+}
+
+/// Most recent comments `note copy --with-comments` carries over.
+const COPY_COMMENT_LIMIT: u32 = 100;
+
+/// Renders comments as a Markdown section of quotes, oldest first, to append
+/// to a copied note. Empty when there are no comments.
+fn quoted_comments_section(comments: &[Value]) -> String {
+    let mut section = String::new();
+    for comment in comments {
+        let field = |pointer: &str| comment.pointer(pointer).and_then(Value::as_str);
+        let author = field("/author/account").unwrap_or("unknown");
+        let header = match field("/publishedAt") {
+            Some(published_at) => format!("> **@{author}** ({published_at})"),
+            None => format!("> **@{author}**"),
+        };
+        section.push_str("\n\n");
+        section.push_str(&header);
+        for line in field("/content").unwrap_or_default().lines() {
+            section.push_str("\n>");
+            if !line.is_empty() {
+                section.push(' ');
+                section.push_str(line);
+            }
+        }
+    }
+    if section.is_empty() {
+        return section;
+    }
+    format!("\n\n---\n\n## Comments{section}\n")
+}
+
+/// Fails with `PRECONDITION_FAILED` when `current` (a `getNoteMetadata`
+/// note) was updated after the snapshot described by `front` was taken.
+/// Snapshots without `updatedAt` are not checked.
+fn ensure_unchanged_since(
+    front: Option<&frontmatter::FrontMatter>,
+    current: &Value,
+    hint: &str,
+) -> Result<(), CliError> {
+    let expected = front.and_then(|front| front.updated_at.as_deref());
+    let actual = current["updatedAt"].as_str();
+    if expected.is_none() || expected == actual {
+        return Ok(());
+    }
+    Err(CliError::new(
+        ErrorCode::PreconditionFailed,
+        format!("note changed since the snapshot was taken; {hint}"),
+    )
+    .with_details(json!({ "expected": expected, "actual": actual })))
+}
+
+/// `note update --from-file`: the file's front matter (if any) and body.
+fn read_note_file(path: &Path) -> Result<(Option<frontmatter::FrontMatter>, String), CliError> {
+    let raw = fs::read_to_string(path).map_err(|error| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            format!("failed to read {}: {error}", path.display()),
+        )
+    })?;
+    let (front, body) = frontmatter::parse(&raw).map_err(|error| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            format!("invalid front matter in {}: {error}", path.display()),
+        )
+    })?;
+    Ok((front, body.to_string()))
+}
+
+/// `--normalize`: runs the content pipeline, uploading local images (paths
+/// relative to the working directory) as attachments. Returns the content to
+/// send and `data.normalized` (`null` when not requested).
+fn normalize_content(
+    ctx: &ClientContext,
+    specs: &[String],
+    content: String,
+) -> Result<(String, Value), CliError> {
+    normalize_content_in(ctx, specs, content, Path::new(""))
+}
+
+/// [`normalize_content`] with local image paths relative to `base`.
+fn normalize_content_in(
+    ctx: &ClientContext,
+    specs: &[String],
+    content: String,
+    base: &Path,
+) -> Result<(String, Value), CliError> {
+    let normalizer = content::Normalizer::parse(specs)
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+    if normalizer.is_empty() {
+        return Ok((content, Value::Null));
+    }
+    let origin = ctx.client.origin().trim_end_matches('/').to_string();
+    let mut attachments = Vec::new();
+    let mut upload_error = None;
+    let mut upload = |target: &str| -> Result<Option<String>, String> {
+        let path = base.join(target);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let uploaded = fs::read(&path)
+            .map_err(|error| {
+                CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("failed to read {target}: {error}"),
+                )
+            })
+            .and_then(|data| {
+                let bytes = data.len();
+                ctx.client
+                    .upload_attachment(&UploadAttachmentInput {
+                        name: transfer::attachment_name(target),
+                        data,
+                    })
+                    .map(|uploaded| (uploaded, bytes))
+                    .map_err(CliError::from)
+            });
+        match uploaded {
+            Ok((uploaded, bytes)) => {
+                let url = format!("{origin}{}", uploaded.path);
+                attachments.push(json!({
+                    "from": target,
+                    "to": url,
+                    "id": uploaded.id,
+                    "bytes": bytes,
+                }));
+                Ok(Some(url))
+            }
+            Err(error) => {
+                let message = error.message.clone();
+                upload_error = Some(error);
+                Err(message)
+            }
+        }
+    };
+    let result = normalizer.apply(&content, &mut upload);
+    match (result, upload_error) {
+        (Ok((content, changes)), _) => Ok((
+            content,
+            json!({ "changes": changes, "attachments": attachments }),
+        )),
+        (Err(_), Some(error)) => Err(error),
+        (Err(error), None) => Err(CliError::new(ErrorCode::InputInvalid, error)),
+    }
+}
+
+/// Resolves `note create` title/content, rendering `--template` when given.
+///
+/// Explicit `--title`/`--content` win over the template; `{{author}}` is only
+/// looked up when the template uses it and `--var author=...` is absent.
+fn note_create_title_and_content(
+    cli: &cli::Cli,
+    command: &cli::NoteCreateArgs,
+    ctx: &ClientContext,
+) -> Result<(String, String), CliError> {
+    let Some(name) = command.template.as_deref() else {
+        return Ok((
+            command.title.clone().unwrap_or_default(),
+            command.content.clone().unwrap_or_default(),
+        ));
+    };
+    let (config_path, config) = load_config(cli)?;
+    let templates_dir = config.templates_dir(&config_path);
+    let note_template = template::load_template(&templates_dir, name).map_err(|message| {
+        CliError::new(ErrorCode::NotFound, message)
+            .with_details(json!({ "templates_dir": templates_dir }))
+    })?;
+
+    let mut vars = BTreeMap::from([("date".to_string(), template::today_utc())]);
+    vars.extend(command.vars.iter().cloned());
+    if !vars.contains_key("author")
+        && note_template
+            .placeholders()
+            .iter()
+            .any(|name| name == "author")
+    {
+        vars.insert("author".to_string(), ctx.client.get_current_user_account()?);
+    }
+
+    let render = |text: &str| {
+        template::render_template(text, &vars).map_err(|message| {
+            CliError::new(ErrorCode::InputInvalid, message).with_details(json!({
+                "template": note_template.name,
+                "hint": "pass missing values with --var KEY=VALUE",
+            }))
+        })
+    };
+    let title = match (&command.title, &note_template.title) {
+        (Some(title), _) => title.clone(),
+        (None, Some(title)) => render(title)?,
+        (None, None) => {
+            return Err(CliError::new(
+                ErrorCode::InputInvalid,
+                format!(
+                    "template `{}` has no `title:` front matter; pass --title",
+                    note_template.name
+                ),
+            ))
+        }
+    };
+    let content = match &command.content {
+        Some(content) => content.clone(),
+        None => render(&note_template.body)?,
+    };
+    Ok((title, content))
+}
+
+/// Profile defaults `note create` applied because their flags were omitted.
+#[derive(Debug, Default)]
+struct NoteCreateDefaults {
+    group: Option<String>,
+    folder_path: Option<String>,
+    coediting: Option<bool>,
+}
+
+impl NoteCreateDefaults {
+    fn to_json(&self) -> Value {
+        let mut applied = serde_json::Map::new();
+        if let Some(group) = &self.group {
+            applied.insert("default_group".to_string(), json!(group));
+        }
+        if let Some(path) = &self.folder_path {
+            applied.insert("default_folder_path".to_string(), json!(path));
+        }
+        if let Some(coediting) = self.coediting {
+            applied.insert("default_coediting".to_string(), json!(coediting));
+        }
+        Value::Object(applied)
+    }
+}
+
+/// Picks the team profile's `default_group` / `default_folder_path` when no
+/// `--group-id`, `--folder`, or `--folder-path` is given, and
+/// `default_coediting` when neither `--coediting` nor `--no-coediting` is.
+fn note_create_defaults(
+    cli: &cli::Cli,
+    ctx: &ClientContext,
+    command: &cli::NoteCreateArgs,
+) -> Result<NoteCreateDefaults, CliError> {
+    let (_, config) = load_config(cli)?;
+    let Some(profile) = config.profiles.get(ctx.team.as_deref().unwrap_or_default()) else {
+        return Ok(NoteCreateDefaults::default());
+    };
+    let placed = !command.group_ids.is_empty()
+        || !command.folders.is_empty()
+        || command.folder_path.is_some();
+    let chose_coediting = command.coediting || command.no_coediting;
+    Ok(NoteCreateDefaults {
+        group: profile.default_group.clone().filter(|_| !placed),
+        folder_path: profile.default_folder_path.clone().filter(|_| !placed),
+        coediting: profile.default_coediting.filter(|_| !chose_coediting),
+    })
+}
+
+/// Resolves the `@account` mentions of rendered template content to user
+/// ids, so a mistyped account fails before the note is created instead of
+/// notifying nobody.
+fn template_mentions(ctx: &ClientContext, content: &str, refresh: bool) -> Result<Value, CliError> {
+    content::analysis::mentions(content)
+        .into_iter()
+        .map(|account| {
+            let id = resolve_user_id(ctx, &account, refresh).map_err(|error| {
+                if error.code != ErrorCode::NotFound {
+                    return error;
+                }
+                error.with_details(json!({
+                    "account": account,
+                    "hint": "fix the mention in the template or --var",
+                }))
+            })?;
+            Ok(json!({ "account": account, "id": id }))
+        })
+        .collect::<Result<Vec<_>, CliError>>()
+        .map(Value::from)
+}
+
+#[allow(clippy::too_many_lines)]
+fn execute_note(
+    cli: &cli::Cli,
+    args: &cli::NoteArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+) -> Result<CommandOutput, CliError> {
+    if let cli::NoteCommand::Transfer(command) = &args.command {
+        return execute_note_transfer(cli, command);
+    }
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+
+    match &args.command {
+        cli::NoteCommand::Create(command) => {
+            let (title, content) = note_create_title_and_content(cli, command, &ctx)?;
+            let mentions = if command.template.is_some() && command.content.is_none() {
+                template_mentions(&ctx, &content, command.refresh_users)?
+            } else {
+                Value::Null
+            };
+            let (content, normalized) = normalize_content(&ctx, &command.normalize, content)?;
+            let secret_scan = scan_outgoing(cli, &[("title", &title), ("content", &content)])?;
+            let check = if command.check {
+                Some(pre_publish_check(cli, &ctx, &content, command.force)?)
+            } else {
+                None
+            };
+            let client_mutation_id = command.client_mutation_id.clone();
+            let mut folders = command
+                .folders
+                .iter()
+                .map(note_folder_arg_to_input)
+                .collect::<Vec<_>>();
+            let mut group_ids = command.group_ids.clone();
+            let defaults = note_create_defaults(cli, &ctx, command)?;
+            if let Some(group) = &defaults.group {
+                group_ids.push(group.clone());
+            }
+            let coediting = command.coediting || defaults.coediting.unwrap_or(false);
+            let folder_path = command
+                .folder_path
+                .as_deref()
+                .or(defaults.folder_path.as_deref());
+            let folder = match folder_path {
+                Some(path) => {
+                    let ensured = ctx.client.ensure_folder_path(&EnsureFolderPathInput {
+                        path: path.to_string(),
+                        create_missing: command.create_missing,
+                    })?;
+                    if !group_ids.contains(&ensured.group_id) {
+                        group_ids.push(ensured.group_id.clone());
+                    }
+                    folders.push(CreateNoteFolderInput {
+                        group_id: ensured.group_id.clone(),
+                        folder_name: ensured.folder_name.clone(),
+                    });
+                    Some(ensured)
+                }
+                None => None,
+            };
+            let created = ctx.client.create_note(&CreateNoteInput {
+                title,
+                content,
+                group_ids,
+                draft: if command.draft { Some(true) } else { None },
+                coediting,
+                folders,
+                author_id: command.author_id.clone(),
+                published_at: command.published_at.clone(),
+                client_mutation_id: client_mutation_id.clone(),
+            })?;
+            let copied = copy_note(cli, &command.copy, &created.note.id, ctx.client.origin())?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": created.note,
+                    "template": command.template,
+                    "mentions": mentions,
+                    "folder": folder,
+                    "normalized": normalized,
+                    "check": check,
+                    "secret_scan": secret_scan,
+                    "copied": copied,
+                    "meta": {
+                        "team": ctx.team,
+                        "origin": ctx.client.origin(),
+                        "token_source": ctx.token_source,
+                        "config_layers": ctx.config_layers,
+                        "client_mutation_id": created.client_mutation_id.or(client_mutation_id),
+                        "defaults": defaults.to_json(),
+                    }
+                }),
+                message: with_secret_warning("note create completed", &secret_scan),
+            })
+        }
+        cli::NoteCommand::Get(command) => {
+            let pipeline = resolve_on_fetch(cli, &command.on_fetch, &ctx)?;
+            let id = resolve_note_id(&ctx.client, &command.id)?;
+            let (mut note, front_matter) = match command.format {
+                cli::NoteFormat::Json => (ctx.client.get_note(&id)?, None),
+                cli::NoteFormat::Markdown => {
+                    let value = ctx.client.get_note_metadata(&id)?;
+                    let text = |key: &str| value[key].as_str().unwrap_or_default().to_string();
+                    let note = Note {
+                        id: text("id"),
+                        title: text("title"),
+                        content: text("content"),
+                    };
+                    (note, Some(frontmatter::FrontMatter::from_note(&value)))
+                }
+            };
+            note.content = pipeline.apply(&note.content, ctx.client.origin());
+            let markdown = front_matter
+                .as_ref()
+                .map(|front| frontmatter::render(front, &note.content));
+            let opened = if command.open {
+                let url = note_path_from_id(&note.id)
+                    .map(|path| path.url(ctx.client.origin()))
+                    .ok_or_else(|| {
+                        CliError::new(
+                            ErrorCode::InputInvalid,
+                            format!("cannot derive a URL for note {}", note.id),
+                        )
+                    })?;
+                open_in_browser(cli, &url)?
+            } else {
+                Value::Null
+            };
+            let copied = copy_note(cli, &command.copy, &note.id, ctx.client.origin())?;
+            let stats = content::analysis::analyze(&note.content);
+            let message = markdown.clone().unwrap_or_else(|| {
+                format!(
+                    "note get completed ({} words, ~{} min read, {} heading(s))",
+                    stats.words,
+                    stats.reading_minutes,
+                    stats.outline.len()
+                )
+            });
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": note,
+                    "content_stats": stats,
+                    "front_matter": front_matter.map(|front| front.to_json()),
+                    "markdown": markdown,
+                    "opened": opened,
+                    "copied": copied,
+                    "meta": on_fetch_meta(&ctx, &pipeline),
+                }),
+                message,
+            })
+        }
+        cli::NoteCommand::GetMany(command) => {
+            let ids = command
+                .ids
+                .iter()
+                .filter_map(|id| normalize_owned(id))
+                .collect::<Vec<_>>();
+            if ids.is_empty() {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "at least one --id is required for note get-many",
+                ));
+            }
+            let pipeline = resolve_on_fetch(cli, &command.on_fetch, &ctx)?;
+            let mut notes = Vec::with_capacity(ids.len());
+            for reference in ids {
+                let id = resolve_note_id(&ctx.client, &reference)?;
+                let note = ctx.client.get_note(&id)?;
+                notes.push(json!({
+                    "id": note.id,
+                    "title": note.title,
+                    "content": pipeline.apply(&note.content, ctx.client.origin()),
+                }));
+            }
+
+            Ok(CommandOutput {
+                data: json!({
+                    "notes": notes,
+                    "meta": on_fetch_meta(&ctx, &pipeline),
+                }),
+                message: "note get-many completed".to_string(),
+            })
+        }
+        cli::NoteCommand::GetFromPath(command) => {
+            let pipeline = resolve_on_fetch(cli, &command.on_fetch, &ctx)?;
+            let mut note = ctx.client.get_note_from_path(&PathLookupInput {
+                path: command.path.clone(),
+                first: command.first,
+            })?;
+            if let Some(Value::String(content)) = note.get_mut("content") {
+                *content = pipeline.apply(content, ctx.client.origin());
+            }
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": note,
+                    "meta": on_fetch_meta(&ctx, &pipeline),
+                }),
+                message: "note get-from-path completed".to_string(),
+            })
+        }
+        cli::NoteCommand::Update(command) => {
+            let source = command
+                .from_file
+                .as_deref()
+                .map(read_note_file)
+                .transpose()?;
+            let group_ids = (!command.group_ids.is_empty()).then(|| command.group_ids.clone());
+            let mut title = command.title.clone();
+            let has_metadata = |title: &Option<String>| {
+                title.is_some() || command.coediting.is_some() || group_ids.is_some()
+            };
+            let mut content = command
+                .base_content
+                .clone()
+                .zip(command.new_content.clone());
+            if source.is_none()
+                && content.is_none()
+                && command.section.is_none()
+                && !has_metadata(&title)
+            {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "nothing to update: pass --base-content/--new-content, --from-file, --section, --title, --coediting, or --group-id",
+                ));
+            }
+            let raw_id = command
+                .id
+                .clone()
+                .or_else(|| {
+                    source
+                        .as_ref()
+                        .and_then(|(front, _)| front.as_ref()?.id.clone())
+                })
+                .ok_or_else(|| {
+                    CliError::new(
+                        ErrorCode::InputInvalid,
+                        "--from-file has no `id` in its front matter; pass --id",
+                    )
+                })?;
+            let id = resolve_note_id(&ctx.client, &raw_id)?;
+            if let Some((front, body)) = &source {
+                let current = ctx.client.get_note_metadata(&id)?;
+                ensure_unchanged_since(
+                    front.as_ref(),
+                    &current,
+                    "run `note get --format markdown` again and reapply your edits",
+                )?;
+                let current_title = current["title"].as_str();
+                if title.is_none() {
+                    title = front
+                        .as_ref()
+                        .and_then(|front| front.title.clone())
+                        .filter(|front_title| Some(front_title.as_str()) != current_title);
+                }
+                let base_content = current["content"].as_str().unwrap_or_default();
+                if base_content != body {
+                    content = Some((base_content.to_string(), body.clone()));
+                }
+            }
+            let mut section = Value::Null;
+            if let (Some(selector), Some(text)) = (&command.section, &command.section_content) {
+                // The fetched content is the base, so a concurrent edit
+                // anywhere in the note fails the update instead of being lost.
+                let current = ctx.client.get_note_metadata(&id)?;
+                let base_content = current["content"].as_str().unwrap_or_default();
+                let (edited, heading) = content::section::edit_section(
+                    base_content,
+                    selector,
+                    text,
+                    command.section_mode,
+                )
+                .map_err(|error| match error {
+                    content::section::SectionError::Missing(message) => {
+                        CliError::new(ErrorCode::NotFound, message)
+                    }
+                    content::section::SectionError::Ambiguous(message) => {
+                        CliError::new(ErrorCode::InputInvalid, message)
+                    }
+                })?;
+                let changed = edited != base_content;
+                section = json!({
+                    "heading": heading,
+                    "mode": command.section_mode.as_str(),
+                    "changed": changed,
+                });
+                if changed {
+                    content = Some((base_content.to_string(), edited));
+                }
+            }
+            let mut note = Value::Null;
+            let mut normalized = Value::Null;
+            let content = match content {
+                Some((base_content, new_content)) => {
+                    let (new_content, changes) =
+                        normalize_content(&ctx, &command.normalize, new_content)?;
+                    normalized = changes;
+                    Some((base_content, new_content))
+                }
+                None => None,
+            };
+            let mut outgoing = Vec::new();
+            if let Some(title) = &title {
+                outgoing.push(("title", title.as_str()));
+            }
+            if let Some((_, new_content)) = &content {
+                outgoing.push(("content", new_content.as_str()));
+            }
+            let secret_scan = scan_outgoing(cli, &outgoing)?;
+            if let Some((base_content, new_content)) = content {
+                note = json!(ctx.client.update_note(&UpdateNoteInput {
+                    id: id.clone(),
+                    base_content,
+                    new_content,
+                })?);
+            }
+            if has_metadata(&title) {
+                note = ctx.client.update_note_metadata(&UpdateNoteMetadataInput {
+                    id: id.clone(),
+                    title,
+                    coediting: command.coediting,
+                    group_ids: group_ids.clone(),
+                })?;
+            }
+            let mut file = Value::Null;
+            if let (Some(path), Some((Some(_), _))) = (&command.from_file, &source) {
+                // Refresh the front matter so the next push checks against
+                // this revision instead of the exported one.
+                let fresh = ctx.client.get_note_metadata(&id)?;
+                let front = frontmatter::FrontMatter::from_note(&fresh);
+                let markdown =
+                    frontmatter::render(&front, fresh["content"].as_str().unwrap_or_default());
+                fs::write(path, format!("{markdown}\n")).map_err(|error| {
+                    CliError::new(
+                        ErrorCode::TransportError,
+                        format!("failed to rewrite {}: {error}", path.display()),
+                    )
+                })?;
+                file = json!({
+                    "path": path.display().to_string(),
+                    "front_matter": front.to_json(),
+                });
+            }
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": note,
+                    "normalized": normalized,
+                    "secret_scan": secret_scan,
+                    "file": file,
+                    "section": section,
+                    "meta": context_meta(&ctx),
+                }),
+                message: with_secret_warning("note update completed", &secret_scan),
+            })
+        }
+        cli::NoteCommand::Append(command) => {
+            execute_note_add(cli, &ctx, command, content::addition::Placement::End)
+        }
+        cli::NoteCommand::Prepend(command) => {
+            execute_note_add(cli, &ctx, command, content::addition::Placement::Start)
+        }
+        cli::NoteCommand::Restore(command) => {
+            let label = command.from.display().to_string();
+            let raw = fs::read_to_string(&command.from).map_err(|error| {
+                CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("failed to read {label}: {error}"),
+                )
+            })?;
+            let restored = snapshot::parse_snapshot(&raw).map_err(|error| {
+                CliError::new(ErrorCode::InputInvalid, format!("{label}: {error}"))
+            })?;
+            let id = resolve_note_id(&ctx.client, &command.id)?;
+            let front = restored.front.as_ref();
+            if let Some(snapshot_id) = front.and_then(|front| front.id.as_deref()) {
+                if !command.force && resolve_note_id(&ctx.client, snapshot_id)? != id {
+                    return Err(CliError::new(
+                        ErrorCode::InputInvalid,
+                        format!("{label} is a snapshot of note {snapshot_id}, not {}; pass --force to restore it anyway", command.id),
+                    ));
+                }
+            }
+            let current = ctx.client.get_note_metadata(&id)?;
+            if !command.force {
+                ensure_unchanged_since(
+                    front,
+                    &current,
+                    "review it with `note diff`, then pass --force to overwrite those changes",
+                )?;
+            }
+
+            let base_content = current["content"].as_str().unwrap_or_default();
+            let content_changed = base_content != restored.content;
+            let title = front
+                .and_then(|front| front.title.clone())
+                .filter(|title| Some(title.as_str()) != current["title"].as_str());
+            let mut outgoing = Vec::new();
+            if let Some(title) = &title {
+                outgoing.push(("title", title.as_str()));
+            }
+            if content_changed {
+                outgoing.push(("content", restored.content.as_str()));
+            }
+            let secret_scan = scan_outgoing(cli, &outgoing)?;
+            let mut note = Value::Null;
+            if content_changed {
+                note = json!(ctx.client.update_note(&UpdateNoteInput {
+                    id: id.clone(),
+                    base_content: base_content.to_string(),
+                    new_content: restored.content.clone(),
+                })?);
+            }
+            let title_changed = title.is_some();
+            if title_changed {
+                note = ctx.client.update_note_metadata(&UpdateNoteMetadataInput {
+                    id,
+                    title,
+                    coediting: None,
+                    group_ids: None,
+                })?;
+            }
+            let message = if content_changed || title_changed {
+                format!("restored {} from {label}", command.id)
+            } else {
+                format!("{} already matches {label}", command.id)
+            };
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": note,
+                    "restored": {
+                        "from": label,
+                        "updatedAt": front.and_then(|front| front.updated_at.clone()),
+                        "content_changed": content_changed,
+                        "title_changed": title_changed,
+                    },
+                    "secret_scan": secret_scan,
+                    "meta": context_meta(&ctx),
+                }),
+                message: with_secret_warning(&message, &secret_scan),
+            })
+        }
+        cli::NoteCommand::Diff(command) => {
+            let label = command.against.display().to_string();
+            let raw = fs::read_to_string(&command.against).map_err(|error| {
+                CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("failed to read {label}: {error}"),
+                )
+            })?;
+            let local = snapshot::parse_snapshot(&raw).map_err(|error| {
+                CliError::new(ErrorCode::InputInvalid, format!("{label}: {error}"))
+            })?;
+            let id = resolve_note_id(&ctx.client, &command.id)?;
+            let current = ctx.client.get_note_metadata(&id)?;
+            let remote = current["content"].as_str().unwrap_or_default();
+            let diff = diff::unified_diff(
+                remote,
+                &local.content,
+                &format!("kibela:{}", command.id),
+                &label,
+                command.context,
+            );
+            let hunks = diff::diff_hunks(remote, &local.content, command.context);
+            // A snapshot exported before the note last changed diffs against
+            // someone else's edits too.
+            let exported_at = local
+                .front
+                .as_ref()
+                .and_then(|front| front.updated_at.clone());
+            let stale = exported_at
+                .as_deref()
+                .map(|exported_at| Some(exported_at) != current["updatedAt"].as_str());
+            let message = match &diff {
+                Some(diff) if out.color => diff::colorize(diff),
+                Some(diff) => diff.clone(),
+                None => "no differences\n".to_string(),
+            };
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": {
+                        "id": current["id"],
+                        "title": current["title"],
+                        "updatedAt": current["updatedAt"],
+                    },
+                    "against": {
+                        "path": label,
+                        "updatedAt": exported_at,
+                        "stale": stale,
+                    },
+                    "changed": diff.is_some(),
+                    "hunks": hunks,
+                    "diff": diff,
+                    "meta": context_meta(&ctx),
+                }),
+                message: message.trim_end_matches('\n').to_string(),
+            })
+        }
+        cli::NoteCommand::Download(command) => {
+            let id = resolve_note_id(&ctx.client, &command.id)?;
+            let value = ctx.client.get_note_metadata(&id)?;
+            let dir = &command.with_assets;
+            let exporter = export::exporter(command.layout);
+            let note = export::ExportNote::from_note(&value);
+            let (content, assets) = mirror_attachments(
+                &ctx,
+                value["content"].as_str().unwrap_or_default(),
+                &dir.join(exporter.assets_dir(&note)),
+                exporter.attachment_prefix(),
+                command.max_attachment_mib,
+            )?;
+
+            let mut targets = export::LinkTargets {
+                origin: ctx.client.origin().to_string(),
+                ..export::LinkTargets::default()
+            };
+            if exporter.needs_link_titles() {
+                for link in links::note_links(&content, ctx.client.origin()) {
+                    if let Some(title) = linked_note_title(&ctx.client, &link.path.to_string())? {
+                        targets.titles.insert(link.link, title);
+                    }
+                }
+            }
+            let content = exporter.rewrite_links(&content, &targets);
+            let mut markdown = exporter.render(&note, &content);
+            if !markdown.ends_with('\n') {
+                markdown.push('\n');
+            }
+            let note_path = dir.join(exporter.note_path(&note));
+            write_output_file(&note_path, markdown.as_bytes())?;
+            let mut scaffolded = Vec::new();
+            for (path, text) in exporter.scaffold(&note) {
+                let path = dir.join(path);
+                if !path.exists() {
+                    write_output_file(&path, text.as_bytes())?;
+                    scaffolded.push(path.display().to_string());
+                }
+            }
+
+            Ok(CommandOutput {
+                data: json!({
+                    "path": note_path.display().to_string(),
+                    "note": { "id": value["id"], "title": value["title"] },
+                    "assets": assets,
+                    "scaffolded": scaffolded,
+                    "meta": context_meta(&ctx),
+                }),
+                message: note_path.display().to_string(),
+            })
+        }
+        cli::NoteCommand::Outline(command) => {
+            let id = resolve_note_id(&ctx.client, &command.id)?;
+            let note = ctx.client.get_note(&id)?;
+            let url = note_path_from_id(&note.id).map(|path| path.url(ctx.client.origin()));
+            if command.links && url.is_none() {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("cannot derive a URL for note {}", note.id),
+                ));
+            }
+            let max_level = command.max_level.map_or(6, usize::from);
+            let headings = content::analysis::outline(&note.content)
+                .into_iter()
+                .filter(|heading| heading.level <= max_level)
+                .collect::<Vec<_>>();
+            let count = headings.len();
+            let tree =
+                content::analysis::outline_tree(headings, url.as_deref().filter(|_| command.links));
+            let mut lines = vec![format!("{} ({count} heading(s))", note.title)];
+            outline_text(&tree, 0, &mut lines);
+            Ok(CommandOutput {
+                data: json!({
+                    "note": {"id": note.id, "title": note.title, "url": url},
+                    "outline": tree,
+                    "count": count,
+                    "meta": context_meta(&ctx),
+                }),
+                message: lines.join("\n"),
+            })
+        }
+        cli::NoteCommand::Render(command) => {
+            let id = resolve_note_id(&ctx.client, &command.id)?;
+            let note = ctx.client.get_note(&id)?;
+            let origin = ctx.client.origin();
+            let Some(dir) = &command.output else {
+                // Printed HTML leaves the attachments on Kibela, so links
+                // must carry the origin to resolve elsewhere.
+                let rewrites = transfer::attachment_links(&note.content, origin)
+                    .into_iter()
+                    .filter(|link| link.starts_with('/'))
+                    .map(|link| {
+                        let url = format!("{}{link}", origin.trim_end_matches('/'));
+                        (link, url)
+                    })
+                    .collect::<HashMap<_, _>>();
+                let html = render::render_document(
+                    &note.title,
+                    &transfer::rewrite_links(&note.content, origin, &rewrites),
+                );
+                return Ok(CommandOutput {
+                    data: json!({
+                        "note": { "id": note.id, "title": note.title },
+                        "html": html,
+                        "meta": context_meta(&ctx),
+                    }),
+                    message: html.trim_end().to_string(),
+                });
+            };
+
+            let (content, assets) = mirror_attachments(
+                &ctx,
+                &note.content,
+                &dir.join("assets"),
+                "assets/",
+                command.max_attachment_mib,
+            )?;
+            let html_path = dir.join("note.html");
+            write_output_file(
+                &html_path,
+                render::render_document(&note.title, &content).as_bytes(),
+            )?;
+            let pdf = if command.pdf {
+                let pdf_path = dir.join("note.pdf");
+                let browser = print_pdf(&html_path, &pdf_path)?;
+                json!({ "path": pdf_path.display().to_string(), "browser": browser })
+            } else {
+                Value::Null
+            };
+            let message = match pdf["path"].as_str() {
+                Some(pdf_path) => format!("{}\n{pdf_path}", html_path.display()),
+                None => html_path.display().to_string(),
+            };
+
+            Ok(CommandOutput {
+                data: json!({
+                    "path": html_path.display().to_string(),
+                    "pdf": pdf,
+                    "note": { "id": note.id, "title": note.title },
+                    "assets": assets,
+                    "meta": context_meta(&ctx),
+                }),
+                message,
+            })
+        }
+        cli::NoteCommand::Drafts(command) => {
+            let Some(limit) = command.limit else {
+                let drafts = ctx.client.get_draft_notes(&DraftNotesInput {
+                    first: command.first,
+                    after: command.after.clone(),
+                })?;
+                return Ok(CommandOutput {
+                    data: json!({
+                        "drafts": drafts["drafts"],
+                        "page_info": drafts["pageInfo"],
+                        "meta": context_meta(&ctx),
+                    }),
+                    message: "note drafts completed".to_string(),
+                });
+            };
+            if limit == 0 || limit > paginate::MAX_MERGED_ITEMS {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("limit must be in range 1..={}", paginate::MAX_MERGED_ITEMS),
+                ));
+            }
+            let merged = paginate::collect_pages(
+                command.after.clone(),
+                Some(limit),
+                paginate::MAX_MERGED_PAGES,
+                |after| -> Result<paginate::Page<Value>, CliError> {
+                    let page = ctx.client.get_draft_notes(&DraftNotesInput {
+                        first: command.first,
+                        after,
+                    })?;
+                    Ok(paginate::Page::new(
+                        page["drafts"].as_array().cloned().unwrap_or_default(),
+                        page["pageInfo"].clone(),
+                    ))
+                },
+            )?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "drafts": merged.items,
+                    "page_info": merged.page_info,
+                    "pages": merged.pages,
+                    "truncated": merged.truncated,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "note drafts completed".to_string(),
+            })
+        }
+        cli::NoteCommand::Publish(command) => {
+            let note = ctx.client.publish_note(&PublishNoteInput {
+                id: resolve_note_id(&ctx.client, &command.id)?,
+                published_at: command.published_at.clone(),
+            })?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": note,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "note publish completed".to_string(),
+            })
+        }
+        cli::NoteCommand::MoveToFolder(command) => {
+            if let Some(path) = &command.batch {
+                return execute_note_move_batch(&ctx, path, command.concurrency, out);
+            }
+            let (Some(id), Some(from_folder), Some(to_folder)) =
+                (&command.id, &command.from_folder, &command.to_folder)
+            else {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "--id, --from-folder and --to-folder are required without --batch",
+                ));
+            };
+            let note = ctx
+                .client
+                .move_note_to_another_folder(&MoveNoteToAnotherFolderInput {
+                    id: resolve_note_id(&ctx.client, id)?,
+                    from_folder: note_folder_arg_to_input(from_folder),
+                    to_folder: note_folder_arg_to_input(to_folder),
+                })?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": note,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "note move-to-folder completed".to_string(),
+            })
+        }
+        cli::NoteCommand::AttachToFolder(command) => {
+            let note = ctx.client.attach_note_to_folder(&AttachNoteToFolderInput {
+                id: resolve_note_id(&ctx.client, &command.id)?,
+                folder: note_folder_arg_to_input(&command.folder),
+            })?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": note,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "note attach-to-folder completed".to_string(),
+            })
+        }
+        cli::NoteCommand::Copy(command) => {
+            let source = ctx
+                .client
+                .get_note(&resolve_note_id(&ctx.client, &command.id)?)?;
+            let mut content = source.content.clone();
+            let mut copied_comments = 0;
+            if command.with_comments {
+                let comments = ctx.client.get_note_comments(&NoteCommentsInput {
+                    note_id: source.id.clone(),
+                    last: Some(COPY_COMMENT_LIMIT),
+                })?;
+                let comments = comments
+                    .get("comments")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                copied_comments = comments.len();
+                content.push_str(&quoted_comments_section(comments));
+            }
+            let folders = command
+                .to_folders
+                .iter()
+                .map(note_folder_arg_to_input)
+                .collect::<Vec<_>>();
+            let mut group_ids = command.to_groups.clone();
+            for folder in &folders {
+                if !group_ids.contains(&folder.group_id) {
+                    group_ids.push(folder.group_id.clone());
+                }
+            }
+            let created = ctx.client.create_note(&CreateNoteInput {
+                title: format!("{}{}", source.title, command.title_suffix),
+                content,
+                group_ids,
+                draft: if command.draft { Some(true) } else { None },
+                coediting: false,
+                folders,
+                author_id: None,
+                published_at: None,
+                client_mutation_id: None,
+            })?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "note": created.note,
+                    "source": { "id": source.id, "title": source.title },
+                    "copied_comments": copied_comments,
+                    "meta": context_meta(&ctx),
+                }),
+                message: "note copy completed".to_string(),
+            })
+        }
+        cli::NoteCommand::Transfer(_) => unreachable!("note transfer is handled above"),
+    }
+}
+
+/// Downloads every attachment `content` links to on the client's origin into
+/// `assets_dir` and returns the content with those links rewritten to
+/// `<prefix><name>`, plus one `{from, path, bytes}` entry per file.
+fn mirror_attachments(
+    ctx: &ClientContext,
+    content: &str,
+    assets_dir: &Path,
+    prefix: &str,
+    max_attachment_mib: u32,
+) -> Result<(String, Vec<Value>), CliError> {
+    let origin = ctx.client.origin();
+    let max_bytes = usize::try_from(max_attachment_mib)
+        .unwrap_or(usize::MAX)
+        .saturating_mul(1024 * 1024);
+    let mut rewrites = HashMap::new();
+    let mut taken = Vec::new();
+    let mut assets = Vec::new();
+    for link in transfer::attachment_links(content, origin) {
+        let data = ctx.client.download_attachment(&DownloadAttachmentInput {
+            url: link.clone(),
+            max_bytes,
+        })?;
+        let name = transfer::unique_attachment_name(&link, &mut taken);
+        let path = assets_dir.join(&name);
+        write_output_file(&path, &data)?;
+        assets.push(json!({
+            "from": link,
+            "path": path.display().to_string(),
+            "bytes": data.len(),
+        }));
+        rewrites.insert(link, format!("{prefix}{name}"));
+    }
+    Ok((transfer::rewrite_links(content, origin, &rewrites), assets))
+}
+
+/// Title of the note at `path`, or `None` when it no longer exists.
+fn linked_note_title(client: &KibelClient, path: &str) -> Result<Option<String>, CliError> {
+    match client.get_note_from_path(&PathLookupInput {
+        path: path.to_string(),
+        first: Some(1),
+    }) {
+        Ok(note) => Ok(note["title"].as_str().map(str::to_string)),
+        Err(error) => {
+            let error = CliError::from(error);
+            if error.code == ErrorCode::NotFound {
+                Ok(None)
+            } else {
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Writes `bytes` to `path`, creating its parent folders.
+fn write_output_file(path: &Path, bytes: &[u8]) -> Result<(), CliError> {
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(path, bytes))
+        .map_err(|error| {
+            CliError::new(
+                ErrorCode::TransportError,
+                format!("failed to write {}: {error}", path.display()),
+            )
+        })
+}
+
+#[cfg(feature = "pdf")]
+fn print_pdf(html: &Path, pdf: &Path) -> Result<String, CliError> {
+    let browser = std::env::var("KIBEL_PDF_BROWSER").ok();
+    render::print_pdf(browser.as_deref(), html, pdf)
+        .map_err(|error| CliError::new(ErrorCode::PreconditionFailed, error))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn print_pdf(_html: &Path, _pdf: &Path) -> Result<String, CliError> {
+    Err(CliError::new(
+        ErrorCode::InputInvalid,
+        "--pdf needs a kibel built with the `pdf` feature (cargo install kibel --features pdf)",
+    ))
+}
+
+/// `note append` / `note prepend`: adds lines to the fetched content and
+/// sends that content as the base, so an edit made in between fails the
+/// update instead of being overwritten. One such conflict is retried against
+/// a fresh fetch.
+fn execute_note_add(
+    cli: &cli::Cli,
+    ctx: &ClientContext,
+    command: &cli::NoteAddArgs,
+    placement: content::addition::Placement,
+) -> Result<CommandOutput, CliError> {
+    if command.content.trim().is_empty() {
+        return Err(CliError::new(ErrorCode::InputInvalid, "--content is empty"));
+    }
+    let id = resolve_note_id(&ctx.client, &command.id)?;
+    let secret_scan = scan_outgoing(cli, &[("content", command.content.as_str())])?;
+    let label = format!("note {}", placement.as_str());
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let current = ctx.client.get_note_metadata(&id)?;
+        let base_content = current["content"].as_str().unwrap_or_default();
+        if command.skip_if_present
+            && content::addition::contains_lines(base_content, &command.content)
+        {
+            return Ok(CommandOutput {
+                data: json!({
+                    "note": Value::Null,
+                    "changed": false,
+                    "attempts": attempts,
+                    "secret_scan": secret_scan,
+                    "meta": context_meta(ctx),
+                }),
+                message: format!("{label} skipped: text already present"),
+            });
+        }
+        let new_content = content::addition::add_lines(base_content, &command.content, placement);
+        match ctx.client.update_note(&UpdateNoteInput {
+            id: id.clone(),
+            base_content: base_content.to_string(),
+            new_content,
+        }) {
+            Ok(note) => {
+                return Ok(CommandOutput {
+                    data: json!({
+                        "note": note,
+                        "changed": true,
+                        "attempts": attempts,
+                        "secret_scan": secret_scan,
+                        "meta": context_meta(ctx),
+                    }),
+                    message: with_secret_warning(&format!("{label} completed"), &secret_scan),
+                });
+            }
+            Err(error) => {
+                let error = CliError::from(error);
+                if attempts > 1 || error.code != ErrorCode::PreconditionFailed {
+                    return Err(error);
+                }
+            }
+        }
+    }
+}
+
+/// Recreates a note from `--from-team` on `--to-team`, re-uploading the
+/// attachments it links to on the source origin and rewriting those links.
+///
+/// Both clients take their token from the team's keychain entry or profile;
+/// stdin/env tokens are not used because they cannot name a team.
+fn execute_note_transfer(
+    cli: &cli::Cli,
+    command: &cli::NoteTransferArgs,
+) -> Result<CommandOutput, CliError> {
+    let from_team = command.from_team.trim();
+    let to_team = command.to_team.trim();
+    if from_team.is_empty() || to_team.is_empty() || from_team == to_team {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "--from-team and --to-team must name two different profiles (use `note copy` within a team)",
+        ));
+    }
+    let source_ctx =
+        resolve_client_context_for(cli, Some(from_team.to_string()), None, None, None)?;
+    let target_ctx = resolve_client_context_for(cli, Some(to_team.to_string()), None, None, None)?;
+    let source = source_ctx
+        .client
+        .get_note(&resolve_note_id(&source_ctx.client, &command.id)?)?;
+
+    let source_origin = source_ctx.client.origin();
+    let target_origin = target_ctx.client.origin().trim_end_matches('/');
+    let max_bytes = usize::try_from(command.max_attachment_mib)
+        .unwrap_or(usize::MAX)
+        .saturating_mul(1024 * 1024);
+    let mut rewrites = HashMap::new();
+    let mut attachments = Vec::new();
+    for link in transfer::attachment_links(&source.content, source_origin) {
+        let data = source_ctx
+            .client
+            .download_attachment(&DownloadAttachmentInput {
+                url: link.clone(),
+                max_bytes,
+            })?;
+        let bytes = data.len();
+        let uploaded = target_ctx
+            .client
+            .upload_attachment(&UploadAttachmentInput {
+                name: transfer::attachment_name(&link),
+                data,
+            })?;
+        let url = format!("{target_origin}{}", uploaded.path);
+        attachments.push(json!({
+            "from": link,
+            "to": url,
+            "id": uploaded.id,
+            "bytes": bytes,
+        }));
+        rewrites.insert(link, url);
+    }
+
+    let folders = command
+        .to_folders
+        .iter()
+        .map(note_folder_arg_to_input)
+        .collect::<Vec<_>>();
+    let mut group_ids = command.to_groups.clone();
+    for folder in &folders {
+        if !group_ids.contains(&folder.group_id) {
+            group_ids.push(folder.group_id.clone());
+        }
+    }
+    let created = target_ctx.client.create_note(&CreateNoteInput {
+        title: source.title.clone(),
+        content: transfer::rewrite_links(&source.content, source_origin, &rewrites),
+        group_ids,
+        draft: if command.draft { Some(true) } else { None },
+        coediting: false,
+        folders,
+        author_id: None,
+        published_at: None,
+        client_mutation_id: None,
+    })?;
+
+    Ok(CommandOutput {
+        data: json!({
+            "note": created.note,
+            "source": {
+                "team": source_ctx.team,
+                "origin": source_origin,
+                "id": source.id,
+                "title": source.title,
+            },
+            "attachments": attachments,
+            "meta": context_meta(&target_ctx),
+        }),
+        message: "note transfer completed".to_string(),
+    })
+}
+
+fn validate_concurrency(concurrency: usize) -> Result<(), CliError> {
+    if concurrency == 0 || concurrency > concurrency::MAX_CONCURRENCY {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "concurrency must be in range 1..={}",
+                concurrency::MAX_CONCURRENCY
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn execute_note_move_batch(
+    ctx: &ClientContext,
+    path: &std::path::Path,
+    concurrency: usize,
+    out: OutputContext,
+) -> Result<CommandOutput, CliError> {
+    validate_concurrency(concurrency)?;
+    let raw = fs::read_to_string(path).map_err(|error| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            format!("failed to read manifest {}: {error}", path.display()),
+        )
+    })?;
+    let rows = manifest::parse_move_manifest(&raw).map_err(|errors| {
+        let details = errors
+            .iter()
+            .map(|error| json!({ "row": error.row, "message": error.message }))
+            .collect::<Vec<_>>();
+        CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "manifest validation failed: {} invalid row(s); nothing was moved",
+                errors.len()
+            ),
+        )
+        .with_details(json!({ "errors": details }))
+    })?;
+
+    let progress = Progress::new(out, "moving notes", None);
+    let outcomes = concurrency::run_with_progress(&rows, concurrency, &progress, |_, row| {
+        let id = resolve_note_id(&ctx.client, &row.note_id)?;
+        ctx.client
+            .move_note_to_another_folder(&MoveNoteToAnotherFolderInput {
+                id,
+                from_folder: note_folder_arg_to_input(&row.from_folder),
+                to_folder: note_folder_arg_to_input(&row.to_folder),
+            })
+            .map_err(CliError::from)
+    });
+
+    let mut first_failure = None;
+    let mut results = Vec::with_capacity(rows.len());
+    for (row, outcome) in rows.iter().zip(outcomes) {
+        match outcome {
+            Ok(note) => results.push(json!({
+                "row": row.row,
+                "note_id": row.note_id,
+                "ok": true,
+                "note": note,
+                "error": Value::Null,
+            })),
+            Err(error) => {
+                results.push(json!({
+                    "row": row.row,
+                    "note_id": row.note_id,
+                    "ok": false,
+                    "note": Value::Null,
+                    "error": {
+                        "code": error.code.as_str(),
+                        "message": error.message,
+                    },
+                }));
+                first_failure.get_or_insert(error.code);
+            }
+        }
+    }
+    let failed = results
+        .iter()
+        .filter(|result| result["ok"] == Value::Bool(false))
+        .count();
+    let summary = json!({
+        "total": rows.len(),
+        "succeeded": rows.len() - failed,
+        "failed": failed,
+    });
+
+    if let Some(code) = first_failure {
+        return Err(CliError::new(
+            code,
+            format!(
+                "note move-to-folder batch: {failed} of {} row(s) failed",
+                rows.len()
+            ),
+        )
+        .with_details(json!({ "results": results, "summary": summary })));
+    }
+
+    Ok(CommandOutput {
+        data: json!({
+            "results": results,
+            "summary": summary,
+            "meta": context_meta(ctx),
+        }),
+        message: format!("note move-to-folder batch completed: {} row(s)", rows.len()),
+    })
+}
+
+fn execute_ci(
+    cli: &cli::Cli,
+    args: &cli::CiArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+) -> Result<CommandOutput, CliError> {
+    match &args.command {
+        cli::CiCommand::VerifyDocs(command) => {
+            validate_concurrency(command.concurrency)?;
+            let mapping_path = docs_mapping::resolve_mapping_path(&command.mapping);
+            let entries = docs_mapping::load_docs_mapping(&mapping_path)
+                .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+
+            // Render every file up front so local mistakes fail before any request.
+            let mut rendered = Vec::with_capacity(entries.len());
+            let mut errors = Vec::new();
+            for entry in &entries {
+                let source = fs::read_to_string(&entry.file)
+                    .map_err(|error| format!("failed to read {}: {error}", entry.file.display()))
+                    .and_then(|text| {
+                        // Exports from `note get --format markdown` compare by body.
+                        let body = match frontmatter::parse(&text) {
+                            Ok((Some(front), body)) if front.id.is_some() => body,
+                            _ => &text,
+                        };
+                        template::render_template(body, &entry.vars)
+                    });
+                match source {
+                    Ok(text) => rendered.push(text),
+                    Err(message) => errors.push(json!({
+                        "note": entry.note,
+                        "file": entry.file.display().to_string(),
+                        "message": message,
+                    })),
+                }
+            }
+            if !errors.is_empty() {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!(
+                        "ci verify-docs: {} file(s) could not be rendered",
+                        errors.len()
+                    ),
+                )
+                .with_details(json!({ "errors": errors })));
+            }
+
+            let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+            let pairs = entries.iter().zip(&rendered).collect::<Vec<_>>();
+            let progress = Progress::new(out, "verifying docs", None);
+            let outcomes = concurrency::run_with_progress(
+                &pairs,
+                command.concurrency,
+                &progress,
+                |_, pair| {
+                    let (entry, expected) = *pair;
+                    let id = resolve_note_id(&ctx.client, &entry.note)?;
+                    let note = ctx.client.get_note(&id)?;
+                    let file_label = entry.file.display().to_string();
+                    Ok::<_, CliError>((
+                        note.id,
+                        diff::unified_diff(
+                            &docs_mapping::normalize_for_compare(&note.content),
+                            &docs_mapping::normalize_for_compare(expected),
+                            &format!("kibela:{}", entry.note),
+                            &file_label,
+                            command.context,
+                        ),
+                    ))
+                },
+            );
+
+            let mut first_error = None;
+            let mut drifted = 0usize;
+            let mut results = Vec::with_capacity(entries.len());
+            for (entry, outcome) in entries.iter().zip(outcomes) {
+                let file = entry.file.display().to_string();
+                match outcome {
+                    Ok((note_id, diff)) => {
+                        drifted += usize::from(diff.is_some());
+                        results.push(json!({
+                            "note": entry.note,
+                            "note_id": note_id,
+                            "file": file,
+                            "ok": diff.is_none(),
+                            "diff": diff,
+                            "error": Value::Null,
+                        }));
+                    }
+                    Err(error) => {
+                        results.push(json!({
+                            "note": entry.note,
+                            "note_id": Value::Null,
+                            "file": file,
+                            "ok": false,
+                            "diff": Value::Null,
+                            "error": {
+                                "code": error.code.as_str(),
+                                "message": error.message,
+                            },
+                        }));
+                        first_error.get_or_insert(error.code);
+                    }
+                }
+            }
+            let failed = results
+                .iter()
+                .filter(|result| !result["error"].is_null())
+                .count();
+            let summary = json!({
+                "checked": entries.len(),
+                "matched": entries.len() - drifted - failed,
+                "drifted": drifted,
+                "failed": failed,
+            });
+            if let Some(code) =
+                first_error.or_else(|| (drifted > 0).then_some(ErrorCode::PreconditionFailed))
+            {
+                return Err(CliError::new(
+                    code,
+                    format!(
+                        "ci verify-docs: {drifted} drifted, {failed} failed of {} note(s)",
+                        entries.len()
+                    ),
+                )
+                .with_details(json!({ "results": results, "summary": summary })));
+            }
+            Ok(CommandOutput {
+                data: json!({
+                    "results": results,
+                    "summary": summary,
+                    "meta": context_meta(&ctx),
+                }),
+                message: format!("ci verify-docs: {} note(s) match", entries.len()),
+            })
+        }
+    }
+}
+
+/// Most recent comments shown in the `tui` preview.
+const TUI_COMMENT_LIMIT: u32 = 50;
+
+fn execute_tui(
+    cli: &cli::Cli,
+    args: &cli::TuiArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+    let source = ClientTuiSource {
+        cli,
+        ctx: &ctx,
+        first: args.first,
+    };
+    // Groups and folders come from the local store when an earlier listing
+    // cached them, so the browser opens without a request; `r` refetches.
+    let cached = if args.refresh {
+        Vec::new()
+    } else {
+        [
+            (tui::ScopeKind::Group, completion::EntryKind::Group),
+            (tui::ScopeKind::Folder, completion::EntryKind::Folder),
+        ]
+        .into_iter()
+        .flat_map(|(kind, entry_kind)| {
+            completion::cached(ctx.team.as_deref(), entry_kind)
+                .into_iter()
+                .map(move |(id, name)| tui::Scope { kind, id, name })
+        })
+        .collect()
+    };
+    let from_cache = !cached.is_empty();
+    let scopes = if from_cache {
+        cached
+    } else {
+        source.fetch_scopes()?
+    };
+    let scope_count = scopes.len();
+    let mut state = tui::TuiState::new(scopes);
+    if from_cache {
+        state.set_status(format!(
+            "{scope_count} cached group(s) and folder(s); r refetches them"
+        ));
+    }
+    let previewed = tui::run(&mut state, &source)
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+
+    Ok(CommandOutput {
+        data: json!({
+            "scopes": scope_count,
+            "cached_scopes": from_cache,
+            "previewed": previewed,
+            "meta": context_meta(&ctx),
+        }),
+        message: format!("tui closed ({previewed} note(s) previewed)"),
+    })
+}
+
+/// [`tui::TuiSource`] over the API client; errors become status-line text.
+struct ClientTuiSource<'a> {
+    cli: &'a cli::Cli,
+    ctx: &'a ClientContext,
+    first: Option<u32>,
+}
+
+impl ClientTuiSource<'_> {
+    /// Fetches groups and folders and records them in the completion cache.
+    fn fetch_scopes(&self) -> Result<Vec<tui::Scope>, CliError> {
+        let page = || PageInput { first: self.first };
+        let team = self.ctx.team.as_deref();
+        let groups = self.ctx.client.get_groups(page())?;
+        let folders = self.ctx.client.get_folders(page())?;
+        completion::remember(team, completion::EntryKind::Group, &groups);
+        completion::remember(team, completion::EntryKind::Folder, &folders);
+        Ok([
+            (tui::ScopeKind::Group, &groups),
+            (tui::ScopeKind::Folder, &folders),
+        ]
+        .into_iter()
+        .flat_map(|(kind, items)| {
+            items
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(move |item| {
+                    Some(tui::Scope {
+                        kind,
+                        id: item.get("id")?.as_str()?.to_string(),
+                        name: item
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string(),
+                    })
+                })
+        })
+        .collect())
+    }
+
+    fn note_items(&self, items: impl IntoIterator<Item = Value>) -> Vec<tui::NoteItem> {
+        items
+            .into_iter()
+            .filter_map(|item| {
+                let id = item.get("id")?.as_str()?.to_string();
+                let url = item
+                    .get("url")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .or_else(|| {
+                        note_path_from_id(&id).map(|path| path.url(self.ctx.client.origin()))
+                    });
+                Some(tui::NoteItem {
+                    title: item
+                        .get("title")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    id,
+                    url,
+                })
+            })
+            .collect()
+    }
+}
+
+impl tui::TuiSource for ClientTuiSource<'_> {
+    fn scopes(&self) -> Result<Vec<tui::Scope>, String> {
+        self.fetch_scopes().map_err(|error| error.message)
+    }
+
+    fn notes(&self, scope: &tui::Scope) -> Result<Vec<tui::NoteItem>, String> {
+        let items = match scope.kind {
+            tui::ScopeKind::Group => self
+                .ctx
+                .client
+                .get_feed_sections(&FeedSectionsInput {
+                    kind: "NOTE".to_string(),
+                    group_id: scope.id.clone(),
+                    first: self.first,
+                    after: None,
+                })
+                .map(|sections| {
+                    sections
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .flat_map(watch::feed_section_notes)
+                        .map(|(_, note)| note.clone())
+                        .collect::<Vec<_>>()
+                }),
+            tui::ScopeKind::Folder => self
+                .ctx
+                .client
+                .get_notes(&GetNotesInput {
+                    folder_id: scope.id.clone(),
+                    first: self.first,
+                    last: None,
+                })
+                .map(|notes| notes.as_array().cloned().unwrap_or_default()),
+        };
+        items
+            .map(|items| self.note_items(items))
+            .map_err(|error| CliError::from(error).message)
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<tui::NoteItem>, String> {
+        let page = self
+            .ctx
+            .client
+            .search_note_page(&SearchNoteInput {
+                query: query.to_string(),
+                resources: Vec::new(),
+                coediting: None,
+                updated: None,
+                group_ids: Vec::new(),
+                user_ids: Vec::new(),
+                folder_ids: Vec::new(),
+                liker_ids: Vec::new(),
+                is_archived: None,
+                sort_by: None,
+                first: self.first,
+                after: None,
+            })
+            .map_err(|error| CliError::from(error).message)?;
+        Ok(self.note_items(page.results))
+    }
+
+    fn preview(&self, note: &tui::NoteItem) -> Result<tui::Preview, String> {
+        let fetched = self
+            .ctx
+            .client
+            .get_note(&note.id)
+            .map_err(|error| CliError::from(error).message)?;
+        let comments = self
+            .ctx
+            .client
+            .get_note_comments(&NoteCommentsInput {
+                note_id: note.id.clone(),
+                last: Some(TUI_COMMENT_LIMIT),
+            })
+            .map_err(|error| CliError::from(error).message)?;
+        let comments = comments
+            .get("comments")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|comment| {
+                let field = |pointer: &str| {
+                    comment
+                        .pointer(pointer)
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                tui::Comment {
+                    author: field("/author/account"),
+                    content: field("/content"),
+                }
+            })
+            .collect();
+        Ok(tui::Preview {
+            title: fetched.title,
+            content: fetched.content,
+            comments,
+        })
+    }
+
+    fn open(&self, url: &str) -> Result<(), String> {
+        open_in_browser(self.cli, url)
+            .map(|_| ())
+            .map_err(|error| error.message)
+    }
+}
+
+fn execute_open(
+    cli: &cli::Cli,
+    args: &cli::OpenArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+    let (kind, id, url) = if args.folder {
+        let target = args.target.trim();
+        let id = if target.starts_with('/') {
+            let folder = ctx.client.get_folder_from_path(&PathLookupInput {
+                path: target.to_string(),
+                first: Some(1),
+            })?;
+            folder
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            target.to_string()
+        };
+        let path = folder_web_path(&id).ok_or_else(|| {
+            CliError::new(
+                ErrorCode::InputInvalid,
+                format!("cannot derive a URL for folder `{id}`"),
+            )
+        })?;
+        let url = format!("{}{path}", ctx.client.origin().trim_end_matches('/'));
+        ("folder", id, url)
+    } else {
+        let reference = parse_note_ref(&args.target)
+            .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+        let resolved = resolve_note_ref(&ctx.client, &reference)?;
+        let url = resolved.url.ok_or_else(|| {
+            CliError::new(
+                ErrorCode::InputInvalid,
+                format!("cannot derive a URL for note {}", resolved.id),
+            )
+        })?;
+        ("note", resolved.id, url)
+    };
+
+    let opened = if args.print_url {
+        Value::Null
+    } else {
+        open_in_browser(cli, &url)?
+    };
+    Ok(CommandOutput {
+        message: if args.print_url {
+            url.clone()
+        } else {
+            format!("opened {url}")
+        },
+        data: json!({
+            "kind": kind,
+            "id": id,
+            "url": url,
+            "opened": opened,
+            "meta": context_meta(&ctx),
+        }),
+    })
+}
+
+/// Launches `url` with `BROWSER`, the config `browser` command, or the
+/// platform opener; returns `{url, command}` for `data.opened`.
+fn open_in_browser(cli: &cli::Cli, url: &str) -> Result<Value, CliError> {
+    let command = match std::env::var("BROWSER")
+        .ok()
+        .filter(|command| !command.trim().is_empty())
+    {
+        Some(command) => Some(command),
+        None => load_config(cli)?.1.browser,
+    };
+    let argv = browser::open_url(command.as_deref(), url)
+        .map_err(|error| CliError::new(ErrorCode::PreconditionFailed, error))?;
+    Ok(json!({ "url": url, "command": argv }))
+}
+
+/// `--copy-url` / `--copy-id`, or the `copy_url` config default: the
+/// clipboard result for `data.copied`, or `null` when nothing is copied.
+fn copy_note(
+    cli: &cli::Cli,
+    copy: &cli::CopyArgs,
+    note_id: &str,
+    origin: &str,
+) -> Result<Value, CliError> {
+    if copy.no_copy {
+        return Ok(Value::Null);
+    }
+    if copy.copy_id {
+        return Ok(copy_to_clipboard(note_id));
+    }
+    let copy_url = copy.copy_url || load_config(cli)?.1.copy_url.unwrap_or(false);
+    if !copy_url {
+        return Ok(Value::Null);
+    }
+    Ok(match note_path_from_id(note_id) {
+        Some(path) => copy_to_clipboard(&path.url(origin)),
+        None => json!({
+            "text": Value::Null,
+            "error": format!("cannot derive a URL for note {note_id}; use --copy-id"),
+        }),
+    })
+}
+
+/// Copies `text` with `KIBEL_CLIPBOARD` or the platform clipboard tool.
+///
+/// Best effort: the command's own work is already done, so a missing tool is
+/// reported as `{text, error}` rather than failing it.
+fn copy_to_clipboard(text: &str) -> Value {
+    let command = std::env::var("KIBEL_CLIPBOARD").ok();
+    match clipboard::copy_text(command.as_deref(), text) {
+        Ok(argv) => json!({ "text": text, "command": argv }),
+        Err(error) => json!({ "text": text, "error": error }),
+    }
+}
+
+fn execute_digest(
+    cli: &cli::Cli,
+    args: &cli::DigestArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    if args.max_pages == 0 {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "max-pages must be greater than 0",
+        ));
+    }
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+    let since = template::utc_date_days_ago(args.since.div_ceil(86_400));
+    let until = template::today_utc();
+    let mut input = FeedSectionsInput {
+        kind: args.kind.clone(),
+        group_id: args.group_id.clone(),
+        first: args.first,
+        after: None,
+    };
+    let mut reached_since = false;
+    let sections = paginate::collect_pages(
+        None,
+        None,
+        args.max_pages,
+        |after| -> Result<paginate::Page<Value>, CliError> {
+            input.after = after;
+            let page = ctx.client.get_feed_sections_with_page_info(&input)?;
+            let mut sections = Vec::new();
+            for section in page
+                .get("sections")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                if feed_section_date(section).is_some_and(|date| date < since.as_str()) {
+                    reached_since = true;
+                } else {
+                    sections.push(section.clone());
+                }
+            }
+            let mut page = paginate::Page::new(
+                sections,
+                page.get("pageInfo").cloned().unwrap_or(Value::Null),
+            );
+            page.stop = reached_since;
+            Ok(page)
+        },
+    )?;
+
+    let mut entries: Vec<digest::DigestEntry> = Vec::new();
+    for section in &sections.items {
+        let node = section.get("node").unwrap_or(&Value::Null);
+        // Parcels already name their folder or author; anything else needs
+        // the note's metadata.
+        let parcel_folder = node.pointer("/folder/name").and_then(Value::as_str);
+        let parcel_author = node.pointer("/user/account").and_then(Value::as_str);
+        for (date, note) in watch::feed_section_notes(section) {
+            let text = |key: &str| note.get(key).and_then(Value::as_str).unwrap_or_default();
+            let id = text("id");
+            if id.is_empty() || entries.iter().any(|entry| entry.id == id) {
+                continue;
+            }
+            let known = match args.by {
+                cli::DigestGroupBy::Folder => parcel_folder,
+                cli::DigestGroupBy::Author => parcel_author,
+            };
+            let metadata = if known.is_some() {
+                Value::Null
+            } else {
+                ctx.client.get_note_metadata(id)?
+            };
+            let folder = parcel_folder.map(str::to_string).or_else(|| {
+                metadata
+                    .pointer("/folders/edges/0/node/fullName")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            });
+            let author = parcel_author.map(str::to_string).or_else(|| {
+                metadata
+                    .pointer("/author/account")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            });
+            let url = metadata["url"]
+                .as_str()
+                .map(str::to_string)
+                .or_else(|| note_path_from_id(id).map(|path| path.url(ctx.client.origin())));
+            entries.push(digest::DigestEntry {
+                id: id.to_string(),
+                title: text("title").to_string(),
+                url: url.unwrap_or_default(),
+                date: date
+                    .as_str()
+                    .and_then(|date| date.get(..10))
+                    .unwrap_or_default()
+                    .to_string(),
+                folder,
+                author,
+            });
+        }
+    }
+    let markdown = digest::render(&entries, args.by, &since, &until);
+
+    let posted = if args.post {
+        let title = args
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Digest {since} – {until}"));
+        let secret_scan = scan_outgoing(cli, &[("title", &title), ("content", &markdown)])?;
+        let group_id = args
+            .to_group
+            .clone()
+            .unwrap_or_else(|| args.group_id.clone());
+        let created = ctx.client.create_note(&CreateNoteInput {
+            title,
+            content: markdown.clone(),
+            group_ids: vec![group_id],
+            draft: if args.draft { Some(true) } else { None },
+            coediting: false,
+            folders: Vec::new(),
+            author_id: None,
+            published_at: None,
+            client_mutation_id: None,
+        })?;
+        json!({ "note": created.note, "secret_scan": secret_scan })
+    } else {
+        Value::Null
+    };
+    let message = match posted.pointer("/note/url").and_then(Value::as_str) {
+        Some(url) => with_secret_warning(&format!("digest posted: {url}"), &posted["secret_scan"]),
+        None => markdown.trim_end().to_string(),
+    };
+
+    Ok(CommandOutput {
+        data: json!({
+            "since": since,
+            "until": until,
+            "by": match args.by {
+                cli::DigestGroupBy::Folder => "folder",
+                cli::DigestGroupBy::Author => "author",
+            },
+            "entries": entries,
+            "markdown": markdown,
+            "pages": sections.pages,
+            "reached_since": reached_since,
+            "posted": posted,
+            "meta": context_meta(&ctx),
+        }),
+        message,
+    })
+}
+
+/// `publish`: walks the manifest's files in order and creates or updates one
+/// note each. The state file is saved after every file, so a failed run
+/// resumes where it stopped.
+fn execute_publish(
+    cli: &cli::Cli,
+    args: &cli::PublishArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    let manifest_path = args
+        .manifest
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(publish::DEFAULT_MANIFEST));
+    let raw = fs::read_to_string(&manifest_path).map_err(|error| {
+        CliError::new(
+            ErrorCode::NotFound,
+            format!("failed to read {}: {error}", manifest_path.display()),
+        )
+    })?;
+    let manifest = publish::Manifest::parse(&raw).map_err(|error| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            format!("{}: {error}", manifest_path.display()),
+        )
+    })?;
+    let root = match manifest_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let state_path = root.join(publish::STATE_FILE);
+    let mut state = publish::PublishState::load(&state_path);
+    let mut files = publish::select_files(&manifest, &root)
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+    let since = match args.since.as_deref().map(str::trim) {
+        Some(rev) => {
+            let rev = if rev.is_empty() {
+                state.commit.clone().ok_or_else(|| {
+                    CliError::new(
+                        ErrorCode::PreconditionFailed,
+                        format!(
+                            "{} has no recorded commit; pass --since <REV> or publish once without --since",
+                            state_path.display()
+                        ),
+                    )
+                })?
+            } else {
+                rev.to_string()
+            };
+            let changed = publish::git_changed_files(&root, &rev)
+                .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+            let selected = files.len();
+            files.retain(|file| changed.contains(&file.key));
+            json!({
+                "rev": rev,
+                "changed": files.len(),
+                "skipped": selected - files.len(),
+            })
+        }
+        None => Value::Null,
+    };
+    let ctx = if args.dry_run {
+        None
+    } else {
+        Some(resolve_client_context(cli, stdin_token, env_token)?)
+    };
+
+    let mut results = Vec::with_capacity(files.len());
+    let mut counts = BTreeMap::<&str, u64>::new();
+    for file in &files {
+        let in_file = |error: CliError| CliError {
+            message: format!("{}: {}", file.key, error.message),
+            ..error
+        };
+        let (front, content) = read_note_file(&file.path).map_err(in_file)?;
+        let title = publish::title_for(
+            front.as_ref().and_then(|front| front.title.as_deref()),
+            &content,
+            &file.path,
+        );
+        let hash = publish::content_hash(&title, &content);
+        let known = state.files.get(&file.key);
+        let target = file
+            .entry
+            .note
+            .clone()
+            .or_else(|| front.as_ref().and_then(|front| front.id.clone()))
+            .or_else(|| known.map(|known| known.note_id.clone()));
+        let unchanged = !args.force
+            && known.is_some_and(|known| {
+                known.local_sha256 == hash && Some(&known.note_id) == target.as_ref()
+            });
+
+        let (action, note_id) = match (&ctx, unchanged) {
+            (_, true) => ("unchanged", target),
+            (None, false) => (if target.is_some() { "update" } else { "create" }, target),
+            (Some(ctx), false) => {
+                let (action, note_id) = publish_file(
+                    cli,
+                    ctx,
+                    file,
+                    (&title, &content, &hash),
+                    target.as_deref(),
+                    known.filter(|_| !args.force),
+                )
+                .map_err(in_file)?;
+                state.files.insert(
+                    file.key.clone(),
+                    publish::PublishedFile {
+                        note_id: note_id.clone(),
+                        local_sha256: hash.clone(),
+                        remote_sha256: Some(hash),
+                    },
+                );
+                state.save(&state_path).map_err(|error| {
+                    CliError::new(
+                        ErrorCode::TransportError,
+                        format!("failed to write {}: {error}", state_path.display()),
+                    )
+                })?;
+                (action, Some(note_id))
+            }
+        };
+        *counts.entry(action).or_default() += 1;
+        let url = note_id
+            .as_deref()
+            .zip(ctx.as_ref())
+            .and_then(|(id, ctx)| Some(note_path_from_id(id)?.url(ctx.client.origin())));
+        results.push(json!({
+            "file": file.key,
+            "title": title,
+            "action": action,
+            "note_id": note_id,
+            "url": url,
+        }));
+    }
+
+    // Only a complete run is recorded, so `--since` alone never skips a file
+    // that failed to publish.
+    let commit = if args.dry_run {
+        None
+    } else {
+        publish::git_head(&root)
+    };
+    if commit.is_some() && commit != state.commit {
+        state.commit.clone_from(&commit);
+        state.save(&state_path).map_err(|error| {
+            CliError::new(
+                ErrorCode::TransportError,
+                format!("failed to write {}: {error}", state_path.display()),
+            )
+        })?;
+    }
+
+    let summary = counts
+        .iter()
+        .map(|(action, count)| format!("{count} {action}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(CommandOutput {
+        data: json!({
+            "manifest": manifest_path,
+            "state": state_path,
+            "dry_run": args.dry_run,
+            "since": since,
+            "commit": commit,
+            "results": results,
+            "counts": counts,
+            "meta": ctx.as_ref().map(context_meta),
+        }),
+        message: match (args.dry_run, summary.is_empty()) {
+            (_, true) => "publish: no files selected".to_string(),
+            (true, false) => format!("publish plan: {summary}"),
+            (false, false) => format!("publish completed: {summary}"),
+        },
+    })
+}
+
+/// Creates the note for `file`, or updates `target` when its title or content
+/// differs. Returns the action taken and the note id.
+///
+/// An update first hashes the note: one that matches the file is left alone,
+/// and one edited in Kibela since the last publish (its hash differs from
+/// `known.remote_sha256`) fails with `PRECONDITION_FAILED` rather than being
+/// overwritten. `known` is `None` under `--force`.
+fn publish_file(
+    cli: &cli::Cli,
+    ctx: &ClientContext,
+    file: &publish::SelectedFile<'_>,
+    (title, content, hash): (&str, &str, &str),
+    target: Option<&str>,
+    known: Option<&publish::PublishedFile>,
+) -> Result<(&'static str, String), CliError> {
+    scan_outgoing(cli, &[("title", title), ("content", content)])?;
+    if let Some(reference) = target {
+        let id = resolve_note_id(&ctx.client, reference)?;
+        let current = ctx.client.get_note_metadata(&id)?;
+        let current_title = current["title"].as_str().unwrap_or_default();
+        let current_content = current["content"].as_str().unwrap_or_default();
+        let remote_hash = publish::content_hash(current_title, current_content);
+        if remote_hash == hash {
+            return Ok(("unchanged", id));
+        }
+        if let Some(known) = known.filter(|known| known.note_id == id) {
+            if known.remote_changed(&remote_hash) {
+                return Err(CliError::new(
+                    ErrorCode::PreconditionFailed,
+                    format!(
+                        "note {id} was edited in Kibela since the last publish; merge those edits into the file or pass --force to overwrite them"
+                    ),
+                )
+                .with_details(json!({
+                    "note_id": id,
+                    "remote_sha256": remote_hash,
+                    "known_remote_sha256": known.remote_sha256,
+                })));
+            }
+        }
+        if docs_mapping::normalize_for_compare(current_content)
+            != docs_mapping::normalize_for_compare(content)
+        {
+            ctx.client.update_note(&UpdateNoteInput {
+                id: id.clone(),
+                base_content: current_content.to_string(),
+                new_content: content.to_string(),
+            })?;
+        }
+        if current_title.trim() != title.trim() {
+            ctx.client.update_note_metadata(&UpdateNoteMetadataInput {
+                id: id.clone(),
+                title: Some(title.to_string()),
+                coediting: None,
+                group_ids: None,
+            })?;
+        }
+        return Ok(("updated", id));
+    }
+
+    let entry = file.entry;
+    let mut group_ids = entry.group_ids.clone();
+    let mut folders = Vec::new();
+    if let Some(path) = &entry.folder_path {
+        let ensured = ctx.client.ensure_folder_path(&EnsureFolderPathInput {
+            path: path.clone(),
+            create_missing: true,
+        })?;
+        if !group_ids.contains(&ensured.group_id) {
+            group_ids.push(ensured.group_id.clone());
+        }
+        folders.push(CreateNoteFolderInput {
+            group_id: ensured.group_id,
+            folder_name: ensured.folder_name,
+        });
+    }
+    let created = ctx.client.create_note(&CreateNoteInput {
+        title: title.to_string(),
+        content: content.to_string(),
+        group_ids,
+        draft: None,
+        coediting: entry.coediting,
+        folders,
+        author_id: None,
+        published_at: None,
+        client_mutation_id: None,
+    })?;
+    Ok(("created", created.note.id))
+}
+
+/// `import confluence` / `import notion`: reads the export, maps each root
+/// (space, top-level page, or database) to a folder from `--map` or a
+/// prompt, then creates a note per page. Links between imported pages are
+/// pointed at the new notes once all of them exist.
+fn execute_import(
+    cli: &cli::Cli,
+    args: &cli::ImportArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    let (kind, command) = match &args.command {
+        cli::ImportCommand::Confluence(command) => ("confluence", command),
+        cli::ImportCommand::Notion(command) => ("notion", command),
+    };
+    let input_invalid = |error: String| CliError::new(ErrorCode::InputInvalid, error);
+    let unpacked = import::unpack(&command.export).map_err(input_invalid)?;
+    let pages = match kind {
+        "confluence" => import::read_confluence(&unpacked.root),
+        _ => import::read_notion(&unpacked.root),
+    }
+    .map_err(input_invalid)?;
+    if pages.is_empty() {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!("no {kind} pages found in {}", command.export.display()),
+        ));
+    }
+
+    let mut roots = Vec::<&str>::new();
+    for page in &pages {
+        if !roots.contains(&page.root.as_str()) {
+            roots.push(&page.root);
+        }
+    }
+    let mut mapping = BTreeMap::<String, Option<String>>::new();
+    for raw in &command.map {
+        let (root, destination) = import::parse_mapping(raw).map_err(input_invalid)?;
+        if !roots.contains(&root.as_str()) {
+            return Err(CliError::new(
+                ErrorCode::InputInvalid,
+                format!("--map `{root}` matches no space, page, or database in the export"),
+            )
+            .with_details(json!({ "roots": roots })));
+        }
+        mapping.insert(root, Some(destination));
+    }
+    let unmapped = roots
+        .iter()
+        .filter(|root| !mapping.contains_key(**root))
+        .collect::<Vec<_>>();
+    if !unmapped.is_empty() && !is_interactive_terminal() {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "{} root(s) have no folder; pass --map ROOT=GROUP[/FOLDER...] for each",
+                unmapped.len()
+            ),
+        )
+        .with_details(json!({ "unmapped": unmapped })));
+    }
+    for root in unmapped {
+        let answer = read_prompt_line(&format!(
+            "Folder for \"{root}\" (GROUP[/FOLDER...], empty to skip)"
+        ))?;
+        let destination = answer.trim().trim_matches('/');
+        mapping.insert(
+            (*root).to_string(),
+            (!destination.is_empty()).then(|| destination.to_string()),
+        );
+    }
+
+    let ctx = if command.dry_run {
+        None
+    } else {
+        Some(resolve_client_context(cli, stdin_token, env_token)?)
+    };
+    let mut folders = HashMap::<String, CreateNoteFolderInput>::new();
+    let mut created = Vec::new();
+    let mut urls = HashMap::new();
+    let mut results = Vec::with_capacity(pages.len());
+    let mut counts = BTreeMap::<&str, u64>::new();
+    for page in &pages {
+        let folder = mapping
+            .get(&page.root)
+            .cloned()
+            .flatten()
+            .map(|destination| import::folder_path(&destination, page));
+        let (action, note_id) = match (&ctx, &folder) {
+            (_, None) => ("skipped", None),
+            (None, Some(_)) => ("create", None),
+            (Some(ctx), Some(folder)) => {
+                let (note_id, content) = import_page(
+                    cli,
+                    ctx,
+                    &unpacked.root,
+                    page,
+                    folder,
+                    command.coediting,
+                    &mut folders,
+                )
+                .map_err(|error| CliError {
+                    message: format!("{}: {}", page.source, error.message),
+                    ..error
+                })?;
+                if let Some(url) =
+                    note_path_from_id(&note_id).map(|path| path.url(ctx.client.origin()))
+                {
+                    urls.insert(page.source.clone(), url);
+                }
+                created.push((results.len(), note_id.clone(), content));
+                ("created", Some(note_id))
+            }
+        };
+        *counts.entry(action).or_default() += 1;
+        results.push(json!({
+            "source": page.source,
+            "title": page.title,
+            "root": page.root,
+            "folder": folder,
+            "action": action,
+            "note_id": note_id,
+            "url": Value::Null,
+        }));
+    }
+
+    let mut linked = 0;
+    if let Some(ctx) = &ctx {
+        for (index, note_id, content) in &created {
+            results[*index]["url"] = json!(urls.get(&pages[*index].source));
+            let (new_content, changed) = import::link_pages(content, &urls);
+            if changed > 0 {
+                ctx.client.update_note(&UpdateNoteInput {
+                    id: note_id.clone(),
+                    base_content: content.clone(),
+                    new_content,
+                })?;
+                linked += changed;
+            }
+        }
+    }
+
+    let summary = counts
+        .iter()
+        .map(|(action, count)| format!("{count} {action}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(CommandOutput {
+        data: json!({
+            "export": command.export,
+            "source": kind,
+            "dry_run": command.dry_run,
+            "roots": mapping
+                .iter()
+                .map(|(root, folder)| json!({ "root": root, "folder": folder }))
+                .collect::<Vec<_>>(),
+            "results": results,
+            "counts": counts,
+            "linked": linked,
+            "meta": ctx.as_ref().map(context_meta),
+        }),
+        message: if command.dry_run {
+            format!("import plan: {summary}")
+        } else {
+            format!("import completed: {summary}")
+        },
+    })
+}
+
+/// Creates the note for one imported page in `folder`, after uploading the
+/// export's images it embeds. Returns the note id and the content sent.
+fn import_page(
+    cli: &cli::Cli,
+    ctx: &ClientContext,
+    root: &Path,
+    page: &import::Page,
+    folder: &str,
+    coediting: bool,
+    folders: &mut HashMap<String, CreateNoteFolderInput>,
+) -> Result<(String, String), CliError> {
+    // Kibela requires content, which pages holding only subpages lack.
+    let content = if page.content.trim().is_empty() {
+        format!("# {}", page.title)
+    } else {
+        page.content.clone()
+    };
+    let (content, _) = normalize_content_in(ctx, &["local-images".to_string()], content, root)?;
+    scan_outgoing(cli, &[("title", &page.title), ("content", &content)])?;
+    let target = match folders.get(folder) {
+        Some(target) => target.clone(),
+        None => {
+            let ensured = ctx.client.ensure_folder_path(&EnsureFolderPathInput {
+                path: folder.to_string(),
+                create_missing: true,
+            })?;
+            let target = CreateNoteFolderInput {
+                group_id: ensured.group_id,
+                folder_name: ensured.folder_name,
+            };
+            folders.insert(folder.to_string(), target.clone());
+            target
+        }
+    };
+    let created = ctx.client.create_note(&CreateNoteInput {
+        title: page.title.clone(),
+        content: content.clone(),
+        group_ids: vec![target.group_id.clone()],
+        draft: None,
+        coediting,
+        folders: vec![target],
+        author_id: None,
+        published_at: None,
+        client_mutation_id: None,
+    })?;
+    Ok((created.note.id, content))
+}
+
+fn execute_stats(
+    cli: &cli::Cli,
+    args: &cli::StatsArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    if args.max_pages == 0 {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "max-pages must be greater than 0",
+        ));
+    }
+    let group_id = args.group_id.as_deref().and_then(normalize_owned);
+    let folder_id = args.folder_id.as_deref().and_then(normalize_owned);
+    if group_id.is_none() && folder_id.is_none() {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "--group-id or --folder-id is required",
+        ));
+    }
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+    // Every aggregate is a search over the scope with an empty query.
+    let scoped =
+        |updated: Option<String>, first: Option<u32>, after: Option<String>| SearchNoteInput {
+            updated,
+            group_ids: group_id.iter().cloned().collect(),
+            folder_ids: folder_id.iter().cloned().collect(),
+            first,
+            after,
+            ..SearchNoteInput::new("")
+        };
+
+    let mut total = None;
+    let scanned = paginate::collect_pages(
+        None,
+        None,
+        args.max_pages,
+        |after| -> Result<paginate::Page<Value>, CliError> {
+            let page = ctx
+                .client
+                .search_note_page(&scoped(None, args.first, after))?;
+            total = total.or(page.total_count);
+            Ok(paginate::Page::new(page.results, page.page_info))
+        },
+    )?;
+    let total = total.unwrap_or(scanned.items.len() as u64);
+
+    // Bucket sizes come from `totalCount`, so one single-item page each.
+    let mut within = Vec::with_capacity(stats::UPDATE_BUCKET_DAYS.len());
+    for days in stats::UPDATE_BUCKET_DAYS {
+        let since = template::utc_date_days_ago(*days);
+        let page =
+            ctx.client
+                .search_note_page(&scoped(Some(format!("{since}..")), Some(1), None))?;
+        within.push(page.total_count.unwrap_or(page.results.len() as u64));
+    }
+
+    let stale_before = template::utc_date_days_ago(args.stale_days);
+    let stale = ctx.client.search_note_page(&scoped(
+        Some(format!("..{stale_before}")),
+        args.first,
+        None,
+    ))?;
+
+    let data = json!({
+        "scope": { "group_id": group_id, "folder_id": folder_id },
+        "notes": total,
+        "updated": stats::update_buckets(total, &within),
+        "top_authors": stats::top_authors(&scanned.items, args.top),
+        "scanned": {
+            "notes": scanned.items.len(),
+            "pages": scanned.pages,
+            "exhausted": scanned.exhausted,
+        },
+        "stale": {
+            "days": args.stale_days,
+            "before": stale_before,
+            "count": stale.total_count.unwrap_or(stale.results.len() as u64),
+            "notes": stale.results.iter().map(stats::stale_note).collect::<Vec<_>>(),
+        },
+    });
+    let message = stats::render_text(&data);
+    let mut data = data;
+    data["meta"] = context_meta(&ctx);
+    Ok(CommandOutput { data, message })
+}
+
+fn execute_lint(
+    cli: &cli::Cli,
+    args: &cli::LintArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    match &args.command {
+        cli::LintCommand::Links(command) => {
+            // Read local files first so a bad --dir fails before any request.
+            let mut sources = Vec::new();
+            if let Some(dir) = &command.dir {
+                let files = links::markdown_files(dir)
+                    .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+                for file in files {
+                    let content = fs::read_to_string(&file).map_err(|error| {
+                        CliError::new(
+                            ErrorCode::InputInvalid,
+                            format!("failed to read {}: {error}", file.display()),
+                        )
+                    })?;
+                    sources.push((file.display().to_string(), None, content));
+                }
+            }
+            let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+            if let Some(folder_id) = &command.folder_id {
+                let notes = ctx.client.get_notes(&GetNotesInput {
+                    folder_id: folder_id.clone(),
+                    first: command.first,
+                    last: None,
+                })?;
+                for item in notes.as_array().into_iter().flatten() {
+                    let Some(id) = item.get("id").and_then(Value::as_str) else {
+                        continue;
+                    };
+                    let note = ctx.client.get_note(id)?;
+                    let label = note_path_from_id(&note.id)
+                        .map_or_else(|| note.id.clone(), |path| path.to_string());
+                    sources.push((label, Some(note.id), note.content));
+                }
+            }
+
+            let mut alive = HashMap::<String, bool>::new();
+            let mut dead = Vec::new();
+            let mut fixed = Vec::new();
+            let mut link_count = 0usize;
+            for (source, note_id, content) in &sources {
+                let mut dead_here = Vec::new();
+                for link in links::note_links(content, ctx.client.origin()) {
+                    link_count += 1;
+                    let path = link.path.to_string();
+                    let exists = match alive.get(&path) {
+                        Some(exists) => *exists,
+                        None => {
+                            let exists = note_exists(&ctx.client, &path)?;
+                            alive.insert(path.clone(), exists);
+                            exists
+                        }
+                    };
+                    if !exists {
+                        dead.push(json!({
+                            "source": source,
+                            "note_id": note_id,
+                            "link": link.link,
+                            "path": path,
+                        }));
+                        dead_here.push(link.link);
+                    }
+                }
+                if let (true, Some(note_id), false) = (command.fix, note_id, dead_here.is_empty()) {
+                    let comment = ctx.client.create_comment(&CreateCommentInput {
+                        content: links::dead_links_comment(&dead_here),
+                        note_id: note_id.clone(),
+                    })?;
+                    fixed.push(json!({ "note_id": note_id, "comment_id": comment.id }));
+                }
+            }
+
+            let summary = json!({
+                "notes": sources.len(),
+                "links": link_count,
+                "targets": alive.len(),
+                "dead": dead.len(),
+                "commented": fixed.len(),
+            });
+            if !dead.is_empty() {
+                return Err(CliError::new(
+                    ErrorCode::PreconditionFailed,
+                    format!(
+                        "lint links: {} dead link(s) in {} note(s)",
+                        dead.len(),
+                        sources.len()
+                    ),
+                )
+                .with_details(json!({ "dead": dead, "fixed": fixed, "summary": summary })));
+            }
+            Ok(CommandOutput {
+                data: json!({
+                    "dead": dead,
+                    "fixed": fixed,
+                    "summary": summary,
+                    "meta": context_meta(&ctx),
+                }),
+                message: format!("lint links: no dead links in {} note(s)", sources.len()),
+            })
+        }
+    }
+}
+
+/// Scans outgoing `(field, text)` pairs for credentials. In `block` mode
+/// (the default) a finding fails with `SECRET_DETECTED` before anything is
+/// sent; in `warn` mode the findings are returned for `data.secret_scan`.
+fn scan_outgoing(cli: &cli::Cli, fields: &[(&str, &str)]) -> Result<Value, CliError> {
+    let (_, config) = load_config(cli)?;
+    let config = config.secret_scan.unwrap_or_default();
+    let mode = cli.secret_scan.or(config.mode).unwrap_or_default();
+    if mode == SecretScanMode::Off {
+        return Ok(Value::Null);
+    }
+    let scanner = SecretScanner::from_config(&config)?;
+    let findings = fields
+        .iter()
+        .flat_map(|(field, text)| scanner.scan(field, text))
+        .collect::<Vec<_>>();
+    if findings.is_empty() {
+        return Ok(Value::Null);
+    }
+    let report = json!({ "mode": mode.as_str(), "findings": findings });
+    if mode == SecretScanMode::Block {
+        return Err(CliError::new(
+            ErrorCode::SecretDetected,
+            format!(
+                "{} possible secret(s) in outgoing content; remove them or pass --secret-scan warn",
+                findings.len()
+            ),
+        )
+        .with_details(report));
+    }
+    Ok(report)
+}
+
+/// `message` with a note about `secret_scan` findings sent in warn mode.
+fn with_secret_warning(message: &str, secret_scan: &Value) -> String {
+    match secret_scan["findings"].as_array() {
+        Some(findings) => format!(
+            "{message} (warning: {} possible secret(s); see data.secret_scan)",
+            findings.len()
+        ),
+        None => message.to_string(),
+    }
+}
+
+/// Runs `note create --check`: the `[pre_publish]` rules from config, plus a
+/// lookup of every note the content links to. Violations fail with
+/// `PRECONDITION_FAILED` before anything is written, unless `force`.
+fn pre_publish_check(
+    cli: &cli::Cli,
+    ctx: &ClientContext,
+    content: &str,
+    force: bool,
+) -> Result<Value, CliError> {
+    let (_, config) = load_config(cli)?;
+    let rules = config.pre_publish.unwrap_or_default();
+    let mut violations = prepublish::check(content, &rules);
+    if rules.check_links {
+        for link in links::note_links(content, ctx.client.origin()) {
+            if !note_exists(&ctx.client, &link.path.to_string())? {
+                violations.push(prepublish::Violation::broken_link(
+                    &link.link,
+                    "links to a note that does not exist",
+                    prepublish::line_of(content, &link.link),
+                ));
+            }
+        }
+    }
+    if !violations.is_empty() && !force {
+        return Err(CliError::new(
+            ErrorCode::PreconditionFailed,
+            format!(
+                "pre-publish check: {} violation(s); pass --force to publish anyway",
+                violations.len()
+            ),
+        )
+        .with_details(json!({ "violations": violations })));
+    }
+    Ok(json!({ "violations": violations, "forced": !violations.is_empty() }))
+}
+
+/// `noteFromPath` succeeds for `path`; `NOT_FOUND` means it does not exist.
+fn note_exists(client: &KibelClient, path: &str) -> Result<bool, CliError> {
+    match client.get_note_from_path(&PathLookupInput {
+        path: path.to_string(),
+        first: Some(1),
+    }) {
+        Ok(_) => Ok(true),
+        Err(error) => {
+            let error = CliError::from(error);
+            if error.code == ErrorCode::NotFound {
+                Ok(false)
+            } else {
+                Err(error)
+            }
+        }
+    }
+}
+
+fn execute_report(
+    cli: &cli::Cli,
+    args: &cli::ReportArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+    out: OutputContext,
+) -> Result<CommandOutput, CliError> {
+    match &args.command {
+        cli::ReportCommand::Attachments(command) => {
+            if command.max_pages == 0 {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "max-pages must be greater than 0",
+                ));
+            }
+            if command.hash_max_mib == 0 || command.hash_max_mib > 64 {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "hash-max-mib must be in range 1..=64",
+                ));
+            }
+            if command.min_size_kib > 0 && !command.hash {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    "--min-size-kib requires --hash (sizes are only known after download)",
+                ));
+            }
+            let group_id = normalize_owned(&command.group_id)
+                .ok_or_else(|| CliError::new(ErrorCode::InputInvalid, "--group-id is required"))?;
+            let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+
+            let scanned = paginate::collect_pages(
+                None,
+                None,
+                command.max_pages,
+                |after| -> Result<paginate::Page<report::AttachmentHit>, CliError> {
+                    let page = ctx.client.search_note_with_page_info(&SearchNoteInput {
+                        resources: vec!["ATTACHMENT".to_string()],
+                        group_ids: vec![group_id.clone()],
+                        first: command.first,
+                        after,
+                        ..SearchNoteInput::new(command.query.clone())
+                    })?;
+                    Ok(paginate::Page::new(
+                        page.get("results")
+                            .and_then(Value::as_array)
+                            .into_iter()
+                            .flatten()
+                            .filter_map(report::AttachmentHit::from_search_result)
+                            .collect(),
+                        page.get("pageInfo").cloned().unwrap_or(Value::Null),
+                    ))
+                },
+            )?;
+            let (pages, exhausted, hits) = (scanned.pages, scanned.exhausted, scanned.items);
+
+            let (dedup_key, keyed, hash_errors) = if command.hash {
+                let max_bytes = usize::try_from(command.hash_max_mib)
+                    .ok()
+                    .and_then(|mib| mib.checked_mul(1024 * 1024))
+                    .ok_or_else(|| {
+                        CliError::new(ErrorCode::InputInvalid, "hash-max-mib is out of range")
+                    })?;
+                let progress = Progress::new(out, "hashing attachments", None);
+                let digests = concurrency::run_with_progress(
+                    &hits,
+                    command.concurrency,
+                    &progress,
+                    |_, hit| {
+                        ctx.client.digest_attachment(&AttachmentDigestInput {
+                            url: hit.url.clone(),
+                            max_bytes,
+                        })
+                    },
+                );
+                let mut keyed = Vec::with_capacity(hits.len());
+                let mut errors = Vec::new();
+                for (hit, digest) in hits.iter().zip(digests) {
+                    match digest {
+                        Ok(digest) => keyed.push(report::KeyedAttachment {
+                            hit: hit.clone(),
+                            key: digest.sha256,
+                            size: digest.content_length,
+                        }),
+                        Err(error) => errors.push(json!({
+                            "url": hit.url,
+                            "message": error.to_string(),
+                        })),
+                    }
+                }
+                (report::DedupKey::ContentHash, keyed, errors)
+            } else {
+                let keyed = hits
+                    .iter()
+                    .map(|hit| report::KeyedAttachment {
+                        key: report::file_name_key(hit),
+                        hit: hit.clone(),
+                        size: None,
+                    })
+                    .collect();
+                (report::DedupKey::FileName, keyed, Vec::new())
+            };
+
+            let groups = report::duplicate_groups(keyed, command.min_size_kib.saturating_mul(1024));
+            let reclaimable_bytes = groups
+                .iter()
+                .filter_map(|group| group["reclaimable_bytes"].as_u64())
+                .sum::<u64>();
+            Ok(CommandOutput {
+                message: format!(
+                    "report attachments: {} duplicate group(s) across {} attachment(s)",
+                    groups.len(),
+                    hits.len()
+                ),
+                data: json!({
+                    "group_id": group_id,
+                    "dedup_key": dedup_key.as_str(),
+                    "scanned": {
+                        "attachments": hits.len(),
+                        "pages": pages,
+                        "complete": exhausted,
+                    },
+                    "duplicates": groups,
+                    "reclaimable_bytes": if command.hash { json!(reclaimable_bytes) } else { Value::Null },
+                    "hash_errors": hash_errors,
+                    "meta": context_meta(&ctx),
+                }),
+            })
+        }
+    }
+}
+
+fn execute_link(
+    cli: &cli::Cli,
+    args: &cli::LinkArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    match &args.command {
+        cli::LinkCommand::Resolve(command) => {
+            let reference = parse_note_ref(&command.reference)
+                .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+            let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+            let resolved = resolve_note_ref(&ctx.client, &reference)?;
+            let message = resolved.url.clone().unwrap_or_else(|| resolved.id.clone());
+
+            Ok(CommandOutput {
+                data: json!({
+                    "input": command.reference.trim(),
+                    "kind": reference.kind(),
+                    "id": resolved.id,
+                    "title": resolved.title,
+                    "path": resolved.path,
+                    "url": resolved.url,
+                    "redirected": resolved.redirected,
+                    "meta": context_meta(&ctx),
+                }),
+                message,
+            })
+        }
+        cli::LinkCommand::Make(command) => {
+            let reference = parse_note_ref(&command.id)
+                .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+            let (origin, path) = match &reference {
+                NoteRef::Id(id) => {
+                    let path = note_path_from_id(id).ok_or_else(|| {
+                        CliError::new(
+                            ErrorCode::InputInvalid,
+                            format!(
+                                "cannot derive a note path from id `{id}`; use `link resolve` instead"
+                            ),
+                        )
+                    })?;
+                    (resolve_link_origin(cli)?, path)
+                }
+                NoteRef::Path(path) => (resolve_link_origin(cli)?, path.clone()),
+                NoteRef::Url { origin, path } => (origin.clone(), path.clone()),
+            };
+            let url = path.url(&origin);
+
+            Ok(CommandOutput {
+                data: json!({
+                    "input": command.id.trim(),
+                    "kind": reference.kind(),
+                    "id": match &reference {
+                        NoteRef::Id(id) => Value::String(id.clone()),
+                        _ => Value::Null,
+                    },
+                    "path": path.to_string(),
+                    "url": url,
+                    "origin": origin,
+                }),
+                message: url,
+            })
+        }
+    }
+}
+
+fn resolve_link_origin(cli: &cli::Cli) -> Result<String, CliError> {
+    let (_, config) = load_config(cli)?;
+    let requested_team = requested_team_from_cli(cli);
+    let requested_origin = requested_origin_from_cli(cli);
+    config
+        .resolve_origin(requested_origin.as_deref(), requested_team.as_deref())
+        .and_then(|origin| normalize_origin_owned(&origin))
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorCode::InputInvalid,
+                "origin is required (--origin/KIBELA_ORIGIN or profile origin)",
+            )
+        })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GraphqlGuardrails {
+    timeout_secs: u64,
+    response_limit_bytes: usize,
+    max_depth: u32,
+    max_complexity: u32,
+    allow_mutation: bool,
+    unsafe_no_cost_check: bool,
+}
+
+fn execute_graphql(
+    cli: &cli::Cli,
+    args: &cli::GraphqlArgs,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<CommandOutput, CliError> {
+    if let cli::GraphqlCommand::Cost(command) = &args.command {
+        return execute_graphql_cost(command);
+    }
+    let ctx = resolve_client_context(cli, stdin_token, env_token)?;
+
+    match &args.command {
+        cli::GraphqlCommand::Run(command) => {
+            let document = &command.document;
+            let query = resolve_graphql_query(document)?;
+            let mut variables = resolve_graphql_variables(document)?;
+            let guardrails = build_graphql_guardrails(command)?;
+            let operation = enforce_graphql_guardrails(
+                &query,
+                document.operation_name.as_deref(),
+                &variables,
+                guardrails,
+            )?;
+            fill_graphql_variables(
+                &operation,
+                &document.vars,
+                &mut variables,
+                is_interactive_terminal(),
+            )?;
+            let schema = document
+                .schema_snapshot
+                .as_deref()
+                .map(|path| load_graphql_schema(path).map(|schema| (path, schema)))
+                .transpose()?;
+            if let Some((path, schema)) = &schema {
+                validate_graphql_against_schema(&operation, schema, path)?;
+            }
+            let cost = enforce_graphql_cost(
+                &operation,
+                &variables,
+                schema.as_ref().map(|(_, schema)| schema),
+                guardrails,
+            )?;
+            if let Some(policy) = ctx.client.policy() {
+                enforce_graphql_policy(policy, &operation, &variables)?;
+            }
+
+            let run = if command.persist {
+                KibelClient::run_untrusted_graphql_persisted
+            } else {
+                KibelClient::run_untrusted_graphql_operation
+            };
+            let response = run(
+                &ctx.client,
+                &query,
+                document.operation_name.as_deref(),
+                variables,
+                guardrails.timeout_secs.saturating_mul(1000),
+                guardrails.response_limit_bytes,
+            )?;
+
+            Ok(CommandOutput {
+                data: json!({
+                    "response": response,
+                    "meta": {
+                        "team": ctx.team,
+                        "origin": ctx.client.origin(),
+                        "token_source": ctx.token_source,
+                        "config_layers": ctx.config_layers,
+                        "operation_name": operation.name,
+                        "cost": cost.map(|cost| cost.total),
+                        "persisted": command.persist,
+                        "guardrails": {
+                            "timeout_secs": guardrails.timeout_secs,
+                            "response_limit_bytes": guardrails.response_limit_bytes,
+                            "max_depth": guardrails.max_depth,
+                            "max_complexity": guardrails.max_complexity,
+                            "allow_mutation": guardrails.allow_mutation,
+                            "unsafe_no_cost_check": guardrails.unsafe_no_cost_check,
+                        }
+                    }
+                }),
+                message: "graphql run completed".to_string(),
+            })
+        }
+        cli::GraphqlCommand::Cost(_) => unreachable!("graphql cost is handled above"),
+    }
+}
+
+/// Estimates a document's cost the way `graphql run` does, without a token or
+/// a request. Variables that are not given count at their default page size.
+fn execute_graphql_cost(command: &cli::GraphqlCostArgs) -> Result<CommandOutput, CliError> {
+    let document = &command.document;
+    let query = resolve_graphql_query(document)?;
+    let mut variables = resolve_graphql_variables(document)?;
+    let operation = graphql_doc::select_operation(&query, document.operation_name.as_deref())
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+    apply_graphql_var_overrides(&operation, &document.vars, &mut variables)?;
+    let schema = document
+        .schema_snapshot
+        .as_deref()
+        .map(load_graphql_schema)
+        .transpose()?;
+    let invalid = |error: String| CliError::new(ErrorCode::InputInvalid, error);
+    let shape = operation.shape().map_err(invalid)?;
+    let cost = operation
+        .cost(&variables, graphql_cost_model(schema.as_ref()))
+        .map_err(invalid)?;
+
+    let mut lines = vec![format!(
+        "estimated cost {} (depth {}, {} field(s) in the document)",
+        cost.total, shape.max_depth, shape.complexity
+    )];
+    for connection in &cost.connections {
+        lines.push(format!(
+            "  {}: {} per page ({}) x {}",
+            connection.path, connection.page_size, connection.page_size_from, connection.multiplier
+        ));
+    }
+    Ok(CommandOutput {
+        data: json!({
+            "operation_name": operation.name,
+            "cost": cost.total,
+            "depth": shape.max_depth,
+            "fields": shape.complexity,
+            "connections": cost.connections,
+            "schema_snapshot": document
+                .schema_snapshot
+                .as_ref()
+                .map(|path| path.display().to_string()),
+        }),
+        message: lines.join("\n"),
+    })
+}
+
+fn resolve_graphql_query(command: &cli::GraphqlDocumentArgs) -> Result<String, CliError> {
+    if let Some(raw) = command.query.as_deref() {
+        return normalize_owned(raw).ok_or_else(|| {
+            CliError::new(
+                ErrorCode::InputInvalid,
+                "--query is empty; provide GraphQL query text",
+            )
+        });
+    }
+
+    if let Some(path) = &command.query_file {
+        let raw = fs::read_to_string(path).map_err(|error| {
+            CliError::new(
+                ErrorCode::TransportError,
+                format!("failed to read query file {}: {error}", path.display()),
+            )
+        })?;
+        return normalize_owned(&raw).ok_or_else(|| {
+            CliError::new(
+                ErrorCode::InputInvalid,
+                "query file is empty; provide GraphQL query text",
+            )
+        });
+    }
+
+    Err(CliError::new(
+        ErrorCode::InputInvalid,
+        "either --query or --query-file is required",
+    ))
+}
+
+fn resolve_graphql_variables(command: &cli::GraphqlDocumentArgs) -> Result<Value, CliError> {
+    let raw = if let Some(path) = &command.variables_file {
+        fs::read_to_string(path).map_err(|error| {
+            CliError::new(
+                ErrorCode::TransportError,
+                format!("failed to read variables file {}: {error}", path.display()),
+            )
+        })?
+    } else {
+        command
+            .variables
+            .clone()
+            .unwrap_or_else(|| "{}".to_string())
+    };
+
+    let parsed = serde_json::from_str::<Value>(&raw).map_err(|error| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            format!("variables must be valid JSON object: {error}"),
+        )
+    })?;
+
+    if !parsed.is_object() {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "variables must be a JSON object",
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Applies `--var` overrides, then prompts for declared variables that are
+/// still unset (TTY only). Without a TTY, missing required variables fail
+/// before anything is sent.
+fn fill_graphql_variables(
+    operation: &graphql_doc::SelectedOperation,
+    overrides: &[(String, String)],
+    variables: &mut Value,
+    interactive: bool,
+) -> Result<(), CliError> {
+    apply_graphql_var_overrides(operation, overrides, variables)?;
+    let Some(object) = variables.as_object_mut() else {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "variables must be a JSON object",
+        ));
+    };
+
+    let mut missing = Vec::new();
+    for decl in &operation.variables() {
+        if decl.has_default || object.contains_key(&decl.name) {
+            continue;
+        }
+        if interactive {
+            if let Some(value) = prompt_graphql_variable(decl)? {
+                object.insert(decl.name.clone(), value);
+            }
+        } else if decl.is_required() {
+            missing.push(format!("${}: {}", decl.name, decl.type_ref));
+        }
+    }
+    if !missing.is_empty() {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "missing required variable(s) {}; pass --var KEY=VALUE or --variables",
+                missing.join(", ")
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Sets each `--var KEY=VALUE`, typed from the operation's declaration.
+fn apply_graphql_var_overrides(
+    operation: &graphql_doc::SelectedOperation,
+    overrides: &[(String, String)],
+    variables: &mut Value,
+) -> Result<(), CliError> {
+    let declared = operation.variables();
+    let Some(object) = variables.as_object_mut() else {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "variables must be a JSON object",
+        ));
+    };
+    for (key, raw) in overrides {
+        let Some(decl) = declared.iter().find(|decl| decl.name == *key) else {
+            return Err(CliError::new(
+                ErrorCode::InputInvalid,
+                format!("--var {key}: the operation does not declare `${key}`"),
+            )
+            .with_details(json!({
+                "declared": declared.iter().map(|decl| &decl.name).collect::<Vec<_>>(),
+            })));
+        };
+        let value = decl.coerce(raw).map_err(|error| {
+            CliError::new(ErrorCode::InputInvalid, format!("--var {key}: {error}"))
+        })?;
+        object.insert(key.clone(), value);
+    }
+    Ok(())
+}
+
+/// Prompts for one variable, masking input when the name looks like a
+/// credential. A blank answer omits a nullable variable.
+fn prompt_graphql_variable(decl: &graphql_doc::VariableDecl) -> Result<Option<Value>, CliError> {
+    let label = if decl.is_required() {
+        format!("${} ({})", decl.name, decl.type_ref)
+    } else {
+        format!("${} ({}, blank to omit)", decl.name, decl.type_ref)
+    };
+    loop {
+        let raw = if kibel_client::redact::is_secret_key(&decl.name) {
+            prompt_password(format!("{label}: ")).map_err(|err| {
+                CliError::new(
+                    ErrorCode::TransportError,
+                    format!("failed to read secret input: {err}"),
+                )
+            })?
+        } else {
+            read_prompt_line(&label)?
+        };
+        if raw.trim().is_empty() {
+            if decl.is_required() {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("${} is required", decl.name),
+                ));
+            }
+            return Ok(None);
+        }
+        match decl.coerce(&raw) {
+            Ok(value) => return Ok(Some(value)),
+            Err(error) => eprintln!("{error}"),
+        }
+    }
+}
+
+fn build_graphql_guardrails(command: &cli::GraphqlRunArgs) -> Result<GraphqlGuardrails, CliError> {
+    if command.timeout_secs == 0 || command.timeout_secs > cli::GRAPHQL_RUN_MAX_TIMEOUT_SECS {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "timeout-secs must be in range 1..={}",
+                cli::GRAPHQL_RUN_MAX_TIMEOUT_SECS
+            ),
+        ));
+    }
+    if command.response_limit_mib == 0
+        || command.response_limit_mib > cli::GRAPHQL_RUN_MAX_RESPONSE_LIMIT_MIB
+    {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "response-limit-mib must be in range 1..={}",
+                cli::GRAPHQL_RUN_MAX_RESPONSE_LIMIT_MIB
+            ),
+        ));
+    }
+    if command.max_depth == 0 {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "max-depth must be greater than 0",
+        ));
+    }
+    if command.max_complexity == 0 {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "max-complexity must be greater than 0",
+        ));
+    }
+
+    let mib = usize::try_from(command.response_limit_mib).map_err(|_| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            "response-limit-mib is out of supported range",
+        )
+    })?;
+    let response_limit_bytes = mib.checked_mul(1024 * 1024).ok_or_else(|| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            "response-limit-mib overflowed byte conversion",
+        )
+    })?;
+
+    Ok(GraphqlGuardrails {
+        timeout_secs: command.timeout_secs,
+        response_limit_bytes,
+        max_depth: command.max_depth,
+        max_complexity: command.max_complexity,
+        allow_mutation: command.allow_mutation,
+        unsafe_no_cost_check: command.unsafe_no_cost_check,
+    })
+}
+
+fn enforce_graphql_guardrails(
+    query: &str,
+    operation_name: Option<&str>,
+    variables: &Value,
+    guardrails: GraphqlGuardrails,
+) -> Result<graphql_doc::SelectedOperation, CliError> {
+    if !variables.is_object() {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "variables must be a JSON object",
+        ));
+    }
+
+    let operation = graphql_doc::select_operation(query, operation_name)
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+
+    if operation.kind == graphql_doc::OperationKind::Mutation {
+        if !guardrails.allow_mutation {
+            return Err(CliError::new(
+                ErrorCode::InputInvalid,
+                "mutation is blocked in graphql run mode; pass --allow-mutation to execute",
+            ));
+        }
+        enforce_mutation_allowlist(&operation)?;
+    }
+
+    match operation.shape() {
+        Ok(shape) => {
+            if shape.max_depth > guardrails.max_depth {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!(
+                        "query depth {} exceeds max-depth {}",
+                        shape.max_depth, guardrails.max_depth
+                    ),
+                ));
+            }
+        }
+        Err(error) => {
+            if !guardrails.unsafe_no_cost_check {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!(
+                        "query shape analysis failed: {error}; rerun with --unsafe-no-cost-check to bypass"
+                    ),
+                ));
+            }
+        }
+    }
+
+    Ok(operation)
+}
+
+/// Checks the estimated cost against `--max-complexity`. Analysis failures
+/// pass only with `--unsafe-no-cost-check` (and then report no cost).
+fn enforce_graphql_cost(
+    operation: &graphql_doc::SelectedOperation,
+    variables: &Value,
+    schema: Option<&graphql_schema::SchemaSnapshot>,
+    guardrails: GraphqlGuardrails,
+) -> Result<Option<graphql_doc::QueryCost>, CliError> {
+    match operation.cost(variables, graphql_cost_model(schema)) {
+        Ok(cost) if cost.total > u64::from(guardrails.max_complexity) => Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "query cost {} exceeds max-complexity {}; check it with `graphql cost`",
+                cost.total, guardrails.max_complexity
+            ),
+        )
+        .with_details(json!({
+            "cost": cost.total,
+            "max_complexity": guardrails.max_complexity,
+            "connections": cost.connections,
+        }))),
+        Ok(cost) => Ok(Some(cost)),
+        Err(_) if guardrails.unsafe_no_cost_check => Ok(None),
+        Err(error) => Err(CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "query cost analysis failed: {error}; rerun with --unsafe-no-cost-check to bypass"
+            ),
+        )),
+    }
+}
+
+/// Connections are the fields the trusted contracts page, plus `*Connection`
+/// fields when a schema snapshot is given.
+fn graphql_cost_model(
+    schema: Option<&graphql_schema::SchemaSnapshot>,
+) -> graphql_doc::CostModel<'_> {
+    static CONNECTION_FIELDS: std::sync::OnceLock<HashSet<String>> = std::sync::OnceLock::new();
+    graphql_doc::CostModel {
+        connection_fields: CONNECTION_FIELDS.get_or_init(|| {
+            graphql_doc::connection_fields(
+                resource_contracts()
+                    .iter()
+                    .map(|contract| contract.document),
+            )
+        }),
+        schema,
+    }
+}
+
+fn load_graphql_schema(path: &Path) -> Result<graphql_schema::SchemaSnapshot, CliError> {
+    graphql_schema::SchemaSnapshot::load(path)
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))
+}
+
+fn validate_graphql_against_schema(
+    operation: &graphql_doc::SelectedOperation,
+    schema: &graphql_schema::SchemaSnapshot,
+    path: &Path,
+) -> Result<(), CliError> {
+    let errors = operation.validate(schema);
+    let Some(first) = errors.first() else {
+        return Ok(());
+    };
+    Err(CliError::new(
+        ErrorCode::InputInvalid,
+        format!(
+            "query does not match schema snapshot ({} error(s)): {first}",
+            errors.len()
+        ),
+    )
+    .with_details(json!({
+        "schema_snapshot": path.display().to_string(),
+        "errors": errors,
+    })))
+}
+
+/// Applies the policy file to the document itself: root fields must belong
+/// to allowlisted operations, and literal arguments get the same caps the
+/// client applies to variables.
+fn enforce_graphql_policy(
+    policy: &Policy,
+    operation: &graphql_doc::SelectedOperation,
+    variables: &Value,
+) -> Result<(), CliError> {
+    let root_fields = operation
+        .root_fields()
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+    policy.check_root_fields(&root_fields)?;
+    let arguments = operation
+        .argument_values(variables)
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+    policy.check_values(&Value::Array(arguments))?;
+    Ok(())
+}
+
+fn enforce_mutation_allowlist(operation: &graphql_doc::SelectedOperation) -> Result<(), CliError> {
+    let mutation_root_fields = operation
+        .root_fields()
+        .map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+    let allowed_roots = trusted_mutation_root_fields();
+    let mut blocked_roots = mutation_root_fields
+        .iter()
+        .filter(|field| !allowed_roots.contains(field.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if blocked_roots.is_empty() {
+        return Ok(());
+    }
+
+    blocked_roots.sort();
+    blocked_roots.dedup();
+
+    let mut allowed_sorted = allowed_roots.iter().copied().collect::<Vec<_>>();
+    allowed_sorted.sort_unstable();
+    Err(CliError::new(
+        ErrorCode::InputInvalid,
+        format!(
+            "mutation root field(s) are not allowlisted for graphql run: {}; allowed fields: {}",
+            blocked_roots.join(", "),
+            allowed_sorted.join(", ")
+        ),
+    ))
+}
+
+fn trusted_mutation_root_fields() -> HashSet<&'static str> {
+    resource_contracts()
+        .iter()
+        .filter(|contract| contract.kind == "mutation")
+        .filter_map(|contract| {
+            contract
+                .graphql_file
+                .strip_prefix("endpoint:mutation.")
+                .filter(|field| !field.is_empty())
+        })
+        .collect()
+}
+
+fn execute_version(command: &cli::VersionArgs) -> Result<CommandOutput, CliError> {
+    let version = env!("CARGO_PKG_VERSION");
+    if !command.check {
+        return Ok(CommandOutput {
+            data: json!({ "version": version }),
+            message: version.to_string(),
+        });
+    }
+    let release = release_updater(None)?.latest(false).map_err(update_error)?;
+    let update_available = self_update::is_newer(release.version(), version);
+    Ok(CommandOutput {
+        data: json!({
+            "version": version,
+            "latest_version": release.version(),
+            "update_available": update_available,
+        }),
+        message: if update_available {
+            format!(
+                "{version} (kibel {} is available; run `kibel self update`)",
+                release.version()
+            )
+        } else {
+            format!("{version} (up to date)")
+        },
+    })
+}
+
+fn execute_self(args: &cli::SelfArgs) -> Result<CommandOutput, CliError> {
+    match &args.command {
+        cli::SelfCommand::Update(command) => {
+            let current = env!("CARGO_PKG_VERSION");
+            let updater = release_updater(command.proxy.as_deref())?;
+            let release = updater
+                .latest(command.channel == cli::UpdateChannel::Prerelease)
+                .map_err(update_error)?;
+            let latest = release.version().to_string();
+            let update_available = self_update::is_newer(&latest, current);
+            let mut data = json!({
+                "current_version": current,
+                "latest_version": latest,
+                "tag": release.tag_name,
+                "channel": command.channel.as_str(),
+                "update_available": update_available,
+                "updated": false,
+                "path": Value::Null,
+                "sha256": Value::Null,
+            });
+            if !update_available {
+                return Ok(CommandOutput {
+                    data,
+                    message: format!("kibel {current} is up to date"),
+                });
+            }
+            if command.check_only {
+                return Ok(CommandOutput {
+                    data,
+                    message: format!(
+                        "kibel {latest} is available (current {current}); run `kibel self update`"
+                    ),
+                });
+            }
+
+            // Resolve symlinks so the swap replaces the real file, not the link.
+            let target = std::env::current_exe()
+                .and_then(fs::canonicalize)
+                .map_err(|error| {
+                    CliError::new(
+                        ErrorCode::PreconditionFailed,
+                        format!("failed to locate the running binary: {error}"),
+                    )
+                })?;
+            let sha256 = updater.install(&release, &target).map_err(update_error)?;
+            data["updated"] = json!(true);
+            data["path"] = json!(target);
+            data["sha256"] = json!(sha256);
+            Ok(CommandOutput {
+                data,
+                message: format!("updated kibel {current} -> {latest}"),
+            })
+        }
+    }
+}
+
+/// `KIBEL_UPDATE_URL` replaces the GitHub releases API, e.g. with a mirror.
+fn release_updater(proxy: Option<&str>) -> Result<self_update::Updater, CliError> {
+    let url = std::env::var("KIBEL_UPDATE_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or_else(|| self_update::DEFAULT_RELEASES_URL.to_string());
+    self_update::Updater::new(&url, proxy).map_err(update_error)
+}
+
+fn update_error(error: self_update::UpdateError) -> CliError {
+    match error {
+        self_update::UpdateError::Transport(message) => {
+            CliError::new(ErrorCode::TransportError, message)
+        }
+        self_update::UpdateError::Precondition(message) => {
+            CliError::new(ErrorCode::PreconditionFailed, message)
+        }
+    }
+}
+
+fn resolve_client_context(
+    cli: &cli::Cli,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<ClientContext, CliError> {
+    resolve_client_context_for(
+        cli,
+        requested_team_from_cli(cli),
+        requested_origin_from_cli(cli),
+        stdin_token,
+        env_token,
+    )
+}
+
+/// Builds a client for an explicit team/origin instead of the global
+/// `--team`/`--origin`, so one invocation can talk to several tenants.
+fn resolve_client_context_for(
+    cli: &cli::Cli,
+    requested_team: Option<String>,
+    requested_origin: Option<String>,
+    stdin_token: Option<String>,
+    env_token: Option<String>,
+) -> Result<ClientContext, CliError> {
+    let (config_path, config, config_layers) = load_layered_config(cli)?;
+    let backend = token_store_backend(&config)?;
+
+    let vcr = vcr_mode(cli)?;
+
+    let resolved = resolve_access_token(
+        &ResolveTokenInput {
+            requested_team: requested_team.clone(),
+            requested_origin: requested_origin.clone(),
+            stdin_token,
+            env_token,
+        },
+        &config,
+        open_token_store(backend, &config_path).as_ref(),
+    )?;
+    // Replays never reach the network, so they run without credentials.
+    let (token, token_source, resolved_team) = match (resolved, &vcr) {
+        (Some(resolved), _) => (
+            resolved.token,
+            token_source_name(resolved.source, backend).to_string(),
+            resolved.team,
+        ),
+        (None, Some(VcrMode::Replay(_))) => ("replay".to_string(), "replay".to_string(), None),
+        (None, _) => {
+            return Err(CliError::new(
+                ErrorCode::AuthFailed,
+                "no access token found (stdin/env/keychain/config)",
+            ))
+        }
+    };
+
+    let team = resolved_team.or_else(|| config.resolve_team(requested_team.as_deref()));
+    let origin = config
+        .resolve_origin(requested_origin.as_deref(), team.as_deref())
+        .ok_or_else(|| {
+            CliError::new(
+                ErrorCode::InputInvalid,
+                "origin is required (--origin/KIBELA_ORIGIN or profile origin)",
+            )
+        })?;
+    let mut client = KibelClient::new(origin, token.clone())?
+        .with_request_id(cli.request_id.clone())
+        .with_read_only(cli.read_only)
+        .with_timeouts(client_timeouts(cli));
+    if let Some(path) = cli.policy.as_deref() {
+        client = client.with_policy(Policy::load(path)?);
+    }
+    if let Some(headers) = team
+        .as_deref()
+        .and_then(|team| config.extra_headers_for_team(team))
+    {
+        client = client.with_extra_headers(headers.clone())?;
+    }
+    if let Some(path) = apq_cache_path() {
+        client = client.with_apq_cache(Arc::new(ApqCache::open(path)));
+    }
+    if cli.metrics {
+        if let Some(path) = metrics_path() {
+            client = client.with_metrics(Arc::new(Metrics::open(path)));
+        }
+    }
+    if let Some(usage) = usage_stats(&config) {
+        client = client.with_usage(Arc::new(usage));
+    }
+    match vcr {
+        Some(VcrMode::Record(dir)) => {
+            let live = Arc::new(client.http_transport().clone());
+            client =
+                client.with_transport(Arc::new(VcrTransport::record(dir, live).redacting(token)));
+        }
+        Some(VcrMode::Replay(dir)) => {
+            client = client.with_transport(Arc::new(VcrTransport::replay(dir)));
+        }
+        None => {}
+    }
+
+    Ok(ClientContext {
+        team,
+        token_source,
+        client,
+        config_layers,
+    })
+}
+
+/// Resolves the content pipeline: `--raw` disables it, `--on-fetch` replaces
+/// it, otherwise the team profile's or top-level `on_fetch` config applies.
+fn resolve_on_fetch(
+    cli: &cli::Cli,
+    args: &cli::OnFetchArgs,
+    ctx: &ClientContext,
+) -> Result<transform::Pipeline, CliError> {
+    if args.raw {
+        return Ok(transform::Pipeline::default());
+    }
+    let pipeline = if args.transforms.is_empty() {
+        let (_, config) = load_config(cli)?;
+        transform::Pipeline::parse(
+            config.on_fetch_for_team(ctx.team.as_deref().unwrap_or_default()),
+        )
+    } else {
+        transform::Pipeline::parse(&args.transforms)
+    };
+    pipeline.map_err(|error| CliError::new(ErrorCode::InputInvalid, error))
+}
+
+fn on_fetch_meta(ctx: &ClientContext, pipeline: &transform::Pipeline) -> Value {
+    let mut meta = context_meta(ctx);
+    if !pipeline.is_empty() {
+        meta["on_fetch"] = json!(pipeline.specs());
+    }
+    meta
+}
+
+fn context_meta(ctx: &ClientContext) -> Value {
+    json!({
+        "team": ctx.team,
+        "origin": ctx.client.origin(),
+        "token_source": ctx.token_source,
+        "config_layers": ctx.config_layers,
+    })
+}
+
+fn kibela_access_token_settings_url(origin: &str) -> String {
+    let base = origin.trim_end_matches('/');
+    format!("{base}/settings/access_tokens")
+}
+
+fn requested_team_from_cli(cli: &cli::Cli) -> Option<String> {
+    cli.team.as_deref().and_then(normalize_owned).or_else(|| {
+        std::env::var("KIBELA_TENANT")
+            .ok()
+            .and_then(|v| normalize_owned(&v))
+    })
+}
+
+fn requested_origin_from_cli(cli: &cli::Cli) -> Option<String> {
+    normalize_origin_owned(&cli.origin).or_else(|| {
+        std::env::var("KIBELA_TENANT_ORIGIN")
+            .ok()
+            .and_then(|v| normalize_origin_owned(&v))
+    })
+}
+
+fn resolve_login_origin(
+    requested_origin: Option<&str>,
+    requested_team: Option<&str>,
+    config: &Config,
+    interactive: bool,
+) -> Result<String, CliError> {
+    if let Some(origin) = requested_origin.and_then(normalize_origin_owned) {
+        return Ok(origin);
+    }
+
+    if let Some(origin) = config.resolve_origin(None, requested_team) {
+        if let Some(normalized) = normalize_origin_owned(&origin) {
+            return Ok(normalized);
+        }
+    }
+
+    if interactive {
+        return prompt_origin_input();
+    }
+
+    Err(CliError::new(
+        ErrorCode::InputInvalid,
+        "origin is required (--origin/KIBELA_ORIGIN or profile origin)",
+    ))
+}
+
+fn resolve_login_team(
+    requested_team: Option<&str>,
+    resolved_origin: Option<&str>,
+    config: &Config,
+    interactive: bool,
+) -> Result<String, CliError> {
+    if let Some(team) = requested_team.and_then(normalize_owned) {
+        return Ok(team);
+    }
+
+    if let Some(origin) = resolved_origin {
+        if let Some(inferred_team) = infer_team_from_origin(origin) {
+            return Ok(inferred_team);
+        }
+    }
+
+    if let Some(team) = config.resolve_team(None) {
+        if let Some(normalized) = normalize_owned(&team) {
+            return Ok(normalized);
+        }
+    }
+
+    if interactive {
+        return prompt_text_input("Kibela team (tenant)");
+    }
+
+    Err(CliError::new(
+        ErrorCode::InputInvalid,
+        "team is required (--team/KIBELA_TEAM/KIBELA_TENANT or config.default_team)",
+    ))
+}
+
+/// Saves a login token to `backend` and records the team profile in the
+/// config. Returns where the token went and, when the keychain refused it,
+/// why.
+fn store_login_token(
+    config_path: &Path,
+    config: &mut Config,
+    backend: TokenStoreBackend,
+    team: &str,
+    origin: &str,
+    token: &str,
+) -> Result<(Vec<&'static str>, Option<String>), CliError> {
+    let store = open_token_store(backend, config_path);
+    let subject = token_store_subject(team, Some(origin));
+    let mut stored_in = Vec::new();
+    let mut keychain_error = None;
+
+    match backend {
+        TokenStoreBackend::Keychain => match store.set_token(&subject, token) {
+            Ok(()) => stored_in.push("keychain"),
+            Err(err) => keychain_error = Some(err.to_string()),
+        },
+        // Nothing else holds the token, so a failure here is fatal.
+        TokenStoreBackend::EncryptedFile => {
+            store.set_token(&subject, token)?;
+            stored_in.push("encrypted-file");
+        }
+        TokenStoreBackend::Plaintext | TokenStoreBackend::None => {}
+    }
+
+    if backend.persists_to_config() {
+        config.set_profile_token(team, token);
+    }
+    config.set_profile_origin(team, origin);
+    config.set_default_team_if_missing(team);
+    config.save(config_path)?;
+    if backend.persists_to_config() {
+        stored_in.push("config");
+    }
+    Ok((stored_in, keychain_error))
+}
+
+/// Whether an error from `command` may be answered by logging in again and
+/// retrying. `auth` commands and `--with-token` runs already chose their token.
+fn reauth_applies(
+    command: &cli::Command,
+    code: ErrorCode,
+    no_reauth: bool,
+    with_token: bool,
+) -> bool {
+    code == ErrorCode::AuthFailed
+        && !no_reauth
+        && !with_token
+        && !matches!(command, cli::Command::Auth(_))
+        && command_uses_token_inputs(command)
+}
+
+/// Offers the `auth login` token prompt after AUTH_FAILED and stores the new
+/// token for the current team. Returns `None` when the user declines.
+fn prompt_reauth(
+    cli: &cli::Cli,
+    env_token_set: bool,
+    io: &mut dyn OutputSink,
+) -> Result<Option<String>, CliError> {
+    let answer =
+        read_prompt_line("Kibela rejected the access token. Log in again and retry? [y/N]")?;
+    if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        return Ok(None);
+    }
+    let token = prompt_secret_input("Kibela access token")?;
+    register_secret(&token);
+
+    let (config_path, mut config) = load_user_config(cli.config_path.clone())?;
+    let team = config.resolve_team(requested_team_from_cli(cli).as_deref());
+    let origin = config.resolve_origin(requested_origin_from_cli(cli).as_deref(), team.as_deref());
+    match (team, origin) {
+        (Some(team), Some(origin)) => {
+            let backend = token_store_backend(&config)?;
+            let (stored_in, keychain_error) =
+                store_login_token(&config_path, &mut config, backend, &team, &origin, &token)?;
+            let mut note = if stored_in.is_empty() {
+                format!("new token for {team} is used for this run only")
+            } else {
+                format!(
+                    "stored the new token for {team} in {}",
+                    stored_in.join(", ")
+                )
+            };
+            if let Some(error) = keychain_error {
+                note.push_str(&format!(" (keychain unavailable: {error})"));
+            }
+            io.stderr(&format!("{note}\n"));
+        }
+        _ => io.stderr("no team profile to store the new token in; using it for this run only\n"),
+    }
+    if env_token_set {
+        io.stderr(&format!(
+            "{} still holds the rejected token and takes precedence next time\n",
+            cli.token_env
+        ));
+    }
+    Ok(Some(token))
+}
+
+fn resolve_login_token(
+    with_token: bool,
+    stdin_token: Option<&str>,
+    env_token: Option<&str>,
+    interactive: bool,
+) -> Result<(String, &'static str), CliError> {
+    if let Some(token) = stdin_token.and_then(normalize_owned) {
+        return Ok((token, "stdin"));
+    }
+    if let Some(token) = env_token.and_then(normalize_owned) {
+        return Ok((token, "env"));
+    }
+    if with_token {
+        return Err(CliError::new(
+            ErrorCode::InputInvalid,
+            "stdin token is empty",
+        ));
+    }
+    if interactive {
+        let token = prompt_secret_input("Kibela access token")?;
+        return Ok((token, "prompt"));
+    }
+    Err(CliError::new(
+        ErrorCode::InputInvalid,
+        "token is required (--with-token stdin or token env)",
+    ))
+}
+
+/// `KIBEL_TOKEN_STORE` overrides the config `token_store` key.
+fn token_store_backend(config: &Config) -> Result<TokenStoreBackend, CliError> {
+    let Some(raw) = std::env::var("KIBEL_TOKEN_STORE")
+        .ok()
+        .filter(|raw| !raw.trim().is_empty())
+    else {
+        return Ok(config.token_store.unwrap_or_default());
+    };
+    TokenStoreBackend::parse(&raw).ok_or_else(|| {
+        let names = TokenStoreBackend::ALL.map(TokenStoreBackend::as_str);
+        CliError::new(
+            ErrorCode::InputInvalid,
+            format!(
+                "KIBEL_TOKEN_STORE must be one of {}: {raw}",
+                names.join(", ")
+            ),
+        )
+    })
+}
+
+fn open_token_store(backend: TokenStoreBackend, config_path: &Path) -> Box<dyn TokenStore> {
+    match backend {
+        TokenStoreBackend::Keychain => Box::new(KeychainTokenStore::default()),
+        TokenStoreBackend::EncryptedFile => Box::new(EncryptedFileTokenStore::new(
+            EncryptedFileTokenStore::default_path(config_path),
+            std::env::var("KIBEL_TOKEN_STORE_PASSPHRASE").ok(),
+        )),
+        TokenStoreBackend::Plaintext | TokenStoreBackend::None => Box::new(NullTokenStore),
+    }
+}
+
+/// `token_store` in `auth` output: the backend plus the OS service or file
+/// behind it.
+fn token_store_json(backend: TokenStoreBackend, config_path: &Path) -> Value {
+    let location = match backend {
+        TokenStoreBackend::Keychain => json!(KeychainTokenStore::platform_service()),
+        TokenStoreBackend::EncryptedFile => {
+            json!(EncryptedFileTokenStore::default_path(config_path))
+        }
+        TokenStoreBackend::Plaintext => json!(config_path),
+        TokenStoreBackend::None => Value::Null,
+    };
+    json!({ "backend": backend.as_str(), "location": location })
+}
+
+/// Like [`token_source_label`], but names the configured backend when the
+/// token came from a store other than the OS keychain.
+fn token_source_name(source: TokenSource, backend: TokenStoreBackend) -> &'static str {
+    match (source, backend) {
+        (TokenSource::Keychain, TokenStoreBackend::EncryptedFile) => backend.as_str(),
+        _ => token_source_label(source),
+    }
+}
+
+fn token_store_lookup_subjects(team: &str, origin: Option<&str>) -> Vec<String> {
+    let mut subjects = Vec::new();
+    if let Some(origin) = origin.and_then(normalize_origin_owned) {
+        let origin_scoped = token_store_subject(team, Some(&origin));
+        subjects.push(origin_scoped);
+    }
+    let legacy = token_store_subject(team, None);
+    if !subjects.iter().any(|subject| subject == &legacy) {
+        subjects.push(legacy);
+    }
+    subjects
+}
+
+fn prompt_text_input(label: &str) -> Result<String, CliError> {
+    let input = read_prompt_line(label)?;
+    normalize_owned(&input)
+        .ok_or_else(|| CliError::new(ErrorCode::InputInvalid, format!("{label} is empty")))
+}
+
+fn read_prompt_line(label: &str) -> Result<String, CliError> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{label}: ").map_err(|err| {
+        CliError::new(
+            ErrorCode::TransportError,
+            format!("failed to write prompt: {err}"),
+        )
+    })?;
+    stdout.flush().map_err(|err| {
+        CliError::new(
+            ErrorCode::TransportError,
+            format!("failed to flush prompt: {err}"),
+        )
+    })?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).map_err(|err| {
+        CliError::new(
+            ErrorCode::TransportError,
+            format!("failed to read prompt input: {err}"),
+        )
+    })?;
+    Ok(input)
+}
+
+fn prompt_secret_input(label: &str) -> Result<String, CliError> {
+    let value = prompt_password(format!("{label}: ")).map_err(|err| {
+        CliError::new(
+            ErrorCode::TransportError,
+            format!("failed to read secret input: {err}"),
+        )
+    })?;
+    normalize_owned(&value)
+        .ok_or_else(|| CliError::new(ErrorCode::InputInvalid, format!("{label} is empty")))
+}
+
+fn prompt_origin_input() -> Result<String, CliError> {
+    let origin = prompt_text_input("Kibela origin (https://<tenant>.kibe.la)")?;
+    normalize_origin_owned(&origin).ok_or_else(|| {
+        CliError::new(
+            ErrorCode::InputInvalid,
+            "origin must start with http:// or https://",
+        )
+    })
+}
+
+fn is_interactive_terminal() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+fn note_folder_arg_to_input(folder: &cli::NoteFolderArg) -> CreateNoteFolderInput {
+    CreateNoteFolderInput {
+        group_id: folder.group_id.clone(),
+        folder_name: folder.folder_name.clone(),
+    }
+}
+
+/// The user config with the nearest project config merged under it; see
+/// [`load_layered_config`].
+fn load_config(cli: &cli::Cli) -> Result<(PathBuf, Config), CliError> {
+    let (config_path, config, _) = load_layered_config(cli)?;
+    Ok((config_path, config))
+}
+
+/// Loads the user config over the safe settings of the nearest `.kibel.toml`
+/// above the current directory (unless `--no-project-config`), so the user
+/// config wins where both set a value. Also returns the files applied, in
+/// merge order, for `meta.config_layers`.
+///
+/// Commands that save the config use [`load_user_config`] instead, so
+/// project settings are never written into the user config.
+fn load_layered_config(cli: &cli::Cli) -> Result<(PathBuf, Config, Value), CliError> {
+    let (config_path, user) = load_user_config(cli.config_path.clone())?;
+    let project_path = if cli.no_project_config {
+        None
+    } else {
+        std::env::current_dir()
+            .ok()
+            .and_then(|dir| find_project_config(&dir))
+    };
+    let mut layers = Vec::new();
+    let config = match project_path {
+        Some(path) => {
+            let mut config = Config::load(&path)
+                .map_err(|error| {
+                    CliError::new(
+                        ErrorCode::InputInvalid,
+                        format!("{}: {error}", path.display()),
+                    )
+                })?
+                .project_settings();
+            layers.push(json!({ "source": "project", "path": path }));
+            config.merge(user);
+            config
+        }
+        None => user,
+    };
+    if config_path.exists() {
+        layers.push(json!({ "source": "user", "path": config_path }));
+    }
+    Ok((config_path, config, Value::Array(layers)))
+}
+
+fn load_user_config(config_path: Option<PathBuf>) -> Result<(PathBuf, Config), CliError> {
+    let config_path = match config_path {
+        Some(path) => path,
+        None => default_config_path()?,
+    };
+
+    let config = Config::load(&config_path)?;
+    Ok((config_path, config))
+}
+
+fn read_stdin_token(is_enabled: bool) -> Result<Option<String>, CliError> {
+    if !is_enabled {
+        return Ok(None);
+    }
+
+    let mut buffer = String::new();
+    io::stdin().read_to_string(&mut buffer).map_err(|err| {
+        CliError::new(
+            ErrorCode::TransportError,
+            format!("failed to read token from stdin: {err}"),
+        )
+    })?;
+
+    normalize_owned(&buffer)
+        .map(Some)
+        .ok_or_else(|| CliError::new(ErrorCode::InputInvalid, "stdin token is empty"))
+}
+
+fn normalize_owned(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+fn normalize_origin_owned(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let normalized = trimmed.trim_end_matches('/');
+    if normalized.is_empty() {
+        return None;
+    }
+    if !(normalized.starts_with("https://") || normalized.starts_with("http://")) {
+        return None;
+    }
+    Some(normalized.to_ascii_lowercase())
+}
+
+fn infer_team_from_origin(origin: &str) -> Option<String> {
+    let normalized = normalize_origin_owned(origin)?;
+    let host_and_path = normalized.split_once("://")?.1;
+    let host = host_and_path.split('/').next()?;
+    let host = host.split(':').next()?;
+    let tenant = host.strip_suffix(".kibe.la")?;
+    if tenant.is_empty() {
+        None
+    } else {
+        Some(tenant.to_string())
+    }
+}
+
+fn unix_timestamp_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_graphql_guardrails, client_timeouts, enforce_graphql_guardrails,
+        fill_graphql_variables, infer_team_from_origin, kibela_access_token_settings_url,
+        merge_search_note_preset, normalize_origin_owned, quoted_comments_section, reauth_applies,
+        resolve_graphql_variables, search_note_mine_has_unsupported_filters,
+        search_note_preset_from_input, token_store_lookup_subjects, trusted_mutation_root_fields,
+        GraphqlGuardrails,
+    };
+    use crate::cli;
+    use crate::error::ErrorCode;
+    use clap::Parser;
+    use kibel_client::{SearchNoteInput, SearchNotePreset, Timeouts};
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn graphql_run_args(query: &str) -> cli::GraphqlRunArgs {
+        cli::GraphqlRunArgs {
+            document: cli::GraphqlDocumentArgs {
+                query: Some(query.to_string()),
+                query_file: None,
+                operation_name: None,
+                schema_snapshot: None,
+                variables: Some("{}".to_string()),
+                variables_file: None,
+                vars: vec![],
+            },
+            timeout_secs: 15,
+            response_limit_mib: 2,
+            max_depth: 8,
+            max_complexity: 1000,
+            allow_mutation: false,
+            unsafe_no_cost_check: false,
+            persist: false,
+        }
+    }
+
+    #[test]
+    fn build_graphql_guardrails_rejects_invalid_ranges() {
+        let mut args = graphql_run_args("query Q { groups { edges { node { id } } } }");
+        args.timeout_secs = 0;
+        assert!(build_graphql_guardrails(&args).is_err());
+        args.timeout_secs = 15;
+        args.response_limit_mib = 9;
+        assert!(build_graphql_guardrails(&args).is_err());
+    }
+
+    #[test]
+    fn resolve_graphql_variables_requires_object() {
+        let mut args = graphql_run_args("query Q { groups { edges { node { id } } } }");
+        args.document.variables = Some("[1,2,3]".to_string());
+        assert!(resolve_graphql_variables(&args.document).is_err());
+    }
+
+    #[test]
+    fn fill_graphql_variables_applies_typed_overrides_and_reports_missing() {
+        let operation = crate::graphql_doc::select_operation(
+            "query Q($id: ID!, $first: Int, $after: String) { notes(first: $first, after: $after) { edges { node { id } } } note(id: $id) { id } }",
+            None,
+        )
+        .expect("query should parse");
+
+        let mut variables = json!({ "after": "cursor-1" });
+        let error = fill_graphql_variables(
+            &operation,
+            &[("first".to_string(), "5".to_string())],
+            &mut variables,
+            false,
+        )
+        .expect_err("missing $id should fail without a TTY");
+        assert!(error.message.contains("$id: ID!"), "{}", error.message);
+
+        let overrides = [
+            ("first".to_string(), "5".to_string()),
+            ("id".to_string(), "N1".to_string()),
+        ];
+        fill_graphql_variables(&operation, &overrides, &mut variables, false)
+            .expect("all required variables are set");
+        assert_eq!(
+            variables,
+            json!({ "id": "N1", "first": 5, "after": "cursor-1" })
+        );
+
+        let unknown = [("nope".to_string(), "1".to_string())];
+        assert!(fill_graphql_variables(&operation, &unknown, &mut variables, false).is_err());
+    }
+
+    #[test]
+    fn enforce_graphql_guardrails_blocks_mutation_without_opt_in() {
+        let guardrails = GraphqlGuardrails {
+            timeout_secs: 15,
+            response_limit_bytes: 2 * 1024 * 1024,
+            max_depth: 8,
+            max_complexity: 1000,
+            allow_mutation: false,
+            unsafe_no_cost_check: false,
+        };
+        let result = enforce_graphql_guardrails(
+            "mutation M($input: CreateFolderInput!) { createFolder(input: $input) { folder { id } } }",
+            None,
+            &json!({ "input": { "folder": { "groupId": "G1", "folderName": "Engineering" } } }),
+            guardrails,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn enforce_graphql_guardrails_blocks_untrusted_mutation_field() {
+        let guardrails = GraphqlGuardrails {
+            timeout_secs: 15,
+            response_limit_bytes: 2 * 1024 * 1024,
+            max_depth: 8,
+            max_complexity: 1000,
+            allow_mutation: true,
+            unsafe_no_cost_check: false,
+        };
+        let result = enforce_graphql_guardrails(
+            "mutation Dangerous($id: ID!) { deleteNote(input: { id: $id }) { clientMutationId } }",
+            None,
+            &json!({ "id": "N1" }),
+            guardrails,
+        );
+        let error = result.expect_err("untrusted mutation root should be blocked");
+        assert!(
+            error
+                .message
+                .contains("not allowlisted for graphql run: deleteNote"),
+            "unexpected error message: {}",
+            error.message
+        );
+    }
+
+    #[test]
+    fn enforce_graphql_guardrails_expands_fragments_for_allowlist() {
+        let guardrails = GraphqlGuardrails {
+            timeout_secs: 15,
+            response_limit_bytes: 2 * 1024 * 1024,
+            max_depth: 8,
+            max_complexity: 1000,
+            allow_mutation: true,
+            unsafe_no_cost_check: false,
+        };
+        let result = enforce_graphql_guardrails(
+            "query Safe { currentUser { id } } mutation Hidden { ...Roots } fragment Roots on Mutation { deleteNote(input: { id: \"N1\" }) { clientMutationId } }",
+            Some("Hidden"),
+            &json!({}),
+            guardrails,
+        );
+        let error = result.expect_err("fragment root should be checked");
+        assert!(error
+            .message
+            .contains("not allowlisted for graphql run: deleteNote"));
+
+        let operation = enforce_graphql_guardrails(
+            "query Safe { currentUser { id } } mutation Hidden { ...Roots } fragment Roots on Mutation { deleteNote(input: { id: \"N1\" }) { clientMutationId } }",
+            Some("Safe"),
+            &json!({}),
+            guardrails,
+        )
+        .expect("query operation should pass");
+        assert_eq!(operation.name.as_deref(), Some("Safe"));
+    }
+
+    #[test]
+    fn trusted_mutation_root_fields_include_create_folder() {
+        let allowed = trusted_mutation_root_fields();
+        assert!(allowed.contains("createFolder"));
+    }
+
+    #[test]
+    fn mine_search_accepts_default_shape_only() {
+        let command = cli::SearchNoteArgs {
+            query: String::new(),
+            after: None,
+            resources: vec![],
+            coediting: None,
+            updated: None,
+            updated_after: None,
+            updated_before: None,
+            group_ids: vec![],
+            user_ids: vec![],
+            authors: vec![],
+            mine: true,
+            folder_ids: vec![],
+            in_folders: vec![],
+            liker_ids: vec![],
+            is_archived: None,
+            sort_by: None,
+            first: Some(10),
+            preset: None,
+            save_preset: None,
+            all: false,
+            limit: None,
+            open_first: false,
+            pick: false,
+            copy_url: false,
+            copy_id: false,
+            all_teams: false,
+            content_stats: false,
+            refresh_users: false,
+        };
+        assert!(!search_note_mine_has_unsupported_filters(&command));
+    }
+
+    #[test]
+    fn mine_search_rejects_additional_filters() {
+        let command = cli::SearchNoteArgs {
+            query: String::new(),
+            after: None,
+            resources: vec!["note".to_string()],
+            coediting: None,
+            updated: None,
+            updated_after: None,
+            updated_before: None,
+            group_ids: vec![],
+            user_ids: vec![],
+            authors: vec![],
+            mine: true,
+            folder_ids: vec![],
+            in_folders: vec![],
+            liker_ids: vec![],
+            is_archived: None,
+            sort_by: None,
+            first: Some(10),
+            preset: None,
+            save_preset: None,
+            all: false,
+            limit: None,
+            open_first: false,
+            pick: false,
+            copy_url: false,
+            copy_id: false,
+            all_teams: false,
+            content_stats: false,
+            refresh_users: false,
+        };
+        assert!(search_note_mine_has_unsupported_filters(&command));
+    }
+
+    #[test]
+    fn merge_search_note_preset_keeps_cli_overrides() {
+        let preset = SearchNotePreset {
+            query: "preset".to_string(),
+            resources: vec!["NOTE".to_string()],
+            group_ids: vec!["G-preset".to_string()],
+            first: Some(10),
+            after: Some("cursor-preset".to_string()),
+            ..SearchNotePreset::default()
+        };
+        let merged = merge_search_note_preset(
+            &preset,
+            SearchNoteInput {
+                query: "cli".to_string(),
+                resources: vec!["COMMENT".to_string()],
+                coediting: None,
+                updated: None,
+                group_ids: vec![],
+                user_ids: vec![],
+                folder_ids: vec![],
+                liker_ids: vec![],
+                is_archived: None,
+                sort_by: None,
+                first: Some(5),
+                after: Some("cursor-cli".to_string()),
+            },
+        );
+        assert_eq!(merged.query, "cli");
+        assert_eq!(merged.resources, vec!["COMMENT".to_string()]);
+        assert_eq!(merged.first, Some(5));
+        assert_eq!(merged.after.as_deref(), Some("cursor-cli"));
+        assert_eq!(merged.group_ids, vec!["G-preset".to_string()]);
+    }
+
+    #[test]
+    fn search_note_preset_from_input_round_trip() {
+        let input = SearchNoteInput {
+            query: "onboarding".to_string(),
+            resources: vec!["NOTE".to_string()],
+            coediting: Some(true),
+            updated: Some("LAST_7_DAYS".to_string()),
+            group_ids: vec!["G1".to_string()],
+            user_ids: vec!["U1".to_string()],
+            folder_ids: vec!["F1".to_string()],
+            liker_ids: vec!["U2".to_string()],
+            is_archived: Some(false),
+            sort_by: Some("UPDATED_AT_DESC".to_string()),
+            first: Some(16),
+            after: Some("cursor-1".to_string()),
+        };
+        let preset = search_note_preset_from_input(&input);
+        assert_eq!(preset.query, "onboarding");
+        assert_eq!(preset.resources, vec!["NOTE"]);
+        assert_eq!(preset.after.as_deref(), Some("cursor-1"));
+    }
+
+    #[test]
+    fn infer_team_from_kibela_origin() {
+        assert_eq!(
+            infer_team_from_origin("https://example-team.kibe.la/"),
+            Some("example-team".to_string())
+        );
+        assert_eq!(infer_team_from_origin("https://example.com"), None);
+    }
+
+    #[test]
+    fn normalize_origin_requires_scheme_and_removes_trailing_slash() {
+        assert_eq!(
+            normalize_origin_owned("https://EXAMPLE-TEAM.kibe.la/"),
+            Some("https://example-team.kibe.la".to_string())
+        );
+        assert_eq!(normalize_origin_owned("example-team.kibe.la"), None);
+    }
+
+    #[test]
+    fn token_store_lookup_subjects_include_origin_specific_and_legacy_team() {
+        let subjects =
+            token_store_lookup_subjects("example-team", Some("https://example-team.kibe.la"));
+        assert_eq!(
+            subjects,
+            vec![
+                "origin::https://example-team.kibe.la::team::example-team".to_string(),
+                "example-team".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn token_store_lookup_subjects_returns_legacy_subject_without_origin() {
+        let subjects = token_store_lookup_subjects("example-team", None);
+        assert_eq!(subjects, vec!["example-team".to_string()]);
+    }
+
+    #[test]
+    fn token_store_lookup_subjects_filters_out_invalid_origin_without_scheme() {
+        let subjects = token_store_lookup_subjects("example-team", Some("example-team.kibe.la"));
+        assert_eq!(subjects, vec!["example-team".to_string()]);
+    }
+
+    #[test]
+    fn token_settings_url_uses_tenant_origin() {
+        assert_eq!(
+            kibela_access_token_settings_url("https://example-team.kibe.la"),
+            "https://example-team.kibe.la/settings/access_tokens".to_string()
+        );
+        assert_eq!(
+            kibela_access_token_settings_url("https://example-team.kibe.la/"),
+            "https://example-team.kibe.la/settings/access_tokens".to_string()
+        );
+    }
+
+    #[test]
+    fn quoted_comments_section_quotes_each_comment_under_a_heading() {
+        assert_eq!(quoted_comments_section(&[]), "");
+        let section = quoted_comments_section(&[
+            json!({
+                "content": "LGTM\n\nship it",
+                "publishedAt": "2026-02-23T00:00:00Z",
+                "author": { "account": "alice" }
+            }),
+            json!({ "content": "+1" }),
+        ]);
+        assert_eq!(
+            section,
+            "\n\n---\n\n## Comments\n\n> **@alice** (2026-02-23T00:00:00Z)\n> LGTM\n>\n> ship it\n\n> **@unknown**\n> +1\n"
+        );
+    }
+
+    #[test]
+    fn client_timeouts_follow_the_mode_and_the_flags() {
+        let parse = |args: &[&str]| {
+            cli::Cli::try_parse_from(std::iter::once("kibel").chain(args.iter().copied()))
+                .expect("parse should succeed")
+        };
+        assert_eq!(
+            client_timeouts(&parse(&["search", "note", "--query", "x"])),
+            Timeouts::INTERACTIVE
+        );
+        assert_eq!(
+            client_timeouts(&parse(&["note", "move-to-folder", "--batch", "moves.csv"])),
+            Timeouts::BATCH
+        );
+        assert_eq!(
+            client_timeouts(&parse(&[
+                "--connect-timeout",
+                "7",
+                "note",
+                "move-to-folder",
+                "--batch",
+                "moves.csv",
+                "--total-timeout",
+                "120",
+            ])),
+            Timeouts {
+                connect: Duration::from_secs(7),
+                total: Duration::from_secs(120),
+                ..Timeouts::BATCH
+            }
+        );
+        assert!(cli::Cli::try_parse_from(["kibel", "--total-timeout", "0", "version"]).is_err());
+    }
+
+    #[test]
+    fn reauth_is_offered_only_for_auth_failures_of_token_commands() {
+        let parse = |args: &[&str]| {
+            cli::Cli::try_parse_from(std::iter::once("kibel").chain(args.iter().copied()))
+                .expect("parse should succeed")
+        };
+        let offered = |args: &[&str], code| {
+            let cli = parse(args);
+            reauth_applies(&cli.command, code, cli.no_reauth, cli.with_token)
+        };
+        assert!(offered(
+            &["note", "get", "--id", "N1"],
+            ErrorCode::AuthFailed
+        ));
+        assert!(!offered(
+            &["note", "get", "--id", "N1"],
+            ErrorCode::NotFound
+        ));
+        assert!(!offered(
+            &["--no-reauth", "note", "get", "--id", "N1"],
+            ErrorCode::AuthFailed
+        ));
+        assert!(!offered(
+            &["--with-token", "note", "get", "--id", "N1"],
+            ErrorCode::AuthFailed
+        ));
+        assert!(!offered(&["auth", "status"], ErrorCode::AuthFailed));
+        assert!(!offered(&["config", "profiles"], ErrorCode::AuthFailed));
+    }
+}
@@ -0,0 +1,70 @@
+use std::process::{Command, Stdio};
+
+/// The platform's URL opener, used when neither `BROWSER` nor the config
+/// `browser` key is set.
+fn platform_default() -> &'static [&'static str] {
+    if cfg!(target_os = "macos") {
+        &["open"]
+    } else if cfg!(target_os = "windows") {
+        &["cmd", "/C", "start", ""]
+    } else {
+        &["xdg-open"]
+    }
+}
+
+/// The argv that opens `url`: `command` split on whitespace, with `%s`
+/// replaced by the URL, or the URL appended when there is no `%s`.
+pub fn browser_argv(command: Option<&str>, url: &str) -> Vec<String> {
+    let words = match command.map(str::trim).filter(|command| !command.is_empty()) {
+        Some(command) => command.split_whitespace().map(str::to_string).collect(),
+        None => platform_default()
+            .iter()
+            .map(|word| (*word).to_string())
+            .collect::<Vec<_>>(),
+    };
+    if words.iter().any(|word| word.contains("%s")) {
+        return words.iter().map(|word| word.replace("%s", url)).collect();
+    }
+    let mut argv = words;
+    argv.push(url.to_string());
+    argv
+}
+
+/// Starts the browser without waiting for it, keeping its output off the
+/// CLI's stdout so the JSON envelope stays clean.
+pub fn open_url(command: Option<&str>, url: &str) -> Result<Vec<String>, String> {
+    let argv = browser_argv(command, url);
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| "browser command is empty".to_string())?;
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|error| format!("failed to start browser `{program}`: {error}"))?;
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::browser_argv;
+
+    #[test]
+    fn browser_argv_substitutes_or_appends_the_url() {
+        let url = "https://acme.kibe.la/notes/1";
+        assert_eq!(
+            browser_argv(Some("firefox --new-tab"), url),
+            vec!["firefox", "--new-tab", url]
+        );
+        assert_eq!(
+            browser_argv(Some("w3m %s -dump"), url),
+            vec!["w3m", url, "-dump"]
+        );
+        assert_eq!(
+            browser_argv(Some("  "), url).last().map(String::as_str),
+            Some(url)
+        );
+    }
+}
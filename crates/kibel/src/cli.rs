@@ -1,10 +1,29 @@
-use clap::{ArgAction, Args, Parser, Subcommand};
+use crate::concurrency::DEFAULT_CONCURRENCY;
+use crate::output::ColorChoice;
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand};
 use clap_complete::Shell;
+use kibel_client::SecretScanMode;
 use std::path::PathBuf;
 
+/// `graphql run` guardrail defaults and caps, also reported by `meta limits`.
+pub const GRAPHQL_RUN_TIMEOUT_SECS: u64 = 15;
+pub const GRAPHQL_RUN_MAX_TIMEOUT_SECS: u64 = 60;
+pub const GRAPHQL_RUN_RESPONSE_LIMIT_MIB: u64 = 2;
+pub const GRAPHQL_RUN_MAX_RESPONSE_LIMIT_MIB: u64 = 8;
+pub const GRAPHQL_RUN_MAX_DEPTH: u32 = 8;
+pub const GRAPHQL_RUN_MAX_COMPLEXITY: u32 = 1000;
+
 #[derive(Debug, Clone, Parser)]
-#[command(name = "kibel", about = "Kibela CLI", version)]
+#[command(
+    name = "kibel",
+    about = "Kibela CLI",
+    version,
+    disable_help_subcommand = true
+)]
 pub struct Cli {
+    /// Sent as `X-Request-Id` on every request and echoed in `meta.request_id`.
+    #[arg(skip = kibel_client::generate_request_id())]
+    pub request_id: String,
     #[arg(long, global = true, action = ArgAction::SetTrue, help = "Output machine-readable JSON (default)")]
     pub json: bool,
     #[arg(
@@ -15,6 +34,29 @@ pub struct Cli {
         help = "Output human-readable text"
     )]
     pub text: bool,
+    #[arg(
+        long,
+        short = 'q',
+        global = true,
+        action = ArgAction::SetTrue,
+        help = "Print nothing on stdout; failures are still reported on stderr"
+    )]
+    pub quiet: bool,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = ColorChoice::Auto,
+        help = "Color text output (auto honors NO_COLOR)"
+    )]
+    pub color: ColorChoice,
+    #[arg(
+        long = "no-color",
+        global = true,
+        action = ArgAction::SetTrue,
+        help = "Same as --color never"
+    )]
+    pub no_color: bool,
     #[arg(long, global = true, action = ArgAction::SetTrue, help = "Read access token from stdin")]
     pub with_token: bool,
     #[arg(
@@ -42,6 +84,95 @@ pub struct Cli {
     pub team: Option<String>,
     #[arg(long, global = true, value_name = "PATH", help = "Config file path")]
     pub config_path: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        action = ArgAction::SetTrue,
+        help = "Add meta.token_estimate (approximate LLM tokens) to the JSON envelope"
+    )]
+    pub estimate_tokens: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        conflicts_with = "replay",
+        help = "Record sanitized GraphQL request/response pairs into DIR (env: KIBEL_VCR=record:DIR)"
+    )]
+    pub record: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "Answer GraphQL requests from pairs recorded in DIR instead of the network (env: KIBEL_VCR=replay:DIR)"
+    )]
+    pub replay: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        env = "KIBEL_READ_ONLY",
+        action = ArgAction::SetTrue,
+        help = "Reject every mutation before it is sent (READ_ONLY_VIOLATION)"
+    )]
+    pub read_only: bool,
+    #[arg(
+        long,
+        global = true,
+        env = "KIBEL_NO_REAUTH",
+        action = ArgAction::SetTrue,
+        help = "On AUTH_FAILED, fail instead of offering to log in again and retry (interactive terminals only)"
+    )]
+    pub no_reauth: bool,
+    #[arg(
+        long,
+        global = true,
+        env = "KIBEL_NO_PROJECT_CONFIG",
+        action = ArgAction::SetTrue,
+        help = "Ignore `.kibel.toml` files in the current directory and its parents"
+    )]
+    pub no_project_config: bool,
+    #[arg(
+        long,
+        global = true,
+        env = "KIBEL_METRICS",
+        action = ArgAction::SetTrue,
+        help = "Accumulate per-operation request counts and latency in the cache directory (see `kibel metrics`)"
+    )]
+    pub metrics: bool,
+    #[arg(
+        long,
+        global = true,
+        env = "KIBEL_POLICY",
+        value_name = "FILE",
+        help = "Operator policy (TOML, or JSON for .json) limiting operations and parameters (POLICY_VIOLATION)"
+    )]
+    pub policy: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        env = "KIBEL_CONNECT_TIMEOUT",
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds to wait for a connection (default 3, 10 in --batch mode)"
+    )]
+    pub connect_timeout: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        env = "KIBEL_TOTAL_TIMEOUT",
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds each request may take in total (default 5, 60 in --batch mode)"
+    )]
+    pub total_timeout: Option<u64>,
+    #[arg(
+        long = "secret-scan",
+        global = true,
+        env = "KIBEL_SECRET_SCAN",
+        value_name = "MODE",
+        value_parser = parse_secret_scan_mode,
+        help = "Credential check on outgoing note/comment content: block (default), warn, or off (SECRET_DETECTED)"
+    )]
+    pub secret_scan: Option<SecretScanMode>,
     #[command(subcommand)]
     pub command: Command,
 }
@@ -56,9 +187,46 @@ pub enum Command {
     Feed(FeedArgs),
     Comment(CommentArgs),
     Note(NoteArgs),
+    Link(LinkArgs),
+    Open(OpenArgs),
+    Report(ReportArgs),
+    Stats(StatsArgs),
+    #[command(about = "Summarize recent feed activity as Markdown, optionally posted as a note")]
+    Digest(DigestArgs),
+    #[command(about = "Browse groups, folders, and notes in a terminal UI")]
+    Tui(TuiArgs),
+    #[command(
+        about = "Create or update notes from local Markdown files listed in a publish manifest"
+    )]
+    Publish(PublishArgs),
+    #[command(about = "Create notes from a Confluence or Notion export")]
+    Import(ImportArgs),
+    Ci(CiArgs),
+    Lint(LintArgs),
+    Template(TemplateArgs),
+    Cache(CacheArgs),
+    Store(StoreArgs),
+    #[command(about = "Show per-operation request metrics, optionally as a Prometheus textfile")]
+    Metrics(MetricsArgs),
+    #[command(about = "Summarize local per-command and per-operation usage over recent days")]
+    Usage(UsageArgs),
+    #[command(
+        about = "Describe what this binary supports (contracts, operations, limits) for tooling"
+    )]
+    Meta(MetaArgs),
     Graphql(GraphqlArgs),
     Completion(CompletionArgs),
     Version(VersionArgs),
+    #[command(name = "self", about = "Manage the kibel binary itself")]
+    SelfManage(SelfArgs),
+    #[command(about = "Show help for a command (`--json` for the structured flag tree)")]
+    Help(HelpArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct HelpArgs {
+    #[arg(value_name = "COMMAND", help = "Command path, e.g. `note create`")]
+    pub command: Vec<String>,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -78,6 +246,12 @@ pub enum AuthCommand {
 pub struct AuthLoginArgs {
     #[arg(long, visible_alias = "tenant", help = "Team name (tenant)")]
     pub team: Option<String>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Never prompt; take team, origin, and token from flags and env only"
+    )]
+    pub non_interactive: bool,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -100,14 +274,47 @@ pub struct ConfigArgs {
 
 #[derive(Debug, Clone, Subcommand)]
 pub enum ConfigCommand {
+    Get(ConfigGetArgs),
     Set(ConfigSetArgs),
     Profiles(ConfigProfilesArgs),
+    Export(ConfigExportArgs),
+    Import(ConfigImportArgs),
+    Convert(ConfigConvertArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ConfigGetArgs {
+    #[arg(
+        value_name = "KEY",
+        help = "Key such as `copy_url` or `profiles.<team>.origin`"
+    )]
+    pub key: String,
 }
 
 #[derive(Debug, Clone, Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct ConfigSetArgs {
     #[command(subcommand)]
-    pub command: ConfigSetCommand,
+    pub command: Option<ConfigSetCommand>,
+    #[arg(
+        value_name = "KEY",
+        required = true,
+        help = "Key such as `copy_url` or `profiles.<team>.origin`"
+    )]
+    pub key: Option<String>,
+    #[arg(
+        value_name = "VALUE",
+        required_unless_present = "unset",
+        help = "New value; lists such as `on_fetch` are comma-separated"
+    )]
+    pub value: Option<String>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        conflicts_with = "value",
+        help = "Remove the key from the config"
+    )]
+    pub unset: bool,
 }
 
 #[derive(Debug, Clone, Subcommand)]
@@ -123,6 +330,55 @@ pub struct ConfigSetTeamArgs {
 #[derive(Debug, Clone, Args)]
 pub struct ConfigProfilesArgs {}
 
+#[derive(Debug, Clone, Args)]
+pub struct ConfigExportArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write to FILE instead of stdout as TOML, or as JSON for a .json FILE"
+    )]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ConfigImportArgs {
+    #[arg(
+        value_name = "FILE",
+        help = "TOML (or .json) file written by `config export`"
+    )]
+    pub file: PathBuf,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Replace the config instead of merging into it (profile tokens are kept)"
+    )]
+    pub replace: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ConfigConvertArgs {
+    #[arg(long, value_enum, help = "Format to write")]
+    pub to: ConfigFileFormat,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write to FILE instead of the config path with the format's extension"
+    )]
+    pub output: Option<PathBuf>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Overwrite FILE when it exists"
+    )]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFileFormat {
+    Toml,
+    Json,
+}
+
 #[derive(Debug, Clone, Args)]
 pub struct SearchArgs {
     #[command(subcommand)]
@@ -131,7 +387,7 @@ pub struct SearchArgs {
 
 #[derive(Debug, Clone, Subcommand)]
 pub enum SearchCommand {
-    Note(SearchNoteArgs),
+    Note(Box<SearchNoteArgs>),
     Folder(SearchFolderArgs),
     User(SearchUserArgs),
 }
@@ -148,14 +404,42 @@ pub struct SearchNoteArgs {
     pub coediting: Option<bool>,
     #[arg(long)]
     pub updated: Option<String>,
+    #[arg(
+        long = "updated-after",
+        value_name = "DATE",
+        value_parser = parse_feed_date,
+        conflicts_with = "updated",
+        help = "Only notes updated on or after DATE (YYYY-MM-DD)"
+    )]
+    pub updated_after: Option<String>,
+    #[arg(
+        long = "updated-before",
+        value_name = "DATE",
+        value_parser = parse_feed_date,
+        conflicts_with = "updated",
+        help = "Only notes updated on or before DATE (YYYY-MM-DD)"
+    )]
+    pub updated_before: Option<String>,
     #[arg(long = "group-id")]
     pub group_ids: Vec<String>,
     #[arg(long = "user-id")]
     pub user_ids: Vec<String>,
+    #[arg(
+        long = "author",
+        value_name = "ACCOUNT",
+        help = "Only notes by ACCOUNT (repeatable; resolved to --user-id)"
+    )]
+    pub authors: Vec<String>,
     #[arg(long, action = ArgAction::SetTrue)]
     pub mine: bool,
     #[arg(long = "folder-id")]
     pub folder_ids: Vec<String>,
+    #[arg(
+        long = "in-folder",
+        value_name = "PATH",
+        help = "Only notes in the folder at PATH (repeatable; resolved to --folder-id)"
+    )]
+    pub in_folders: Vec<String>,
     #[arg(long = "liker-id")]
     pub liker_ids: Vec<String>,
     #[arg(long = "is-archived")]
@@ -168,6 +452,67 @@ pub struct SearchNoteArgs {
     pub preset: Option<String>,
     #[arg(long = "save-preset")]
     pub save_preset: Option<String>,
+    #[arg(
+        long,
+        help = "Follow pages from --after until the results run out (capped by --limit)"
+    )]
+    pub all: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "all",
+        help = "Stop --all after N results (default and max 1000)"
+    )]
+    pub limit: Option<usize>,
+    #[arg(
+        long = "open-first",
+        action = ArgAction::SetTrue,
+        help = "Open the first result in the browser"
+    )]
+    pub open_first: bool,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Choose a result interactively and print its URL"
+    )]
+    pub pick: bool,
+    #[arg(
+        long = "copy-url",
+        action = ArgAction::SetTrue,
+        requires = "pick",
+        conflicts_with = "copy_id",
+        help = "Copy the picked note's URL to the clipboard"
+    )]
+    pub copy_url: bool,
+    #[arg(
+        long = "copy-id",
+        action = ArgAction::SetTrue,
+        requires = "pick",
+        help = "Copy the picked note's id to the clipboard"
+    )]
+    pub copy_id: bool,
+    #[arg(
+        long = "all-teams",
+        action = ArgAction::SetTrue,
+        conflicts_with_all = [
+            "mine", "all", "after", "pick", "open_first", "save_preset",
+            "group_ids", "user_ids", "folder_ids", "liker_ids",
+        ],
+        help = "Search every configured profile concurrently and merge the results"
+    )]
+    pub all_teams: bool,
+    #[arg(
+        long = "content-stats",
+        action = ArgAction::SetTrue,
+        help = "Add word count and reading time, estimated from each snippet"
+    )]
+    pub content_stats: bool,
+    #[arg(
+        long = "refresh-users",
+        action = ArgAction::SetTrue,
+        help = "Look --author accounts up again instead of using cached user ids"
+    )]
+    pub refresh_users: bool,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -205,6 +550,31 @@ pub enum GroupCommand {
 pub struct GroupListArgs {
     #[arg(long)]
     pub first: Option<u32>,
+    #[command(flatten)]
+    pub filter: ListFilterArgs,
+}
+
+// Filters `group list` and `folder list` apply to the fetched page.
+#[derive(Debug, Clone, Args)]
+pub struct ListFilterArgs {
+    #[arg(
+        long = "include-archived",
+        action = ArgAction::SetTrue,
+        help = "Also list archived entries (hidden by default)"
+    )]
+    pub include_archived: bool,
+    #[arg(
+        long = "private-only",
+        action = ArgAction::SetTrue,
+        help = "Only list private entries"
+    )]
+    pub private_only: bool,
+    #[arg(
+        long = "name-contains",
+        value_name = "TEXT",
+        help = "Only list entries whose name contains TEXT (case-insensitive)"
+    )]
+    pub name_contains: Option<String>,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -219,6 +589,7 @@ pub enum FolderCommand {
     Get(FolderGetArgs),
     GetFromPath(FolderGetFromPathArgs),
     Notes(FolderNotesArgs),
+    Tree(FolderTreeArgs),
     Create(FolderCreateArgs),
 }
 
@@ -226,6 +597,39 @@ pub enum FolderCommand {
 pub struct FolderListArgs {
     #[arg(long)]
     pub first: Option<u32>,
+    #[command(flatten)]
+    pub filter: ListFilterArgs,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Choose a folder interactively and print its id"
+    )]
+    pub pick: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct FolderTreeArgs {
+    #[arg(
+        long = "group-id",
+        required_unless_present = "path",
+        conflicts_with = "path",
+        help = "Render every folder of the group"
+    )]
+    pub group_id: Option<String>,
+    #[arg(long, help = "Render the folder at PATH and its sub-folders")]
+    pub path: Option<String>,
+    #[arg(
+        long = "max-depth",
+        default_value_t = crate::folder_tree::DEFAULT_MAX_DEPTH,
+        help = "Levels of sub-folders to follow below each root"
+    )]
+    pub max_depth: usize,
+    #[arg(
+        long,
+        default_value_t = 100,
+        help = "Page size for the folder list, sub-folders, and notes per folder"
+    )]
+    pub first: u32,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -271,6 +675,8 @@ pub struct FeedArgs {
 #[derive(Debug, Clone, Subcommand)]
 pub enum FeedCommand {
     Sections(FeedSectionsArgs),
+    Watch(FeedWatchArgs),
+    Relay(FeedRelayArgs),
 }
 
 #[derive(Debug, Clone, Args)]
@@ -281,6 +687,74 @@ pub struct FeedSectionsArgs {
     pub group_id: String,
     #[arg(long)]
     pub first: Option<u32>,
+    #[arg(long, help = "Continue from a previous page's endCursor")]
+    pub after: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_feed_date,
+        help = "Keep paging until sections are older than this date (YYYY-MM-DD)"
+    )]
+    pub since: Option<String>,
+    #[arg(
+        long = "max-pages",
+        default_value_t = 10,
+        requires = "since",
+        help = "Maximum pages fetched with --since"
+    )]
+    pub max_pages: u32,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct FeedWatchArgs {
+    #[arg(long, default_value = "ALL")]
+    pub kind: String,
+    #[arg(long = "group-id")]
+    pub group_id: String,
+    #[arg(long)]
+    pub first: Option<u32>,
+    #[arg(long, default_value_t = 60, help = "Seconds between polls (min 5)")]
+    pub interval: u64,
+    #[arg(
+        long = "state-file",
+        help = "Persist seen notes/comments here so restarts do not re-emit events"
+    )]
+    pub state_file: Option<PathBuf>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Also watch new comments in the group"
+    )]
+    pub comments: bool,
+    #[arg(
+        long = "emit-existing",
+        action = ArgAction::SetTrue,
+        help = "Emit events for entries seen on the first poll without state"
+    )]
+    pub emit_existing: bool,
+    #[arg(long = "max-polls", help = "Stop after this many polls")]
+    pub max_polls: Option<u32>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct FeedRelayArgs {
+    #[command(flatten)]
+    pub watch: FeedWatchArgs,
+    #[arg(long, help = "Webhook URL (https://, or http:// for localhost)")]
+    pub url: String,
+    #[arg(
+        long = "secret-env",
+        default_value = "KIBEL_RELAY_SECRET",
+        help = "Env var holding the HMAC signing secret"
+    )]
+    pub secret_env: String,
+    #[arg(long = "max-retries", default_value_t = 3)]
+    pub max_retries: u32,
+    #[arg(
+        long = "retry-base-ms",
+        default_value_t = 500,
+        help = "Initial retry backoff, doubled per attempt"
+    )]
+    pub retry_base_ms: u64,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -293,6 +767,10 @@ pub struct CommentArgs {
 pub enum CommentCommand {
     Create(CommentCreateArgs),
     Reply(CommentReplyArgs),
+    Edit(CommentEditArgs),
+    Delete(CommentDeleteArgs),
+    #[command(about = "Show a comment with its replies as a thread")]
+    Thread(CommentThreadArgs),
 }
 
 #[derive(Debug, Clone, Args)]
@@ -301,6 +779,40 @@ pub struct CommentCreateArgs {
     pub content: String,
     #[arg(long = "note-id")]
     pub note_id: String,
+    #[arg(
+        long = "suppress-duplicate",
+        help = "Skip posting when you already left the same comment within --window"
+    )]
+    pub suppress_duplicate: bool,
+    #[arg(
+        long,
+        value_parser = parse_window,
+        default_value = "7d",
+        requires = "suppress_duplicate",
+        help = "Look-back window for --suppress-duplicate (e.g. 30m, 24h, 7d)"
+    )]
+    pub window: u64,
+    #[arg(
+        long,
+        requires = "anchor",
+        conflicts_with = "suppress_duplicate",
+        help = "Attach the comment to the --anchor passage of the note"
+    )]
+    pub inline: bool,
+    #[arg(
+        long,
+        requires = "inline",
+        help = "Passage of the note content to comment on"
+    )]
+    pub anchor: Option<String>,
+    #[arg(
+        long = "anchor-occurrence",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "anchor",
+        help = "Which match of --anchor to use when it occurs more than once"
+    )]
+    pub anchor_occurrence: u32,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -312,96 +824,1003 @@ pub struct CommentReplyArgs {
 }
 
 #[derive(Debug, Clone, Args)]
-pub struct NoteArgs {
+pub struct CommentEditArgs {
+    #[arg(long, help = "Comment or reply id")]
+    pub id: String,
+    #[arg(long)]
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CommentThreadArgs {
+    #[arg(value_name = "COMMENT", help = "Comment id")]
+    pub comment_id: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CommentDeleteArgs {
+    #[arg(long, help = "Comment or reply id")]
+    pub id: String,
+    #[arg(long, help = "Delete without asking for confirmation")]
+    pub yes: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteArgs {
+    #[command(subcommand)]
+    pub command: NoteCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum NoteCommand {
+    Create(NoteCreateArgs),
+    Get(NoteGetArgs),
+    GetMany(NoteGetManyArgs),
+    GetFromPath(NoteGetFromPathArgs),
+    Update(NoteUpdateArgs),
+    #[command(about = "Add lines to the end of a note, retrying once if it changes meanwhile")]
+    Append(NoteAddArgs),
+    #[command(about = "Add lines to the start of a note, retrying once if it changes meanwhile")]
+    Prepend(NoteAddArgs),
+    #[command(about = "Diff a note's current content against a local file or exported snapshot")]
+    Diff(NoteDiffArgs),
+    #[command(about = "Put a note back to an exported Markdown or JSON snapshot")]
+    Restore(NoteRestoreArgs),
+    #[command(about = "Export a note and the attachments it links to into a local folder")]
+    Download(NoteDownloadArgs),
+    #[command(about = "Render a note to standalone HTML (and PDF) for readers outside Kibela")]
+    Render(NoteRenderArgs),
+    #[command(about = "Print a note's heading tree with anchors for linking to sections")]
+    Outline(NoteOutlineArgs),
+    Drafts(NoteDraftsArgs),
+    Publish(NotePublishArgs),
+    MoveToFolder(NoteMoveToFolderArgs),
+    AttachToFolder(NoteAttachToFolderArgs),
+    Copy(NoteCopyArgs),
+    Transfer(NoteTransferArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteCreateArgs {
+    #[arg(long, required_unless_present = "template")]
+    pub title: Option<String>,
+    #[arg(long, required_unless_present = "template")]
+    pub content: Option<String>,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Local template from the templates dir; --title/--content override it"
+    )]
+    pub template: Option<String>,
+    #[arg(
+        long = "var",
+        value_name = "KEY=VALUE",
+        value_parser = parse_key_value,
+        requires = "template",
+        help = "Template variable (repeatable); overrides {{date}}/{{author}}"
+    )]
+    pub vars: Vec<(String, String)>,
+    #[arg(long = "group-id")]
+    pub group_ids: Vec<String>,
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub draft: bool,
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub coediting: bool,
+    #[arg(
+        long = "no-coediting",
+        action = ArgAction::SetTrue,
+        conflicts_with = "coediting",
+        help = "Keep coediting off even when the profile sets default_coediting"
+    )]
+    pub no_coediting: bool,
+    #[arg(long = "folder", value_parser = parse_folder_arg)]
+    pub folders: Vec<NoteFolderArg>,
+    #[arg(
+        long = "folder-path",
+        value_name = "GROUP/FOLDER/...",
+        help = "Folder to put the note in, by path; its group is added to --group-id"
+    )]
+    pub folder_path: Option<String>,
+    #[arg(
+        long = "create-missing",
+        action = ArgAction::SetTrue,
+        requires = "folder_path",
+        help = "Create the folders of --folder-path that do not exist yet"
+    )]
+    pub create_missing: bool,
+    #[arg(long = "author-id")]
+    pub author_id: Option<String>,
+    #[arg(long = "published-at")]
+    pub published_at: Option<String>,
+    #[arg(long = "client-mutation-id")]
+    pub client_mutation_id: Option<String>,
+    #[arg(
+        long,
+        value_name = "STEPS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "all",
+        value_delimiter = ',',
+        help = "Normalize the content before upload: all (default) or headings,tabs,trailing-whitespace,local-images"
+    )]
+    pub normalize: Vec<String>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Validate the content against the [pre_publish] config rules before publishing"
+    )]
+    pub check: bool,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        requires = "check",
+        help = "Publish even when --check finds violations"
+    )]
+    pub force: bool,
+    #[arg(
+        long = "refresh-users",
+        action = ArgAction::SetTrue,
+        requires = "template",
+        help = "Look template @mentions up again instead of using cached user ids"
+    )]
+    pub refresh_users: bool,
+    #[command(flatten)]
+    pub copy: CopyArgs,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteFolderArg {
+    pub group_id: String,
+    pub folder_name: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteGetArgs {
+    #[arg(long)]
+    pub id: String,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = NoteFormat::Json,
+        help = "`markdown` renders the note as a file with YAML front matter"
+    )]
+    pub format: NoteFormat,
+    #[command(flatten)]
+    pub on_fetch: OnFetchArgs,
+    #[arg(long, action = ArgAction::SetTrue, help = "Also open the note in the browser")]
+    pub open: bool,
+    #[command(flatten)]
+    pub copy: CopyArgs,
+}
+
+/// `note get --format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NoteFormat {
+    /// The note object in the JSON envelope.
+    #[default]
+    Json,
+    /// Front matter (id, title, url, groups, folders, updatedAt) plus the
+    /// content, ready for `note update --from-file`.
+    Markdown,
+}
+
+// Clipboard flags shared by commands that produce a note.
+#[derive(Debug, Clone, Default, Args)]
+pub struct CopyArgs {
+    #[arg(
+        long = "copy-url",
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["copy_id", "no_copy"],
+        help = "Copy the note URL to the clipboard (config: copy_url)"
+    )]
+    pub copy_url: bool,
+    #[arg(
+        long = "copy-id",
+        action = ArgAction::SetTrue,
+        conflicts_with = "no_copy",
+        help = "Copy the note id to the clipboard"
+    )]
+    pub copy_id: bool,
+    #[arg(
+        long = "no-copy",
+        action = ArgAction::SetTrue,
+        help = "Ignore the copy_url config default"
+    )]
+    pub no_copy: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteGetManyArgs {
+    #[arg(long = "id", required = true)]
+    pub ids: Vec<String>,
+    #[command(flatten)]
+    pub on_fetch: OnFetchArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteGetFromPathArgs {
+    #[arg(long)]
+    pub path: String,
+    #[arg(long)]
+    pub first: Option<u32>,
+    #[command(flatten)]
+    pub on_fetch: OnFetchArgs,
+}
+
+// Per-command override of the configured `on_fetch` content pipeline.
+#[derive(Debug, Clone, Default, Args)]
+pub struct OnFetchArgs {
+    #[arg(
+        long = "on-fetch",
+        value_name = "TRANSFORM",
+        value_delimiter = ',',
+        conflicts_with = "raw",
+        help = "Content transforms to apply instead of the configured on_fetch pipeline"
+    )]
+    pub transforms: Vec<String>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Return content without applying the on_fetch pipeline"
+    )]
+    pub raw: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+#[command(group(ArgGroup::new("content_source").args(["new_content", "from_file"])))]
+pub struct NoteUpdateArgs {
+    #[arg(long, required_unless_present = "from_file")]
+    pub id: Option<String>,
+    #[arg(long = "base-content", requires = "new_content")]
+    pub base_content: Option<String>,
+    #[arg(long = "new-content", requires = "base_content")]
+    pub new_content: Option<String>,
+    #[arg(
+        long = "from-file",
+        value_name = "PATH",
+        help = "Push a Markdown file; front matter from `note get --format markdown` supplies the id, title, and conflict check"
+    )]
+    pub from_file: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "STEPS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "all",
+        value_delimiter = ',',
+        requires = "content_source",
+        help = "Normalize the new content before upload (see `note create --normalize`)"
+    )]
+    pub normalize: Vec<String>,
+    #[arg(long, help = "Rename the note")]
+    pub title: Option<String>,
+    #[arg(long, value_name = "BOOL", help = "Turn coediting on or off")]
+    pub coediting: Option<bool>,
+    #[arg(
+        long = "group-id",
+        help = "Replace the groups the note is shared with (repeatable)"
+    )]
+    pub group_ids: Vec<String>,
+    #[arg(
+        long,
+        value_name = "HEADING",
+        requires = "section_content",
+        conflicts_with_all = ["base_content", "from_file"],
+        help = "Edit only this section: `## Deployment`, `Deployment`, or its anchor"
+    )]
+    pub section: Option<String>,
+    #[arg(
+        long = "section-content",
+        value_name = "TEXT",
+        requires = "section",
+        allow_hyphen_values = true,
+        help = "Text that replaces (or with --section-mode append, extends) the section body"
+    )]
+    pub section_content: Option<String>,
+    #[arg(
+        long = "section-mode",
+        value_enum,
+        default_value_t,
+        requires = "section"
+    )]
+    pub section_mode: SectionMode,
+}
+
+/// `note append` / `note prepend`.
+#[derive(Debug, Clone, Args)]
+pub struct NoteAddArgs {
+    #[arg(long, help = "Note id, path, or URL")]
+    pub id: String,
+    #[arg(long, value_name = "TEXT", allow_hyphen_values = true)]
+    pub content: String,
+    #[arg(
+        long = "skip-if-present",
+        help = "Leave the note unchanged when every line of --content is already in it"
+    )]
+    pub skip_if_present: bool,
+}
+
+/// `note update --section-mode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SectionMode {
+    /// Replace the section body, subsections included; the heading stays.
+    #[default]
+    Replace,
+    /// Add after the last line of the section body.
+    Append,
+}
+
+impl SectionMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Replace => "replace",
+            Self::Append => "append",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteDiffArgs {
+    #[arg(value_name = "NOTE", help = "Note id, path, or URL")]
+    pub id: String,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Markdown file, `note get --format markdown` export, or saved `note get --json` output"
+    )]
+    pub against: PathBuf,
+    #[arg(long, default_value_t = 3, help = "Context lines in the diff")]
+    pub context: usize,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteDownloadArgs {
+    #[arg(value_name = "NOTE", help = "Note id, path, or URL")]
+    pub id: String,
+    #[arg(
+        long = "with-assets",
+        value_name = "DIR",
+        help = "Folder to write the note and its attachments into (created if missing)"
+    )]
+    pub with_assets: PathBuf,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ExportLayout::Kibel,
+        help = "Layout of the written files, front matter, and links"
+    )]
+    pub layout: ExportLayout,
+    #[arg(
+        long = "max-attachment-mib",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u32).range(1..=100),
+        help = "Largest attachment to download"
+    )]
+    pub max_attachment_mib: u32,
+}
+
+/// `note download --layout`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportLayout {
+    /// `note.md` (the `note get --format markdown` export) and `assets/`.
+    #[default]
+    Kibel,
+    /// A vault folder per group and folder, the note named by its title,
+    /// wikilinks, and an `attachments/` folder next to the note.
+    Obsidian,
+    /// A page bundle under `content/` with `title`/`slug`/`date` YAML front
+    /// matter and a section per group and folder.
+    Hugo,
+    /// As `hugo`, with TOML front matter and Kibela fields under `[extra]`.
+    Zola,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteOutlineArgs {
+    #[arg(long)]
+    pub id: String,
+    #[arg(
+        long = "max-level",
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(1..=6),
+        help = "Leave out headings deeper than N (1 for `#` only)"
+    )]
+    pub max_level: Option<u8>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Add each heading's deep link (`<note url>#<anchor>`)"
+    )]
+    pub links: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteRenderArgs {
+    #[arg(value_name = "NOTE", help = "Note id, path, or URL")]
+    pub id: String,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Write note.html and its attachments into DIR instead of printing the HTML"
+    )]
+    pub output: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "output",
+        help = "Also print note.pdf with a headless browser (needs the `pdf` build feature)"
+    )]
+    pub pdf: bool,
+    #[arg(
+        long = "max-attachment-mib",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u32).range(1..=100),
+        help = "Largest attachment to download"
+    )]
+    pub max_attachment_mib: u32,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteRestoreArgs {
+    #[arg(value_name = "NOTE", help = "Note id, path, or URL")]
+    pub id: String,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "`note get --format markdown` export or saved `note get --json` output"
+    )]
+    pub from: PathBuf,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Restore even if the note changed since the snapshot or the snapshot is of another note"
+    )]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteDraftsArgs {
+    #[arg(long)]
+    pub first: Option<u32>,
+    #[arg(long)]
+    pub after: Option<String>,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Follow pages and merge up to N drafts (max 1000)"
+    )]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NotePublishArgs {
+    #[arg(long)]
+    pub id: String,
+    #[arg(long = "published-at", help = "RFC 3339 timestamp; defaults to now")]
+    pub published_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteMoveToFolderArgs {
+    #[arg(long, required_unless_present = "batch", conflicts_with = "batch")]
+    pub id: Option<String>,
+    #[arg(
+        long = "from-folder",
+        value_parser = parse_folder_arg,
+        required_unless_present = "batch",
+        conflicts_with = "batch"
+    )]
+    pub from_folder: Option<NoteFolderArg>,
+    #[arg(
+        long = "to-folder",
+        value_parser = parse_folder_arg,
+        required_unless_present = "batch",
+        conflicts_with = "batch"
+    )]
+    pub to_folder: Option<NoteFolderArg>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "CSV or JSON manifest with note_id, from_folder, to_folder per row"
+    )]
+    pub batch: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_CONCURRENCY,
+        requires = "batch",
+        help = "Parallel moves in --batch mode"
+    )]
+    pub concurrency: usize,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteAttachToFolderArgs {
+    #[arg(long)]
+    pub id: String,
+    #[arg(long = "folder", value_parser = parse_folder_arg)]
+    pub folder: NoteFolderArg,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteCopyArgs {
+    #[arg(value_name = "NOTE", help = "Note id, path, or URL to copy")]
+    pub id: String,
+    #[arg(
+        long = "to-group",
+        value_name = "GROUP_ID",
+        required_unless_present = "to_folders",
+        help = "Group to create the copy in (repeatable)"
+    )]
+    pub to_groups: Vec<String>,
+    #[arg(
+        long = "to-folder",
+        value_name = "GROUP_ID:FOLDER_NAME",
+        value_parser = parse_folder_arg,
+        help = "Folder to create the copy in (repeatable); its group is added to --to-group"
+    )]
+    pub to_folders: Vec<NoteFolderArg>,
+    #[arg(long = "title-suffix", default_value = " (copy)")]
+    pub title_suffix: String,
+    #[arg(
+        long = "with-comments",
+        action = ArgAction::SetTrue,
+        help = "Append the source note's comments to the copy as quotes"
+    )]
+    pub with_comments: bool,
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub draft: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct NoteTransferArgs {
+    #[arg(value_name = "NOTE", help = "Note id, path, or URL on --from-team")]
+    pub id: String,
+    #[arg(
+        long = "from-team",
+        value_name = "TEAM",
+        help = "Profile to read the note from"
+    )]
+    pub from_team: String,
+    #[arg(
+        long = "to-team",
+        value_name = "TEAM",
+        help = "Profile to create the note in"
+    )]
+    pub to_team: String,
+    #[arg(
+        long = "to-group",
+        value_name = "GROUP_ID",
+        required_unless_present = "to_folders",
+        help = "Group on --to-team to create the note in (repeatable)"
+    )]
+    pub to_groups: Vec<String>,
+    #[arg(
+        long = "to-folder",
+        value_name = "GROUP_ID:FOLDER_NAME",
+        value_parser = parse_folder_arg,
+        help = "Folder on --to-team to create the note in (repeatable)"
+    )]
+    pub to_folders: Vec<NoteFolderArg>,
+    #[arg(
+        long = "max-attachment-mib",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u32).range(1..=100),
+        help = "Largest attachment to re-upload"
+    )]
+    pub max_attachment_mib: u32,
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub draft: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LinkArgs {
+    #[command(subcommand)]
+    pub command: LinkCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum LinkCommand {
+    Resolve(LinkResolveArgs),
+    Make(LinkMakeArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LinkResolveArgs {
+    #[arg(value_name = "URL_OR_PATH", help = "Note URL, path, or id")]
+    pub reference: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LinkMakeArgs {
+    #[arg(value_name = "NOTE_ID", help = "Note id (or any note reference)")]
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ReportArgs {
+    #[command(subcommand)]
+    pub command: ReportCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ReportCommand {
+    Attachments(ReportAttachmentsArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ReportAttachmentsArgs {
+    #[arg(long = "group-id")]
+    pub group_id: String,
+    #[arg(long, default_value = "", help = "Search query to narrow attachments")]
+    pub query: String,
+    #[arg(long, help = "Search page size")]
+    pub first: Option<u32>,
+    #[arg(
+        long = "max-pages",
+        default_value_t = 5,
+        help = "Maximum search pages to scan"
+    )]
+    pub max_pages: u32,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Download attachments and match by SHA-256 instead of file name"
+    )]
+    pub hash: bool,
+    #[arg(
+        long = "hash-max-mib",
+        default_value_t = 8,
+        help = "Bytes hashed per attachment (MiB)"
+    )]
+    pub hash_max_mib: u64,
+    #[arg(
+        long = "min-size-kib",
+        default_value_t = 0,
+        help = "Ignore attachments smaller than this (requires --hash)"
+    )]
+    pub min_size_kib: u64,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_CONCURRENCY,
+        help = "Parallel downloads with --hash"
+    )]
+    pub concurrency: usize,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct OpenArgs {
+    #[arg(
+        value_name = "TARGET",
+        help = "Note id, path, or URL; with --folder, a folder id or path"
+    )]
+    pub target: String,
+    #[arg(long, action = ArgAction::SetTrue, help = "Open a folder instead of a note")]
+    pub folder: bool,
+    #[arg(
+        long = "print-url",
+        action = ArgAction::SetTrue,
+        help = "Print the resolved URL instead of launching the browser"
+    )]
+    pub print_url: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TuiArgs {
+    #[arg(long, help = "Maximum items fetched per list")]
+    pub first: Option<u32>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Fetch groups and folders instead of starting from the cached list"
+    )]
+    pub refresh: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PublishArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Publish manifest (default: kibel-publish.toml in the current directory)"
+    )]
+    pub manifest: Option<PathBuf>,
+    #[arg(
+        long = "dry-run",
+        action = ArgAction::SetTrue,
+        help = "List what would be created or updated without calling the API"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Publish every file, even when its content hash matches the state file"
+    )]
+    pub force: bool,
+    #[arg(
+        long,
+        value_name = "REV",
+        num_args = 0..=1,
+        default_missing_value = "",
+        help = "Only publish files changed since REV (`git diff --name-only`); without REV, since the commit in the state file"
+    )]
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ImportArgs {
+    #[command(subcommand)]
+    pub command: ImportCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ImportCommand {
+    #[command(about = "Import a Confluence space export (HTML)")]
+    Confluence(ImportSourceArgs),
+    #[command(about = "Import a Notion workspace export (Markdown & CSV)")]
+    Notion(ImportSourceArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ImportSourceArgs {
+    #[arg(
+        value_name = "EXPORT",
+        help = "The export .zip, or the folder it unpacks to"
+    )]
+    pub export: PathBuf,
+    #[arg(
+        long = "map",
+        value_name = "ROOT=GROUP[/FOLDER...]",
+        help = "Folder for the pages of a space, top-level page, or database (repeatable); others are asked for"
+    )]
+    pub map: Vec<String>,
+    #[arg(long, action = ArgAction::SetTrue, help = "Create the notes with coediting on")]
+    pub coediting: bool,
+    #[arg(
+        long = "dry-run",
+        action = ArgAction::SetTrue,
+        help = "List the notes that would be created without calling the API"
+    )]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DigestArgs {
+    #[arg(
+        long = "group-id",
+        visible_alias = "group",
+        value_name = "GROUP_ID",
+        help = "Group whose feed is summarized"
+    )]
+    pub group_id: String,
+    #[arg(long, default_value = "ALL", help = "Feed kind")]
+    pub kind: String,
+    #[arg(
+        long,
+        default_value = "7d",
+        value_parser = parse_window,
+        help = "Look-back window (`7d`, `24h`); feed dates are whole days"
+    )]
+    pub since: u64,
+    #[arg(long, value_enum, default_value_t, help = "Section the digest by")]
+    pub by: DigestGroupBy,
+    #[arg(long, help = "Feed page size")]
+    pub first: Option<u32>,
+    #[arg(
+        long = "max-pages",
+        default_value_t = 10,
+        help = "Maximum feed pages fetched"
+    )]
+    pub max_pages: u32,
+    #[arg(long, help = "Create the digest as a new note")]
+    pub post: bool,
+    #[arg(
+        long = "to-group",
+        value_name = "GROUP_ID",
+        requires = "post",
+        help = "Group to post in (default: --group-id)"
+    )]
+    pub to_group: Option<String>,
+    #[arg(
+        long,
+        requires = "post",
+        help = "Note title (default: `Digest <from> – <to>`)"
+    )]
+    pub title: Option<String>,
+    #[arg(long, requires = "post", help = "Post as a draft")]
+    pub draft: bool,
+}
+
+/// `digest --by`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DigestGroupBy {
+    /// One section per folder.
+    #[default]
+    Folder,
+    /// One section per author account.
+    Author,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct StatsArgs {
+    #[arg(
+        long = "group-id",
+        value_name = "GROUP_ID",
+        required_unless_present = "folder_id",
+        conflicts_with = "folder_id",
+        help = "Aggregate the notes of a group"
+    )]
+    pub group_id: Option<String>,
+    #[arg(
+        long = "folder-id",
+        value_name = "FOLDER_ID",
+        help = "Aggregate the notes of a folder"
+    )]
+    pub folder_id: Option<String>,
+    #[arg(
+        long = "stale-days",
+        default_value_t = crate::stats::DEFAULT_STALE_DAYS,
+        help = "Notes not updated for this many days count as stale"
+    )]
+    pub stale_days: u64,
+    #[arg(long, default_value_t = 10, help = "Authors to list")]
+    pub top: usize,
+    #[arg(long, help = "Search page size")]
+    pub first: Option<u32>,
+    #[arg(
+        long = "max-pages",
+        default_value_t = 5,
+        help = "Maximum search pages scanned for authors"
+    )]
+    pub max_pages: u32,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CiArgs {
+    #[command(subcommand)]
+    pub command: CiCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum CiCommand {
+    VerifyDocs(CiVerifyDocsArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CiVerifyDocsArgs {
+    #[arg(
+        long,
+        value_name = "PATH|workspace",
+        help = "Docs mapping JSON; `workspace` uses ./kibel-docs.json"
+    )]
+    pub mapping: String,
+    #[arg(long, default_value_t = 3, help = "Context lines in diffs")]
+    pub context: usize,
+    #[arg(long, default_value_t = DEFAULT_CONCURRENCY)]
+    pub concurrency: usize,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LintArgs {
+    #[command(subcommand)]
+    pub command: LintCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum LintCommand {
+    Links(LintLinksArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LintLinksArgs {
+    #[arg(
+        long,
+        value_name = "DIR",
+        required_unless_present = "folder_id",
+        conflicts_with = "folder_id",
+        help = "Scan the Markdown files of a synced/exported notes directory"
+    )]
+    pub dir: Option<PathBuf>,
+    #[arg(
+        long = "folder-id",
+        value_name = "FOLDER_ID",
+        help = "Scan the notes of a folder live"
+    )]
+    pub folder_id: Option<String>,
+    #[arg(long, help = "Notes to read from --folder-id")]
+    pub first: Option<u32>,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        requires = "folder_id",
+        help = "Comment on each note that has dead links"
+    )]
+    pub fix: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TemplateArgs {
     #[command(subcommand)]
-    pub command: NoteCommand,
+    pub command: TemplateCommand,
 }
 
 #[derive(Debug, Clone, Subcommand)]
-pub enum NoteCommand {
-    Create(NoteCreateArgs),
-    Get(NoteGetArgs),
-    GetMany(NoteGetManyArgs),
-    GetFromPath(NoteGetFromPathArgs),
-    Update(NoteUpdateArgs),
-    MoveToFolder(NoteMoveToFolderArgs),
-    AttachToFolder(NoteAttachToFolderArgs),
+pub enum TemplateCommand {
+    List,
+    Show(TemplateShowArgs),
 }
 
 #[derive(Debug, Clone, Args)]
-pub struct NoteCreateArgs {
-    #[arg(long)]
-    pub title: String,
-    #[arg(long)]
-    pub content: String,
-    #[arg(long = "group-id")]
-    pub group_ids: Vec<String>,
-    #[arg(long, action = ArgAction::SetTrue)]
-    pub draft: bool,
-    #[arg(long, action = ArgAction::SetTrue)]
-    pub coediting: bool,
-    #[arg(long = "folder", value_parser = parse_folder_arg)]
-    pub folders: Vec<NoteFolderArg>,
-    #[arg(long = "author-id")]
-    pub author_id: Option<String>,
-    #[arg(long = "published-at")]
-    pub published_at: Option<String>,
-    #[arg(long = "client-mutation-id")]
-    pub client_mutation_id: Option<String>,
+pub struct TemplateShowArgs {
+    #[arg(value_name = "NAME")]
+    pub name: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct NoteFolderArg {
-    pub group_id: String,
-    pub folder_name: String,
+#[derive(Debug, Clone, Args)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub command: CacheCommand,
 }
 
-#[derive(Debug, Clone, Args)]
-pub struct NoteGetArgs {
-    #[arg(long)]
-    pub id: String,
+#[derive(Debug, Clone, Subcommand)]
+pub enum CacheCommand {
+    #[command(about = "Show persisted-query cache location and counters")]
+    Stats,
 }
 
 #[derive(Debug, Clone, Args)]
-pub struct NoteGetManyArgs {
-    #[arg(long = "id", required = true)]
-    pub ids: Vec<String>,
+pub struct StoreArgs {
+    #[command(subcommand)]
+    pub command: StoreCommand,
 }
 
-#[derive(Debug, Clone, Args)]
-pub struct NoteGetFromPathArgs {
-    #[arg(long)]
-    pub path: String,
-    #[arg(long)]
-    pub first: Option<u32>,
+#[derive(Debug, Clone, Subcommand)]
+pub enum StoreCommand {
+    #[command(about = "Show the local store's location, schema version, size, and row counts")]
+    Stats,
+    #[command(about = "Rebuild the local store to give back space freed by deleted rows")]
+    Vacuum,
 }
 
 #[derive(Debug, Clone, Args)]
-pub struct NoteUpdateArgs {
-    #[arg(long)]
-    pub id: String,
-    #[arg(long = "base-content")]
-    pub base_content: String,
-    #[arg(long = "new-content")]
-    pub new_content: String,
+pub struct MetricsArgs {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Also write the Prometheus text to PATH (atomically, for node_exporter's textfile collector)"
+    )]
+    pub textfile: Option<PathBuf>,
+    #[arg(long, action = ArgAction::SetTrue, help = "Clear the counters after reading them")]
+    pub reset: bool,
 }
 
 #[derive(Debug, Clone, Args)]
-pub struct NoteMoveToFolderArgs {
-    #[arg(long)]
-    pub id: String,
-    #[arg(long = "from-folder", value_parser = parse_folder_arg)]
-    pub from_folder: NoteFolderArg,
-    #[arg(long = "to-folder", value_parser = parse_folder_arg)]
-    pub to_folder: NoteFolderArg,
+pub struct UsageArgs {
+    #[arg(
+        long,
+        default_value_t = 30,
+        value_parser = clap::value_parser!(u64).range(1..=kibel_client::usage::USAGE_RETENTION_DAYS),
+        help = "Days to summarize, counting today"
+    )]
+    pub days: u64,
+    #[arg(long, action = ArgAction::SetTrue, help = "Clear the stats after reading them")]
+    pub reset: bool,
 }
 
 #[derive(Debug, Clone, Args)]
-pub struct NoteAttachToFolderArgs {
-    #[arg(long)]
-    pub id: String,
-    #[arg(long = "folder", value_parser = parse_folder_arg)]
-    pub folder: NoteFolderArg,
+pub struct MetaArgs {
+    #[command(subcommand)]
+    pub command: MetaCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum MetaCommand {
+    #[command(
+        about = "Print the embedded resource contracts: operations, variables, and documents"
+    )]
+    Contracts,
+    #[command(about = "List the trusted operations built-in commands send")]
+    Operations,
+    #[command(about = "Print guardrail and timeout defaults")]
+    Limits,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -413,10 +1832,12 @@ pub struct GraphqlArgs {
 #[derive(Debug, Clone, Subcommand)]
 pub enum GraphqlCommand {
     Run(GraphqlRunArgs),
+    Cost(GraphqlCostArgs),
 }
 
+// The document and variables shared by `graphql run` and `graphql cost`.
 #[derive(Debug, Clone, Args)]
-pub struct GraphqlRunArgs {
+pub struct GraphqlDocumentArgs {
     #[arg(long, conflicts_with = "query_file", help = "GraphQL query text")]
     pub query: Option<String>,
     #[arg(
@@ -426,6 +1847,18 @@ pub struct GraphqlRunArgs {
         help = "Path to a file containing GraphQL query text"
     )]
     pub query_file: Option<PathBuf>,
+    #[arg(
+        long = "operation-name",
+        value_name = "NAME",
+        help = "Operation to run when the document defines more than one"
+    )]
+    pub operation_name: Option<String>,
+    #[arg(
+        long = "schema-snapshot",
+        value_name = "PATH",
+        help = "Validate fields, arguments, and variable types against an introspection snapshot before sending"
+    )]
+    pub schema_snapshot: Option<PathBuf>,
     #[arg(
         long,
         conflicts_with = "variables_file",
@@ -439,20 +1872,38 @@ pub struct GraphqlRunArgs {
         help = "Path to a file containing GraphQL variables JSON object"
     )]
     pub variables_file: Option<PathBuf>,
-    #[arg(long, default_value_t = 15, help = "Request timeout (seconds)")]
+    #[arg(
+        long = "var",
+        value_name = "KEY=VALUE",
+        value_parser = parse_key_value,
+        help = "GraphQL variable (repeatable), typed from the operation's declaration; overrides --variables"
+    )]
+    pub vars: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GraphqlRunArgs {
+    #[command(flatten)]
+    pub document: GraphqlDocumentArgs,
+    #[arg(long, default_value_t = GRAPHQL_RUN_TIMEOUT_SECS, help = "Request timeout (seconds)")]
     pub timeout_secs: u64,
     #[arg(
         long = "response-limit-mib",
-        default_value_t = 2,
+        default_value_t = GRAPHQL_RUN_RESPONSE_LIMIT_MIB,
         help = "Response size limit (MiB)"
     )]
     pub response_limit_mib: u64,
-    #[arg(long = "max-depth", default_value_t = 8, help = "Maximum query depth")]
+    #[arg(
+        long = "max-depth",
+        default_value_t = GRAPHQL_RUN_MAX_DEPTH,
+        help = "Maximum query depth"
+    )]
     pub max_depth: u32,
     #[arg(
         long = "max-complexity",
-        default_value_t = 1000,
-        help = "Maximum static complexity score"
+        visible_alias = "max-cost",
+        default_value_t = GRAPHQL_RUN_MAX_COMPLEXITY,
+        help = "Maximum estimated cost (fields resolved; see `graphql cost`)"
     )]
     pub max_complexity: u32,
     #[arg(
@@ -467,6 +1918,18 @@ pub struct GraphqlRunArgs {
         help = "Allow execution when query shape analysis fails"
     )]
     pub unsafe_no_cost_check: bool,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Register the query by hash (APQ) and reuse it via GET on later runs"
+    )]
+    pub persist: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GraphqlCostArgs {
+    #[command(flatten)]
+    pub document: GraphqlDocumentArgs,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -478,9 +1941,65 @@ pub struct CompletionArgs {
 pub struct VersionArgs {
     #[arg(long, action = ArgAction::SetTrue)]
     pub json: bool,
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Also ask GitHub releases whether a newer stable version exists"
+    )]
+    pub check: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SelfArgs {
+    #[command(subcommand)]
+    pub command: SelfCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum SelfCommand {
+    #[command(about = "Replace this binary with the latest release after verifying its checksum")]
+    Update(SelfUpdateArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SelfUpdateArgs {
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Only report whether a newer version exists"
+    )]
+    pub check_only: bool,
+    #[arg(long, value_enum, default_value_t, help = "Release channel to follow")]
+    pub channel: UpdateChannel,
+    #[arg(
+        long,
+        env = "KIBEL_UPDATE_PROXY",
+        value_name = "URL",
+        help = "Proxy for release downloads (default: HTTPS_PROXY/ALL_PROXY)"
+    )]
+    pub proxy: Option<String>,
+}
+
+/// `self update --channel`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum UpdateChannel {
+    /// Published releases only.
+    #[default]
+    Stable,
+    /// Prereleases too.
+    Prerelease,
+}
+
+impl UpdateChannel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Prerelease => "prerelease",
+        }
+    }
 }
 
-fn parse_folder_arg(raw: &str) -> Result<NoteFolderArg, String> {
+pub fn parse_folder_arg(raw: &str) -> Result<NoteFolderArg, String> {
     let value = raw.trim();
     let (group_id, folder_name) = value
         .split_once(':')
@@ -497,11 +2016,74 @@ fn parse_folder_arg(raw: &str) -> Result<NoteFolderArg, String> {
     })
 }
 
+pub fn parse_key_value(raw: &str) -> Result<(String, String), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| "value must be `KEY=VALUE`".to_string())?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("key must not be empty".to_string());
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+pub fn parse_feed_date(raw: &str) -> Result<String, String> {
+    let value = raw.trim();
+    let bytes = value.as_bytes();
+    let shaped = bytes.len() == 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(index, c)| matches!(index, 4 | 7) || c.is_ascii_digit());
+    let in_range = shaped
+        && matches!(value[5..7].parse::<u8>(), Ok(1..=12))
+        && matches!(value[8..10].parse::<u8>(), Ok(1..=31));
+    if !in_range {
+        return Err("date must be `YYYY-MM-DD`".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Parses a `--secret-scan` mode.
+///
+/// # Errors
+/// Returns a message listing the valid modes.
+pub fn parse_secret_scan_mode(raw: &str) -> Result<SecretScanMode, String> {
+    SecretScanMode::parse(raw).ok_or_else(|| {
+        let names = SecretScanMode::ALL.map(SecretScanMode::as_str);
+        format!("must be one of {}", names.join(", "))
+    })
+}
+
+/// Parses a look-back window such as `90s`, `30m`, `24h`, or `7d` into seconds.
+///
+/// # Errors
+/// Returns a message when the value is not a positive count with a unit.
+pub fn parse_window(raw: &str) -> Result<u64, String> {
+    let value = raw.trim();
+    let unit_secs = match value.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 3_600,
+        Some('d') => 86_400,
+        _ => return Err("window must end with s, m, h, or d (e.g. `7d`)".to_string()),
+    };
+    match value[..value.len() - 1].parse::<u64>() {
+        Ok(count) if count > 0 => count
+            .checked_mul(unit_secs)
+            .ok_or_else(|| "window is too large".to_string()),
+        _ => Err("window must be a positive count followed by a unit (e.g. `7d`)".to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        parse_folder_arg, AuthCommand, Cli, Command, ConfigCommand, ConfigSetCommand,
-        GraphqlCommand, GroupCommand, NoteCommand, SearchCommand,
+        parse_feed_date, parse_folder_arg, parse_key_value, parse_window, AuthCommand, Cli,
+        Command, CommentCommand, ConfigCommand, ConfigSetCommand, FeedCommand, GraphqlCommand,
+        GroupCommand, LinkCommand, NoteCommand, ReportCommand, SearchCommand,
     };
     use clap::Parser;
 
@@ -538,9 +2120,9 @@ mod tests {
         match cli.command {
             Command::Note(args) => match args.command {
                 NoteCommand::Update(update) => {
-                    assert_eq!(update.id, "N1");
-                    assert_eq!(update.base_content, "old");
-                    assert_eq!(update.new_content, "new");
+                    assert_eq!(update.id.as_deref(), Some("N1"));
+                    assert_eq!(update.base_content.as_deref(), Some("old"));
+                    assert_eq!(update.new_content.as_deref(), Some("new"));
                 }
                 _ => panic!("expected update command"),
             },
@@ -569,8 +2151,9 @@ mod tests {
         match cli.command {
             Command::Note(args) => match args.command {
                 NoteCommand::Create(create) => {
-                    assert_eq!(create.title, "hello");
-                    assert_eq!(create.content, "world");
+                    assert_eq!(create.title.as_deref(), Some("hello"));
+                    assert_eq!(create.content.as_deref(), Some("world"));
+                    assert!(create.template.is_none());
                     assert_eq!(create.group_ids, vec!["G1"]);
                     assert!(!create.draft);
                     assert!(!create.coediting);
@@ -756,15 +2339,57 @@ mod tests {
         match cli.command {
             Command::Note(args) => match args.command {
                 NoteCommand::MoveToFolder(move_args) => {
-                    assert_eq!(move_args.id, "N1");
-                    assert_eq!(move_args.from_folder.group_id, "G1");
-                    assert_eq!(move_args.from_folder.folder_name, "Old");
-                    assert_eq!(move_args.to_folder.folder_name, "New");
+                    assert_eq!(move_args.id.as_deref(), Some("N1"));
+                    let from_folder = move_args.from_folder.expect("from folder");
+                    assert_eq!(from_folder.group_id, "G1");
+                    assert_eq!(from_folder.folder_name, "Old");
+                    assert_eq!(move_args.to_folder.expect("to folder").folder_name, "New");
+                    assert!(move_args.batch.is_none());
+                }
+                _ => panic!("expected move-to-folder command"),
+            },
+            _ => panic!("expected note command"),
+        }
+    }
+
+    #[test]
+    fn parse_note_move_to_folder_batch_args() {
+        let cli = Cli::try_parse_from([
+            "kibel",
+            "note",
+            "move-to-folder",
+            "--batch",
+            "moves.csv",
+            "--concurrency",
+            "8",
+        ])
+        .expect("parse should succeed");
+
+        match cli.command {
+            Command::Note(args) => match args.command {
+                NoteCommand::MoveToFolder(move_args) => {
+                    assert!(move_args.id.is_none());
+                    assert_eq!(
+                        move_args.batch.as_deref(),
+                        Some(std::path::Path::new("moves.csv"))
+                    );
+                    assert_eq!(move_args.concurrency, 8);
                 }
                 _ => panic!("expected move-to-folder command"),
             },
             _ => panic!("expected note command"),
         }
+
+        assert!(Cli::try_parse_from([
+            "kibel",
+            "note",
+            "move-to-folder",
+            "--batch",
+            "moves.csv",
+            "--id",
+            "N1",
+        ])
+        .is_err());
     }
 
     #[test]
@@ -783,6 +2408,186 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_link_resolve_and_make() {
+        let cli = Cli::try_parse_from(["kibel", "link", "resolve", "https://acme.kibe.la/notes/1"])
+            .expect("parse should succeed");
+        match cli.command {
+            Command::Link(args) => match args.command {
+                LinkCommand::Resolve(resolve) => {
+                    assert_eq!(resolve.reference, "https://acme.kibe.la/notes/1");
+                }
+                LinkCommand::Make(_) => panic!("expected link resolve command"),
+            },
+            _ => panic!("expected link command"),
+        }
+
+        let cli = Cli::try_parse_from(["kibel", "link", "make", "QmxvZy8x"])
+            .expect("parse should succeed");
+        match cli.command {
+            Command::Link(args) => match args.command {
+                LinkCommand::Make(make) => assert_eq!(make.id, "QmxvZy8x"),
+                LinkCommand::Resolve(_) => panic!("expected link make command"),
+            },
+            _ => panic!("expected link command"),
+        }
+    }
+
+    #[test]
+    fn parse_report_attachments_defaults() {
+        let cli = Cli::try_parse_from(["kibel", "report", "attachments", "--group-id", "G1"])
+            .expect("parse should succeed");
+
+        match cli.command {
+            Command::Report(args) => match args.command {
+                ReportCommand::Attachments(report) => {
+                    assert_eq!(report.group_id, "G1");
+                    assert_eq!(report.max_pages, 5);
+                    assert!(!report.hash);
+                    assert_eq!(report.hash_max_mib, 8);
+                    assert_eq!(report.min_size_kib, 0);
+                }
+            },
+            _ => panic!("expected report command"),
+        }
+    }
+
+    #[test]
+    fn parse_feed_sections_since_and_after() {
+        let cli = Cli::try_parse_from([
+            "kibel",
+            "feed",
+            "sections",
+            "--kind",
+            "ALL",
+            "--group-id",
+            "G1",
+            "--since",
+            "2026-02-16",
+            "--after",
+            "cursor-1",
+        ])
+        .expect("parse should succeed");
+
+        match cli.command {
+            Command::Feed(args) => match args.command {
+                FeedCommand::Sections(feed) => {
+                    assert_eq!(feed.since.as_deref(), Some("2026-02-16"));
+                    assert_eq!(feed.after.as_deref(), Some("cursor-1"));
+                    assert_eq!(feed.max_pages, 10);
+                }
+                FeedCommand::Watch(_) | FeedCommand::Relay(_) => {
+                    panic!("expected feed sections command")
+                }
+            },
+            _ => panic!("expected feed command"),
+        }
+        assert!(parse_feed_date("2026-2-16").is_err());
+        assert!(parse_feed_date("2026-13-01").is_err());
+    }
+
+    #[test]
+    fn parse_comment_create_suppress_duplicate_window() {
+        let cli = Cli::try_parse_from([
+            "kibel",
+            "comment",
+            "create",
+            "--note-id",
+            "N1",
+            "--content",
+            "deploy finished",
+            "--suppress-duplicate",
+            "--window",
+            "24h",
+        ])
+        .expect("parse should succeed");
+
+        match cli.command {
+            Command::Comment(args) => match args.command {
+                CommentCommand::Create(create) => {
+                    assert!(create.suppress_duplicate);
+                    assert_eq!(create.window, 86_400);
+                }
+                _ => panic!("expected comment create command"),
+            },
+            _ => panic!("expected comment command"),
+        }
+        assert!(Cli::try_parse_from([
+            "kibel",
+            "comment",
+            "create",
+            "--note-id",
+            "N1",
+            "--content",
+            "x",
+            "--window",
+            "1d",
+        ])
+        .is_err());
+        assert_eq!(parse_window("7d"), Ok(604_800));
+        assert!(parse_window("0d").is_err());
+        assert!(parse_window("7w").is_err());
+    }
+
+    #[test]
+    fn parse_feed_watch_defaults() {
+        let cli = Cli::try_parse_from(["kibel", "feed", "watch", "--group-id", "G1", "--comments"])
+            .expect("parse should succeed");
+
+        match cli.command {
+            Command::Feed(args) => match args.command {
+                FeedCommand::Watch(watch) => {
+                    assert_eq!(watch.kind, "ALL");
+                    assert_eq!(watch.interval, 60);
+                    assert!(watch.comments);
+                    assert!(!watch.emit_existing);
+                    assert_eq!(watch.max_polls, None);
+                }
+                FeedCommand::Sections(_) | FeedCommand::Relay(_) => {
+                    panic!("expected feed watch command")
+                }
+            },
+            _ => panic!("expected feed command"),
+        }
+    }
+
+    #[test]
+    fn parse_note_create_from_template_with_vars() {
+        let cli = Cli::try_parse_from([
+            "kibel",
+            "note",
+            "create",
+            "--template",
+            "daily",
+            "--var",
+            "sprint=42",
+            "--var",
+            "query=a=b",
+        ])
+        .expect("parse should succeed");
+
+        match cli.command {
+            Command::Note(args) => match args.command {
+                NoteCommand::Create(create) => {
+                    assert_eq!(create.template.as_deref(), Some("daily"));
+                    assert!(create.title.is_none());
+                    assert_eq!(
+                        create.vars,
+                        vec![
+                            ("sprint".to_string(), "42".to_string()),
+                            ("query".to_string(), "a=b".to_string()),
+                        ]
+                    );
+                }
+                _ => panic!("unexpected note subcommand"),
+            },
+            _ => panic!("unexpected command"),
+        }
+
+        assert!(Cli::try_parse_from(["kibel", "note", "create", "--content", "x"]).is_err());
+        assert!(parse_key_value("=x").is_err());
+    }
+
     #[test]
     fn parse_folder_arg_rejects_invalid_value() {
         assert!(parse_folder_arg("just-group").is_err());
@@ -797,9 +2602,10 @@ mod tests {
         match cli.command {
             Command::Config(args) => match args.command {
                 ConfigCommand::Set(set) => match set.command {
-                    ConfigSetCommand::Team(team) => assert_eq!(team.team, "acme"),
+                    Some(ConfigSetCommand::Team(team)) => assert_eq!(team.team, "acme"),
+                    None => panic!("expected set team subcommand"),
                 },
-                ConfigCommand::Profiles(_) => panic!("expected set command"),
+                _ => panic!("expected set command"),
             },
             _ => panic!("expected config command"),
         }
@@ -819,8 +2625,8 @@ mod tests {
         match cli.command {
             Command::Graphql(args) => match args.command {
                 GraphqlCommand::Run(run) => {
-                    assert!(run.query.is_some());
-                    assert!(run.variables.is_none());
+                    assert!(run.document.query.is_some());
+                    assert!(run.document.variables.is_none());
                     assert_eq!(run.timeout_secs, 15);
                     assert_eq!(run.response_limit_mib, 2);
                     assert_eq!(run.max_depth, 8);
@@ -828,6 +2634,7 @@ mod tests {
                     assert!(!run.allow_mutation);
                     assert!(!run.unsafe_no_cost_check);
                 }
+                GraphqlCommand::Cost(_) => panic!("expected graphql run"),
             },
             _ => panic!("expected graphql command"),
         }
@@ -0,0 +1,91 @@
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// Clipboard tools tried in order when no command is configured; the first
+/// one that is installed wins.
+fn platform_candidates() -> Vec<&'static [&'static str]> {
+    if cfg!(target_os = "macos") {
+        vec![&["pbcopy"]]
+    } else if cfg!(target_os = "windows") {
+        vec![&["clip"]]
+    } else {
+        let mut candidates: Vec<&'static [&'static str]> = Vec::new();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            candidates.push(&["wl-copy"]);
+        }
+        candidates.push(&["xclip", "-selection", "clipboard"]);
+        candidates.push(&["xsel", "--clipboard", "--input"]);
+        candidates
+    }
+}
+
+/// Places `text` on the system clipboard by piping it into `command` (split
+/// on whitespace) or the first available platform tool.
+///
+/// Returns the argv that received the text.
+pub fn copy_text(command: Option<&str>, text: &str) -> Result<Vec<String>, String> {
+    if let Some(command) = command.map(str::trim).filter(|command| !command.is_empty()) {
+        let argv = command
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        return match pipe_into(&argv, text) {
+            Ok(()) => Ok(argv),
+            Err(PipeError::Missing) => Err(format!("clipboard command `{}` not found", argv[0])),
+            Err(PipeError::Failed(message)) => Err(message),
+        };
+    }
+
+    let mut tried = Vec::new();
+    for candidate in platform_candidates() {
+        let argv = candidate
+            .iter()
+            .map(|word| (*word).to_string())
+            .collect::<Vec<_>>();
+        match pipe_into(&argv, text) {
+            Ok(()) => return Ok(argv),
+            Err(PipeError::Missing) => tried.push(argv[0].clone()),
+            Err(PipeError::Failed(message)) => return Err(message),
+        }
+    }
+    Err(format!(
+        "no clipboard tool found (tried {}); set KIBEL_CLIPBOARD",
+        tried.join(", ")
+    ))
+}
+
+enum PipeError {
+    Missing,
+    Failed(String),
+}
+
+fn pipe_into(argv: &[String], text: &str) -> Result<(), PipeError> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| PipeError::Failed("clipboard command is empty".to_string()))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|error| match error.kind() {
+            ErrorKind::NotFound => PipeError::Missing,
+            _ => PipeError::Failed(format!("failed to start `{program}`: {error}")),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).map_err(|error| {
+            PipeError::Failed(format!("failed to write to `{program}`: {error}"))
+        })?;
+    }
+    let status = child
+        .wait()
+        .map_err(|error| PipeError::Failed(format!("failed to wait for `{program}`: {error}")))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(PipeError::Failed(format!(
+            "`{program}` exited with {status}"
+        )))
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{cli, store};
+use clap::{Command, CommandFactory};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use kibel_client::{default_config_path, Config, LocalStore};
+use serde_json::Value;
+
+/// Which listing a cache update came from. Group and folder names seen by
+/// `group list` / `folder list` are kept per team in the local store, so
+/// shell completion can offer them without a network request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Group,
+    Folder,
+}
+
+impl EntryKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Group => "group",
+            Self::Folder => "folder",
+        }
+    }
+}
+
+/// Replaces the team's groups or folders with the `{id, name}` items of a
+/// listing, so renamed or deleted entries do not linger.
+fn record(
+    store: &mut LocalStore,
+    team: &str,
+    kind: EntryKind,
+    items: &[Value],
+) -> Result<(), kibel_client::KibelClientError> {
+    let entries = items.iter().filter_map(|item| {
+        let id = item.get("id").and_then(Value::as_str)?;
+        let name = item.get("name").and_then(Value::as_str).unwrap_or_default();
+        Some((id, name))
+    });
+    store.replace_completion_entries(team, kind.as_str(), entries)
+}
+
+/// Entries of `team` when the store has any for it, else of every cached
+/// team.
+fn entries(store: &LocalStore, team: Option<&str>, kind: EntryKind) -> Vec<(String, String)> {
+    let team = team.filter(|team| store.has_completion_team(team).unwrap_or(false));
+    store
+        .completion_entries(team, kind.as_str())
+        .unwrap_or_default()
+}
+
+/// The `(id, name)` entries of `kind` cached for `team` alone; empty when
+/// none are, or the store cannot be opened.
+pub fn cached(team: Option<&str>, kind: EntryKind) -> Vec<(String, String)> {
+    store::open()
+        .and_then(|store| {
+            store
+                .completion_entries(Some(team.unwrap_or_default()), kind.as_str())
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
+/// Best-effort cache update after a successful listing; completion data is
+/// never worth failing a command for.
+pub fn remember(team: Option<&str>, kind: EntryKind, items: &Value) {
+    let (Some(mut store), Some(items)) = (store::open(), items.as_array()) else {
+        return;
+    };
+    let _ = record(&mut store, team.unwrap_or_default(), kind, items);
+}
+
+/// The CLI command tree with runtime value completers attached, for
+/// `COMPLETE=<shell> kibel`.
+pub fn command() -> Command {
+    attach_completers(cli::Cli::command())
+}
+
+fn attach_completers(command: Command) -> Command {
+    command
+        .mut_args(|arg| match arg.get_long() {
+            Some("team") => arg.add(ArgValueCandidates::new(team_candidates)),
+            Some("group-id" | "to-group") => arg.add(ArgValueCandidates::new(group_candidates)),
+            Some("folder-id") => arg.add(ArgValueCandidates::new(folder_candidates)),
+            _ => arg,
+        })
+        .mut_subcommands(attach_completers)
+}
+
+fn config() -> Config {
+    default_config_path()
+        .ok()
+        .and_then(|path| Config::load(path).ok())
+        .unwrap_or_default()
+}
+
+fn team_candidates() -> Vec<CompletionCandidate> {
+    let config = config();
+    config
+        .profiles
+        .iter()
+        .map(|(team, profile)| {
+            let default = config.default_team.as_deref() == Some(team.as_str());
+            let help = match (&profile.origin, default) {
+                (Some(origin), true) => format!("{origin} (default)"),
+                (Some(origin), false) => origin.clone(),
+                (None, true) => "default".to_string(),
+                (None, false) => String::new(),
+            };
+            candidate(team, &help)
+        })
+        .collect()
+}
+
+fn group_candidates() -> Vec<CompletionCandidate> {
+    cached_candidates(EntryKind::Group)
+}
+
+fn folder_candidates() -> Vec<CompletionCandidate> {
+    cached_candidates(EntryKind::Folder)
+}
+
+/// Cached ids for the team in `KIBELA_TEAM` or the configured default team,
+/// falling back to every cached team.
+fn cached_candidates(kind: EntryKind) -> Vec<CompletionCandidate> {
+    let Some(store) = store::open() else {
+        return Vec::new();
+    };
+    let team = std::env::var("KIBELA_TEAM")
+        .ok()
+        .filter(|team| !team.trim().is_empty())
+        .or_else(|| config().default_team);
+    entries(&store, team.as_deref(), kind)
+        .into_iter()
+        .map(|(id, name)| candidate(&id, &name))
+        .collect()
+}
+
+fn candidate(value: &str, help: &str) -> CompletionCandidate {
+    CompletionCandidate::new(value).help((!help.is_empty()).then(|| help.to_string().into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command, entries, record, EntryKind};
+    use clap::Arg;
+    use clap_complete::engine::ArgValueCandidates;
+    use kibel_client::LocalStore;
+    use serde_json::json;
+
+    fn has_candidates(arg: &Arg) -> bool {
+        arg.get::<ArgValueCandidates>().is_some()
+    }
+
+    #[test]
+    fn cache_records_per_team_and_completers_are_attached() {
+        let dir = std::env::temp_dir().join(format!("kibel-completion-{}", std::process::id()));
+        let mut store = LocalStore::open(LocalStore::path_in(&dir)).expect("store should open");
+        record(
+            &mut store,
+            "acme",
+            EntryKind::Group,
+            &[
+                json!({"id": "G1", "name": "Engineering"}),
+                json!({"name": "no id"}),
+            ],
+        )
+        .expect("record groups");
+        record(
+            &mut store,
+            "beta",
+            EntryKind::Group,
+            &[json!({"id": "G9", "name": "Ops"})],
+        )
+        .expect("record groups");
+        record(
+            &mut store,
+            "acme",
+            EntryKind::Folder,
+            &[json!({"id": "F1", "name": "Specs"})],
+        )
+        .expect("record folders");
+        assert_eq!(
+            entries(&store, Some("acme"), EntryKind::Group),
+            [("G1".to_string(), "Engineering".to_string())]
+        );
+        assert_eq!(entries(&store, None, EntryKind::Group).len(), 2);
+        assert_eq!(entries(&store, Some("unknown"), EntryKind::Group).len(), 2);
+        record(&mut store, "acme", EntryKind::Group, &[]).expect("clear groups");
+        assert!(entries(&store, Some("acme"), EntryKind::Group).is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut root = command();
+        root.build();
+        let stats = root.find_subcommand("stats").expect("stats command");
+        for long in ["group-id", "folder-id", "team"] {
+            let arg = stats
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(long))
+                .expect("argument exists");
+            assert!(has_candidates(arg), "--{long} has candidates");
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::progress::Progress;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+pub const DEFAULT_CONCURRENCY: usize = 4;
+pub const MAX_CONCURRENCY: usize = 16;
+
+/// Runs `task` over `items` with at most `concurrency` worker threads.
+///
+/// Results are returned in input order regardless of completion order.
+pub fn run_bounded<T, R, F>(items: &[T], concurrency: usize, task: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Sync,
+{
+    let workers = concurrency.clamp(1, MAX_CONCURRENCY).min(items.len());
+    if workers <= 1 {
+        return items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| task(index, item))
+            .collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let slots = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<R>>>());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = task(index, item);
+                if let Ok(mut slots) = slots.lock() {
+                    slots[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    slots
+        .into_inner()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .into_iter()
+        .map(|slot| slot.expect("every item is processed exactly once"))
+        .collect()
+}
+
+/// [`run_bounded`] for fallible tasks, reporting each finished item (and
+/// whether it failed) to `progress`.
+pub fn run_with_progress<T, V, E, F>(
+    items: &[T],
+    concurrency: usize,
+    progress: &Progress,
+    task: F,
+) -> Vec<Result<V, E>>
+where
+    T: Sync,
+    V: Send,
+    E: Send,
+    F: Fn(usize, &T) -> Result<V, E> + Sync,
+{
+    progress.set_total(items.len() as u64);
+    let results = run_bounded(items, concurrency, |index, item| {
+        let result = task(index, item);
+        progress.record(result.is_ok());
+        result
+    });
+    progress.finish();
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_bounded, run_with_progress};
+    use crate::output::OutputContext;
+    use crate::progress::Progress;
+
+    #[test]
+    fn run_bounded_preserves_input_order() {
+        let items = (0..32).collect::<Vec<u32>>();
+        let results = run_bounded(&items, 8, |index, item| (index, item * 2));
+        for (index, (seen_index, doubled)) in results.into_iter().enumerate() {
+            assert_eq!(seen_index, index);
+            assert_eq!(doubled, items[index] * 2);
+        }
+    }
+
+    #[test]
+    fn run_bounded_handles_empty_input() {
+        let results = run_bounded(&Vec::<u32>::new(), 4, |_, item| *item);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn run_with_progress_counts_failures() {
+        let progress = Progress::new(OutputContext::default(), "test", None);
+        let items = (0..10).collect::<Vec<u32>>();
+        let results = run_with_progress(&items, 4, &progress, |_, item| {
+            if item % 3 == 0 {
+                Err(*item)
+            } else {
+                Ok(*item)
+            }
+        });
+        assert_eq!(results.iter().filter(|result| result.is_err()).count(), 4);
+        assert_eq!(results[4], Ok(4));
+    }
+}
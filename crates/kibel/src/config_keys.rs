@@ -0,0 +1,246 @@
+//! Keys `config get` and `config set` read and write, so scripts can
+//! provision settings without editing the config file.
+
+use crate::transform;
+use kibel_client::{Config, FolderPath, TokenStoreBackend};
+use serde_json::{json, Value};
+
+/// Every accepted key; `<team>` is a profile name.
+pub const AVAILABLE: &[&str] = &[
+    "default_team",
+    "templates_dir",
+    "browser",
+    "copy_url",
+    "usage_stats",
+    "token_store",
+    "on_fetch",
+    "profiles.<team>.origin",
+    "profiles.<team>.on_fetch",
+    "profiles.<team>.default_group",
+    "profiles.<team>.default_folder_path",
+    "profiles.<team>.default_coediting",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigKey {
+    DefaultTeam,
+    TemplatesDir,
+    Browser,
+    CopyUrl,
+    UsageStats,
+    TokenStore,
+    OnFetch,
+    Profile(String, ProfileKey),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileKey {
+    Origin,
+    OnFetch,
+    DefaultGroup,
+    DefaultFolderPath,
+    DefaultCoediting,
+}
+
+impl ConfigKey {
+    /// Parses a dotted key such as `copy_url` or `profiles.acme.origin`.
+    /// `team` is accepted for `default_team`, matching `config set team`.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let key = match raw {
+            "default_team" | "team" => Self::DefaultTeam,
+            "templates_dir" => Self::TemplatesDir,
+            "browser" => Self::Browser,
+            "copy_url" => Self::CopyUrl,
+            "usage_stats" => Self::UsageStats,
+            "token_store" => Self::TokenStore,
+            "on_fetch" => Self::OnFetch,
+            _ => {
+                let Some((team, field)) = raw
+                    .strip_prefix("profiles.")
+                    .and_then(|rest| rest.rsplit_once('.'))
+                    .filter(|(team, _)| !team.trim().is_empty())
+                else {
+                    return Err(unknown_key(raw));
+                };
+                let field = match field {
+                    "origin" => ProfileKey::Origin,
+                    "on_fetch" => ProfileKey::OnFetch,
+                    "default_group" => ProfileKey::DefaultGroup,
+                    "default_folder_path" => ProfileKey::DefaultFolderPath,
+                    "default_coediting" => ProfileKey::DefaultCoediting,
+                    "token" => {
+                        return Err(format!(
+                            "`{raw}` is not settable here; use `auth login --team {team}`"
+                        ))
+                    }
+                    _ => return Err(unknown_key(raw)),
+                };
+                Self::Profile(team.to_string(), field)
+            }
+        };
+        Ok(key)
+    }
+
+    /// The current value, `null` when unset.
+    pub fn get(&self, config: &Config) -> Value {
+        match self {
+            Self::DefaultTeam => json!(config.default_team),
+            Self::TemplatesDir => json!(config.templates_dir),
+            Self::Browser => json!(config.browser),
+            Self::CopyUrl => json!(config.copy_url),
+            Self::UsageStats => json!(config.usage_stats),
+            Self::TokenStore => json!(config.token_store.map(TokenStoreBackend::as_str)),
+            Self::OnFetch => json!(config.on_fetch),
+            Self::Profile(team, field) => {
+                let Some(profile) = config.profiles.get(team) else {
+                    return Value::Null;
+                };
+                match field {
+                    ProfileKey::Origin => json!(profile.origin),
+                    ProfileKey::OnFetch => json!(profile.on_fetch),
+                    ProfileKey::DefaultGroup => json!(profile.default_group),
+                    ProfileKey::DefaultFolderPath => json!(profile.default_folder_path),
+                    ProfileKey::DefaultCoediting => json!(profile.default_coediting),
+                }
+            }
+        }
+    }
+
+    /// Validates `raw` for this key and stores it, or clears the key when
+    /// `raw` is `None`. Returns the stored value.
+    pub fn set(&self, config: &mut Config, raw: Option<&str>) -> Result<Value, String> {
+        let raw = raw.map(str::trim);
+        match self {
+            Self::DefaultTeam => config.default_team = raw.map(non_empty).transpose()?,
+            Self::TemplatesDir => config.templates_dir = raw.map(non_empty).transpose()?,
+            Self::Browser => config.browser = raw.map(non_empty).transpose()?,
+            Self::CopyUrl => config.copy_url = raw.map(boolean).transpose()?,
+            Self::UsageStats => config.usage_stats = raw.map(boolean).transpose()?,
+            Self::TokenStore => {
+                config.token_store = raw
+                    .map(|raw| {
+                        TokenStoreBackend::parse(raw).ok_or_else(|| {
+                            format!(
+                                "unknown token store `{raw}`; available: {}",
+                                TokenStoreBackend::ALL
+                                    .map(TokenStoreBackend::as_str)
+                                    .join(", ")
+                            )
+                        })
+                    })
+                    .transpose()?;
+            }
+            Self::OnFetch => config.on_fetch = raw.map(pipeline).transpose()?,
+            Self::Profile(team, field) => {
+                if raw.is_none() && !config.profiles.contains_key(team) {
+                    return Ok(Value::Null);
+                }
+                let profile = config.profiles.entry(team.clone()).or_default();
+                match field {
+                    ProfileKey::Origin => profile.origin = raw.map(origin).transpose()?,
+                    ProfileKey::OnFetch => profile.on_fetch = raw.map(pipeline).transpose()?,
+                    ProfileKey::DefaultGroup => {
+                        profile.default_group = raw.map(non_empty).transpose()?;
+                    }
+                    ProfileKey::DefaultFolderPath => {
+                        profile.default_folder_path = raw
+                            .map(|raw| {
+                                FolderPath::parse(raw)
+                                    .map(|_| raw.to_string())
+                                    .map_err(|error| error.to_string())
+                            })
+                            .transpose()?;
+                    }
+                    ProfileKey::DefaultCoediting => {
+                        profile.default_coediting = raw.map(boolean).transpose()?;
+                    }
+                }
+            }
+        }
+        Ok(self.get(config))
+    }
+}
+
+fn unknown_key(raw: &str) -> String {
+    format!(
+        "unknown config key `{raw}`; available: {}",
+        AVAILABLE.join(", ")
+    )
+}
+
+fn non_empty(raw: &str) -> Result<String, String> {
+    if raw.is_empty() {
+        Err("value is empty; pass --unset to clear the key".to_string())
+    } else {
+        Ok(raw.to_string())
+    }
+}
+
+fn boolean(raw: &str) -> Result<bool, String> {
+    match raw {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("expected `true` or `false`, got `{raw}`")),
+    }
+}
+
+fn origin(raw: &str) -> Result<String, String> {
+    let origin = raw.trim_end_matches('/');
+    if origin.starts_with("https://") || origin.starts_with("http://") {
+        Ok(origin.to_ascii_lowercase())
+    } else {
+        Err("origin must start with http:// or https://".to_string())
+    }
+}
+
+/// A comma-separated `on_fetch` pipeline such as `strip-html,truncate:500`.
+fn pipeline(raw: &str) -> Result<Vec<String>, String> {
+    let specs = raw.split(',').map(ToOwned::to_owned).collect::<Vec<_>>();
+    Ok(transform::Pipeline::parse(&specs)?.specs().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigKey, ProfileKey};
+    use kibel_client::Config;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn keys_are_validated_before_they_are_stored() {
+        let mut config = Config::default();
+        let origin = ConfigKey::parse("profiles.acme.origin").expect("profile key");
+        assert_eq!(
+            origin,
+            ConfigKey::Profile("acme".to_string(), ProfileKey::Origin)
+        );
+        assert_eq!(
+            origin.set(&mut config, Some("https://ACME.kibe.la/")),
+            Ok(json!("https://acme.kibe.la"))
+        );
+        assert!(origin.set(&mut config, Some("acme.kibe.la")).is_err());
+
+        let on_fetch = ConfigKey::parse("on_fetch").expect("top-level key");
+        assert_eq!(
+            on_fetch.set(&mut config, Some("strip-html, truncate:50")),
+            Ok(json!(["strip-html", "truncate:50"]))
+        );
+        assert!(on_fetch.set(&mut config, Some("shout")).is_err());
+        assert!(ConfigKey::parse("copy_url")
+            .expect("top-level key")
+            .set(&mut config, Some("yes"))
+            .is_err());
+
+        assert_eq!(on_fetch.set(&mut config, None), Ok(Value::Null));
+        assert_eq!(config.on_fetch, None);
+        assert_eq!(
+            ConfigKey::parse("team").expect("alias"),
+            ConfigKey::DefaultTeam
+        );
+        assert!(ConfigKey::parse("profiles.acme.token")
+            .expect_err("tokens are refused")
+            .contains("auth login"));
+        assert!(ConfigKey::parse("profiles..origin").is_err());
+        assert!(ConfigKey::parse("timeout").is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! The opt-in `--normalize` pipeline applied to note content before
+//! `note create` / `note update` upload it, [`analysis`] of fetched content,
+//! and [`section`] and [`addition`] edits.
+
+pub mod addition;
+pub mod analysis;
+mod headings;
+mod local_images;
+pub mod section;
+mod tabs;
+mod trailing_whitespace;
+
+pub use local_images::Upload;
+
+pub const AVAILABLE: &[&str] = &["headings", "tabs", "trailing-whitespace", "local-images"];
+
+/// One normalization step, parsed from its name; `all` expands to every step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalize {
+    Headings,
+    Tabs,
+    TrailingWhitespace,
+    LocalImages,
+}
+
+impl Normalize {
+    pub fn parse(raw: &str) -> Result<Vec<Self>, String> {
+        match raw.trim() {
+            "all" => Ok(vec![
+                Self::Headings,
+                Self::Tabs,
+                Self::TrailingWhitespace,
+                Self::LocalImages,
+            ]),
+            "headings" => Ok(vec![Self::Headings]),
+            "tabs" => Ok(vec![Self::Tabs]),
+            "trailing-whitespace" => Ok(vec![Self::TrailingWhitespace]),
+            "local-images" => Ok(vec![Self::LocalImages]),
+            other => Err(format!(
+                "unknown --normalize step `{other}`; available: all, {}",
+                AVAILABLE.join(", ")
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Headings => "headings",
+            Self::Tabs => "tabs",
+            Self::TrailingWhitespace => "trailing-whitespace",
+            Self::LocalImages => "local-images",
+        }
+    }
+
+    /// The rewritten content and how many lines (or images) changed.
+    fn apply(self, content: &str, upload: &mut Upload<'_>) -> Result<(String, usize), String> {
+        match self {
+            Self::Headings => Ok(headings::apply(content)),
+            Self::Tabs => Ok(tabs::apply(content)),
+            Self::TrailingWhitespace => Ok(trailing_whitespace::apply(content)),
+            Self::LocalImages => local_images::apply(content, upload),
+        }
+    }
+}
+
+/// Ordered, de-duplicated normalization steps; the output of each feeds the
+/// next.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Normalizer {
+    steps: Vec<Normalize>,
+}
+
+impl Normalizer {
+    pub fn parse(specs: &[String]) -> Result<Self, String> {
+        let mut steps = Vec::new();
+        for spec in specs.iter().filter(|spec| !spec.trim().is_empty()) {
+            for step in Normalize::parse(spec)? {
+                if !steps.contains(&step) {
+                    steps.push(step);
+                }
+            }
+        }
+        Ok(Self { steps })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Runs every step, reporting `{step: changes}` for command metadata.
+    pub fn apply(
+        &self,
+        content: &str,
+        upload: &mut Upload<'_>,
+    ) -> Result<(String, serde_json::Value), String> {
+        let mut content = content.to_string();
+        let mut changes = serde_json::Map::new();
+        for step in &self.steps {
+            let (normalized, changed) = step.apply(&content, upload)?;
+            content = normalized;
+            changes.insert(step.name().to_string(), changed.into());
+        }
+        Ok((content, serde_json::Value::Object(changes)))
+    }
+}
+
+/// Splits `content` into lines (keeping a final empty line when it ends with a
+/// newline), each paired with whether it sits inside a fenced code block.
+/// Fence lines themselves count as code.
+fn fenced_lines(content: &str) -> Vec<(&str, bool)> {
+    let mut fence: Option<&str> = None;
+    content
+        .split('\n')
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let marker = ["```", "~~~"]
+                .into_iter()
+                .find(|marker| trimmed.starts_with(marker));
+            match (fence, marker) {
+                (None, Some(marker)) => {
+                    fence = Some(marker);
+                    (line, true)
+                }
+                (Some(open), Some(marker)) if open == marker => {
+                    fence = None;
+                    (line, true)
+                }
+                (open, _) => (line, open.is_some()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fenced_lines, Normalize, Normalizer};
+
+    #[test]
+    fn parse_expands_all_and_rejects_unknown_steps() {
+        assert_eq!(Normalize::parse("tabs"), Ok(vec![Normalize::Tabs]));
+        assert_eq!(Normalize::parse("all").map(|steps| steps.len()), Ok(4));
+        assert!(Normalize::parse("shout")
+            .expect_err("unknown step")
+            .contains("available: all, headings"));
+        let normalizer = Normalizer::parse(&["tabs".to_string(), "all".to_string()])
+            .expect("normalizer should parse");
+        assert_eq!(normalizer.steps[0], Normalize::Tabs);
+        assert_eq!(normalizer.steps.len(), 4);
+    }
+
+    #[test]
+    fn pipeline_reports_changes_per_step() {
+        let normalizer = Normalizer::parse(&["all".to_string()]).expect("normalizer");
+        let mut upload = |_: &str| Ok(None);
+        let (content, changes) = normalizer
+            .apply("### Title  \n\n\tindented\t\n#### Sub\n", &mut upload)
+            .expect("normalize");
+        assert_eq!(content, "# Title\n\n    indented\n## Sub\n");
+        assert_eq!(changes["headings"], 2);
+        assert_eq!(changes["tabs"], 1);
+        assert_eq!(changes["trailing-whitespace"], 2);
+        assert_eq!(changes["local-images"], 0);
+    }
+
+    #[test]
+    fn fences_are_tracked_by_marker() {
+        let lines = fenced_lines("a\n```\n~~~\n```\nb");
+        assert_eq!(
+            lines.iter().map(|(_, code)| *code).collect::<Vec<_>>(),
+            vec![false, true, true, true, false]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+/// Where `note append` / `note prepend` put the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Start,
+    End,
+}
+
+impl Placement {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Start => "prepend",
+            Self::End => "append",
+        }
+    }
+}
+
+/// Adds `text` as whole lines at the start or end of `content`, keeping
+/// whether `content` ended with a newline.
+pub fn add_lines(content: &str, text: &str, placement: Placement) -> String {
+    let addition = text.trim_end_matches('\n');
+    let body = content.trim_end_matches('\n');
+    if body.is_empty() {
+        return addition.to_string();
+    }
+    match placement {
+        Placement::Start => format!("{addition}\n{content}"),
+        Placement::End => {
+            let trailing = if content.ends_with('\n') { "\n" } else { "" };
+            format!("{body}\n{addition}{trailing}")
+        }
+    }
+}
+
+/// Whether every non-blank line of `text` is already a line of `content`,
+/// ignoring trailing whitespace.
+pub fn contains_lines(content: &str, text: &str) -> bool {
+    let present = content.lines().map(str::trim_end).collect::<Vec<_>>();
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| present.contains(&line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_lines, contains_lines, Placement};
+
+    #[test]
+    fn adds_whole_lines_and_detects_present_ones() {
+        let changelog = "# Changelog\n- v1\n";
+        assert_eq!(
+            add_lines(changelog, "- v2\n", Placement::End),
+            "# Changelog\n- v1\n- v2\n"
+        );
+        assert_eq!(add_lines("a", "b", Placement::End), "a\nb");
+        assert_eq!(
+            add_lines(changelog, "> draft", Placement::Start),
+            "> draft\n# Changelog\n- v1\n"
+        );
+        assert_eq!(add_lines("\n", "first", Placement::End), "first");
+
+        assert!(contains_lines(changelog, "- v1  \n\n"));
+        assert!(!contains_lines(changelog, "- v1\n- v2"));
+        assert!(!contains_lines(changelog, "- v"));
+    }
+}
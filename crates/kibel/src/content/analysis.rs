@@ -0,0 +1,299 @@
+use super::fenced_lines;
+use super::headings::heading_level;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Latin-script words read per minute.
+const WORDS_PER_MINUTE: usize = 200;
+/// CJK characters read per minute; Japanese prose runs about 400-600.
+const CJK_CHARS_PER_MINUTE: usize = 500;
+
+/// Figures derived from note Markdown for `note get` and `search note
+/// --content-stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ContentStats {
+    /// Latin-script words plus CJK characters, outside fenced code.
+    pub words: usize,
+    /// Estimated minutes to read, rounded up; 0 only for empty content.
+    pub reading_minutes: usize,
+    /// ATX headings in document order; empty for snippets.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outline: Vec<Heading>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    /// 1 for `#` through 6 for `######`.
+    pub level: usize,
+    /// The heading text without markers or a closing `#` sequence.
+    pub text: String,
+    /// 1-based line number in the content.
+    pub line: usize,
+    /// Fragment id of the heading: a GitHub-style slug, `-1`, `-2`, ... added
+    /// to repeats.
+    pub anchor: String,
+}
+
+/// A heading with the headings nested under it, for `note outline`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutlineNode {
+    #[serde(flatten)]
+    pub heading: Heading,
+    /// `<note url>#<anchor>`, when asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub children: Vec<OutlineNode>,
+}
+
+/// Counts words and reads the heading outline of `markdown`.
+pub fn analyze(markdown: &str) -> ContentStats {
+    let mut stats = count_words(
+        fenced_lines(markdown)
+            .into_iter()
+            .filter(|(_, code)| !code)
+            .map(|(line, _)| line),
+    );
+    stats.outline = outline(markdown);
+    stats
+}
+
+/// Counts words of plain text such as a search snippet, without an outline.
+pub fn analyze_text(text: &str) -> ContentStats {
+    count_words(text.lines())
+}
+
+/// ATX headings outside fenced code, in document order.
+pub fn outline(markdown: &str) -> Vec<Heading> {
+    let mut seen = HashMap::<String, usize>::new();
+    fenced_lines(markdown)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (_, code))| !code)
+        .filter_map(|(index, (line, _))| {
+            let level = heading_level(line)?;
+            let text = line.trim_start()[level..].trim();
+            // A closing sequence must be preceded by a space: `# C#` keeps its `#`.
+            let stripped = text.trim_end_matches('#');
+            let text = if stripped.is_empty() || stripped.ends_with([' ', '\t']) {
+                stripped.trim_end()
+            } else {
+                text
+            };
+            let slug = slug(text);
+            let repeats = seen.entry(slug.clone()).or_default();
+            let anchor = match *repeats {
+                0 => slug,
+                n => format!("{slug}-{n}"),
+            };
+            *repeats += 1;
+            Some(Heading {
+                level,
+                text: text.to_string(),
+                line: index + 1,
+                anchor,
+            })
+        })
+        .collect()
+}
+
+/// Accounts mentioned as `@account` outside fenced and inline code, in
+/// first-mention order without repeats. An `@` after a letter or digit, as in
+/// an e-mail address, is not a mention.
+pub fn mentions(markdown: &str) -> Vec<String> {
+    let mut accounts = Vec::<String>::new();
+    for (line, _) in fenced_lines(markdown).into_iter().filter(|(_, code)| !code) {
+        let mut previous = ' ';
+        let mut in_code = false;
+        for (index, c) in line.char_indices() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if c == '@' && !in_code && !previous.is_alphanumeric() {
+                let account = line[index + 1..]
+                    .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-')))
+                    .next()
+                    .unwrap_or_default();
+                if !account.is_empty() && !accounts.iter().any(|seen| seen == account) {
+                    accounts.push(account.to_string());
+                }
+            }
+            previous = c;
+        }
+    }
+    accounts
+}
+
+/// Nests `headings` under the nearest shallower heading before them; a
+/// heading with none is a root.
+pub fn outline_tree(headings: Vec<Heading>, note_url: Option<&str>) -> Vec<OutlineNode> {
+    fn attach(nodes: &mut Vec<OutlineNode>, node: OutlineNode) {
+        match nodes.last_mut() {
+            Some(parent) if parent.heading.level < node.heading.level => {
+                attach(&mut parent.children, node);
+            }
+            _ => nodes.push(node),
+        }
+    }
+    let mut roots = Vec::new();
+    for heading in headings {
+        let url = note_url.map(|url| format!("{url}#{}", heading.anchor));
+        attach(
+            &mut roots,
+            OutlineNode {
+                heading,
+                url,
+                children: Vec::new(),
+            },
+        );
+    }
+    roots
+}
+
+/// Lowercases `text`, drops punctuation other than `-` and `_`, and turns
+/// spaces into `-`; letters of every script are kept.
+fn slug(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            ' ' | '\t' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn count_words<'a>(lines: impl Iterator<Item = &'a str>) -> ContentStats {
+    let (mut latin, mut cjk) = (0, 0);
+    for line in lines {
+        let mut in_word = false;
+        for c in line.chars() {
+            if is_cjk(c) {
+                cjk += 1;
+                in_word = false;
+            } else if c.is_alphanumeric() || (in_word && matches!(c, '\'' | '-' | '’')) {
+                latin += usize::from(!in_word);
+                in_word = true;
+            } else {
+                in_word = false;
+            }
+        }
+    }
+    let words = latin + cjk;
+    let minutes = (latin * CJK_CHARS_PER_MINUTE + cjk * WORDS_PER_MINUTE)
+        .div_ceil(WORDS_PER_MINUTE * CJK_CHARS_PER_MINUTE);
+    ContentStats {
+        words,
+        reading_minutes: if words == 0 { 0 } else { minutes.max(1) },
+        outline: Vec::new(),
+    }
+}
+
+/// Han, kana, and Hangul, which are read per character rather than per word.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{ac00}'..='\u{d7af}'
+        | '\u{ff66}'..='\u{ff9f}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze, analyze_text, mentions, outline, outline_tree, Heading};
+
+    #[test]
+    fn counts_latin_words_and_cjk_characters_outside_code() {
+        let stats = analyze(
+            "# Deploy guide #\n\nIt's a well-known step.\n```\nnot counted here\n```\n## 手順\n本番に反映する\n### C#\n",
+        );
+        // Deploy guide / It's a well-known step / 手順 (2) / 本番に反映する (7) / C
+        assert_eq!(stats.words, 2 + 4 + 2 + 7 + 1);
+        assert_eq!(stats.reading_minutes, 1);
+        assert_eq!(
+            stats.outline,
+            [
+                Heading {
+                    level: 1,
+                    text: "Deploy guide".to_string(),
+                    line: 1,
+                    anchor: "deploy-guide".to_string(),
+                },
+                Heading {
+                    level: 2,
+                    text: "手順".to_string(),
+                    line: 7,
+                    anchor: "手順".to_string(),
+                },
+                Heading {
+                    level: 3,
+                    text: "C#".to_string(),
+                    line: 9,
+                    anchor: "c".to_string(),
+                },
+            ]
+        );
+
+        let long = analyze_text(&"word ".repeat(401));
+        assert_eq!((long.words, long.reading_minutes), (401, 3));
+        assert_eq!(analyze_text("").reading_minutes, 0);
+        assert!(long.outline.is_empty());
+    }
+
+    #[test]
+    fn mentions_skip_code_and_email_addresses() {
+        assert_eq!(
+            mentions("cc @alice, @bob-ops and @alice\nmail ops@example.com\n`@inline`\n```\n@fenced\n```\n(@carol_1)"),
+            ["alice", "bob-ops", "carol_1"]
+        );
+    }
+
+    #[test]
+    fn outline_tree_nests_headings_and_numbers_repeated_anchors() {
+        let headings = outline(
+            "## Setup\n# Deploy: Step 1\n### Notes\n## Rollback\n### Notes\n# Deploy: Step 1\n",
+        );
+        let anchors = headings
+            .iter()
+            .map(|heading| heading.anchor.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            anchors,
+            [
+                "setup",
+                "deploy-step-1",
+                "notes",
+                "rollback",
+                "notes-1",
+                "deploy-step-1-1"
+            ]
+        );
+
+        let tree = outline_tree(headings, Some("https://acme.kibe.la/notes/1"));
+        let shape = tree
+            .iter()
+            .map(|node| {
+                let children = node
+                    .children
+                    .iter()
+                    .map(|child| format!("{}({})", child.heading.text, child.children.len()))
+                    .collect::<Vec<_>>();
+                format!("{}[{}]", node.heading.text, children.join(","))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shape,
+            [
+                "Setup[]",
+                "Deploy: Step 1[Notes(0),Rollback(1)]",
+                "Deploy: Step 1[]"
+            ]
+        );
+        assert_eq!(
+            tree[1].children[1].children[0].url.as_deref(),
+            Some("https://acme.kibe.la/notes/1#notes-1")
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use super::fenced_lines;
+
+/// Shifts ATX headings so the shallowest one is `#`, and pulls in headings
+/// that skip levels (`#` followed by `###` becomes `#`, `##`).
+pub fn apply(content: &str) -> (String, usize) {
+    let lines = fenced_lines(content);
+    let levels = lines
+        .iter()
+        .map(|(line, code)| if *code { None } else { heading_level(line) })
+        .collect::<Vec<_>>();
+    let Some(shallowest) = levels.iter().flatten().min().copied() else {
+        return (content.to_string(), 0);
+    };
+
+    let (mut previous, mut changed) = (0, 0);
+    let output = lines
+        .iter()
+        .zip(&levels)
+        .map(|((line, _), level)| {
+            let Some(level) = *level else {
+                return (*line).to_string();
+            };
+            let normalized = (level - shallowest + 1).min(previous + 1);
+            previous = normalized;
+            if normalized == level {
+                return (*line).to_string();
+            }
+            changed += 1;
+            let trimmed = line.trim_start();
+            format!("{}{}", "#".repeat(normalized), &trimmed[level..])
+        })
+        .collect::<Vec<_>>();
+    (output.join("\n"), changed)
+}
+
+/// `#`..`######` followed by a space (or nothing), after at most three spaces.
+pub(super) fn heading_level(line: &str) -> Option<usize> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let trimmed = &line[indent..];
+    let level = trimmed.chars().take_while(|ch| *ch == '#').count();
+    let rest = &trimmed[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])))
+        .then_some(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+
+    #[test]
+    fn shifts_and_closes_level_gaps_outside_code() {
+        let (content, changed) =
+            apply("## Intro\n#### Detail\n```\n## not a heading\n```\n### Next\n#tag");
+        assert_eq!(
+            content,
+            "# Intro\n## Detail\n```\n## not a heading\n```\n## Next\n#tag"
+        );
+        assert_eq!(changed, 3);
+        assert_eq!(apply("plain text"), ("plain text".to_string(), 0));
+    }
+}
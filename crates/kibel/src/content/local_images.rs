@@ -0,0 +1,113 @@
+use super::fenced_lines;
+use std::collections::HashMap;
+
+/// Uploads a local image path and returns the URL to link instead, or `None`
+/// when the path is not a local file.
+pub type Upload<'a> = dyn FnMut(&str) -> Result<Option<String>, String> + 'a;
+
+/// Rewrites `![alt](path)` targets that point at local files to uploaded
+/// attachments; each distinct path is uploaded once.
+pub fn apply(content: &str, upload: &mut Upload<'_>) -> Result<(String, usize), String> {
+    let mut uploaded = HashMap::<String, Option<String>>::new();
+    let mut changed = 0;
+    let mut output = Vec::new();
+    for (line, code) in fenced_lines(content) {
+        if code || !line.contains("![") {
+            output.push(line.to_string());
+            continue;
+        }
+        let mut rewritten = String::with_capacity(line.len());
+        let mut rest = line;
+        while let Some(start) = rest.find("![") {
+            let Some((target, target_start, target_end)) = image_target(&rest[start..]) else {
+                rewritten.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                continue;
+            };
+            let replacement = if is_local(target) {
+                match uploaded.get(target) {
+                    Some(url) => url.clone(),
+                    None => {
+                        let url = upload(target)?;
+                        uploaded.insert(target.to_string(), url.clone());
+                        url
+                    }
+                }
+            } else {
+                None
+            };
+            rewritten.push_str(&rest[..start + target_start]);
+            match replacement {
+                Some(url) => {
+                    changed += 1;
+                    rewritten.push_str(&url);
+                }
+                None => rewritten.push_str(target),
+            }
+            rest = &rest[start + target_end..];
+        }
+        rewritten.push_str(rest);
+        output.push(rewritten);
+    }
+    Ok((output.join("\n"), changed))
+}
+
+/// The target of `![alt](target "title")` at the start of `input`, with its
+/// byte range; angle brackets and the optional title are not part of it.
+fn image_target(input: &str) -> Option<(&str, usize, usize)> {
+    let open = input.find("](")? + 2;
+    if input[2..open - 2].contains('\n') {
+        return None;
+    }
+    let close = input[open..].find(')')? + open;
+    let raw = &input[open..close];
+    let leading = raw.len() - raw.trim_start().len();
+    let trimmed = raw.trim_start();
+    let (start, target) = match trimmed.strip_prefix('<') {
+        Some(inner) => (open + leading + 1, &inner[..inner.find('>')?]),
+        None => (
+            open + leading,
+            trimmed.split([' ', '\t']).next().unwrap_or_default(),
+        ),
+    };
+    Some((target, start, start + target.len()))
+}
+
+/// Anything without a URL scheme, fragment, or root-relative Kibela path.
+fn is_local(target: &str) -> bool {
+    !target.is_empty()
+        && !target.contains("://")
+        && !target.starts_with("data:")
+        && !target.starts_with('#')
+        && !target.starts_with("/attachments/")
+        && !target.starts_with("//")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+
+    #[test]
+    fn uploads_each_local_image_once() {
+        let mut calls = Vec::new();
+        let mut upload = |path: &str| {
+            calls.push(path.to_string());
+            Ok((path != "missing.png")
+                .then(|| format!("https://acme.kibe.la/attachments/{}", calls.len())))
+        };
+        let (content, changed) = apply(
+            "![a](img/a.png) ![b](<img/a.png> \"t\") ![c](https://x/c.png)\n![d](missing.png)\n```\n![e](e.png)\n```",
+            &mut upload,
+        )
+        .expect("rewrite");
+        assert_eq!(
+            content,
+            "![a](https://acme.kibe.la/attachments/1) ![b](<https://acme.kibe.la/attachments/1> \"t\") ![c](https://x/c.png)\n![d](missing.png)\n```\n![e](e.png)\n```"
+        );
+        assert_eq!(changed, 2);
+        assert_eq!(calls, vec!["img/a.png", "missing.png"]);
+
+        let mut failing = |_: &str| Err("boom".to_string());
+        assert_eq!(apply("![x](x.png)", &mut failing), Err("boom".to_string()));
+    }
+}
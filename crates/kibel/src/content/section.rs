@@ -0,0 +1,154 @@
+use super::analysis::{outline, Heading};
+use crate::cli::SectionMode;
+
+/// Why `--section` did not pick exactly one heading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SectionError {
+    Missing(String),
+    Ambiguous(String),
+}
+
+/// Rewrites the body of one heading's section: the lines after the heading up
+/// to the next heading of the same or a shallower level, subsections
+/// included.
+///
+/// `selector` is `## Deployment` (text and level), `Deployment` (text at any
+/// level), or the heading's anchor. Returns the new content and the matched
+/// heading.
+pub fn edit_section(
+    content: &str,
+    selector: &str,
+    text: &str,
+    mode: SectionMode,
+) -> Result<(String, Heading), SectionError> {
+    let headings = outline(content);
+    let index = find_heading(&headings, selector)?;
+    let heading = &headings[index];
+    let end = headings[index + 1..]
+        .iter()
+        .find(|next| next.level <= heading.level)
+        .map(|next| next.line - 1);
+
+    let (body, trailing_newline) = match content.strip_suffix('\n') {
+        Some(body) => (body, true),
+        None => (content, false),
+    };
+    let lines = body.split('\n').collect::<Vec<_>>();
+    let start = heading.line;
+    let stop = end.unwrap_or(lines.len());
+    let addition = text.trim_end_matches('\n');
+
+    let mut out = lines[..start].to_vec();
+    let mut kept = match mode {
+        SectionMode::Replace => Vec::new(),
+        SectionMode::Append => lines[start..stop].to_vec(),
+    };
+    while kept.last().is_some_and(|line| line.trim().is_empty()) {
+        kept.pop();
+    }
+    if kept.is_empty() && !addition.is_empty() {
+        out.push("");
+    }
+    out.extend(kept);
+    if !addition.is_empty() {
+        out.extend(addition.split('\n'));
+    }
+    if end.is_some() {
+        out.push("");
+    }
+    out.extend(&lines[stop..]);
+
+    let mut edited = out.join("\n");
+    if trailing_newline {
+        edited.push('\n');
+    }
+    Ok((edited, heading.clone()))
+}
+
+fn find_heading(headings: &[Heading], selector: &str) -> Result<usize, SectionError> {
+    let selector = selector.trim();
+    let hashes = selector.chars().take_while(|c| *c == '#').count();
+    let (level, text) = if hashes > 0 && selector[hashes..].starts_with([' ', '\t']) {
+        (Some(hashes), selector[hashes..].trim())
+    } else {
+        (None, selector)
+    };
+    let matches = headings
+        .iter()
+        .enumerate()
+        .filter(|(_, heading)| {
+            level.is_none_or(|level| heading.level == level)
+                && (heading.text == text || (level.is_none() && heading.anchor == text))
+        })
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(SectionError::Missing(format!(
+            "no heading matches `{selector}`"
+        ))),
+        _ => Err(SectionError::Ambiguous(format!(
+            "`{selector}` matches {} headings (lines {}); add the `#` level or use the anchor from `note outline`",
+            matches.len(),
+            matches
+                .iter()
+                .map(|index| headings[*index].line.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_section, SectionError};
+    use crate::cli::SectionMode;
+
+    const RUNBOOK: &str = "# Runbook\n\nIntro\n\n## Deploy\n\n- step 1\n\n### Verify\n\ncurl\n\n## Rollback\n\nrevert\n";
+
+    #[test]
+    fn replace_and_append_stay_inside_the_section() {
+        let (edited, heading) =
+            edit_section(RUNBOOK, "## Deploy", "- step A\n", SectionMode::Replace)
+                .expect("section should match");
+        assert_eq!(heading.line, 5);
+        assert_eq!(
+            edited,
+            "# Runbook\n\nIntro\n\n## Deploy\n\n- step A\n\n## Rollback\n\nrevert\n"
+        );
+
+        let (edited, _) = edit_section(RUNBOOK, "verify", "curl -f", SectionMode::Append)
+            .expect("anchor should match");
+        assert_eq!(
+            edited,
+            "# Runbook\n\nIntro\n\n## Deploy\n\n- step 1\n\n### Verify\n\ncurl\ncurl -f\n\n## Rollback\n\nrevert\n"
+        );
+
+        let (edited, _) = edit_section(
+            RUNBOOK,
+            "Rollback",
+            "- revert\n- notify",
+            SectionMode::Replace,
+        )
+        .expect("last section should match");
+        assert!(edited.ends_with("## Rollback\n\n- revert\n- notify\n"));
+    }
+
+    #[test]
+    fn selectors_must_match_exactly_one_heading() {
+        let content = "# A\n## Notes\n# B\n## Notes\n";
+        let Err(SectionError::Ambiguous(error)) =
+            edit_section(content, "Notes", "x", SectionMode::Append)
+        else {
+            panic!("two headings should match");
+        };
+        assert!(error.contains("lines 2, 4"), "{error}");
+        assert!(edit_section(content, "notes-1", "x", SectionMode::Append).is_ok());
+        assert_eq!(
+            edit_section(content, "### Notes", "x", SectionMode::Append),
+            Err(SectionError::Missing(
+                "no heading matches `### Notes`".to_string()
+            ))
+        );
+    }
+}
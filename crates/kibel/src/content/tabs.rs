@@ -0,0 +1,44 @@
+use super::fenced_lines;
+
+const TAB_WIDTH: usize = 4;
+
+/// Expands tabs to spaces at 4-column tab stops, leaving fenced code (where
+/// tabs may be significant) alone.
+pub fn apply(content: &str) -> (String, usize) {
+    let mut changed = 0;
+    let output = fenced_lines(content)
+        .into_iter()
+        .map(|(line, code)| {
+            if code || !line.contains('\t') {
+                return line.to_string();
+            }
+            changed += 1;
+            let mut expanded = String::with_capacity(line.len() + TAB_WIDTH);
+            let mut column = 0;
+            for ch in line.chars() {
+                if ch == '\t' {
+                    let spaces = TAB_WIDTH - column % TAB_WIDTH;
+                    expanded.push_str(&" ".repeat(spaces));
+                    column += spaces;
+                } else {
+                    expanded.push(ch);
+                    column += 1;
+                }
+            }
+            expanded
+        })
+        .collect::<Vec<_>>();
+    (output.join("\n"), changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+
+    #[test]
+    fn expands_to_tab_stops_outside_fences() {
+        let (content, changed) = apply("\t- item\nab\tc\n```\n\tcode\n```");
+        assert_eq!(content, "    - item\nab  c\n```\n\tcode\n```");
+        assert_eq!(changed, 2);
+    }
+}
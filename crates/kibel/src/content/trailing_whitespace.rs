@@ -0,0 +1,45 @@
+/// Strips trailing spaces, tabs, and `\r` from every line, keeping two-space
+/// hard line breaks that precede more text, and collapses trailing blank
+/// lines to one final newline.
+pub fn apply(content: &str) -> (String, usize) {
+    let source = content.split('\n').collect::<Vec<_>>();
+    let mut changed = 0;
+    let mut lines = source
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let trimmed = line.trim_end_matches([' ', '\t', '\r']);
+            let hard_break = !trimmed.is_empty()
+                && line.strip_prefix(trimmed) == Some("  ")
+                && source
+                    .get(index + 1)
+                    .is_some_and(|next| !next.trim().is_empty());
+            if hard_break || trimmed.len() == line.len() {
+                return (*line).to_string();
+            }
+            changed += 1;
+            trimmed.to_string()
+        })
+        .collect::<Vec<_>>();
+    let ends_with_newline = lines.len() > 1 && lines.last().is_some_and(String::is_empty);
+    while lines.len() > 1 && lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    if ends_with_newline {
+        lines.push(String::new());
+    }
+    (lines.join("\n"), changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+
+    #[test]
+    fn trims_lines_but_keeps_hard_breaks() {
+        let (content, changed) = apply("a \t\nhard  \nwin\r\n   \nend  \n\n\n");
+        assert_eq!(content, "a\nhard  \nwin\n\nend\n");
+        assert_eq!(changed, 4);
+        assert_eq!(apply("no newline"), ("no newline".to_string(), 0));
+    }
+}
@@ -0,0 +1,122 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// How many recent comments `comment create --suppress-duplicate` inspects.
+pub const RECENT_COMMENT_LIMIT: u32 = 50;
+
+/// Hashes comment content after trimming, collapsing whitespace runs, and
+/// lowercasing, so retried pipelines that reflow or re-case a message still
+/// match the earlier post.
+pub fn fingerprint(content: &str) -> String {
+    let normalized = content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    Sha256::digest(normalized.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Returns the newest comment in `comments` written by `account` at or after
+/// `cutoff` (unix seconds) whose content has the same [`fingerprint`].
+pub fn find_duplicate<'a>(
+    comments: &'a [Value],
+    account: &str,
+    content: &str,
+    cutoff: i64,
+) -> Option<&'a Value> {
+    let expected = fingerprint(content);
+    comments.iter().rev().find(|comment| {
+        comment.pointer("/author/account").and_then(Value::as_str) == Some(account)
+            && comment
+                .get("publishedAt")
+                .and_then(Value::as_str)
+                .and_then(parse_timestamp)
+                .is_some_and(|published| published >= cutoff)
+            && comment
+                .get("content")
+                .and_then(Value::as_str)
+                .is_some_and(|existing| fingerprint(existing) == expected)
+    })
+}
+
+/// Parses an RFC 3339 timestamp (`2026-02-23T09:00:00+09:00`) into unix
+/// seconds. Fractional seconds are ignored.
+pub fn parse_timestamp(raw: &str) -> Option<i64> {
+    let raw = raw.trim();
+    let (date, time) = raw.split_once(['T', 't', ' '])?;
+    let mut date_parts = date.splitn(3, '-');
+    let year = date_parts.next()?.parse::<i64>().ok()?;
+    let month = date_parts.next()?.parse::<i64>().ok()?;
+    let day = date_parts.next()?.parse::<i64>().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (clock, offset_secs) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let split = time.rfind(['+', '-'])?;
+        let (clock, offset) = time.split_at(split);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let offset = hours.parse::<i64>().ok()? * 3_600 + minutes.parse::<i64>().ok()? * 60;
+        (clock, sign * offset)
+    };
+    let clock = clock.split('.').next()?;
+    let mut clock_parts = clock.splitn(3, ':');
+    let hour = clock_parts.next()?.parse::<i64>().ok()?;
+    let minute = clock_parts.next()?.parse::<i64>().ok()?;
+    let second = clock_parts.next()?.parse::<i64>().ok()?;
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second - offset_secs)
+}
+
+/// Converts a proleptic Gregorian date to days since 1970-01-01.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_duplicate, fingerprint, parse_timestamp};
+    use serde_json::json;
+
+    #[test]
+    fn parse_timestamp_applies_offsets() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            parse_timestamp("2026-02-23T09:00:00+09:00"),
+            parse_timestamp("2026-02-23T00:00:00Z")
+        );
+        assert_eq!(
+            parse_timestamp("2026-02-23T00:00:00.250Z"),
+            Some(1_771_804_800)
+        );
+        assert_eq!(parse_timestamp("2026-02-23"), None);
+    }
+
+    #[test]
+    fn find_duplicate_matches_normalized_content_in_window() {
+        assert_eq!(fingerprint("Build  passed\n"), fingerprint("build passed"));
+        let comments = vec![
+            json!({"id": "C1", "content": "Build passed", "publishedAt": "2026-02-20T00:00:00Z", "author": {"account": "bot"}}),
+            json!({"id": "C2", "content": "build\npassed", "publishedAt": "2026-02-23T00:00:00Z", "author": {"account": "bot"}}),
+            json!({"id": "C3", "content": "build passed", "publishedAt": "2026-02-23T00:00:00Z", "author": {"account": "alice"}}),
+        ];
+        let cutoff = parse_timestamp("2026-02-22T00:00:00Z").expect("cutoff");
+        let found = find_duplicate(&comments, "bot", "BUILD passed", cutoff);
+        assert_eq!(found.and_then(|c| c["id"].as_str()), Some("C2"));
+        assert!(find_duplicate(&comments, "bot", "build failed", cutoff).is_none());
+        assert!(find_duplicate(&comments, "carol", "build passed", 0).is_none());
+    }
+}
@@ -0,0 +1,244 @@
+use serde::Serialize;
+
+/// Above this many line-pair comparisons the LCS table is skipped and the
+/// whole remaining range is reported as replaced.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// One `@@` block of a unified diff; `lines` keep their ` ` / `-` / `+`
+/// prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<String>,
+}
+
+impl Hunk {
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start, self.old_lines, self.new_start, self.new_lines
+        )
+    }
+}
+
+/// Renders a unified diff from `old` to `new`, or `None` when they are equal.
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_label: &str,
+    new_label: &str,
+    context: usize,
+) -> Option<String> {
+    if old == new {
+        return None;
+    }
+    let hunks = diff_hunks(old, new, context);
+    if hunks.is_empty() {
+        // Only line endings / trailing newline differ.
+        return Some(format!(
+            "--- {old_label}\n+++ {new_label}\n@@ whitespace-only change (line endings or trailing newline) @@\n"
+        ));
+    }
+
+    let mut out = format!("--- {old_label}\n+++ {new_label}\n");
+    for hunk in hunks {
+        out.push_str(&hunk.header());
+        out.push('\n');
+        for line in &hunk.lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    Some(out)
+}
+
+/// The changed line ranges from `old` to `new` with `context` lines around
+/// each; empty when the lines are equal (even if line endings differ).
+pub fn diff_hunks(old: &str, new: &str, context: usize) -> Vec<Hunk> {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    let ops = diff_ops(&old_lines, &new_lines);
+    let changed = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Equal)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let mut ranges = Vec::<(usize, usize)>::new();
+    for index in changed {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let mut hunks = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        let (mut old_line, mut new_line) = (1, 1);
+        for (op, _) in &ops[..start] {
+            match op {
+                Op::Equal => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                Op::Delete => old_line += 1,
+                Op::Insert => new_line += 1,
+            }
+        }
+        let slice = &ops[start..end];
+        hunks.push(Hunk {
+            old_start: old_line,
+            old_lines: slice.iter().filter(|(op, _)| *op != Op::Insert).count(),
+            new_start: new_line,
+            new_lines: slice.iter().filter(|(op, _)| *op != Op::Delete).count(),
+            lines: slice
+                .iter()
+                .map(|(op, line)| {
+                    let prefix = match op {
+                        Op::Equal => ' ',
+                        Op::Delete => '-',
+                        Op::Insert => '+',
+                    };
+                    format!("{prefix}{line}")
+                })
+                .collect(),
+        });
+    }
+    hunks
+}
+
+/// `diff` with ANSI colors: file headers bold, hunk headers cyan, removed
+/// lines red, added lines green.
+pub fn colorize(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let color = if line.starts_with("---") || line.starts_with("+++") {
+                "1"
+            } else if line.starts_with("@@") {
+                "36"
+            } else if line.starts_with('-') {
+                "31"
+            } else if line.starts_with('+') {
+                "32"
+            } else {
+                return format!("{line}\n");
+            };
+            format!("\u{1b}[{color}m{line}\u{1b}[0m\n")
+        })
+        .collect()
+}
+
+fn diff_ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(left, right)| left == right)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(left, right)| left == right)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops = old[..prefix]
+        .iter()
+        .map(|line| (Op::Equal, *line))
+        .collect::<Vec<_>>();
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_LCS_CELLS {
+        ops.extend(old_mid.iter().map(|line| (Op::Delete, *line)));
+        ops.extend(new_mid.iter().map(|line| (Op::Insert, *line)));
+    } else {
+        ops.extend(lcs_ops(old_mid, new_mid));
+    }
+    ops.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| (Op::Equal, *line)),
+    );
+    ops
+}
+
+fn lcs_ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let width = new.len() + 1;
+    let mut table = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i * width + j] = if old[i] == new[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push((Op::Equal, old[i]));
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            ops.push((Op::Delete, old[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| (Op::Delete, *line)));
+    ops.extend(new[j..].iter().map(|line| (Op::Insert, *line)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{colorize, diff_hunks, unified_diff};
+
+    #[test]
+    fn unified_diff_reports_changed_lines_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\n";
+        let new = "a\nb\nC\nd\ne\nf\ng\n";
+        let diff = unified_diff(old, new, "kibela", "repo", 1).expect("texts differ");
+        assert_eq!(
+            diff,
+            "--- kibela\n+++ repo\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n@@ -6,1 +6,2 @@\n f\n+g\n"
+        );
+    }
+
+    #[test]
+    fn unified_diff_returns_none_for_equal_text() {
+        assert_eq!(unified_diff("a\n", "a\n", "x", "y", 3), None);
+        assert!(unified_diff("a\n", "a", "x", "y", 3)
+            .is_some_and(|diff| diff.contains("whitespace-only")));
+    }
+
+    #[test]
+    fn diff_hunks_carry_ranges_and_colorize_marks_lines() {
+        let hunks = diff_hunks("a\nb\n", "a\nB\n", 0);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].header(), "@@ -2,1 +2,1 @@");
+        assert_eq!(hunks[0].lines, vec!["-b", "+B"]);
+        assert!(diff_hunks("a\r\n", "a\n", 3).is_empty());
+
+        assert_eq!(
+            colorize("@@ -1 +1 @@\n-b\n+B\n x\n"),
+            "\u{1b}[36m@@ -1 +1 @@\u{1b}[0m\n\u{1b}[31m-b\u{1b}[0m\n\u{1b}[32m+B\u{1b}[0m\n x\n"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::cli::DigestGroupBy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One note in a digest, flattened from a feed section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DigestEntry {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    /// Feed date, `YYYY-MM-DD`.
+    pub date: String,
+    /// Full name of the note's folder (`Ops/Runbooks`), when it has one.
+    pub folder: Option<String>,
+    /// Author account, when known.
+    pub author: Option<String>,
+}
+
+impl DigestEntry {
+    fn key(&self, by: DigestGroupBy) -> Option<&str> {
+        match by {
+            DigestGroupBy::Folder => self.folder.as_deref(),
+            DigestGroupBy::Author => self.author.as_deref(),
+        }
+    }
+}
+
+/// Entries sectioned by folder or author, sections by name and entries
+/// newest first. Entries without the key form a last section.
+pub fn sections(entries: &[DigestEntry], by: DigestGroupBy) -> Vec<(String, Vec<&DigestEntry>)> {
+    let mut keyed = BTreeMap::<&str, Vec<&DigestEntry>>::new();
+    let mut rest = Vec::new();
+    for entry in entries {
+        match entry.key(by).filter(|key| !key.is_empty()) {
+            Some(key) => keyed.entry(key).or_default().push(entry),
+            None => rest.push(entry),
+        }
+    }
+    let mut sections = keyed
+        .into_iter()
+        .map(|(key, entries)| (key.to_string(), entries))
+        .collect::<Vec<_>>();
+    if !rest.is_empty() {
+        let label = match by {
+            DigestGroupBy::Folder => "No folder",
+            DigestGroupBy::Author => "Unknown author",
+        };
+        sections.push((label.to_string(), rest));
+    }
+    for (_, entries) in &mut sections {
+        entries.sort_by(|left, right| {
+            right
+                .date
+                .cmp(&left.date)
+                .then(left.title.cmp(&right.title))
+        });
+    }
+    sections
+}
+
+/// The digest note body: a summary line, then one `##` section per folder
+/// or author listing `- [title](url) (date, other key)`.
+pub fn render(entries: &[DigestEntry], by: DigestGroupBy, since: &str, until: &str) -> String {
+    let mut out = format!(
+        "{} note(s) created or updated from {since} to {until}.\n",
+        entries.len()
+    );
+    for (heading, entries) in sections(entries, by) {
+        out.push_str(&format!("\n## {heading}\n\n"));
+        for entry in entries {
+            let other = match by {
+                DigestGroupBy::Folder => entry.author.as_ref().map(|author| format!("@{author}")),
+                DigestGroupBy::Author => entry.folder.clone(),
+            };
+            let detail = other.map_or_else(
+                || entry.date.clone(),
+                |other| format!("{}, {other}", entry.date),
+            );
+            out.push_str(&format!(
+                "- [{}]({}) ({detail})\n",
+                entry.title.replace(['[', ']'], ""),
+                entry.url
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, DigestEntry};
+    use crate::cli::DigestGroupBy;
+
+    fn entry(id: &str, date: &str, folder: Option<&str>, author: Option<&str>) -> DigestEntry {
+        DigestEntry {
+            id: id.to_string(),
+            title: format!("Note {id}"),
+            url: format!("/notes/{id}"),
+            date: date.to_string(),
+            folder: folder.map(str::to_string),
+            author: author.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn render_sections_by_folder_or_author_newest_first() {
+        let entries = [
+            entry("1", "2026-02-20", Some("Ops/Runbooks"), Some("alice")),
+            entry("2", "2026-02-22", None, Some("bob")),
+            entry("3", "2026-02-23", Some("Ops/Runbooks"), None),
+            entry("4", "2026-02-21", Some("Design"), Some("alice")),
+        ];
+        assert_eq!(
+            render(&entries, DigestGroupBy::Folder, "2026-02-17", "2026-02-24"),
+            "4 note(s) created or updated from 2026-02-17 to 2026-02-24.\n\
+             \n## Design\n\n- [Note 4](/notes/4) (2026-02-21, @alice)\n\
+             \n## Ops/Runbooks\n\n- [Note 3](/notes/3) (2026-02-23)\n\
+             - [Note 1](/notes/1) (2026-02-20, @alice)\n\
+             \n## No folder\n\n- [Note 2](/notes/2) (2026-02-22, @bob)\n"
+        );
+        let by_author = render(&entries, DigestGroupBy::Author, "2026-02-17", "2026-02-24");
+        assert!(by_author.contains(
+            "## alice\n\n- [Note 4](/notes/4) (2026-02-21, Design)\n\
+             - [Note 1](/notes/1) (2026-02-20, Ops/Runbooks)\n"
+        ));
+        assert!(by_author
+            .ends_with("## Unknown author\n\n- [Note 3](/notes/3) (2026-02-23, Ops/Runbooks)\n"));
+    }
+}
@@ -0,0 +1,110 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `--mapping workspace` resolves to this file in the current directory.
+pub const WORKSPACE_MAPPING_FILE: &str = "kibel-docs.json";
+
+/// One `note <-> file` pair from a docs mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocsMappingEntry {
+    pub note: String,
+    pub file: PathBuf,
+    pub vars: BTreeMap<String, String>,
+}
+
+pub fn resolve_mapping_path(raw: &str) -> PathBuf {
+    if raw == "workspace" {
+        PathBuf::from(WORKSPACE_MAPPING_FILE)
+    } else {
+        PathBuf::from(raw)
+    }
+}
+
+/// Loads `{"vars": {...}, "entries": [{"note", "file", "vars"}]}`.
+///
+/// Files are resolved relative to the mapping file; entry vars override the
+/// top-level ones.
+pub fn load_docs_mapping(path: &Path) -> Result<Vec<DocsMappingEntry>, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|error| format!("failed to read mapping {}: {error}", path.display()))?;
+    let value = serde_json::from_str::<Value>(&raw)
+        .map_err(|error| format!("mapping {} is not valid JSON: {error}", path.display()))?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let shared = string_map(value.get("vars"), "vars")?;
+    let items = value
+        .get("entries")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "mapping must contain an `entries` array".to_string())?;
+    if items.is_empty() {
+        return Err("mapping has no entries".to_string());
+    }
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let field = |key: &str| {
+                item.get(key)
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .ok_or_else(|| format!("entries[{index}]: missing string field `{key}`"))
+            };
+            let mut vars = shared.clone();
+            vars.extend(string_map(
+                item.get("vars"),
+                &format!("entries[{index}].vars"),
+            )?);
+            Ok(DocsMappingEntry {
+                note: field("note")?.to_string(),
+                file: base.join(field("file")?),
+                vars,
+            })
+        })
+        .collect()
+}
+
+fn string_map(value: Option<&Value>, context: &str) -> Result<BTreeMap<String, String>, String> {
+    let Some(value) = value else {
+        return Ok(BTreeMap::new());
+    };
+    let object = value
+        .as_object()
+        .ok_or_else(|| format!("{context} must be an object"))?;
+    object
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(text) => Ok((key.clone(), text.clone())),
+            Value::Number(_) | Value::Bool(_) => Ok((key.clone(), value.to_string())),
+            _ => Err(format!("{context}.{key} must be a string")),
+        })
+        .collect()
+}
+
+/// Normalizes line endings and trailing whitespace before comparing.
+pub fn normalize_for_compare(text: &str) -> String {
+    let mut lines = text
+        .replace("\r\n", "\n")
+        .lines()
+        .map(str::trim_end)
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_for_compare;
+
+    #[test]
+    fn normalize_for_compare_ignores_line_endings_and_trailing_space() {
+        assert_eq!(normalize_for_compare("a  \r\nb\n\n\n"), "a\nb\n");
+    }
+}
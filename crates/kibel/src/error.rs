@@ -13,6 +13,9 @@ pub enum ErrorCode {
     ThrottledRetryable,
     ThrottledRewriteRequired,
     TransportError,
+    ReadOnlyViolation,
+    PolicyViolation,
+    SecretDetected,
     UnknownError,
 }
 
@@ -27,6 +30,9 @@ impl ErrorCode {
             Self::ThrottledRetryable => "THROTTLED_RETRYABLE",
             Self::ThrottledRewriteRequired => "THROTTLED_REWRITE_REQUIRED",
             Self::TransportError => "TRANSPORT_ERROR",
+            Self::ReadOnlyViolation => "READ_ONLY_VIOLATION",
+            Self::PolicyViolation => "POLICY_VIOLATION",
+            Self::SecretDetected => "SECRET_DETECTED",
             Self::UnknownError => "UNKNOWN_ERROR",
         }
     }
@@ -39,6 +45,7 @@ impl ErrorCode {
             Self::PreconditionFailed | Self::IdempotencyConflict => 5,
             Self::ThrottledRetryable | Self::TransportError => 6,
             Self::ThrottledRewriteRequired => 7,
+            Self::ReadOnlyViolation | Self::PolicyViolation | Self::SecretDetected => 8,
             Self::UnknownError => 10,
         }
     }
@@ -80,22 +87,86 @@ impl CliError {
         self.details = details;
         self
     }
+
+    /// Adds `details.<key>` when `value` is present.
+    fn with_detail(mut self, key: &str, value: Option<Value>) -> Self {
+        if let (Some(value), Some(details)) = (value, self.details.as_object_mut()) {
+            details.insert(key.to_string(), value);
+        }
+        self
+    }
+
+    /// The `X-Request-Id` of the failed request, for errors from the server.
+    pub fn request_id(&self) -> Option<&str> {
+        self.details.get("request_id").and_then(Value::as_str)
+    }
 }
 
 impl From<KibelClientError> for CliError {
     fn from(value: KibelClientError) -> Self {
         match value {
             KibelClientError::InputInvalid(message) => Self::new(ErrorCode::InputInvalid, message),
-            KibelClientError::Api { code, message } => {
-                let mapped = map_graphql_error(&code);
-                Self::new(mapped, message).with_details(json!({ "graphql_code": code }))
+            KibelClientError::Api {
+                ref code,
+                ref message,
+                status_code,
+                ref path,
+                ..
+            } => {
+                // Codes without a mapping fall back to what the HTTP status says.
+                let mapped = match map_graphql_error(code) {
+                    ErrorCode::UnknownError if status_code == Some(429) => {
+                        ErrorCode::ThrottledRetryable
+                    }
+                    ErrorCode::UnknownError if value.retryable() => ErrorCode::TransportError,
+                    mapped => mapped,
+                };
+                Self::new(mapped, message.clone())
+                    .with_details(json!({ "graphql_code": code }))
+                    .with_detail("status_code", status_code.map(Value::from))
+                    .with_detail(
+                        "path",
+                        (!path.is_empty()).then(|| Value::from(path.clone())),
+                    )
+                    .with_detail("request_id", value.request_id().map(Value::from))
             }
-            KibelClientError::Transport(message) => Self::new(ErrorCode::TransportError, message),
+            KibelClientError::Transport {
+                message,
+                status_code,
+                request_id,
+            } => Self::new(ErrorCode::TransportError, message)
+                .with_detail("status_code", status_code.map(Value::from))
+                .with_detail("request_id", request_id.map(Value::from)),
+            KibelClientError::ResponseTooLarge {
+                limit,
+                received,
+                content_length,
+                request_id,
+            } => Self::new(
+                ErrorCode::TransportError,
+                format!("response body exceeds limit: {limit} bytes"),
+            )
+            .with_details(json!({
+                "limit_bytes": limit,
+                "received_bytes": received,
+                "content_length": content_length,
+            }))
+            .with_detail("request_id", request_id.map(Value::from)),
             KibelClientError::Keychain(message) => Self::new(
                 ErrorCode::AuthFailed,
                 "failed to access OS credential store",
             )
             .with_details(json!({ "cause": message })),
+            KibelClientError::ReadOnlyViolation(message) => {
+                Self::new(ErrorCode::ReadOnlyViolation, message)
+            }
+            KibelClientError::PolicyViolation(message) => {
+                Self::new(ErrorCode::PolicyViolation, message)
+            }
+            KibelClientError::TokenStore(message) => {
+                Self::new(ErrorCode::AuthFailed, "failed to access token store")
+                    .with_details(json!({ "cause": message }))
+            }
             other => Self::new(ErrorCode::UnknownError, other.to_string()),
         }
     }
@@ -103,7 +174,9 @@ impl From<KibelClientError> for CliError {
 
 #[cfg(test)]
 mod tests {
-    use super::{map_graphql_error, ErrorCode};
+    use super::{map_graphql_error, CliError, ErrorCode};
+    use kibel_client::KibelClientError;
+    use serde_json::json;
 
     #[test]
     fn graphql_error_mapping_is_stable() {
@@ -126,4 +199,46 @@ mod tests {
             assert_eq!(map_graphql_error(raw), expected, "raw code: {raw}");
         }
     }
+
+    #[test]
+    fn client_errors_keep_status_path_and_request_id_in_details() {
+        let error = CliError::from(KibelClientError::Api {
+            code: "NOT_FOUND".to_string(),
+            message: "gone".to_string(),
+            status_code: None,
+            path: vec!["note".to_string(), "folders".to_string(), "0".to_string()],
+            request_id: Some("req-1".to_string()),
+        });
+        assert_eq!(error.code, ErrorCode::NotFound);
+        assert_eq!(error.message, "gone");
+        assert_eq!(
+            error.details,
+            json!({"graphql_code": "NOT_FOUND", "path": ["note", "folders", "0"], "request_id": "req-1"})
+        );
+
+        let unmapped = |status| {
+            CliError::from(KibelClientError::Api {
+                code: "INTERNAL".to_string(),
+                message: "boom".to_string(),
+                status_code: Some(status),
+                path: Vec::new(),
+                request_id: None,
+            })
+            .code
+        };
+        assert_eq!(unmapped(429), ErrorCode::ThrottledRetryable);
+        assert_eq!(unmapped(503), ErrorCode::TransportError);
+        assert_eq!(unmapped(400), ErrorCode::UnknownError);
+
+        let error = CliError::from(KibelClientError::Transport {
+            message: "http status 502 with a non-JSON body".to_string(),
+            status_code: Some(502),
+            request_id: Some("req-2".to_string()),
+        });
+        assert_eq!(error.code, ErrorCode::TransportError);
+        assert_eq!(
+            error.details,
+            json!({"status_code": 502, "request_id": "req-2"})
+        );
+    }
 }
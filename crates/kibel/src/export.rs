@@ -0,0 +1,198 @@
+//! Layouts `note download --layout` writes a note in: where the note and its
+//! attachments go, what its front matter looks like, and how links to
+//! attachments and other notes are rewritten for the target tool.
+
+mod native;
+mod obsidian;
+mod static_site;
+
+use crate::cli::ExportLayout;
+use crate::frontmatter::FrontMatter;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// One layout. Paths are relative to the folder given to `--with-assets`.
+pub trait Exporter {
+    /// Where the note file is written.
+    fn note_path(&self, note: &ExportNote) -> PathBuf;
+
+    /// Folder downloaded attachments are written to.
+    fn assets_dir(&self, note: &ExportNote) -> PathBuf;
+
+    /// What attachment URLs are replaced with, before the file name: the
+    /// assets folder relative to the note file.
+    fn attachment_prefix(&self) -> &'static str;
+
+    /// Whether [`Exporter::rewrite_links`] needs the titles of linked notes,
+    /// which costs a lookup per linked note.
+    fn needs_link_titles(&self) -> bool {
+        false
+    }
+
+    /// Rewrites content whose attachment URLs already point at the
+    /// downloaded files into the layout's link syntax.
+    fn rewrite_links(&self, content: &str, targets: &LinkTargets) -> String;
+
+    /// The note file: front matter, then `content`.
+    fn render(&self, note: &ExportNote, content: &str) -> String;
+
+    /// Files the layout needs besides the note, e.g. section index pages.
+    /// They are written only when missing, so edits to them are kept.
+    fn scaffold(&self, _note: &ExportNote) -> Vec<(PathBuf, String)> {
+        Vec::new()
+    }
+}
+
+pub fn exporter(layout: ExportLayout) -> Box<dyn Exporter> {
+    match layout {
+        ExportLayout::Kibel => Box::new(native::Native),
+        ExportLayout::Obsidian => Box::new(obsidian::Obsidian),
+        ExportLayout::Hugo => Box::new(static_site::StaticSite::Hugo),
+        ExportLayout::Zola => Box::new(static_site::StaticSite::Zola),
+    }
+}
+
+/// A note being exported, read from a `getNoteMetadata` note object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportNote {
+    pub front: FrontMatter,
+    /// The group of the note's first folder, then that folder's path from
+    /// the top; just the first group for a note outside folders.
+    pub sections: Vec<String>,
+}
+
+impl ExportNote {
+    pub fn from_note(note: &Value) -> Self {
+        let front = FrontMatter::from_note(note);
+        let folder = note.pointer("/folders/edges/0/node");
+        let sections = match folder {
+            Some(folder) => folder
+                .pointer("/group/name")
+                .and_then(Value::as_str)
+                .into_iter()
+                .chain(
+                    folder
+                        .get("fullName")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .split('/'),
+                )
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+            None => note
+                .pointer("/groups/0/name")
+                .and_then(Value::as_str)
+                .map(|name| vec![name.to_string()])
+                .unwrap_or_default(),
+        };
+        Self { front, sections }
+    }
+
+    pub fn title(&self) -> &str {
+        self.front.title.as_deref().unwrap_or_default()
+    }
+
+    /// [`slug`] of the title, else of the note id.
+    pub fn slug(&self) -> String {
+        Some(slug(self.title()))
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| slug(&format!("note-{}", self.front.id.as_deref().unwrap_or(""))))
+    }
+}
+
+/// What note links in the content point at.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkTargets {
+    pub origin: String,
+    /// Titles of linked notes by the link as written; filled only for
+    /// layouts that ask via [`Exporter::needs_link_titles`]. A link to a
+    /// note that no longer exists has no entry.
+    pub titles: HashMap<String, String>,
+}
+
+/// Lowercases `text` and joins its runs of letters and digits, of every
+/// script, with `-`.
+pub fn slug(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Replaces root-relative note links with URLs on `origin`, for content
+/// read outside Kibela. Each token is checked on its own, so a path inside a
+/// full URL is left alone.
+fn absolute_note_links(content: &str, origin: &str) -> String {
+    let origin = origin.trim_end_matches('/');
+    let mut output = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for (start, end) in crate::links::token_spans(content) {
+        let token = &content[start..end];
+        let link = token.trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        if !link.starts_with('/') || kibel_client::NotePath::parse(link).is_err() {
+            continue;
+        }
+        output.push_str(&content[cursor..start]);
+        output.push_str(origin);
+        cursor = start;
+    }
+    output.push_str(&content[cursor..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{absolute_note_links, slug, ExportNote};
+    use serde_json::json;
+
+    #[test]
+    fn sections_come_from_the_first_folder_or_group() {
+        let note = ExportNote::from_note(&json!({
+            "id": "N1",
+            "title": "Deploy: Step 1",
+            "groups": [{ "id": "G1", "name": "Acme" }],
+            "folders": { "edges": [{ "node": {
+                "fullName": "Ops/Runbooks",
+                "group": { "id": "G1", "name": "Acme" },
+            } }] },
+        }));
+        assert_eq!(note.sections, ["Acme", "Ops", "Runbooks"]);
+        assert_eq!(note.slug(), "deploy-step-1");
+
+        let note = ExportNote::from_note(&json!({
+            "id": "N2",
+            "title": "!!",
+            "groups": [{ "id": "G1", "name": "Acme" }],
+            "folders": { "edges": [] },
+        }));
+        assert_eq!(note.sections, ["Acme"]);
+        assert_eq!(note.slug(), "note-n2");
+        assert_eq!(slug("手順 v2.0"), "手順-v2-0");
+    }
+
+    #[test]
+    fn root_relative_note_links_become_absolute() {
+        assert_eq!(
+            absolute_note_links(
+                "see /notes/1, [b](/@alice/2) and https://acme.kibe.la/notes/3",
+                "https://acme.kibe.la/"
+            ),
+            "see https://acme.kibe.la/notes/1, [b](https://acme.kibe.la/@alice/2) and https://acme.kibe.la/notes/3"
+        );
+        assert_eq!(
+            absolute_note_links(
+                "see /notes/1 and https://acme.kibe.la/notes/1",
+                "https://acme.kibe.la"
+            ),
+            "see https://acme.kibe.la/notes/1 and https://acme.kibe.la/notes/1"
+        );
+        assert_eq!(
+            absolute_note_links("a /notes/1 b /notes/2 c /notes/1", "https://acme.kibe.la"),
+            "a https://acme.kibe.la/notes/1 b https://acme.kibe.la/notes/2 c https://acme.kibe.la/notes/1"
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use super::{ExportNote, Exporter, LinkTargets};
+use crate::frontmatter;
+use std::path::PathBuf;
+
+/// `note.md` and `assets/`, the file `note update --from-file` reads back.
+pub struct Native;
+
+impl Exporter for Native {
+    fn note_path(&self, _note: &ExportNote) -> PathBuf {
+        PathBuf::from("note.md")
+    }
+
+    fn assets_dir(&self, _note: &ExportNote) -> PathBuf {
+        PathBuf::from("assets")
+    }
+
+    fn attachment_prefix(&self) -> &'static str {
+        "assets/"
+    }
+
+    fn rewrite_links(&self, content: &str, _targets: &LinkTargets) -> String {
+        content.to_string()
+    }
+
+    fn render(&self, note: &ExportNote, content: &str) -> String {
+        frontmatter::render(&note.front, content)
+    }
+}
@@ -0,0 +1,228 @@
+use super::{absolute_note_links, ExportNote, Exporter, LinkTargets};
+use crate::{frontmatter, links};
+use std::path::PathBuf;
+
+/// Attachment folder, next to the note as Obsidian's "in subfolder under
+/// current folder" setting puts it.
+const ATTACHMENTS: &str = "attachments";
+
+/// Characters Obsidian does not allow in note names, since they are either
+/// invalid in file names or have a meaning inside `[[...]]`.
+const FORBIDDEN: &[char] = &[
+    '\\', '/', ':', '*', '?', '"', '<', '>', '|', '#', '^', '[', ']',
+];
+
+/// A vault: a folder per group and folder, the note named by its title, and
+/// links as `[[wikilinks]]`.
+pub struct Obsidian;
+
+impl Obsidian {
+    fn folder(note: &ExportNote) -> PathBuf {
+        note.sections
+            .iter()
+            .map(|section| note_name(section))
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+}
+
+impl Exporter for Obsidian {
+    fn note_path(&self, note: &ExportNote) -> PathBuf {
+        let name = Some(note_name(note.title()))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| note.slug());
+        Self::folder(note).join(format!("{name}.md"))
+    }
+
+    fn assets_dir(&self, note: &ExportNote) -> PathBuf {
+        Self::folder(note).join(ATTACHMENTS)
+    }
+
+    fn attachment_prefix(&self) -> &'static str {
+        "attachments/"
+    }
+
+    fn needs_link_titles(&self) -> bool {
+        true
+    }
+
+    /// Markdown links to attachments become embeds (`![[a.png]]`) or
+    /// `[[a.pdf|text]]`, and links to notes with a title become
+    /// `[[Title]]`; other note links are made absolute on the origin, since
+    /// the vault has no file for them.
+    fn rewrite_links(&self, content: &str, targets: &LinkTargets) -> String {
+        let prefix = self.attachment_prefix();
+        let mut linked = String::with_capacity(content.len());
+        let mut cursor = 0;
+        for link in markdown_links(content) {
+            let replacement = if let Some(name) = link.target.strip_prefix(prefix) {
+                wikilink(link.image, name, link.text)
+            } else if let Some(title) = targets.titles.get(link.target) {
+                wikilink(false, &note_name(title), link.text)
+            } else {
+                continue;
+            };
+            linked.push_str(&content[cursor..link.start]);
+            linked.push_str(&replacement);
+            cursor = link.end;
+        }
+        linked.push_str(&content[cursor..]);
+
+        let mut output = String::with_capacity(linked.len());
+        let mut cursor = 0;
+        for (start, end) in links::token_spans(&linked) {
+            let token = linked[start..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            // Attribute values and autolinks keep a URL.
+            let quoted = linked[..start].ends_with(['"', '\'', '=', '<']);
+            let Some(title) = targets.titles.get(token).filter(|_| !quoted) else {
+                continue;
+            };
+            output.push_str(&linked[cursor..start]);
+            output.push_str(&wikilink(false, &note_name(title), ""));
+            cursor = start + token.len();
+        }
+        output.push_str(&linked[cursor..]);
+        absolute_note_links(&output, &targets.origin)
+    }
+
+    /// The same front matter as the `kibel` layout, which Obsidian shows as
+    /// properties, so the file still works with `note diff` and
+    /// `note update --from-file`.
+    fn render(&self, note: &ExportNote, content: &str) -> String {
+        frontmatter::render(&note.front, content)
+    }
+}
+
+/// `title` without the characters in [`FORBIDDEN`], runs of whitespace, or
+/// leading dots (which would hide the file).
+fn note_name(title: &str) -> String {
+    let replaced = title
+        .chars()
+        .map(|c| {
+            if FORBIDDEN.contains(&c) || c.is_control() {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+    replaced
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_start_matches('.')
+        .trim_start()
+        .to_string()
+}
+
+fn wikilink(embed: bool, target: &str, text: &str) -> String {
+    let bang = if embed { "!" } else { "" };
+    let text = text.trim();
+    if text.is_empty() || text == target {
+        format!("{bang}[[{target}]]")
+    } else {
+        format!("{bang}[[{target}|{}]]", text.replace('|', "-"))
+    }
+}
+
+/// An inline Markdown link or image, `[text](target "title")`.
+#[derive(Debug, PartialEq, Eq)]
+struct MarkdownLink<'a> {
+    start: usize,
+    end: usize,
+    image: bool,
+    text: &'a str,
+    target: &'a str,
+}
+
+/// Inline links whose text and target are each on one line; nested
+/// brackets in the text are not supported.
+fn markdown_links(content: &str) -> Vec<MarkdownLink<'_>> {
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(offset) = content[from..].find("](") {
+        let middle = from + offset;
+        from = middle + 2;
+        let Some(close) = content[from..].find([')', '\n']) else {
+            break;
+        };
+        if content[from + close..].starts_with('\n') {
+            continue;
+        }
+        let line_start = content[..middle].rfind('\n').map_or(0, |index| index + 1);
+        let Some(open) = content[line_start..middle].rfind('[') else {
+            continue;
+        };
+        let Some(target) = content[from..from + close].split_whitespace().next() else {
+            continue;
+        };
+        let mut start = line_start + open;
+        let image = content[..start].ends_with('!');
+        if image {
+            start -= 1;
+        }
+        found.push(MarkdownLink {
+            start,
+            end: from + close + 1,
+            image,
+            text: &content[line_start + open + 1..middle],
+            target,
+        });
+        from += close + 1;
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{note_name, Obsidian};
+    use crate::export::{ExportNote, Exporter, LinkTargets};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    #[test]
+    fn notes_are_named_by_title_inside_their_folders() {
+        let note = ExportNote::from_note(&json!({
+            "id": "N1",
+            "title": "Deploy: Step 1/2",
+            "groups": [{ "id": "G1", "name": "Acme" }],
+            "folders": { "edges": [{ "node": {
+                "fullName": "Ops/Runbooks",
+                "group": { "id": "G1", "name": "Acme" },
+            } }] },
+        }));
+        assert_eq!(
+            Obsidian.note_path(&note),
+            PathBuf::from("Acme/Ops/Runbooks/Deploy Step 1 2.md")
+        );
+        assert_eq!(
+            Obsidian.assets_dir(&note),
+            PathBuf::from("Acme/Ops/Runbooks/attachments")
+        );
+        assert_eq!(note_name("..hidden [draft]"), "hidden draft");
+    }
+
+    #[test]
+    fn links_become_wikilinks_or_absolute_urls() {
+        let targets = LinkTargets {
+            origin: "https://acme.kibe.la".to_string(),
+            titles: HashMap::from([
+                ("/notes/1".to_string(), "Setup: Mac".to_string()),
+                (
+                    "https://acme.kibe.la/notes/2".to_string(),
+                    "FAQ".to_string(),
+                ),
+            ]),
+        };
+        let content = "![diagram](attachments/diagram.png) [spec](attachments/spec.pdf)\n\
+            see [setup](/notes/1), https://acme.kibe.la/notes/2.\n\
+            [gone](/notes/3) <a href=\"/notes/1\">x</a> [site](https://example.com)";
+        assert_eq!(
+            Obsidian.rewrite_links(content, &targets),
+            "![[diagram.png|diagram]] [[spec.pdf|spec]]\n\
+            see [[Setup Mac|setup]], [[FAQ]].\n\
+            [gone](https://acme.kibe.la/notes/3) <a href=\"https://acme.kibe.la/notes/1\">x</a> [site](https://example.com)"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use super::{absolute_note_links, slug, ExportNote, Exporter, LinkTargets};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// A page bundle, `content/<section>/.../<slug>/index.md` with its
+/// attachments beside it, for a static site generator.
+pub enum StaticSite {
+    /// YAML front matter; Kibela fields under `params`.
+    Hugo,
+    /// TOML front matter; Kibela fields under `[extra]`.
+    Zola,
+}
+
+impl StaticSite {
+    fn section_dirs(note: &ExportNote) -> Vec<String> {
+        note.sections
+            .iter()
+            .map(|section| slug(section))
+            .filter(|section| !section.is_empty())
+            .collect()
+    }
+
+    fn bundle(note: &ExportNote) -> PathBuf {
+        let mut path = PathBuf::from("content");
+        path.extend(Self::section_dirs(note));
+        path.join(note.slug())
+    }
+
+    /// Front matter between the generator's fences: `fields` at the top,
+    /// then `extra` in the table generators keep custom fields in.
+    fn front_matter(&self, fields: &[(&str, Value)], extra: &[(&str, Value)]) -> String {
+        let (fence, separator, table) = match self {
+            Self::Hugo => ("---", ": ", "params:"),
+            Self::Zola => ("+++", " = ", "[extra]"),
+        };
+        let mut out = format!("{fence}\n");
+        for (key, value) in fields.iter().filter(|(_, value)| !value.is_null()) {
+            out.push_str(&format!("{key}{separator}{value}\n"));
+        }
+        let extra = extra
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .collect::<Vec<_>>();
+        if !extra.is_empty() {
+            out.push_str(&format!("{table}\n"));
+            let indent = if matches!(self, Self::Hugo) { "  " } else { "" };
+            for (key, value) in extra {
+                out.push_str(&format!("{indent}{key}{separator}{value}\n"));
+            }
+        }
+        out.push_str(&format!("{fence}\n"));
+        out
+    }
+}
+
+impl Exporter for StaticSite {
+    fn note_path(&self, note: &ExportNote) -> PathBuf {
+        Self::bundle(note).join("index.md")
+    }
+
+    fn assets_dir(&self, note: &ExportNote) -> PathBuf {
+        Self::bundle(note)
+    }
+
+    fn attachment_prefix(&self) -> &'static str {
+        ""
+    }
+
+    /// Other notes are not part of the site, so links to them go to Kibela.
+    fn rewrite_links(&self, content: &str, targets: &LinkTargets) -> String {
+        absolute_note_links(content, &targets.origin)
+    }
+
+    /// `title`, `slug`, and `date` (the note's `updatedAt`, the only
+    /// timestamp Kibela returns with it), then the note's id and URL.
+    fn render(&self, note: &ExportNote, content: &str) -> String {
+        let front = &note.front;
+        let mut out = self.front_matter(
+            &[
+                ("title", Value::from(note.title())),
+                ("slug", Value::from(note.slug())),
+                ("date", Value::from(front.updated_at.clone())),
+            ],
+            &[
+                ("kibela_id", Value::from(front.id.clone())),
+                ("kibela_url", Value::from(front.url.clone())),
+            ],
+        );
+        out.push('\n');
+        out.push_str(content);
+        out
+    }
+
+    /// An `_index.md` per section, titled with the group or folder name.
+    fn scaffold(&self, note: &ExportNote) -> Vec<(PathBuf, String)> {
+        let mut dir = PathBuf::from("content");
+        note.sections
+            .iter()
+            .filter(|section| !slug(section).is_empty())
+            .map(|section| {
+                dir.push(slug(section));
+                let index = self.front_matter(&[("title", Value::from(section.as_str()))], &[]);
+                (dir.join("_index.md"), index)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticSite;
+    use crate::export::{ExportNote, Exporter};
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn note() -> ExportNote {
+        ExportNote::from_note(&json!({
+            "id": "N1",
+            "title": "Deploy \"v2\"",
+            "url": "https://acme.kibe.la/notes/1",
+            "updatedAt": "2026-02-23T00:00:00+09:00",
+            "groups": [{ "id": "G1", "name": "Acme" }],
+            "folders": { "edges": [{ "node": {
+                "fullName": "Ops",
+                "group": { "id": "G1", "name": "Acme" },
+            } }] },
+        }))
+    }
+
+    #[test]
+    fn hugo_writes_a_page_bundle_with_yaml_front_matter() {
+        let note = note();
+        assert_eq!(
+            StaticSite::Hugo.note_path(&note),
+            PathBuf::from("content/acme/ops/deploy-v2/index.md")
+        );
+        assert_eq!(
+            StaticSite::Hugo.render(&note, "body"),
+            "---\ntitle: \"Deploy \\\"v2\\\"\"\nslug: \"deploy-v2\"\ndate: \"2026-02-23T00:00:00+09:00\"\n\
+             params:\n  kibela_id: \"N1\"\n  kibela_url: \"https://acme.kibe.la/notes/1\"\n---\n\nbody"
+        );
+        assert_eq!(
+            StaticSite::Hugo.scaffold(&note),
+            vec![
+                (
+                    PathBuf::from("content/acme/_index.md"),
+                    "---\ntitle: \"Acme\"\n---\n".to_string()
+                ),
+                (
+                    PathBuf::from("content/acme/ops/_index.md"),
+                    "---\ntitle: \"Ops\"\n---\n".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn zola_writes_toml_front_matter_with_an_extra_table() {
+        assert_eq!(
+            StaticSite::Zola.render(&note(), "body"),
+            "+++\ntitle = \"Deploy \\\"v2\\\"\"\nslug = \"deploy-v2\"\ndate = \"2026-02-23T00:00:00+09:00\"\n\
+             [extra]\nkibela_id = \"N1\"\nkibela_url = \"https://acme.kibe.la/notes/1\"\n+++\n\nbody"
+        );
+    }
+}
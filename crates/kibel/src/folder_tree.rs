@@ -0,0 +1,239 @@
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Default for `folder tree --max-depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 10;
+
+/// A folder and its sub-folders, as read from `folder(id:)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderNode {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    /// Notes listed in the folder, capped by the page size.
+    pub note_count: usize,
+    /// `true` when `note_count` hit the page size, so there may be more.
+    pub notes_capped: bool,
+    pub children: Vec<FolderNode>,
+}
+
+impl FolderNode {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "path": self.path,
+            "note_count": self.note_count,
+            "notes_capped": self.notes_capped,
+            "children": self.children.iter().map(Self::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        1 + self.children.iter().map(Self::count).sum::<usize>()
+    }
+}
+
+/// Walks `folders(first:)` from each root, fetching every folder not in
+/// `prefetched` once with `fetch(id)` (a `folder(id:)` payload). Folders
+/// already visited, or deeper than `max_depth` below a root, are left out.
+pub fn build_tree<E, F>(
+    roots: &[String],
+    prefetched: Vec<(String, Value)>,
+    max_depth: usize,
+    page_size: usize,
+    mut fetch: F,
+) -> Result<Vec<FolderNode>, E>
+where
+    F: FnMut(&str) -> Result<Value, E>,
+{
+    let mut cache = prefetched.into_iter().collect::<HashMap<_, _>>();
+    let mut visited = HashSet::new();
+    let mut tree = Vec::new();
+    for root in roots {
+        if let Some(node) = walk(
+            root,
+            0,
+            max_depth,
+            page_size,
+            &mut fetch,
+            &mut cache,
+            &mut visited,
+        )? {
+            tree.push(node);
+        }
+    }
+    Ok(tree)
+}
+
+fn walk<E, F>(
+    id: &str,
+    depth: usize,
+    max_depth: usize,
+    page_size: usize,
+    fetch: &mut F,
+    cache: &mut HashMap<String, Value>,
+    visited: &mut HashSet<String>,
+) -> Result<Option<FolderNode>, E>
+where
+    F: FnMut(&str) -> Result<Value, E>,
+{
+    if !visited.insert(id.to_string()) {
+        return Ok(None);
+    }
+    let folder = match cache.get(id) {
+        Some(folder) => folder.clone(),
+        None => {
+            let folder = fetch(id)?;
+            cache.insert(id.to_string(), folder.clone());
+            folder
+        }
+    };
+    let text = |pointer: &str| {
+        folder
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let note_count = connection_nodes(&folder, "/notes/edges").len();
+    let mut children = Vec::new();
+    if depth < max_depth {
+        for child in child_ids(&folder) {
+            if let Some(node) = walk(
+                &child,
+                depth + 1,
+                max_depth,
+                page_size,
+                fetch,
+                cache,
+                visited,
+            )? {
+                children.push(node);
+            }
+        }
+    }
+    Ok(Some(FolderNode {
+        id: id.to_string(),
+        name: text("/name"),
+        path: text("/fixedPath"),
+        note_count,
+        notes_capped: note_count >= page_size,
+        children,
+    }))
+}
+
+/// Ids of the sub-folders listed in a `folder(id:)` payload.
+pub fn child_ids(folder: &Value) -> Vec<String> {
+    connection_nodes(folder, "/folders/edges")
+        .iter()
+        .filter_map(|node| node.get("id").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+fn connection_nodes<'a>(folder: &'a Value, pointer: &str) -> Vec<&'a Value> {
+    folder
+        .pointer(pointer)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|edge| edge.get("node"))
+        .collect()
+}
+
+/// Keeps the folders that are not listed as a sub-folder of another one in
+/// `folders` (`(id, folder payload)` pairs), preserving order.
+pub fn top_level_ids(folders: &[(String, Value)]) -> Vec<String> {
+    let nested = folders
+        .iter()
+        .flat_map(|(_, folder)| child_ids(folder))
+        .collect::<HashSet<_>>();
+    folders
+        .iter()
+        .filter(|(id, _)| !nested.contains(id))
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+/// Indented `--text` rendering, one folder per line with its note count.
+pub fn render_text(tree: &[FolderNode]) -> String {
+    fn push(lines: &mut Vec<String>, node: &FolderNode, depth: usize) {
+        let plus = if node.notes_capped { "+" } else { "" };
+        lines.push(format!(
+            "{}{} ({}{plus} notes)",
+            "  ".repeat(depth),
+            node.name,
+            node.note_count
+        ));
+        for child in &node.children {
+            push(lines, child, depth + 1);
+        }
+    }
+    let mut lines = Vec::new();
+    for node in tree {
+        push(&mut lines, node, 0);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_tree, render_text, top_level_ids};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+
+    fn folder(name: &str, children: &[&str], notes: usize) -> Value {
+        json!({
+            "name": name,
+            "fixedPath": format!("/acme/{name}"),
+            "folders": { "edges": children.iter().map(|id| json!({"node": {"id": id}})).collect::<Vec<_>>() },
+            "notes": { "edges": (0..notes).map(|i| json!({"node": {"id": format!("N{i}")}})).collect::<Vec<_>>() },
+        })
+    }
+
+    #[test]
+    fn build_tree_walks_children_once_and_honors_depth() {
+        let folders = HashMap::from([
+            ("F1", folder("eng", &["F2", "F3"], 2)),
+            ("F2", folder("backend", &["F4", "F1"], 3)),
+            ("F3", folder("frontend", &[], 0)),
+            ("F4", folder("db", &[], 1)),
+        ]);
+        let mut fetched = Vec::new();
+        let tree = build_tree(
+            &["F1".to_string()],
+            Vec::new(),
+            10,
+            3,
+            |id| -> Result<Value, ()> {
+                fetched.push(id.to_string());
+                Ok(folders[id].clone())
+            },
+        )
+        .expect("walk succeeds");
+        assert_eq!(fetched, vec!["F1", "F2", "F4", "F3"]);
+        assert_eq!(tree[0].count(), 4);
+        assert_eq!(
+            render_text(&tree),
+            "eng (2 notes)\n  backend (3+ notes)\n    db (1 notes)\n  frontend (0 notes)"
+        );
+
+        let prefetched = vec![("F1".to_string(), folders["F1"].clone())];
+        let shallow = build_tree(&["F1".to_string()], prefetched, 1, 3, |id| {
+            if id == "F1" {
+                return Err("F1 was prefetched");
+            }
+            Ok(folders[id].clone())
+        })
+        .expect("walk succeeds");
+        assert_eq!(shallow[0].count(), 3);
+
+        let roots = top_level_ids(&[
+            ("F2".to_string(), folders["F2"].clone()),
+            ("F3".to_string(), folders["F3"].clone()),
+            ("F4".to_string(), folders["F4"].clone()),
+        ]);
+        assert_eq!(roots, vec!["F2", "F3"]);
+    }
+}
@@ -0,0 +1,299 @@
+use serde_json::{json, Map, Value};
+
+/// Keys written to an exported note, in order.
+const KEYS: &[&str] = &["id", "title", "url", "groups", "folders", "updatedAt"];
+
+/// Note metadata carried in the leading `---` block of a Markdown export
+/// (`note get --format markdown`).
+///
+/// Values are written as JSON, which YAML reads as-is; the parser also
+/// accepts plain and single-quoted scalars so hand-edited files keep working.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrontMatter {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    /// `{id, name}` per group the note is shared with.
+    pub groups: Vec<Value>,
+    /// `{id, fullName, groupId}` per folder the note is in.
+    pub folders: Vec<Value>,
+    pub updated_at: Option<String>,
+}
+
+impl FrontMatter {
+    /// Reads a `getNoteMetadata` note object.
+    pub fn from_note(note: &Value) -> Self {
+        let text = |key: &str| note.get(key).and_then(Value::as_str).map(str::to_string);
+        let groups = note
+            .get("groups")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|group| json!({ "id": group["id"], "name": group["name"] }))
+            .collect();
+        let folders = note
+            .pointer("/folders/edges")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|edge| {
+                let folder = &edge["node"];
+                json!({
+                    "id": folder["id"],
+                    "fullName": folder["fullName"],
+                    "groupId": folder["group"]["id"],
+                })
+            })
+            .collect();
+        Self {
+            id: text("id"),
+            title: text("title"),
+            url: text("url"),
+            groups,
+            folders,
+            updated_at: text("updatedAt"),
+        }
+    }
+
+    /// Inverse of [`FrontMatter::to_json`].
+    pub fn from_json(value: &Value) -> Self {
+        let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+        let list = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default()
+        };
+        Self {
+            id: text("id"),
+            title: text("title"),
+            url: text("url"),
+            groups: list("groups"),
+            folders: list("folders"),
+            updated_at: text("updatedAt"),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "title": self.title,
+            "url": self.url,
+            "groups": self.groups,
+            "folders": self.folders,
+            "updatedAt": self.updated_at,
+        })
+    }
+}
+
+/// `content` preceded by a `---` block holding `front`.
+pub fn render(front: &FrontMatter, content: &str) -> String {
+    let values = front.to_json();
+    let mut out = String::from("---\n");
+    for key in KEYS {
+        match &values[*key] {
+            Value::Array(items) if items.is_empty() => out.push_str(&format!("{key}: []\n")),
+            Value::Array(items) => {
+                out.push_str(&format!("{key}:\n"));
+                for item in items {
+                    out.push_str(&format!("  - {}\n", flow(item)));
+                }
+            }
+            value => out.push_str(&format!("{key}: {value}\n")),
+        }
+    }
+    out.push_str("---\n");
+    out.push_str(content);
+    out
+}
+
+/// JSON with a space after `:` and `,` in objects, as YAML flow style is
+/// usually written.
+fn flow(value: &Value) -> String {
+    match value {
+        Value::Object(fields) => {
+            let fields = fields
+                .iter()
+                .map(|(key, value)| format!("{}: {}", Value::from(key.as_str()), flow(value)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", fields.join(", "))
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Splits a Markdown file into its front matter and body.
+///
+/// A file that does not start with `---` has no front matter. The body starts
+/// right after the closing `---` line, so blank lines leading it are kept;
+/// one trailing newline (as added when the export was written from stdout)
+/// is dropped, so an untouched export yields the note content unchanged.
+/// Unknown keys are ignored.
+pub fn parse(raw: &str) -> Result<(Option<FrontMatter>, &str), String> {
+    let Some(rest) = raw
+        .strip_prefix("---\n")
+        .or_else(|| raw.strip_prefix("---\r\n"))
+    else {
+        return Ok((None, raw));
+    };
+    let (header, body) = if let Some(body) = rest.strip_prefix("---") {
+        ("", body)
+    } else if let Some(end) = rest.find("\n---") {
+        (&rest[..end], &rest[end + 4..])
+    } else {
+        return Err("front matter is not closed by a `---` line".to_string());
+    };
+    // The rest of the closing line.
+    let body = strip_line_break(body);
+    let body = body
+        .strip_suffix("\r\n")
+        .or_else(|| body.strip_suffix('\n'))
+        .unwrap_or(body);
+
+    let mut fields = Map::new();
+    let mut list_key: Option<String> = None;
+    for (index, line) in header.lines().enumerate() {
+        let line_number = index + 2;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or((trimmed == "-").then_some(""))
+        {
+            let Some(key) = &list_key else {
+                return Err(format!("line {line_number}: list item outside a list"));
+            };
+            if let Some(Value::Array(items)) = fields.get_mut(key) {
+                items.push(
+                    scalar(item).map_err(|message| format!("line {line_number}: {message}"))?,
+                );
+            }
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once(':') else {
+            return Err(format!("line {line_number}: expected `key: value`"));
+        };
+        let key = key.trim().to_string();
+        let value = value.trim();
+        if value.is_empty() {
+            fields.insert(key.clone(), Value::Array(Vec::new()));
+            list_key = Some(key);
+        } else {
+            let value =
+                scalar(value).map_err(|message| format!("line {line_number}: {message}"))?;
+            fields.insert(key, value);
+            list_key = None;
+        }
+    }
+
+    let text = |key: &str| match fields.get(key) {
+        Some(Value::String(text)) if !text.is_empty() => Some(text.clone()),
+        Some(Value::Number(number)) => Some(number.to_string()),
+        _ => None,
+    };
+    let list = |key: &str| match fields.get(key) {
+        Some(Value::Array(items)) => items.clone(),
+        _ => Vec::new(),
+    };
+    let front = FrontMatter {
+        id: text("id"),
+        title: text("title"),
+        url: text("url"),
+        groups: list("groups"),
+        folders: list("folders"),
+        updated_at: text("updatedAt"),
+    };
+    Ok((Some(front), body))
+}
+
+fn strip_line_break(text: &str) -> &str {
+    text.strip_prefix("\r\n")
+        .or_else(|| text.strip_prefix('\n'))
+        .unwrap_or(text)
+}
+
+/// One YAML value: JSON (double-quoted strings, `[...]`, `{...}`, numbers),
+/// `null` / `~`, a single-quoted string, or a plain string.
+fn scalar(raw: &str) -> Result<Value, String> {
+    let raw = raw.trim();
+    match raw {
+        "" | "null" | "~" => return Ok(Value::Null),
+        _ => {}
+    }
+    if raw.starts_with('"') {
+        return serde_json::from_str(raw)
+            .map_err(|error| format!("invalid value `{raw}`: {error}"));
+    }
+    if raw.starts_with(['[', '{']) {
+        // YAML flow collections that are not JSON are kept as text.
+        return Ok(serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())));
+    }
+    if let Some(quoted) = raw.strip_prefix('\'') {
+        let inner = quoted
+            .strip_suffix('\'')
+            .ok_or_else(|| format!("unterminated string `{raw}`"))?;
+        return Ok(Value::String(inner.replace("''", "'")));
+    }
+    if let Ok(number) = raw.parse::<i64>() {
+        return Ok(Value::from(number));
+    }
+    Ok(Value::String(raw.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, render, FrontMatter};
+    use serde_json::json;
+
+    #[test]
+    fn render_and_parse_round_trip_a_note() {
+        let note = json!({
+            "id": "Tm90ZS8x",
+            "title": "Runbook: \"deploy\"",
+            "url": "https://acme.kibe.la/notes/1",
+            "updatedAt": "2026-02-23T00:00:00Z",
+            "groups": [{"id": "G1", "name": "Acme"}],
+            "folders": {"edges": [{"node": {
+                "id": "F1", "name": "Runbooks", "fullName": "Ops/Runbooks",
+                "group": {"id": "G1", "name": "Acme"}
+            }}]},
+        });
+        let front = FrontMatter::from_note(&note);
+        let exported = render(&front, "# Deploy\n\nsteps");
+        assert!(exported.starts_with("---\nid: \"Tm90ZS8x\"\ntitle: \"Runbook: \\\"deploy\\\"\"\n"));
+        assert!(exported.contains("groups:\n  - {\"id\": \"G1\", \"name\": \"Acme\"}\n"));
+
+        let written = format!("{exported}\n");
+        let (parsed, body) = parse(&written).expect("parses");
+        assert_eq!(parsed, Some(front));
+        assert_eq!(body, "# Deploy\n\nsteps");
+    }
+
+    #[test]
+    fn parse_keeps_leading_blank_lines_of_the_body() {
+        let raw = "---\nid: \"N1\"\ntitle: null\nurl: null\ngroups: []\nfolders: []\nupdatedAt: null\n---\n\n\nbody";
+        let (front, body) = parse(raw).expect("parses");
+        assert_eq!(body, "\n\nbody");
+        assert_eq!(render(&front.expect("front matter"), body), raw);
+    }
+
+    #[test]
+    fn parse_accepts_hand_written_yaml_and_rejects_broken_blocks() {
+        let (front, body) =
+            parse("---\nid: N1\ntitle: 'It''s done'\ngroups:\n  - G1\ntags: [a]\n---\nbody\n")
+                .expect("parses");
+        let front = front.expect("front matter");
+        assert_eq!(front.id.as_deref(), Some("N1"));
+        assert_eq!(front.title.as_deref(), Some("It's done"));
+        assert_eq!(front.groups, vec![json!("G1")]);
+        assert_eq!(front.updated_at, None);
+        assert_eq!(body, "body");
+
+        assert_eq!(parse("# Plain\n"), Ok((None, "# Plain\n")));
+        assert!(parse("---\nid: N1\n").is_err());
+        assert!(parse("---\n  - orphan\n---\n").is_err());
+    }
+}
@@ -0,0 +1,1005 @@
+use crate::graphql_schema::{named_type, variable_fits, SchemaSnapshot};
+use async_graphql_parser::types::{
+    DocumentOperations, ExecutableDocument, Field, FragmentDefinition, OperationDefinition,
+    OperationType, Selection, SelectionSet, Type,
+};
+use async_graphql_value::{ConstValue, Value};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    Query,
+    Mutation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryShape {
+    pub max_depth: u32,
+    pub complexity: u32,
+}
+
+/// Page size assumed for a connection whose `first`/`last` cannot be
+/// resolved; Kibela pages hold at most 100 items.
+pub const DEFAULT_PAGE_SIZE: u64 = 100;
+
+/// What [`SelectedOperation::cost`] treats as a connection, besides any field
+/// given a `first`/`last` argument.
+#[derive(Debug, Clone, Copy)]
+pub struct CostModel<'a> {
+    /// Fields the trusted resource contracts page (see [`connection_fields`]).
+    pub connection_fields: &'a HashSet<String>,
+    /// With a schema snapshot, fields typed `*Connection` too.
+    pub schema: Option<&'a SchemaSnapshot>,
+}
+
+/// Estimated number of fields the server resolves for an operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueryCost {
+    pub total: u64,
+    pub connections: Vec<ConnectionCost>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConnectionCost {
+    /// Response path with aliases, e.g. `search.edges.node.folders`.
+    pub path: String,
+    pub page_size: u64,
+    /// `argument` when `first`/`last` resolved, `default` when
+    /// [`DEFAULT_PAGE_SIZE`] was assumed.
+    pub page_size_from: &'static str,
+    /// How many times the connection itself is resolved.
+    pub multiplier: u64,
+}
+
+/// A variable declared by the selected operation, e.g. `$first: Int = 16`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableDecl {
+    pub name: String,
+    pub type_ref: String,
+    pub has_default: bool,
+}
+
+/// The operation `graphql run` will execute, with the document's fragments.
+#[derive(Debug)]
+pub struct SelectedOperation {
+    pub name: Option<String>,
+    pub kind: OperationKind,
+    operation: OperationDefinition,
+    fragments: HashMap<String, FragmentDefinition>,
+}
+
+/// Parses a GraphQL document and selects the operation to run.
+///
+/// A document with several named operations requires `operation_name`; a
+/// document with exactly one operation may omit it.
+pub fn select_operation(
+    query: &str,
+    operation_name: Option<&str>,
+) -> Result<SelectedOperation, String> {
+    let ExecutableDocument {
+        operations,
+        fragments,
+    } = async_graphql_parser::parse_query(query)
+        .map_err(|error| format!("invalid GraphQL document: {error}"))?;
+
+    let (name, operation) = match (operations, operation_name) {
+        (DocumentOperations::Single(operation), None) => (None, operation.node),
+        (DocumentOperations::Single(_), Some(name)) => {
+            return Err(format!(
+                "operation `{name}` not found; the document has a single anonymous operation"
+            ));
+        }
+        (DocumentOperations::Multiple(mut operations), Some(name)) => {
+            let Some(operation) = operations.remove(name) else {
+                return Err(format!(
+                    "operation `{name}` not found; available operations: {}",
+                    sorted_names(operations.keys().map(|name| name.as_str()))
+                ));
+            };
+            (Some(name.to_string()), operation.node)
+        }
+        (DocumentOperations::Multiple(operations), None) if operations.len() == 1 => {
+            let (name, operation) = operations
+                .into_iter()
+                .next()
+                .expect("length was checked above");
+            (Some(name.to_string()), operation.node)
+        }
+        (DocumentOperations::Multiple(operations), None) => {
+            return Err(format!(
+                "document defines {} operations ({}); pass --operation-name to pick one",
+                operations.len(),
+                sorted_names(operations.keys().map(|name| name.as_str()))
+            ));
+        }
+    };
+
+    let kind = match operation.ty {
+        OperationType::Mutation => OperationKind::Mutation,
+        OperationType::Query | OperationType::Subscription => OperationKind::Query,
+    };
+    Ok(SelectedOperation {
+        name,
+        kind,
+        operation,
+        fragments: fragments
+            .into_iter()
+            .map(|(name, fragment)| (name.to_string(), fragment.node))
+            .collect(),
+    })
+}
+
+impl SelectedOperation {
+    /// Root field names (not aliases) of the operation, with fragment spreads
+    /// and inline fragments at the root expanded.
+    pub fn root_fields(&self) -> Result<Vec<String>, String> {
+        let mut fields = Vec::new();
+        let mut visiting = Vec::new();
+        self.collect_root_fields(
+            &self.operation.selection_set.node,
+            &mut visiting,
+            &mut fields,
+        )?;
+        Ok(fields)
+    }
+
+    fn collect_root_fields<'a>(
+        &'a self,
+        selection_set: &'a SelectionSet,
+        visiting: &mut Vec<&'a str>,
+        fields: &mut Vec<String>,
+    ) -> Result<(), String> {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    let name = field.node.name.node.as_str();
+                    if name != "__typename" {
+                        fields.push(name.to_string());
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.collect_root_fields(&fragment.node.selection_set.node, visiting, fields)?;
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.node.fragment_name.node.as_str();
+                    let fragment = self.fragment(name, visiting)?;
+                    visiting.push(name);
+                    self.collect_root_fields(&fragment.selection_set.node, visiting, fields)?;
+                    visiting.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Variables declared by the operation, in declaration order.
+    pub fn variables(&self) -> Vec<VariableDecl> {
+        self.operation
+            .variable_definitions
+            .iter()
+            .map(|definition| VariableDecl {
+                name: definition.node.name.node.to_string(),
+                type_ref: definition.node.var_type.node.to_string(),
+                has_default: definition.node.default_value.is_some(),
+            })
+            .collect()
+    }
+
+    /// Arguments of every field in the operation, fragments expanded, as one
+    /// JSON object per field that has any. Variables resolve to `variables`,
+    /// then to their declared default, then to `null`.
+    pub fn argument_values(
+        &self,
+        variables: &serde_json::Value,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let resolved = self.resolve_variables(variables)?;
+        let mut arguments = Vec::new();
+        let mut visiting = Vec::new();
+        self.collect_arguments(
+            &self.operation.selection_set.node,
+            &resolved,
+            &mut visiting,
+            &mut arguments,
+        )?;
+        Ok(arguments)
+    }
+
+    /// Each declared variable's value: from `variables`, then its default,
+    /// then `null`.
+    fn resolve_variables(
+        &self,
+        variables: &serde_json::Value,
+    ) -> Result<HashMap<String, ConstValue>, String> {
+        let mut resolved = HashMap::new();
+        for definition in &self.operation.variable_definitions {
+            let name = definition.node.name.node.as_str();
+            let value = match variables.get(name) {
+                Some(value) => ConstValue::from_json(value.clone())
+                    .map_err(|error| format!("variable `{name}`: {error}"))?,
+                None => definition
+                    .node
+                    .default_value
+                    .as_ref()
+                    .map(|value| value.node.clone())
+                    .unwrap_or(ConstValue::Null),
+            };
+            resolved.insert(name.to_string(), value);
+        }
+        Ok(resolved)
+    }
+
+    fn collect_arguments<'a>(
+        &'a self,
+        selection_set: &'a SelectionSet,
+        variables: &HashMap<String, ConstValue>,
+        visiting: &mut Vec<&'a str>,
+        arguments: &mut Vec<serde_json::Value>,
+    ) -> Result<(), String> {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    let mut object = serde_json::Map::new();
+                    for (name, value) in &field.node.arguments {
+                        let value = value
+                            .node
+                            .clone()
+                            .into_const_with(|variable| {
+                                Ok::<_, String>(
+                                    variables
+                                        .get(variable.as_str())
+                                        .cloned()
+                                        .unwrap_or(ConstValue::Null),
+                                )
+                            })?
+                            .into_json()
+                            .map_err(|error| format!("argument `{}`: {error}", name.node))?;
+                        object.insert(name.node.to_string(), value);
+                    }
+                    if !object.is_empty() {
+                        arguments.push(serde_json::Value::Object(object));
+                    }
+                    self.collect_arguments(
+                        &field.node.selection_set.node,
+                        variables,
+                        visiting,
+                        arguments,
+                    )?;
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.collect_arguments(
+                        &fragment.node.selection_set.node,
+                        variables,
+                        visiting,
+                        arguments,
+                    )?;
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.node.fragment_name.node.as_str();
+                    let fragment = self.fragment(name, visiting)?;
+                    visiting.push(name);
+                    self.collect_arguments(
+                        &fragment.selection_set.node,
+                        variables,
+                        visiting,
+                        arguments,
+                    )?;
+                    visiting.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Depth and field count of the operation with every fragment expanded.
+    ///
+    /// Root fields are depth 1; fragments add no depth of their own.
+    pub fn shape(&self) -> Result<QueryShape, String> {
+        let mut memo = HashMap::new();
+        let mut visiting = Vec::new();
+        self.selection_shape(&self.operation.selection_set.node, &mut memo, &mut visiting)
+    }
+
+    fn selection_shape<'a>(
+        &'a self,
+        selection_set: &'a SelectionSet,
+        memo: &mut HashMap<&'a str, QueryShape>,
+        visiting: &mut Vec<&'a str>,
+    ) -> Result<QueryShape, String> {
+        let mut shape = QueryShape {
+            max_depth: 0,
+            complexity: 0,
+        };
+        for selection in &selection_set.items {
+            let item = match &selection.node {
+                Selection::Field(field) => {
+                    let children =
+                        self.selection_shape(&field.node.selection_set.node, memo, visiting)?;
+                    QueryShape {
+                        max_depth: children.max_depth.saturating_add(1),
+                        complexity: children.complexity.saturating_add(1),
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.selection_shape(&fragment.node.selection_set.node, memo, visiting)?
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.node.fragment_name.node.as_str();
+                    if let Some(cached) = memo.get(name) {
+                        *cached
+                    } else {
+                        let fragment = self.fragment(name, visiting)?;
+                        visiting.push(name);
+                        let computed =
+                            self.selection_shape(&fragment.selection_set.node, memo, visiting)?;
+                        visiting.pop();
+                        memo.insert(name, computed);
+                        computed
+                    }
+                }
+            };
+            shape.max_depth = shape.max_depth.max(item.max_depth);
+            shape.complexity = shape.complexity.saturating_add(item.complexity);
+        }
+        Ok(shape)
+    }
+
+    fn fragment(&self, name: &str, visiting: &[&str]) -> Result<&FragmentDefinition, String> {
+        if visiting.contains(&name) {
+            return Err(format!("fragment `{name}` spreads itself"));
+        }
+        self.fragments
+            .get(name)
+            .ok_or_else(|| format!("unknown fragment `{name}`"))
+    }
+}
+
+impl SelectedOperation {
+    /// Estimates how many fields the server resolves: every field counts once
+    /// per time it is resolved, and the `edges`/`nodes` of a connection are
+    /// resolved once per item of its page, so nested connections multiply.
+    ///
+    /// A connection's page size is its `first` (or `last`) argument, literal or
+    /// from `variables`, else [`DEFAULT_PAGE_SIZE`].
+    pub fn cost(
+        &self,
+        variables: &serde_json::Value,
+        model: CostModel<'_>,
+    ) -> Result<QueryCost, String> {
+        let root = model.schema.and_then(|schema| match self.kind {
+            OperationKind::Query => Some(schema.query_type.as_str()),
+            OperationKind::Mutation => schema.mutation_type.as_deref(),
+        });
+        let mut walk = CostWalk {
+            operation: self,
+            model,
+            variables: self.resolve_variables(variables)?,
+            visiting: Vec::new(),
+            connections: Vec::new(),
+        };
+        let total = walk.selection_set(&self.operation.selection_set.node, root, 1, None, "")?;
+        Ok(QueryCost {
+            total,
+            connections: walk.connections,
+        })
+    }
+}
+
+/// Names of the fields that `documents` page with a `first` or `last`
+/// argument. Fed the trusted resource contracts, this recognizes Kibela's
+/// connections even when an ad-hoc query leaves the page size out.
+pub fn connection_fields<'a>(documents: impl IntoIterator<Item = &'a str>) -> HashSet<String> {
+    fn collect(selection_set: &SelectionSet, names: &mut HashSet<String>) {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    if field
+                        .node
+                        .arguments
+                        .iter()
+                        .any(|(name, _)| matches!(name.node.as_str(), "first" | "last"))
+                    {
+                        names.insert(field.node.name.node.to_string());
+                    }
+                    collect(&field.node.selection_set.node, names);
+                }
+                Selection::InlineFragment(fragment) => {
+                    collect(&fragment.node.selection_set.node, names);
+                }
+                Selection::FragmentSpread(_) => {}
+            }
+        }
+    }
+
+    let mut names = HashSet::new();
+    for document in documents {
+        let Ok(document) = async_graphql_parser::parse_query(document) else {
+            continue;
+        };
+        for (_, operation) in document.operations.iter() {
+            collect(&operation.node.selection_set.node, &mut names);
+        }
+        for fragment in document.fragments.values() {
+            collect(&fragment.node.selection_set.node, &mut names);
+        }
+    }
+    names
+}
+
+struct CostWalk<'a> {
+    operation: &'a SelectedOperation,
+    model: CostModel<'a>,
+    variables: HashMap<String, ConstValue>,
+    visiting: Vec<&'a str>,
+    connections: Vec<ConnectionCost>,
+}
+
+impl<'a> CostWalk<'a> {
+    /// Cost of `selection_set` on `parent` (when the schema knows it),
+    /// resolved `multiplier` times. `page` is set inside a connection.
+    fn selection_set(
+        &mut self,
+        selection_set: &'a SelectionSet,
+        parent: Option<&'a str>,
+        multiplier: u64,
+        page: Option<u64>,
+        path: &str,
+    ) -> Result<u64, String> {
+        let mut total = 0u64;
+        for selection in &selection_set.items {
+            let cost = match &selection.node {
+                Selection::Field(field) => {
+                    self.field(&field.node, parent, multiplier, page, path)?
+                }
+                Selection::InlineFragment(fragment) => {
+                    let target = fragment
+                        .node
+                        .type_condition
+                        .as_ref()
+                        .map(|condition| condition.node.on.node.as_str())
+                        .or(parent);
+                    self.selection_set(
+                        &fragment.node.selection_set.node,
+                        target,
+                        multiplier,
+                        page,
+                        path,
+                    )?
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.node.fragment_name.node.as_str();
+                    let fragment = self.operation.fragment(name, &self.visiting)?;
+                    self.visiting.push(name);
+                    let cost = self.selection_set(
+                        &fragment.selection_set.node,
+                        Some(fragment.type_condition.node.on.node.as_str()),
+                        multiplier,
+                        page,
+                        path,
+                    )?;
+                    self.visiting.pop();
+                    cost
+                }
+            };
+            total = total.saturating_add(cost);
+        }
+        Ok(total)
+    }
+
+    fn field(
+        &mut self,
+        field: &'a Field,
+        parent: Option<&'a str>,
+        multiplier: u64,
+        page: Option<u64>,
+        path: &str,
+    ) -> Result<u64, String> {
+        let name = field.name.node.as_str();
+        let multiplier = match page {
+            Some(size) if matches!(name, "edges" | "nodes") => multiplier.saturating_mul(size),
+            _ => multiplier,
+        };
+        let key = field
+            .alias
+            .as_ref()
+            .map_or(name, |alias| alias.node.as_str());
+        let path = if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        };
+        let child_type = parent
+            .and_then(|parent| self.model.schema?.type_def(parent))
+            .and_then(|type_def| type_def.fields.get(name))
+            .map(|definition| named_type(&definition.type_ref));
+
+        let page_argument = field.arguments.iter().find_map(|(argument, value)| {
+            matches!(argument.node.as_str(), "first" | "last")
+                .then(|| self.page_size(&value.node))
+                .flatten()
+        });
+        let is_connection = page_argument.is_some()
+            || self.model.connection_fields.contains(name)
+            || child_type.is_some_and(|child| child.ends_with("Connection"));
+        let child_page = is_connection.then(|| {
+            let (page_size, page_size_from) =
+                page_argument.map_or((DEFAULT_PAGE_SIZE, "default"), |size| (size, "argument"));
+            self.connections.push(ConnectionCost {
+                path: path.clone(),
+                page_size,
+                page_size_from,
+                multiplier,
+            });
+            page_size
+        });
+
+        let children = self.selection_set(
+            &field.selection_set.node,
+            child_type,
+            multiplier,
+            child_page,
+            &path,
+        )?;
+        Ok(multiplier.saturating_add(children))
+    }
+
+    fn page_size(&self, value: &Value) -> Option<u64> {
+        match value {
+            Value::Number(number) => number.as_u64(),
+            Value::Variable(variable) => match self.variables.get(variable.as_str()) {
+                Some(ConstValue::Number(number)) => number.as_u64(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl SelectedOperation {
+    /// Checks fields, arguments, and variable types against a schema snapshot,
+    /// returning every mismatch found.
+    pub fn validate(&self, schema: &SchemaSnapshot) -> Vec<String> {
+        let mut check = SchemaCheck {
+            operation: self,
+            schema,
+            variables: HashMap::new(),
+            checked_fragments: HashSet::new(),
+            errors: Vec::new(),
+        };
+        for definition in &self.operation.variable_definitions {
+            let name = definition.node.name.node.as_str();
+            let var_type = &definition.node.var_type.node;
+            let type_name = var_type.to_string();
+            if schema.type_def(named_type(&type_name)).is_none() {
+                check
+                    .errors
+                    .push(format!("variable `${name}` has unknown type `{type_name}`"));
+            }
+            check
+                .variables
+                .insert(name, (var_type, definition.node.default_value.is_some()));
+        }
+
+        let root = match self.kind {
+            OperationKind::Query => Some(schema.query_type.as_str()),
+            OperationKind::Mutation => schema.mutation_type.as_deref(),
+        };
+        match root.filter(|root| schema.type_def(root).is_some()) {
+            Some(root) => check.selection_set(&self.operation.selection_set.node, root),
+            None => check
+                .errors
+                .push("schema snapshot has no root type for this operation".to_string()),
+        }
+        check.errors
+    }
+}
+
+struct SchemaCheck<'a> {
+    operation: &'a SelectedOperation,
+    schema: &'a SchemaSnapshot,
+    variables: HashMap<&'a str, (&'a Type, bool)>,
+    checked_fragments: HashSet<&'a str>,
+    errors: Vec<String>,
+}
+
+impl<'a> SchemaCheck<'a> {
+    fn selection_set(&mut self, selection_set: &'a SelectionSet, parent: &'a str) {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => self.field(&field.node, parent),
+                Selection::InlineFragment(fragment) => {
+                    let target = match &fragment.node.type_condition {
+                        Some(condition) => condition.node.on.node.as_str(),
+                        None => parent,
+                    };
+                    if self.known_type(target) {
+                        self.selection_set(&fragment.node.selection_set.node, target);
+                    }
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.node.fragment_name.node.as_str();
+                    if !self.checked_fragments.insert(name) {
+                        continue;
+                    }
+                    let Some(fragment) = self.operation.fragments.get(name) else {
+                        self.errors.push(format!("unknown fragment `{name}`"));
+                        continue;
+                    };
+                    let target = fragment.type_condition.node.on.node.as_str();
+                    if self.known_type(target) {
+                        self.selection_set(&fragment.selection_set.node, target);
+                    }
+                }
+            }
+        }
+    }
+
+    fn field(&mut self, field: &'a Field, parent: &'a str) {
+        let name = field.name.node.as_str();
+        if name == "__typename"
+            || (parent == self.schema.query_type && matches!(name, "__schema" | "__type"))
+        {
+            return;
+        }
+        let Some(definition) = self
+            .schema
+            .type_def(parent)
+            .and_then(|type_def| type_def.fields.get(name))
+        else {
+            self.errors
+                .push(format!("unknown field `{name}` on type `{parent}`"));
+            return;
+        };
+
+        for (argument, value) in &field.arguments {
+            let argument = argument.node.as_str();
+            let Some(expected) = definition.args.iter().find(|arg| arg.name == argument) else {
+                self.errors.push(format!(
+                    "unknown argument `{argument}` on field `{parent}.{name}`"
+                ));
+                continue;
+            };
+            let Value::Variable(variable) = &value.node else {
+                continue;
+            };
+            let variable = variable.as_str();
+            match self.variables.get(variable) {
+                None => self
+                    .errors
+                    .push(format!("variable `${variable}` is not defined")),
+                Some((declared, has_default)) => {
+                    let fits = Type::new(&expected.type_ref)
+                        .is_none_or(|expected| variable_fits(declared, *has_default, &expected));
+                    if !fits {
+                        self.errors.push(format!(
+                            "variable `${variable}` of type `{declared}` cannot be used for argument `{argument}` of type `{}` on field `{parent}.{name}`",
+                            expected.type_ref
+                        ));
+                    }
+                }
+            }
+        }
+        for expected in &definition.args {
+            let provided = field
+                .arguments
+                .iter()
+                .any(|(argument, _)| argument.node.as_str() == expected.name);
+            if !provided && !expected.has_default && expected.type_ref.ends_with('!') {
+                self.errors.push(format!(
+                    "field `{parent}.{name}` is missing required argument `{}: {}`",
+                    expected.name, expected.type_ref
+                ));
+            }
+        }
+
+        let child = named_type(&definition.type_ref);
+        let has_selection = !field.selection_set.node.items.is_empty();
+        match self.schema.type_def(child) {
+            Some(type_def) if type_def.is_composite() => {
+                if has_selection {
+                    self.selection_set(&field.selection_set.node, child);
+                } else {
+                    self.errors.push(format!(
+                        "field `{parent}.{name}` of type `{}` must have a selection of subfields",
+                        definition.type_ref
+                    ));
+                }
+            }
+            _ if has_selection => self.errors.push(format!(
+                "field `{parent}.{name}` of type `{}` has no subfields",
+                definition.type_ref
+            )),
+            _ => {}
+        }
+    }
+
+    fn known_type(&mut self, name: &str) -> bool {
+        let known = self.schema.type_def(name).is_some();
+        if !known {
+            self.errors.push(format!("unknown type `{name}`"));
+        }
+        known
+    }
+}
+
+impl VariableDecl {
+    /// Whether the server rejects the operation when this variable is omitted.
+    pub fn is_required(&self) -> bool {
+        self.type_ref.ends_with('!') && !self.has_default
+    }
+
+    /// Converts a `--var` or prompt string to JSON using the declared type:
+    /// `Int`/`Float`/`Boolean` are parsed, lists and input objects are read
+    /// as JSON, and every other type (`String`, `ID`, enums, custom scalars)
+    /// is sent as a string. `null` is accepted for nullable variables.
+    pub fn coerce(&self, raw: &str) -> Result<serde_json::Value, String> {
+        let value = raw.trim();
+        if value == "null" && !self.type_ref.ends_with('!') {
+            return Ok(serde_json::Value::Null);
+        }
+        if self.type_ref.starts_with('[') || value.starts_with('{') {
+            return serde_json::from_str(value)
+                .map_err(|error| format!("expected JSON for `{}`: {error}", self.type_ref));
+        }
+        match named_type(&self.type_ref) {
+            "Int" => value
+                .parse::<i64>()
+                .map(serde_json::Value::from)
+                .map_err(|_| format!("expected an integer for `{}`", self.type_ref)),
+            "Float" => value
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(serde_json::Value::Number)
+                .ok_or_else(|| format!("expected a number for `{}`", self.type_ref)),
+            "Boolean" => value
+                .parse::<bool>()
+                .map(serde_json::Value::Bool)
+                .map_err(|_| format!("expected true or false for `{}`", self.type_ref)),
+            _ => Ok(serde_json::Value::String(raw.to_string())),
+        }
+    }
+}
+
+fn sorted_names<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    let mut names = names.collect::<Vec<_>>();
+    names.sort_unstable();
+    names.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{connection_fields, select_operation, CostModel, OperationKind, VariableDecl};
+    use crate::graphql_schema::SchemaSnapshot;
+    use serde_json::json;
+
+    fn schema() -> SchemaSnapshot {
+        let named = |kind: &str, name: &str| json!({ "kind": kind, "name": name, "ofType": null });
+        let non_null = |inner| json!({ "kind": "NON_NULL", "name": null, "ofType": inner });
+        SchemaSnapshot::from_introspection(&json!({ "__schema": {
+            "queryType": { "name": "Query" },
+            "mutationType": null,
+            "types": [
+                { "kind": "OBJECT", "name": "Query", "fields": [{
+                    "name": "note",
+                    "args": [{ "name": "id", "defaultValue": null, "type": non_null(named("SCALAR", "ID")) }],
+                    "type": named("OBJECT", "Note"),
+                }]},
+                { "kind": "OBJECT", "name": "Note", "fields": [
+                    { "name": "id", "args": [], "type": non_null(named("SCALAR", "ID")) },
+                    { "name": "title", "args": [], "type": non_null(named("SCALAR", "String")) },
+                ]},
+                { "kind": "SCALAR", "name": "ID", "fields": null },
+                { "kind": "SCALAR", "name": "String", "fields": null },
+            ]
+        }}))
+        .expect("schema should parse")
+    }
+
+    #[test]
+    fn select_operation_detects_kind_after_comments() {
+        let query = select_operation("query Q { groups { edges { node { id } } } }", None)
+            .expect("query should parse");
+        assert_eq!(query.kind, OperationKind::Query);
+        let mutation = select_operation(
+            "# generated by agent\n   # keep for tracing\nmutation M { createFolder(input: {}) { folder { id } } }",
+            None,
+        )
+        .expect("mutation should parse");
+        assert_eq!(mutation.kind, OperationKind::Mutation);
+        assert_eq!(mutation.name.as_deref(), Some("M"));
+    }
+
+    #[test]
+    fn select_operation_requires_name_for_multi_operation_documents() {
+        let document = "query A { currentUser { id } } mutation B { createFolder(input: {}) { folder { id } } }";
+        let error = select_operation(document, None).expect_err("ambiguous document");
+        assert!(error.contains("pass --operation-name"), "{error}");
+        let error = select_operation(document, Some("C")).expect_err("unknown operation");
+        assert!(error.contains("available operations: A, B"), "{error}");
+        let selected = select_operation(document, Some("B")).expect("B should be selected");
+        assert_eq!(selected.kind, OperationKind::Mutation);
+    }
+
+    #[test]
+    fn shape_expands_fragments_without_adding_depth() {
+        let selected = select_operation(
+            "query Q { groups { edges { ...Edge } } } fragment Edge on GroupEdge { node { id ... on Group { name } } }",
+            None,
+        )
+        .expect("query should parse");
+        let shape = selected.shape().expect("shape should be computed");
+        assert_eq!(shape.max_depth, 4);
+        assert_eq!(shape.complexity, 5);
+    }
+
+    #[test]
+    fn cost_multiplies_nested_connection_pages() {
+        let selected = select_operation(
+            "query Q($first: Int = 10) { search(query: \"x\", first: $first) { totalCount edges { node { id folders(first: 5) { edges { node { name } } } } } } all: groups { nodes { id } } }",
+            None,
+        )
+        .expect("query should parse");
+        let known = ["groups".to_string()].into_iter().collect();
+        let model = CostModel {
+            connection_fields: &known,
+            schema: None,
+        };
+
+        let cost = selected.cost(&json!({}), model).expect("cost");
+        assert_eq!(cost.total, 393);
+        let connections = cost
+            .connections
+            .iter()
+            .map(|connection| {
+                (
+                    connection.path.as_str(),
+                    connection.page_size,
+                    connection.page_size_from,
+                    connection.multiplier,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            connections,
+            vec![
+                ("search", 10, "argument", 1),
+                ("search.edges.node.folders", 5, "argument", 10),
+                ("all", 100, "default", 1),
+            ]
+        );
+        let cost = selected.cost(&json!({ "first": 2 }), model).expect("cost");
+        assert_eq!(cost.total, 241);
+    }
+
+    #[test]
+    fn connection_fields_collect_paged_fields_from_documents() {
+        let names = connection_fields([
+            "query A($first: Int!) { notes(first: $first) { edges { node { ...F } } } } fragment F on Note { comments(last: 3) { nodes { id } } }",
+            "not graphql",
+        ]);
+        let mut names = names.into_iter().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["comments", "notes"]);
+    }
+
+    #[test]
+    fn root_fields_expand_fragments_and_ignore_aliases() {
+        let selected = select_operation(
+            "mutation M { alias: createFolder(input: {}) { folder { id } } ...Extra } fragment Extra on Mutation { __typename ... { deleteNote(input: {}) { clientMutationId } } }",
+            None,
+        )
+        .expect("mutation should parse");
+        assert_eq!(
+            selected.root_fields().expect("root fields"),
+            vec!["createFolder".to_string(), "deleteNote".to_string()]
+        );
+    }
+
+    #[test]
+    fn root_fields_reject_unknown_and_cyclic_fragments() {
+        let unknown = select_operation("mutation M { ...Missing }", None).expect("parses");
+        assert!(unknown
+            .root_fields()
+            .expect_err("unknown fragment")
+            .contains("unknown fragment `Missing`"));
+        let cyclic = select_operation(
+            "mutation M { ...A } fragment A on Mutation { ...B } fragment B on Mutation { ...A }",
+            None,
+        )
+        .expect("parses");
+        assert!(cyclic
+            .root_fields()
+            .expect_err("cyclic fragment")
+            .contains("spreads itself"));
+        assert!(cyclic.shape().is_err());
+    }
+
+    #[test]
+    fn argument_values_resolve_variables_defaults_and_fragments() {
+        let selected = select_operation(
+            "query Q($first: Int = 30, $group: ID!) { search(first: $first, groupIds: [$group]) { edges { node { ...N } } } } fragment N on SearchResult { folders(first: 5) { totalCount } }",
+            None,
+        )
+        .expect("query should parse");
+        assert_eq!(
+            selected
+                .argument_values(&serde_json::json!({ "group": "G1" }))
+                .expect("arguments"),
+            vec![
+                serde_json::json!({ "first": 30, "groupIds": ["G1"] }),
+                serde_json::json!({ "first": 5 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_accepts_matching_document() {
+        let selected = select_operation(
+            "query Q($id: ID!) { note(id: $id) { ...NoteFields __typename } } fragment NoteFields on Note { id title }",
+            None,
+        )
+        .expect("query should parse");
+        assert_eq!(selected.validate(&schema()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn validate_reports_fields_arguments_and_variable_types() {
+        let selected = select_operation(
+            "query Q($id: String, $first: Int) { note(id: $id, first: $first) { id titel } }",
+            None,
+        )
+        .expect("query should parse");
+        let errors = selected.validate(&schema());
+        assert_eq!(
+            errors,
+            vec![
+                "variable `$first` has unknown type `Int`".to_string(),
+                "variable `$id` of type `String` cannot be used for argument `id` of type `ID!` on field `Query.note`".to_string(),
+                "unknown argument `first` on field `Query.note`".to_string(),
+                "unknown field `titel` on type `Note`".to_string(),
+            ]
+        );
+        let missing = select_operation("query { note { title { id } } }", None).expect("parses");
+        assert_eq!(
+            missing.validate(&schema()),
+            vec![
+                "field `Query.note` is missing required argument `id: ID!`".to_string(),
+                "field `Note.title` of type `String!` has no subfields".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn variables_are_declared_in_order_and_coerced_by_type() {
+        let operation = select_operation(
+            "query Q($id: ID!, $first: Int = 16, $ids: [ID!], $draft: Boolean) { note(id: $id) { id } }",
+            None,
+        )
+        .expect("query should parse");
+        let variables = operation.variables();
+        let names = variables
+            .iter()
+            .map(|variable| variable.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["id", "first", "ids", "draft"]);
+        assert!(variables[0].is_required());
+        assert!(!variables[1].is_required());
+
+        assert_eq!(variables[0].coerce("42"), Ok(json!("42")));
+        assert_eq!(variables[1].coerce("3"), Ok(json!(3)));
+        assert!(variables[1].coerce("three").is_err());
+        assert_eq!(
+            variables[2].coerce(r#"["N1","N2"]"#),
+            Ok(json!(["N1", "N2"]))
+        );
+        assert_eq!(variables[3].coerce("true"), Ok(json!(true)));
+        assert_eq!(variables[3].coerce("null"), Ok(json!(null)));
+        let input = VariableDecl {
+            name: "input".to_string(),
+            type_ref: "CreateFolderInput!".to_string(),
+            has_default: false,
+        };
+        assert_eq!(input.coerce(r#"{"a":1}"#), Ok(json!({ "a": 1 })));
+    }
+}
@@ -0,0 +1,233 @@
+use async_graphql_parser::types::{BaseType, Type};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Field and argument types from a cached introspection result, used to
+/// validate `graphql run` documents before they are sent.
+#[derive(Debug, Clone)]
+pub struct SchemaSnapshot {
+    pub query_type: String,
+    pub mutation_type: Option<String>,
+    types: HashMap<String, TypeDef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeDef {
+    pub kind: String,
+    pub fields: HashMap<String, FieldDef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldDef {
+    pub args: Vec<ArgDef>,
+    pub type_ref: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArgDef {
+    pub name: String,
+    pub type_ref: String,
+    pub has_default: bool,
+}
+
+impl SchemaSnapshot {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        let payload = serde_json::from_str::<Value>(&raw)
+            .map_err(|error| format!("failed to parse {}: {error}", path.display()))?;
+        Self::from_introspection(&payload)
+            .map_err(|error| format!("invalid schema snapshot {}: {error}", path.display()))
+    }
+
+    /// Accepts `{"data": {"__schema": ...}}`, `{"__schema": ...}`, or the
+    /// `__schema` object itself.
+    pub fn from_introspection(payload: &Value) -> Result<Self, String> {
+        let schema = payload
+            .pointer("/data/__schema")
+            .or_else(|| payload.get("__schema"))
+            .unwrap_or(payload);
+        let types = schema
+            .get("types")
+            .and_then(Value::as_array)
+            .ok_or_else(|| "missing `types` array".to_string())?;
+
+        let mut snapshot = Self {
+            query_type: root_type_name(schema, "queryType")
+                .unwrap_or("Query")
+                .to_string(),
+            mutation_type: schema
+                .get("mutationType")
+                .filter(|value| !value.is_null())
+                .map(|_| {
+                    root_type_name(schema, "mutationType")
+                        .unwrap_or("Mutation")
+                        .to_string()
+                }),
+            types: HashMap::new(),
+        };
+        for item in types {
+            let Some(name) = item.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            snapshot.types.insert(
+                name.to_string(),
+                TypeDef {
+                    kind: item
+                        .get("kind")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    fields: parse_fields(item.get("fields"))?,
+                },
+            );
+        }
+
+        // The kibel-tools introspection query selects root fields inline
+        // without the root type name, so fold them into the root types here.
+        for (key, name) in [
+            ("queryType", Some(snapshot.query_type.clone())),
+            ("mutationType", snapshot.mutation_type.clone()),
+        ] {
+            let (Some(name), Some(fields)) = (name, schema.pointer(&format!("/{key}/fields")))
+            else {
+                continue;
+            };
+            let root = snapshot.types.entry(name).or_insert_with(|| TypeDef {
+                kind: "OBJECT".to_string(),
+                fields: HashMap::new(),
+            });
+            root.fields.extend(parse_fields(Some(fields))?);
+        }
+        Ok(snapshot)
+    }
+
+    pub fn type_def(&self, name: &str) -> Option<&TypeDef> {
+        self.types.get(name)
+    }
+}
+
+impl TypeDef {
+    /// Whether a field of this type must (object-like) or must not (leaf) have
+    /// a sub-selection.
+    pub fn is_composite(&self) -> bool {
+        matches!(self.kind.as_str(), "OBJECT" | "INTERFACE" | "UNION")
+    }
+}
+
+fn root_type_name<'a>(schema: &'a Value, key: &str) -> Option<&'a str> {
+    schema.get(key)?.get("name")?.as_str()
+}
+
+fn parse_fields(value: Option<&Value>) -> Result<HashMap<String, FieldDef>, String> {
+    let Some(items) = value.and_then(Value::as_array) else {
+        return Ok(HashMap::new());
+    };
+    let mut fields = HashMap::new();
+    for item in items {
+        let name = item
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| "field is missing `name`".to_string())?;
+        let type_ref = render_type_ref(item.get("type"))
+            .ok_or_else(|| format!("field `{name}` has no type"))?;
+        let args = item
+            .get("args")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|arg| {
+                let arg_name = arg
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("argument of `{name}` is missing `name`"))?;
+                Ok(ArgDef {
+                    name: arg_name.to_string(),
+                    type_ref: render_type_ref(arg.get("type"))
+                        .ok_or_else(|| format!("argument `{name}({arg_name})` has no type"))?,
+                    has_default: arg
+                        .get("defaultValue")
+                        .is_some_and(|value| !value.is_null()),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        fields.insert(name.to_string(), FieldDef { args, type_ref });
+    }
+    Ok(fields)
+}
+
+/// Renders an introspection `__Type` reference in SDL notation, e.g. `[ID!]!`.
+fn render_type_ref(value: Option<&Value>) -> Option<String> {
+    let value = value?;
+    match value.get("kind").and_then(Value::as_str)? {
+        "NON_NULL" => Some(format!("{}!", render_type_ref(value.get("ofType"))?)),
+        "LIST" => Some(format!("[{}]", render_type_ref(value.get("ofType"))?)),
+        _ => value
+            .get("name")
+            .and_then(Value::as_str)
+            .map(ToOwned::to_owned),
+    }
+}
+
+/// The innermost named type of an SDL type reference.
+pub fn named_type(type_ref: &str) -> &str {
+    type_ref.trim_matches(|c| matches!(c, '[' | ']' | '!'))
+}
+
+/// Whether a variable of type `variable` may be passed to an argument of
+/// type `argument`. A default value lets a nullable variable feed a
+/// non-null argument.
+pub fn variable_fits(variable: &Type, has_default: bool, argument: &Type) -> bool {
+    if !argument.nullable && variable.nullable && !has_default {
+        return false;
+    }
+    match (&variable.base, &argument.base) {
+        (BaseType::Named(left), BaseType::Named(right)) => left == right,
+        (BaseType::List(left), BaseType::List(right)) => variable_fits(left, false, right),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{named_type, variable_fits, SchemaSnapshot};
+    use async_graphql_parser::types::Type;
+    use serde_json::json;
+
+    #[test]
+    fn from_introspection_folds_inline_root_fields() {
+        let snapshot = SchemaSnapshot::from_introspection(&json!({
+            "data": { "__schema": {
+                "queryType": { "fields": [{
+                    "name": "note",
+                    "args": [{ "name": "id", "defaultValue": null, "type": {
+                        "kind": "NON_NULL", "name": null,
+                        "ofType": { "kind": "SCALAR", "name": "ID", "ofType": null }
+                    }}],
+                    "type": { "kind": "OBJECT", "name": "Note", "ofType": null }
+                }]},
+                "mutationType": null,
+                "types": [{ "kind": "OBJECT", "name": "Note", "fields": [] }]
+            }}
+        }))
+        .expect("snapshot should parse");
+        let note = &snapshot.type_def("Query").expect("root type").fields["note"];
+        assert_eq!(note.type_ref, "Note");
+        assert_eq!(note.args[0].type_ref, "ID!");
+        assert!(snapshot.mutation_type.is_none());
+        assert_eq!(named_type("[Note!]!"), "Note");
+    }
+
+    #[test]
+    fn variable_fits_follows_nullability_rules() {
+        let ty = |raw: &str| Type::new(raw).expect("type should parse");
+        assert!(variable_fits(&ty("ID!"), false, &ty("ID")));
+        assert!(!variable_fits(&ty("ID"), false, &ty("ID!")));
+        assert!(variable_fits(&ty("ID"), true, &ty("ID!")));
+        assert!(variable_fits(&ty("[ID!]!"), false, &ty("[ID!]")));
+        assert!(!variable_fits(&ty("[ID]"), false, &ty("[ID!]")));
+        assert!(!variable_fits(&ty("String!"), false, &ty("ID!")));
+    }
+}
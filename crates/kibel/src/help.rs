@@ -0,0 +1,246 @@
+use clap::builder::ValueParser;
+use clap::{Arg, ArgAction, Command};
+use serde_json::{json, Value};
+use std::any::TypeId;
+use std::path::PathBuf;
+
+/// How a command touches Kibela: `read`, `write`, `guarded` (read unless
+/// explicitly allowed to mutate), `local` (no API call), or `namespace` for
+/// parent commands that only hold subcommands.
+///
+/// Every command path must be listed; the unit test below walks the clap tree
+/// so a new command cannot ship without a classification.
+pub fn classify(path: &str) -> Option<&'static str> {
+    let access = match path {
+        "" | "auth" | "config" | "search" | "group" | "folder" | "feed" | "comment" | "note"
+        | "link" | "report" | "import" | "ci" | "lint" | "template" | "cache" | "store"
+        | "meta" | "self" | "graphql" => "namespace",
+        "auth login"
+        | "auth logout"
+        | "config set"
+        | "config set team"
+        | "config import"
+        | "note create"
+        | "note update"
+        | "note append"
+        | "note prepend"
+        | "note restore"
+        | "note publish"
+        | "note move-to-folder"
+        | "note attach-to-folder"
+        | "note copy"
+        | "note transfer"
+        | "comment create"
+        | "comment reply"
+        | "comment edit"
+        | "comment delete"
+        | "folder create"
+        | "digest"
+        | "publish"
+        | "import confluence"
+        | "import notion"
+        | "lint links" => "write",
+        "auth status"
+        | "search note"
+        | "search folder"
+        | "search user"
+        | "group list"
+        | "folder list"
+        | "folder get"
+        | "folder get-from-path"
+        | "folder notes"
+        | "folder tree"
+        | "feed sections"
+        | "feed watch"
+        | "feed relay"
+        | "note get"
+        | "note get-many"
+        | "note get-from-path"
+        | "note diff"
+        | "note download"
+        | "note render"
+        | "note outline"
+        | "comment thread"
+        | "note drafts"
+        | "link resolve"
+        | "open"
+        | "report attachments"
+        | "stats"
+        | "tui"
+        | "ci verify-docs" => "read",
+        "graphql run" => "guarded",
+        "config get" | "config profiles" | "config export" | "config convert" | "link make"
+        | "template list" | "template show" | "cache stats" | "store stats" | "store vacuum"
+        | "metrics" | "usage" | "meta contracts" | "meta operations" | "meta limits"
+        | "self update" | "graphql cost" | "completion" | "version" | "help" => "local",
+        _ => return None,
+    };
+    Some(access)
+}
+
+/// Finds the subcommand at `path` in a built command tree.
+pub fn find_command<'a>(root: &'a Command, path: &[String]) -> Result<&'a Command, String> {
+    let mut current = root;
+    for (depth, name) in path.iter().enumerate() {
+        current = current.find_subcommand(name).ok_or_else(|| {
+            format!(
+                "unknown command: `{}`",
+                path[..=depth].join(" ").trim_start()
+            )
+        })?;
+    }
+    Ok(current)
+}
+
+/// Describes `command` and its descendants; global args are reported once by
+/// [`global_args`] instead of on every node.
+pub fn describe_command(command: &Command, path: &[String]) -> Value {
+    let joined = path.join(" ");
+    let args = command
+        .get_arguments()
+        .filter(|arg| !arg.is_global_set() && !is_builtin(arg))
+        .map(describe_arg)
+        .collect::<Vec<_>>();
+    let subcommands = command
+        .get_subcommands()
+        .map(|sub| {
+            let mut child = path.to_vec();
+            child.push(sub.get_name().to_string());
+            describe_command(sub, &child)
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "name": command.get_name(),
+        "path": joined,
+        "about": command.get_about().map(ToString::to_string),
+        "long_about": command.get_long_about().map(ToString::to_string),
+        "access": classify(&joined),
+        "args": args,
+        "subcommands": subcommands,
+    })
+}
+
+pub fn global_args(root: &Command) -> Vec<Value> {
+    root.get_arguments()
+        .filter(|arg| arg.is_global_set())
+        .map(describe_arg)
+        .collect()
+}
+
+fn is_builtin(arg: &Arg) -> bool {
+    matches!(arg.get_id().as_str(), "help" | "version")
+}
+
+fn describe_arg(arg: &Arg) -> Value {
+    let possible_values = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect::<Vec<_>>();
+    let value_type = match arg.get_action() {
+        ArgAction::SetTrue | ArgAction::SetFalse => "bool",
+        ArgAction::Count => "count",
+        _ if !possible_values.is_empty() => "enum",
+        _ => value_type_name(arg.get_value_parser()),
+    };
+    json!({
+        "id": arg.get_id().as_str(),
+        "long": arg.get_long(),
+        "short": arg.get_short().map(String::from),
+        "aliases": arg.get_visible_aliases().unwrap_or_default(),
+        "positional": arg.is_positional(),
+        "value_names": arg
+            .get_value_names()
+            .filter(|_| !matches!(value_type, "bool" | "count"))
+            .map(|names| names.iter().map(ToString::to_string).collect::<Vec<_>>()),
+        "type": value_type,
+        "required": arg.is_required_set(),
+        "multiple": matches!(arg.get_action(), ArgAction::Append),
+        "default": arg
+            .get_default_values()
+            .iter()
+            .map(|value| value.to_string_lossy().to_string())
+            .collect::<Vec<_>>(),
+        "env": arg.get_env().map(|env| env.to_string_lossy().to_string()),
+        "possible_values": possible_values,
+        "help": arg.get_help().map(ToString::to_string),
+    })
+}
+
+fn value_type_name(parser: &ValueParser) -> &'static str {
+    let id = parser.type_id();
+    if [
+        TypeId::of::<u8>(),
+        TypeId::of::<u16>(),
+        TypeId::of::<u32>(),
+        TypeId::of::<u64>(),
+        TypeId::of::<usize>(),
+        TypeId::of::<i32>(),
+        TypeId::of::<i64>(),
+    ]
+    .iter()
+    .any(|integer| id == *integer)
+    {
+        "integer"
+    } else if id == TypeId::of::<PathBuf>() {
+        "path"
+    } else {
+        "string"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, describe_command, find_command};
+    use crate::cli::Cli;
+    use clap::{Command, CommandFactory};
+
+    fn leaf_paths(command: &Command, path: &mut Vec<String>, out: &mut Vec<String>) {
+        out.push(path.join(" "));
+        for sub in command.get_subcommands() {
+            path.push(sub.get_name().to_string());
+            leaf_paths(sub, path, out);
+            path.pop();
+        }
+    }
+
+    #[test]
+    fn every_command_path_is_classified() {
+        let mut paths = Vec::new();
+        leaf_paths(&Cli::command(), &mut Vec::new(), &mut paths);
+        let missing = paths
+            .iter()
+            .filter(|path| classify(path).is_none())
+            .collect::<Vec<_>>();
+        assert!(missing.is_empty(), "unclassified commands: {missing:?}");
+    }
+
+    #[test]
+    fn describe_command_reports_types_defaults_and_env() {
+        let mut root = Cli::command();
+        root.build();
+        let path = vec!["report".to_string(), "attachments".to_string()];
+        let command = find_command(&root, &path).expect("command should exist");
+        let described = describe_command(command, &path);
+        assert_eq!(described["access"], "read");
+        let arg = |id: &str| {
+            described["args"]
+                .as_array()
+                .and_then(|args| args.iter().find(|arg| arg["id"] == id))
+                .cloned()
+                .expect("arg should be described")
+        };
+        assert_eq!(arg("max_pages")["type"], "integer");
+        assert_eq!(arg("max_pages")["default"][0], "5");
+        assert_eq!(arg("hash")["type"], "bool");
+        assert_eq!(arg("group_id")["required"], true);
+
+        let origin = super::global_args(&root)
+            .into_iter()
+            .find(|arg| arg["id"] == "origin")
+            .expect("origin is global");
+        assert_eq!(origin["env"], "KIBELA_ORIGIN");
+        assert!(find_command(&root, &["nope".to_string()]).is_err());
+    }
+}
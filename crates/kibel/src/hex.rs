@@ -0,0 +1,8 @@
+/// `bytes` as lowercase hex, as digests and signatures are written.
+pub fn encode(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
@@ -0,0 +1,332 @@
+//! `kibel import confluence|notion`: reads another wiki's export into
+//! Markdown pages, each under a root (a Confluence space, or a Notion
+//! top-level page or database) that is mapped to a Kibela folder.
+
+mod confluence;
+mod html;
+mod notion;
+
+pub use confluence::read as read_confluence;
+pub use notion::read as read_notion;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// A page read from an export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// Path of the page file inside the export, `/`-separated.
+    pub source: String,
+    pub title: String,
+    /// Markdown. Relative link and image targets that resolve to a file in
+    /// the export are rewritten to `<path>` from the export root.
+    pub content: String,
+    /// Name of the space, top-level page, or database the page is under.
+    pub root: String,
+    /// Titles of the pages between the root and this page, outermost first.
+    pub ancestors: Vec<String>,
+}
+
+/// The export as a folder: `export` itself, or a `.zip` unpacked with the
+/// system `unzip` into a temporary folder removed on drop.
+pub struct Unpacked {
+    pub root: PathBuf,
+    temporary: bool,
+}
+
+impl Drop for Unpacked {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+/// Unpacks `export` when it is a file. Zips inside the top folder are
+/// unpacked too, as Notion splits large exports into parts.
+pub fn unpack(export: &Path) -> Result<Unpacked, String> {
+    if export.is_dir() {
+        return Ok(Unpacked {
+            root: export.to_path_buf(),
+            temporary: false,
+        });
+    }
+    if !export.is_file() {
+        return Err(format!("{} does not exist", export.display()));
+    }
+    let root = std::env::temp_dir().join(format!("kibel-import-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let unpacked = Unpacked {
+        root,
+        temporary: true,
+    };
+    unzip(export, &unpacked.root)?;
+    let nested = fs::read_dir(&unpacked.root)
+        .map_err(|error| format!("failed to read {}: {error}", unpacked.root.display()))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "zip"))
+        .collect::<Vec<_>>();
+    for zip in nested {
+        unzip(&zip, &unpacked.root)?;
+        let _ = fs::remove_file(&zip);
+    }
+    Ok(unpacked)
+}
+
+fn unzip(archive: &Path, into: &Path) -> Result<(), String> {
+    let status = Command::new("unzip")
+        .arg("-q")
+        .arg("-o")
+        .arg(archive)
+        .arg("-d")
+        .arg(into)
+        .status()
+        .map_err(|error| format!("failed to run unzip: {error}"))?;
+    if !status.success() {
+        return Err(format!(
+            "unzip exited with {status} while unpacking {}",
+            archive.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Files under `dir` with `extension`, recursively, sorted by path text so a
+/// Notion page comes before the folder of its subpages.
+fn files_with_extension(dir: &Path, extension: &str) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current)
+            .map_err(|error| format!("failed to read {}: {error}", current.display()))?;
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|found| found == extension) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort_by_key(|path| path.to_string_lossy().into_owned());
+    Ok(files)
+}
+
+/// `path` relative to `root`, `/`-separated.
+fn relative_source(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `target` as written in a page in `dir` (both relative to the export
+/// root), resolved to a path from the root: percent-decoded, without query
+/// or fragment. `None` for URLs, anchors, and paths leaving the export.
+fn resolve(dir: &str, target: &str) -> Option<String> {
+    let target = target.split(['#', '?']).next().unwrap_or_default();
+    if target.is_empty() || target.contains("://") || target.starts_with(['/', '\\']) {
+        return None;
+    }
+    if target.starts_with("mailto:") || target.starts_with("data:") {
+        return None;
+    }
+    let decoded = percent_decode(target);
+    let mut segments = Vec::new();
+    for component in Path::new(dir).join(decoded).components() {
+        match component {
+            Component::Normal(segment) => segments.push(segment.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                segments.pop()?;
+            }
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(segments.join("/"))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Rewrites the targets of `[text](target)` and `![alt](target)` in a page
+/// in `dir` that resolve to a file under `root` to `<path>` from the root.
+fn root_relative_links(content: &str, dir: &str, root: &Path) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(index) = rest.find("](") {
+        let (before, after) = rest.split_at(index + 2);
+        output.push_str(before);
+        let Some(close) = after.find([')', '\n']) else {
+            rest = after;
+            break;
+        };
+        let raw = &after[..close];
+        let trimmed = raw.trim();
+        let (target, title) = match trimmed.strip_prefix('<') {
+            Some(inner) => inner.split_once('>').unwrap_or((inner, "")),
+            None => trimmed.split_once(' ').unwrap_or((trimmed, "")),
+        };
+        match resolve(dir, target).filter(|path| root.join(path).is_file()) {
+            Some(path) if title.trim().is_empty() => output.push_str(&format!("<{path}>")),
+            Some(path) => output.push_str(&format!("<{path}> {}", title.trim())),
+            None => output.push_str(raw),
+        }
+        rest = &after[close..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Replaces `](<source>)` links to imported pages with `](<url>)`; returns
+/// the content and how many links changed.
+pub fn link_pages(content: &str, urls: &HashMap<String, String>) -> (String, usize) {
+    let mut changed = 0;
+    let mut output = content.to_string();
+    for (source, url) in urls {
+        let from = format!("](<{source}>)");
+        let count = output.matches(&from).count();
+        if count > 0 {
+            output = output.replace(&from, &format!("]({url})"));
+            changed += count;
+        }
+    }
+    (output, changed)
+}
+
+/// `--map ROOT=GROUP[/FOLDER...]`.
+pub fn parse_mapping(raw: &str) -> Result<(String, String), String> {
+    let (root, destination) = raw
+        .rsplit_once('=')
+        .ok_or_else(|| format!("--map `{raw}` must be ROOT=GROUP[/FOLDER...]"))?;
+    let destination = destination.trim().trim_matches('/');
+    if root.trim().is_empty() || destination.is_empty() {
+        return Err(format!("--map `{raw}` must be ROOT=GROUP[/FOLDER...]"));
+    }
+    Ok((root.trim().to_string(), destination.to_string()))
+}
+
+/// The folder path (`GROUP/FOLDER/...`) a page mapped to `destination` is
+/// created in: the destination, the root's name when the destination is
+/// only a group, then the page's ancestors.
+pub fn folder_path(destination: &str, page: &Page) -> String {
+    let mut segments = destination
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    if segments.len() == 1 {
+        segments.push(folder_name(&page.root));
+    }
+    segments.extend(page.ancestors.iter().map(|title| folder_name(title)));
+    segments.join("/")
+}
+
+/// `title` usable as one folder path segment.
+fn folder_name(title: &str) -> String {
+    let name = title.replace('/', "-");
+    let name = name.trim();
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{folder_path, link_pages, parse_mapping, resolve, root_relative_links, Page};
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn relative_targets_resolve_from_the_page_folder() {
+        assert_eq!(
+            resolve("Docs abc", "Sub%20Page%20def.md#top").as_deref(),
+            Some("Docs abc/Sub Page def.md")
+        );
+        assert_eq!(resolve("ENG", "../img/a.png").as_deref(), Some("img/a.png"));
+        assert_eq!(resolve("", "../a.png"), None);
+        assert_eq!(resolve("ENG", "https://example.com/a.png"), None);
+        assert_eq!(resolve("ENG", "#section"), None);
+
+        let root = std::env::temp_dir().join(format!("kibel-import-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("ENG/attachments/1")).expect("export dir");
+        fs::write(root.join("ENG/attachments/1/a b.png"), "png").expect("image");
+        fs::write(root.join("ENG/Other_2.html"), "").expect("page");
+        assert_eq!(
+            root_relative_links(
+                "![a](attachments/1/a%20b.png \"t\") [o](Other_2.html) [x](missing.html) [w](https://example.com)",
+                "ENG",
+                &root
+            ),
+            "![a](<ENG/attachments/1/a b.png> \"t\") [o](<ENG/Other_2.html>) [x](missing.html) [w](https://example.com)"
+        );
+        let _ = fs::remove_dir_all(&root);
+
+        let urls = HashMap::from([(
+            "ENG/Other_2.html".to_string(),
+            "https://acme.kibe.la/notes/9".to_string(),
+        )]);
+        assert_eq!(
+            link_pages("[o](<ENG/Other_2.html>) [p](<ENG/P.html>)", &urls),
+            (
+                "[o](https://acme.kibe.la/notes/9) [p](<ENG/P.html>)".to_string(),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn mappings_and_folder_paths() {
+        assert_eq!(
+            parse_mapping("Eng Wiki=Engineering/Imported/"),
+            Ok(("Eng Wiki".to_string(), "Engineering/Imported".to_string()))
+        );
+        assert!(parse_mapping("Eng Wiki").is_err());
+        assert!(parse_mapping("Eng Wiki=/").is_err());
+
+        let page = Page {
+            source: "ENG/Deploy_3.html".to_string(),
+            title: "Deploy".to_string(),
+            content: String::new(),
+            root: "Eng Wiki".to_string(),
+            ancestors: vec!["Ops/Infra".to_string()],
+        };
+        assert_eq!(
+            folder_path("Engineering", &page),
+            "Engineering/Eng Wiki/Ops-Infra"
+        );
+        assert_eq!(
+            folder_path("Engineering/Imported", &page),
+            "Engineering/Imported/Ops-Infra"
+        );
+    }
+}
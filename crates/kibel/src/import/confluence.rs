@@ -0,0 +1,172 @@
+//! Confluence space exports in HTML: a folder per space holding `index.html`
+//! and a page file per page, with attachments under `attachments/`.
+
+use super::html;
+use super::{files_with_extension, relative_source, root_relative_links, Page};
+use std::fs;
+use std::path::Path;
+
+/// Every page under `root`. The space is named by its `index.html` title,
+/// else its folder; ancestors come from each page's breadcrumbs.
+pub fn read(root: &Path) -> Result<Vec<Page>, String> {
+    let mut pages = Vec::new();
+    for path in files_with_extension(root, "html")? {
+        if path.file_name().is_some_and(|name| name == "index.html") {
+            continue;
+        }
+        let raw = fs::read_to_string(&path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        let Some(body) = main_content(&raw) else {
+            continue;
+        };
+        let source = relative_source(root, &path);
+        let dir = source.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let space_dir = path.parent().unwrap_or(root);
+        let space = fs::read_to_string(space_dir.join("index.html"))
+            .ok()
+            .and_then(|index| title(&index))
+            .or_else(|| {
+                space_dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "Confluence".to_string());
+        let title = title(&raw).unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        pages.push(Page {
+            content: root_relative_links(&html::to_markdown(body), dir, root),
+            source,
+            title,
+            root: space,
+            ancestors: breadcrumbs(&raw),
+        });
+    }
+    Ok(pages)
+}
+
+/// The `<title>` text without the `SPACE : ` prefix page titles carry.
+fn title(raw: &str) -> Option<String> {
+    let lowered = raw.to_ascii_lowercase();
+    let start = lowered.find("<title>")? + "<title>".len();
+    let end = lowered[start..].find("</title>")? + start;
+    let text = html::text(&raw[start..end]);
+    let text = text
+        .split_once(" : ")
+        .map_or(text.as_str(), |(_, title)| title)
+        .trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The inside of `<div id="main-content">`, up to its closing tag.
+fn main_content(raw: &str) -> Option<&str> {
+    let marker = raw.find("id=\"main-content\"")?;
+    let start = raw[marker..].find('>')? + marker + 1;
+    let mut depth = 1;
+    let mut cursor = start;
+    while let Some(offset) = raw[cursor..].find("div") {
+        let at = cursor + offset;
+        cursor = at + 3;
+        let opening = raw[..at].ends_with('<');
+        let closing = raw[..at].ends_with("</");
+        let whole = raw[cursor..].starts_with(|c: char| c == '>' || c.is_whitespace());
+        if !whole {
+            continue;
+        }
+        if closing {
+            depth -= 1;
+            if depth == 0 {
+                return Some(&raw[start..at - 2]);
+            }
+        } else if opening {
+            depth += 1;
+        }
+    }
+    Some(&raw[start..])
+}
+
+/// Link texts of `<ol id="breadcrumbs">` other than the space overview.
+fn breadcrumbs(raw: &str) -> Vec<String> {
+    let Some(start) = raw.find("id=\"breadcrumbs\"") else {
+        return Vec::new();
+    };
+    let end = raw[start..]
+        .find("</ol>")
+        .map_or(raw.len(), |end| start + end);
+    let mut ancestors = Vec::new();
+    let mut rest = &raw[start..end];
+    while let Some(open) = rest.find("<a ") {
+        rest = &rest[open..];
+        let Some(close) = rest.find("</a>") else {
+            break;
+        };
+        let anchor = &rest[..close];
+        rest = &rest[close + 4..];
+        if anchor.contains("href=\"index.html\"") {
+            continue;
+        }
+        let text = anchor.find('>').map(|gt| html::text(&anchor[gt + 1..]));
+        if let Some(text) = text.filter(|text| !text.is_empty()) {
+            ancestors.push(text);
+        }
+    }
+    ancestors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read;
+    use std::fs;
+
+    #[test]
+    fn reads_pages_with_their_space_and_breadcrumbs() {
+        let root =
+            std::env::temp_dir().join(format!("kibel-import-confluence-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let space = root.join("ENG");
+        fs::create_dir_all(space.join("attachments/7")).expect("space dir");
+        fs::write(space.join("attachments/7/plan.png"), "png").expect("attachment");
+        fs::write(
+            space.join("index.html"),
+            "<html><head><title>Engineering</title></head><body>overview</body></html>",
+        )
+        .expect("index");
+        fs::write(
+            space.join("Deploy_7.html"),
+            r#"<html><head><title>ENG : Deploy</title></head><body>
+<div id="breadcrumb-section"><ol id="breadcrumbs">
+<li class="first"><span><a href="index.html">Engineering</a></span></li>
+<li><span><a href="Ops_5.html">Ops</a></span></li>
+</ol></div>
+<div id="main-content" class="wiki-content group">
+<p>See <a href="Ops_5.html">ops</a>.</p>
+<div class="confluence-information-macro"><p>Note</p></div>
+<p><img class="confluence-embedded-image" src="attachments/7/plan.png"></p>
+</div>
+<div class="pageSection group"><h2 id="attachments">Attachments:</h2></div>
+</body></html>"#,
+        )
+        .expect("page");
+        fs::write(
+            space.join("Ops_5.html"),
+            r#"<title>ENG : Ops</title><div id="main-content">ops</div>"#,
+        )
+        .expect("parent page");
+
+        let pages = read(&root).expect("reads");
+        assert_eq!(pages.len(), 2);
+        let deploy = &pages[0];
+        assert_eq!(deploy.source, "ENG/Deploy_7.html");
+        assert_eq!(deploy.title, "Deploy");
+        assert_eq!(deploy.root, "Engineering");
+        assert_eq!(deploy.ancestors, ["Ops"]);
+        assert_eq!(
+            deploy.content,
+            "See [ops](<ENG/Ops_5.html>).\n\nNote\n\n![](<ENG/attachments/7/plan.png>)"
+        );
+        assert_eq!(pages[1].ancestors, Vec::<String>::new());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,498 @@
+//! HTML to Markdown for exported wiki pages: headings, paragraphs, emphasis,
+//! code, links, images, lists, tables, and quotes. Other tags are dropped and
+//! their text kept.
+
+use std::collections::HashMap;
+
+pub fn to_markdown(html: &str) -> String {
+    let mut writer = Writer::default();
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let is_tag = rest.starts_with('<')
+            && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '/' | '!'));
+        if !is_tag {
+            let end = rest[1..].find('<').map_or(rest.len(), |index| index + 1);
+            writer.text(&decode_entities(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+        let end = tag_end(rest);
+        let tag = Tag::parse(&rest[1..end]);
+        rest = rest.get(end + 1..).unwrap_or_default();
+        if !tag.closing && matches!(tag.name.as_str(), "script" | "style") {
+            let close = format!("</{}", tag.name);
+            rest = rest
+                .to_ascii_lowercase()
+                .find(&close)
+                .map_or("", |index| &rest[index..]);
+            continue;
+        }
+        writer.tag(&tag);
+    }
+    writer.finish()
+}
+
+/// The text of `html` with its tags removed and entities decoded.
+pub fn text(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            _ => output.push(c),
+        }
+    }
+    decode_entities(&output)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Named entities common in exports, plus numeric ones.
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| {
+                let name = &rest[1..=end];
+                let c = match name {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" | "#39" => Some('\''),
+                    "nbsp" => Some(' '),
+                    _ => name
+                        .strip_prefix("#x")
+                        .or_else(|| name.strip_prefix("#X"))
+                        .map(|hex| u32::from_str_radix(hex, 16))
+                        .or_else(|| name.strip_prefix('#').map(str::parse))
+                        .and_then(Result::ok)
+                        .and_then(char::from_u32),
+                };
+                c.map(|c| (c, end + 2))
+            });
+        match decoded {
+            Some((c, length)) => {
+                output.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Index of the `>` closing the tag at the start of `input`; quoted
+/// attribute values may contain `>`.
+fn tag_end(input: &str) -> usize {
+    let mut quote = None;
+    for (index, c) in input.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            (None, '>') => return index,
+            _ => {}
+        }
+    }
+    input.len()
+}
+
+struct Tag {
+    name: String,
+    closing: bool,
+    attributes: HashMap<String, String>,
+}
+
+impl Tag {
+    fn parse(raw: &str) -> Self {
+        let raw = raw.trim_end_matches('/');
+        let (closing, raw) = match raw.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, raw),
+        };
+        let name_end = raw.find(|c: char| c.is_whitespace()).unwrap_or(raw.len());
+        let mut attributes = HashMap::new();
+        let mut rest = raw[name_end..].trim_start();
+        while !rest.is_empty() {
+            let key_end = rest
+                .find(|c: char| c == '=' || c.is_whitespace())
+                .unwrap_or(rest.len());
+            let key = rest[..key_end].to_ascii_lowercase();
+            rest = rest[key_end..].trim_start();
+            let value = if let Some(value) = rest.strip_prefix('=') {
+                let value = value.trim_start();
+                let (text, after) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                        (&value[1..end], value.get(end + 1..).unwrap_or_default())
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = after.trim_start();
+                decode_entities(text)
+            } else {
+                String::new()
+            };
+            if !key.is_empty() {
+                attributes.insert(key, value);
+            }
+        }
+        Self {
+            name: raw[..name_end].to_ascii_lowercase(),
+            closing,
+            attributes,
+        }
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .get(key)
+            .map(String::as_str)
+            .filter(|value| !value.trim().is_empty())
+    }
+}
+
+struct Writer {
+    /// Output, then one buffer per open link, table cell, and quote.
+    buffers: Vec<String>,
+    /// Open `<pre>` elements; text inside them is kept as is.
+    pre: usize,
+    /// Open lists: `None` for `<ul>`, the next number for `<ol>`.
+    lists: Vec<Option<usize>>,
+    /// `href` of each open `<a>`.
+    links: Vec<Option<String>>,
+    /// Rows of cells of each open `<table>`.
+    tables: Vec<Vec<Vec<String>>>,
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Self {
+            buffers: vec![String::new()],
+            pre: 0,
+            lists: Vec::new(),
+            links: Vec::new(),
+            tables: Vec::new(),
+        }
+    }
+}
+
+impl Writer {
+    fn out(&mut self) -> &mut String {
+        self.buffers
+            .last_mut()
+            .expect("the output buffer is never popped")
+    }
+
+    fn push(&mut self, text: &str) {
+        self.out().push_str(text);
+    }
+
+    /// Starts a new block; inside lists, blocks stay part of the item.
+    fn block(&mut self) {
+        if !self.lists.is_empty() {
+            return;
+        }
+        let out = self.out();
+        if out.is_empty() || out.ends_with("\n\n") {
+            return;
+        }
+        out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+    }
+
+    fn line(&mut self) {
+        let out = self.out();
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre > 0 {
+            self.push(text);
+            return;
+        }
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let out = self.out();
+        let at_line_start = out.is_empty() || out.ends_with(['\n', ' ']);
+        if text.starts_with(char::is_whitespace) && !at_line_start {
+            out.push(' ');
+        }
+        out.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+            out.push(' ');
+        }
+    }
+
+    fn tag(&mut self, tag: &Tag) {
+        let name = tag.name.as_str();
+        match (name, tag.closing) {
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                self.block();
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                self.push(&format!("{} ", "#".repeat(level)));
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true)
+            | ("p" | "div" | "section" | "article" | "header" | "footer" | "main", _) => {
+                self.block();
+            }
+            ("br", _) => self.push("\n"),
+            ("hr", _) => {
+                self.block();
+                self.push("---");
+                self.block();
+            }
+            ("strong" | "b", _) => self.push("**"),
+            ("em" | "i", _) => self.push("*"),
+            ("del" | "s" | "strike", _) => self.push("~~"),
+            ("code", _) if self.pre == 0 => self.push("`"),
+            ("pre", false) => {
+                self.block();
+                let language = code_language(tag);
+                self.push(&format!("```{language}\n"));
+                self.pre += 1;
+            }
+            ("pre", true) => {
+                self.pre = self.pre.saturating_sub(1);
+                self.line();
+                self.push("```");
+                self.block();
+            }
+            ("a", false) => {
+                self.links.push(tag.attribute("href").map(str::to_string));
+                self.buffers.push(String::new());
+            }
+            ("a", true) if self.buffers.len() > 1 && !self.links.is_empty() => {
+                let text = self.buffers.pop().unwrap_or_default();
+                let text = text.trim();
+                match self.links.pop().flatten() {
+                    Some(href) if !href.starts_with('#') => {
+                        let text = if text.is_empty() { href.as_str() } else { text };
+                        self.push(&format!("[{text}]({href})"));
+                    }
+                    _ => self.push(text),
+                }
+            }
+            ("img", false) => {
+                if let Some(src) = tag.attribute("src") {
+                    let alt = tag
+                        .attribute("alt")
+                        .or_else(|| tag.attribute("title"))
+                        .unwrap_or_default();
+                    self.push(&format!("![{alt}]({src})"));
+                }
+            }
+            ("ul", false) => {
+                self.line();
+                self.lists.push(None);
+            }
+            ("ol", false) => {
+                self.line();
+                let start = tag
+                    .attribute("start")
+                    .and_then(|start| start.parse().ok())
+                    .unwrap_or(1);
+                self.lists.push(Some(start));
+            }
+            ("ul" | "ol", true) => {
+                self.lists.pop();
+                self.line();
+                self.block();
+            }
+            ("li", false) => {
+                self.line();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.push(&format!("{}{marker}", " ".repeat(depth * 4)));
+            }
+            ("blockquote", false) => {
+                self.block();
+                self.buffers.push(String::new());
+            }
+            ("blockquote", true) if self.buffers.len() > 1 => {
+                let quoted = self.buffers.pop().unwrap_or_default();
+                let quoted = quoted
+                    .trim()
+                    .lines()
+                    .map(|line| format!("> {line}").trim_end().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.push(&quoted);
+                self.block();
+            }
+            ("table", false) => {
+                self.block();
+                self.tables.push(Vec::new());
+            }
+            ("table", true) => {
+                if let Some(rows) = self.tables.pop() {
+                    let table = render_table(&rows);
+                    self.push(&table);
+                    self.block();
+                }
+            }
+            ("tr", false) => {
+                if let Some(rows) = self.tables.last_mut() {
+                    rows.push(Vec::new());
+                }
+            }
+            ("td" | "th", false) if !self.tables.is_empty() => self.buffers.push(String::new()),
+            ("td" | "th", true) if !self.tables.is_empty() && self.buffers.len() > 1 => {
+                let cell = self.buffers.pop().unwrap_or_default();
+                let cell = cell
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .replace('|', "\\|");
+                if let Some(rows) = self.tables.last_mut() {
+                    if rows.is_empty() {
+                        rows.push(Vec::new());
+                    }
+                    if let Some(row) = rows.last_mut() {
+                        row.push(cell);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The Markdown, with at most one blank line in a row outside code
+    /// blocks and no trailing whitespace.
+    fn finish(mut self) -> String {
+        while self.buffers.len() > 1 {
+            let inner = self.buffers.pop().unwrap_or_default();
+            self.push(&inner);
+        }
+        let mut lines = Vec::new();
+        let mut in_fence = false;
+        for line in self.buffers[0].lines() {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            let line = if in_fence { line } else { line.trim_end() };
+            if !in_fence
+                && line.is_empty()
+                && lines.last().is_none_or(|last: &&str| last.is_empty())
+            {
+                continue;
+            }
+            lines.push(line);
+        }
+        lines.join("\n").trim().to_string()
+    }
+}
+
+/// The language of a code block: Confluence's `brush: <lang>` parameter or
+/// a `language-<lang>` class.
+fn code_language(tag: &Tag) -> String {
+    let brush = tag
+        .attribute("data-syntaxhighlighter-params")
+        .and_then(|params| {
+            params
+                .split(';')
+                .find_map(|param| param.trim().strip_prefix("brush:"))
+        });
+    let class = tag.attribute("class").and_then(|classes| {
+        classes
+            .split_whitespace()
+            .find_map(|class| class.strip_prefix("language-"))
+    });
+    brush
+        .or(class)
+        .map(str::trim)
+        .filter(|language| {
+            language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// A GitHub-flavored table; the first row is the header.
+fn render_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let line = |row: &[String]| {
+        let cells = (0..columns)
+            .map(|index| row.get(index).map_or("", String::as_str))
+            .collect::<Vec<_>>();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = vec![line(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_entities, text, to_markdown};
+
+    #[test]
+    fn converts_common_blocks_and_inline_markup() {
+        let html = r#"<h1 id="x">Deploy &amp; roll back</h1>
+<p>Run <code>make</code> then <strong>check</strong> the
+  <a href="Runbook_12.html">runbook</a>.<br/>Done.</p>
+<!-- comment -->
+<ul><li>one</li><li>two<ol><li>a</li><li>b</li></ol></li></ul>
+<pre class="syntaxhighlighter-pre" data-syntaxhighlighter-params="brush: bash; gutter: false">echo &lt;hi&gt;
+  indented</pre>
+<blockquote><p>quoted</p></blockquote>
+<img src="attachments/1/2.png" alt="diagram">
+<table><tbody><tr><th>Key</th><th>Value</th></tr><tr><td>a|b</td><td><p>c</p></td></tr></tbody></table>
+<script>alert(1)</script>"#;
+        assert_eq!(
+            to_markdown(html),
+            "# Deploy & roll back\n\n\
+             Run `make` then **check** the [runbook](Runbook_12.html).\nDone.\n\n\
+             - one\n- two\n    1. a\n    2. b\n\n\
+             ```bash\necho <hi>\n  indented\n```\n\n\
+             > quoted\n\n\
+             ![diagram](attachments/1/2.png)\n\n\
+             | Key | Value |\n| --- | --- |\n| a\\|b | c |"
+        );
+    }
+
+    #[test]
+    fn text_and_entities() {
+        assert_eq!(
+            text("<span> ENG : Home &#x2013; <b>v2</b></span>"),
+            "ENG : Home – v2"
+        );
+        assert_eq!(
+            decode_entities("a &amp b &#169; &unknown;"),
+            "a &amp b © &unknown;"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Notion workspace exports in "Markdown & CSV": a `Title <id>.md` file per
+//! page, its subpages in a `Title <id>/` folder, and a database as a CSV
+//! next to a folder of its rows.
+
+use super::{files_with_extension, relative_source, root_relative_links, Page};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Every page under `root`; database CSVs are skipped, their rows read as
+/// pages. The root is the first path segment, ancestors are the folders
+/// between it and the page, each without its Notion id.
+pub fn read(root: &Path) -> Result<Vec<Page>, String> {
+    let top = content_root(root)?;
+    let mut pages = Vec::new();
+    for path in files_with_extension(&top, "md")? {
+        let raw = fs::read_to_string(&path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        let source = relative_source(root, &path);
+        let within = relative_source(&top, &path);
+        let mut names = within
+            .split('/')
+            .map(|segment| display_name(segment.strip_suffix(".md").unwrap_or(segment)))
+            .collect::<Vec<_>>();
+        let file_title = names.pop().unwrap_or_default();
+        let page_root = names.first().cloned().unwrap_or_else(|| file_title.clone());
+        let ancestors = names.into_iter().skip(1).collect();
+        let (title, body) = split_title(&raw);
+        let dir = source.rsplit_once('/').map_or("", |(dir, _)| dir);
+        pages.push(Page {
+            title: title.unwrap_or(file_title),
+            content: root_relative_links(body, dir, root),
+            source,
+            root: page_root,
+            ancestors,
+        });
+    }
+    Ok(pages)
+}
+
+/// Some exports wrap the workspace in one folder (`Private & Shared/`);
+/// its contents are the top level then.
+fn content_root(root: &Path) -> Result<PathBuf, String> {
+    let mut current = root.to_path_buf();
+    loop {
+        let entries = fs::read_dir(&current)
+            .map_err(|error| format!("failed to read {}: {error}", current.display()))?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        match entries.as_slice() {
+            [only] if only.is_dir() => current.clone_from(only),
+            _ => return Ok(current),
+        }
+    }
+}
+
+/// `name` without the ` <32 hex digits>` id Notion appends.
+fn display_name(name: &str) -> String {
+    let stripped = name
+        .rsplit_once(' ')
+        .filter(|(_, id)| id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()));
+    stripped.map_or(name, |(title, _)| title).trim().to_string()
+}
+
+/// The leading `# Title` line, and the content after it.
+fn split_title(raw: &str) -> (Option<String>, &str) {
+    let raw = raw.trim_start_matches('\u{feff}');
+    let (first, rest) = raw.split_once('\n').unwrap_or((raw, ""));
+    match first.trim_end().strip_prefix("# ") {
+        Some(title) if !title.trim().is_empty() => (
+            Some(title.trim().to_string()),
+            rest.trim_start_matches(['\r', '\n']),
+        ),
+        _ => (None, raw),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{display_name, read};
+    use std::fs;
+
+    #[test]
+    fn reads_pages_databases_and_subpages() {
+        let root = std::env::temp_dir().join(format!("kibel-import-notion-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let id = "0123456789abcdef0123456789abcdef";
+        let wiki = root.join(format!("Private & Shared/Team Wiki {id}"));
+        fs::create_dir_all(wiki.join(format!("Tasks {id}"))).expect("export dirs");
+        fs::write(
+            root.join(format!("Private & Shared/Team Wiki {id}.md")),
+            format!("# Team Wiki\n\nSee [Tasks](Team%20Wiki%20{id}/Tasks%20{id}.csv) and ![](Team%20Wiki%20{id}/logo.png)\n"),
+        )
+        .expect("top page");
+        fs::write(wiki.join("logo.png"), "png").expect("image");
+        fs::write(wiki.join(format!("Tasks {id}.csv")), "Name\nShip\n").expect("database");
+        fs::write(
+            wiki.join(format!("Tasks {id}/Ship v2 {id}.md")),
+            "# Ship v2\n\nStatus: Done\n",
+        )
+        .expect("row");
+
+        let pages = read(&root).expect("reads");
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].title, "Team Wiki");
+        assert_eq!(pages[0].root, "Team Wiki");
+        assert_eq!(
+            pages[0].content,
+            format!(
+                "See [Tasks](<Private & Shared/Team Wiki {id}/Tasks {id}.csv>) and ![](<Private & Shared/Team Wiki {id}/logo.png>)\n"
+            )
+        );
+        assert_eq!(pages[1].title, "Ship v2");
+        assert_eq!(pages[1].root, "Team Wiki");
+        assert_eq!(pages[1].ancestors, ["Tasks"]);
+        assert_eq!(pages[1].content, "Status: Done\n");
+        assert_eq!(display_name("Notes 1234"), "Notes 1234");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,56 @@
+//! The `kibel` CLI as a library.
+//!
+//! [`run_command`] executes a command in-process with the same parsing,
+//! config/token resolution, and JSON envelope as the `kibel` binary, so test
+//! harnesses and embedding tools do not need to spawn subprocesses.
+
+mod anchor;
+mod app;
+mod browser;
+mod cli;
+mod clipboard;
+mod completion;
+mod concurrency;
+mod config_keys;
+mod content;
+mod dedupe;
+mod diff;
+mod digest;
+mod docs_mapping;
+mod error;
+mod export;
+mod folder_tree;
+mod frontmatter;
+mod graphql_doc;
+mod graphql_schema;
+mod help;
+mod hex;
+mod import;
+mod links;
+mod listing;
+mod manifest;
+mod note_ref;
+mod output;
+mod paginate;
+mod picker;
+mod prepublish;
+mod progress;
+mod publish;
+mod relay;
+mod render;
+mod report;
+mod search_filter;
+mod self_update;
+mod snapshot;
+mod stats;
+mod store;
+mod template;
+mod tokens;
+mod transfer;
+mod transform;
+mod tui;
+mod users;
+mod watch;
+
+pub use app::{run_command, run_from_env};
+pub use output::{CapturedOutput, CommandResult, OutputSink, StdioSink};
@@ -0,0 +1,120 @@
+use kibel_client::NotePath;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Characters that end a URL inside Markdown or HTML.
+const DELIMITERS: &[char] = &['(', ')', '[', ']', '<', '>', '"', '\'', '`'];
+
+/// Byte ranges of the whitespace/delimiter separated tokens of `content`.
+pub fn token_spans(content: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (index, c) in content.char_indices().chain([(content.len(), ' ')]) {
+        let boundary = c.is_whitespace() || DELIMITERS.contains(&c);
+        match (start, boundary) {
+            (None, false) => start = Some(index),
+            (Some(token_start), true) => {
+                spans.push((token_start, index));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+/// A link to a note found in content, as written and as a canonical path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteLink {
+    pub link: String,
+    pub path: NotePath,
+}
+
+/// Note links in `content`: URLs on `origin` and root-relative paths
+/// (`/notes/366`, `/@alice/12`, `/groups/1/folders/2/notes/366`), once each,
+/// first occurrence first. Trailing sentence punctuation is ignored.
+pub fn note_links(content: &str, origin: &str) -> Vec<NoteLink> {
+    let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+    let mut links: Vec<NoteLink> = Vec::new();
+    for (start, end) in token_spans(content) {
+        let link = content[start..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        let lowered = link.to_ascii_lowercase();
+        let path = match lowered.strip_prefix(&origin) {
+            Some(rest) if rest.starts_with('/') => &link[origin.len()..],
+            Some(_) => continue,
+            None if link.starts_with('/') => link,
+            None => continue,
+        };
+        let Ok(path) = NotePath::parse(path) else {
+            continue;
+        };
+        if !links.iter().any(|seen| seen.link == link) {
+            links.push(NoteLink {
+                link: link.to_string(),
+                path,
+            });
+        }
+    }
+    links
+}
+
+/// Markdown files (`*.md`) under `dir`, recursively, sorted by path.
+pub fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current)
+            .map_err(|error| format!("failed to read {}: {error}", current.display()))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|error| format!("failed to read {}: {error}", current.display()))?
+                .path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "md") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Comment body `lint links --fix` leaves on a note with dead links.
+pub fn dead_links_comment(links: &[String]) -> String {
+    let mut comment = "This note links to notes that no longer exist:\n".to_string();
+    for link in links {
+        comment.push_str(&format!("\n- `{link}`"));
+    }
+    comment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dead_links_comment, note_links};
+
+    #[test]
+    fn note_links_keep_origin_and_relative_note_paths_only() {
+        let content = "See [spec](/notes/12), https://Acme.kibe.la/@alice/3#c-1 and\n\
+                       /groups/1/folders/2/notes/12 or https://other.example/notes/4.\n\
+                       ![img](/attachments/5) https://acme.kibe.la/notes/99.";
+        let links = note_links(content, "https://acme.kibe.la/");
+        let found = links
+            .iter()
+            .map(|link| (link.link.as_str(), link.path.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("/notes/12", "/notes/12".to_string()),
+                ("https://Acme.kibe.la/@alice/3#c-1", "/@alice/3".to_string()),
+                ("/groups/1/folders/2/notes/12", "/notes/12".to_string()),
+                ("https://acme.kibe.la/notes/99", "/notes/99".to_string()),
+            ]
+        );
+        assert_eq!(
+            dead_links_comment(&["/notes/99".to_string()]),
+            "This note links to notes that no longer exist:\n\n- `/notes/99`"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use serde_json::Value;
+
+/// Client-side filters for `group list` and `folder list`, applied to the
+/// fetched page.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListFilter {
+    pub include_archived: bool,
+    pub private_only: bool,
+    /// Matched case-insensitively against `name`.
+    pub name_contains: Option<String>,
+}
+
+impl ListFilter {
+    /// Whether `item` passes: archived items only with `include_archived`,
+    /// only private items with `private_only`, and a `name` containing
+    /// `name_contains`. A missing flag counts as `false`.
+    pub fn matches(&self, item: &Value) -> bool {
+        let flag = |key: &str| item.get(key).and_then(Value::as_bool).unwrap_or(false);
+        if !self.include_archived && flag("isArchived") {
+            return false;
+        }
+        if self.private_only && !flag("isPrivate") {
+            return false;
+        }
+        self.name_contains.as_deref().is_none_or(|needle| {
+            item.get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_lowercase()
+                .contains(&needle.to_lowercase())
+        })
+    }
+
+    pub fn apply(&self, items: &Value) -> Vec<Value> {
+        items
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|item| self.matches(item))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A text table of `items` with one column per `(header, key)`. Booleans
+/// render as `yes`/`no`, missing values as `-`.
+pub fn render_table(items: &[Value], columns: &[(&str, &str)]) -> String {
+    let cell = |item: &Value, key: &str| match item.get(key) {
+        Some(Value::String(text)) => text.replace(['\n', '\t'], " "),
+        Some(Value::Bool(true)) => "yes".to_string(),
+        Some(Value::Bool(false)) => "no".to_string(),
+        Some(Value::Number(number)) => number.to_string(),
+        Some(Value::Object(object)) => object
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("-")
+            .to_string(),
+        _ => "-".to_string(),
+    };
+    let rows: Vec<Vec<String>> = items
+        .iter()
+        .map(|item| columns.iter().map(|(_, key)| cell(item, key)).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, (header, _))| {
+            rows.iter()
+                .map(|row| row[index].chars().count())
+                .chain([header.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let line = |cells: Vec<String>| {
+        let last = cells.len().saturating_sub(1);
+        cells
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| {
+                if index == last {
+                    text
+                } else {
+                    let padding = widths[index] - text.chars().count();
+                    format!("{text}{}", " ".repeat(padding))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    let mut lines = vec![line(
+        columns
+            .iter()
+            .map(|(header, _)| (*header).to_string())
+            .collect(),
+    )];
+    lines.extend(rows.into_iter().map(line));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_table, ListFilter};
+    use serde_json::json;
+
+    #[test]
+    fn filters_hide_archived_and_match_privacy_and_name() {
+        let items = json!([
+            {"id": "G1", "name": "Engineering", "isArchived": false, "isPrivate": false},
+            {"id": "G2", "name": "Old Eng", "isArchived": true, "isPrivate": true},
+            {"id": "G3", "name": "Security", "isArchived": false, "isPrivate": true},
+            {"id": "G4", "name": "Legacy"},
+        ]);
+        let ids = |filter: &ListFilter| {
+            filter
+                .apply(&items)
+                .iter()
+                .map(|item| item["id"].as_str().unwrap_or_default().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(&ListFilter::default()), ["G1", "G3", "G4"]);
+        assert_eq!(
+            ids(&ListFilter {
+                include_archived: true,
+                private_only: true,
+                ..ListFilter::default()
+            }),
+            ["G2", "G3"]
+        );
+        assert_eq!(
+            ids(&ListFilter {
+                include_archived: true,
+                name_contains: Some("ENG".to_string()),
+                ..ListFilter::default()
+            }),
+            ["G1", "G2"]
+        );
+    }
+
+    #[test]
+    fn table_pads_columns_and_renders_flags() {
+        let table = render_table(
+            &[
+                json!({"name": "Engineering", "id": "G1", "isPrivate": false}),
+                json!({"name": "Sec", "id": "G3", "isPrivate": true, "group": {"name": "Acme"}}),
+            ],
+            &[
+                ("NAME", "name"),
+                ("ID", "id"),
+                ("PRIVATE", "isPrivate"),
+                ("GROUP", "group"),
+            ],
+        );
+        assert_eq!(
+            table,
+            "NAME         ID  PRIVATE  GROUP\n\
+             Engineering  G1  no       -\n\
+             Sec          G3  yes      Acme"
+        );
+    }
+}
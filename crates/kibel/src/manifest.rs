@@ -0,0 +1,220 @@
+use crate::cli::{parse_folder_arg, NoteFolderArg};
+use crate::note_ref::parse_note_ref;
+use serde_json::Value;
+
+/// One row of a `note move-to-folder --batch` manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveManifestRow {
+    pub row: usize,
+    pub note_id: String,
+    pub from_folder: NoteFolderArg,
+    pub to_folder: NoteFolderArg,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestRowError {
+    pub row: usize,
+    pub message: String,
+}
+
+/// Parses a move manifest as JSON (array of objects) or CSV (with header).
+///
+/// Every row is validated before anything is returned so a single bad row
+/// fails the whole batch up front.
+pub fn parse_move_manifest(raw: &str) -> Result<Vec<MoveManifestRow>, Vec<ManifestRowError>> {
+    let records = if raw.trim_start().starts_with('[') {
+        json_records(raw)?
+    } else {
+        csv_records(raw)?
+    };
+
+    if records.is_empty() {
+        return Err(vec![ManifestRowError {
+            row: 0,
+            message: "manifest has no rows".to_string(),
+        }]);
+    }
+
+    let mut rows = Vec::with_capacity(records.len());
+    let mut errors = Vec::new();
+    for (row, note_id, from_folder, to_folder) in records {
+        match validate_row(row, &note_id, &from_folder, &to_folder) {
+            Ok(parsed) => rows.push(parsed),
+            Err(message) => errors.push(ManifestRowError { row, message }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(rows)
+    } else {
+        Err(errors)
+    }
+}
+
+type RawRecord = (usize, String, String, String);
+
+fn validate_row(
+    row: usize,
+    note_id: &str,
+    from_folder: &str,
+    to_folder: &str,
+) -> Result<MoveManifestRow, String> {
+    parse_note_ref(note_id).map_err(|error| format!("note_id: {error}"))?;
+    let from_folder =
+        parse_folder_arg(from_folder).map_err(|error| format!("from_folder: {error}"))?;
+    let to_folder = parse_folder_arg(to_folder).map_err(|error| format!("to_folder: {error}"))?;
+    if from_folder == to_folder {
+        return Err("from_folder and to_folder must differ".to_string());
+    }
+    Ok(MoveManifestRow {
+        row,
+        note_id: note_id.trim().to_string(),
+        from_folder,
+        to_folder,
+    })
+}
+
+fn json_records(raw: &str) -> Result<Vec<RawRecord>, Vec<ManifestRowError>> {
+    let parsed = serde_json::from_str::<Value>(raw).map_err(|error| {
+        vec![ManifestRowError {
+            row: 0,
+            message: format!("manifest is not valid JSON: {error}"),
+        }]
+    })?;
+    let items = parsed.as_array().ok_or_else(|| {
+        vec![ManifestRowError {
+            row: 0,
+            message: "JSON manifest must be an array of objects".to_string(),
+        }]
+    })?;
+
+    let mut records = Vec::with_capacity(items.len());
+    let mut errors = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let row = index + 1;
+        let field = |key: &str| {
+            item.get(key)
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| format!("missing string field `{key}`"))
+        };
+        match (field("note_id"), field("from_folder"), field("to_folder")) {
+            (Ok(note_id), Ok(from_folder), Ok(to_folder)) => {
+                records.push((row, note_id, from_folder, to_folder));
+            }
+            (Err(message), _, _) | (_, Err(message), _) | (_, _, Err(message)) => {
+                errors.push(ManifestRowError { row, message });
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        Err(errors)
+    }
+}
+
+fn csv_records(raw: &str) -> Result<Vec<RawRecord>, Vec<ManifestRowError>> {
+    let mut lines = raw
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let header = split_csv_line(header)
+        .into_iter()
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let (Some(note_index), Some(from_index), Some(to_index)) = (
+        column("note_id"),
+        column("from_folder"),
+        column("to_folder"),
+    ) else {
+        return Err(vec![ManifestRowError {
+            row: 0,
+            message: "CSV header must contain note_id,from_folder,to_folder".to_string(),
+        }]);
+    };
+
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    for (row, (_, line)) in lines.enumerate() {
+        let row = row + 1;
+        let fields = split_csv_line(line);
+        let get = |index: usize| fields.get(index).cloned();
+        match (get(note_index), get(from_index), get(to_index)) {
+            (Some(note_id), Some(from_folder), Some(to_folder)) => {
+                records.push((row, note_id, from_folder, to_folder));
+            }
+            _ => errors.push(ManifestRowError {
+                row,
+                message: format!("expected {} columns", header.len()),
+            }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        Err(errors)
+    }
+}
+
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_move_manifest;
+
+    #[test]
+    fn parse_csv_manifest_with_quoted_folder() {
+        let rows = parse_move_manifest(
+            "note_id,from_folder,to_folder\nN1,G1:Old,\"G1:New, Archive\"\n\n/notes/2,G1:Old,G1:New\n",
+        )
+        .expect("manifest should parse");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].to_folder.folder_name, "New, Archive");
+        assert_eq!(rows[1].row, 2);
+        assert_eq!(rows[1].note_id, "/notes/2");
+    }
+
+    #[test]
+    fn parse_json_manifest() {
+        let rows = parse_move_manifest(
+            r#"[{"note_id":"N1","from_folder":"G1:Old","to_folder":"G2:New"}]"#,
+        )
+        .expect("manifest should parse");
+        assert_eq!(rows[0].from_folder.group_id, "G1");
+        assert_eq!(rows[0].to_folder.group_id, "G2");
+    }
+
+    #[test]
+    fn parse_manifest_reports_every_invalid_row() {
+        let errors = parse_move_manifest(
+            "note_id,from_folder,to_folder\nN1,bad,G1:New\nN2,G1:Same,G1:Same\nN3,G1:Old,G1:New\n",
+        )
+        .expect_err("invalid rows should be rejected");
+        assert_eq!(
+            errors.iter().map(|error| error.row).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::{CliError, ErrorCode};
+use kibel_client::{KibelClient, NotePath, PathLookupInput};
+use serde_json::{json, Value};
+
+/// A user-supplied note reference: a GraphQL id, a Kibela path, or a full URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NoteRef {
+    Id(String),
+    Path(NotePath),
+    Url { origin: String, path: NotePath },
+}
+
+impl NoteRef {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Id(_) => "id",
+            Self::Path(_) => "path",
+            Self::Url { .. } => "url",
+        }
+    }
+
+    pub fn path(&self) -> Option<&NotePath> {
+        match self {
+            Self::Id(_) => None,
+            Self::Path(path) | Self::Url { path, .. } => Some(path),
+        }
+    }
+}
+
+/// Result of resolving a [`NoteRef`] against the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedNote {
+    pub id: String,
+    pub title: Option<String>,
+    pub path: Option<String>,
+    pub url: Option<String>,
+    pub redirected: bool,
+}
+
+pub fn parse_note_ref(raw: &str) -> Result<NoteRef, String> {
+    let value = raw.trim();
+    if value.is_empty() {
+        return Err("note reference is empty".to_string());
+    }
+
+    let lowered = value.to_ascii_lowercase();
+    if lowered.starts_with("https://") || lowered.starts_with("http://") {
+        let (scheme, rest) = value
+            .split_once("://")
+            .ok_or_else(|| format!("invalid note url: {value}"))?;
+        let (host, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, ""),
+        };
+        if host.is_empty() {
+            return Err(format!("note url has no host: {value}"));
+        }
+        let path =
+            NotePath::parse(path).map_err(|_| format!("note url has no note path: {value}"))?;
+        return Ok(NoteRef::Url {
+            origin: format!(
+                "{}://{}",
+                scheme.to_ascii_lowercase(),
+                host.to_ascii_lowercase()
+            ),
+            path,
+        });
+    }
+
+    if value.starts_with('/') || value.starts_with("notes/") || value.starts_with('@') {
+        return NotePath::parse(value)
+            .map(NoteRef::Path)
+            .map_err(|error| error.to_string());
+    }
+
+    // GraphQL ids are opaque base64 strings; a bare number is a note path id.
+    if value.bytes().all(|c| c.is_ascii_digit()) {
+        return NotePath::note(value)
+            .map(NoteRef::Path)
+            .map_err(|error| error.to_string());
+    }
+
+    if value.contains('/') {
+        return Err(format!(
+            "note reference must be an id, `/notes/<number>` path, or URL: {value}"
+        ));
+    }
+    Ok(NoteRef::Id(value.to_string()))
+}
+
+/// Derives the canonical `/notes/<number>` path from a Kibela GraphQL id.
+pub fn note_path_from_id(id: &str) -> Option<NotePath> {
+    NotePath::from_relay_id(id)
+}
+
+pub fn note_url(origin: &str, path: &str) -> String {
+    format!("{}{path}", origin.trim_end_matches('/'))
+}
+
+/// Resolves any note reference to a note id, querying `noteFromPath` when the
+/// reference is a path or URL.
+pub fn resolve_note_id(client: &KibelClient, raw: &str) -> Result<String, CliError> {
+    let reference =
+        parse_note_ref(raw).map_err(|error| CliError::new(ErrorCode::InputInvalid, error))?;
+    match reference {
+        NoteRef::Id(id) => Ok(id),
+        other => Ok(resolve_note_ref(client, &other)?.id),
+    }
+}
+
+pub fn resolve_note_ref(
+    client: &KibelClient,
+    reference: &NoteRef,
+) -> Result<ResolvedNote, CliError> {
+    if let NoteRef::Url { origin, .. } = reference {
+        let current = client.origin().to_ascii_lowercase();
+        if *origin != current {
+            return Err(CliError::new(
+                ErrorCode::InputInvalid,
+                format!("note url belongs to {origin}, but the current origin is {current}"),
+            )
+            .with_details(json!({ "url_origin": origin, "origin": current })));
+        }
+    }
+
+    match reference {
+        NoteRef::Id(id) => {
+            let note = client.get_note(id)?;
+            let path = note_path_from_id(&note.id);
+            Ok(ResolvedNote {
+                url: path.as_ref().map(|path| path.url(client.origin())),
+                id: note.id,
+                title: Some(note.title),
+                path: path.map(|path| path.to_string()),
+                redirected: false,
+            })
+        }
+        NoteRef::Path(path) | NoteRef::Url { path, .. } => {
+            let note = client.get_note_from_path(&PathLookupInput {
+                path: path.to_string(),
+                first: None,
+            })?;
+            let id = note
+                .get("id")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| CliError::new(ErrorCode::NotFound, "note not found"))?;
+            let url = note
+                .get("url")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned);
+            let canonical_path = url
+                .as_deref()
+                .and_then(|url| parse_note_ref(url).ok())
+                .and_then(|parsed| parsed.path().cloned())
+                .or_else(|| note_path_from_id(&id))
+                .unwrap_or_else(|| path.clone());
+            Ok(ResolvedNote {
+                title: note
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .map(ToOwned::to_owned),
+                url: url.or_else(|| Some(canonical_path.url(client.origin()))),
+                redirected: canonical_path != *path,
+                path: Some(canonical_path.to_string()),
+                id,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{note_path_from_id, parse_note_ref, NoteRef};
+    use kibel_client::NotePath;
+
+    #[test]
+    fn parse_note_ref_accepts_ids_paths_and_urls() {
+        assert_eq!(
+            parse_note_ref("QmxvZy8zNjY"),
+            Ok(NoteRef::Id("QmxvZy8zNjY".to_string()))
+        );
+        assert_eq!(
+            parse_note_ref("/notes/366"),
+            Ok(NoteRef::Path(NotePath::parse("/notes/366").expect("path")))
+        );
+        assert_eq!(
+            parse_note_ref("366"),
+            Ok(NoteRef::Path(NotePath::parse("/notes/366").expect("path")))
+        );
+        assert_eq!(
+            parse_note_ref("https://Acme.kibe.la/notes/366?foo=1#comment_2"),
+            Ok(NoteRef::Url {
+                origin: "https://acme.kibe.la".to_string(),
+                path: NotePath::parse("/notes/366").expect("path"),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_note_ref_collapses_folder_relative_paths() {
+        assert_eq!(
+            parse_note_ref("/groups/1/folders/2/notes/3/"),
+            Ok(NoteRef::Path(NotePath::parse("/notes/3").expect("path")))
+        );
+        assert_eq!(
+            parse_note_ref("@alice/12"),
+            Ok(NoteRef::Path(NotePath::parse("/@alice/12").expect("path")))
+        );
+    }
+
+    #[test]
+    fn parse_note_ref_rejects_empty_and_ambiguous_values() {
+        assert!(parse_note_ref("  ").is_err());
+        assert!(parse_note_ref("https://acme.kibe.la").is_err());
+        assert!(parse_note_ref("foo/bar").is_err());
+    }
+
+    #[test]
+    fn note_path_from_id_decodes_relay_ids() {
+        assert_eq!(
+            note_path_from_id("QmxvZy8zNjY").map(|path| path.to_string()),
+            Some("/notes/366".to_string())
+        );
+        assert_eq!(
+            note_path_from_id("QmxvZy8zNjY=").map(|path| path.to_string()),
+            Some("/notes/366".to_string())
+        );
+        assert_eq!(note_path_from_id("VXNlci8x"), None);
+        assert_eq!(note_path_from_id("N1"), None);
+    }
+}
@@ -0,0 +1,318 @@
+use serde_json::Value;
+use std::io::{self, IsTerminal, Write};
+
+/// Destination for everything a command prints.
+///
+/// `stdout` receives the JSON envelope (or text message) and help output;
+/// `stderr` receives text-mode errors and usage errors. Text is passed through
+/// unchanged, including trailing newlines.
+pub trait OutputSink {
+    fn stdout(&mut self, text: &str);
+    fn stderr(&mut self, text: &str);
+
+    /// Whether `stdout` is a terminal, for `--color auto`.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl<T: OutputSink + ?Sized> OutputSink for &mut T {
+    fn stdout(&mut self, text: &str) {
+        (**self).stdout(text);
+    }
+
+    fn stderr(&mut self, text: &str) {
+        (**self).stderr(text);
+    }
+
+    fn is_terminal(&self) -> bool {
+        (**self).is_terminal()
+    }
+}
+
+/// Writes to the process stdout/stderr, as the `kibel` binary does.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdioSink;
+
+impl OutputSink for StdioSink {
+    fn stdout(&mut self, text: &str) {
+        let _ = io::stdout().write_all(text.as_bytes());
+    }
+
+    fn stderr(&mut self, text: &str) {
+        let _ = io::stderr().write_all(text.as_bytes());
+    }
+
+    fn is_terminal(&self) -> bool {
+        io::stdout().is_terminal()
+    }
+}
+
+/// Collects output in memory; pass `&mut CapturedOutput` to keep ownership.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CapturedOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl OutputSink for CapturedOutput {
+    fn stdout(&mut self, text: &str) {
+        self.stdout.push_str(text);
+    }
+
+    fn stderr(&mut self, text: &str) {
+        self.stderr.push_str(text);
+    }
+}
+
+/// `--color` setting; `--no-color` is the same as `never`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color a terminal unless `NO_COLOR` is set to a non-empty value.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Output settings shared by every command, resolved once from the global
+/// flags so handlers never decide on their own what to print or style.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputContext {
+    /// Print the JSON envelope (and JSON lines for streams) instead of text.
+    pub json: bool,
+    /// Keep stdout empty; failures are still reported on stderr.
+    pub quiet: bool,
+    /// Style text with ANSI escapes. Never set for JSON output.
+    pub color: bool,
+    /// Draw progress bars on stderr: text output on a terminal, not quiet.
+    pub progress: bool,
+}
+
+impl OutputContext {
+    pub fn new(
+        json: bool,
+        quiet: bool,
+        choice: ColorChoice,
+        terminal: bool,
+        no_color_env: bool,
+    ) -> Self {
+        let color = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => terminal && !no_color_env,
+        };
+        Self {
+            json,
+            quiet,
+            color: color && !json,
+            progress: terminal && !json && !quiet,
+        }
+    }
+
+    /// How search highlights are rendered in text output.
+    pub fn highlight(self) -> Highlight {
+        if self.color {
+            Highlight::Ansi
+        } else {
+            Highlight::Markdown
+        }
+    }
+
+    /// Writes a command's stdout output unless `--quiet` is set.
+    pub fn stdout(self, io: &mut dyn OutputSink, text: &str) {
+        if !self.quiet {
+            io.stdout(text);
+        }
+    }
+
+    /// Text-mode error line: `[CODE] message`, with the code in red.
+    pub fn error_line(self, code: &str, message: &str) -> String {
+        if self.color {
+            format!("\u{1b}[31m[{code}]\u{1b}[0m {message}\n")
+        } else {
+            format!("[{code}] {message}\n")
+        }
+    }
+}
+
+/// Outcome of [`crate::run_command`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandResult {
+    /// Process exit code the binary would have returned.
+    pub exit_code: i32,
+    /// The `ok/data/error/meta` envelope, regardless of `--text`/`--json`.
+    /// `None` for usage errors, `--help`, and `completion`.
+    pub envelope: Option<Value>,
+}
+
+impl CommandResult {
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// The envelope `data` on success.
+    #[must_use]
+    pub fn data(&self) -> Option<&Value> {
+        self.envelope
+            .as_ref()
+            .and_then(|envelope| envelope.get("data"))
+            .filter(|data| !data.is_null())
+    }
+
+    /// The envelope `error.code` on failure (e.g. `INPUT_INVALID`).
+    #[must_use]
+    pub fn error_code(&self) -> Option<&str> {
+        self.envelope
+            .as_ref()
+            .and_then(|envelope| envelope.pointer("/error/code"))
+            .and_then(Value::as_str)
+    }
+}
+
+/// How [`html_to_text`] renders `<b>`/`<strong>`/`<em>`/`<mark>` highlights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    /// Drop the markup and keep the text.
+    Plain,
+    /// Wrap highlights in `**`.
+    Markdown,
+    /// Bold them with ANSI escapes, for terminals.
+    Ansi,
+}
+
+impl Highlight {
+    fn open(self) -> &'static str {
+        match self {
+            Self::Plain => "",
+            Self::Markdown => "**",
+            Self::Ansi => "\u{1b}[1m",
+        }
+    }
+
+    fn close(self) -> &'static str {
+        match self {
+            Self::Plain => "",
+            Self::Markdown => "**",
+            Self::Ansi => "\u{1b}[0m",
+        }
+    }
+}
+
+/// Converts an HTML fragment such as `contentSummaryHtml` to one line of
+/// text: tags are dropped, block and line breaks become spaces, entities
+/// are decoded, and whitespace runs collapse.
+pub fn html_to_text(html: &str, highlight: Highlight) -> String {
+    let mut raw = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(['<', '&']) {
+        raw.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with('<') {
+            let Some(end) = rest.find('>') else {
+                raw.push_str(rest);
+                rest = "";
+                break;
+            };
+            raw.push_str(tag_replacement(&rest[1..end], highlight));
+            rest = &rest[end + 1..];
+        } else {
+            let (decoded, consumed) = decode_entity(rest);
+            raw.push_str(&decoded);
+            rest = &rest[consumed..];
+        }
+    }
+    raw.push_str(rest);
+    raw.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn tag_replacement(tag: &str, highlight: Highlight) -> &'static str {
+    let closing = tag.starts_with('/');
+    let name = tag
+        .trim_start_matches('/')
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match name.as_str() {
+        "b" | "strong" | "em" | "mark" if closing => highlight.close(),
+        "b" | "strong" | "em" | "mark" => highlight.open(),
+        "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => " ",
+        _ => "",
+    }
+}
+
+/// Decodes the entity at the start of `input`, returning the text and how
+/// many bytes it used. Unknown entities are kept as a literal `&`.
+fn decode_entity(input: &str) -> (String, usize) {
+    let Some(end) = input.find(';').filter(|end| *end <= 10) else {
+        return ("&".to_string(), 1);
+    };
+    let name = &input[1..end];
+    let decoded = match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" | "#39" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => name
+            .strip_prefix("#x")
+            .or_else(|| name.strip_prefix("#X"))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .or_else(|| name.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+            .and_then(char::from_u32),
+    };
+    match decoded {
+        Some(c) => (c.to_string(), end + 1),
+        None => ("&".to_string(), 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{html_to_text, ColorChoice, Highlight, OutputContext};
+
+    #[test]
+    fn html_to_text_strips_tags_and_styles_highlights() {
+        let html =
+            "<p>Deploy <b>rust</b>&nbsp;crate</p><p>&lt;v2&gt; &amp; <em>Rust</em>&#x21;</p>";
+        assert_eq!(
+            html_to_text(html, Highlight::Plain),
+            "Deploy rust crate <v2> & Rust!"
+        );
+        assert_eq!(
+            html_to_text(html, Highlight::Markdown),
+            "Deploy **rust** crate <v2> & **Rust**!"
+        );
+        assert_eq!(
+            html_to_text("<mark>ok</mark>", Highlight::Ansi),
+            "\u{1b}[1mok\u{1b}[0m"
+        );
+        assert_eq!(
+            html_to_text("a & b <br/>c &unknown; 1 < 2", Highlight::Plain),
+            "a & b c &unknown; 1 < 2"
+        );
+    }
+
+    #[test]
+    fn output_context_resolves_color_and_never_colors_json() {
+        let auto = |terminal, no_color| {
+            OutputContext::new(false, false, ColorChoice::Auto, terminal, no_color).color
+        };
+        assert!(auto(true, false));
+        assert!(!auto(true, true));
+        assert!(!auto(false, false));
+        assert!(OutputContext::new(false, false, ColorChoice::Always, false, true).color);
+        assert!(!OutputContext::new(false, false, ColorChoice::Never, true, false).color);
+        assert!(!OutputContext::new(true, false, ColorChoice::Always, true, false).color);
+        assert!(OutputContext::new(false, false, ColorChoice::Never, true, false).progress);
+        assert!(!OutputContext::new(true, false, ColorChoice::Auto, true, false).progress);
+        assert!(!OutputContext::new(false, true, ColorChoice::Auto, true, false).progress);
+
+        let plain = OutputContext::default();
+        assert_eq!(plain.error_line("NOT_FOUND", "gone"), "[NOT_FOUND] gone\n");
+        assert_eq!(plain.highlight(), Highlight::Markdown);
+    }
+}
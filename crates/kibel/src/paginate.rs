@@ -0,0 +1,128 @@
+use serde_json::Value;
+
+/// Upper bounds for merging several pages into one response.
+pub const MAX_MERGED_ITEMS: usize = 1000;
+pub const MAX_MERGED_PAGES: u32 = 50;
+
+/// One fetched connection page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The connection `pageInfo` (`{hasNextPage, endCursor}`).
+    pub page_info: Value,
+    /// Set by the fetcher to stop after this page (e.g. a date cutoff was hit).
+    pub stop: bool,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, page_info: Value) -> Self {
+        Self {
+            items,
+            page_info,
+            stop: false,
+        }
+    }
+
+    fn next_cursor(&self) -> Option<&str> {
+        let has_next = self
+            .page_info
+            .get("hasNextPage")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        self.page_info
+            .get("endCursor")
+            .and_then(Value::as_str)
+            .filter(|cursor| has_next && !cursor.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedPages<T> {
+    pub items: Vec<T>,
+    pub pages: u32,
+    /// `pageInfo` of the last fetched page; its cursor resumes after `items`
+    /// unless the merge was cut short by `limit`.
+    pub page_info: Value,
+    /// `true` when the connection has no further pages.
+    pub exhausted: bool,
+    /// `true` when items were dropped to honor `limit`.
+    pub truncated: bool,
+}
+
+/// Follows `endCursor` from `after`, merging pages until the connection is
+/// exhausted, `limit` items are collected, `max_pages` pages are fetched, or
+/// the fetcher marks a page with `stop`.
+pub fn collect_pages<T, E, F>(
+    after: Option<String>,
+    limit: Option<usize>,
+    max_pages: u32,
+    mut fetch: F,
+) -> Result<MergedPages<T>, E>
+where
+    F: FnMut(Option<String>) -> Result<Page<T>, E>,
+{
+    let mut merged = MergedPages {
+        items: Vec::new(),
+        pages: 0,
+        page_info: Value::Null,
+        exhausted: false,
+        truncated: false,
+    };
+    let mut after = after;
+    while merged.pages < max_pages {
+        let page = fetch(after.take())?;
+        merged.pages += 1;
+        let next = page.next_cursor().map(ToOwned::to_owned);
+        merged.exhausted = next.is_none();
+        merged.items.extend(page.items);
+        merged.page_info = page.page_info;
+        if let Some(limit) = limit {
+            if merged.items.len() >= limit {
+                merged.truncated = merged.items.len() > limit;
+                merged.items.truncate(limit);
+                break;
+            }
+        }
+        match next {
+            Some(cursor) if !page.stop => after = Some(cursor),
+            _ => break,
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect_pages, Page};
+    use serde_json::json;
+
+    fn fetch_numbers(after: Option<String>) -> Result<Page<u32>, String> {
+        let start = after.map_or(0, |cursor| cursor.parse::<u32>().unwrap_or(0));
+        let end = start + 3;
+        Ok(Page::new(
+            (start..end).collect(),
+            json!({"hasNextPage": end < 7, "endCursor": end.to_string()}),
+        ))
+    }
+
+    #[test]
+    fn collect_pages_merges_until_exhausted() {
+        let merged = collect_pages(None, None, 10, fetch_numbers).expect("fetch succeeds");
+        assert_eq!(merged.items, (0..9).collect::<Vec<_>>());
+        assert_eq!(merged.pages, 3);
+        assert!(merged.exhausted);
+        assert!(!merged.truncated);
+    }
+
+    #[test]
+    fn collect_pages_honors_limit_and_max_pages() {
+        let merged = collect_pages(None, Some(4), 10, fetch_numbers).expect("fetch succeeds");
+        assert_eq!(merged.items, vec![0, 1, 2, 3]);
+        assert_eq!(merged.pages, 2);
+        assert!(merged.truncated);
+
+        let merged = collect_pages(None, None, 1, fetch_numbers).expect("fetch succeeds");
+        assert_eq!(merged.items.len(), 3);
+        assert!(!merged.exhausted);
+    }
+}
@@ -0,0 +1,184 @@
+use console::{Key, Term};
+
+/// Rows of matches shown below the query line.
+const VISIBLE_ROWS: usize = 10;
+
+/// Scores `text` against `query` as a case-insensitive subsequence; `None`
+/// when some query character is missing. Consecutive matches and matches at
+/// word starts score higher, and every place the first query character
+/// occurs is tried, so `gu` finds the `gu` of "Onboarding guide" rather than
+/// the first `g`. Whitespace in the query is ignored.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let query = query
+        .chars()
+        .filter(|wanted| !wanted.is_whitespace())
+        .collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let Some(&first) = query.first() else {
+        return Some(0);
+    };
+    (0..text.len())
+        .filter(|&start| same_letter(text[start], first))
+        .filter_map(|start| score_from(&query, &text, start))
+        .max()
+}
+
+/// Greedy match of `query` with its first character pinned at `start`.
+fn score_from(query: &[char], text: &[char], start: usize) -> Option<i64> {
+    let (mut score, mut position, mut previous) = (0, start, None::<usize>);
+    for &wanted in query {
+        let found = (position..text.len()).find(|&index| same_letter(text[index], wanted))?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+fn same_letter(left: char, right: char) -> bool {
+    left.to_lowercase().eq(right.to_lowercase())
+}
+
+/// Indexes of the `labels` matching `query`, best first; ties keep the
+/// original order.
+pub fn rank(query: &str, labels: &[String]) -> Vec<usize> {
+    let mut scored = labels
+        .iter()
+        .enumerate()
+        .filter_map(|(index, label)| fuzzy_score(query, label).map(|score| (score, index)))
+        .collect::<Vec<_>>();
+    scored.sort_by(|left, right| right.0.cmp(&left.0));
+    scored.into_iter().map(|(_, index)| index).collect()
+}
+
+/// Lets the user filter `labels` by typing and pick one with Enter, drawing
+/// on stderr so stdout only carries the result.
+///
+/// Returns `None` when the picker is cancelled with Esc / Ctrl-C or nothing
+/// matches.
+pub fn pick(prompt: &str, labels: &[String]) -> Result<Option<usize>, String> {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err("--pick needs an interactive terminal".to_string());
+    }
+    let width = usize::from(term.size().1).saturating_sub(3).max(10);
+    let (mut query, mut cursor, mut drawn) = (String::new(), 0_usize, 0);
+    let _ = term.hide_cursor();
+    let picked = loop {
+        let matches = rank(&query, labels);
+        cursor = cursor.min(matches.len().saturating_sub(1));
+        let mut lines = vec![format!(
+            "{prompt} {}/{}> {query}",
+            matches.len(),
+            labels.len()
+        )];
+        let start = cursor.saturating_sub(VISIBLE_ROWS - 1);
+        for (row, index) in matches.iter().enumerate().skip(start).take(VISIBLE_ROWS) {
+            let marker = if row == cursor { '>' } else { ' ' };
+            lines.push(format!(
+                "{marker} {}",
+                truncate_columns(&labels[*index], width)
+            ));
+        }
+        let drawing = term
+            .clear_last_lines(drawn)
+            .and_then(|()| term.write_line(&lines.join("\n")));
+        if let Err(error) = drawing {
+            break Err(format!("failed to draw the picker: {error}"));
+        }
+        drawn = lines.len();
+
+        match term.read_key() {
+            Ok(Key::Enter) => break Ok(matches.get(cursor).copied()),
+            Ok(Key::Escape | Key::CtrlC) => break Ok(None),
+            Ok(Key::ArrowUp | Key::BackTab) => cursor = cursor.saturating_sub(1),
+            Ok(Key::ArrowDown | Key::Tab) => cursor += 1,
+            Ok(Key::Backspace) => {
+                query.pop();
+                cursor = 0;
+            }
+            Ok(Key::Char(typed)) if !typed.is_control() => {
+                query.push(typed);
+                cursor = 0;
+            }
+            Ok(_) => {}
+            Err(error) => break Err(format!("failed to read a key: {error}")),
+        }
+    };
+    let _ = term.clear_last_lines(drawn);
+    let _ = term.show_cursor();
+    picked
+}
+
+/// Terminal columns of `ch`: two for East Asian wide characters and emoji,
+/// one otherwise. Close enough to keep wide titles from wrapping a redraw.
+pub fn char_columns(ch: char) -> usize {
+    match u32::from(ch) {
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Terminal columns of `text` (see [`char_columns`]).
+pub fn display_columns(text: &str) -> usize {
+    text.chars().map(char_columns).sum()
+}
+
+/// Cuts `label` to at most `width` columns, ending it with a one-column `…`
+/// when cut.
+pub fn truncate_columns(label: &str, width: usize) -> String {
+    if display_columns(label) <= width {
+        return label.to_string();
+    }
+    let mut columns = 0;
+    let mut cut = String::new();
+    for ch in label.chars() {
+        columns += char_columns(ch);
+        if columns + 1 > width {
+            break;
+        }
+        cut.push(ch);
+    }
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_score, rank, truncate_columns};
+
+    #[test]
+    fn fuzzy_matching_prefers_word_starts_and_runs() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert!(fuzzy_score("xyz", "onboarding guide").is_none());
+        assert!(fuzzy_score("OG", "onboarding guide").is_some());
+
+        let labels = ["dog tricks", "Onboarding guide", "long doc"]
+            .map(str::to_string)
+            .to_vec();
+        assert_eq!(rank("gu", &labels), vec![1]);
+        assert_eq!(rank("og", &labels), vec![0, 1, 2]);
+        assert_eq!(rank("doc", &labels), vec![2, 0]);
+        assert_eq!(rank("", &labels), vec![0, 1, 2]);
+
+        assert_eq!(truncate_columns("設計メモ", 5), "設計…");
+        assert_eq!(truncate_columns("short", 10), "short");
+    }
+}
@@ -0,0 +1,180 @@
+use kibel_client::PrePublishChecks;
+use serde::Serialize;
+
+/// One failed pre-publish rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// `max_bytes`, `required_section`, `forbidden_string`, or `broken_link`.
+    pub rule: &'static str,
+    pub message: String,
+    /// 1-based line the violation was found on, when it has one.
+    pub line: Option<usize>,
+}
+
+impl Violation {
+    pub fn broken_link(link: &str, reason: &str, line: Option<usize>) -> Self {
+        Self {
+            rule: "broken_link",
+            message: format!("`{link}` {reason}"),
+            line,
+        }
+    }
+}
+
+/// Runs the rules that need no network: size, required sections, forbidden
+/// strings, and relative links (`./img.png`, `other.md`) that cannot resolve
+/// once the content is on Kibela. Note links are checked by the caller.
+pub fn check(content: &str, rules: &PrePublishChecks) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if let Some(max) = rules.max_bytes {
+        if content.len() > max {
+            violations.push(Violation {
+                rule: "max_bytes",
+                message: format!("content is {} bytes; the limit is {max}", content.len()),
+                line: None,
+            });
+        }
+    }
+
+    let headings = content
+        .lines()
+        .filter_map(heading_text)
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    for section in &rules.required_sections {
+        if !headings.contains(&section.trim().to_lowercase()) {
+            violations.push(Violation {
+                rule: "required_section",
+                message: format!("missing section `{}`", section.trim()),
+                line: None,
+            });
+        }
+    }
+
+    for (index, line) in content.lines().enumerate() {
+        for pattern in rules.forbidden.iter().filter(|pattern| !pattern.is_empty()) {
+            if line.contains(pattern.as_str()) {
+                violations.push(Violation {
+                    rule: "forbidden_string",
+                    message: format!("contains forbidden string `{pattern}`"),
+                    line: Some(index + 1),
+                });
+            }
+        }
+        if rules.check_links {
+            for target in link_targets(line) {
+                if is_relative(target) {
+                    violations.push(Violation::broken_link(
+                        target,
+                        "is a relative link and will not resolve on Kibela",
+                        Some(index + 1),
+                    ));
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// 1-based line of the first occurrence of `needle` in `content`.
+pub fn line_of(content: &str, needle: &str) -> Option<usize> {
+    content
+        .lines()
+        .position(|line| line.contains(needle))
+        .map(|index| index + 1)
+}
+
+/// The text of an ATX heading line (`## Summary` -> `Summary`).
+fn heading_text(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some(rest.trim().trim_end_matches('#').trim_end())
+}
+
+/// Targets of inline Markdown links and images on `line`: `[text](target)`.
+fn link_targets(line: &str) -> Vec<&str> {
+    let mut targets = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("](") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find(')') else {
+            break;
+        };
+        let target = after[..end].split_whitespace().next().unwrap_or_default();
+        let target = target.trim_start_matches('<').trim_end_matches('>');
+        if !target.is_empty() {
+            targets.push(target);
+        }
+        rest = &after[end..];
+    }
+    targets
+}
+
+/// Whether `target` is relative to the file it was written in: not a URL,
+/// a root-relative path, or an anchor.
+fn is_relative(target: &str) -> bool {
+    if target.starts_with(['/', '#', '?']) {
+        return false;
+    }
+    let scheme_end = target.find(':');
+    let path_start = target.find(['/', '?', '#']);
+    let has_scheme = match (scheme_end, path_start) {
+        (Some(colon), Some(slash)) => colon < slash,
+        (Some(_), None) => true,
+        _ => false,
+    };
+    !has_scheme
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, line_of};
+    use kibel_client::PrePublishChecks;
+
+    #[test]
+    fn check_reports_each_rule_with_lines() {
+        let rules = PrePublishChecks {
+            max_bytes: Some(40),
+            required_sections: vec!["Summary".to_string(), "Rollback".to_string()],
+            forbidden: vec!["AKIA".to_string()],
+            check_links: true,
+        };
+        let content = "# summary\n\
+                       key AKIAEXAMPLE\n\
+                       ![diagram](./diagram.png) [spec](/notes/1) [site](https://example.com/a)\n\
+                       [mail](mailto:a@example.com) [anchor](#summary) [doc](docs/guide.md)";
+        let found = check(content, &rules)
+            .into_iter()
+            .map(|violation| (violation.rule, violation.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("max_bytes", None),
+                ("required_section", None),
+                ("forbidden_string", Some(2)),
+                ("broken_link", Some(3)),
+                ("broken_link", Some(4)),
+            ]
+        );
+        assert_eq!(line_of(content, "/notes/1"), Some(3));
+
+        let clean = "## Summary\n\n## Rollback #\n";
+        assert!(check(clean, &rules).is_empty());
+        assert!(check(
+            "[doc](docs/guide.md)",
+            &PrePublishChecks {
+                check_links: false,
+                ..PrePublishChecks::default()
+            }
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::output::OutputContext;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const TEMPLATE: &str = "{msg} [{bar:30}] {pos}/{len} ({eta})";
+const SPINNER_TEMPLATE: &str = "{spinner} {msg} {pos} ({elapsed})";
+
+/// Progress of a long-running batch, drawn on stderr.
+///
+/// Only shown for text output on a terminal (see [`OutputContext::progress`]);
+/// otherwise every call is a no-op, so callers never branch on it.
+#[derive(Debug)]
+pub struct Progress {
+    bar: Option<ProgressBar>,
+    label: String,
+    failures: AtomicU64,
+}
+
+impl Progress {
+    /// A bar of `total` items, or a spinner when the total is not known yet.
+    pub fn new(out: OutputContext, label: &str, total: Option<u64>) -> Self {
+        let bar = out.progress.then(|| {
+            let bar = match total {
+                Some(total) => {
+                    ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stderr())
+                        .with_style(bar_style())
+                }
+                None => ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr())
+                    .with_style(spinner_style()),
+            };
+            bar.set_message(label.to_string());
+            bar
+        });
+        Self {
+            bar,
+            label: label.to_string(),
+            failures: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets or corrects the total, switching a spinner to a bar.
+    pub fn set_total(&self, total: u64) {
+        if let Some(bar) = &self.bar {
+            bar.set_style(bar_style());
+            bar.set_length(total);
+        }
+    }
+
+    /// Counts `items` processed items.
+    pub fn advance(&self, items: u64) {
+        if let Some(bar) = &self.bar {
+            bar.inc(items);
+        }
+    }
+
+    /// Counts one processed item, and a failure unless `ok`.
+    pub fn record(&self, ok: bool) {
+        if !ok {
+            let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(bar) = &self.bar {
+                bar.set_message(format!("{} ({failures} failed)", self.label));
+            }
+        }
+        self.advance(1);
+    }
+
+    /// Clears the bar so the command's own output starts on a clean line.
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        // Errors returned with `?` skip `finish`; never leave a bar behind.
+        self.finish();
+    }
+}
+
+fn bar_style() -> ProgressStyle {
+    ProgressStyle::with_template(TEMPLATE)
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ")
+}
+
+fn spinner_style() -> ProgressStyle {
+    ProgressStyle::with_template(SPINNER_TEMPLATE)
+        .unwrap_or_else(|_| ProgressStyle::default_spinner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Progress;
+    use crate::output::OutputContext;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn hidden_progress_still_counts_failures() {
+        let progress = Progress::new(OutputContext::default(), "moving notes", None);
+        assert!(progress.bar.is_none());
+        progress.set_total(3);
+        progress.record(true);
+        progress.record(false);
+        progress.record(false);
+        progress.finish();
+        assert_eq!(progress.failures.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,218 @@
+//! `kibel publish`: a manifest maps local Markdown files to Kibela notes, and
+//! a state file next to it remembers which note each file became.
+
+mod state;
+
+pub use state::{content_hash, PublishState, PublishedFile};
+
+use crate::content::analysis::outline;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Manifest read when `--manifest` is not given, in the current directory.
+pub const DEFAULT_MANIFEST: &str = "kibel-publish.toml";
+/// State file kept next to the manifest.
+pub const STATE_FILE: &str = ".kibel-publish-state.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// One `[[entries]]` table: which files, and where they go.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestEntry {
+    /// Glob relative to the manifest's directory, e.g. `docs/**/*.md`.
+    pub files: String,
+    /// Existing note the single matched file updates.
+    #[serde(default)]
+    pub note: Option<String>,
+    /// `GROUP/FOLDER/...` new notes are filed in; missing folders are
+    /// created.
+    #[serde(default)]
+    pub folder_path: Option<String>,
+    /// Groups new notes are shared with.
+    #[serde(default)]
+    pub group_ids: Vec<String>,
+    /// Whether new notes are coedited.
+    #[serde(default)]
+    pub coediting: bool,
+}
+
+impl Manifest {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let manifest = toml::from_str::<Self>(raw).map_err(|error| error.to_string())?;
+        if manifest.entries.is_empty() {
+            return Err("manifest has no [[entries]]".to_string());
+        }
+        for entry in &manifest.entries {
+            if entry.note.is_none() && entry.folder_path.is_none() && entry.group_ids.is_empty() {
+                return Err(format!(
+                    "entry `{}` needs `note`, `folder_path`, or `group_ids`",
+                    entry.files
+                ));
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// A file selected by the manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedFile<'a> {
+    pub path: PathBuf,
+    /// The path relative to the manifest's directory with `/` separators;
+    /// the state file is keyed by it.
+    pub key: String,
+    pub entry: &'a ManifestEntry,
+}
+
+/// Expands every entry's glob under `root`, in entry order and then path
+/// order. A file matched by several entries belongs to the first.
+pub fn select_files<'a>(
+    manifest: &'a Manifest,
+    root: &Path,
+) -> Result<Vec<SelectedFile<'a>>, String> {
+    let prefix = glob::Pattern::escape(&root.to_string_lossy());
+    let mut seen = HashSet::new();
+    let mut selected = Vec::new();
+    for entry in &manifest.entries {
+        let pattern = format!("{prefix}/{}", entry.files.trim_start_matches("./"));
+        let paths = glob::glob(&pattern)
+            .map_err(|error| format!("invalid glob `{}`: {error}", entry.files))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        if entry.note.is_some() && paths.len() != 1 {
+            return Err(format!(
+                "entry `{}` sets `note` but matches {} files; it must match exactly one",
+                entry.files,
+                paths.len()
+            ));
+        }
+        for path in paths {
+            let key = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if seen.insert(key.clone()) {
+                selected.push(SelectedFile { path, key, entry });
+            }
+        }
+    }
+    Ok(selected)
+}
+
+/// The note title for a file: its front matter `title`, else its first `#`
+/// heading, else the file name without extension.
+pub fn title_for(front_title: Option<&str>, content: &str, path: &Path) -> String {
+    front_title
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(ToOwned::to_owned)
+        .or_else(|| {
+            outline(content)
+                .into_iter()
+                .find(|heading| heading.level == 1)
+                .map(|heading| heading.text)
+        })
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+}
+
+/// Files under `root` that differ between `rev` and the working tree, as
+/// paths relative to `root` (`git diff --name-only --relative`).
+pub fn git_changed_files(root: &Path, rev: &str) -> Result<HashSet<String>, String> {
+    if rev.starts_with('-') {
+        return Err(format!("`{rev}` is not a git revision"));
+    }
+    let stdout = git(root, &["diff", "--name-only", "--relative", rev, "--"])?;
+    Ok(stdout
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+/// The commit checked out at `root`, when it is in a git repository.
+pub fn git_head(root: &Path) -> Option<String> {
+    git(root, &["rev-parse", "--verify", "HEAD"])
+        .ok()
+        .map(|stdout| stdout.trim().to_string())
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .map_err(|error| format!("failed to run git: {error}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_files, title_for, Manifest};
+    use std::path::Path;
+
+    #[test]
+    fn manifest_entries_select_files_once_in_order() {
+        let root = std::env::temp_dir().join(format!("kibel-publish-{}", std::process::id()));
+        for file in ["docs/a.md", "docs/guides/b.md", "docs/c.txt", "README.md"] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().expect("has parent")).expect("create dir");
+            std::fs::write(&path, "# Title\n").expect("write file");
+        }
+        let manifest = Manifest::parse(
+            "[[entries]]\nfiles = \"README.md\"\nnote = \"/notes/1\"\n\n[[entries]]\nfiles = \"docs/**/*.md\"\nfolder_path = \"eng/docs\"\n\n[[entries]]\nfiles = \"./docs/a.md\"\ngroup_ids = [\"G1\"]\n",
+        )
+        .expect("manifest should parse");
+
+        let selected = select_files(&manifest, &root).expect("globs should expand");
+        let keys = selected
+            .iter()
+            .map(|file| (file.key.as_str(), file.entry.files.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            [
+                ("README.md", "README.md"),
+                ("docs/a.md", "docs/**/*.md"),
+                ("docs/guides/b.md", "docs/**/*.md"),
+            ]
+        );
+
+        let broad = Manifest::parse("[[entries]]\nfiles = \"**/*.md\"\nnote = \"/notes/1\"\n")
+            .expect("manifest should parse");
+        assert!(select_files(&broad, &root).is_err());
+        assert!(Manifest::parse("[[entries]]\nfiles = \"*.md\"\n").is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn titles_fall_back_from_front_matter_to_heading_to_file_name() {
+        let path = Path::new("docs/runbook.md");
+        assert_eq!(title_for(Some("Ops"), "# Runbook", path), "Ops");
+        assert_eq!(title_for(None, "## Intro\n# Runbook\n", path), "Runbook");
+        assert_eq!(title_for(Some(" "), "text", path), "runbook");
+    }
+}
@@ -0,0 +1,102 @@
+//! The publish state file: for each file, the note it became and content
+//! hashes of both sides as of the last publish.
+
+use crate::docs_mapping::normalize_for_compare;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// What the last publish left for each file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishState {
+    /// `HEAD` when the last complete, non-dry-run publish finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(default)]
+    pub files: BTreeMap<String, PublishedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedFile {
+    pub note_id: String,
+    /// [`content_hash`] of the file as last published.
+    #[serde(alias = "sha256")]
+    pub local_sha256: String,
+    /// [`content_hash`] of the note as last written or read; missing in
+    /// state written before remote hashes were kept.
+    #[serde(default)]
+    pub remote_sha256: Option<String>,
+}
+
+impl PublishedFile {
+    /// Whether the note no longer matches the version this state last knew,
+    /// i.e. someone edited it in Kibela since. Unknown counts as unchanged.
+    pub fn remote_changed(&self, remote_sha256: &str) -> bool {
+        self.remote_sha256
+            .as_deref()
+            .is_some_and(|known| known != remote_sha256)
+    }
+}
+
+impl PublishState {
+    /// Reads the state; a missing or unreadable file is an empty state.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let serialized = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        let staging = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&staging, format!("{serialized}\n"))?;
+        fs::rename(&staging, path)
+    }
+}
+
+/// SHA-256 of a title and content, hex-encoded. Content is compared the way
+/// `ci verify-docs` does, ignoring line endings and trailing whitespace, so
+/// a note and the file it was published from hash the same.
+pub fn content_hash(title: &str, content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(title.trim().as_bytes());
+    hasher.update([0]);
+    hasher.update(normalize_for_compare(content).as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_hash, PublishState};
+
+    #[test]
+    fn hashes_ignore_whitespace_noise_and_old_state_still_loads() {
+        assert_eq!(
+            content_hash("T", "a\r\nb  \n\n"),
+            content_hash("T ", "a\nb")
+        );
+        assert_ne!(content_hash("a", "bc"), content_hash("ab", "c"));
+
+        let state = serde_json::from_str::<PublishState>(
+            r#"{"files": {"docs/a.md": {"note_id": "N1", "sha256": "abc"}}}"#,
+        )
+        .expect("state without remote hashes should parse");
+        let file = &state.files["docs/a.md"];
+        assert_eq!(file.local_sha256, "abc");
+        assert!(!file.remote_changed("anything"));
+
+        let known = super::PublishedFile {
+            remote_sha256: Some("r1".to_string()),
+            ..file.clone()
+        };
+        assert!(!known.remote_changed("r1"));
+        assert!(known.remote_changed("r2"));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::hex;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::thread;
+use std::time::Duration;
+
+const RELAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A webhook endpoint that receives `feed relay` events.
+#[derive(Debug, Clone)]
+pub struct RelayTarget {
+    pub url: String,
+    pub secret: String,
+    pub max_retries: u32,
+    pub retry_base: Duration,
+}
+
+/// Outcome of delivering one event (after retries).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delivery {
+    pub attempts: u32,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl Delivery {
+    pub fn ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "ok": self.ok(),
+            "attempts": self.attempts,
+            "status": self.status,
+            "error": self.error,
+        })
+    }
+}
+
+/// Accepts `https://` URLs, and plain `http://` only for loopback hosts.
+pub fn validate_relay_url(raw: &str) -> Result<String, String> {
+    let url = raw.trim();
+    let lowered = url.to_ascii_lowercase();
+    if lowered.starts_with("https://") && url.len() > "https://".len() {
+        return Ok(url.to_string());
+    }
+    if let Some(rest) = lowered.strip_prefix("http://") {
+        let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        // An IPv6 literal keeps its colons; only a `:port` after `]` is stripped.
+        let host = match host.rfind(']') {
+            Some(end) => &host[..=end],
+            None => host.rsplit_once(':').map_or(host, |(host, _)| host),
+        };
+        if matches!(host, "localhost" | "127.0.0.1" | "[::1]") {
+            return Ok(url.to_string());
+        }
+    }
+    Err("relay url must use https:// (http:// is allowed only for localhost)".to_string())
+}
+
+/// `sha256=<hex>` HMAC over `<timestamp>.<body>`, sent as `X-Kibel-Signature`.
+pub fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let message = format!("{timestamp}.{body}");
+    format!(
+        "sha256={}",
+        hex::encode(&hmac_sha256(secret.as_bytes(), message.as_bytes()))
+    )
+}
+
+impl RelayTarget {
+    /// POSTs `event` as JSON, retrying transport errors, 429 and 5xx with
+    /// exponential backoff. Other 4xx responses are not retried.
+    pub fn deliver(&self, event: &Value, timestamp: u64) -> Delivery {
+        let body = event.to_string();
+        let signature = sign(&self.secret, timestamp, &body);
+        let event_type = event.get("type").and_then(Value::as_str).unwrap_or("event");
+        let agent = ureq::AgentBuilder::new().timeout(RELAY_TIMEOUT).build();
+
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let result = agent
+                .post(&self.url)
+                .set("Content-Type", "application/json")
+                .set("User-Agent", concat!("kibel/", env!("CARGO_PKG_VERSION")))
+                .set("X-Kibel-Event", event_type)
+                .set("X-Kibel-Timestamp", &timestamp.to_string())
+                .set("X-Kibel-Signature", &signature)
+                .send_string(&body);
+            let (status, error, retryable) = match result {
+                Ok(response) => (Some(response.status()), None, false),
+                Err(ureq::Error::Status(code, _)) => (
+                    Some(code),
+                    Some(format!("webhook responded with HTTP {code}")),
+                    code == 429 || code >= 500,
+                ),
+                Err(error) => (None, Some(error.to_string()), true),
+            };
+            if error.is_none() || !retryable || attempts > self.max_retries {
+                return Delivery {
+                    attempts,
+                    status,
+                    error,
+                };
+            }
+            thread::sleep(self.retry_base.saturating_mul(1 << (attempts - 1).min(6)));
+        }
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hmac_sha256, sign, validate_relay_url};
+    use crate::hex;
+
+    #[test]
+    fn hmac_matches_rfc4231_vector() {
+        assert_eq!(
+            hex::encode(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert!(sign("Jefe", 1, "{}").starts_with("sha256="));
+    }
+
+    #[test]
+    fn validate_relay_url_requires_https_outside_loopback() {
+        assert!(validate_relay_url("https://hooks.example.com/x").is_ok());
+        assert!(validate_relay_url("http://127.0.0.1:8080/hook").is_ok());
+        assert!(validate_relay_url("http://localhost/hook").is_ok());
+        assert!(validate_relay_url("http://[::1]/hook").is_ok());
+        assert!(validate_relay_url("http://[::1]:8080/hook").is_ok());
+        assert!(validate_relay_url("http://[::2]/hook").is_err());
+        assert!(validate_relay_url("http://hooks.example.com/x").is_err());
+        assert!(validate_relay_url("ftp://example.com").is_err());
+    }
+}
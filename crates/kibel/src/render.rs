@@ -0,0 +1,122 @@
+use pulldown_cmark::{html, Options, Parser};
+#[cfg(feature = "pdf")]
+use std::path::Path;
+
+/// Inlined into every rendered page so the file needs nothing else to display.
+const STYLESHEET: &str = "\
+body{max-width:46rem;margin:2rem auto;padding:0 1rem;font:16px/1.6 -apple-system,\
+\"Segoe UI\",\"Hiragino Sans\",\"Noto Sans JP\",sans-serif;color:#1f2328}\
+h1,h2,h3{line-height:1.3}h1.title{border-bottom:1px solid #d0d7de;padding-bottom:.3em}\
+a{color:#0969da}img{max-width:100%}\
+pre{background:#f6f8fa;padding:1em;overflow:auto}\
+code{font-family:ui-monospace,Menlo,Consolas,monospace;font-size:.9em}\
+blockquote{margin:0;padding:0 1em;color:#59636e;border-left:.25em solid #d0d7de}\
+table{border-collapse:collapse}th,td{border:1px solid #d0d7de;padding:.3em .7em}\
+@media print{body{margin:0;max-width:none}}";
+
+/// The note body as an HTML fragment. Tables, strikethrough, task lists,
+/// and footnotes follow GitHub-flavored Markdown; raw HTML in the note is
+/// kept as written, as Kibela displays it.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let mut out = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut out, Parser::new_ext(markdown, options));
+    out
+}
+
+/// A standalone HTML document: `title` as the heading, the rendered body,
+/// and the inline stylesheet.
+pub fn render_document(title: &str, markdown: &str) -> String {
+    let title = escape(title);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>{STYLESHEET}</style>\n</head>\n<body>\n\
+         <h1 class=\"title\">{title}</h1>\n{}</body>\n</html>\n",
+        markdown_to_html(markdown)
+    )
+}
+
+/// Headless browsers tried in order when `KIBEL_PDF_BROWSER` is unset.
+#[cfg(feature = "pdf")]
+const PDF_BROWSERS: [&str; 5] = [
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "msedge",
+];
+
+/// Prints the HTML file at `html` to `pdf` with a headless Chromium-family
+/// browser: `browser` when given, else the first of [`PDF_BROWSERS`] found.
+///
+/// Returns the browser that printed it.
+#[cfg(feature = "pdf")]
+pub fn print_pdf(browser: Option<&str>, html: &Path, pdf: &Path) -> Result<String, String> {
+    use std::io::ErrorKind;
+    use std::process::{Command, Stdio};
+
+    let html = html
+        .canonicalize()
+        .map_err(|error| format!("failed to resolve {}: {error}", html.display()))?;
+    let candidates = match browser.map(str::trim).filter(|browser| !browser.is_empty()) {
+        Some(browser) => vec![browser],
+        None => PDF_BROWSERS.to_vec(),
+    };
+    for program in &candidates {
+        let status = Command::new(program)
+            .args(["--headless", "--disable-gpu", "--no-pdf-header-footer"])
+            .arg(format!("--print-to-pdf={}", pdf.display()))
+            .arg(format!("file://{}", html.display()))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => return Ok((*program).to_string()),
+            Ok(status) => return Err(format!("`{program}` exited with {status}")),
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => return Err(format!("failed to start `{program}`: {error}")),
+        }
+    }
+    Err(format!(
+        "no headless browser found (tried {}); set KIBEL_PDF_BROWSER",
+        candidates.join(", ")
+    ))
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_document;
+
+    #[test]
+    fn document_escapes_the_title_and_renders_gfm() {
+        let html = render_document(
+            "Q&A <draft>",
+            "## Steps\n\n- [x] done\n\n| a | b |\n|---|---|\n| 1 | ~~2~~ |\n\n![d](assets/d.png)\n",
+        );
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<title>Q&amp;A &lt;draft&gt;</title>"));
+        assert!(html.contains("<h1 class=\"title\">Q&amp;A &lt;draft&gt;</h1>"));
+        assert!(html.contains("<h2>Steps</h2>"));
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\" checked=\"\"/>"));
+        assert!(html.contains("<td><del>2</del></td>"));
+        assert!(html.contains("<img src=\"assets/d.png\" alt=\"d\" />"));
+    }
+}
@@ -0,0 +1,209 @@
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// An attachment hit from `search note --resource ATTACHMENT`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentHit {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub path: String,
+}
+
+impl AttachmentHit {
+    pub fn from_search_result(item: &Value) -> Option<Self> {
+        let text = |key: &str| {
+            item.get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let hit = Self {
+            id: text("id"),
+            title: text("title"),
+            url: text("url"),
+            path: text("path"),
+        };
+        if hit.url.is_empty() && hit.title.is_empty() {
+            return None;
+        }
+        Some(hit)
+    }
+
+    /// The note that embeds the attachment, derived from the hit path.
+    pub fn note_key(&self) -> &str {
+        let path = if self.path.is_empty() {
+            &self.url
+        } else {
+            &self.path
+        };
+        path.split('#').next().unwrap_or(path)
+    }
+}
+
+/// How attachments were matched to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupKey {
+    FileName,
+    ContentHash,
+}
+
+impl DedupKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FileName => "file_name",
+            Self::ContentHash => "content_sha256",
+        }
+    }
+}
+
+/// Per-attachment key material; `size` is only known when content was hashed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyedAttachment {
+    pub hit: AttachmentHit,
+    pub key: String,
+    pub size: Option<u64>,
+}
+
+pub fn file_name_key(hit: &AttachmentHit) -> String {
+    let name = hit
+        .title
+        .rsplit('/')
+        .next()
+        .unwrap_or(&hit.title)
+        .trim()
+        .to_lowercase();
+    if name.is_empty() {
+        hit.url.clone()
+    } else {
+        name
+    }
+}
+
+/// Groups attachments sharing a key across more than one note and ranks the
+/// groups by reclaimable bytes, then by copy count.
+pub fn duplicate_groups(attachments: Vec<KeyedAttachment>, min_size: u64) -> Vec<Value> {
+    let mut groups = BTreeMap::<String, Vec<KeyedAttachment>>::new();
+    for attachment in attachments {
+        if attachment.size.is_some_and(|size| size < min_size) {
+            continue;
+        }
+        groups
+            .entry(attachment.key.clone())
+            .or_default()
+            .push(attachment);
+    }
+
+    let mut rendered = groups
+        .into_iter()
+        .filter_map(|(key, members)| {
+            let mut notes = members
+                .iter()
+                .map(|member| member.hit.note_key().to_string())
+                .collect::<Vec<_>>();
+            notes.sort();
+            notes.dedup();
+            if notes.len() < 2 {
+                return None;
+            }
+            let size = members.iter().find_map(|member| member.size);
+            let copies = members.len() as u64;
+            let reclaimable = size.map(|size| size.saturating_mul(copies - 1));
+            let occurrences = members
+                .iter()
+                .map(|member| {
+                    json!({
+                        "id": member.hit.id,
+                        "title": member.hit.title,
+                        "url": member.hit.url,
+                        "path": member.hit.path,
+                    })
+                })
+                .collect::<Vec<_>>();
+            Some(json!({
+                "key": key,
+                "copies": copies,
+                "note_count": notes.len(),
+                "size_bytes": size,
+                "reclaimable_bytes": reclaimable,
+                "suggestion": format!(
+                    "upload `{}` once to a shared note and link to it from the other {} note(s)",
+                    members[0].hit.title,
+                    notes.len() - 1
+                ),
+                "occurrences": occurrences,
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    rendered.sort_by(|left, right| {
+        let bytes = |value: &Value| value["reclaimable_bytes"].as_u64().unwrap_or(0);
+        let copies = |value: &Value| value["copies"].as_u64().unwrap_or(0);
+        bytes(right)
+            .cmp(&bytes(left))
+            .then_with(|| copies(right).cmp(&copies(left)))
+    });
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{duplicate_groups, file_name_key, AttachmentHit, KeyedAttachment};
+
+    fn hit(note: u32, title: &str) -> AttachmentHit {
+        AttachmentHit {
+            id: format!("A{note}"),
+            title: title.to_string(),
+            url: format!("https://acme.kibe.la/notes/{note}#attachment"),
+            path: format!("/notes/{note}"),
+        }
+    }
+
+    #[test]
+    fn duplicate_groups_require_more_than_one_note() {
+        let attachments = vec![
+            KeyedAttachment {
+                key: file_name_key(&hit(1, "Screenshot.png")),
+                hit: hit(1, "Screenshot.png"),
+                size: None,
+            },
+            KeyedAttachment {
+                key: file_name_key(&hit(1, "screenshot.png")),
+                hit: hit(1, "screenshot.png"),
+                size: None,
+            },
+            KeyedAttachment {
+                key: file_name_key(&hit(2, "logo.svg")),
+                hit: hit(2, "logo.svg"),
+                size: None,
+            },
+        ];
+        assert!(duplicate_groups(attachments, 0).is_empty());
+    }
+
+    #[test]
+    fn duplicate_groups_rank_by_reclaimable_bytes() {
+        let keyed = |note, key: &str, size| KeyedAttachment {
+            hit: hit(note, "image.png"),
+            key: key.to_string(),
+            size: Some(size),
+        };
+        let groups = duplicate_groups(
+            vec![
+                keyed(1, "small", 10),
+                keyed(2, "small", 10),
+                keyed(3, "small", 10),
+                keyed(1, "large", 1000),
+                keyed(4, "large", 1000),
+                keyed(5, "tiny", 1),
+                keyed(6, "tiny", 1),
+            ],
+            5,
+        );
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0]["key"], "large");
+        assert_eq!(groups[0]["reclaimable_bytes"], 1000);
+        assert_eq!(groups[1]["copies"], 3);
+    }
+}
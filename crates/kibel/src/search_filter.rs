@@ -0,0 +1,164 @@
+use crate::error::{CliError, ErrorCode};
+use kibel_client::SearchNoteInput;
+
+/// How many search hits `--author` inspects when resolving an account.
+pub const AUTHOR_LOOKUP_FIRST: u32 = 50;
+
+/// Friendly `search note` filters that expand into [`SearchNoteInput`]
+/// fields: a date range for `updated`, folder paths for `folderIds`, and
+/// accounts for `userIds`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilterBuilder {
+    updated_after: Option<String>,
+    updated_before: Option<String>,
+    folder_paths: Vec<String>,
+    authors: Vec<String>,
+}
+
+impl SearchFilterBuilder {
+    #[must_use]
+    pub fn updated_after(mut self, date: Option<String>) -> Self {
+        self.updated_after = date;
+        self
+    }
+
+    #[must_use]
+    pub fn updated_before(mut self, date: Option<String>) -> Self {
+        self.updated_before = date;
+        self
+    }
+
+    #[must_use]
+    pub fn in_folders(mut self, paths: &[String]) -> Self {
+        self.folder_paths.extend(paths.iter().cloned());
+        self
+    }
+
+    #[must_use]
+    pub fn authors(mut self, accounts: &[String]) -> Self {
+        self.authors.extend(accounts.iter().cloned());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The `updated` value for the date range: `AFTER..BEFORE`, with either
+    /// side left open when not given.
+    ///
+    /// # Errors
+    /// Returns `INPUT_INVALID` when the range ends before it starts.
+    pub fn search_date(&self) -> Result<Option<String>, CliError> {
+        if let (Some(after), Some(before)) = (&self.updated_after, &self.updated_before) {
+            // `YYYY-MM-DD` compares correctly as a string.
+            if after > before {
+                return Err(CliError::new(
+                    ErrorCode::InputInvalid,
+                    format!("--updated-after {after} is later than --updated-before {before}"),
+                ));
+            }
+        }
+        if self.updated_after.is_none() && self.updated_before.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(format!(
+            "{}..{}",
+            self.updated_after.as_deref().unwrap_or_default(),
+            self.updated_before.as_deref().unwrap_or_default()
+        )))
+    }
+
+    /// Writes the filters into `input`, resolving folder paths and accounts
+    /// to ids with the given lookups. Resolved ids are appended to any
+    /// `--folder-id` / `--user-id` already present.
+    ///
+    /// # Errors
+    /// Returns the first lookup error, or `INPUT_INVALID` for a bad range.
+    pub fn apply<F, A>(
+        &self,
+        input: &mut SearchNoteInput,
+        mut folder_id: F,
+        mut user_id: A,
+    ) -> Result<(), CliError>
+    where
+        F: FnMut(&str) -> Result<String, CliError>,
+        A: FnMut(&str) -> Result<String, CliError>,
+    {
+        if let Some(updated) = self.search_date()? {
+            input.updated = Some(updated);
+        }
+        for path in &self.folder_paths {
+            push_unique(&mut input.folder_ids, folder_id(path)?);
+        }
+        for account in &self.authors {
+            let account = account.trim().trim_start_matches('@');
+            push_unique(&mut input.user_ids, user_id(account)?);
+        }
+        Ok(())
+    }
+}
+
+fn push_unique(ids: &mut Vec<String>, id: String) {
+    if !ids.contains(&id) {
+        ids.push(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchFilterBuilder;
+    use crate::error::{CliError, ErrorCode};
+    use kibel_client::SearchNoteInput;
+
+    #[test]
+    fn apply_expands_range_folders_and_authors() {
+        let filter = SearchFilterBuilder::default()
+            .updated_after(Some("2026-01-01".to_string()))
+            .in_folders(&["/acme/engineering".to_string()])
+            .authors(&["@alice".to_string(), "alice".to_string()]);
+        let mut input = SearchNoteInput::new("deploy");
+        input.folder_ids = vec!["F0".to_string()];
+        filter
+            .apply(
+                &mut input,
+                |path| Ok(format!("F:{path}")),
+                |account| Ok(format!("U:{account}")),
+            )
+            .expect("filters apply");
+        assert_eq!(input.updated.as_deref(), Some("2026-01-01.."));
+        assert_eq!(input.folder_ids, vec!["F0", "F:/acme/engineering"]);
+        assert_eq!(input.user_ids, vec!["U:alice"]);
+    }
+
+    #[test]
+    fn search_date_rejects_inverted_range_and_propagates_lookup_errors() {
+        let filter = SearchFilterBuilder::default()
+            .updated_after(Some("2026-03-01".to_string()))
+            .updated_before(Some("2026-02-01".to_string()));
+        let error = filter.search_date().expect_err("range is inverted");
+        assert_eq!(error.code, ErrorCode::InputInvalid);
+        assert_eq!(
+            SearchFilterBuilder::default()
+                .updated_before(Some("2026-02-01".to_string()))
+                .search_date()
+                .expect("open start"),
+            Some("..2026-02-01".to_string())
+        );
+
+        let filter = SearchFilterBuilder::default().authors(&["ghost".to_string()]);
+        let error = filter
+            .apply(
+                &mut SearchNoteInput::new(""),
+                |_| unreachable!("no folders"),
+                |account| {
+                    Err(CliError::new(
+                        ErrorCode::NotFound,
+                        format!("user not found: {account}"),
+                    ))
+                },
+            )
+            .expect_err("lookup fails");
+        assert_eq!(error.code, ErrorCode::NotFound);
+    }
+}
@@ -0,0 +1,374 @@
+use crate::hex;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Release API of the repository kibel ships from. `KIBEL_UPDATE_URL`
+/// points the updater at a mirror instead.
+pub const DEFAULT_RELEASES_URL: &str = "https://api.github.com/repos/masayannuu/kibel/releases";
+
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Release archives are a few MiB; anything far larger is not ours.
+const MAX_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateError {
+    /// The release server could not be reached or answered with an error.
+    Transport(String),
+    /// No usable release for this platform, or the download failed
+    /// verification.
+    Precondition(String),
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(message) | Self::Precondition(message) => f.write_str(message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// The tag without its `v` prefix.
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// Talks to the release API: `<releases_url>` lists releases, and assets are
+/// downloaded from their `browser_download_url`.
+pub struct Updater {
+    releases_url: String,
+    agent: ureq::Agent,
+}
+
+impl Updater {
+    /// Sends requests through `proxy` when given, else through
+    /// `HTTPS_PROXY`/`ALL_PROXY` from the environment.
+    pub fn new(releases_url: &str, proxy: Option<&str>) -> Result<Self, UpdateError> {
+        let mut builder = ureq::AgentBuilder::new()
+            .timeout(UPDATE_TIMEOUT)
+            .user_agent(concat!("kibel/", env!("CARGO_PKG_VERSION")))
+            .try_proxy_from_env(true);
+        if let Some(proxy) = proxy {
+            let proxy = ureq::Proxy::new(proxy).map_err(|error| {
+                UpdateError::Precondition(format!("invalid proxy `{proxy}`: {error}"))
+            })?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(Self {
+            releases_url: releases_url.trim_end_matches('/').to_string(),
+            agent: builder.build(),
+        })
+    }
+
+    /// The newest published release, counting prereleases only when asked.
+    pub fn latest(&self, include_prereleases: bool) -> Result<Release, UpdateError> {
+        let body = self.get(&format!("{}?per_page=30", self.releases_url))?;
+        let releases = serde_json::from_slice::<Vec<Release>>(&body).map_err(|error| {
+            UpdateError::Transport(format!("unexpected release list from the server: {error}"))
+        })?;
+        releases
+            .into_iter()
+            .filter(|release| !release.draft && (include_prereleases || !release.prerelease))
+            .filter(|release| parse_version(release.version()).is_some())
+            .max_by(|left, right| compare_versions(left.version(), right.version()))
+            .ok_or_else(|| UpdateError::Precondition("no published release found".to_string()))
+    }
+
+    /// Downloads this platform's archive of `release`, verifies it against
+    /// the published SHA-256, and swaps the binary at `target` for it.
+    ///
+    /// Returns the verified checksum.
+    pub fn install(&self, release: &Release, target: &Path) -> Result<String, UpdateError> {
+        let name = platform_asset_name(
+            &release.tag_name,
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+        )
+        .ok_or_else(|| {
+            UpdateError::Precondition(format!(
+                "no release build for {}-{}",
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ))
+        })?;
+        let archive = release.asset(&name).ok_or_else(|| {
+            UpdateError::Precondition(format!("{} has no asset {name}", release.tag_name))
+        })?;
+        let expected = self.published_checksum(release, &name)?;
+        let bytes = self.get(&archive.browser_download_url)?;
+        install_archive(&bytes, &expected, target)?;
+        Ok(expected)
+    }
+
+    /// The checksum from the combined `checksums.txt`, else from the
+    /// per-archive `.sha256` file. A release without either is refused.
+    fn published_checksum(&self, release: &Release, name: &str) -> Result<String, UpdateError> {
+        let sources = [
+            format!("kibel-{}-checksums.txt", release.tag_name),
+            format!("{name}.sha256"),
+        ];
+        for source in &sources {
+            let Some(asset) = release.asset(source) else {
+                continue;
+            };
+            let body = self.get(&asset.browser_download_url)?;
+            if let Some(checksum) = checksum_for(&String::from_utf8_lossy(&body), name) {
+                return Ok(checksum);
+            }
+        }
+        Err(UpdateError::Precondition(format!(
+            "{} publishes no checksum for {name}; refusing to install it",
+            release.tag_name
+        )))
+    }
+
+    fn get(&self, url: &str) -> Result<Vec<u8>, UpdateError> {
+        let response = self
+            .agent
+            .get(url)
+            .set(
+                "Accept",
+                "application/vnd.github+json, application/octet-stream",
+            )
+            .call()
+            .map_err(|error| match error {
+                ureq::Error::Status(code, _) => {
+                    UpdateError::Transport(format!("{url} responded with HTTP {code}"))
+                }
+                error => UpdateError::Transport(format!("failed to fetch {url}: {error}")),
+            })?;
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .take(MAX_DOWNLOAD_BYTES + 1)
+            .read_to_end(&mut body)
+            .map_err(|error| UpdateError::Transport(format!("failed to read {url}: {error}")))?;
+        if body.len() as u64 > MAX_DOWNLOAD_BYTES {
+            return Err(UpdateError::Precondition(format!(
+                "{url} is larger than {MAX_DOWNLOAD_BYTES} bytes"
+            )));
+        }
+        Ok(body)
+    }
+}
+
+/// `kibel-<tag>-<os>-<arch>.tar.gz`, the archive name the release workflow
+/// publishes; `None` for platforms without a release build.
+pub fn platform_asset_name(tag: &str, os: &str, arch: &str) -> Option<String> {
+    let os = match os {
+        "linux" => "linux",
+        "macos" => "darwin",
+        _ => return None,
+    };
+    matches!(arch, "x86_64" | "aarch64").then(|| format!("kibel-{tag}-{os}-{arch}.tar.gz"))
+}
+
+/// Whether `candidate` is a newer version than `current`. Unparsable
+/// versions are never newer.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    parse_version(candidate).is_some()
+        && compare_versions(candidate.trim_start_matches('v'), current) == Ordering::Greater
+}
+
+/// `MAJOR.MINOR.PATCH[-PRERELEASE]`; a prerelease sorts before its release.
+fn parse_version(raw: &str) -> Option<([u64; 3], Option<&str>)> {
+    let raw = raw.trim().trim_start_matches('v');
+    let (core, prerelease) = match raw.split_once('-') {
+        Some((core, prerelease)) => (core, Some(prerelease)),
+        None => (raw, None),
+    };
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let version = [parts.next()??, parts.next()??, parts.next()??];
+    parts.next().is_none().then_some((version, prerelease))
+}
+
+fn compare_versions(left: &str, right: &str) -> Ordering {
+    match (parse_version(left), parse_version(right)) {
+        (Some((left, left_pre)), Some((right, right_pre))) => {
+            left.cmp(&right).then(match (left_pre, right_pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(left), Some(right)) => left.cmp(right),
+            })
+        }
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// The hex digest listed for `name` in `sha256sum` output.
+fn checksum_for(checksums: &str, name: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let (digest, file) = line.trim().split_once(char::is_whitespace)?;
+        let file = file.trim().trim_start_matches('*');
+        let file = file.rsplit('/').next().unwrap_or(file);
+        (file == name && digest.len() == 64).then(|| digest.to_ascii_lowercase())
+    })
+}
+
+/// Verifies `archive` against `expected_sha256`, unpacks its `kibel` binary
+/// beside `target`, and renames it over `target`, so an interrupted update
+/// leaves the old binary in place.
+pub fn install_archive(
+    archive: &[u8],
+    expected_sha256: &str,
+    target: &Path,
+) -> Result<(), UpdateError> {
+    let actual = hex::encode(&Sha256::digest(archive));
+    if !actual.eq_ignore_ascii_case(expected_sha256) {
+        return Err(UpdateError::Precondition(format!(
+            "checksum mismatch: expected {expected_sha256}, downloaded {actual}"
+        )));
+    }
+
+    let dir = target.parent().ok_or_else(|| {
+        UpdateError::Precondition(format!("{} has no parent directory", target.display()))
+    })?;
+    let staging = dir.join(format!(".kibel-update-{}", std::process::id()));
+    let result = unpack_and_swap(archive, &staging, target);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn unpack_and_swap(archive: &[u8], staging: &Path, target: &Path) -> Result<(), UpdateError> {
+    let io_error = |what: &str, path: &Path, error: std::io::Error| {
+        UpdateError::Precondition(format!("failed to {what} {}: {error}", path.display()))
+    };
+    fs::create_dir_all(staging).map_err(|error| io_error("create", staging, error))?;
+    let archive_path = staging.join("kibel.tar.gz");
+    fs::write(&archive_path, archive).map_err(|error| io_error("write", &archive_path, error))?;
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(staging)
+        .status()
+        .map_err(|error| UpdateError::Precondition(format!("failed to run tar: {error}")))?;
+    if !status.success() {
+        return Err(UpdateError::Precondition(format!(
+            "tar exited with {status} while unpacking the release"
+        )));
+    }
+
+    let binary: PathBuf = staging.join("kibel");
+    if !binary.is_file() {
+        return Err(UpdateError::Precondition(
+            "the release archive has no kibel binary".to_string(),
+        ));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755))
+            .map_err(|error| io_error("mark executable", &binary, error))?;
+    }
+    fs::rename(&binary, target).map_err(|error| io_error("replace", target, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum_for, install_archive, is_newer, platform_asset_name, UpdateError};
+    use crate::hex;
+    use sha2::{Digest, Sha256};
+    use std::fs;
+    use std::process::Command;
+
+    #[test]
+    fn versions_assets_and_checksums() {
+        assert!(is_newer("v0.3.0", "0.2.8"));
+        assert!(is_newer("0.2.10", "0.2.9"));
+        assert!(is_newer("0.3.0", "0.3.0-rc.1"));
+        assert!(!is_newer("0.3.0-rc.1", "0.3.0"));
+        assert!(!is_newer("v0.2.8", "0.2.8"));
+        assert!(!is_newer("nightly", "0.2.8"));
+
+        assert_eq!(
+            platform_asset_name("v0.3.0", "macos", "aarch64").as_deref(),
+            Some("kibel-v0.3.0-darwin-aarch64.tar.gz")
+        );
+        assert_eq!(platform_asset_name("v0.3.0", "windows", "x86_64"), None);
+
+        let digest = "a".repeat(64);
+        let checksums = format!(
+            "{}  kibel-v0.3.0-linux-x86_64.tar.gz\n{digest} *dist/kibel-v0.3.0-linux-aarch64.tar.gz\n",
+            "b".repeat(64)
+        );
+        assert_eq!(
+            checksum_for(&checksums, "kibel-v0.3.0-linux-aarch64.tar.gz"),
+            Some(digest)
+        );
+        assert_eq!(
+            checksum_for(&checksums, "kibel-v0.3.0-darwin-x86_64.tar.gz"),
+            None
+        );
+    }
+
+    #[test]
+    fn install_archive_verifies_then_swaps_the_binary() {
+        let dir = std::env::temp_dir().join(format!("kibel-self-update-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let build = dir.join("build");
+        fs::create_dir_all(&build).expect("build dir should be created");
+        fs::write(build.join("kibel"), "new").expect("binary should be written");
+        let archive_path = dir.join("release.tar.gz");
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&archive_path)
+            .arg("-C")
+            .arg(&build)
+            .arg("kibel")
+            .status()
+            .expect("tar should run");
+        assert!(status.success());
+        let archive = fs::read(&archive_path).expect("archive should be readable");
+        let target = dir.join("kibel");
+        fs::write(&target, "old").expect("target should be written");
+
+        let error = install_archive(&archive, &"0".repeat(64), &target)
+            .expect_err("a wrong checksum should be refused");
+        assert!(
+            matches!(error, UpdateError::Precondition(message) if message.contains("checksum mismatch"))
+        );
+        assert_eq!(fs::read_to_string(&target).expect("target"), "old");
+
+        install_archive(&archive, &hex::encode(&Sha256::digest(&archive)), &target)
+            .expect("install should succeed");
+        assert_eq!(fs::read_to_string(&target).expect("target"), "new");
+        assert!(!dir
+            .join(format!(".kibel-update-{}", std::process::id()))
+            .exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::frontmatter::{self, FrontMatter};
+use serde_json::Value;
+
+/// A note saved locally: a `note get --format markdown` export, a saved
+/// `note get --json` envelope (or bare note object), or plain Markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteSnapshot {
+    /// `None` for plain Markdown, which carries no metadata.
+    pub front: Option<FrontMatter>,
+    pub content: String,
+}
+
+pub fn parse_snapshot(raw: &str) -> Result<NoteSnapshot, String> {
+    if raw.trim_start().starts_with('{') {
+        if let Ok(value) = serde_json::from_str::<Value>(raw) {
+            return json_snapshot(&value);
+        }
+    }
+    let (front, body) = frontmatter::parse(raw)?;
+    Ok(NoteSnapshot {
+        front,
+        content: body.to_string(),
+    })
+}
+
+fn json_snapshot(value: &Value) -> Result<NoteSnapshot, String> {
+    let data = value.get("data").unwrap_or(value);
+    let note = data.get("note").unwrap_or(data);
+    let Some(content) = note.get("content").and_then(Value::as_str) else {
+        return Err(
+            "JSON snapshot has no `content` (expected `note get --json` output)".to_string(),
+        );
+    };
+    let front = match data.get("front_matter") {
+        Some(front @ Value::Object(_)) => FrontMatter::from_json(front),
+        _ => FrontMatter::from_note(note),
+    };
+    Ok(NoteSnapshot {
+        front: Some(front),
+        content: content.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_snapshot;
+
+    #[test]
+    fn snapshots_read_markdown_exports_and_json_envelopes() {
+        let markdown = parse_snapshot("---\nid: \"N1\"\nupdatedAt: \"t1\"\n---\nbody\n")
+            .expect("markdown export");
+        let front = markdown.front.expect("front matter");
+        assert_eq!(
+            (front.id.as_deref(), front.updated_at.as_deref()),
+            (Some("N1"), Some("t1"))
+        );
+        assert_eq!(markdown.content, "body");
+
+        let envelope = parse_snapshot(
+            r#"{"ok": true, "data": {"note": {"id": "N1", "title": "t", "content": "c\n"}}}"#,
+        )
+        .expect("json envelope");
+        assert_eq!(envelope.content, "c\n");
+        assert_eq!(envelope.front.expect("metadata").id.as_deref(), Some("N1"));
+
+        let plain = parse_snapshot("# Plain\n").expect("plain markdown");
+        assert_eq!((plain.front, plain.content.as_str()), (None, "# Plain\n"));
+        assert!(parse_snapshot(r#"{"ok": true, "data": {}}"#).is_err());
+    }
+}
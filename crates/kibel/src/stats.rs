@@ -0,0 +1,144 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Default for `stats --stale-days`.
+pub const DEFAULT_STALE_DAYS: u64 = 180;
+
+/// Upper bounds, in days since the last update, of the `stats` update
+/// buckets. Notes older than the last bound fall into a final open bucket.
+pub const UPDATE_BUCKET_DAYS: &[u64] = &[7, 30, 90, 365];
+
+/// Turns cumulative "updated within N days" counts, one per
+/// [`UPDATE_BUCKET_DAYS`] bound, into disjoint buckets; the rest of `total`
+/// goes to the last one.
+pub fn update_buckets(total: u64, within: &[u64]) -> Vec<Value> {
+    let mut buckets = Vec::with_capacity(UPDATE_BUCKET_DAYS.len() + 1);
+    let (mut lower, mut counted) = (0, 0);
+    for (&days, &count) in UPDATE_BUCKET_DAYS.iter().zip(within) {
+        // Counts come from separate searches, so never let them go backwards.
+        let count = count.max(counted).min(total);
+        buckets.push(json!({
+            "label": format!("{lower}-{days}d"),
+            "notes": count - counted,
+        }));
+        (lower, counted) = (days + 1, count);
+    }
+    buckets.push(json!({
+        "label": format!(">{}d", lower.saturating_sub(1)),
+        "notes": total - counted,
+    }));
+    buckets
+}
+
+/// Authors of the scanned search hits, most notes first, then by account.
+pub fn top_authors(hits: &[Value], top: usize) -> Vec<Value> {
+    let mut counts = HashMap::<&str, (&str, u64)>::new();
+    for hit in hits {
+        let Some(account) = hit
+            .pointer("/author/account")
+            .and_then(Value::as_str)
+            .filter(|account| !account.is_empty())
+        else {
+            continue;
+        };
+        let real_name = hit
+            .pointer("/author/realName")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        counts.entry(account).or_insert((real_name, 0)).1 += 1;
+    }
+    let mut authors = counts.into_iter().collect::<Vec<_>>();
+    authors.sort_by(|left, right| right.1 .1.cmp(&left.1 .1).then(left.0.cmp(right.0)));
+    authors
+        .into_iter()
+        .take(top)
+        .map(|(account, (real_name, notes))| {
+            json!({ "account": account, "real_name": real_name, "notes": notes })
+        })
+        .collect()
+}
+
+/// A stale search hit, trimmed to what a dashboard links to.
+pub fn stale_note(hit: &Value) -> Value {
+    json!({
+        "id": hit.get("id").cloned().unwrap_or(Value::Null),
+        "title": hit.get("title").cloned().unwrap_or(Value::Null),
+        "url": hit.get("url").cloned().unwrap_or(Value::Null),
+        "author": hit.pointer("/author/account").cloned().unwrap_or(Value::Null),
+    })
+}
+
+/// `--text` rendering of the `stats` data as small aligned tables.
+pub fn render_text(data: &Value) -> String {
+    let number = |pointer: &str| data.pointer(pointer).and_then(Value::as_u64).unwrap_or(0);
+    let mut lines = vec![format!("notes: {}", number("/notes"))];
+
+    lines.push(String::new());
+    lines.push("last update   notes".to_string());
+    for bucket in data["updated"].as_array().into_iter().flatten() {
+        lines.push(format!(
+            "{:<12}  {:>5}",
+            bucket["label"].as_str().unwrap_or_default(),
+            bucket["notes"].as_u64().unwrap_or(0)
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push(format!(
+        "top authors (of {} scanned)",
+        number("/scanned/notes")
+    ));
+    for author in data["top_authors"].as_array().into_iter().flatten() {
+        lines.push(format!(
+            "{:<20}  {:>5}",
+            author["account"].as_str().unwrap_or_default(),
+            author["notes"].as_u64().unwrap_or(0)
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push(format!(
+        "stale (> {} days): {}",
+        number("/stale/days"),
+        number("/stale/count")
+    ));
+    for note in data["stale"]["notes"].as_array().into_iter().flatten() {
+        lines.push(format!(
+            "- {}  {}",
+            note["title"].as_str().unwrap_or_default(),
+            note["url"].as_str().unwrap_or_default()
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{top_authors, update_buckets};
+    use serde_json::json;
+
+    #[test]
+    fn buckets_are_disjoint_and_authors_ranked() {
+        let buckets = update_buckets(20, &[2, 5, 5, 12]);
+        assert_eq!(
+            buckets,
+            vec![
+                json!({"label": "0-7d", "notes": 2}),
+                json!({"label": "8-30d", "notes": 3}),
+                json!({"label": "31-90d", "notes": 0}),
+                json!({"label": "91-365d", "notes": 7}),
+                json!({"label": ">365d", "notes": 8}),
+            ]
+        );
+        // Inconsistent counts are clamped instead of underflowing.
+        assert_eq!(update_buckets(3, &[4, 1, 1, 1])[4]["notes"], 0);
+
+        let hit = |account: &str| json!({"author": {"account": account, "realName": ""}});
+        let hits = vec![hit("bob"), hit("alice"), hit("bob"), hit("carol"), hit("")];
+        let authors = top_authors(&hits, 2);
+        assert_eq!(authors[0]["account"], "bob");
+        assert_eq!(authors[0]["notes"], 2);
+        assert_eq!(authors[1]["account"], "alice");
+        assert_eq!(authors.len(), 2);
+    }
+}
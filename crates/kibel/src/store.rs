@@ -0,0 +1,135 @@
+//! The local SQLite store (`kibel.db` next to the persisted query cache) and
+//! the import of the JSON cache files it replaced.
+
+use crate::error::{CliError, ErrorCode};
+use kibel_client::LocalStore;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `users.json` and `completion.json` from before the store; imported and
+/// removed the first time the store is opened next to them.
+const LEGACY_USER_CACHE_FILE: &str = "users.json";
+const LEGACY_COMPLETION_CACHE_FILE: &str = "completion.json";
+
+/// `kibel.db` next to the persisted query cache (`KIBEL_CACHE_DIR` when set).
+pub fn path() -> Option<PathBuf> {
+    match std::env::var_os("KIBEL_CACHE_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(LocalStore::path_in(Path::new(&dir))),
+        None => LocalStore::default_path().ok(),
+    }
+}
+
+/// Opens the store for a cache lookup or update. A store that cannot be
+/// opened only costs extra requests, so failures are `None`.
+pub fn open() -> Option<LocalStore> {
+    open_at(&path()?).ok()
+}
+
+/// Opens the store for `store` commands, where a failure is the answer.
+pub fn open_required() -> Result<LocalStore, CliError> {
+    let path = path().ok_or_else(|| {
+        CliError::new(
+            ErrorCode::NotFound,
+            "cache directory is unavailable; set KIBEL_CACHE_DIR",
+        )
+    })?;
+    open_at(&path).map_err(|error| {
+        CliError::new(ErrorCode::UnknownError, error.to_string())
+            .with_details(serde_json::json!({ "path": path }))
+    })
+}
+
+fn open_at(path: &Path) -> Result<LocalStore, kibel_client::KibelClientError> {
+    let mut store = LocalStore::open(path)?;
+    if let Some(dir) = path.parent() {
+        import_legacy(&mut store, dir);
+    }
+    Ok(store)
+}
+
+/// Moves the old JSON caches in `dir` into `store`. A file is removed only
+/// once its rows are stored; an unreadable one is left alone.
+fn import_legacy(store: &mut LocalStore, dir: &Path) {
+    let users = dir.join(LEGACY_USER_CACHE_FILE);
+    if let Some(teams) = read_teams(&users) {
+        let imported = teams.iter().all(|(team, accounts)| {
+            let pairs = accounts
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter_map(|(account, id)| Some((account.as_str(), id.as_str()?)));
+            store.record_user_ids(team, pairs).is_ok()
+        });
+        if imported {
+            let _ = fs::remove_file(&users);
+        }
+    }
+    let completion = dir.join(LEGACY_COMPLETION_CACHE_FILE);
+    if let Some(teams) = read_teams(&completion) {
+        let imported = teams.iter().all(|(team, entries)| {
+            [("groups", "group"), ("folders", "folder")]
+                .into_iter()
+                .all(|(field, kind)| {
+                    let entries = entries
+                        .get(field)
+                        .and_then(Value::as_object)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|(id, name)| Some((id.as_str(), name.as_str()?)));
+                    store
+                        .replace_completion_entries(team, kind, entries)
+                        .is_ok()
+                })
+        });
+        if imported {
+            let _ = fs::remove_file(&completion);
+        }
+    }
+}
+
+/// The `teams` object both legacy files were keyed by.
+fn read_teams(path: &Path) -> Option<serde_json::Map<String, Value>> {
+    let raw = fs::read_to_string(path).ok()?;
+    match serde_json::from_str::<Value>(&raw).ok()?.get("teams") {
+        Some(Value::Object(teams)) => Some(teams.clone()),
+        _ => Some(serde_json::Map::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::open_at;
+    use kibel_client::LocalStore;
+
+    #[test]
+    fn legacy_json_caches_are_imported_once_and_removed() {
+        let dir = std::env::temp_dir().join(format!("kibel-store-legacy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(
+            dir.join("users.json"),
+            r#"{"teams": {"acme": {"alice": "U1"}}}"#,
+        )
+        .expect("write users.json");
+        std::fs::write(
+            dir.join("completion.json"),
+            r#"{"teams": {"acme": {"groups": {"G1": "Eng"}, "folders": {}}}}"#,
+        )
+        .expect("write completion.json");
+
+        let store = open_at(&LocalStore::path_in(&dir)).expect("store should open");
+        assert_eq!(
+            store.user_id("acme", "alice").expect("query"),
+            Some("U1".to_string())
+        );
+        assert_eq!(
+            store
+                .completion_entries(Some("acme"), "group")
+                .expect("query"),
+            [("G1".to_string(), "Eng".to_string())]
+        );
+        assert!(!dir.join("users.json").exists());
+        assert!(!dir.join("completion.json").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A local note template (`<templates_dir>/<name>.md`).
+///
+/// An optional leading `---` block may set `title: ...`, used when
+/// `note create --template` is given no `--title`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteTemplate {
+    pub name: String,
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub body: String,
+}
+
+impl NoteTemplate {
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut names = placeholders(self.title.as_deref().unwrap_or_default());
+        names.extend(placeholders(&self.body));
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+pub fn list_templates(dir: &Path) -> Result<Vec<NoteTemplate>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(dir)
+        .map_err(|error| format!("failed to read templates dir {}: {error}", dir.display()))?;
+    let mut templates = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|error| format!("failed to read templates dir {}: {error}", dir.display()))?
+            .path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        templates.push(read_template(name, &path)?);
+    }
+    templates.sort_by(|left, right| left.name.cmp(&right.name));
+    Ok(templates)
+}
+
+pub fn load_template(dir: &Path, name: &str) -> Result<NoteTemplate, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("invalid template name: `{name}`"));
+    }
+    let path = dir.join(format!("{name}.md"));
+    if !path.exists() {
+        return Err(format!(
+            "template `{name}` not found in {} (see `kibel template list`)",
+            dir.display()
+        ));
+    }
+    read_template(name, &path)
+}
+
+fn read_template(name: &str, path: &Path) -> Result<NoteTemplate, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|error| format!("failed to read template {}: {error}", path.display()))?;
+    let (title, body) = split_front_matter(&raw);
+    Ok(NoteTemplate {
+        name: name.to_string(),
+        path: path.to_path_buf(),
+        title,
+        body: body.to_string(),
+    })
+}
+
+fn split_front_matter(raw: &str) -> (Option<String>, &str) {
+    let Some(rest) = raw
+        .strip_prefix("---\n")
+        .or_else(|| raw.strip_prefix("---\r\n"))
+    else {
+        return (None, raw);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (None, raw);
+    };
+    let header = &rest[..end];
+    let body = rest[end + 4..].trim_start_matches(['\r', '\n']);
+    let title = header.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "title").then(|| value.trim().trim_matches('"').to_string())
+    });
+    (title.filter(|title| !title.is_empty()), body)
+}
+
+/// Substitutes `{{name}}` placeholders. Unknown names are an error so a
+/// typo cannot silently reach the output.
+pub fn render_template(text: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let (rendered, mut missing) = scan(text, vars);
+    if missing.is_empty() {
+        Ok(rendered)
+    } else {
+        missing.sort();
+        missing.dedup();
+        Err(format!(
+            "undefined template variables: {}",
+            missing.join(", ")
+        ))
+    }
+}
+
+pub fn placeholders(text: &str) -> Vec<String> {
+    let (_, mut names) = scan(text, &BTreeMap::new());
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn scan(text: &str, vars: &BTreeMap<String, String>) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(text.len());
+    let mut missing = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let name = after[..end].trim();
+        let is_placeholder = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+        if !is_placeholder {
+            out.push_str("{{");
+            rest = after;
+            continue;
+        }
+        match vars.get(name) {
+            Some(value) => out.push_str(value),
+            None => missing.push(name.to_string()),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    (out, missing)
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`.
+pub fn today_utc() -> String {
+    utc_date_days_ago(0)
+}
+
+/// The UTC date `days` before today as `YYYY-MM-DD`.
+pub fn utc_date_days_ago(days: u64) -> String {
+    let today = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400);
+    civil_date(i64::try_from(today.saturating_sub(days)).unwrap_or_default())
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian date.
+pub fn civil_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{civil_date, placeholders, render_template, split_front_matter};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn render_template_substitutes_and_rejects_unknown_vars() {
+        let vars = BTreeMap::from([("version".to_string(), "0.2.8".to_string())]);
+        assert_eq!(
+            render_template("v{{ version }} {{not a var}}", &vars),
+            Ok("v0.2.8 {{not a var}}".to_string())
+        );
+        assert!(render_template("{{missing}}", &vars).is_err());
+        assert_eq!(placeholders("{{b}} {{a}} {{b}}"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn split_front_matter_reads_title() {
+        let (title, body) = split_front_matter("---\ntitle: Daily {{date}}\n---\n\n# Notes\n");
+        assert_eq!(title.as_deref(), Some("Daily {{date}}"));
+        assert_eq!(body, "# Notes\n");
+        assert_eq!(split_front_matter("# Plain\n"), (None, "# Plain\n"));
+    }
+
+    #[test]
+    fn civil_date_converts_epoch_days() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(20_507), "2026-02-23");
+        assert_eq!(civil_date(11_016), "2000-02-29");
+    }
+}
@@ -0,0 +1,71 @@
+use serde_json::{json, Map, Value};
+
+/// Identifies the heuristic so consumers can tell estimates apart if it changes.
+pub const ESTIMATE_METHOD: &str = "bpe-approx-v1";
+
+/// Approximates how many BPE tokens `text` costs in an LLM prompt.
+///
+/// ASCII alphanumeric runs count one token per four characters, ASCII
+/// punctuation one token each, whitespace nothing (it merges into the next
+/// token), and every other character one token (CJK text rarely merges).
+pub fn estimate_text(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut run = 0usize;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            run += 1;
+            continue;
+        }
+        tokens += run.div_ceil(4);
+        run = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + run.div_ceil(4)
+}
+
+/// Estimates `value` as compact JSON, the form `--json` prints.
+pub fn estimate_value(value: &Value) -> usize {
+    estimate_text(&value.to_string())
+}
+
+/// Builds `meta.token_estimate`: the total for `value` plus a breakdown per
+/// top-level field when `value` is an object.
+pub fn estimate_breakdown(value: &Value) -> Value {
+    let fields = value
+        .as_object()
+        .map(|object| {
+            object
+                .iter()
+                .map(|(key, field)| (key.clone(), json!(estimate_value(field))))
+                .collect::<Map<_, _>>()
+        })
+        .unwrap_or_default();
+    json!({
+        "method": ESTIMATE_METHOD,
+        "total": estimate_value(value),
+        "fields": fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_breakdown, estimate_text};
+    use serde_json::json;
+
+    #[test]
+    fn estimate_text_counts_words_punctuation_and_cjk() {
+        assert_eq!(estimate_text(""), 0);
+        assert_eq!(estimate_text("hello world"), 4);
+        assert_eq!(estimate_text("a, b."), 4);
+        assert_eq!(estimate_text("議事録"), 3);
+    }
+
+    #[test]
+    fn estimate_breakdown_reports_top_level_fields() {
+        let estimate = estimate_breakdown(&json!({"note": {"title": "x"}, "meta": null}));
+        assert_eq!(estimate["fields"]["meta"], json!(1));
+        assert!(estimate["total"].as_u64() > estimate["fields"]["note"].as_u64());
+    }
+}
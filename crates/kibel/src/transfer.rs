@@ -0,0 +1,112 @@
+use crate::links;
+use std::collections::HashMap;
+
+/// Attachment URLs in `content` that live on `origin`, root-relative
+/// (`/attachments/...`) or absolute, first occurrence first.
+pub fn attachment_links(content: &str, origin: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    for (start, end) in attachment_spans(content, origin) {
+        let link = &content[start..end];
+        if !links.iter().any(|seen| seen == link) {
+            links.push(link.to_string());
+        }
+    }
+    links
+}
+
+/// Replaces every attachment URL found by [`attachment_links`] with its
+/// entry in `rewrites`; URLs without an entry are kept.
+pub fn rewrite_links(content: &str, origin: &str, rewrites: &HashMap<String, String>) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for (start, end) in attachment_spans(content, origin) {
+        if let Some(rewritten) = rewrites.get(&content[start..end]) {
+            output.push_str(&content[cursor..start]);
+            output.push_str(rewritten);
+            cursor = end;
+        }
+    }
+    output.push_str(&content[cursor..]);
+    output
+}
+
+/// File name for re-uploading: the last path segment, without a query.
+pub fn attachment_name(link: &str) -> String {
+    let path = link.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or("attachment")
+        .to_string()
+}
+
+/// [`attachment_name`] made unique among `taken` by numbering repeats
+/// (`a.png`, `a-2.png`, ...), then recorded in `taken`.
+pub fn unique_attachment_name(link: &str, taken: &mut Vec<String>) -> String {
+    let name = attachment_name(link);
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem.to_string(), format!(".{extension}")),
+        _ => (name.clone(), String::new()),
+    };
+    let mut candidate = name;
+    let mut counter = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{stem}-{counter}{extension}");
+        counter += 1;
+    }
+    taken.push(candidate.clone());
+    candidate
+}
+
+fn attachment_spans(content: &str, origin: &str) -> Vec<(usize, usize)> {
+    let prefix = format!("{}/attachments/", origin.trim_end_matches('/'));
+    links::token_spans(content)
+        .into_iter()
+        .filter(|&(start, end)| {
+            let token = &content[start..end];
+            token.starts_with("/attachments/") || token.starts_with(&prefix)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{attachment_links, attachment_name, rewrite_links, unique_attachment_name};
+    use std::collections::HashMap;
+
+    #[test]
+    fn links_are_found_and_rewritten_by_exact_token() {
+        let origin = "https://acme.kibe.la";
+        let content =
+            "![a](/attachments/1/a.png) <img src=\"https://acme.kibe.la/attachments/12\">\n\
+                       again /attachments/1/a.png and https://other.example/attachments/3";
+        assert_eq!(
+            attachment_links(content, origin),
+            vec![
+                "/attachments/1/a.png",
+                "https://acme.kibe.la/attachments/12"
+            ]
+        );
+
+        let rewrites = HashMap::from([(
+            "/attachments/1/a.png".to_string(),
+            "https://beta.kibe.la/attachments/9/a.png".to_string(),
+        )]);
+        assert_eq!(
+            rewrite_links(content, origin, &rewrites),
+            "![a](https://beta.kibe.la/attachments/9/a.png) <img src=\"https://acme.kibe.la/attachments/12\">\n\
+             again https://beta.kibe.la/attachments/9/a.png and https://other.example/attachments/3"
+        );
+        assert_eq!(attachment_name("/attachments/1/a.png?v=2"), "a.png");
+        assert_eq!(attachment_name("/attachments/12/"), "12");
+
+        let mut taken = Vec::new();
+        let names = [
+            "/attachments/1/a.png",
+            "/attachments/2/a.png",
+            "/attachments/3/a.png",
+            "/attachments/4/",
+        ]
+        .map(|link| unique_attachment_name(link, &mut taken));
+        assert_eq!(names, ["a.png", "a-2.png", "a-3.png", "4"]);
+    }
+}
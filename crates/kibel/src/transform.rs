@@ -0,0 +1,123 @@
+//! The `on_fetch` pipeline applied to note content returned by read commands.
+
+mod resolve_links;
+mod strip_html;
+mod truncate;
+
+pub const AVAILABLE: &[&str] = &["strip-html", "resolve-links", "truncate:<chars>"];
+
+/// One step of an `on_fetch` pipeline, parsed from `name` or `name:arg`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transform {
+    StripHtml,
+    ResolveLinks,
+    Truncate(usize),
+}
+
+impl Transform {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let (name, arg) = match raw.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (raw, None),
+        };
+        match (name, arg) {
+            ("strip-html", None) => Ok(Self::StripHtml),
+            ("resolve-links", None) => Ok(Self::ResolveLinks),
+            ("truncate", Some(arg)) => arg
+                .parse::<usize>()
+                .ok()
+                .filter(|chars| *chars > 0)
+                .map(Self::Truncate)
+                .ok_or_else(|| format!("truncate expects a positive character count: {raw}")),
+            _ => Err(format!(
+                "unknown on_fetch transform `{raw}`; available: {}",
+                AVAILABLE.join(", ")
+            )),
+        }
+    }
+
+    fn apply(&self, content: &str, origin: &str) -> String {
+        match self {
+            Self::StripHtml => strip_html::apply(content),
+            Self::ResolveLinks => resolve_links::apply(content, origin),
+            Self::Truncate(chars) => truncate::apply(content, *chars),
+        }
+    }
+}
+
+/// An ordered list of transforms; the output of each feeds the next.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    steps: Vec<Transform>,
+    specs: Vec<String>,
+}
+
+impl Pipeline {
+    pub fn parse(specs: &[String]) -> Result<Self, String> {
+        let specs = specs
+            .iter()
+            .map(|spec| spec.trim())
+            .filter(|spec| !spec.is_empty())
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        let steps = specs
+            .iter()
+            .map(|spec| Transform::parse(spec))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { steps, specs })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The normalized specs, echoed in command metadata.
+    pub fn specs(&self) -> &[String] {
+        &self.specs
+    }
+
+    pub fn apply(&self, content: &str, origin: &str) -> String {
+        self.steps
+            .iter()
+            .fold(content.to_string(), |content, step| {
+                step.apply(&content, origin)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pipeline, Transform};
+
+    #[test]
+    fn parse_accepts_known_transforms() {
+        assert_eq!(Transform::parse("strip-html"), Ok(Transform::StripHtml));
+        assert_eq!(
+            Transform::parse(" truncate: 200 "),
+            Ok(Transform::Truncate(200))
+        );
+        assert!(Transform::parse("truncate:0").is_err());
+        assert!(Transform::parse("truncate").is_err());
+        assert!(Transform::parse("shout")
+            .expect_err("unknown transform")
+            .contains("available: strip-html"));
+    }
+
+    #[test]
+    fn pipeline_applies_steps_in_order() {
+        let pipeline = Pipeline::parse(&[
+            "strip-html".to_string(),
+            "resolve-links".to_string(),
+            "truncate:30".to_string(),
+        ])
+        .expect("pipeline should parse");
+        assert_eq!(
+            pipeline.apply(
+                "<p>See [spec](/notes/12)</p> and more text here",
+                "https://acme.kibe.la"
+            ),
+            "See [spec](https://acme.kibe.l…\n\n[truncated 30 of 60 characters]"
+        );
+    }
+}
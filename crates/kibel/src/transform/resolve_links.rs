@@ -0,0 +1,38 @@
+use crate::note_ref::note_url;
+
+/// Rewrites root-relative Markdown link targets (`](/notes/1)`) into absolute
+/// URLs on `origin`, so links stay usable once content leaves Kibela.
+pub fn apply(content: &str, origin: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(index) = rest.find("](/") {
+        let (before, after) = rest.split_at(index + 2);
+        output.push_str(before);
+        let end = after.find([')', ' ']).unwrap_or(after.len());
+        let (path, tail) = after.split_at(end);
+        if path.starts_with("//") {
+            output.push_str(path);
+        } else {
+            output.push_str(&note_url(origin, path));
+        }
+        rest = tail;
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+
+    #[test]
+    fn resolves_root_relative_links_only() {
+        assert_eq!(
+            apply(
+                "[a](/notes/1) [b](https://example.com/x) [c](//cdn/x) ![img](/attachments/2 \"t\")",
+                "https://acme.kibe.la/"
+            ),
+            "[a](https://acme.kibe.la/notes/1) [b](https://example.com/x) [c](//cdn/x) ![img](https://acme.kibe.la/attachments/2 \"t\")"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+/// Removes HTML tags and decodes common entities, leaving fenced code blocks
+/// untouched so embedded markup examples survive.
+pub fn apply(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut in_fence = false;
+    for line in content.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            output.push_str(line);
+            continue;
+        }
+        if in_fence {
+            output.push_str(line);
+        } else {
+            output.push_str(&decode_entities(&strip_tags(line)));
+        }
+    }
+    output
+}
+
+fn strip_tags(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut in_tag = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' if !in_tag
+                && chars.peek().is_some_and(|next| {
+                    next.is_ascii_alphabetic() || matches!(next, '/' | '!')
+                }) =>
+            {
+                in_tag = true;
+            }
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+fn decode_entities(text: &str) -> String {
+    [
+        ("&nbsp;", " "),
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&quot;", "\""),
+        ("&#39;", "'"),
+        ("&amp;", "&"),
+    ]
+    .iter()
+    .fold(text.to_string(), |text, (entity, replacement)| {
+        text.replace(entity, replacement)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+
+    #[test]
+    fn strips_tags_outside_code_fences() {
+        assert_eq!(
+            apply("<div class=\"x\">a &amp; b</div> 1 < 2\n```html\n<b>keep</b>\n```\n"),
+            "a & b 1 < 2\n```html\n<b>keep</b>\n```\n"
+        );
+    }
+}
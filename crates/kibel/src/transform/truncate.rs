@@ -0,0 +1,27 @@
+/// Keeps the first `max_chars` characters and appends a marker saying how
+/// much was dropped.
+pub fn apply(content: &str, max_chars: usize) -> String {
+    let total = content.chars().count();
+    if total <= max_chars {
+        return content.to_string();
+    }
+    let kept = content.chars().take(max_chars).collect::<String>();
+    format!(
+        "{kept}…\n\n[truncated {} of {total} characters]",
+        total - max_chars
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+
+    #[test]
+    fn truncates_on_character_boundaries() {
+        assert_eq!(apply("短い", 5), "短い");
+        assert_eq!(
+            apply("議事録の本文", 3),
+            "議事録…\n\n[truncated 3 of 6 characters]"
+        );
+    }
+}
@@ -0,0 +1,588 @@
+use crate::picker::{char_columns, display_columns, truncate_columns};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::Frame;
+use std::io::IsTerminal;
+
+/// Lines `PageUp` / `PageDown` scroll the preview by.
+const PAGE_LINES: usize = 10;
+const HELP: &str = "Tab:pane  j/k:move  Enter:load  /:search  o:open  c:comments  r:refresh  \
+                    PgUp/PgDn:scroll  q:quit";
+
+/// Whether a left-pane entry lists a group's feed or a folder's notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Group,
+    Folder,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteItem {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub author: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub title: String,
+    pub content: String,
+    pub comments: Vec<Comment>,
+}
+
+/// Everything the TUI fetches after start-up; `kibel tui` implements it over
+/// the API client, tests over fixtures.
+pub trait TuiSource {
+    /// Groups and folders fetched again, for `r`.
+    fn scopes(&self) -> Result<Vec<Scope>, String>;
+    fn notes(&self, scope: &Scope) -> Result<Vec<NoteItem>, String>;
+    fn search(&self, query: &str) -> Result<Vec<NoteItem>, String>;
+    fn preview(&self, note: &NoteItem) -> Result<Preview, String>;
+    fn open(&self, url: &str) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Scopes,
+    Notes,
+    Preview,
+}
+
+/// What a key press asks the event loop to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Redraw,
+    Quit,
+    RefreshScopes,
+    LoadNotes(usize),
+    Search(String),
+    LoadPreview(usize),
+    Open(String),
+}
+
+/// Pane contents, cursors, and the status line; key handling is pure and
+/// drawing goes through a ratatui [`Frame`], so both can be tested without a
+/// terminal.
+#[derive(Debug, Clone)]
+pub struct TuiState {
+    scopes: Vec<Scope>,
+    scope_cursor: usize,
+    notes: Vec<NoteItem>,
+    note_cursor: usize,
+    notes_label: String,
+    preview: Option<Preview>,
+    scroll: usize,
+    show_comments: bool,
+    focus: Pane,
+    query: Option<String>,
+    status: String,
+}
+
+impl TuiState {
+    pub fn new(scopes: Vec<Scope>) -> Self {
+        Self {
+            scopes,
+            scope_cursor: 0,
+            notes: Vec::new(),
+            note_cursor: 0,
+            notes_label: "Notes".to_string(),
+            preview: None,
+            scroll: 0,
+            show_comments: false,
+            focus: Pane::Scopes,
+            query: None,
+            status: String::new(),
+        }
+    }
+
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = status.into();
+    }
+
+    /// Replaces the groups and folders, keeping the cursor in range.
+    pub fn show_scopes(&mut self, scopes: Vec<Scope>) {
+        self.status = format!("{} group(s) and folder(s)", scopes.len());
+        self.scope_cursor = self.scope_cursor.min(scopes.len().saturating_sub(1));
+        self.scopes = scopes;
+    }
+
+    /// Replaces the note list and moves the focus to it.
+    pub fn show_notes(&mut self, label: impl Into<String>, notes: Vec<NoteItem>) {
+        self.notes_label = label.into();
+        self.status = format!("{} note(s)", notes.len());
+        self.notes = notes;
+        self.note_cursor = 0;
+        self.focus = Pane::Notes;
+    }
+
+    /// Shows a note in the preview pane, scrolled to the top.
+    pub fn show_preview(&mut self, preview: Preview) {
+        self.status = format!("{} ({} comment(s))", preview.title, preview.comments.len());
+        self.preview = Some(preview);
+        self.scroll = 0;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl_c =
+            key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if let Some(query) = self.query.as_mut() {
+            match key.code {
+                _ if ctrl_c => self.query = None,
+                KeyCode::Enter => {
+                    let query = self.query.take().unwrap_or_default();
+                    if !query.trim().is_empty() {
+                        return Action::Search(query.trim().to_string());
+                    }
+                }
+                KeyCode::Esc => self.query = None,
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Char(typed) if !typed.is_control() => query.push(typed),
+                _ => {}
+            }
+            return Action::Redraw;
+        }
+        if ctrl_c {
+            return Action::Quit;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => {
+                self.focus = match self.focus {
+                    Pane::Scopes => Pane::Notes,
+                    Pane::Notes => Pane::Preview,
+                    Pane::Preview => Pane::Scopes,
+                };
+            }
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => {
+                self.focus = match self.focus {
+                    Pane::Scopes => Pane::Preview,
+                    Pane::Notes => Pane::Scopes,
+                    Pane::Preview => Pane::Notes,
+                };
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1, true),
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(1, false),
+            KeyCode::PageDown => self.scroll += PAGE_LINES,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(PAGE_LINES),
+            KeyCode::Enter => match self.focus {
+                Pane::Scopes if !self.scopes.is_empty() => {
+                    return Action::LoadNotes(self.scope_cursor)
+                }
+                Pane::Notes if !self.notes.is_empty() => {
+                    return Action::LoadPreview(self.note_cursor)
+                }
+                _ => {}
+            },
+            KeyCode::Char('/') => self.query = Some(String::new()),
+            KeyCode::Char('r') => return Action::RefreshScopes,
+            KeyCode::Char('c') => {
+                self.show_comments = !self.show_comments;
+                self.scroll = 0;
+            }
+            KeyCode::Char('o') => match self.notes.get(self.note_cursor) {
+                Some(NoteItem { url: Some(url), .. }) => return Action::Open(url.clone()),
+                Some(_) => self.status = "this note has no URL".to_string(),
+                None => self.status = "no note selected".to_string(),
+            },
+            _ => {}
+        }
+        Action::Redraw
+    }
+
+    fn move_cursor(&mut self, step: usize, down: bool) {
+        let (cursor, len) = match self.focus {
+            Pane::Scopes => (&mut self.scope_cursor, self.scopes.len()),
+            Pane::Notes => (&mut self.note_cursor, self.notes.len()),
+            Pane::Preview => (&mut self.scroll, usize::MAX),
+        };
+        *cursor = if down {
+            cursor.saturating_add(step).min(len.saturating_sub(1))
+        } else {
+            cursor.saturating_sub(step)
+        };
+    }
+
+    /// Draws the three panes side by side over a status line.
+    pub fn draw(&self, frame: &mut Frame) {
+        let [body, footer] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [scopes_area, notes_area, preview_area] = Layout::horizontal([
+            Constraint::Percentage(20),
+            Constraint::Percentage(30),
+            Constraint::Fill(1),
+        ])
+        .areas(body);
+
+        let scopes = self
+            .scopes
+            .iter()
+            .map(|scope| match scope.kind {
+                ScopeKind::Group => format!("# {}", scope.name),
+                ScopeKind::Folder => format!("/ {}", scope.name),
+            })
+            .collect::<Vec<_>>();
+        self.draw_list(
+            frame,
+            scopes_area,
+            "Groups / Folders",
+            scopes,
+            self.scope_cursor,
+            Pane::Scopes,
+        );
+        let notes = self.notes.iter().map(|note| note.title.clone()).collect();
+        self.draw_list(
+            frame,
+            notes_area,
+            &self.notes_label,
+            notes,
+            self.note_cursor,
+            Pane::Notes,
+        );
+
+        let block = self.block("Preview", Pane::Preview);
+        let width = usize::from(block.inner(preview_area).width).max(1);
+        let lines = self.preview_lines(width);
+        let scroll = self.scroll.min(lines.len().saturating_sub(1));
+        let preview = Paragraph::new(lines.into_iter().map(Line::from).collect::<Vec<_>>())
+            .block(block)
+            .scroll((u16::try_from(scroll).unwrap_or(u16::MAX), 0));
+        frame.render_widget(preview, preview_area);
+
+        let status = match &self.query {
+            Some(query) => format!("search: {query}▏"),
+            None if self.status.is_empty() => HELP.to_string(),
+            None => format!("{}  |  {HELP}", self.status),
+        };
+        frame.render_widget(Paragraph::new(status), footer);
+    }
+
+    /// A bordered pane, its border bold while it has the focus.
+    fn block<'a>(&self, title: &'a str, pane: Pane) -> Block<'a> {
+        let block = Block::bordered().title(title);
+        if self.focus == pane {
+            block.border_style(Style::new().add_modifier(Modifier::BOLD))
+        } else {
+            block
+        }
+    }
+
+    fn draw_list(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: &str,
+        items: Vec<String>,
+        cursor: usize,
+        pane: Pane,
+    ) {
+        let selected = if self.focus == pane {
+            Style::new().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::new().add_modifier(Modifier::BOLD)
+        };
+        let selection = (!items.is_empty()).then_some(cursor);
+        let list = List::new(items)
+            .block(self.block(title, pane))
+            .highlight_symbol("> ")
+            .highlight_style(selected);
+        frame.render_stateful_widget(
+            list,
+            area,
+            &mut ListState::default().with_selected(selection),
+        );
+    }
+
+    fn preview_lines(&self, width: usize) -> Vec<String> {
+        let Some(preview) = &self.preview else {
+            return vec!["Select a note and press Enter.".to_string()];
+        };
+        let mut lines = wrap(&preview.title, width);
+        lines.push("═".repeat(display_columns(&preview.title).clamp(1, width)));
+        if self.show_comments {
+            lines.push(format!("{} comment(s)", preview.comments.len()));
+            for comment in &preview.comments {
+                lines.push(String::new());
+                lines.push(format!("@{}", comment.author));
+                lines.extend(markdown_lines(&comment.content, width));
+            }
+        } else {
+            lines.extend(markdown_lines(&preview.content, width));
+        }
+        lines
+    }
+}
+
+/// Hard-wraps `text` at `width` columns; an empty line stays one line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let (mut line, mut columns) = (String::new(), 0);
+    for ch in text.chars() {
+        let ch_columns = char_columns(ch);
+        if columns + ch_columns > width && !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            columns = 0;
+        }
+        line.push(ch);
+        columns += ch_columns;
+    }
+    lines.push(line);
+    lines
+}
+
+/// A plain-text rendering of Markdown for the preview pane: headings are
+/// underlined, code blocks fenced with `│`, links shown as `text <url>`, and
+/// emphasis markers dropped.
+pub fn markdown_lines(content: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for raw in content.lines() {
+        let trimmed = raw.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(truncate_columns(&format!("│ {raw}"), width));
+            continue;
+        }
+        let level = trimmed.chars().take_while(|ch| *ch == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            let heading = inline_text(trimmed[level..].trim());
+            let rule = if level == 1 { "═" } else { "─" };
+            let underline = rule.repeat(display_columns(&heading).clamp(1, width));
+            lines.extend(wrap(&heading, width));
+            lines.push(underline);
+            continue;
+        }
+        lines.extend(wrap(&inline_text(raw), width));
+    }
+    lines
+}
+
+/// Rewrites inline Markdown: `![alt](src)` → `[image: alt]`,
+/// `[text](url)` → `text <url>`, and `**` / `__` / `` ` `` removed.
+fn inline_text(line: &str) -> String {
+    let mut text = String::new();
+    let mut rest = line;
+    while let Some(open) = rest.find('[') {
+        let image = open > 0 && rest[..open].ends_with('!');
+        let Some(link) = parse_link(&rest[open..]) else {
+            text.push_str(&rest[..=open]);
+            rest = &rest[open + 1..];
+            continue;
+        };
+        let (label, target, consumed) = link;
+        if image {
+            text.push_str(&rest[..open - 1]);
+            text.push_str(&format!("[image: {label}]"));
+        } else {
+            text.push_str(&rest[..open]);
+            text.push_str(&format!("{label} <{target}>"));
+        }
+        rest = &rest[open + consumed..];
+    }
+    text.push_str(rest);
+    text.replace("**", "").replace("__", "").replace('`', "")
+}
+
+/// `[label](target)` at the start of `input`: the label, the target, and the
+/// bytes consumed.
+fn parse_link(input: &str) -> Option<(&str, &str, usize)> {
+    let close = input.find("](")?;
+    let label = &input[1..close];
+    if label.contains('[') {
+        return None;
+    }
+    let end = input[close + 2..].find(')')? + close + 2;
+    Some((label, &input[close + 2..end], end + 1))
+}
+
+/// Runs the TUI on the terminal's alternate screen until the user quits.
+///
+/// Returns how many notes were previewed.
+pub fn run(state: &mut TuiState, source: &dyn TuiSource) -> Result<usize, String> {
+    if !std::io::stdout().is_terminal() {
+        return Err("tui needs an interactive terminal".to_string());
+    }
+    let mut terminal =
+        ratatui::try_init().map_err(|error| format!("failed to prepare the terminal: {error}"))?;
+    let result = event_loop(&mut terminal, state, source);
+    let _ = ratatui::try_restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut ratatui::DefaultTerminal,
+    state: &mut TuiState,
+    source: &dyn TuiSource,
+) -> Result<usize, String> {
+    let mut previewed = 0;
+    loop {
+        terminal
+            .draw(|frame| state.draw(frame))
+            .map_err(|error| format!("failed to draw: {error}"))?;
+
+        let key = match event::read().map_err(|error| format!("failed to read a key: {error}"))? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match state.handle_key(key) {
+            Action::Redraw => {}
+            Action::Quit => return Ok(previewed),
+            Action::RefreshScopes => match source.scopes() {
+                Ok(scopes) => state.show_scopes(scopes),
+                Err(error) => state.set_status(error),
+            },
+            Action::LoadNotes(index) => {
+                let scope = state.scopes[index].clone();
+                match source.notes(&scope) {
+                    Ok(notes) => state.show_notes(scope.name, notes),
+                    Err(error) => state.set_status(error),
+                }
+            }
+            Action::Search(query) => match source.search(&query) {
+                Ok(notes) => state.show_notes(format!("search: {query}"), notes),
+                Err(error) => state.set_status(error),
+            },
+            Action::LoadPreview(index) => {
+                let note = state.notes[index].clone();
+                match source.preview(&note) {
+                    Ok(preview) => {
+                        previewed += 1;
+                        state.show_preview(preview);
+                    }
+                    Err(error) => state.set_status(error),
+                }
+            }
+            Action::Open(url) => match source.open(&url) {
+                Ok(()) => state.set_status(format!("opened {url}")),
+                Err(error) => state.set_status(error),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{markdown_lines, Action, NoteItem, Pane, Preview, Scope, ScopeKind, TuiState};
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::Terminal;
+
+    fn note(id: &str, url: Option<&str>) -> NoteItem {
+        NoteItem {
+            id: id.to_string(),
+            title: format!("title {id}"),
+            url: url.map(str::to_string),
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::from(code)
+    }
+
+    #[test]
+    fn keys_drive_panes_and_draw_fills_the_screen() {
+        let mut state = TuiState::new(vec![
+            Scope {
+                kind: ScopeKind::Group,
+                id: "G1".to_string(),
+                name: "Engineering".to_string(),
+            },
+            Scope {
+                kind: ScopeKind::Folder,
+                id: "F1".to_string(),
+                name: "設計メモ".to_string(),
+            },
+        ]);
+        assert_eq!(state.handle_key(key(KeyCode::Char('j'))), Action::Redraw);
+        assert_eq!(state.handle_key(key(KeyCode::Char('j'))), Action::Redraw);
+        assert_eq!(state.handle_key(key(KeyCode::Enter)), Action::LoadNotes(1));
+        assert_eq!(
+            state.handle_key(key(KeyCode::Char('r'))),
+            Action::RefreshScopes
+        );
+
+        state.show_notes("設計メモ", vec![note("N1", None), note("N2", Some("u2"))]);
+        assert_eq!(state.focus, Pane::Notes);
+        assert_eq!(state.handle_key(key(KeyCode::Char('o'))), Action::Redraw);
+        state.handle_key(key(KeyCode::Down));
+        assert_eq!(
+            state.handle_key(key(KeyCode::Char('o'))),
+            Action::Open("u2".to_string())
+        );
+        assert_eq!(
+            state.handle_key(key(KeyCode::Enter)),
+            Action::LoadPreview(1)
+        );
+
+        for typed in "/deploy".chars() {
+            state.handle_key(key(KeyCode::Char(typed)));
+        }
+        assert_eq!(state.handle_key(key(KeyCode::Char('q'))), Action::Redraw);
+        assert_eq!(
+            state.handle_key(key(KeyCode::Enter)),
+            Action::Search("deployq".to_string())
+        );
+        assert_eq!(state.handle_key(key(KeyCode::Char('c'))), Action::Redraw);
+        assert!(state.show_comments);
+        state.handle_key(key(KeyCode::Char('c')));
+        assert_eq!(
+            state.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Action::Quit
+        );
+
+        state.show_preview(Preview {
+            title: "Runbook".to_string(),
+            content: "# Deploy\nsee [docs](https://example.com) and ![x](a.png)".to_string(),
+            comments: Vec::new(),
+        });
+        state.show_scopes(Vec::new());
+        let mut terminal = Terminal::new(TestBackend::new(100, 12)).expect("test terminal");
+        terminal.draw(|frame| state.draw(frame)).expect("draw");
+        let screen = terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(100)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>();
+        assert!(screen[0].contains("Groups / Folders"), "{screen:?}");
+        assert!(screen[2].contains("> title N2"), "{screen:?}");
+        assert!(
+            screen
+                .iter()
+                .any(|row| row.contains("docs <https://example.com>")),
+            "{screen:?}"
+        );
+        assert!(
+            screen[11].starts_with("0 group(s) and folder(s)"),
+            "{screen:?}"
+        );
+    }
+
+    #[test]
+    fn markdown_lines_strip_syntax_and_wrap() {
+        let lines = markdown_lines("## **Plan**\n```\nlet x = 1;\n```\n`code` text", 8);
+        assert_eq!(lines, vec!["Plan", "────", "│ let x…", "code tex", "t"]);
+    }
+}
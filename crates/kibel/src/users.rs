@@ -0,0 +1,58 @@
+use kibel_client::LocalStore;
+use serde_json::Value;
+
+/// Account to user id mappings per team live in the local store, filled by
+/// `--author` and mention lookups and by `search user`, so repeated lookups
+/// skip the search API.
+pub fn cached_id(store: &LocalStore, team: &str, account: &str) -> Option<String> {
+    store.user_id(team, account).ok().flatten()
+}
+
+/// Records the `{id, account}` pairs of `search user` results; best-effort.
+pub fn record(store: &mut LocalStore, team: &str, users: &[Value]) {
+    let _ = store.record_user_ids(team, account_ids(users));
+}
+
+/// `(account, id)` of each user that has both.
+fn account_ids(users: &[Value]) -> impl Iterator<Item = (&str, &str)> {
+    users.iter().filter_map(|user| {
+        Some((
+            user.get("account").and_then(Value::as_str)?,
+            user.get("id").and_then(Value::as_str)?,
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cached_id, record};
+    use kibel_client::LocalStore;
+    use serde_json::json;
+
+    #[test]
+    fn mappings_are_kept_per_team_and_survive_a_reopen() {
+        let dir = std::env::temp_dir().join(format!("kibel-users-{}", std::process::id()));
+        let path = LocalStore::path_in(&dir);
+        let mut store = LocalStore::open(&path).expect("store should open");
+        record(
+            &mut store,
+            "acme",
+            &[
+                json!({"id": "U1", "account": "alice"}),
+                json!({"id": null, "account": "ghost"}),
+            ],
+        );
+        record(
+            &mut store,
+            "other",
+            &[json!({"id": "U9", "account": "alice"})],
+        );
+        drop(store);
+
+        let store = LocalStore::open(&path).expect("store should reopen");
+        assert_eq!(cached_id(&store, "acme", "alice").as_deref(), Some("U1"));
+        assert_eq!(cached_id(&store, "acme", "ghost"), None);
+        assert_eq!(cached_id(&store, "other", "alice").as_deref(), Some("U9"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
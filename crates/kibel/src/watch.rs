@@ -0,0 +1,240 @@
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Oldest comment ids are dropped once the state holds this many.
+const MAX_SEEN_COMMENTS: usize = 5000;
+
+/// De-duplication state for `feed watch`, persisted as JSON between runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatchState {
+    /// note id -> fingerprint of the last observed title/summary.
+    pub notes: BTreeMap<String, String>,
+    /// comment ids in observation order.
+    pub comments: Vec<String>,
+    pub initialized: bool,
+}
+
+impl WatchState {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = fs::read_to_string(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        let value = serde_json::from_str::<Value>(&raw)
+            .map_err(|error| format!("state file {} is not JSON: {error}", path.display()))?;
+        let notes = value
+            .get("notes")
+            .and_then(Value::as_object)
+            .map(|notes| {
+                notes
+                    .iter()
+                    .filter_map(|(id, fingerprint)| {
+                        Some((id.clone(), fingerprint.as_str()?.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let comments = value
+            .get("comments")
+            .and_then(Value::as_array)
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(Value::as_str)
+                    .map(ToOwned::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            notes,
+            comments,
+            initialized: true,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let notes = self
+            .notes
+            .iter()
+            .map(|(id, fingerprint)| (id.clone(), Value::String(fingerprint.clone())))
+            .collect::<Map<_, _>>();
+        let body = json!({ "notes": notes, "comments": self.comments });
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .map_err(|error| format!("failed to create {}: {error}", parent.display()))?;
+        }
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, format!("{body}\n"))
+            .and_then(|()| fs::rename(&temp, path))
+            .map_err(|error| format!("failed to write {}: {error}", path.display()))
+    }
+
+    /// Records the notes in `sections` and returns events for new or changed ones.
+    pub fn observe_notes(&mut self, sections: &[Value]) -> Vec<Value> {
+        let mut events = Vec::new();
+        for (date, note) in sections.iter().flat_map(feed_section_notes) {
+            let Some(id) = note.get("id").and_then(Value::as_str) else {
+                continue;
+            };
+            let fingerprint = note_fingerprint(note);
+            let kind = match self.notes.insert(id.to_string(), fingerprint.clone()) {
+                None => "note.created",
+                Some(previous) if previous != fingerprint => "note.updated",
+                Some(_) => continue,
+            };
+            events.push(json!({
+                "type": kind,
+                "id": id,
+                "title": note.get("title").cloned().unwrap_or(Value::Null),
+                "date": date,
+            }));
+        }
+        events
+    }
+
+    /// Records comment search hits and returns events for unseen ones.
+    pub fn observe_comments(&mut self, results: &[Value]) -> Vec<Value> {
+        let mut seen = self.comments.iter().cloned().collect::<BTreeSet<_>>();
+        let mut events = Vec::new();
+        for hit in results {
+            let Some(id) = hit.get("id").and_then(Value::as_str) else {
+                continue;
+            };
+            if !seen.insert(id.to_string()) {
+                continue;
+            }
+            self.comments.push(id.to_string());
+            events.push(json!({
+                "type": "comment.created",
+                "id": id,
+                "title": hit.get("title").cloned().unwrap_or(Value::Null),
+                "url": hit.get("url").cloned().unwrap_or(Value::Null),
+                "author": hit.pointer("/author/account").cloned().unwrap_or(Value::Null),
+            }));
+        }
+        if self.comments.len() > MAX_SEEN_COMMENTS {
+            let overflow = self.comments.len() - MAX_SEEN_COMMENTS;
+            self.comments.drain(..overflow);
+        }
+        events
+    }
+
+    /// Undoes the observation behind `event`, back to what `before` held, so
+    /// the next poll reports it again.
+    pub fn forget(&mut self, event: &Value, before: &WatchState) {
+        let Some(id) = event.get("id").and_then(Value::as_str) else {
+            return;
+        };
+        match event.get("type").and_then(Value::as_str) {
+            Some("comment.created") => self.comments.retain(|seen| seen != id),
+            Some("note.created" | "note.updated") => match before.notes.get(id) {
+                Some(fingerprint) => {
+                    self.notes.insert(id.to_string(), fingerprint.clone());
+                }
+                None => {
+                    self.notes.remove(id);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Flattens the note entries of a feed section edge (single notes and parcels).
+pub fn feed_section_notes(section: &Value) -> Vec<(Value, &Value)> {
+    let node = section.get("node").unwrap_or(&Value::Null);
+    let date = node.get("date").cloned().unwrap_or(Value::Null);
+    if let Some(note) = node.get("note").filter(|note| note.is_object()) {
+        return vec![(date, note)];
+    }
+    node.pointer("/notes/edges")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|edge| edge.get("node"))
+        .map(|note| (date.clone(), note))
+        .collect()
+}
+
+fn note_fingerprint(note: &Value) -> String {
+    let text = |key: &str| note.get(key).and_then(Value::as_str).unwrap_or_default();
+    // FNV-1a: stable across runs and enough to detect edits between polls.
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for byte in text("title")
+        .bytes()
+        .chain([0])
+        .chain(text("contentSummaryHtml").bytes())
+    {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchState;
+    use serde_json::json;
+
+    #[test]
+    fn observe_notes_reports_created_then_updated() {
+        let mut state = WatchState::default();
+        let sections = |summary: &str| {
+            vec![
+                json!({"node": {"date": "2026-02-23", "note": {"id": "N1", "title": "a", "contentSummaryHtml": summary}}}),
+                json!({"node": {"date": "2026-02-22", "notes": {"edges": [{"node": {"id": "N2", "title": "b", "contentSummaryHtml": ""}}]}}}),
+            ]
+        };
+        let events = state.observe_notes(&sections("v1"));
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1]["id"], "N2");
+        assert!(state.observe_notes(&sections("v1")).is_empty());
+        let events = state.observe_notes(&sections("v2"));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["type"], "note.updated");
+    }
+
+    #[test]
+    fn forgotten_events_are_reported_again() {
+        let mut state = WatchState::default();
+        let sections = |summary: &str| {
+            vec![
+                json!({"node": {"note": {"id": "N1", "title": "a", "contentSummaryHtml": summary}}}),
+            ]
+        };
+        state.observe_notes(&sections("v1"));
+        let before = state.clone();
+        let events = state.observe_notes(&sections("v2"));
+        state.forget(&events[0], &before);
+        assert_eq!(state, before);
+        assert_eq!(
+            state.observe_notes(&sections("v2"))[0]["type"],
+            "note.updated"
+        );
+
+        let events = state.observe_comments(&[json!({"id": "C1"})]);
+        state.forget(&events[0], &before);
+        assert_eq!(state.observe_comments(&[json!({"id": "C1"})]).len(), 1);
+    }
+
+    #[test]
+    fn state_round_trips_through_file() {
+        let path =
+            std::env::temp_dir().join(format!("kibel-watch-state-{}.json", std::process::id()));
+        let mut state = WatchState::default();
+        state.observe_notes(&[json!({"node": {"note": {"id": "N1", "title": "a"}}})]);
+        state.observe_comments(&[json!({"id": "C1"}), json!({"id": "C1"})]);
+        state.save(&path).expect("state should save");
+
+        let loaded = WatchState::load(&path).expect("state should load");
+        assert!(loaded.initialized);
+        assert_eq!(loaded.notes, state.notes);
+        assert_eq!(loaded.comments, vec!["C1".to_string()]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
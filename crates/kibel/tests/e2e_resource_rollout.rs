@@ -13,6 +13,10 @@ fn run_kibel_json(args: &[&str], envs: &[(&str, String)]) -> (Output, Value) {
         "KIBEL_TEST_CREATE_NOTE_SCHEMA_RESPONSE",
         "KIBEL_TEST_TRANSPORT_ERROR",
         "KIBEL_TEST_CAPTURE_REQUEST_PATH",
+        "KIBEL_TEST_ATTACHMENT_BODY",
+        "KIBEL_RELAY_SECRET",
+        "KIBEL_UPDATE_URL",
+        "KIBEL_UPDATE_PROXY",
     ] {
         command.env_remove(key);
     }
@@ -26,6 +30,21 @@ fn run_kibel_json(args: &[&str], envs: &[(&str, String)]) -> (Output, Value) {
     (output, payload)
 }
 
+fn run_kibel_ndjson(args: &[&str], envs: &[(&str, String)]) -> (Output, Vec<Value>) {
+    let mut command = Command::new(assert_cmd::cargo::cargo_bin!("kibel"));
+    command.arg("--json").args(args);
+    command.env_remove("KIBEL_TEST_CAPTURE_REQUEST_PATH");
+    for (key, value) in envs {
+        command.env(key, value);
+    }
+    let output = command.output().expect("failed to run kibel");
+    let lines = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).expect("each line should be JSON"))
+        .collect();
+    (output, lines)
+}
+
 fn base_env(response: Value) -> Vec<(&'static str, String)> {
     let response = match response {
         Value::String(raw) => raw,
@@ -96,6 +115,122 @@ fn search_note_success() {
     );
 }
 
+#[test]
+fn note_get_and_search_note_report_content_stats() {
+    let response = json!({
+        "data": {
+            "note": {
+                "id": "N1",
+                "title": "Runbook",
+                "content": "# Runbook\n\n本番に反映する\n\n## Deploy\n\nship it now\n"
+            }
+        }
+    });
+    let (output, payload) = run_kibel_json(&["note", "get", "--id", "N1"], &base_env(response));
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        payload["data"]["content_stats"],
+        json!({
+            "words": 12,
+            "reading_minutes": 1,
+            "outline": [
+                {"level": 1, "text": "Runbook", "line": 1, "anchor": "runbook"},
+                {"level": 2, "text": "Deploy", "line": 5, "anchor": "deploy"}
+            ]
+        })
+    );
+
+    let response = json!({
+        "data": {
+            "search": {
+                "pageInfo": {"hasNextPage": false, "endCursor": null},
+                "edges": [{
+                    "node": {
+                        "document": {"id": "N1"},
+                        "title": "Runbook",
+                        "url": "https://example.kibe.la/notes/N1",
+                        "contentSummaryHtml": "ship <em>it</em> now",
+                        "path": "/notes/N1"
+                    }
+                }]
+            }
+        }
+    });
+    let (output, payload) = run_kibel_json(
+        &["search", "note", "--query", "it", "--content-stats"],
+        &base_env(response.clone()),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        payload["data"]["results"][0]["content_stats"],
+        json!({"words": 3, "reading_minutes": 1})
+    );
+    let (_, payload) = run_kibel_json(&["search", "note", "--query", "it"], &base_env(response));
+    assert!(payload["data"]["results"][0].get("content_stats").is_none());
+}
+
+#[test]
+fn note_outline_prints_the_heading_tree_with_deep_links() {
+    let response = json!({
+        "data": {
+            "note": {
+                "id": "Tm90ZS8x",
+                "title": "Runbook",
+                "content": "# Runbook\n## Deploy\n### Verify\n```\n# not a heading\n```\n## Rollback\n### Verify\n"
+            }
+        }
+    });
+    let (output, payload) = run_kibel_json(
+        &[
+            "note",
+            "outline",
+            "--id",
+            "Tm90ZS8x",
+            "--links",
+            "--max-level",
+            "2",
+        ],
+        &base_env(response.clone()),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["count"], json!(3));
+    assert_eq!(
+        payload["data"]["note"]["url"],
+        json!("http://fixture.local/notes/1")
+    );
+    assert_eq!(
+        payload["data"]["outline"],
+        json!([{
+            "level": 1,
+            "text": "Runbook",
+            "line": 1,
+            "anchor": "runbook",
+            "url": "http://fixture.local/notes/1#runbook",
+            "children": [
+                {
+                    "level": 2, "text": "Deploy", "line": 2, "anchor": "deploy",
+                    "url": "http://fixture.local/notes/1#deploy", "children": []
+                },
+                {
+                    "level": 2, "text": "Rollback", "line": 7, "anchor": "rollback",
+                    "url": "http://fixture.local/notes/1#rollback", "children": []
+                }
+            ]
+        }])
+    );
+
+    let output = std::process::Command::new(assert_cmd::cargo::cargo_bin!("kibel"))
+        .args(["--text", "note", "outline", "--id", "Tm90ZS8x"])
+        .envs(base_env(response))
+        .env_remove("KIBEL_TEST_CAPTURE_REQUEST_PATH")
+        .output()
+        .expect("failed to run kibel");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Runbook (5 heading(s))\n- Runbook  #runbook\n  - Deploy  #deploy\n    - Verify  #verify\n  - Rollback  #rollback\n    - Verify  #verify-1\n"
+    );
+}
+
 #[test]
 fn search_user_success() {
     let response = json!({
@@ -379,6 +514,508 @@ fn note_get_from_path_success() {
     );
 }
 
+#[test]
+fn link_resolve_and_flexible_note_reference_success() {
+    let response = json!({
+        "data": {
+            "noteFromPath": {
+                "id": "QmxvZy8zNjY",
+                "title": "hello",
+                "content": "world",
+                "url": "http://fixture.local/notes/366",
+                "author": {"account": "alice", "realName": "Alice"},
+                "folders": {"edges": []},
+                "comments": {"edges": []},
+                "inlineComments": {"edges": []}
+            },
+            "note": {
+                "id": "QmxvZy8zNjY",
+                "title": "hello",
+                "content": "world"
+            }
+        }
+    });
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "link",
+            "resolve",
+            "http://fixture.local/groups/1/folders/2/notes/366?tab=comments",
+        ],
+        &base_env(response.clone()),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["kind"], Value::String("url".to_string()));
+    assert_eq!(
+        payload["data"]["id"],
+        Value::String("QmxvZy8zNjY".to_string())
+    );
+    assert_eq!(
+        payload["data"]["path"],
+        Value::String("/notes/366".to_string())
+    );
+    assert_eq!(payload["data"]["redirected"], Value::Bool(false));
+
+    let (output, payload) = run_kibel_json(
+        &["note", "get", "--id", "/notes/366"],
+        &base_env(response.clone()),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        payload["data"]["note"]["id"],
+        Value::String("QmxvZy8zNjY".to_string())
+    );
+
+    let (output, payload) = run_kibel_json(
+        &["link", "resolve", "https://other.kibe.la/notes/366"],
+        &base_env(response),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        payload["error"]["code"],
+        Value::String("INPUT_INVALID".to_string())
+    );
+}
+
+#[test]
+fn link_make_builds_url_without_token() {
+    let (output, payload) = run_kibel_json(
+        &[
+            "--origin",
+            "https://acme.kibe.la",
+            "link",
+            "make",
+            "QmxvZy8zNjY",
+        ],
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        payload["data"]["url"],
+        Value::String("https://acme.kibe.la/notes/366".to_string())
+    );
+
+    let (output, payload) = run_kibel_json(
+        &["--origin", "https://acme.kibe.la", "link", "make", "N1"],
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        payload["error"]["code"],
+        Value::String("INPUT_INVALID".to_string())
+    );
+}
+
+#[test]
+fn meta_contracts_matches_the_checked_in_manifest() {
+    let (output, payload) = run_kibel_json(&["meta", "contracts"], &[]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let manifest_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../schema/contracts/resource_contracts.manifest.json");
+    let manifest = serde_json::from_str::<Value>(
+        &std::fs::read_to_string(manifest_path).expect("manifest should exist"),
+    )
+    .expect("manifest should be JSON");
+    assert_eq!(payload["data"], manifest);
+
+    let get_note = payload["data"]["operations"]
+        .as_array()
+        .expect("operations should be an array")
+        .iter()
+        .find(|operation| operation["name"] == "getNote")
+        .expect("getNote should be listed");
+    assert_eq!(get_note["required_variables"], json!(["id"]));
+    assert_eq!(get_note["variable_types"], json!({"id": "ID!"}));
+}
+
+#[test]
+fn meta_operations_and_limits_describe_the_binary() {
+    let (output, payload) = run_kibel_json(&["meta", "operations"], &[]);
+    assert_eq!(output.status.code(), Some(0));
+    let operations = payload["data"]["operations"]
+        .as_array()
+        .expect("operations should be an array");
+    assert!(operations.contains(&json!({
+        "name": "getNote",
+        "kind": "query",
+        "operation": "GetNote",
+        "client_method": "get_note",
+        "transport": "persisted-query-get",
+    })));
+    assert!(operations.iter().any(
+        |operation| operation["operation"] == "CreateNote" && operation["transport"] == "post"
+    ));
+    assert_eq!(payload["data"]["contract_version"], json!(1));
+
+    let (output, payload) = run_kibel_json(&["meta", "limits"], &[]);
+    assert_eq!(output.status.code(), Some(0));
+    let graphql_run = &payload["data"]["graphql_run"];
+    assert_eq!(
+        graphql_run["timeout_secs"],
+        json!({"default": 15, "max": 60})
+    );
+    assert_eq!(
+        graphql_run["response_limit_mib"],
+        json!({"default": 2, "max": 8})
+    );
+    assert_eq!(graphql_run["max_depth"], json!(8));
+    assert_eq!(graphql_run["max_complexity"], json!(1000));
+    assert!(graphql_run["mutation_root_fields"]
+        .as_array()
+        .expect("mutation root fields should be an array")
+        .contains(&json!("createNote")));
+    assert_eq!(
+        payload["data"]["timeouts"]["batch"],
+        json!({"connect_secs": 10, "read_secs": 30, "total_secs": 60})
+    );
+}
+
+#[test]
+fn estimate_tokens_annotates_envelope_meta() {
+    let (output, payload) = run_kibel_json(
+        &["note", "get", "--id", "N1", "--estimate-tokens"],
+        &base_env(
+            json!({"data": {"note": {"id": "N1", "title": "議事録", "content": "hello world"}}}),
+        ),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    let estimate = &payload["meta"]["token_estimate"];
+    assert_eq!(estimate["method"], json!("bpe-approx-v1"));
+    let total = estimate["total"]
+        .as_u64()
+        .expect("total should be a number");
+    let note = estimate["fields"]["note"]
+        .as_u64()
+        .expect("per-field estimate should be present");
+    assert!(note > 0 && note < total);
+
+    let (_, payload) = run_kibel_json(&["note", "get", "--id", "N1"], &base_env(json!({})));
+    assert!(payload["meta"].get("token_estimate").is_none());
+}
+
+#[test]
+fn graphql_run_var_flags_are_typed_from_declarations() {
+    let document = "query Q($id: ID!, $first: Int, $archived: Boolean) { \
+        note(id: $id) { id } \
+        notes(first: $first, isArchived: $archived) { edges { node { id } } } }";
+    let capture_path = isolated_capture_path();
+    let mut envs = base_env(json!({"data": {"note": {"id": "N1"}}}));
+    envs.push(("KIBEL_TEST_CAPTURE_REQUEST_PATH", capture_path.clone()));
+
+    let (output, payload) = run_kibel_json(
+        &["graphql", "run", "--query", document, "--var", "first=3"],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(payload["error"]["message"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("$id: ID!"));
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "graphql",
+            "run",
+            "--query",
+            document,
+            "--variables",
+            r#"{"id":"N0","first":1}"#,
+            "--var",
+            "id=N1",
+            "--var",
+            "archived=false",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["ok"], Value::Bool(true));
+    let captured_raw = std::fs::read_to_string(&capture_path).expect("capture file should exist");
+    let captured =
+        serde_json::from_str::<Value>(&captured_raw).expect("captured request must be JSON");
+    assert_eq!(
+        captured["variables"],
+        json!({"id": "N1", "first": 1, "archived": false})
+    );
+}
+
+#[test]
+fn graphql_cost_estimates_locally_and_run_enforces_it() {
+    let document = "query Q($first: Int!) { groups(first: $first) { nodes { id \
+        folders { edges { node { name } } } } } }";
+    let capture_path = isolated_capture_path();
+    let mut envs = base_env(json!({"data": {"groups": {"nodes": []}}}));
+    envs.push(("KIBEL_TEST_CAPTURE_REQUEST_PATH", capture_path.clone()));
+
+    let (output, payload) = run_kibel_json(
+        &["graphql", "cost", "--query", document, "--var", "first=2"],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["cost"], 607);
+    assert_eq!(
+        payload["data"]["connections"][1],
+        json!({
+            "path": "groups.nodes.folders",
+            "page_size": 100,
+            "page_size_from": "default",
+            "multiplier": 2,
+        })
+    );
+    assert!(!std::path::Path::new(&capture_path).exists());
+
+    let (output, payload) = run_kibel_json(
+        &["graphql", "run", "--query", document, "--var", "first=20"],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["details"]["cost"], 6061);
+    assert!(!std::path::Path::new(&capture_path).exists());
+
+    let (output, payload) = run_kibel_json(
+        &["graphql", "run", "--query", document, "--var", "first=2"],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["meta"]["cost"], 607);
+}
+
+#[test]
+fn graphql_run_selects_named_operation_with_fragments() {
+    let document = "query Groups { groups(first: 1) { ...GroupFields } } \
+        query Me { currentUser { ...UserFields } } \
+        fragment GroupFields on GroupConnection { nodes { id name } } \
+        fragment UserFields on User { id account }";
+    let capture_path = isolated_capture_path();
+    let mut envs = base_env(json!({"data": {"currentUser": {"id": "U1", "account": "alice"}}}));
+    envs.push(("KIBEL_TEST_CAPTURE_REQUEST_PATH", capture_path.clone()));
+
+    let (output, payload) = run_kibel_json(&["graphql", "run", "--query", document], &envs);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["code"], "INPUT_INVALID");
+    assert!(payload["error"]["message"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("--operation-name"));
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "graphql",
+            "run",
+            "--query",
+            document,
+            "--operation-name",
+            "Me",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["meta"]["operation_name"], "Me");
+    assert_eq!(
+        payload["data"]["response"]["data"]["currentUser"]["account"],
+        "alice"
+    );
+
+    let captured_raw = std::fs::read_to_string(&capture_path).expect("capture file should exist");
+    let captured =
+        serde_json::from_str::<Value>(&captured_raw).expect("captured request must be JSON");
+    assert_eq!(captured["operationName"], "Me");
+    assert!(captured["query"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("fragment UserFields"));
+}
+
+#[test]
+fn graphql_run_schema_snapshot_rejects_unknown_fields_before_sending() {
+    let schema_path =
+        std::env::temp_dir().join(format!("{}.json", unique_value("kibel-e2e-schema")));
+    let string_type = json!({ "kind": "SCALAR", "name": "String", "ofType": null });
+    std::fs::write(
+        &schema_path,
+        json!({ "__schema": {
+            "queryType": { "fields": [{
+                "name": "currentUser",
+                "args": [],
+                "type": { "kind": "OBJECT", "name": "User", "ofType": null },
+            }]},
+            "mutationType": null,
+            "types": [
+                { "kind": "OBJECT", "name": "User", "fields": [
+                    { "name": "account", "args": [], "type": string_type },
+                ]},
+                { "kind": "SCALAR", "name": "String", "fields": null },
+            ]
+        }})
+        .to_string(),
+    )
+    .expect("schema snapshot should be written");
+    let schema_path = schema_path.to_string_lossy().to_string();
+    let capture_path = isolated_capture_path();
+    let mut envs = base_env(json!({"data": {"currentUser": {"account": "alice"}}}));
+    envs.push(("KIBEL_TEST_CAPTURE_REQUEST_PATH", capture_path.clone()));
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "graphql",
+            "run",
+            "--schema-snapshot",
+            &schema_path,
+            "--query",
+            "query { currentUser { acount } }",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["code"], "INPUT_INVALID");
+    assert_eq!(
+        payload["error"]["details"]["errors"],
+        json!(["unknown field `acount` on type `User`"])
+    );
+    assert!(
+        !std::path::Path::new(&capture_path).exists(),
+        "invalid documents should fail before transport"
+    );
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "graphql",
+            "run",
+            "--schema-snapshot",
+            &schema_path,
+            "--query",
+            "query { currentUser { account } }",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        payload["data"]["response"]["data"]["currentUser"]["account"],
+        "alice"
+    );
+}
+
+#[test]
+fn note_move_to_folder_batch_reports_per_row_results() {
+    let manifest_path = std::env::temp_dir().join(format!("{}.csv", unique_value("kibel-move")));
+    std::fs::write(
+        &manifest_path,
+        "note_id,from_folder,to_folder\nN1,G1:Old,G1:New\nN2,G1:Old,G1:New\n",
+    )
+    .expect("failed to write manifest");
+    let manifest = manifest_path.to_string_lossy().to_string();
+
+    let (output, payload) = run_kibel_json(
+        &["note", "move-to-folder", "--batch", &manifest],
+        &base_env(json!({
+            "data": { "moveNoteToAnotherFolder": { "note": { "id": "N1" } } }
+        })),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["summary"]["succeeded"], json!(2));
+    assert_eq!(payload["data"]["results"][1]["row"], json!(2));
+
+    let (output, payload) = run_kibel_json(
+        &["note", "move-to-folder", "--batch", &manifest],
+        &base_env(json!({
+            "errors": [{ "message": "not found", "extensions": { "code": "NOT_FOUND" } }]
+        })),
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(payload["error"]["details"]["summary"]["failed"], json!(2));
+    assert_eq!(
+        payload["error"]["details"]["results"][0]["error"]["code"],
+        Value::String("NOT_FOUND".to_string())
+    );
+
+    std::fs::write(
+        &manifest_path,
+        "note_id,from_folder,to_folder\nN1,G1:Old,G1:New\nN2,broken,G1:New\n",
+    )
+    .expect("failed to write manifest");
+    let capture_path = isolated_capture_path();
+    let mut envs = base_env(json!({
+        "data": { "moveNoteToAnotherFolder": { "note": { "id": "N1" } } }
+    }));
+    envs.push(("KIBEL_TEST_CAPTURE_REQUEST_PATH", capture_path.clone()));
+    let (output, payload) =
+        run_kibel_json(&["note", "move-to-folder", "--batch", &manifest], &envs);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["details"]["errors"][0]["row"], json!(2));
+    assert!(
+        !std::path::Path::new(&capture_path).exists(),
+        "no request should be sent when validation fails"
+    );
+    let _ = std::fs::remove_file(&manifest_path);
+}
+
+#[test]
+fn report_attachments_groups_duplicates_across_notes() {
+    let hit = |note: u32, title: &str| {
+        json!({
+            "node": {
+                "document": {"id": format!("A{note}")},
+                "title": title,
+                "url": format!("http://fixture.local/attachments/{note}"),
+                "path": format!("/notes/{note}#attachment"),
+                "contentSummaryHtml": "",
+                "author": {"id": "U1", "account": "alice", "realName": "Alice"}
+            }
+        })
+    };
+    let response = json!({
+        "data": {
+            "search": {
+                "edges": [hit(1, "Diagram.PNG"), hit(2, "diagram.png"), hit(3, "logo.svg")],
+                "pageInfo": {"endCursor": null, "hasNextPage": false}
+            }
+        }
+    });
+
+    let (output, payload) = run_kibel_json(
+        &["report", "attachments", "--group-id", "G1"],
+        &base_env(response.clone()),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["dedup_key"], json!("file_name"));
+    assert_eq!(payload["data"]["scanned"]["attachments"], json!(3));
+    assert_eq!(
+        payload["data"]["duplicates"][0]["key"],
+        json!("diagram.png")
+    );
+    assert_eq!(payload["data"]["duplicates"][0]["note_count"], json!(2));
+    assert_eq!(
+        payload["data"]["duplicates"].as_array().map(Vec::len),
+        Some(1)
+    );
+
+    let mut envs = base_env(response);
+    envs.push(("KIBEL_TEST_ATTACHMENT_BODY", "same-bytes".to_string()));
+    let (output, payload) = run_kibel_json(
+        &["report", "attachments", "--group-id", "G1", "--hash"],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["dedup_key"], json!("content_sha256"));
+    assert_eq!(payload["data"]["duplicates"][0]["copies"], json!(3));
+    assert_eq!(payload["data"]["duplicates"][0]["size_bytes"], json!(10));
+    assert_eq!(payload["data"]["reclaimable_bytes"], json!(20));
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "report",
+            "attachments",
+            "--group-id",
+            "G1",
+            "--min-size-kib",
+            "1",
+        ],
+        &base_env(json!({})),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["code"], json!("INPUT_INVALID"));
+}
+
 #[test]
 fn feed_sections_success() {
     let response = json!({
@@ -408,6 +1045,446 @@ fn feed_sections_success() {
     );
 }
 
+#[test]
+fn feed_sections_since_stops_at_cutoff() {
+    let section = |date: &str, id: &str| {
+        json!({
+            "node": {
+                "date": date,
+                "note": {"id": id, "title": "hello", "contentSummaryHtml": "summary"}
+            }
+        })
+    };
+    let response = json!({
+        "data": {
+            "feedSections": {
+                "pageInfo": {"hasNextPage": true, "endCursor": "cursor-1"},
+                "edges": [section("2026-02-23", "N1"), section("2026-02-10", "N2")]
+            }
+        }
+    });
+    let capture_path = isolated_capture_path();
+    let mut envs = base_env(response);
+    envs.push(("KIBEL_TEST_CAPTURE_REQUEST_PATH", capture_path.clone()));
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "feed",
+            "sections",
+            "--kind",
+            "ALL",
+            "--group-id",
+            "G1",
+            "--since",
+            "2026-02-16",
+            "--after",
+            "cursor-0",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["pages"], json!(1));
+    assert_eq!(payload["data"]["reached_since"], Value::Bool(true));
+    assert_eq!(
+        payload["data"]["sections"].as_array().map(Vec::len),
+        Some(1)
+    );
+    assert_eq!(
+        payload["data"]["sections"][0]["node"]["note"]["id"],
+        json!("N1")
+    );
+    let captured_raw = std::fs::read_to_string(&capture_path).expect("capture file should exist");
+    let captured =
+        serde_json::from_str::<Value>(&captured_raw).expect("captured request must be JSON");
+    assert_eq!(captured["variables"]["after"], json!("cursor-0"));
+    let _ = std::fs::remove_file(&capture_path);
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "feed",
+            "sections",
+            "--kind",
+            "ALL",
+            "--group-id",
+            "G1",
+            "--since",
+            "2026-02-01",
+            "--max-pages",
+            "3",
+        ],
+        &base_env(json!({
+            "data": {
+                "feedSections": {
+                    "pageInfo": {"hasNextPage": true, "endCursor": "cursor-1"},
+                    "edges": [section("2026-02-23", "N1")]
+                }
+            }
+        })),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["pages"], json!(3));
+    assert_eq!(payload["data"]["reached_since"], Value::Bool(false));
+    assert_eq!(
+        payload["data"]["sections"].as_array().map(Vec::len),
+        Some(3)
+    );
+}
+
+#[test]
+fn note_drafts_limit_merges_pages() {
+    let response = json!({"data": {"currentUser": {"draftNotes": {
+        "pageInfo": {"hasNextPage": true, "endCursor": "c1"},
+        "edges": [
+            {"node": {"id": "D1", "title": "one"}},
+            {"node": {"id": "D2", "title": "two"}}
+        ]
+    }}}});
+    let (output, payload) = run_kibel_json(
+        &["note", "drafts", "--first", "2", "--limit", "3"],
+        &base_env(response.clone()),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["drafts"].as_array().map(Vec::len), Some(3));
+    assert_eq!(payload["data"]["pages"], json!(2));
+    assert_eq!(payload["data"]["truncated"], json!(true));
+
+    let (output, payload) =
+        run_kibel_json(&["note", "drafts", "--limit", "1001"], &base_env(response));
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["code"], json!("INPUT_INVALID"));
+}
+
+#[test]
+fn feed_watch_emits_ndjson_events_and_persists_state() {
+    let feed = |summary: &str| {
+        json!({
+            "data": {
+                "feedSections": {
+                    "edges": [{
+                        "node": {
+                            "date": "2026-02-23",
+                            "note": {"id": "N1", "title": "hello", "contentSummaryHtml": summary}
+                        }
+                    }]
+                }
+            }
+        })
+    };
+    let state_path = std::env::temp_dir().join(format!("{}.json", unique_value("kibel-watch")));
+    let state = state_path.to_string_lossy().to_string();
+    let args = [
+        "feed",
+        "watch",
+        "--group-id",
+        "G1",
+        "--max-polls",
+        "1",
+        "--state-file",
+        &state,
+    ];
+
+    let (output, lines) = run_kibel_ndjson(&args, &base_env(feed("v1")));
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(lines.len(), 1, "first poll only seeds the state");
+    assert_eq!(lines[0]["data"]["polls"], json!(1));
+    assert!(state_path.exists());
+
+    let (output, lines) = run_kibel_ndjson(&args, &base_env(feed("v2")));
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["type"], json!("note.updated"));
+    assert_eq!(lines[0]["id"], json!("N1"));
+    assert_eq!(lines[1]["data"]["events"], json!(1));
+    let _ = std::fs::remove_file(&state_path);
+
+    let (output, lines) = run_kibel_ndjson(
+        &[
+            "feed",
+            "watch",
+            "--group-id",
+            "G1",
+            "--max-polls",
+            "1",
+            "--emit-existing",
+        ],
+        &base_env(feed("v1")),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(lines[0]["type"], json!("note.created"));
+}
+
+/// Accepts `statuses.len()` webhook requests, answering each with the next
+/// status, and returns the raw requests.
+fn spawn_webhook_server(statuses: Vec<u16>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{Read, Write};
+    let listener =
+        std::net::TcpListener::bind(("127.0.0.1", 0)).expect("failed to bind webhook server");
+    let url = format!(
+        "http://{}/hook",
+        listener
+            .local_addr()
+            .expect("listener should have an address")
+    );
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for status in statuses {
+            let (mut stream, _) = listener.accept().expect("webhook accept failed");
+            let mut raw = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = stream.read(&mut buffer).expect("webhook read failed");
+                raw.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&raw).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
+            }
+            requests.push(String::from_utf8_lossy(&raw).to_string());
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            );
+        }
+        requests
+    });
+    (url, handle)
+}
+
+/// Answers `count` requests with the same JSON `body`.
+fn spawn_json_server(count: usize, body: Value) -> String {
+    use std::io::{Read, Write};
+    let listener =
+        std::net::TcpListener::bind(("127.0.0.1", 0)).expect("failed to bind release server");
+    let url = format!(
+        "http://{}/releases",
+        listener
+            .local_addr()
+            .expect("listener should have an address")
+    );
+    std::thread::spawn(move || {
+        let body = body.to_string();
+        for _ in 0..count {
+            let (mut stream, _) = listener.accept().expect("release server accept failed");
+            let mut raw = Vec::new();
+            let mut buffer = [0u8; 4096];
+            while !String::from_utf8_lossy(&raw).contains("\r\n\r\n") {
+                let read = stream
+                    .read(&mut buffer)
+                    .expect("release server read failed");
+                if read == 0 {
+                    break;
+                }
+                raw.extend_from_slice(&buffer[..read]);
+            }
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+    url
+}
+
+#[test]
+fn version_check_and_self_update_check_only_follow_the_channel() {
+    let url = spawn_json_server(
+        3,
+        json!([
+            {"tag_name": "v999.0.0-rc.1", "prerelease": true, "draft": false, "assets": []},
+            {"tag_name": "v998.1.0", "prerelease": false, "draft": false, "assets": []},
+            {"tag_name": "v999.9.9", "prerelease": false, "draft": true, "assets": []},
+            {"tag_name": "nightly", "prerelease": false, "draft": false, "assets": []}
+        ]),
+    );
+    let envs = [("KIBEL_UPDATE_URL", url)];
+
+    let (output, payload) = run_kibel_json(&["version", "--check"], &envs);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["version"], json!(env!("CARGO_PKG_VERSION")));
+    assert_eq!(payload["data"]["latest_version"], json!("998.1.0"));
+    assert_eq!(payload["data"]["update_available"], json!(true));
+
+    let (output, payload) = run_kibel_json(&["self", "update", "--check-only"], &envs);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["tag"], json!("v998.1.0"));
+    assert_eq!(payload["data"]["channel"], json!("stable"));
+    assert_eq!(payload["data"]["update_available"], json!(true));
+    assert_eq!(payload["data"]["updated"], json!(false));
+
+    let (output, payload) = run_kibel_json(
+        &["self", "update", "--check-only", "--channel", "prerelease"],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["latest_version"], json!("999.0.0-rc.1"));
+    assert_eq!(payload["data"]["channel"], json!("prerelease"));
+}
+
+#[test]
+fn self_update_reports_unreachable_release_servers() {
+    let (output, payload) = run_kibel_json(
+        &["self", "update", "--check-only"],
+        &[(
+            "KIBEL_UPDATE_URL",
+            "http://127.0.0.1:1/releases".to_string(),
+        )],
+    );
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(payload["error"]["code"], json!("TRANSPORT_ERROR"));
+}
+
+#[test]
+fn feed_relay_signs_and_retries_deliveries() {
+    let (url, server) = spawn_webhook_server(vec![500, 200]);
+    let mut envs = base_env(json!({
+        "data": {
+            "feedSections": {
+                "edges": [{
+                    "node": {
+                        "date": "2026-02-23",
+                        "note": {"id": "N1", "title": "hello", "contentSummaryHtml": "v1"}
+                    }
+                }]
+            }
+        }
+    }));
+    envs.push(("KIBEL_RELAY_SECRET", "s3cret".to_string()));
+    let (output, lines) = run_kibel_ndjson(
+        &[
+            "feed",
+            "relay",
+            "--group-id",
+            "G1",
+            "--max-polls",
+            "1",
+            "--emit-existing",
+            "--url",
+            &url,
+            "--retry-base-ms",
+            "1",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(lines[0]["type"], json!("note.created"));
+    assert_eq!(lines[0]["delivery"]["ok"], Value::Bool(true));
+    assert_eq!(lines[0]["delivery"]["attempts"], json!(2));
+    assert_eq!(lines[1]["data"]["delivered"], json!(1));
+
+    let requests = server.join().expect("webhook server panicked");
+    assert_eq!(requests.len(), 2);
+    let lowered = requests[1].to_ascii_lowercase();
+    assert!(lowered.contains("x-kibel-signature: sha256="));
+    assert!(lowered.contains("x-kibel-event: note.created"));
+    assert!(requests[1].contains("\"id\":\"N1\""));
+
+    let state_path = std::env::temp_dir().join(format!("{}.json", unique_value("kibel-relay")));
+    let state = state_path.to_string_lossy().to_string();
+    for (status, ok) in [(400, false), (200, true)] {
+        let (url, server) = spawn_webhook_server(vec![status]);
+        let (output, lines) = run_kibel_ndjson(
+            &[
+                "feed",
+                "relay",
+                "--group-id",
+                "G1",
+                "--max-polls",
+                "1",
+                "--emit-existing",
+                "--state-file",
+                &state,
+                "--url",
+                &url,
+            ],
+            &envs,
+        );
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(
+            lines[0]["id"],
+            json!("N1"),
+            "a failed delivery is sent again"
+        );
+        assert_eq!(lines[0]["delivery"]["ok"], Value::Bool(ok));
+        assert_eq!(server.join().expect("webhook server panicked").len(), 1);
+    }
+    let _ = std::fs::remove_file(&state_path);
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "feed",
+            "relay",
+            "--group-id",
+            "G1",
+            "--url",
+            "http://hooks.example.com/x",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["code"], json!("INPUT_INVALID"));
+}
+
+#[test]
+fn ci_verify_docs_reports_drift_with_diff() {
+    let dir = std::env::temp_dir().join(unique_value("kibel-verify-docs"));
+    std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+    std::fs::write(dir.join("intro.md"), "# Intro\nversion {{version}}\n")
+        .expect("failed to write doc");
+    std::fs::write(
+        dir.join("kibel-docs.json"),
+        json!({
+            "vars": {"version": "1.0"},
+            "entries": [{"note": "N1", "file": "intro.md"}]
+        })
+        .to_string(),
+    )
+    .expect("failed to write mapping");
+    let mapping = dir.join("kibel-docs.json").to_string_lossy().to_string();
+    let note = |content: &str| json!({"data": {"note": {"id": "N1", "title": "Intro", "content": content}}});
+
+    let (output, payload) = run_kibel_json(
+        &["ci", "verify-docs", "--mapping", &mapping],
+        &base_env(note("# Intro\r\nversion 1.0  \n")),
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["summary"]["matched"], json!(1));
+
+    let (output, payload) = run_kibel_json(
+        &["ci", "verify-docs", "--mapping", &mapping],
+        &base_env(note("# Intro\nversion 0.9\n")),
+    );
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(payload["error"]["code"], json!("PRECONDITION_FAILED"));
+    assert_eq!(payload["error"]["details"]["summary"]["drifted"], json!(1));
+    let diff = payload["error"]["details"]["results"][0]["diff"]
+        .as_str()
+        .expect("drifted entry should carry a diff");
+    assert!(diff.contains("-version 0.9\n+version 1.0"));
+
+    std::fs::write(dir.join("intro.md"), "{{missing}}").expect("failed to write doc");
+    let (output, payload) = run_kibel_json(
+        &["ci", "verify-docs", "--mapping", &mapping],
+        &base_env(note("")),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["code"], json!("INPUT_INVALID"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
 #[test]
 #[allow(clippy::too_many_lines)]
 fn command_request_shapes_are_preserved() {
@@ -10,6 +10,7 @@ fn run_kibel_json(args: &[&str], envs: &[(&str, String)]) -> (Output, Value) {
         "KIBELA_ORIGIN",
         "KIBELA_TEAM",
         "KIBELA_ACCESS_TOKEN",
+        "KIBEL_TOKEN_STORE",
         "KIBEL_TEST_GRAPHQL_RESPONSE",
         "KIBEL_TEST_CREATE_NOTE_SCHEMA_RESPONSE",
         "KIBEL_TEST_TRANSPORT_ERROR",
@@ -34,6 +35,7 @@ fn run_kibel_default(args: &[&str], envs: &[(&str, String)]) -> (Output, Value)
         "KIBELA_ORIGIN",
         "KIBELA_TEAM",
         "KIBELA_ACCESS_TOKEN",
+        "KIBEL_TOKEN_STORE",
         "KIBEL_TEST_GRAPHQL_RESPONSE",
         "KIBEL_TEST_CREATE_NOTE_SCHEMA_RESPONSE",
         "KIBEL_TEST_TRANSPORT_ERROR",
@@ -689,6 +691,102 @@ fn contract_request_shape_for_create_note_is_preserved() {
     );
 }
 
+#[test]
+fn note_create_renders_local_template() {
+    let config_path = isolated_config_path();
+    let templates_dir = std::path::Path::new(&config_path)
+        .parent()
+        .expect("config path has a parent")
+        .join(unique_value("kibel-e2e-templates"));
+    std::fs::create_dir_all(&templates_dir).expect("failed to create templates dir");
+    std::fs::write(
+        templates_dir.join("daily.md"),
+        "---\ntitle: Daily {{date}}\n---\nby {{author}}, sprint {{sprint}}\n",
+    )
+    .expect("failed to write template");
+    std::fs::write(
+        &config_path,
+        format!(
+            "templates_dir = \"{}\"\n",
+            templates_dir
+                .file_name()
+                .expect("templates dir has a name")
+                .to_string_lossy()
+        ),
+    )
+    .expect("failed to write config");
+
+    let (output, payload) =
+        run_kibel_json(&["--config-path", &config_path, "template", "list"], &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["templates"][0]["name"], json!("daily"));
+    assert_eq!(
+        payload["data"]["templates"][0]["placeholders"],
+        json!(["author", "date", "sprint"])
+    );
+
+    let capture_path = isolated_capture_path();
+    let mut envs = base_env(
+        "http://fixture.local",
+        fixture_create_note("N1", "Daily", "body", None),
+    );
+    envs.push(("KIBEL_TEST_CAPTURE_REQUEST_PATH", capture_path.clone()));
+    let (output, payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &config_path,
+            "note",
+            "create",
+            "--template",
+            "daily",
+            "--var",
+            "author=alice",
+            "--var",
+            "sprint=7",
+            "--group-id",
+            "G1",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["template"], json!("daily"));
+    let captured = serde_json::from_str::<Value>(
+        &std::fs::read_to_string(&capture_path).expect("capture file should exist"),
+    )
+    .expect("captured request must be JSON");
+    let title = captured["variables"]["input"]["title"]
+        .as_str()
+        .expect("title must be string");
+    assert!(title.starts_with("Daily 20") && title.len() == "Daily YYYY-MM-DD".len());
+    assert_eq!(
+        captured["variables"]["input"]["content"],
+        json!("by alice, sprint 7")
+    );
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &config_path,
+            "note",
+            "create",
+            "--template",
+            "daily",
+            "--var",
+            "author=alice",
+            "--group-id",
+            "G1",
+        ],
+        &base_env(
+            "http://fixture.local",
+            fixture_create_note("N1", "", "", None),
+        ),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_error(&payload, "INPUT_INVALID", false);
+    let _ = std::fs::remove_dir_all(&templates_dir);
+    let _ = std::fs::remove_file(&config_path);
+}
+
 #[test]
 fn contract_request_shape_for_update_note_is_preserved() {
     let capture_path = isolated_capture_path();
@@ -795,6 +893,279 @@ fn config_set_team_and_profiles_are_machine_readable_without_token_leak() {
     );
 }
 
+#[test]
+fn config_export_strips_tokens_and_import_merges_profiles() {
+    let config_path = isolated_config_path();
+    write_config(
+        &config_path,
+        Some("acme"),
+        Some("acme"),
+        Some("super-secret-token"),
+        Some("https://acme.kibe.la"),
+    );
+    let export_path = isolated_config_path();
+
+    let (export_output, export_payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &config_path,
+            "config",
+            "export",
+            "--output",
+            &export_path,
+        ],
+        &[],
+    );
+    assert_eq!(export_output.status.code(), Some(0));
+    assert_eq!(export_payload["data"]["tokens_removed"], json!(1));
+    assert!(!String::from_utf8_lossy(&export_output.stdout).contains("super-secret-token"));
+    let exported = std::fs::read_to_string(&export_path).expect("export should be written");
+    assert!(!exported.contains("super-secret-token"));
+    assert!(exported.contains("https://acme.kibe.la"));
+
+    // A fresh agent imports the shared file, then the profile gets its origin.
+    let agent_config_path = isolated_config_path();
+    let (import_output, import_payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &agent_config_path,
+            "config",
+            "import",
+            &export_path,
+        ],
+        &[],
+    );
+    assert_eq!(import_output.status.code(), Some(0));
+    assert_eq!(import_payload["data"]["imported_profiles"], json!(["acme"]));
+    let (_, profiles_payload) = run_kibel_json(
+        &["--config-path", &agent_config_path, "config", "profiles"],
+        &[],
+    );
+    assert_eq!(profiles_payload["data"]["default_team"], json!("acme"));
+    assert_eq!(
+        profiles_payload["data"]["profiles"][0]["has_token"],
+        json!(false)
+    );
+    assert_eq!(
+        profiles_payload["data"]["profiles"][0]["origin"],
+        json!("https://acme.kibe.la")
+    );
+
+    // Files carrying tokens are refused.
+    let (rejected_output, rejected_payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &agent_config_path,
+            "config",
+            "import",
+            &config_path,
+        ],
+        &[],
+    );
+    assert_eq!(rejected_output.status.code(), Some(2));
+    assert_eq!(rejected_payload["error"]["code"], json!("INPUT_INVALID"));
+    assert_eq!(
+        rejected_payload["error"]["details"]["teams"],
+        json!(["acme"])
+    );
+
+    // Non-interactive login never prompts for a missing token.
+    let (login_output, login_payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &agent_config_path,
+            "auth",
+            "login",
+            "--team",
+            "acme",
+            "--non-interactive",
+        ],
+        &[],
+    );
+    assert_eq!(login_output.status.code(), Some(2));
+    assert_eq!(login_payload["error"]["code"], json!("INPUT_INVALID"));
+
+    for path in [&config_path, &export_path, &agent_config_path] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn config_get_and_set_validate_keys_and_keep_config_set_team() {
+    let config_path = isolated_config_path();
+    let set = |args: &[&str]| {
+        let mut full = vec!["--config-path", config_path.as_str(), "config", "set"];
+        full.extend_from_slice(args);
+        run_kibel_json(&full, &[])
+    };
+
+    let (output, payload) = set(&["profiles.acme.origin", "https://ACME.kibe.la/"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["value"], json!("https://acme.kibe.la"));
+    assert_eq!(payload["data"]["previous"], Value::Null);
+    let (output, _) = set(&["on_fetch", "strip-html,truncate:200"]);
+    assert_eq!(output.status.code(), Some(0));
+    let (output, _) = set(&["team", "acme"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let (output, payload) = run_kibel_json(
+        &["--config-path", &config_path, "config", "get", "on_fetch"],
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        payload["data"]["value"],
+        json!(["strip-html", "truncate:200"])
+    );
+    let (_, profiles_payload) =
+        run_kibel_json(&["--config-path", &config_path, "config", "profiles"], &[]);
+    assert_eq!(profiles_payload["data"]["default_team"], json!("acme"));
+    assert_eq!(
+        profiles_payload["data"]["profiles"][0]["origin"],
+        json!("https://acme.kibe.la")
+    );
+
+    let (output, payload) = set(&["on_fetch", "--unset"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["value"], Value::Null);
+
+    for args in [
+        &["copy_url", "maybe"][..],
+        &["timeout", "30"],
+        &["profiles.acme.token", "secret"],
+    ] {
+        let (output, payload) = set(args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert_eq!(payload["error"]["code"], json!("INPUT_INVALID"));
+    }
+
+    let _ = std::fs::remove_file(&config_path);
+}
+
+#[test]
+fn config_convert_writes_json_that_loads_like_the_toml() {
+    let config_path = isolated_config_path();
+    write_config(
+        &config_path,
+        Some("acme"),
+        Some("acme"),
+        Some("super-secret-token"),
+        Some("https://acme.kibe.la"),
+    );
+    let json_path = config_path.replace(".toml", ".json");
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &config_path,
+            "config",
+            "convert",
+            "--to",
+            "json",
+        ],
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["to"], json!(json_path));
+    assert_eq!(payload["data"]["format"], json!("json"));
+    let converted: Value = serde_json::from_str(
+        &std::fs::read_to_string(&json_path).expect("json config should be written"),
+    )
+    .expect("converted config should be JSON");
+    assert_eq!(converted["default_team"], json!("acme"));
+    assert!(std::path::Path::new(&config_path).exists());
+
+    let (_, profiles_payload) =
+        run_kibel_json(&["--config-path", &json_path, "config", "profiles"], &[]);
+    assert_eq!(profiles_payload["data"]["default_team"], json!("acme"));
+    assert_eq!(
+        profiles_payload["data"]["profiles"][0]["origin"],
+        json!("https://acme.kibe.la")
+    );
+
+    // An existing target is only replaced with --force.
+    let (again_output, again_payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &config_path,
+            "config",
+            "convert",
+            "--to",
+            "json",
+        ],
+        &[],
+    );
+    assert_eq!(again_output.status.code(), Some(5));
+    assert_eq!(again_payload["error"]["code"], json!("PRECONDITION_FAILED"));
+    let (same_output, _) = run_kibel_json(
+        &[
+            "--config-path",
+            &json_path,
+            "config",
+            "convert",
+            "--to",
+            "json",
+        ],
+        &[],
+    );
+    assert_eq!(same_output.status.code(), Some(2));
+
+    for path in [&config_path, &json_path] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn auth_login_honors_the_selected_token_store_backend() {
+    let config_path = isolated_config_path();
+    let login = |backend: &str| {
+        run_kibel_json(
+            &[
+                "--config-path",
+                &config_path,
+                "--origin",
+                "https://acme.kibe.la",
+                "auth",
+                "login",
+                "--team",
+                "acme",
+                "--non-interactive",
+            ],
+            &[
+                ("KIBEL_TOKEN_STORE", backend.to_string()),
+                ("KIBELA_ACCESS_TOKEN", "plain-token".to_string()),
+            ],
+        )
+    };
+
+    let (output, payload) = login("none");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["stored_in"], json!([]));
+    assert_eq!(payload["data"]["token_store"]["backend"], json!("none"));
+    let raw = std::fs::read_to_string(&config_path).expect("config should be written");
+    assert!(!raw.contains("plain-token"));
+
+    let (output, payload) = login("plaintext");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["stored_in"], json!(["config"]));
+
+    let (status_output, status_payload) = run_kibel_json(
+        &["--config-path", &config_path, "auth", "status"],
+        &[("KIBEL_TOKEN_STORE", "plaintext".to_string())],
+    );
+    assert_eq!(status_output.status.code(), Some(0));
+    assert_eq!(status_payload["data"]["token_source"], json!("config"));
+    assert_eq!(
+        status_payload["data"]["token_store"],
+        json!({ "backend": "plaintext", "location": config_path })
+    );
+
+    let (invalid_output, invalid_payload) = login("vault");
+    assert_eq!(invalid_output.status.code(), Some(2));
+    assert_eq!(invalid_payload["error"]["code"], json!("INPUT_INVALID"));
+    let _ = std::fs::remove_file(&config_path);
+}
+
 #[test]
 fn note_get_uses_profile_origin_when_origin_flag_is_missing() {
     let config_path = isolated_config_path();
@@ -870,6 +1241,89 @@ fn note_get_uses_default_team_origin_when_team_is_missing() {
     );
 }
 
+#[test]
+fn note_get_applies_on_fetch_pipeline_from_profile() {
+    let config_path = isolated_config_path();
+    std::fs::write(
+        &config_path,
+        "default_team = \"acme\"\non_fetch = [\"truncate:5\"]\n\n[profiles.acme]\norigin = \"https://acme.kibe.la\"\non_fetch = [\"strip-html\", \"resolve-links\"]\n",
+    )
+    .expect("failed to write config");
+    let envs = [
+        ("KIBELA_ACCESS_TOKEN", "env-token".to_string()),
+        (
+            "KIBEL_TEST_GRAPHQL_RESPONSE",
+            fixture_note("N1", "stub-title", "<b>see</b> [spec](/notes/2)"),
+        ),
+    ];
+
+    let (output, payload) = run_kibel_json(
+        &["--config-path", &config_path, "note", "get", "--id", "N1"],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        payload["data"]["note"]["content"],
+        json!("see [spec](https://acme.kibe.la/notes/2)")
+    );
+    assert_eq!(
+        payload["data"]["meta"]["on_fetch"],
+        json!(["strip-html", "resolve-links"])
+    );
+
+    let (_, payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &config_path,
+            "note",
+            "get",
+            "--id",
+            "N1",
+            "--on-fetch",
+            "strip-html,truncate:3",
+        ],
+        &envs,
+    );
+    assert_eq!(
+        payload["data"]["note"]["content"],
+        json!("see…\n\n[truncated 17 of 20 characters]")
+    );
+
+    let (_, payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &config_path,
+            "note",
+            "get",
+            "--id",
+            "N1",
+            "--raw",
+        ],
+        &envs,
+    );
+    assert_eq!(
+        payload["data"]["note"]["content"],
+        json!("<b>see</b> [spec](/notes/2)")
+    );
+    assert!(payload["data"]["meta"].get("on_fetch").is_none());
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "--config-path",
+            &config_path,
+            "note",
+            "get",
+            "--id",
+            "N1",
+            "--on-fetch",
+            "shout",
+        ],
+        &envs,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(payload["error"]["code"], json!("INPUT_INVALID"));
+}
+
 #[test]
 fn with_token_flag_does_not_break_config_profiles() {
     let config_path = isolated_config_path();
@@ -906,3 +1360,169 @@ fn with_token_flag_does_not_break_version() {
         "version should be present"
     );
 }
+
+#[test]
+fn note_update_metadata_sends_only_changed_fields() {
+    let capture_path = isolated_capture_path();
+    let envs = vec![
+        ("KIBELA_ORIGIN", "http://fixture.local".to_string()),
+        ("KIBELA_TEAM", "acme".to_string()),
+        ("KIBELA_ACCESS_TOKEN", "test-token".to_string()),
+        (
+            "KIBEL_TEST_GRAPHQL_RESPONSE",
+            json!({
+                "data": {
+                    "updateNote": {
+                        "note": { "id": "N1", "title": "renamed", "url": "https://example.kibe.la/notes/1" }
+                    }
+                }
+            })
+            .to_string(),
+        ),
+        ("KIBEL_TEST_CAPTURE_REQUEST_PATH", capture_path.clone()),
+    ];
+
+    let (output, payload) = run_kibel_json(
+        &[
+            "note",
+            "update",
+            "--id",
+            "N1",
+            "--title",
+            "renamed",
+            "--coediting",
+            "false",
+            "--group-id",
+            "G1",
+            "--group-id",
+            "G2",
+        ],
+        &envs,
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["note"]["title"], "renamed");
+    let captured_raw = std::fs::read_to_string(&capture_path).expect("capture file should exist");
+    let captured =
+        serde_json::from_str::<Value>(&captured_raw).expect("captured request must be JSON");
+    assert!(captured["query"]
+        .as_str()
+        .expect("query must be string")
+        .contains("mutation UpdateNote("));
+    assert_eq!(
+        captured["variables"]["input"],
+        json!({ "id": "N1", "title": "renamed", "coediting": false, "groupIds": ["G1", "G2"] })
+    );
+
+    let (output, payload) = run_kibel_json(&["note", "update", "--id", "N1"], &envs);
+    assert_eq!(output.status.code(), Some(2));
+    assert_error(&payload, "INPUT_INVALID", false);
+}
+
+#[test]
+fn comment_edit_and_delete_target_comment_id() {
+    let edit_capture_path = isolated_capture_path();
+    let edit_envs = vec![
+        ("KIBELA_ORIGIN", "http://fixture.local".to_string()),
+        ("KIBELA_TEAM", "acme".to_string()),
+        ("KIBELA_ACCESS_TOKEN", "test-token".to_string()),
+        (
+            "KIBEL_TEST_GRAPHQL_RESPONSE",
+            json!({ "data": { "updateComment": { "comment": { "id": "C1" } } } }).to_string(),
+        ),
+        ("KIBEL_TEST_CAPTURE_REQUEST_PATH", edit_capture_path.clone()),
+    ];
+    let (output, payload) = run_kibel_json(
+        &["comment", "edit", "--id", "C1", "--content", "build: green"],
+        &edit_envs,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["comment"]["id"], "C1");
+    let captured_raw =
+        std::fs::read_to_string(&edit_capture_path).expect("capture file should exist");
+    let captured =
+        serde_json::from_str::<Value>(&captured_raw).expect("captured request must be JSON");
+    assert!(captured["query"]
+        .as_str()
+        .expect("query must be string")
+        .contains("mutation UpdateComment("));
+    assert_eq!(
+        captured["variables"]["input"],
+        json!({ "id": "C1", "content": "build: green" })
+    );
+
+    let delete_capture_path = isolated_capture_path();
+    let delete_envs = vec![
+        ("KIBELA_ORIGIN", "http://fixture.local".to_string()),
+        ("KIBELA_TEAM", "acme".to_string()),
+        ("KIBELA_ACCESS_TOKEN", "test-token".to_string()),
+        (
+            "KIBEL_TEST_GRAPHQL_RESPONSE",
+            json!({ "data": { "deleteComment": { "clientMutationId": null } } }).to_string(),
+        ),
+        (
+            "KIBEL_TEST_CAPTURE_REQUEST_PATH",
+            delete_capture_path.clone(),
+        ),
+    ];
+    let (output, payload) = run_kibel_json(&["comment", "delete", "--id", "C1"], &delete_envs);
+    assert_eq!(output.status.code(), Some(2));
+    assert_error(&payload, "INPUT_INVALID", false);
+    assert!(!std::path::Path::new(&delete_capture_path).exists());
+
+    let (output, payload) =
+        run_kibel_json(&["comment", "delete", "--id", "C1", "--yes"], &delete_envs);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(payload["data"]["deleted"]["id"], "C1");
+    let captured_raw =
+        std::fs::read_to_string(&delete_capture_path).expect("capture file should exist");
+    let captured =
+        serde_json::from_str::<Value>(&captured_raw).expect("captured request must be JSON");
+    assert!(captured["query"]
+        .as_str()
+        .expect("query must be string")
+        .contains("mutation DeleteComment("));
+    assert_eq!(captured["variables"]["input"], json!({ "id": "C1" }));
+}
+
+#[test]
+fn search_note_adds_plain_snippet_and_renders_highlights_in_text_mode() {
+    let response = json!({
+        "data": {
+            "search": {
+                "edges": [{
+                    "node": {
+                        "document": { "id": "N1" },
+                        "title": "Release notes",
+                        "url": "https://example.kibe.la/notes/1",
+                        "contentSummaryHtml": "<p>Ship the <em>rust</em> crate &amp; tag</p>",
+                        "path": "/notes/1",
+                        "author": { "id": "U1", "account": "alice", "realName": "Alice" }
+                    }
+                }]
+            }
+        }
+    })
+    .to_string();
+    let envs = base_env("http://fixture.local", response);
+
+    let (output, payload) = run_kibel_json(&["search", "note", "--query", "rust"], &envs);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        payload["data"]["results"][0]["snippet"],
+        "Ship the rust crate & tag"
+    );
+
+    let mut command = Command::new(assert_cmd::cargo::cargo_bin!("kibel"));
+    command
+        .args(["--text", "search", "note", "--query", "rust"])
+        .env_remove("NO_COLOR");
+    for (key, value) in &envs {
+        command.env(key, value);
+    }
+    let output = command.output().expect("failed to run kibel");
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("- Release notes  https://example.kibe.la/notes/1"));
+    assert!(stdout.contains("  Ship the **rust** crate & tag"));
+}
@@ -27,9 +27,13 @@ fn all_command_paths_expose_help() {
         &["auth", "logout"],
         &["auth", "status"],
         &["config"],
+        &["config", "get"],
         &["config", "set"],
         &["config", "set", "team"],
         &["config", "profiles"],
+        &["config", "export"],
+        &["config", "import"],
+        &["config", "convert"],
         &["search"],
         &["search", "note"],
         &["search", "folder"],
@@ -42,11 +46,17 @@ fn all_command_paths_expose_help() {
         &["folder", "get-from-path"],
         &["folder", "notes"],
         &["folder", "create"],
+        &["folder", "tree"],
         &["feed"],
         &["feed", "sections"],
+        &["feed", "watch"],
+        &["feed", "relay"],
         &["comment"],
         &["comment", "create"],
         &["comment", "reply"],
+        &["comment", "thread"],
+        &["comment", "edit"],
+        &["comment", "delete"],
         &["note"],
         &["note", "create"],
         &["note", "get"],
@@ -54,14 +64,115 @@ fn all_command_paths_expose_help() {
         &["note", "get-from-path"],
         &["note", "move-to-folder"],
         &["note", "attach-to-folder"],
+        &["note", "copy"],
+        &["note", "transfer"],
         &["note", "update"],
+        &["note", "append"],
+        &["note", "prepend"],
+        &["note", "diff"],
+        &["note", "restore"],
+        &["note", "download"],
+        &["note", "render"],
+        &["note", "outline"],
+        &["note", "drafts"],
+        &["note", "publish"],
+        &["link"],
+        &["link", "resolve"],
+        &["link", "make"],
+        &["open"],
+        &["report"],
+        &["report", "attachments"],
+        &["stats"],
+        &["digest"],
+        &["publish"],
+        &["import"],
+        &["import", "confluence"],
+        &["import", "notion"],
+        &["tui"],
+        &["ci"],
+        &["ci", "verify-docs"],
+        &["lint"],
+        &["lint", "links"],
+        &["template"],
+        &["template", "list"],
+        &["template", "show"],
+        &["cache"],
+        &["cache", "stats"],
+        &["store"],
+        &["store", "stats"],
+        &["store", "vacuum"],
+        &["metrics"],
+        &["usage"],
+        &["meta"],
+        &["meta", "contracts"],
+        &["meta", "operations"],
+        &["meta", "limits"],
+        &["self"],
+        &["self", "update"],
         &["graphql"],
         &["graphql", "run"],
+        &["graphql", "cost"],
         &["completion"],
         &["version"],
+        &["help"],
     ];
 
     for args in command_paths {
         assert_help_ok(args);
     }
 }
+
+#[test]
+fn help_json_describes_command_tree() {
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("kibel"))
+        .args(["help", "--json", "note", "create"])
+        .output()
+        .expect("failed to run kibel help --json");
+    assert_eq!(output.status.code(), Some(0));
+    let payload = serde_json::from_slice::<serde_json::Value>(&output.stdout)
+        .expect("help --json must print JSON");
+    assert_eq!(payload["data"]["command"]["path"], "note create");
+    assert_eq!(payload["data"]["command"]["access"], "write");
+    assert!(payload["data"]["command"]["args"]
+        .as_array()
+        .is_some_and(|args| args.iter().any(|arg| arg["long"] == "title")));
+    assert!(payload["data"]["global_args"]
+        .as_array()
+        .is_some_and(|args| args.iter().any(|arg| arg["env"] == "KIBELA_TEAM")));
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("kibel"))
+        .args(["help", "note", "create"])
+        .output()
+        .expect("failed to run kibel help");
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).expect("stdout must be utf-8");
+    assert!(stdout.contains("Usage: kibel note create"));
+}
+
+fn help_json_about(path: &[&str]) -> serde_json::Value {
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("kibel"))
+        .args(["help", "--json"])
+        .args(path)
+        .output()
+        .expect("failed to run kibel help --json");
+    assert_eq!(output.status.code(), Some(0));
+    let payload = serde_json::from_slice::<serde_json::Value>(&output.stdout)
+        .expect("help --json must print JSON");
+    payload["data"]["command"]["about"].clone()
+}
+
+#[test]
+fn flattened_args_do_not_describe_their_commands() {
+    for path in [
+        &["note", "create"][..],
+        &["note", "get"],
+        &["note", "get-many"],
+        &["note", "get-from-path"],
+        &["group", "list"],
+        &["folder", "list"],
+        &["graphql", "run"],
+        &["graphql", "cost"],
+    ] {
+        assert_eq!(help_json_about(path), serde_json::Value::Null, "{path:?}");
+    }
+}
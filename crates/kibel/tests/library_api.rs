@@ -0,0 +1,100 @@
+use kibel::{run_command, CapturedOutput};
+use serde_json::json;
+
+#[test]
+fn run_command_captures_envelope_in_process() {
+    let mut output = CapturedOutput::default();
+    let result = run_command(
+        &[
+            "--json",
+            "--origin",
+            "https://acme.kibe.la",
+            "link",
+            "make",
+            "QmxvZy8zNjY",
+        ],
+        &mut output,
+    );
+
+    assert!(result.is_success());
+    assert_eq!(
+        result.data().and_then(|data| data.get("url")),
+        Some(&json!("https://acme.kibe.la/notes/366"))
+    );
+    let printed = serde_json::from_str::<serde_json::Value>(output.stdout.trim())
+        .expect("stdout should contain the JSON envelope");
+    assert_eq!(Some(printed), result.envelope);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn run_command_reports_errors_without_exiting() {
+    let mut output = CapturedOutput::default();
+    let result = run_command(
+        &["--origin", "https://acme.kibe.la", "link", "make", "N1"],
+        &mut output,
+    );
+    assert_eq!(result.exit_code, 2);
+    assert_eq!(result.error_code(), Some("INPUT_INVALID"));
+
+    let mut output = CapturedOutput::default();
+    let result = run_command(&["no-such-command"], &mut output);
+    assert_eq!(result.exit_code, 2);
+    assert!(result.envelope.is_none());
+    assert!(output.stderr.contains("no-such-command"));
+
+    let mut output = CapturedOutput::default();
+    let result = run_command(&["link", "--help"], &mut output);
+    assert!(result.is_success());
+    assert!(output.stdout.contains("resolve"));
+}
+
+#[test]
+fn run_command_honors_quiet_and_color_settings() {
+    let make = ["--origin", "https://acme.kibe.la", "link", "make"];
+
+    let mut output = CapturedOutput::default();
+    let result = run_command(
+        &[&make[..], &["QmxvZy8zNjY", "--quiet"]].concat(),
+        &mut output,
+    );
+    assert!(result.is_success());
+    assert!(result.envelope.is_some());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+
+    let mut output = CapturedOutput::default();
+    let result = run_command(&[&make[..], &["N1", "-q"]].concat(), &mut output);
+    assert_eq!(result.exit_code, 2);
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.starts_with("[INPUT_INVALID] "));
+
+    let mut output = CapturedOutput::default();
+    run_command(
+        &[&make[..], &["N1", "--text", "--color", "always"]].concat(),
+        &mut output,
+    );
+    assert!(output
+        .stderr
+        .starts_with("\u{1b}[31m[INPUT_INVALID]\u{1b}[0m "));
+
+    let mut output = CapturedOutput::default();
+    run_command(
+        &[
+            &make[..],
+            &["N1", "--text", "--color", "always", "--no-color"],
+        ]
+        .concat(),
+        &mut output,
+    );
+    assert!(output.stderr.starts_with("[INPUT_INVALID] "));
+
+    let mut output = CapturedOutput::default();
+    run_command(
+        &[&make[..], &["N1", "--color", "always"]].concat(),
+        &mut output,
+    );
+    assert!(!output.stdout.contains('\u{1b}'));
+    serde_json::from_str::<serde_json::Value>(output.stdout.trim())
+        .expect("JSON output stays machine-clean");
+}